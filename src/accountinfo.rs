@@ -0,0 +1,177 @@
+//! Any account on the cluster, looked up by address, with its data decoded
+//! when the layout of the owner program is known
+
+use crate::programs;
+use crate::rent;
+use crate::rpc::RpcApi;
+use anyhow::{Context, Result};
+use serde_json::Value;
+use solana_account_decoder::parse_account_data::{
+    parse_account_data_v3, AccountAdditionalDataV3, ParseAccountError, SplTokenAdditionalDataV2,
+};
+use solana_sdk::{account::Account, pubkey::Pubkey};
+
+/// Offset of `decimals` in an SPL token mint, after the mint authority and
+/// the supply
+const MINT_DECIMALS_OFFSET: usize = 44;
+
+/// Bytes per line of the hex dump
+pub const DUMP_WIDTH: usize = 16;
+
+#[derive(Debug, Clone)]
+pub struct AccountInfo {
+    pub address: Pubkey,
+    pub account: Account,
+    /// Name of the owner program, for the well-known ones
+    pub owner_name: Option<&'static str>,
+    /// Rent-exempt minimum for the account's data length, if it could be
+    /// fetched
+    pub rent_exempt_minimum: Option<u64>,
+    /// Which layout the data was decoded with (`spl-token`, `stake`,
+    /// `nonce`...) and the decoded fields
+    pub parsed: Option<(String, Value)>,
+}
+
+impl AccountInfo {
+    pub fn rent_exempt(&self) -> Option<bool> {
+        self.rent_exempt_minimum
+            .map(|minimum| self.account.lamports >= minimum)
+    }
+}
+
+/// Look up the account at `address`; `None` if there isn't one
+pub fn fetch(rpc_client: &dyn RpcApi, address: &Pubkey) -> Result<Option<AccountInfo>> {
+    let Some(account) = rpc_client
+        .get_account_with_commitment(address, rpc_client.commitment())
+        .with_context(|| format!("Failed to fetch account {}", address))?
+        .value
+    else {
+        return Ok(None);
+    };
+    let rent_exempt_minimum = rent::minimum(rpc_client, account.data.len()).ok();
+    let parsed = parse(rpc_client, address, &account);
+    Ok(Some(AccountInfo {
+        address: *address,
+        owner_name: programs::name(&account.owner),
+        account,
+        rent_exempt_minimum,
+        parsed,
+    }))
+}
+
+/// Decode the data with the layouts the RPC's `jsonParsed` encoding knows.
+/// Token accounts need their mint's decimals, which are fetched.
+fn parse(rpc_client: &dyn RpcApi, address: &Pubkey, account: &Account) -> Option<(String, Value)> {
+    let parse = |additional_data| {
+        parse_account_data_v3(address, &account.owner, &account.data, additional_data)
+    };
+    let parsed = match parse(None) {
+        Err(ParseAccountError::AdditionalDataMissing(_)) => {
+            let mint = Pubkey::try_from(account.data.get(..32)?).ok()?;
+            let mint = rpc_client.get_account(&mint).ok()?;
+            let decimals = *mint.data.get(MINT_DECIMALS_OFFSET)?;
+            parse(Some(AccountAdditionalDataV3 {
+                spl_token_additional_data: Some(SplTokenAdditionalDataV2::with_decimals(decimals)),
+            }))
+        }
+        parsed => parsed,
+    };
+    let parsed = parsed.ok()?;
+    Some((parsed.program, parsed.parsed))
+}
+
+/// Decoded JSON as `path: value` lines, nested objects joined with dots
+pub fn fields(value: &Value) -> Vec<(String, String)> {
+    let mut fields = Vec::new();
+    flatten(value, String::new(), &mut fields);
+    fields
+}
+
+fn flatten(value: &Value, path: String, fields: &mut Vec<(String, String)>) {
+    let join = |key: &str| {
+        if path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", path, key)
+        }
+    };
+    match value {
+        Value::Object(object) if !object.is_empty() => {
+            for (key, value) in object {
+                flatten(value, join(key), fields);
+            }
+        }
+        Value::Array(array) if !array.is_empty() => {
+            for (i, value) in array.iter().enumerate() {
+                flatten(value, join(&i.to_string()), fields);
+            }
+        }
+        Value::String(text) => fields.push((path, text.clone())),
+        value => fields.push((path, value.to_string())),
+    }
+}
+
+/// `offset  hex bytes  |ascii|` lines, 16 bytes each
+pub fn hex_dump(data: &[u8]) -> Vec<String> {
+    data.chunks(DUMP_WIDTH)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let hex: Vec<String> = chunk.iter().map(|byte| format!("{:02x}", byte)).collect();
+            let ascii: String = chunk
+                .iter()
+                .map(|&byte| {
+                    if byte.is_ascii_graphic() || byte == b' ' {
+                        char::from(byte)
+                    } else {
+                        '.'
+                    }
+                })
+                .collect();
+            format!(
+                "{:08x}  {:<width$}  |{}|",
+                i * DUMP_WIDTH,
+                hex.join(" "),
+                ascii,
+                width = DUMP_WIDTH * 3 - 1
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn flattens_parsed_data() {
+        let parsed = json!({
+            "type": "initialized",
+            "info": {"authority": "abc", "feeCalculator": {"lamportsPerSignature": "5000"}},
+            "extensions": [],
+        });
+        assert_eq!(
+            fields(&parsed),
+            [
+                ("extensions".to_string(), "[]".to_string()),
+                ("info.authority".to_string(), "abc".to_string()),
+                (
+                    "info.feeCalculator.lamportsPerSignature".to_string(),
+                    "5000".to_string()
+                ),
+                ("type".to_string(), "initialized".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn dumps_hex_with_ascii() {
+        let dump = hex_dump(b"solace\x00\x01 wallet for the terminal!");
+        assert_eq!(dump.len(), 3);
+        assert_eq!(
+            dump[0],
+            "00000000  73 6f 6c 61 63 65 00 01 20 77 61 6c 6c 65 74 20  |solace.. wallet |"
+        );
+        assert!(dump[2].starts_with("00000020  21   "));
+    }
+}
@@ -0,0 +1,347 @@
+//! How the TUI writes SOL and token amounts: digits grouped in thousands,
+//! rounded to a number of decimal places or shortened to `1.2K`, with the
+//! separators of the locale, and SOL in SOL or in lamports. The command
+//! line prints amounts in full so scripts can read them.
+
+use crate::{
+    config::DisplayConfig,
+    dashboard::TokenBalance,
+    history::{mint_symbol, TokenDelta, SOL_DECIMALS},
+    i18n,
+    wallet::sol_to_lamports,
+};
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// `K`, `M`, `B` and `T`, for each three digits left out
+const SUFFIXES: [&str; 4] = ["K", "M", "B", "T"];
+
+/// What SOL is shown and entered in
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Unit {
+    #[default]
+    Sol,
+    /// For localnet testing, without counting nine decimal places
+    Lamports,
+}
+
+impl Unit {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Sol => "SOL",
+            Self::Lamports => "lamports",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AmountFormat {
+    decimal: char,
+    /// Between thousands, if they're grouped
+    thousands: Option<char>,
+    decimals: Option<u8>,
+    compact: bool,
+    unit: Unit,
+}
+
+impl Default for AmountFormat {
+    /// `1,234.5`, in full
+    fn default() -> Self {
+        Self {
+            decimal: '.',
+            thousands: Some(','),
+            decimals: None,
+            compact: false,
+            unit: Unit::Sol,
+        }
+    }
+}
+
+impl AmountFormat {
+    pub fn from_config(config: &DisplayConfig) -> Result<Self> {
+        let (thousands, decimal) = match config.locale {
+            Some(ref locale) => separators(locale)
+                .ok_or_else(|| anyhow!("Unknown locale `{}` in [display]", locale))?,
+            None => i18n::locale(None, "LC_NUMERIC")
+                .and_then(|locale| separators(&locale))
+                .unwrap_or((',', '.')),
+        };
+        // Places past a lamport show nothing, and too many overflow scaling
+        if let Some(places) = config.decimals.filter(|&places| places > SOL_DECIMALS) {
+            bail!(
+                "decimals = {} in [display] is more than the {} places SOL has",
+                places,
+                SOL_DECIMALS
+            );
+        }
+        Ok(Self {
+            decimal,
+            thousands: config.thousands.then_some(thousands),
+            decimals: config.decimals,
+            compact: config.compact,
+            unit: config.unit,
+        })
+    }
+
+    pub fn unit(&self) -> Unit {
+        self.unit
+    }
+
+    /// Switch between SOL and lamports
+    pub fn toggle_unit(&mut self) {
+        self.unit = match self.unit {
+            Unit::Sol => Unit::Lamports,
+            Unit::Lamports => Unit::Sol,
+        };
+    }
+
+    /// An amount typed in the unit
+    pub fn parse(&self, text: &str) -> Result<u64> {
+        match self.unit {
+            Unit::Sol => Ok(sol_to_lamports(
+                text.trim().parse::<f64>().context("Invalid amount")?,
+            )),
+            Unit::Lamports => text
+                .trim()
+                .replace('_', "")
+                .parse::<u64>()
+                .context("Invalid amount, in whole lamports"),
+        }
+    }
+
+    /// `lamports` in the unit as it would be typed, for inputs filled in
+    pub fn input(&self, lamports: u64) -> String {
+        match self.unit {
+            Unit::Sol => crate::token::format_amount(lamports, SOL_DECIMALS),
+            Unit::Lamports => lamports.to_string(),
+        }
+    }
+
+    /// The same separators with every decimal place, for amounts about to
+    /// be signed
+    pub fn exact(&self) -> Self {
+        Self {
+            decimals: None,
+            compact: false,
+            ..self.clone()
+        }
+    }
+
+    /// `1,234.5 SOL`, or `5,000 lamports`
+    pub fn sol(&self, lamports: u64) -> String {
+        format!(
+            "{} {}",
+            self.amount(lamports, self.sol_decimals()),
+            self.unit.name()
+        )
+    }
+
+    /// `+1.5 SOL` or `-0.000005 SOL`, or `-5,000 lamports`
+    pub fn sol_change(&self, lamports: i64) -> String {
+        format!(
+            "{} {}",
+            self.change(i128::from(lamports), self.sol_decimals()),
+            self.unit.name()
+        )
+    }
+
+    /// SOL in the unit, as SOL amounts go to `amount` and `change`
+    pub fn sol_decimals(&self) -> u8 {
+        match self.unit {
+            Unit::Sol => SOL_DECIMALS,
+            Unit::Lamports => 0,
+        }
+    }
+
+    /// `25.5 USDC`
+    pub fn token(&self, balance: &TokenBalance) -> String {
+        format!(
+            "{} {}",
+            self.amount(balance.amount, balance.decimals),
+            mint_symbol(&balance.mint)
+        )
+    }
+
+    /// `-25 USDC`
+    pub fn token_change(&self, delta: &TokenDelta) -> String {
+        format!(
+            "{} {}",
+            self.change(delta.amount, delta.decimals),
+            delta.symbol()
+        )
+    }
+
+    /// `amount` of the smallest units of something with `decimals` places
+    pub fn amount(&self, amount: u64, decimals: u8) -> String {
+        self.unsigned(u128::from(amount), u32::from(decimals))
+    }
+
+    /// `amount` with its sign, `+` for none
+    pub fn change(&self, amount: i128, decimals: u8) -> String {
+        let sign = if amount < 0 { "-" } else { "+" };
+        format!(
+            "{}{}",
+            sign,
+            self.unsigned(amount.unsigned_abs(), u32::from(decimals))
+        )
+    }
+
+    fn unsigned(&self, amount: u128, decimals: u32) -> String {
+        // More places than a u128 can scale by, which no real mint has, so
+        // the smallest units are shown as they are
+        let Some(scale) = 10u128.checked_pow(decimals) else {
+            return self.write(amount, 0, false);
+        };
+        let trim = self.decimals.is_none();
+        let whole = amount / scale;
+        if self.compact && whole >= 1000 {
+            let places = self.decimals.map_or(2, u32::from);
+            let mut digits = (whole.ilog10() / 3 * 3).min(12);
+            loop {
+                let scaled = round(amount, decimals + digits, places);
+                // 999,999 rounds up to 1000K, which is 1M
+                if scaled / 10u128.pow(places) >= 1000 && digits < 12 {
+                    digits += 3;
+                    continue;
+                }
+                let suffix = SUFFIXES[digits as usize / 3 - 1];
+                return format!("{}{}", self.write(scaled, places, trim), suffix);
+            }
+        }
+        let places = self
+            .decimals
+            .map_or(decimals, |places| u32::from(places).min(decimals));
+        let scaled = round(amount, decimals, places);
+        if scaled == 0 && amount > 0 {
+            // Rather than 0 for dust
+            return format!("<{}", self.write(1, places, false));
+        }
+        self.write(scaled, places, trim)
+    }
+
+    /// `scaled` hundredths for two `places`, and so on
+    fn write(&self, scaled: u128, places: u32, trim: bool) -> String {
+        let scale = 10u128.pow(places);
+        let digits = (scaled / scale).to_string();
+        let mut text = String::with_capacity(digits.len() * 4 / 3 + places as usize + 1);
+        for (i, digit) in digits.chars().enumerate() {
+            if let Some(separator) = self.thousands {
+                if i > 0 && (digits.len() - i).is_multiple_of(3) {
+                    text.push(separator);
+                }
+            }
+            text.push(digit);
+        }
+        if places == 0 {
+            return text;
+        }
+        let fraction = format!("{:0width$}", scaled % scale, width = places as usize);
+        let fraction = if trim {
+            fraction.trim_end_matches('0')
+        } else {
+            &fraction
+        };
+        if !fraction.is_empty() {
+            text.push(self.decimal);
+            text.push_str(fraction);
+        }
+        text
+    }
+}
+
+/// `amount` in units of `decimals` places, rounded half up to `places`
+fn round(amount: u128, decimals: u32, places: u32) -> u128 {
+    if places >= decimals {
+        amount * 10u128.pow(places - decimals)
+    } else {
+        let divisor = 10u128.pow(decimals - places);
+        (amount + divisor / 2) / divisor
+    }
+}
+
+/// The thousands and decimal separators of a locale such as `de_DE.UTF-8`,
+/// by its language
+fn separators(locale: &str) -> Option<(char, char)> {
+    match i18n::language(locale).as_str() {
+        "c" | "posix" | "en" | "ja" | "ko" | "zh" | "he" | "th" | "hi" => Some((',', '.')),
+        "de" | "es" | "it" | "nl" | "pt" | "da" | "id" | "tr" | "el" | "ro" => Some(('.', ',')),
+        "fr" | "ru" | "pl" | "sv" | "nb" | "fi" | "cs" | "sk" | "uk" | "hu" | "bg" => {
+            Some((' ', ','))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_amounts_as_configured() {
+        let full = AmountFormat::default();
+        assert_eq!(full.sol(1_234_500_000_000), "1,234.5 SOL");
+        assert_eq!(full.sol(5_000), "0.000005 SOL");
+        assert_eq!(full.sol_change(-2_000_000_000), "-2 SOL");
+
+        let display = |config: DisplayConfig| AmountFormat::from_config(&config).unwrap();
+        let rounded = display(DisplayConfig {
+            locale: Some("de_DE.UTF-8".to_string()),
+            decimals: Some(2),
+            ..DisplayConfig::default()
+        });
+        assert_eq!(rounded.sol(1_234_567_000_000), "1.234,57 SOL");
+        assert_eq!(rounded.sol(2_000_000_000), "2,00 SOL");
+        assert_eq!(rounded.sol(5_000), "<0,01 SOL");
+        assert_eq!(rounded.amount(7, 0), "7");
+        assert_eq!(full.amount(1_234_567, 255), "1,234,567");
+        assert_eq!(full.change(-5, 39), "-5");
+        assert_eq!(
+            full.amount(5, 38),
+            "0.00000000000000000000000000000000000005"
+        );
+
+        let compact = display(DisplayConfig {
+            locale: Some("en_US".to_string()),
+            thousands: false,
+            compact: true,
+            ..DisplayConfig::default()
+        });
+        assert_eq!(compact.amount(1_500_000, 3), "1.5K");
+        assert_eq!(compact.amount(999_999_000, 3), "1M");
+        assert_eq!(compact.amount(2_500_000_000_000_000, 6), "2.5B");
+        assert_eq!(compact.amount(123_456, 3), "123.456");
+        let precise = display(DisplayConfig {
+            decimals: Some(SOL_DECIMALS),
+            compact: true,
+            ..DisplayConfig::default()
+        });
+        assert_eq!(precise.amount(u64::MAX, SOL_DECIMALS), "18.446744074B");
+
+        let mut lamports = display(DisplayConfig {
+            locale: Some("en".to_string()),
+            unit: Unit::Lamports,
+            ..DisplayConfig::default()
+        });
+        assert_eq!(lamports.sol(1_500_000_000), "1,500,000,000 lamports");
+        assert_eq!(lamports.sol_change(-5_000), "-5,000 lamports");
+        assert_eq!(lamports.parse("5_000").unwrap(), 5_000);
+        assert!(lamports.parse("0.5").is_err());
+        lamports.toggle_unit();
+        assert_eq!(lamports.parse("0.5").unwrap(), 500_000_000);
+        assert_eq!(lamports.parse("1.005").unwrap(), 1_005_000_000);
+        assert_eq!(lamports.input(1_500_000), "0.0015");
+
+        assert!(AmountFormat::from_config(&DisplayConfig {
+            locale: Some("xx".to_string()),
+            ..DisplayConfig::default()
+        })
+        .is_err());
+        assert!(AmountFormat::from_config(&DisplayConfig {
+            decimals: Some(39),
+            compact: true,
+            ..DisplayConfig::default()
+        })
+        .is_err());
+    }
+}
@@ -0,0 +1,2566 @@
+//! The terminal UI: the wallet's state, what the keys do to it and how
+//! it's drawn. The binary only sets up the terminal and passes events in.
+
+use self::{
+    send::{PendingInspected, PendingSend, SendInputMode, SendState},
+    state::{
+        AccountsState, DashboardState, DerivedAccount, ExportKind, ExportState, ExportStep,
+        ImportState, InspectState, LookupResult, LookupState, NonceAction, NonceState, NonceStep,
+        ReceiveState, RequestsState, Setting, SettingsInput, SettingsState, SignInState, SignState,
+        VanityState, VanityStep, VerifyState, WatchState,
+    },
+    ui::ui,
+};
+use crate::{
+    clipboard, hyperlink,
+    lock::{SessionLock, Unlock},
+    plain::PlainMode,
+    plugin::{self, Plugin, PluginContext},
+    session::{SessionState, WalletSession},
+    toast::Toasts,
+};
+use anyhow::{bail, Context, Result};
+use ratatui::{
+    layout::Rect,
+    style::Style,
+    text::{Line, Span},
+    Frame,
+};
+use solace::{
+    accountinfo,
+    amount::AmountFormat,
+    audit::{self, Chain},
+    blocklist::Blocklist,
+    config::{self, Config, Notify},
+    dashboard,
+    explorer::{self, Cluster, Explorer, Target},
+    failure::{Failure, Retry},
+    grind::Grinder,
+    grpc,
+    history::{self, History, HistoryEntry, TxKind},
+    historydb::HistoryDb,
+    i18n::{tr, tr_fmt},
+    idl::IdlRegistry,
+    keymap::{Action, Key, KeyContext, Keymap},
+    keys::{self, Derivation, KeypairSource, SecretKeypair},
+    limits::SpendingLedger,
+    logging::LogBuffer,
+    nonce, notify,
+    output::Refused,
+    pay::{self, PaymentRequest, RequestStatus},
+    price, rent,
+    report::{self, ReportFormat},
+    rpc::{self, RpcApi},
+    sender::{self, Expired, Guard},
+    staking,
+    task::Task,
+    theme::{Theme, ThemePreset},
+    throttle::Backoff,
+    validator::LocalValidator,
+    wallet::{self, resolve_rpc_url, sender_rent_message, NoBlockhash},
+    watch::{self, Alert},
+    webhook::{Hooks, Notice},
+};
+use solana_sdk::{
+    commitment_config::CommitmentLevel,
+    hash::Hash,
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    signature::{Signature, Signer},
+};
+use std::{
+    cell::Cell,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use zeroize::Zeroizing;
+
+mod input;
+mod send;
+mod state;
+#[cfg(test)]
+mod tests;
+mod ui;
+
+pub use self::input::{handle_key, handle_mouse};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum AppState {
+    Home,
+    Wallet,
+    Send,
+    Receive,
+    SignMessage,
+    VerifyMessage,
+    SignIn,
+    InspectTx,
+    Lookup,
+    Nonce,
+    Transactions,
+    Accounts,
+    Watch,
+    Requests,
+    Vanity,
+    Settings,
+    Export,
+    Import,
+    /// The screen of the plugin at this index in `App::plugins`
+    Plugin(usize),
+}
+
+/// Why a signing command or screen is turned down under `--read-only`
+pub const READ_ONLY: &str = "Read-only mode: nothing can be signed or sent";
+
+/// Actions that sign with the wallet's key, handle it or lead to a screen
+/// that does, turned down in read-only mode
+fn signs(action: Action) -> bool {
+    matches!(
+        action,
+        Action::ImportKey
+            | Action::ExportKey
+            | Action::CreateNonce
+            | Action::AdvanceNonce
+            | Action::WithdrawNonce
+            | Action::FundNonce
+            | Action::SwitchAccount
+            | Action::Resend
+    )
+}
+
+impl AppState {
+    /// Screens for signing with the wallet's key or handling it, closed in
+    /// read-only mode
+    fn signs(self) -> bool {
+        matches!(
+            self,
+            AppState::Send
+                | AppState::SignMessage
+                | AppState::SignIn
+                | AppState::Export
+                | AppState::Import
+        )
+    }
+}
+
+/// Screens listed in the side menu, in order, before the plugins
+const MENU_ITEMS: [(&str, AppState); 16] = [
+    ("Home", AppState::Home),
+    ("Wallet", AppState::Wallet),
+    ("Send", AppState::Send),
+    ("Receive", AppState::Receive),
+    ("Sign", AppState::SignMessage),
+    ("Verify", AppState::VerifyMessage),
+    ("Sign In", AppState::SignIn),
+    ("Inspect", AppState::InspectTx),
+    ("Lookup", AppState::Lookup),
+    ("Nonce", AppState::Nonce),
+    ("Transactions", AppState::Transactions),
+    ("Accounts", AppState::Accounts),
+    ("Watch", AppState::Watch),
+    ("Requests", AppState::Requests),
+    ("Vanity", AppState::Vanity),
+    ("Settings", AppState::Settings),
+];
+
+/// How long to wait for input before running periodic checks
+pub const TICK_RATE: Duration = Duration::from_millis(250);
+
+/// How often the chain is checked for the payment of an open request
+const PAYMENT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// The wallet the TUI opened, and its keys
+pub struct WalletInfo {
+    /// Signing key, dropped from memory while the session is locked
+    pub keypair: Option<Arc<SecretKeypair>>,
+    pub keypair_source: KeypairSource,
+    /// Pays the fees of transfers instead of the wallet, and is dropped
+    /// with the signing key
+    pub fee_payer: Option<Arc<SecretKeypair>>,
+    pub fee_payer_source: Option<KeypairSource>,
+    pub address: Pubkey,
+    /// As of the last refresh
+    pub lamports: u64,
+}
+
+impl WalletInfo {
+    /// Who pays the fees of transfers from the wallet
+    fn fee_payer(&self) -> Pubkey {
+        self.fee_payer
+            .as_ref()
+            .map_or(self.address, |fee_payer| fee_payer.pubkey())
+    }
+}
+
+/// Everything the TUI shows and does; [`App::draw`] draws it, and
+/// [`handle_key`] and [`handle_mouse`] take input
+pub struct App {
+    state: AppState,
+    selected_menu_item: usize,
+    wallet: WalletInfo,
+    rpc_client: Arc<dyn RpcApi>,
+    rpc_url: String,
+    send_state: SendState,
+    last_tx_signature: Option<Signature>,
+    config: Config,
+    blocklist: Blocklist,
+    spending: SpendingLedger,
+    /// Cached result of the genesis hash check for custom RPC URLs
+    mainnet: Option<bool>,
+    pub session_lock: SessionLock,
+    lock_error: Option<Failure>,
+    export_state: ExportState,
+    import_state: ImportState,
+    sign_state: SignState,
+    verify_state: VerifyState,
+    sign_in_state: SignInState,
+    inspect_state: InspectState,
+    lookup_state: LookupState,
+    nonce_state: NonceState,
+    accounts_state: AccountsState,
+    vanity_state: VanityState,
+    history: History,
+    /// Opened the first time the Transactions view is shown
+    history_db: Option<HistoryDb>,
+    /// Genesis hash of the connected cluster, used to key the history index
+    cluster_id: Option<String>,
+    /// The genesis hash fetched at startup, until `cluster_id` is set
+    cluster_task: Option<Task<Hash>>,
+    pub idls: IdlRegistry,
+    /// Render addresses and signatures as OSC 8 links to the explorer
+    hyperlinks: bool,
+    toasts: Toasts,
+    /// Webhook and command hooks, when configured
+    hooks: Option<Hooks>,
+    /// The wallet followed over gRPC, when an endpoint is configured
+    grpc: Option<grpc::Stream>,
+    /// The prices checked for `[price_alerts]`, when any are set
+    prices: Option<price::Checker>,
+    pub keymap: Keymap,
+    pub theme: Theme,
+    pub plain: PlainMode,
+    /// Started with `--read-only`: there's no signing key, and the screens
+    /// that would sign don't open
+    pub read_only: bool,
+    /// Started with `--start-local-validator`, stopped with the app
+    pub validator: Option<LocalValidator>,
+    pub amounts: AmountFormat,
+    /// Scroll offset of the help overlay, while it's open
+    help: Option<u16>,
+    /// Scroll offset of the debug log overlay, while it's open
+    log_view: Option<u16>,
+    /// The latest log lines, for the log overlay
+    pub log_buffer: LogBuffer,
+    /// Scroll offset of the audit log overlay, while it's open
+    audit_view: Option<u16>,
+    /// The audit log as it was when the overlay opened
+    audit_lines: Vec<Line<'static>>,
+    /// First key of a pair like `gg`, waiting for the second
+    pending_key: Option<Key>,
+    /// Furthest the open scrolling view (help or transaction details) can
+    /// scroll, as of the last draw
+    scroll_limit: Cell<u16>,
+    /// Scroll offset of the content pane on screens without their own
+    /// scrolling, reset when the screen changes
+    content_scroll: u16,
+    content_limit: Cell<u16>,
+    scrolled_screen: AppState,
+    /// Where the menu and the content pane were last drawn, for mouse clicks
+    menu_area: Cell<Rect>,
+    content_area: Cell<Rect>,
+    /// Requests running in the background, picked up by `poll_tasks`
+    balance_task: Option<Task<u64>>,
+    /// Whether to toast the balance once `balance_task` is done
+    announce_balance: bool,
+    /// Why the last balance refresh failed, shown on the wallet screen
+    balance_error: Option<Failure>,
+    history_task: Option<HistoryFetch>,
+    /// The newest page of history fetched at startup, taken up by the
+    /// Transactions screen if nothing is cached
+    history_head: Option<Task<HistoryUpdate>>,
+    /// Older pages still to fetch after the running one, to fill a filter
+    history_prefetch: usize,
+    send_task: Option<PendingSend>,
+    inspect_task: Option<PendingInspected>,
+    nonce_task: Option<(Task<Signature>, NonceAction)>,
+    plugins: Vec<Box<dyn Plugin>>,
+    /// When the balance and the open screen last refreshed on their own
+    last_refresh: Instant,
+    /// Whether the RPC client is retrying requests the node throttled
+    pub backoff: Option<Arc<Backoff>>,
+    /// Where the TUI was left, saved again on quit
+    session: SessionState,
+    /// The address the wallet was opened with, which its saved session is
+    /// kept under
+    opened_address: Pubkey,
+    /// Where changes made on the Settings screen are saved; none in tests
+    pub config_path: Option<PathBuf>,
+    settings: SettingsState,
+    dashboard: DashboardState,
+    watch: WatchState,
+    receive: ReceiveState,
+    requests: RequestsState,
+}
+
+/// What the first screens need, fetched in the background at once when the
+/// wallet opens rather than one roundtrip after another
+pub struct Startup {
+    balance: Task<u64>,
+    genesis_hash: Task<Hash>,
+    history_head: Task<HistoryUpdate>,
+}
+
+impl Startup {
+    pub fn spawn(rpc_client: &Arc<dyn RpcApi>, address: Pubkey) -> Self {
+        let rpc = rpc_client.clone();
+        let balance =
+            Task::spawn(move || rpc.get_balance(&address).context("Failed to fetch balance"));
+        let rpc = rpc_client.clone();
+        let genesis_hash = Task::spawn(move || {
+            rpc.get_genesis_hash()
+                .context("Failed to fetch the genesis hash")
+        });
+        let rpc = rpc_client.clone();
+        let history_head = Task::spawn(move || {
+            history::fetch_page(&*rpc, &address, None, None).map(HistoryUpdate::Older)
+        });
+        Self {
+            balance,
+            genesis_hash,
+            history_head,
+        }
+    }
+}
+
+/// A history fetch running in the background
+struct HistoryFetch {
+    task: Task<HistoryUpdate>,
+    /// What runs it again if it fails
+    retry: Retry,
+}
+
+/// History fetched by a background task
+enum HistoryUpdate {
+    /// The page older than the loaded entries
+    Older(Vec<HistoryEntry>),
+    /// Entries newer than the loaded ones, and whether they reach them
+    Newer(Vec<HistoryEntry>, bool),
+}
+
+impl App {
+    pub fn new(
+        wallet: WalletInfo,
+        rpc_client: Arc<dyn RpcApi>,
+        rpc_url: String,
+        config: Config,
+        blocklist: Blocklist,
+        spending: SpendingLedger,
+        session_lock: SessionLock,
+    ) -> Self {
+        let hyperlinks = config
+            .explorer
+            .hyperlinks
+            .unwrap_or_else(hyperlink::supported);
+        let hooks = Hooks::start(&config.webhook, &rpc_url, wallet.address);
+        let grpc = grpc::Stream::start(&config.grpc, wallet.address);
+        let mut toasts = Toasts::default();
+        let prices = price::Checker::start(&config.price_alerts).unwrap_or_else(|e| {
+            toasts.error(format!("{:#}", e));
+            None
+        });
+        let app = Self {
+            opened_address: wallet.address,
+            state: AppState::Home,
+            selected_menu_item: 0,
+            wallet,
+            rpc_client,
+            rpc_url,
+            send_state: SendState::default(),
+            last_tx_signature: None,
+            config,
+            blocklist,
+            spending,
+            mainnet: None,
+            session_lock,
+            lock_error: None,
+            export_state: ExportState::default(),
+            import_state: ImportState::default(),
+            sign_state: SignState::default(),
+            verify_state: VerifyState::default(),
+            sign_in_state: SignInState::default(),
+            inspect_state: InspectState::default(),
+            lookup_state: LookupState::default(),
+            nonce_state: NonceState::default(),
+            accounts_state: AccountsState::default(),
+            vanity_state: VanityState::default(),
+            history: History::default(),
+            history_db: None,
+            cluster_id: None,
+            cluster_task: None,
+            idls: IdlRegistry::default(),
+            hyperlinks,
+            toasts,
+            hooks,
+            grpc,
+            prices,
+            keymap: Keymap::default(),
+            theme: Theme::default(),
+            plain: PlainMode::default(),
+            read_only: false,
+            validator: None,
+            amounts: AmountFormat::default(),
+            help: None,
+            log_view: None,
+            log_buffer: LogBuffer::default(),
+            audit_view: None,
+            audit_lines: Vec::new(),
+            pending_key: None,
+            scroll_limit: Cell::new(0),
+            content_scroll: 0,
+            content_limit: Cell::new(0),
+            scrolled_screen: AppState::Home,
+            menu_area: Cell::default(),
+            content_area: Cell::default(),
+            balance_task: None,
+            announce_balance: false,
+            balance_error: None,
+            history_task: None,
+            history_head: None,
+            history_prefetch: 0,
+            send_task: None,
+            inspect_task: None,
+            nonce_task: None,
+            plugins: plugin::builtin(),
+            last_refresh: Instant::now(),
+            backoff: None,
+            session: SessionState::default(),
+            config_path: None,
+            settings: SettingsState::default(),
+            dashboard: DashboardState::default(),
+            watch: WatchState::default(),
+            receive: ReceiveState::default(),
+            requests: RequestsState::default(),
+        };
+        app.guard_sends();
+        app
+    }
+
+    /// Have everything sent from here checked against this blocklist and
+    /// the cluster's pre-send hooks
+    fn guard_sends(&self) {
+        sender::set_guard(Guard {
+            blocklist: self.blocklist.clone(),
+            hooks: self.config.webhook.clone(),
+            rpc_url: self.rpc_url.clone(),
+        });
+    }
+
+    /// Draw a frame. A screen opened since the last one starts at its top.
+    pub fn draw(&mut self, f: &mut Frame) {
+        if self.state != self.scrolled_screen {
+            self.content_scroll = 0;
+            self.scrolled_screen = self.state;
+        }
+        ui(f, self);
+        self.plain.apply(f.buffer_mut());
+    }
+
+    /// Whether anything is running in the background
+    pub fn busy(&self) -> bool {
+        self.balance_task.is_some()
+            || self.history_task.is_some()
+            || self.send_task.is_some()
+            || self.inspect_task.is_some()
+            || self.nonce_task.is_some()
+            || self.dashboard.task.is_some()
+            || self.watch.task.is_some()
+            || self.receive.task.is_some()
+            || self.requests.task.is_some()
+            || self.plugins.iter().any(|plugin| plugin.busy())
+    }
+
+    /// The side menu's entries: the built-in screens, then the plugins
+    fn menu(&self) -> Vec<(&str, AppState)> {
+        let plugins = self
+            .plugins
+            .iter()
+            .enumerate()
+            .map(|(i, plugin)| (plugin.name(), AppState::Plugin(i)));
+        MENU_ITEMS.iter().copied().chain(plugins).collect()
+    }
+
+    /// How the config and the saved session refer to the open screen: its
+    /// name in the side menu, lowercased with underscores for spaces
+    /// (`sign_in` for "Sign In")
+    fn screen_name(&self) -> Option<String> {
+        self.menu()
+            .into_iter()
+            .find(|(_, state)| *state == self.state)
+            .map(|(name, _)| name.to_lowercase().replace(' ', "_"))
+    }
+
+    /// Go back to where the last session was left, as far as it still
+    /// applies: the derived account switched to, then the open screen, how
+    /// far it was scrolled and what was selected on it
+    pub fn restore(&mut self, session: SessionState) {
+        let saved = session
+            .wallets
+            .get(&self.opened_address.to_string())
+            .cloned()
+            .unwrap_or_default();
+        let current = self
+            .wallet
+            .keypair
+            .as_ref()
+            .and_then(|keypair| keypair.seed_and_derivation())
+            .map(|(_, derivation)| derivation.to_string());
+        if let Some(account) = saved
+            .account
+            .filter(|account| Some(account) != current.as_ref() && !self.read_only)
+        {
+            let derivation = match account.as_str() {
+                "root" => Ok(Derivation::Root),
+                path => Derivation::parse(path),
+            };
+            if let Err(e) = derivation.and_then(|derivation| self.switch_account(&derivation)) {
+                self.toasts.error(format!(
+                    "Couldn't switch back to account {}: {:#}",
+                    account, e
+                ));
+            }
+        }
+
+        let menu = self.menu();
+        let index = session.screen.as_ref().and_then(|screen| {
+            menu.iter()
+                .position(|(name, _)| name.to_lowercase().replace(' ', "_") == *screen)
+        });
+        if let Some(index) = index {
+            self.selected_menu_item = index;
+            self.open_selected_menu_item();
+            self.content_scroll = session.scroll;
+            self.scrolled_screen = self.state;
+        }
+        match self.state {
+            AppState::Transactions => {
+                self.history.filters = saved.history_filters;
+                self.history.search = saved.history_search;
+                let last = self.history.visible().len().saturating_sub(1);
+                self.history.selected = saved.history_selected.min(last);
+            }
+            AppState::Accounts => {
+                let last = self.accounts_state.accounts.len().saturating_sub(1);
+                self.accounts_state.selected = saved.accounts_selected.min(last);
+            }
+            _ => {}
+        }
+        self.session = session;
+    }
+
+    /// Record where the TUI is for the next start
+    pub fn save_session(&mut self) -> Result<()> {
+        if !self.config.session.restore {
+            return Ok(());
+        }
+        let key = self.opened_address.to_string();
+        // A locked wallet has dropped its keypair, but not the account
+        let account = self
+            .wallet
+            .keypair
+            .as_ref()
+            .and_then(|keypair| keypair.seed_and_derivation())
+            .map(|(_, derivation)| derivation.to_string())
+            .or_else(|| {
+                self.session
+                    .wallets
+                    .get(&key)
+                    .and_then(|saved| saved.account.clone())
+            });
+        self.session.screen = self.screen_name();
+        self.session.scroll = self.content_scroll;
+        self.session.wallets.insert(
+            key,
+            WalletSession {
+                account,
+                history_filters: self.history.filters.clone(),
+                history_search: self.history.search.clone(),
+                history_selected: self.history.selected,
+                accounts_selected: self.accounts_state.selected,
+            },
+        );
+        self.session.save()
+    }
+
+    /// Show the signatures in the audit log, newest at the bottom, under
+    /// whether its chain holds
+    fn open_audit_log(&mut self) {
+        let muted = Style::default().fg(self.theme.muted);
+        let mut lines = Vec::new();
+        if !self.config.audit.enabled {
+            lines.push(Line::from(Span::styled(
+                "Signatures aren't being recorded ([audit] enabled = false)",
+                Style::default().fg(self.theme.error),
+            )));
+        }
+        let missed = audit::missed();
+        if missed > 0 {
+            lines.push(Line::from(Span::styled(
+                format!("{} signatures this session couldn't be recorded", missed),
+                Style::default().fg(self.theme.error),
+            )));
+        }
+        match audit::load() {
+            Ok(records) => {
+                let chain = audit::verify(&records);
+                let style = match chain {
+                    Chain::Intact => Style::default().fg(self.theme.success),
+                    Chain::Broken(_) => Style::default().fg(self.theme.error),
+                };
+                lines.push(Line::from(Span::styled(
+                    audit::summary(&records, &chain),
+                    style,
+                )));
+                lines.extend(
+                    records.iter().map(|record| {
+                        Line::from(Span::styled(audit::format_record(record), muted))
+                    }),
+                );
+            }
+            Err(e) => lines.push(Line::from(Span::styled(
+                format!("{:#}", e),
+                Style::default().fg(self.theme.error),
+            ))),
+        }
+        self.audit_lines = lines;
+        self.audit_view = Some(u16::MAX);
+    }
+
+    /// Whether a request is waiting out the node's rate limit, to show in
+    /// place of the error it would otherwise be
+    fn throttled(&self) -> bool {
+        self.backoff
+            .as_ref()
+            .is_some_and(|backoff| backoff.retrying())
+    }
+
+    /// What plugins see of the wallet
+    fn plugin_context(&self) -> PluginContext<'_> {
+        PluginContext {
+            rpc_client: &self.rpc_client,
+            rpc_url: &self.rpc_url,
+            address: self.wallet.address,
+            balance: self.wallet.lamports as f64 / LAMPORTS_PER_SOL as f64,
+            theme: &self.theme,
+            ascii: self.plain.ascii,
+            retry_key: self.retry_key(),
+        }
+    }
+
+    /// Run `f` on the plugin at `index`; nothing happens if there's none
+    fn with_plugin(&mut self, index: usize, f: impl FnOnce(&mut dyn Plugin, &PluginContext)) {
+        // Out of `self` for the call, so the context can borrow the rest
+        let mut plugins = std::mem::take(&mut self.plugins);
+        if let Some(plugin) = plugins.get_mut(index) {
+            f(plugin.as_mut(), &self.plugin_context());
+        }
+        self.plugins = plugins;
+    }
+
+    /// Which set of key bindings applies right now
+    fn key_context(&self) -> KeyContext {
+        if self.session_lock.is_locked() {
+            return KeyContext::Lock;
+        }
+        if self.help.is_some() {
+            return KeyContext::Help;
+        }
+        if self.log_view.is_some() || self.audit_view.is_some() {
+            return KeyContext::Logs;
+        }
+        match self.state {
+            AppState::Home if self.has_dashboard() => KeyContext::Dashboard,
+            AppState::Home => KeyContext::Home,
+            AppState::Wallet => KeyContext::Wallet,
+            AppState::Receive
+                if self.receive.save_path.is_some() || self.receive.amount.is_some() =>
+            {
+                KeyContext::Prompt
+            }
+            AppState::Receive => KeyContext::Receive,
+            AppState::Settings if self.settings.input.is_some() => KeyContext::Prompt,
+            AppState::Settings => KeyContext::Settings,
+            AppState::Accounts if self.accounts_state.search.is_some() => KeyContext::Prompt,
+            AppState::Accounts => KeyContext::Accounts,
+            AppState::Watch if self.watch.input.is_some() => KeyContext::Prompt,
+            AppState::Watch => KeyContext::Watch,
+            AppState::Requests => KeyContext::Requests,
+            AppState::Transactions if self.history.export_path.is_some() => {
+                KeyContext::ExportPrompt
+            }
+            AppState::Transactions
+                if self.history.searching || self.history.editing_note.is_some() =>
+            {
+                KeyContext::Prompt
+            }
+            AppState::Transactions if self.history.inspection.is_some() => KeyContext::Inspection,
+            AppState::Transactions => KeyContext::Transactions,
+            AppState::Send
+                if self.send_state.input_mode == SendInputMode::Confirming
+                    && !self.send_state.typed_confirmation =>
+            {
+                KeyContext::SendConfirm
+            }
+            AppState::Vanity
+                if matches!(
+                    self.vanity_state.step,
+                    VanityStep::EnteringPrefix | VanityStep::EnteringSuffix
+                ) =>
+            {
+                KeyContext::VanityPattern
+            }
+            AppState::Nonce => match self.nonce_state.step {
+                NonceStep::List => KeyContext::Nonces,
+                NonceStep::EnteringWithdrawal => KeyContext::Form,
+                NonceStep::Confirming(_) => KeyContext::SendConfirm,
+            },
+            AppState::Export if self.export_state.step == ExportStep::Choosing => {
+                KeyContext::ExportChoice
+            }
+            AppState::Export if self.export_state.step == ExportStep::SavingShares => {
+                KeyContext::SharesForm
+            }
+            AppState::Send
+            | AppState::SignMessage
+            | AppState::VerifyMessage
+            | AppState::SignIn
+            | AppState::InspectTx
+            | AppState::Vanity
+            | AppState::Export
+            | AppState::Import => KeyContext::Form,
+            AppState::Lookup => KeyContext::Lookup,
+            AppState::Plugin(_) => KeyContext::Plugin,
+        }
+    }
+
+    fn open_selected_menu_item(&mut self) {
+        let Some(&(_, state)) = self.menu().get(self.selected_menu_item) else {
+            return;
+        };
+        if self.read_only && state.signs() {
+            self.toasts.error(READ_ONLY);
+            return;
+        }
+        match state {
+            AppState::Wallet => {
+                // Refresh balance when entering wallet view
+                self.refresh_balance(false);
+            }
+            AppState::Send => self.send_state = SendState::default(),
+            AppState::SignMessage => self.sign_state = SignState::default(),
+            AppState::VerifyMessage => self.verify_state = VerifyState::default(),
+            AppState::SignIn => self.sign_in_state = SignInState::default(),
+            AppState::InspectTx => self.inspect_state = InspectState::default(),
+            AppState::Lookup => self.lookup_state = LookupState::default(),
+            AppState::Nonce => {
+                self.nonce_state = NonceState::default();
+                self.load_nonce_accounts();
+            }
+            AppState::Home if self.has_dashboard() && self.dashboard.task.is_none() => {
+                self.refresh_dashboard();
+            }
+            AppState::Transactions => self.reload_history(),
+            AppState::Watch => {
+                self.watch.input = None;
+                self.watch.error = None;
+                self.refresh_watched();
+            }
+            AppState::Requests => {
+                self.requests.error = None;
+                self.refresh_requests();
+            }
+            AppState::Accounts => {
+                self.accounts_state = AccountsState::default();
+                if let Err(e) = self.load_derived_accounts() {
+                    self.accounts_state.error = Some(Failure::new(&e).retry(Retry::LoadAccounts));
+                }
+            }
+            AppState::Plugin(index) => self.with_plugin(index, |plugin, context| {
+                plugin.open(context);
+            }),
+            _ => {}
+        }
+        self.state = state;
+        tracing::info!(
+            screen = self.screen_name().unwrap_or_default(),
+            "Opened screen"
+        );
+    }
+
+    /// Show the instructions of the selected transaction
+    fn inspect_selected_transaction(&mut self) {
+        let Some(signature) = self.history.selected_entry().map(|e| e.signature.clone()) else {
+            return;
+        };
+        match history::inspect(&*self.rpc_client, &signature, &mut self.idls) {
+            Ok(inspection) => {
+                self.history.inspection = Some(inspection);
+                self.history.error = None;
+            }
+            Err(e) => self.history.error = Some(Failure::new(&e).retry(Retry::Inspect)),
+        }
+    }
+
+    /// Fetch the account at the address on the Lookup screen, or the
+    /// rent-exempt minimum if a data length was entered
+    fn lookup_account(&mut self) {
+        let lookup = &mut self.lookup_state;
+        let input = lookup.input.trim();
+        let result = if let Ok(data_len) = input.parse::<usize>() {
+            rent::minimum(&*self.rpc_client, data_len).and_then(|minimum| {
+                Ok(LookupResult::Rent {
+                    data_len,
+                    minimum,
+                    common: rent::common_minimums(&*self.rpc_client)?,
+                })
+            })
+        } else {
+            let Ok(address) = Pubkey::from_str(input) else {
+                lookup.error = Some(Failure::message(tr("Enter an address or a data length")));
+                return;
+            };
+            accountinfo::fetch(&*self.rpc_client, &address)
+                .map(|account| LookupResult::Account(address, account))
+        };
+        match result {
+            Ok(result) => {
+                lookup.result = Some(result);
+                lookup.error = None;
+                lookup.field = 0;
+            }
+            Err(e) => lookup.error = Some(Failure::new(&e).retry(Retry::LookupAccount)),
+        }
+    }
+
+    /// Find the wallet's nonce accounts, keeping the selection on the same
+    /// one if it's still there
+    fn load_nonce_accounts(&mut self) {
+        let state = &mut self.nonce_state;
+        let selected = state.selected_account().map(|account| account.address);
+        match nonce::list(&*self.rpc_client, &self.wallet.address) {
+            Ok((accounts, free)) => {
+                state.selected = selected
+                    .and_then(|address| accounts.iter().position(|a| a.address == address))
+                    .unwrap_or(0);
+                state.accounts = accounts;
+                state.free = free;
+                state.error = None;
+            }
+            Err(e) => state.error = Some(Failure::new(&e).retry(Retry::LoadNonceAccounts)),
+        }
+    }
+
+    /// Check that a new nonce account can be made and paid for, and how
+    /// much it takes
+    fn prepare_nonce_account(&self) -> Result<NonceAction> {
+        if self.nonce_state.free.is_none() {
+            bail!(
+                "All {} nonce account seeds are in use; withdraw from one to close it",
+                nonce::MAX_ACCOUNTS
+            );
+        }
+        let minimum = rent::minimum(&*self.rpc_client, nonce::ACCOUNT_SIZE)?;
+        if let Some(shortfall) = rent::sender_shortfall(
+            &*self.rpc_client,
+            &self.wallet.address,
+            &self.wallet.address,
+            minimum,
+        )? {
+            bail!(Refused(sender_rent_message(shortfall)));
+        }
+        Ok(NonceAction::Create(minimum))
+    }
+
+    /// The withdrawal entered for the selected nonce account
+    fn prepare_nonce_withdrawal(&self) -> Result<NonceAction> {
+        let account = self
+            .nonce_state
+            .selected_account()
+            .context("No nonce account selected")?;
+        let lamports = self.amounts.parse(&self.nonce_state.amount)?;
+        if lamports == 0 {
+            bail!("The amount must be more than 0");
+        }
+        let minimum = rent::minimum(&*self.rpc_client, nonce::ACCOUNT_SIZE)?;
+        nonce::check_withdrawal(account, lamports, minimum)?;
+        Ok(NonceAction::Withdraw(lamports))
+    }
+
+    /// Sign and send the confirmed nonce action in the background
+    fn start_nonce_action(&mut self, action: NonceAction) -> Result<()> {
+        let keypair = self.signer()?;
+        let rpc_client = self.rpc_client.clone();
+        let wallet = self.wallet.address;
+        let account = self.nonce_state.selected_account().cloned();
+        let free = self.nonce_state.free;
+        let task = Task::spawn(move || match action {
+            NonceAction::Create(lamports) => {
+                let index = free.context("No nonce account seed is free")?;
+                let (_, signature) = nonce::create(&*rpc_client, &*keypair, index, lamports)?;
+                Ok(signature)
+            }
+            NonceAction::Advance => {
+                let account = account.context("No nonce account selected")?;
+                nonce::advance(&*rpc_client, &*keypair, &account)
+            }
+            NonceAction::Withdraw(lamports) => {
+                let account = account.context("No nonce account selected")?;
+                nonce::withdraw(&*rpc_client, &*keypair, &account, &wallet, lamports)
+            }
+        });
+        self.nonce_state.error = None;
+        self.nonce_task = Some((task, action));
+        Ok(())
+    }
+
+    fn finish_nonce_action(&mut self, signature: Signature, action: NonceAction) {
+        self.last_tx_signature = Some(signature);
+        self.toasts.success(match action {
+            NonceAction::Create(_) => format!("Nonce account created: {}", signature),
+            NonceAction::Advance => format!("Nonce advanced: {}", signature),
+            NonceAction::Withdraw(_) => format!("Withdrawn from nonce account: {}", signature),
+        });
+        self.refresh_balance(false);
+        self.nonce_state.step = NonceStep::List;
+        self.nonce_state.amount.clear();
+        self.load_nonce_accounts();
+    }
+
+    /// Open the Send screen at the amount, to the selected nonce account
+    fn fund_selected_nonce_account(&mut self) {
+        let Some(account) = self.nonce_state.selected_account() else {
+            return;
+        };
+        let mut send = SendState::default();
+        send.recipient.set(account.address.to_string());
+        send.advance();
+        self.send_state = send;
+        self.state = AppState::Send;
+    }
+
+    /// Open Send at the confirmation of the transfer the selected failed
+    /// transaction made. It's signed anew, with a fresh blockhash and the
+    /// current fee settings.
+    fn resend_selected_transaction(&mut self) -> Result<()> {
+        let entry = self
+            .history
+            .selected_entry()
+            .context("No transaction selected")?;
+        if !entry.retryable() {
+            bail!("Only failed transactions can be sent again");
+        }
+        let signature = Signature::from_str(&entry.signature).context("Invalid signature")?;
+        let reference = entry
+            .reference
+            .as_deref()
+            .and_then(|reference| Pubkey::from_str(reference).ok());
+        // Once sent again, it's that one that could pay twice
+        let address = self.wallet.address;
+        let resent_as = self.cluster_id().and_then(|cluster| {
+            self.open_history_db()?
+                .resent_as(&address, &cluster, &signature.to_string())
+        })?;
+        if let Some(resent_as) = resent_as {
+            let resent_as = Signature::from_str(&resent_as).context("Invalid signature")?;
+            history::check_resent(&*self.rpc_client, &resent_as)?;
+        }
+        let transfer = history::sent_transfer(&*self.rpc_client, &signature, &self.wallet.address)?;
+        // The list may be out of date, so what the ledger says goes
+        history::check_resend(&*self.rpc_client, &signature, &transfer.blockhash)?;
+
+        let mut send = SendState::default();
+        send.recipient.set(transfer.recipient.to_string());
+        send.amount.set(self.amounts.input(transfer.lamports));
+        send.input_mode = SendInputMode::EditingAmount;
+        send.reference_override = Some(reference.is_some());
+        send.reference_key = reference;
+        send.resending = Some(signature);
+        self.send_state = send;
+        self.state = AppState::Send;
+        // Whatever stops it is shown on Send, where it can be dealt with
+        if let Err(e) = self.review_send() {
+            self.send_state.error = Some(Failure::new(&e));
+        }
+        Ok(())
+    }
+
+    /// The error shown on the current screen
+    fn failure(&self) -> Option<&Failure> {
+        if self.session_lock.is_locked() {
+            return self.lock_error.as_ref();
+        }
+        match self.state {
+            AppState::Wallet => self.balance_error.as_ref(),
+            AppState::Send => self.send_state.error.as_ref(),
+            AppState::Transactions => self.history.error.as_ref(),
+            AppState::Accounts => self.accounts_state.error.as_ref(),
+            AppState::Watch => self.watch.error.as_ref(),
+            AppState::Vanity => self.vanity_state.error.as_ref(),
+            AppState::Import => self.import_state.error.as_ref(),
+            AppState::SignMessage => self.sign_state.error.as_ref(),
+            AppState::VerifyMessage => self.verify_state.error.as_ref(),
+            AppState::SignIn => self.sign_in_state.error.as_ref(),
+            AppState::InspectTx => self.inspect_state.error.as_ref(),
+            AppState::Lookup => self.lookup_state.error.as_ref(),
+            AppState::Nonce => self.nonce_state.error.as_ref(),
+            AppState::Export => self.export_state.error.as_ref(),
+            AppState::Plugin(index) => self.plugins.get(index).and_then(|plugin| plugin.failure()),
+            AppState::Settings => self.settings.error.as_ref(),
+            AppState::Home => self.dashboard.error.as_ref(),
+            AppState::Receive => self.receive.error.as_ref(),
+            AppState::Requests => self.requests.error.as_ref(),
+        }
+    }
+
+    /// The keys that retry, for the error panel
+    fn retry_key(&self) -> Option<String> {
+        self.keymap.keys_for(self.key_context(), Action::Retry)
+    }
+
+    /// Run what the error on the current screen came from again
+    fn retry(&mut self) {
+        let Some(retry) = self.failure().and_then(|failure| failure.retry) else {
+            return;
+        };
+        match retry {
+            Retry::RefreshBalance => {
+                self.balance_error = None;
+                self.refresh_balance(true);
+            }
+            Retry::LoadHistory => self.reload_history(),
+            Retry::HistoryPage => {
+                self.history.error = None;
+                self.start_history_page();
+            }
+            Retry::HistorySync => {
+                self.history.error = None;
+                self.start_history_sync();
+            }
+            Retry::Inspect => self.inspect_selected_transaction(),
+            Retry::LoadAccounts => {
+                self.accounts_state.error = self
+                    .load_derived_accounts()
+                    .err()
+                    .map(|e| Failure::new(&e).retry(Retry::LoadAccounts));
+            }
+            Retry::LookupAccount => self.lookup_account(),
+            Retry::LoadNonceAccounts => self.load_nonce_accounts(),
+            Retry::Watch => {
+                self.watch.error = None;
+                self.refresh_watched();
+            }
+            Retry::Requests => {
+                self.requests.error = None;
+                self.refresh_requests();
+            }
+            Retry::Dashboard => {
+                self.dashboard.error = None;
+                self.refresh_dashboard();
+            }
+            Retry::Send => {
+                self.send_state.error = self.send_transaction().err().map(|e| Failure::new(&e));
+            }
+            Retry::Plugin => {
+                if let AppState::Plugin(index) = self.state {
+                    self.with_plugin(index, |plugin, context| plugin.open(context));
+                }
+            }
+        }
+    }
+
+    /// The wallet's signing key, which is dropped while the session is
+    /// locked and never kept in read-only mode
+    fn signer(&self) -> Result<Arc<SecretKeypair>> {
+        if self.read_only {
+            return Err(Refused(READ_ONLY.to_string()).into());
+        }
+        self.wallet.keypair.clone().context("Wallet is locked")
+    }
+
+    /// Whether a bound action would do anything, to keep it off the footer
+    fn action_available(&self, action: Action) -> bool {
+        match action {
+            action if self.read_only && signs(action) => false,
+            Action::OpenLastTransaction => self.last_tx_signature.is_some(),
+            Action::RefreshBlocklist => self.blocklist.has_remote(),
+            Action::SwitchAccount => !self.accounts_state.accounts.is_empty(),
+            Action::CreateNonce => self.nonce_state.free.is_some(),
+            Action::AdvanceNonce | Action::WithdrawNonce | Action::FundNonce => {
+                !self.nonce_state.accounts.is_empty()
+            }
+            Action::BumpAmount | Action::SetPriorityFee | Action::ToggleReference => {
+                self.state == AppState::Send
+            }
+            Action::ShowRequest => !self.requests.requests.is_empty(),
+            Action::Resend => self
+                .history
+                .selected_entry()
+                .is_some_and(HistoryEntry::retryable),
+            Action::Open if self.key_context() == KeyContext::Requests => self
+                .requests
+                .requests
+                .get(self.requests.selected)
+                .is_some_and(|saved| saved.signature.is_some()),
+            Action::Refresh => match self.key_context() {
+                KeyContext::Wallet => self.balance_task.is_none(),
+                KeyContext::Transactions => self.history_task.is_none(),
+                KeyContext::Requests => self.requests.task.is_none(),
+                KeyContext::Plugin => !self.plugins.iter().any(|plugin| plugin.busy()),
+                _ => true,
+            },
+            Action::Cancel if self.key_context() == KeyContext::Wallet => {
+                self.balance_task.is_some()
+            }
+            Action::Retry => self
+                .failure()
+                .is_some_and(|failure| failure.retry.is_some()),
+            Action::PageUp | Action::PageDown => match self.key_context() {
+                KeyContext::Transactions => true,
+                KeyContext::Help | KeyContext::Inspection => self.scroll_limit.get() > 0,
+                _ => self.content_limit.get() > 0,
+            },
+            _ => true,
+        }
+    }
+
+    /// Derive the configured number of accounts from the wallet's seed phrase
+    /// and fetch their balances
+    fn load_derived_accounts(&mut self) -> Result<()> {
+        let keypair = self.signer()?;
+        let (seed, current) = keypair
+            .seed_and_derivation()
+            .context("This wallet was not derived from a seed phrase")?;
+
+        let mut derivations = vec![Derivation::Root];
+        derivations.extend((0..self.config.accounts.count).map(Derivation::account));
+
+        let addresses = derivations
+            .iter()
+            .map(|derivation| seed.derive(derivation).map(|keypair| keypair.pubkey()))
+            .collect::<Result<Vec<_>>>()?;
+        let balances = self
+            .rpc_client
+            .get_multiple_accounts(&addresses)
+            .context("Failed to fetch account balances")?;
+
+        self.accounts_state.selected = derivations
+            .iter()
+            .position(|derivation| derivation == current)
+            .unwrap_or(0);
+        self.accounts_state.accounts = derivations
+            .into_iter()
+            .zip(addresses)
+            .zip(balances)
+            .map(|((derivation, address), account)| DerivedAccount {
+                derivation,
+                address,
+                lamports: account.map_or(0, |account| account.lamports),
+            })
+            .collect();
+        Ok(())
+    }
+
+    /// Make the selected derived account the active signing account
+    fn switch_to_selected_account(&mut self) -> Result<()> {
+        let Some(account) = self
+            .accounts_state
+            .accounts
+            .get(self.accounts_state.selected)
+        else {
+            return Ok(());
+        };
+        self.switch_account(&account.derivation.clone())
+    }
+
+    /// Make the account derived at `derivation` from the wallet's seed
+    /// phrase the active signing account
+    fn switch_account(&mut self, derivation: &Derivation) -> Result<()> {
+        let keypair = self.signer()?;
+        let (seed, _) = keypair
+            .seed_and_derivation()
+            .context("This wallet was not derived from a seed phrase")?;
+
+        let keypair = seed.derive(derivation)?;
+        self.wallet.address = keypair.pubkey();
+        tracing::info!(%derivation, address = %self.wallet.address, "Switched account");
+        self.wallet.keypair = Some(Arc::new(keypair));
+        if let Some(ref hooks) = self.hooks {
+            hooks.watch(self.wallet.address);
+        }
+        if let Some(ref grpc) = self.grpc {
+            grpc.watch(self.wallet.address);
+        }
+        self.wallet.lamports = 0;
+        self.send_state = SendState::default();
+        self.last_tx_signature = None;
+        self.history_head = None;
+        self.refresh_balance(false);
+        Ok(())
+    }
+
+    /// Change the selected setting: the next choice for those with a few,
+    /// or start typing a new value
+    fn edit_setting(&mut self) {
+        let setting = Setting::ALL[self.settings.selected];
+        match setting.next(&self.config) {
+            Some(value) => {
+                self.settings.error = self
+                    .change_setting(setting, &value)
+                    .err()
+                    .map(|e| Failure::new(&e));
+            }
+            None => {
+                self.settings.error = None;
+                self.settings.input = Some((SettingsInput::Value, setting.value(self)));
+            }
+        }
+    }
+
+    /// Put `value` in use for `setting` and save it to the config file
+    fn change_setting(&mut self, setting: Setting, value: &str) -> Result<()> {
+        let value = value.trim();
+        let saved: toml_edit::Value = match setting {
+            Setting::Cluster => {
+                if value.is_empty() {
+                    bail!("Enter a cluster name or RPC URL");
+                }
+                self.switch_cluster(resolve_rpc_url(value))?;
+                self.config.cluster.name = value.to_string();
+                value.into()
+            }
+            Setting::Commitment => {
+                let commitment: CommitmentLevel = value
+                    .parse()
+                    .map_err(|_| anyhow::anyhow!("Unknown commitment: {}", value))?;
+                let previous = std::mem::replace(&mut self.config.cluster.commitment, commitment);
+                if let Err(e) = self.switch_cluster(self.rpc_url.clone()) {
+                    self.config.cluster.commitment = previous;
+                    return Err(e);
+                }
+                commitment.to_string().into()
+            }
+            Setting::Explorer => {
+                self.config.explorer.provider = Explorer::ALL
+                    .into_iter()
+                    .find(|explorer| explorer.name() == value)
+                    .with_context(|| format!("Unknown explorer: {}", value))?;
+                value.into()
+            }
+            Setting::Theme => {
+                let mut theme = self.config.theme.clone();
+                theme.preset = ThemePreset::ALL
+                    .into_iter()
+                    .find(|preset| preset.name() == value)
+                    .with_context(|| format!("Unknown theme: {}", value))?;
+                self.theme = Theme::from_config(&theme)?;
+                self.config.theme = theme;
+                value.into()
+            }
+            Setting::Refresh => {
+                let seconds: u32 = value
+                    .parse()
+                    .context("Enter a whole number of seconds, or 0 to only refresh with r")?;
+                self.config.refresh.seconds = u64::from(seconds);
+                i64::from(seconds).into()
+            }
+            Setting::FeePercentile => {
+                let percentile: u8 = value
+                    .parse()
+                    .ok()
+                    .filter(|&percentile| percentile <= 100)
+                    .context("Enter a percentile from 0 to 100, or 0 for no priority fee")?;
+                self.config.priority_fee.percentile = percentile;
+                i64::from(percentile).into()
+            }
+            Setting::FeeMax => {
+                let max: u32 = value
+                    .parse()
+                    .context("Enter a whole number of micro-lamports per compute unit")?;
+                self.config.priority_fee.max = u64::from(max);
+                i64::from(max).into()
+            }
+            Setting::PriceAbove | Setting::PriceBelow => {
+                let price = if value.is_empty() {
+                    None
+                } else {
+                    Some(
+                        value
+                            .parse::<f64>()
+                            .ok()
+                            .filter(|price| price.is_finite() && *price > 0.0)
+                            .context("Enter a price in USD, or nothing to turn the alert off")?,
+                    )
+                };
+                let mut alerts = self.config.price_alerts.clone();
+                if setting == Setting::PriceAbove {
+                    alerts.sol_above = price;
+                } else {
+                    alerts.sol_below = price;
+                }
+                self.prices = price::Checker::start(&alerts)?;
+                self.config.price_alerts = alerts;
+                tracing::info!(setting = setting.label(), value, "Changed setting");
+                if let Some(ref path) = self.config_path {
+                    let (section, key) = setting.key();
+                    match price {
+                        Some(price) => Config::save_setting(path, section, key, price)?,
+                        None => Config::remove_setting(path, section, key)?,
+                    }
+                }
+                return Ok(());
+            }
+        };
+        tracing::info!(setting = setting.label(), value, "Changed setting");
+
+        if let Some(ref path) = self.config_path {
+            let (section, key) = setting.key();
+            Config::save_setting(path, section, key, saved)?;
+        }
+        Ok(())
+    }
+
+    /// The name given to the wallet, if any
+    fn wallet_name(&self) -> Option<&str> {
+        self.config.wallet_name(&self.wallet.address)
+    }
+
+    /// Give the wallet a display name, or take it away with an empty one,
+    /// and save it to the config file
+    fn name_wallet(&mut self, name: &str) -> Result<()> {
+        let name = name.trim();
+        let address = self.wallet.address.to_string();
+        if let Some(ref path) = self.config_path {
+            if name.is_empty() {
+                Config::remove_setting(path, "names", &address)?;
+            } else {
+                Config::save_setting(path, "names", &address, name)?;
+            }
+        }
+        tracing::info!(%address, name, "Named wallet");
+        if name.is_empty() {
+            self.config.names.remove(&address);
+        } else {
+            self.config.names.insert(address, name.to_string());
+        }
+        Ok(())
+    }
+
+    /// Load the keypair from `source` and make it the wallet, starting its
+    /// balance and history afresh and forgetting everything of the one
+    /// before
+    fn switch_keypair(&mut self, source: KeypairSource) -> Result<()> {
+        if !matches!(source, KeypairSource::File(_)) {
+            bail!("Only keypair files can be opened while solace is running");
+        }
+        if self.send_task.is_some() || self.nonce_task.is_some() {
+            bail!("Wait for the transaction being sent to land first");
+        }
+        let keypair = wallet::load_keypair(
+            &source,
+            None,
+            None,
+            &*self.rpc_client,
+            self.config.accounts.count,
+        )?;
+        // Kept under the wallet it's for, before that changes
+        if let Err(e) = self.save_session() {
+            self.toasts.error(format!("{:#}", e));
+        }
+
+        self.wallet.address = keypair.pubkey();
+        tracing::info!(%source, address = %self.wallet.address, "Opened keypair");
+        self.wallet.keypair = (!self.read_only).then(|| Arc::new(keypair));
+        self.wallet.keypair_source = source;
+        self.opened_address = self.wallet.address;
+        if let Some(ref hooks) = self.hooks {
+            hooks.watch(self.wallet.address);
+        }
+        if let Some(ref grpc) = self.grpc {
+            grpc.watch(self.wallet.address);
+        }
+
+        // Everything of the wallet before
+        self.wallet.lamports = 0;
+        self.balance_error = None;
+        self.send_state = SendState::default();
+        self.last_tx_signature = None;
+        self.export_state = ExportState::default();
+        self.sign_state = SignState::default();
+        self.sign_in_state = SignInState::default();
+        self.accounts_state = AccountsState::default();
+        self.nonce_state = NonceState::default();
+        self.history = History::default();
+        self.history_task = None;
+        self.history_prefetch = 0;
+        self.start(Startup::spawn(&self.rpc_client, self.wallet.address));
+        self.toasts
+            .success(format!("Switched to wallet {}", self.wallet.address));
+        Ok(())
+    }
+
+    /// Connect to `rpc_url` at the configured commitment, and start the
+    /// balance and history over from what that cluster has
+    fn switch_cluster(&mut self, rpc_url: String) -> Result<()> {
+        if self.send_task.is_some() || self.nonce_task.is_some() {
+            bail!("Wait for the transaction being sent to land first");
+        }
+        let (rpc_client, backoff) = rpc::connect(&self.config, &rpc_url);
+        let cluster = Cluster::label(&rpc_url);
+        tracing::info!(%cluster, commitment = %self.config.cluster.commitment, "Switched cluster");
+        audit::set_cluster(&cluster);
+        self.hooks = Hooks::start(&self.config.webhook, &rpc_url, self.wallet.address);
+        // The endpoint serves the cluster connected to at startup
+        if self.grpc.take().is_some() {
+            self.toasts
+                .info("Stopped following the wallet over gRPC; polling the RPC instead");
+        }
+        self.rpc_client = rpc_client;
+        self.backoff = Some(backoff);
+        self.rpc_url = rpc_url;
+        self.guard_sends();
+
+        // Everything read from the cluster before
+        self.mainnet = None;
+        self.cluster_id = None;
+        self.dashboard.balances = None;
+        self.watch.watched.clear();
+        self.wallet.lamports = 0;
+        self.balance_error = None;
+        self.send_state = SendState::default();
+        self.last_tx_signature = None;
+        self.accounts_state = AccountsState::default();
+        self.nonce_state = NonceState::default();
+        self.history = History::default();
+        self.history_task = None;
+        self.history_prefetch = 0;
+        self.start(Startup::spawn(&self.rpc_client, self.wallet.address));
+        Ok(())
+    }
+
+    pub fn on_tick(&mut self) {
+        self.toasts.expire();
+        if let Some(ref hooks) = self.hooks {
+            for error in hooks.take_errors() {
+                self.toasts.error(error);
+            }
+        }
+        let notices = self.hooks.as_ref().map(Hooks::take_notices);
+        for notice in notices.into_iter().flatten() {
+            match notice {
+                Notice::Message(text) => self.toasts.info(text),
+                Notice::Label {
+                    wallet,
+                    signature,
+                    label,
+                } if wallet == self.wallet.address.to_string() => {
+                    if let Err(e) = self.set_history_note(&signature, &label) {
+                        self.toasts.error(format!("{:#}", e));
+                    }
+                }
+                Notice::Label { .. } => {}
+            }
+        }
+        self.apply_grpc_updates();
+        self.deliver_price_alerts();
+        if self.session_lock.should_lock() {
+            self.lock();
+        }
+
+        if self
+            .refresh_interval()
+            .is_some_and(|interval| self.last_refresh.elapsed() >= interval)
+        {
+            self.auto_refresh();
+        }
+
+        if self.state == AppState::Transactions
+            && self.history.pending_sync
+            && self.history_task.is_none()
+        {
+            self.start_history_sync();
+        }
+
+        let receive = &self.receive;
+        if receive.request.is_some()
+            && receive.paid.is_none()
+            && receive.task.is_none()
+            && !self.session_lock.is_locked()
+            && receive
+                .checked
+                .is_none_or(|at| at.elapsed() >= PAYMENT_POLL_INTERVAL)
+        {
+            self.check_payment();
+        }
+
+        if let Some(keypair) = self
+            .vanity_state
+            .grinder
+            .as_ref()
+            .and_then(Grinder::take_result)
+        {
+            let vanity = &mut self.vanity_state;
+            vanity.grinder = None;
+            vanity.path_input = keys::default_import_path(&keypair.pubkey())
+                .map(|path| path.display().to_string())
+                .unwrap_or_default();
+            vanity.keypair = Some(keypair);
+            vanity.step = VanityStep::Saving;
+        }
+
+        let reveal_timeout = Duration::from_secs(self.config.export.reveal_seconds);
+        if self
+            .export_state
+            .revealed_at
+            .is_some_and(|at| at.elapsed() >= reveal_timeout)
+        {
+            self.export_state = ExportState::default();
+            self.state = AppState::Settings;
+        }
+    }
+
+    /// Tell of the prices that crossed their `[price_alerts]` thresholds
+    fn deliver_price_alerts(&mut self) {
+        let Some(ref prices) = self.prices else {
+            return;
+        };
+        for error in prices.take_errors() {
+            self.toasts.error(error);
+        }
+        for alert in prices.take_alerts() {
+            let message = alert.message();
+            tracing::info!(mint = %alert.mint, message, "Price alert");
+            if self.config.price_alerts.desktop {
+                if let Err(e) = notify::desktop(&message) {
+                    self.toasts.error(format!("{:#}", e));
+                }
+            }
+            self.toasts.info(message);
+        }
+    }
+
+    /// Take in what the gRPC stream pushed: balances as they are, and
+    /// transactions as a cue to look again at what they change
+    fn apply_grpc_updates(&mut self) {
+        let Some(ref grpc) = self.grpc else {
+            return;
+        };
+        for error in grpc.take_errors() {
+            self.toasts.error(error);
+        }
+        for (address, update) in grpc.take_updates() {
+            if address != self.wallet.address {
+                continue;
+            }
+            match update {
+                grpc::Update::Balance(lamports) => {
+                    self.wallet.lamports = lamports;
+                    self.balance_error = None;
+                }
+                grpc::Update::Transaction { signature, .. } => {
+                    tracing::debug!(%signature, "Transaction pushed over gRPC");
+                    if self.state == AppState::Transactions && !self.history.entries.is_empty() {
+                        self.history.pending_sync = true;
+                    }
+                    // A payment being waited for is checked right away
+                    self.receive.checked = None;
+                }
+            }
+        }
+    }
+
+    /// How often the open screen refreshes on its own: its override under
+    /// `[refresh.screens]`, or the interval for every screen. `None` if it
+    /// doesn't, or while the session is locked.
+    fn refresh_interval(&self) -> Option<Duration> {
+        if self.session_lock.is_locked() {
+            return None;
+        }
+        let refresh = &self.config.refresh;
+        let seconds = self
+            .screen_name()
+            .and_then(|name| refresh.screens.get(&name).copied())
+            .unwrap_or(refresh.seconds);
+        (seconds > 0).then(|| Duration::from_secs(seconds))
+    }
+
+    /// Refresh the balance, unless the gRPC stream keeps it, and what the
+    /// open screen shows if it loads in the background: newer transactions,
+    /// or a plugin's pane. Sends follow their own status until they land.
+    fn auto_refresh(&mut self) {
+        self.last_refresh = Instant::now();
+        let streamed = self.grpc.as_ref().is_some_and(grpc::Stream::is_live);
+        if self.balance_task.is_none() && !streamed {
+            self.refresh_balance(false);
+        }
+        match self.state {
+            AppState::Transactions if !self.history.entries.is_empty() => {
+                self.history.pending_sync = true;
+            }
+            AppState::Plugin(i) => self.with_plugin(i, |plugin, context| plugin.refresh(context)),
+            AppState::Home if self.has_dashboard() && self.dashboard.task.is_none() => {
+                self.refresh_dashboard();
+            }
+            AppState::Requests if self.requests.task.is_none() => self.refresh_requests(),
+            _ => {}
+        }
+        // Alerts need the addresses followed from any screen
+        let watching = self.state == AppState::Watch || !self.config.watch.alerts.is_empty();
+        if watching && self.watch.task.is_none() {
+            self.refresh_watched();
+        }
+    }
+
+    /// Copy the chosen secret out of the keypair for display
+    fn reveal_secret(&mut self) -> Result<()> {
+        let keypair = self.signer()?;
+        let secret = match self.export_state.kind {
+            ExportKind::PrivateKey => keypair.to_base58(),
+            ExportKind::SeedPhrase => Zeroizing::new(
+                keypair
+                    .seed_phrase()
+                    .context("This wallet was not derived from a seed phrase")?
+                    .to_string(),
+            ),
+            ExportKind::PaperWallet => {
+                self.export_state.path_input = dirs::home_dir()
+                    .map(|home| {
+                        home.join(format!("solace-paper-{}.svg", keypair.pubkey()))
+                            .display()
+                            .to_string()
+                    })
+                    .unwrap_or_default();
+                self.export_state.step = ExportStep::SavingPaper;
+                return Ok(());
+            }
+            ExportKind::Shares => {
+                self.export_state.path_input = dirs::home_dir()
+                    .map(|home| {
+                        home.join(format!("solace-shares-{}", keypair.pubkey()))
+                            .display()
+                            .to_string()
+                    })
+                    .unwrap_or_default();
+                self.export_state.step = ExportStep::SavingShares;
+                return Ok(());
+            }
+        };
+        self.export_state.secret = Some(secret);
+        self.export_state.revealed_at = Some(Instant::now());
+        self.export_state.step = ExportStep::Revealed;
+        Ok(())
+    }
+
+    /// Lock the session, dropping the signing key (if it can be reloaded on
+    /// unlock) and any in-progress send
+    fn lock(&mut self) {
+        tracing::info!("Locked session");
+        self.session_lock.lock();
+        if self.wallet.keypair_source.is_reloadable() {
+            self.wallet.keypair = None;
+        }
+        if self
+            .wallet
+            .fee_payer_source
+            .as_ref()
+            .is_some_and(KeypairSource::is_reloadable)
+        {
+            self.wallet.fee_payer = None;
+        }
+        self.send_state = SendState::default();
+        self.export_state = ExportState::default();
+        self.import_state = ImportState::default();
+        self.sign_state = SignState::default();
+        self.sign_in_state = SignInState::default();
+        self.inspect_state = InspectState::default();
+        // Nothing confirmed before the lock goes ahead after it
+        self.nonce_state.step = NonceStep::List;
+        if matches!(
+            self.state,
+            AppState::Send
+                | AppState::SignMessage
+                | AppState::SignIn
+                | AppState::InspectTx
+                | AppState::Export
+                | AppState::Import
+        ) {
+            self.state = AppState::Wallet;
+        }
+        self.lock_error = None;
+    }
+
+    /// Check the typed PIN and reload the signing key
+    fn unlock(&mut self) {
+        match self.session_lock.try_unlock() {
+            Unlock::Unlocked => {}
+            Unlock::Wrong(wait) => {
+                tracing::info!("Incorrect PIN entered");
+                self.lock_error = Some(Failure::message(match wait {
+                    Some(wait) => {
+                        tr_fmt("Incorrect PIN; try again in {} seconds", &[&wait.as_secs()])
+                    }
+                    None => tr("Incorrect PIN").to_string(),
+                }));
+                return;
+            }
+            Unlock::TooSoon(wait) => {
+                self.lock_error = Some(Failure::message(tr_fmt(
+                    "Too many incorrect PINs; try again in {} seconds",
+                    &[&wait.as_secs().max(1)],
+                )));
+                return;
+            }
+        }
+        tracing::info!("Unlocked session");
+        if let (None, Some(ref source)) = (&self.wallet.fee_payer, &self.wallet.fee_payer_source) {
+            match source.load() {
+                Ok(fee_payer) => self.wallet.fee_payer = Some(Arc::new(fee_payer)),
+                Err(e) => {
+                    self.session_lock.lock();
+                    self.lock_error = Some(Failure::new(&e));
+                    return;
+                }
+            }
+        }
+        if self.wallet.keypair.is_some() || self.read_only {
+            self.lock_error = None;
+            return;
+        }
+
+        match self.wallet.keypair_source.load() {
+            Ok(keypair) if keypair.pubkey() == self.wallet.address => {
+                self.wallet.keypair = Some(Arc::new(keypair));
+                self.lock_error = None;
+            }
+            Ok(_) => {
+                self.session_lock.lock();
+                self.lock_error = Some(Failure::message(tr(
+                    "Keypair source now holds a different wallet",
+                )));
+            }
+            Err(e) => {
+                self.session_lock.lock();
+                self.lock_error = Some(Failure::new(&e));
+            }
+        }
+    }
+
+    fn refresh_blocklist(&mut self) {
+        match self.blocklist.refresh_remote() {
+            Ok(count) => {
+                self.guard_sends();
+                self.toasts
+                    .success(format!("Fetched {} remote blocklist entries", count));
+            }
+            Err(e) => self.toasts.error(format!("{:#}", e)),
+        }
+    }
+
+    /// Take over the fetches started with the wallet
+    pub fn start(&mut self, startup: Startup) {
+        self.announce_balance = false;
+        self.balance_task = Some(startup.balance);
+        self.cluster_task = Some(startup.genesis_hash);
+        self.history_head = Some(startup.history_head);
+        if self.has_dashboard() {
+            self.refresh_dashboard();
+        }
+        // What the first alerts compare to
+        if !self.config.watch.alerts.is_empty() {
+            self.refresh_watched();
+        }
+    }
+
+    /// Whether Home shows the dashboard rather than the welcome text
+    fn has_dashboard(&self) -> bool {
+        !self.config.profiles.is_empty()
+    }
+
+    /// Read the profiles' addresses if that's still to do, and list the
+    /// wallets the dashboard shows
+    fn dashboard_wallets(&mut self) -> Result<Vec<(Pubkey, Option<PathBuf>)>> {
+        let profiles = match self.dashboard.profiles {
+            Some(ref profiles) => profiles.clone(),
+            None => {
+                let profiles = self
+                    .config
+                    .profiles
+                    .iter()
+                    .map(|profile| {
+                        let path = config::expand_tilde(&profile.keypair);
+                        let keypair =
+                            KeypairSource::File(path.clone()).load().with_context(|| {
+                                format!("Failed to load profile keypair from {}", path.display())
+                            })?;
+                        Ok((keypair.pubkey(), path))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                self.dashboard.profiles.insert(profiles).clone()
+            }
+        };
+        let mut wallets: Vec<(Pubkey, Option<PathBuf>)> = Vec::new();
+        if !profiles
+            .iter()
+            .any(|&(address, _)| address == self.wallet.address)
+        {
+            wallets.push((self.wallet.address, None));
+        }
+        for (address, path) in profiles {
+            if !wallets.iter().any(|&(listed, _)| listed == address) {
+                wallets.push((address, Some(path)));
+            }
+        }
+        Ok(wallets)
+    }
+
+    /// Fetch the balances of every wallet on the dashboard in the background
+    fn refresh_dashboard(&mut self) {
+        let wallets = match self.dashboard_wallets() {
+            Ok(wallets) => wallets,
+            Err(e) => {
+                self.dashboard.error = Some(Failure::new(&e).retry(Retry::Dashboard));
+                return;
+            }
+        };
+        let addresses: Vec<Pubkey> = wallets.iter().map(|&(address, _)| address).collect();
+        let rpc_client = self.rpc_client.clone();
+        self.dashboard.selected = self.dashboard.selected.min(wallets.len().saturating_sub(1));
+        self.dashboard.wallets = wallets;
+        self.dashboard.task = Some(Task::spawn(move || {
+            dashboard::fetch(&*rpc_client, &addresses)
+        }));
+    }
+
+    /// Show a Solana Pay request for `amount` on the Receive screen instead
+    /// of the address, and watch for its payment
+    fn request_payment(&mut self, amount: &str) -> Result<()> {
+        let lamports = self.amounts.parse(amount)?;
+        if lamports == 0 {
+            bail!("The amount must be more than 0");
+        }
+        let label = self.wallet_name().map(str::to_string);
+        let request = PaymentRequest::new(self.wallet.address, lamports, label);
+        tracing::info!(reference = %request.reference, lamports, "Payment requested");
+        // Still shown if it can't be kept, just not listed under Requests
+        if let Err(e) = self
+            .cluster_id()
+            .and_then(|cluster| self.open_history_db()?.save_request(&cluster, &request))
+        {
+            self.toasts.error(format!("{:#}", e));
+        }
+        self.receive = ReceiveState {
+            request: Some(request),
+            ..ReceiveState::default()
+        };
+        Ok(())
+    }
+
+    /// Look for the payment of the open request in the background
+    fn check_payment(&mut self) {
+        let Some(request) = self.receive.request.clone() else {
+            return;
+        };
+        let rpc_client = self.rpc_client.clone();
+        self.receive.checked = Some(Instant::now());
+        self.receive.task = Some(Task::spawn(move || {
+            pay::find_payment(&*rpc_client, &request)
+        }));
+    }
+
+    /// The history database, opened the first time it's needed
+    fn open_history_db(&mut self) -> Result<&HistoryDb> {
+        if self.history_db.is_none() {
+            self.history_db = Some(HistoryDb::open()?);
+        }
+        Ok(self.history_db.as_ref().expect("opened above"))
+    }
+
+    /// Load the saved payment requests, then look for the payments of the
+    /// pending ones in the background
+    fn refresh_requests(&mut self) {
+        let address = self.wallet.address;
+        match self
+            .cluster_id()
+            .and_then(|cluster| self.open_history_db()?.load_requests(&address, &cluster))
+        {
+            Ok(requests) => self.requests.requests = requests,
+            Err(e) => {
+                self.requests.error = Some(Failure::new(&e).retry(Retry::Requests));
+                return;
+            }
+        }
+        let requests = &mut self.requests;
+        requests.selected = requests
+            .selected
+            .min(requests.requests.len().saturating_sub(1));
+
+        let (now, expire_hours) = (pay::now(), self.config.payments.expire_hours);
+        let pending: Vec<PaymentRequest> = requests
+            .requests
+            .iter()
+            .filter(|saved| saved.status(now, expire_hours) == RequestStatus::Pending)
+            .map(|saved| saved.request.clone())
+            .collect();
+        if pending.is_empty() {
+            requests.task = None;
+            return;
+        }
+        let rpc_client = self.rpc_client.clone();
+        requests.task = Some(Task::spawn(move || {
+            pay::check_requests(&*rpc_client, &pending)
+        }));
+    }
+
+    /// Keep the transaction that paid `request`, and say it arrived
+    fn record_payment(&mut self, request: &PaymentRequest, signature: &str) {
+        tracing::info!(%signature, lamports = request.lamports, "Payment received");
+        self.toasts.success(format!(
+            "Payment of {} received",
+            self.amounts.sol(request.lamports)
+        ));
+        if let Err(e) = self.cluster_id().and_then(|cluster| {
+            self.open_history_db()?.set_request_paid(
+                &request.recipient,
+                &cluster,
+                &request.reference,
+                signature,
+            )
+        }) {
+            self.toasts.error(format!("{:#}", e));
+        }
+        let saved = self
+            .requests
+            .requests
+            .iter_mut()
+            .find(|saved| saved.request.reference == request.reference);
+        if let Some(saved) = saved {
+            saved.signature = Some(signature.to_string());
+        }
+        let receive = &mut self.receive;
+        if receive
+            .request
+            .as_ref()
+            .is_some_and(|shown| shown.reference == request.reference)
+        {
+            receive.paid = Some(signature.to_string());
+        }
+    }
+
+    /// Fetch what the watched addresses hold and their latest transactions
+    /// in the background
+    fn refresh_watched(&mut self) {
+        let addresses = match watch::addresses(&self.config.watch) {
+            Ok(addresses) => addresses,
+            Err(e) => {
+                self.watch.error = Some(Failure::new(&e).retry(Retry::Watch));
+                return;
+            }
+        };
+        self.watch.selected = self.watch.selected.min(addresses.len().saturating_sub(1));
+        self.watch.addresses = addresses.clone();
+        if addresses.is_empty() {
+            self.watch.task = None;
+            return;
+        }
+        let rpc_client = self.rpc_client.clone();
+        self.watch.task = Some(Task::spawn(move || watch::fetch(&*rpc_client, &addresses)));
+    }
+
+    /// Tell of activity on a watched address the ways `[watch] notify` lists
+    fn deliver_alert(&mut self, alert: &Alert) {
+        let address = alert.address();
+        let label = self
+            .config
+            .wallet_name(&address)
+            .map_or_else(|| address.to_string(), str::to_string);
+        let message = alert.message(&label);
+        tracing::info!(%address, message, "Watch alert");
+        for &channel in &self.config.watch.notify {
+            match channel {
+                Notify::Toast => self.toasts.info(message.clone()),
+                Notify::Desktop => {
+                    if let Err(e) = notify::desktop(&message) {
+                        self.toasts.error(format!("{:#}", e));
+                    }
+                }
+                Notify::Webhook => {
+                    if let Some(ref hooks) = self.hooks {
+                        hooks.alert(alert);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Start watching `address`, saving it to the config file
+    fn add_watched(&mut self, address: &str) -> Result<()> {
+        let address = Pubkey::from_str(address.trim()).context("Invalid address")?;
+        if self.watch.addresses.contains(&address) {
+            bail!("{} is already watched", address);
+        }
+        let mut addresses = self.config.watch.addresses.clone();
+        addresses.push(address.to_string());
+        self.save_watched(addresses)?;
+        tracing::info!(%address, "Watching address");
+        self.refresh_watched();
+        self.watch.selected = self.watch.addresses.len().saturating_sub(1);
+        Ok(())
+    }
+
+    /// Stop watching the selected address, saving that to the config file
+    fn remove_watched(&mut self) -> Result<()> {
+        let Some(address) = self.watch.addresses.get(self.watch.selected).copied() else {
+            return Ok(());
+        };
+        let addresses = self
+            .config
+            .watch
+            .addresses
+            .iter()
+            .filter(|listed| Pubkey::from_str(listed.trim()).ok() != Some(address))
+            .cloned()
+            .collect();
+        self.save_watched(addresses)?;
+        tracing::info!(%address, "Stopped watching address");
+        self.watch
+            .watched
+            .retain(|watched| watched.balance.address != address);
+        self.refresh_watched();
+        Ok(())
+    }
+
+    /// Make `addresses` the watched ones, in the config file too
+    fn save_watched(&mut self, addresses: Vec<String>) -> Result<()> {
+        if let Some(ref path) = self.config_path {
+            let array: toml_edit::Array = addresses.iter().map(String::as_str).collect();
+            Config::save_setting(path, "watch", "addresses", array)?;
+        }
+        self.config.watch.addresses = addresses;
+        Ok(())
+    }
+
+    /// Switch to the wallet selected on the dashboard, unless it's the open
+    /// one, and show it
+    fn open_dashboard_wallet(&mut self) -> Result<()> {
+        let Some((address, path)) = self.dashboard.wallets.get(self.dashboard.selected).cloned()
+        else {
+            return Ok(());
+        };
+        if address != self.wallet.address {
+            let path = path.context("That wallet is no longer open")?;
+            self.switch_keypair(KeypairSource::File(path))?;
+        }
+        if let Some(index) = self
+            .menu()
+            .iter()
+            .position(|&(_, state)| state == AppState::Wallet)
+        {
+            self.selected_menu_item = index;
+        }
+        self.state = AppState::Wallet;
+        Ok(())
+    }
+
+    /// Genesis hash of the cluster, which keys what the history database
+    /// keeps. The RPC URL won't do in its place: it may hold an API key, and
+    /// would file one cluster's notes and requests under two keys.
+    fn cluster_id(&mut self) -> Result<String> {
+        if let Some(ref id) = self.cluster_id {
+            return Ok(id.clone());
+        }
+        // Waiting on the fetch already running rather than asking again
+        let hash = match self.cluster_task.take() {
+            Some(task) => task.wait()?,
+            None => self
+                .rpc_client
+                .get_genesis_hash()
+                .context("Failed to fetch the genesis hash")?,
+        };
+        Ok(self.cluster_id.insert(hash.to_string()).clone())
+    }
+
+    /// Fetch the next page of history in the background, unless a fetch is
+    /// already running
+    fn start_history_page(&mut self) {
+        if self.history_task.is_some() {
+            return;
+        }
+        let before = match self.history.oldest() {
+            Ok(before) => before,
+            Err(e) => {
+                self.history.error = Some(Failure::new(&e));
+                return;
+            }
+        };
+        let rpc_client = self.rpc_client.clone();
+        let address = self.wallet.address;
+        self.history_task = Some(HistoryFetch {
+            task: Task::spawn(move || {
+                history::fetch_page(&*rpc_client, &address, before, None).map(HistoryUpdate::Older)
+            }),
+            retry: Retry::HistoryPage,
+        });
+    }
+
+    /// Fetch transactions newer than the cached ones in the background
+    fn start_history_sync(&mut self) {
+        let until = match self.history.newest() {
+            Ok(until) => until,
+            Err(e) => {
+                self.history.error = Some(Failure::new(&e));
+                return;
+            }
+        };
+        self.history.pending_sync = false;
+        let rpc_client = self.rpc_client.clone();
+        let address = self.wallet.address;
+        self.history_task = Some(HistoryFetch {
+            task: Task::spawn(move || {
+                history::fetch_newer(&*rpc_client, &address, until)
+                    .map(|(new, caught_up)| HistoryUpdate::Newer(new, caught_up))
+            }),
+            retry: Retry::HistorySync,
+        });
+    }
+
+    /// Fetch another page while the filters still leave little to show, up
+    /// to `history_prefetch` more
+    fn prefetch_history(&mut self) {
+        if self.history_prefetch > 0
+            && self.history_task.is_none()
+            && self.history.wants_more()
+            && self.history.error.is_none()
+        {
+            self.history_prefetch -= 1;
+            self.start_history_page();
+        }
+    }
+
+    /// Block until the running history fetch is done
+    fn wait_for_history(&mut self) {
+        if let Some(fetch) = self.history_task.take() {
+            let result = fetch.task.wait();
+            self.finish_history_update(result, fetch.retry);
+        }
+    }
+
+    /// Add fetched history to the view, saving it to the index and recording
+    /// any error for display
+    fn finish_history_update(&mut self, update: Result<HistoryUpdate>, retry: Retry) {
+        let address = self.wallet.address;
+        // Shown but not saved until the cluster is known
+        let cluster = self.cluster_id();
+        let db = match cluster {
+            Ok(ref cluster) => self.history_db.as_mut().map(|db| (db, cluster.as_str())),
+            Err(_) => None,
+        };
+        let result = update
+            .and_then(|update| match update {
+                HistoryUpdate::Older(page) => {
+                    let new = self.history.append_page(page);
+                    if let Some((db, cluster)) = db {
+                        db.insert(&address, cluster, new)?;
+                        if self.history.exhausted {
+                            db.set_complete(&address, cluster, true)?;
+                        }
+                    }
+                    Ok(())
+                }
+                HistoryUpdate::Newer(new, caught_up) => {
+                    let (new, replaced) = self.history.prepend_newer(new, caught_up);
+                    match db {
+                        Some((db, cluster)) => {
+                            if replaced {
+                                db.clear(&address, cluster)?;
+                            }
+                            db.insert(&address, cluster, new)
+                        }
+                        None => Ok(()),
+                    }
+                }
+            })
+            .and_then(|()| self.apply_history_notes(&cluster?));
+        self.history.error = result.err().map(|e| Failure::new(&e).retry(retry));
+        self.prefetch_history();
+    }
+
+    /// Notes and reference keys outlive cache clears, and references are
+    /// recorded before the transfer is fetched, so entries fetched need them
+    fn apply_history_notes(&mut self, cluster: &str) -> Result<()> {
+        if let Some(ref db) = self.history_db {
+            let notes = db.load_notes(&self.wallet.address, cluster)?;
+            self.history.apply_notes(&notes);
+            let references = db.load_references(&self.wallet.address, cluster)?;
+            self.history.apply_references(&references);
+        }
+        Ok(())
+    }
+
+    /// Save the note typed for the selected entry (an empty one removes it)
+    fn save_history_note(&mut self, note: &str) -> Result<()> {
+        let signature = self
+            .history
+            .selected_entry()
+            .map(|entry| entry.signature.clone())
+            .context("No transaction selected")?;
+        self.set_history_note(&signature, note)
+    }
+
+    /// Save the note for the transaction `signature`, shown once its entry
+    /// is loaded if it isn't yet
+    fn set_history_note(&mut self, signature: &str, note: &str) -> Result<()> {
+        let cluster = self.cluster_id()?;
+        let db = self
+            .history_db
+            .as_ref()
+            .context("The history database is not available")?;
+        db.set_note(&self.wallet.address, &cluster, signature, note)?;
+        if let Some(entry) = self
+            .history
+            .entries
+            .iter_mut()
+            .find(|entry| entry.signature == signature)
+        {
+            entry.note = (!note.is_empty()).then(|| note.to_string());
+        }
+        Ok(())
+    }
+
+    /// Load the rest of the history and write the entries matching the
+    /// current filters and search to a report, with the staking rewards if
+    /// they match too, returning how many of each there were
+    fn export_history(&mut self, path: &Path, format: ReportFormat) -> Result<(usize, usize)> {
+        self.history_prefetch = 0;
+        self.wait_for_history();
+        if self.history.pending_sync {
+            self.start_history_sync();
+            self.wait_for_history();
+        }
+        while !self.history.exhausted && self.history.error.is_none() {
+            self.start_history_page();
+            self.wait_for_history();
+        }
+        if let Some(ref failure) = self.history.error {
+            bail!("{}", failure);
+        }
+        let rewards = if self.history.shows_rewards() {
+            staking::wallet_rewards(
+                &*self.rpc_client,
+                &self.wallet.address,
+                self.config.staking.reward_epochs,
+            )?
+            .1
+        } else {
+            Vec::new()
+        };
+        let entries = self.history.visible();
+        report::write_to_file(&entries, &rewards, format, path)?;
+        Ok((entries.len(), rewards.len()))
+    }
+
+    /// Show the cached history right away (checking for newer transactions
+    /// on the next tick), or start from the newest transaction
+    fn reload_history(&mut self) {
+        let mut error = None;
+        if self.history_db.is_none() {
+            match HistoryDb::open() {
+                Ok(db) => self.history_db = Some(db),
+                Err(e) => error = Some(Failure::new(&e).retry(Retry::LoadHistory)),
+            }
+        }
+
+        // Whatever was loading belongs to the history being replaced
+        self.history_task = None;
+        self.history_prefetch = 0;
+        let cached = self.cluster_id().and_then(|cluster| {
+            self.history_db
+                .as_ref()
+                .map(|db| db.load(&self.wallet.address, &cluster))
+                .transpose()
+        });
+        self.history = match cached {
+            Ok(Some((entries, complete))) => History::from_cache(entries, complete),
+            Ok(None) => History::default(),
+            Err(e) => {
+                error = Some(Failure::new(&e).retry(Retry::LoadHistory));
+                History::default()
+            }
+        };
+
+        // The startup fetch is only the newest page when nothing is cached
+        let head = self.history_head.take();
+        if self.history.entries.is_empty() {
+            match head {
+                Some(task) => {
+                    self.history_task = Some(HistoryFetch {
+                        task,
+                        retry: Retry::HistoryPage,
+                    })
+                }
+                None => self.start_history_page(),
+            }
+        }
+        if error.is_some() {
+            self.history.error = error;
+        }
+    }
+
+    /// Fetch the balance in the background, replacing any fetch already
+    /// running (which may be for another account)
+    fn refresh_balance(&mut self, announce: bool) {
+        let rpc_client = self.rpc_client.clone();
+        let address = self.wallet.address;
+        self.announce_balance = announce;
+        self.balance_task = Some(Task::spawn(move || {
+            rpc_client
+                .get_balance(&address)
+                .context("Failed to fetch balance")
+        }));
+    }
+
+    /// Pick up the results of background requests that have finished
+    pub fn poll_tasks(&mut self) {
+        if let Some(status) = self.validator.as_mut().and_then(LocalValidator::exited) {
+            self.toasts
+                .error(format!("The local validator stopped ({})", status));
+            self.validator = None;
+        }
+        if let Some(task) = self.balance_task.take() {
+            match task.poll() {
+                None => self.balance_task = Some(task),
+                Some(Ok(lamports)) => {
+                    self.wallet.lamports = lamports;
+                    self.balance_error = None;
+                    if self.announce_balance {
+                        let balance = self.amounts.sol(lamports);
+                        self.toasts
+                            .success(format!("Balance refreshed: {}", balance));
+                    }
+                }
+                Some(Err(e)) => {
+                    self.balance_error = Some(Failure::new(&e).retry(Retry::RefreshBalance));
+                }
+            }
+        }
+
+        if let Some(task) = self.dashboard.task.take() {
+            match task.poll() {
+                None => self.dashboard.task = Some(task),
+                Some(Ok(balances)) => {
+                    self.dashboard.balances = Some(balances);
+                    self.dashboard.error = None;
+                }
+                Some(Err(e)) => {
+                    self.dashboard.error = Some(Failure::new(&e).retry(Retry::Dashboard));
+                }
+            }
+        }
+
+        if let Some(task) = self.watch.task.take() {
+            match task.poll() {
+                None => self.watch.task = Some(task),
+                Some(Ok(watched)) => {
+                    let alerts = watch::alerts(&self.config.watch, &self.watch.watched, &watched);
+                    self.watch.watched = watched;
+                    self.watch.error = None;
+                    for alert in alerts {
+                        self.deliver_alert(&alert);
+                    }
+                }
+                Some(Err(e)) => self.watch.error = Some(Failure::new(&e).retry(Retry::Watch)),
+            }
+        }
+
+        if let Some(task) = self.receive.task.take() {
+            match task.poll() {
+                None => self.receive.task = Some(task),
+                Some(Ok(paid)) => {
+                    self.receive.error = None;
+                    let request = self.receive.request.clone();
+                    if let (Some(signature), Some(request)) = (paid, request) {
+                        self.record_payment(&request, &signature);
+                    }
+                }
+                // Looked for again at the next check
+                Some(Err(e)) => self.receive.error = Some(Failure::new(&e)),
+            }
+        }
+
+        if let Some(task) = self.requests.task.take() {
+            match task.poll() {
+                None => self.requests.task = Some(task),
+                Some(Ok(paid)) => {
+                    self.requests.error = None;
+                    for (reference, signature) in paid {
+                        let request = self
+                            .requests
+                            .requests
+                            .iter()
+                            .find(|saved| saved.request.reference == reference)
+                            .map(|saved| saved.request.clone());
+                        if let Some(request) = request {
+                            self.record_payment(&request, &signature);
+                        }
+                    }
+                }
+                Some(Err(e)) => {
+                    self.requests.error = Some(Failure::new(&e).retry(Retry::Requests));
+                }
+            }
+        }
+
+        if let Some(task) = self.cluster_task.take() {
+            match task.poll() {
+                None => self.cluster_task = Some(task),
+                Some(Ok(hash)) => self.cluster_id = Some(hash.to_string()),
+                // Asked again when it's needed
+                Some(Err(_)) => {}
+            }
+        }
+
+        if let Some(fetch) = self.history_task.take() {
+            match fetch.task.poll() {
+                None => self.history_task = Some(fetch),
+                Some(result) => self.finish_history_update(result, fetch.retry),
+            }
+        }
+
+        for plugin in &mut self.plugins {
+            plugin.poll();
+        }
+
+        if let Some(send) = self.send_task.take() {
+            match send.task.poll() {
+                None => self.send_task = Some(send),
+                Some(Ok(landed)) => self.finish_send(landed, &send),
+                Some(Err(e)) => {
+                    if !e.is::<Expired>() {
+                        if let Some((signature, _)) = send.tracker.get() {
+                            self.record_resend(&send, &signature);
+                        }
+                    }
+                    let failure = Failure::new(&e);
+                    // Neither was sent, so sending again can't pay twice
+                    self.send_state.error = Some(if e.is::<NoBlockhash>() || e.is::<Expired>() {
+                        failure.retry(Retry::Send)
+                    } else {
+                        failure
+                    });
+                }
+            }
+        }
+
+        if let Some(send) = self.inspect_task.take() {
+            match send.task.poll() {
+                None => self.inspect_task = Some(send),
+                Some(Ok(landed)) => self.finish_inspected_send(landed, send.lamports),
+                Some(Err(e)) => self.inspect_state.error = Some(Failure::new(&e)),
+            }
+        }
+
+        if let Some((task, action)) = self.nonce_task.take() {
+            match task.poll() {
+                None => self.nonce_task = Some((task, action)),
+                Some(Ok(signature)) => self.finish_nonce_action(signature, action),
+                Some(Err(e)) => {
+                    self.nonce_state.step = NonceStep::List;
+                    self.nonce_state.error = Some(Failure::new(&e));
+                }
+            }
+        }
+    }
+
+    /// Put `text` on the clipboard through the terminal
+    fn copy_to_clipboard(&mut self, text: &str) {
+        match clipboard::copy(text) {
+            Ok(()) => self.toasts.success("Copied to the clipboard"),
+            Err(e) => self.toasts.error(format!("Could not copy: {}", e)),
+        }
+    }
+
+    /// The cluster connected to, by its genesis hash once that's known and
+    /// by the RPC URL until then
+    fn cluster(&self) -> Cluster {
+        self.cluster_id
+            .as_deref()
+            .and_then(Cluster::from_genesis_hash)
+            .unwrap_or_else(|| Cluster::from_rpc_url(&self.rpc_url))
+    }
+
+    /// Open a transaction or address in the configured block explorer
+    fn open_in_explorer(&mut self, target: Target) {
+        let cluster = self.cluster();
+        let url = self.config.explorer.provider.url(target, &cluster);
+        match explorer::open(&url) {
+            Ok(()) => self.toasts.info(format!("Opening {}", url)),
+            Err(e) => self.toasts.error(format!("{:#}", e)),
+        }
+    }
+
+    /// Addresses and signatures that may be on screen, with their explorer
+    /// links
+    fn links(&self) -> Vec<(String, String)> {
+        let mut addresses = vec![self.wallet.address];
+        let mut signatures: Vec<String> = self
+            .last_tx_signature
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        if matches!(self.state, AppState::Transactions) {
+            if let Some(ref inspection) = self.history.inspection {
+                signatures.push(inspection.signature.clone());
+                for instruction in &inspection.instructions {
+                    addresses.push(instruction.program);
+                    addresses.extend(&instruction.accounts);
+                }
+            } else if let Some(entry) = self.history.selected_entry() {
+                signatures.push(entry.signature.clone());
+                if let Some(ref details) = entry.details {
+                    addresses.extend(details.counterparty);
+                    if let TxKind::Program(program) = details.kind {
+                        addresses.push(program);
+                    }
+                }
+            }
+        }
+
+        let cluster = self.cluster();
+        let explorer = self.config.explorer.provider;
+        let addresses = addresses.into_iter().map(|address| {
+            let address = address.to_string();
+            let url = explorer.url(Target::Address(&address), &cluster);
+            (address, url)
+        });
+        let signatures = signatures.into_iter().map(|signature| {
+            let url = explorer.url(Target::Transaction(&signature), &cluster);
+            (signature, url)
+        });
+        addresses.chain(signatures).collect()
+    }
+
+    /// Whether sends go to mainnet. Until the genesis hash is known, a
+    /// custom RPC may well be mainnet, so it's taken to be one and asked
+    /// about again next time rather than cached.
+    fn is_mainnet(&mut self) -> bool {
+        if let Some(mainnet) = self.mainnet {
+            return mainnet;
+        }
+        if self.rpc_url.contains("mainnet") {
+            return *self.mainnet.insert(true);
+        }
+        // The genesis hash may already be on its way from startup
+        match self.cluster_id() {
+            Ok(cluster) => *self.mainnet.insert(cluster == wallet::MAINNET_GENESIS_HASH),
+            Err(_) => true,
+        }
+    }
+}
@@ -0,0 +1,1540 @@
+//! Keys and the mouse, and what they do on each screen
+
+use super::{
+    send::{handle_send_input, SendState},
+    signs,
+    state::{
+        ExportKind, ExportState, ExportStep, ImportState, ImportStep, LookupResult, NonceAction,
+        NonceStep, ReceiveState, Setting, SettingsInput, VanityState, VanityStep, VerifyState,
+        VerifyStep, EXPORT_ACK_WORD,
+    },
+    ui::inspection_lines,
+    App, AppState, READ_ONLY,
+};
+use anyhow::{Context, Result};
+use crossterm::event::{KeyCode, KeyEvent, MouseButton, MouseEvent, MouseEventKind};
+use ratatui::layout::{Margin, Position};
+use solace::{
+    config,
+    explorer::Target,
+    failure::{Failure, Retry},
+    grind::{GrindPattern, Grinder},
+    history::{Clock, ListRow},
+    i18n::{tr, tr_fmt},
+    inspector,
+    keymap::{Action, Key, KeyContext, Lookup},
+    keys::{self, KeypairSource, SecretKeypair},
+    message, paper, qr,
+    report::ReportFormat,
+    shamir,
+};
+use solana_sdk::signature::Signer;
+use std::path::Path;
+use zeroize::Zeroizing;
+
+/// Act on a key press, returning false to quit
+pub fn handle_key(app: &mut App, key: KeyEvent) -> bool {
+    let pending = app.pending_key.take();
+    let pressed = Key::from_event(&key);
+    let action = match app.keymap.lookup(app.key_context(), pending, pressed) {
+        Lookup::Action(action) => Some(action),
+        Lookup::Pending => {
+            app.pending_key = Some(pressed);
+            return true;
+        }
+        Lookup::Unbound => None,
+    };
+    if app.session_lock.is_locked() {
+        return !handle_lock_input(app, key, action);
+    }
+    if app.read_only && action.is_some_and(signs) {
+        app.toasts.error(READ_ONLY);
+        return true;
+    }
+    let limit = app.scroll_limit.get();
+    let overlay = if app.help.is_some() {
+        &mut app.help
+    } else if app.log_view.is_some() {
+        &mut app.log_view
+    } else {
+        &mut app.audit_view
+    };
+    if let Some(scroll) = *overlay {
+        // The log opens at its end, before it's known how far that is
+        let scroll = scroll.min(limit);
+        *overlay = match action {
+            Some(Action::Up) => Some(scroll.saturating_sub(1)),
+            Some(Action::Down) => Some((scroll + 1).min(limit)),
+            Some(Action::First) => Some(0),
+            Some(Action::Last) => Some(limit),
+            Some(Action::PageUp) => Some(scroll.saturating_sub(SCROLL_PAGE)),
+            Some(Action::PageDown) => Some((scroll + SCROLL_PAGE).min(limit)),
+            Some(Action::Close) => None,
+            _ => Some(scroll),
+        };
+        return true;
+    }
+    match action {
+        Some(Action::Quit) => return false,
+        Some(Action::Help) => {
+            app.help = Some(0);
+            return true;
+        }
+        Some(Action::Logs) => {
+            app.log_view = Some(u16::MAX);
+            return true;
+        }
+        Some(Action::AuditLog) => {
+            app.open_audit_log();
+            return true;
+        }
+        Some(Action::ToggleUnit) => {
+            app.amounts.toggle_unit();
+            return true;
+        }
+        Some(Action::Retry) => {
+            app.retry();
+            return true;
+        }
+        Some(Action::PageUp) if app.state != AppState::Transactions => {
+            app.content_scroll = app.content_scroll.saturating_sub(SCROLL_PAGE);
+            return true;
+        }
+        Some(Action::PageDown) if app.state != AppState::Transactions => {
+            app.content_scroll = (app.content_scroll + SCROLL_PAGE).min(app.content_limit.get());
+            return true;
+        }
+        _ => {}
+    }
+
+    if let AppState::Plugin(index) = app.state {
+        let mut handled = false;
+        app.with_plugin(index, |plugin, context| {
+            handled = plugin.handle_key(&key, action, context);
+        });
+        if handled {
+            return true;
+        }
+    }
+
+    // Handle Send state input
+    if matches!(app.state, AppState::Send) && app.send_task.is_some() {
+        // Nothing else to do while the transfer confirms
+        if let Some(Action::Back | Action::Cancel) = action {
+            app.send_task = None;
+            app.send_state.error = Some(Failure::message(tr(
+                "Stopped waiting for confirmation; the transaction may still land, so check the history before sending again",
+            )));
+        }
+        return true;
+    }
+    if matches!(app.state, AppState::Send) {
+        match handle_send_input(app, key, action) {
+            Ok(should_continue) => {
+                if !should_continue {
+                    app.state = AppState::Wallet;
+                    app.send_state = SendState::default();
+                }
+            }
+            Err(e) => {
+                app.send_state.error = Some(Failure::new(&e));
+            }
+        }
+        return true;
+    }
+
+    if app.key_context() == KeyContext::Dashboard {
+        if !handle_dashboard_input(app, action) {
+            app.state = AppState::Wallet;
+        }
+        return true;
+    }
+
+    if matches!(app.state, AppState::Watch) {
+        if !handle_watch_input(app, key, action) {
+            app.state = AppState::Wallet;
+        }
+        return true;
+    }
+
+    if matches!(app.state, AppState::Requests) {
+        if !handle_requests_input(app, action) {
+            app.state = AppState::Wallet;
+        }
+        return true;
+    }
+
+    if matches!(app.state, AppState::Accounts) {
+        if !handle_accounts_input(app, key, action) {
+            app.state = AppState::Wallet;
+        }
+        return true;
+    }
+
+    if matches!(app.state, AppState::Transactions) {
+        if !handle_transactions_input(app, key, action) {
+            app.state = AppState::Wallet;
+        }
+        return true;
+    }
+
+    if matches!(app.state, AppState::Vanity) {
+        if !handle_vanity_input(app, key, action) {
+            app.state = AppState::Wallet;
+            app.vanity_state = VanityState::default();
+        }
+        return true;
+    }
+
+    if matches!(app.state, AppState::SignMessage) {
+        if !handle_sign_input(app, key, action) {
+            app.state = AppState::Wallet;
+        }
+        return true;
+    }
+
+    if matches!(app.state, AppState::VerifyMessage) {
+        if !handle_verify_input(&mut app.verify_state, key, action) {
+            app.state = AppState::Wallet;
+        }
+        return true;
+    }
+
+    if matches!(app.state, AppState::SignIn) {
+        if !handle_sign_in_input(app, key, action) {
+            app.state = AppState::Wallet;
+        }
+        return true;
+    }
+
+    if matches!(app.state, AppState::InspectTx) {
+        if !handle_inspect_input(app, key, action) {
+            app.state = AppState::Wallet;
+        }
+        return true;
+    }
+
+    if matches!(app.state, AppState::Lookup) {
+        match action {
+            Some(Action::Confirm) => app.lookup_account(),
+            Some(Action::Back) => app.state = AppState::Wallet,
+            Some(action @ (Action::NextField | Action::PreviousField | Action::Copy)) => {
+                let lookup = &mut app.lookup_state;
+                let fields = lookup
+                    .result
+                    .as_ref()
+                    .map_or_else(Vec::new, LookupResult::fields);
+                match action {
+                    Action::Copy => {
+                        if let Some(value) = fields.get(lookup.field) {
+                            app.copy_to_clipboard(&value.clone());
+                        }
+                    }
+                    _ => lookup.field = cycle_field(lookup.field, fields.len(), action),
+                }
+            }
+            _ => {
+                if app
+                    .lookup_state
+                    .input
+                    .handle_key(&key, |c| c.is_alphanumeric())
+                {
+                    app.lookup_state.result = None;
+                    app.lookup_state.error = None;
+                }
+            }
+        }
+        return true;
+    }
+
+    if matches!(app.state, AppState::Nonce) {
+        if !handle_nonce_input(app, key, action) {
+            app.state = AppState::Wallet;
+        }
+        return true;
+    }
+
+    if matches!(app.state, AppState::Import) {
+        if !handle_import_input(app, key, action) {
+            app.state = AppState::Settings;
+            app.import_state = ImportState::default();
+        }
+        return true;
+    }
+
+    if matches!(app.state, AppState::Export) {
+        if !handle_export_input(app, key, action) {
+            app.state = AppState::Settings;
+            app.export_state = ExportState::default();
+        }
+        return true;
+    }
+
+    if matches!(app.state, AppState::Settings) && handle_settings_input(app, key, action) {
+        return true;
+    }
+
+    if matches!(app.state, AppState::Receive) && handle_receive_input(app, key, action) {
+        return true;
+    }
+
+    match action {
+        Some(Action::Refresh) if app.balance_task.is_none() => app.refresh_balance(true),
+        Some(Action::Cancel) if app.balance_task.is_some() => {
+            app.balance_task = None;
+            app.toasts.error("Stopped refreshing the balance");
+        }
+        Some(Action::OpenAddress) => {
+            let address = app.wallet.address.to_string();
+            app.open_in_explorer(Target::Address(&address));
+        }
+        Some(Action::OpenLastTransaction) => {
+            if let Some(signature) = app.last_tx_signature {
+                app.open_in_explorer(Target::Transaction(&signature.to_string()));
+            }
+        }
+        Some(Action::RefreshBlocklist) if app.blocklist.has_remote() => {
+            app.refresh_blocklist();
+        }
+        Some(Action::ImportKey) => {
+            app.import_state = ImportState::default();
+            app.state = AppState::Import;
+        }
+        Some(Action::ExportKey) => {
+            app.export_state = ExportState::default();
+            app.state = AppState::Export;
+        }
+        Some(Action::Back) => app.state = AppState::Wallet,
+        Some(Action::Up) if app.selected_menu_item > 0 => {
+            app.selected_menu_item -= 1;
+        }
+        Some(Action::Down) if app.selected_menu_item < app.menu().len() - 1 => {
+            app.selected_menu_item += 1;
+        }
+        Some(Action::First) => app.selected_menu_item = 0,
+        Some(Action::Last) => app.selected_menu_item = app.menu().len() - 1,
+        Some(Action::Select) => app.open_selected_menu_item(),
+        _ => {}
+    }
+    true
+}
+
+/// Handle a key on the lock screen, returning whether to quit
+fn handle_lock_input(app: &mut App, key: KeyEvent, action: Option<Action>) -> bool {
+    match (action, key.code) {
+        (Some(Action::Quit), _) => return true,
+        (_, KeyCode::Char(c)) => app.session_lock.pin_input.push(c),
+        (_, KeyCode::Backspace) => {
+            app.session_lock.pin_input.pop();
+        }
+        (Some(Action::Confirm), _) => app.unlock(),
+        _ => {}
+    }
+    false
+}
+
+/// Handle the keys that save the receive QR code and ask for payments,
+/// returning whether the key was one of them
+fn handle_receive_input(app: &mut App, key: KeyEvent, action: Option<Action>) -> bool {
+    let receive = &mut app.receive;
+    if receive.fullscreen {
+        if matches!(action, Some(Action::Back | Action::FullscreenQr)) {
+            receive.fullscreen = false;
+        }
+        return true;
+    }
+    if let Some(ref mut path) = receive.save_path {
+        match (action, key.code) {
+            (Some(Action::Confirm), _) if !path.is_empty() => {
+                let path = config::expand_tilde(Path::new(path));
+                receive.save_path = None;
+                match qr::write_to_file(&receive.payload(&app.wallet.address), &path) {
+                    Ok(()) => app
+                        .toasts
+                        .success(format!("Saved the QR code to {}", path.display())),
+                    Err(e) => app.toasts.error(format!("{:#}", e)),
+                }
+            }
+            (Some(Action::Cancel), _) => receive.save_path = None,
+            (_, KeyCode::Char(c)) => path.push(c),
+            (_, KeyCode::Backspace) => {
+                path.pop();
+            }
+            _ => {}
+        }
+        return true;
+    }
+    if let Some(ref mut amount) = receive.amount {
+        match (action, key.code) {
+            (Some(Action::Confirm), _) => {
+                let amount = std::mem::take(amount);
+                receive.amount = None;
+                if let Err(e) = app.request_payment(&amount) {
+                    app.receive.error = Some(Failure::new(&e));
+                }
+            }
+            (Some(Action::Cancel), _) => receive.amount = None,
+            (_, KeyCode::Char(c)) if c.is_ascii_digit() || c == '.' || c == '_' => {
+                amount.push(c);
+            }
+            (_, KeyCode::Backspace) => {
+                amount.pop();
+            }
+            _ => {}
+        }
+        return true;
+    }
+
+    match action {
+        Some(Action::FullscreenQr) => receive.fullscreen = true,
+        Some(Action::SaveQr) => {
+            let file = match app.wallet_name() {
+                Some(name) => format!("solace-qr-{}.png", file_name_part(name)),
+                None => "solace-qr.png".to_string(),
+            };
+            app.receive.save_path = Some(
+                dirs::home_dir()
+                    .map(|home| home.join(file).display().to_string())
+                    .unwrap_or_default(),
+            );
+        }
+        Some(Action::RequestPayment) => {
+            receive.error = None;
+            receive.amount = Some(String::new());
+        }
+        // Back to the address before leaving the screen
+        Some(Action::Back) if receive.request.is_some() => app.receive = ReceiveState::default(),
+        _ => return false,
+    }
+    true
+}
+
+/// Handle the keys that pick and change settings, returning whether the key
+/// was one of them
+fn handle_settings_input(app: &mut App, key: KeyEvent, action: Option<Action>) -> bool {
+    let settings = &mut app.settings;
+    if let Some((input, ref mut text)) = settings.input {
+        match (action, key.code) {
+            (Some(Action::Confirm), _) => {
+                let text = std::mem::take(text);
+                settings.input = None;
+                let result = match input {
+                    SettingsInput::Value => {
+                        let setting = Setting::ALL[settings.selected];
+                        app.change_setting(setting, &text)
+                    }
+                    SettingsInput::Keypair => app.switch_keypair(KeypairSource::parse(text.trim())),
+                    SettingsInput::Name => app.name_wallet(&text),
+                };
+                app.settings.error = result.err().map(|e| Failure::new(&e));
+            }
+            (Some(Action::Cancel), _) => settings.input = None,
+            (_, KeyCode::Char(c)) => text.push(c),
+            (_, KeyCode::Backspace) => {
+                text.pop();
+            }
+            _ => {}
+        }
+        return true;
+    }
+
+    match action {
+        Some(Action::Up) => settings.selected = settings.selected.saturating_sub(1),
+        Some(Action::Down) => {
+            settings.selected = (settings.selected + 1).min(Setting::ALL.len() - 1);
+        }
+        Some(Action::First) => settings.selected = 0,
+        Some(Action::Last) => settings.selected = Setting::ALL.len() - 1,
+        Some(Action::EditSetting) => app.edit_setting(),
+        Some(Action::OpenKeypair) => {
+            settings.error = None;
+            settings.input = Some((SettingsInput::Keypair, String::new()));
+        }
+        Some(Action::NameWallet) => {
+            let name = app.wallet_name().unwrap_or_default().to_string();
+            app.settings.error = None;
+            app.settings.input = Some((SettingsInput::Name, name));
+        }
+        _ => return false,
+    }
+    true
+}
+
+/// Handle a key on the dashboard, returning whether to stay on it
+fn handle_dashboard_input(app: &mut App, action: Option<Action>) -> bool {
+    let dashboard = &mut app.dashboard;
+    let last = dashboard.wallets.len().saturating_sub(1);
+    match action {
+        Some(Action::Up) => dashboard.selected = dashboard.selected.saturating_sub(1),
+        Some(Action::Down) => dashboard.selected = (dashboard.selected + 1).min(last),
+        Some(Action::First) => dashboard.selected = 0,
+        Some(Action::Last) => dashboard.selected = last,
+        Some(Action::Refresh) if dashboard.task.is_none() => {
+            dashboard.error = None;
+            app.refresh_dashboard();
+        }
+        Some(Action::OpenWallet) => {
+            if let Err(e) = app.open_dashboard_wallet() {
+                app.dashboard.error = Some(Failure::new(&e));
+            }
+        }
+        Some(Action::Back) => return false,
+        _ => {}
+    }
+    true
+}
+
+/// Handle a key on the Watch screen, returning whether to stay on it
+fn handle_watch_input(app: &mut App, key: KeyEvent, action: Option<Action>) -> bool {
+    let watch = &mut app.watch;
+    if let Some(ref mut text) = watch.input {
+        match (action, key.code) {
+            (Some(Action::Confirm), _) => {
+                let text = std::mem::take(text);
+                watch.input = None;
+                app.watch.error = app.add_watched(&text).err().map(|e| Failure::new(&e));
+            }
+            (Some(Action::Cancel), _) => watch.input = None,
+            (_, KeyCode::Char(c)) => text.push(c),
+            (_, KeyCode::Backspace) => {
+                text.pop();
+            }
+            _ => {}
+        }
+        return true;
+    }
+
+    let last = watch.addresses.len().saturating_sub(1);
+    match action {
+        Some(Action::Up) => watch.selected = watch.selected.saturating_sub(1),
+        Some(Action::Down) => watch.selected = (watch.selected + 1).min(last),
+        Some(Action::First) => watch.selected = 0,
+        Some(Action::Last) => watch.selected = last,
+        Some(Action::AddWatch) => {
+            watch.error = None;
+            watch.input = Some(String::new());
+        }
+        Some(Action::RemoveWatch) => {
+            app.watch.error = app.remove_watched().err().map(|e| Failure::new(&e));
+        }
+        Some(Action::OpenAddress) => {
+            if let Some(address) = watch.addresses.get(watch.selected) {
+                let address = address.to_string();
+                app.open_in_explorer(Target::Address(&address));
+            }
+        }
+        Some(Action::Refresh) if watch.task.is_none() => {
+            watch.error = None;
+            app.refresh_watched();
+        }
+        Some(Action::Back) => return false,
+        _ => {}
+    }
+    true
+}
+
+/// Handle a key on the Requests screen, returning whether to stay on it
+fn handle_requests_input(app: &mut App, action: Option<Action>) -> bool {
+    let requests = &mut app.requests;
+    let last = requests.requests.len().saturating_sub(1);
+    match action {
+        Some(Action::Up) => requests.selected = requests.selected.saturating_sub(1),
+        Some(Action::Down) => requests.selected = (requests.selected + 1).min(last),
+        Some(Action::First) => requests.selected = 0,
+        Some(Action::Last) => requests.selected = last,
+        Some(Action::ShowRequest) => {
+            if let Some(saved) = requests.requests.get(requests.selected) {
+                app.receive = ReceiveState {
+                    request: Some(saved.request.clone()),
+                    paid: saved.signature.clone(),
+                    ..ReceiveState::default()
+                };
+                app.state = AppState::Receive;
+            }
+        }
+        Some(Action::Open) => {
+            let signature = requests
+                .requests
+                .get(requests.selected)
+                .and_then(|saved| saved.signature.clone());
+            if let Some(signature) = signature {
+                app.open_in_explorer(Target::Transaction(&signature));
+            }
+        }
+        Some(Action::Refresh) if requests.task.is_none() => {
+            requests.error = None;
+            app.refresh_requests();
+        }
+        Some(Action::Back) => return false,
+        _ => {}
+    }
+    true
+}
+
+/// Handle a key on the accounts screen, returning whether to stay on it
+fn handle_accounts_input(app: &mut App, key: KeyEvent, action: Option<Action>) -> bool {
+    let accounts = &mut app.accounts_state;
+    if let Some(ref mut query) = accounts.search {
+        match (action, key.code) {
+            (Some(Action::Confirm | Action::Cancel), _) => accounts.search = None,
+            (_, KeyCode::Char(c)) => query.push(c),
+            (_, KeyCode::Backspace) => {
+                query.pop();
+            }
+            _ => {}
+        }
+        accounts.select_match();
+        return true;
+    }
+
+    match action {
+        Some(Action::Up) => accounts.selected = accounts.selected.saturating_sub(1),
+        Some(Action::Down) if accounts.selected + 1 < accounts.accounts.len() => {
+            accounts.selected += 1;
+        }
+        Some(Action::First) => accounts.selected = 0,
+        Some(Action::Last) => accounts.selected = accounts.accounts.len().saturating_sub(1),
+        Some(Action::Search) => accounts.search = Some(String::new()),
+        Some(Action::Refresh) => {
+            app.accounts_state.error = app
+                .load_derived_accounts()
+                .err()
+                .map(|e| Failure::new(&e).retry(Retry::LoadAccounts));
+        }
+        Some(Action::SwitchAccount) => {
+            app.accounts_state.error = app
+                .switch_to_selected_account()
+                .err()
+                .map(|e| Failure::new(&e));
+        }
+        Some(Action::Back) => return false,
+        _ => {}
+    }
+    true
+}
+
+/// Handle a key on the transactions screen, returning whether to stay on it
+fn handle_transactions_input(app: &mut App, key: KeyEvent, action: Option<Action>) -> bool {
+    if let Some(ref mut note) = app.history.editing_note {
+        match (action, key.code) {
+            (Some(Action::Confirm), _) => {
+                let note = note.trim().to_string();
+                app.history.editing_note = None;
+                if let Err(e) = app.save_history_note(&note) {
+                    app.history.error = Some(Failure::new(&e));
+                }
+            }
+            (Some(Action::Cancel), _) => app.history.editing_note = None,
+            (_, KeyCode::Char(c)) => note.push(c),
+            (_, KeyCode::Backspace) => {
+                note.pop();
+            }
+            _ => {}
+        }
+        return true;
+    }
+
+    if let Some(ref mut path) = app.history.export_path {
+        match (action, key.code) {
+            (Some(Action::CycleFormat), _) => {
+                // By extension, then each format in turn
+                app.history.export_format = match app.history.export_format {
+                    None => Some(ReportFormat::ALL[0]),
+                    Some(format) => ReportFormat::ALL
+                        .iter()
+                        .position(|f| *f == format)
+                        .and_then(|i| ReportFormat::ALL.get(i + 1))
+                        .copied(),
+                };
+            }
+            (Some(Action::Confirm), _) if !path.is_empty() => {
+                let path = config::expand_tilde(Path::new(path));
+                let format = app
+                    .history
+                    .export_format
+                    .unwrap_or_else(|| ReportFormat::from_path(&path));
+                app.history.export_path = None;
+                match app.export_history(&path, format) {
+                    Ok((count, 0)) => app.toasts.success(format!(
+                        "Exported {} transactions to {}",
+                        count,
+                        path.display()
+                    )),
+                    Ok((count, rewards)) => app.toasts.success(format!(
+                        "Exported {} transactions and {} staking rewards to {}",
+                        count,
+                        rewards,
+                        path.display()
+                    )),
+                    Err(e) => app.toasts.error(format!("{:#}", e)),
+                }
+            }
+            (Some(Action::Cancel), _) => app.history.export_path = None,
+            (_, KeyCode::Char(c)) => path.push(c),
+            (_, KeyCode::Backspace) => {
+                path.pop();
+            }
+            _ => {}
+        }
+        return true;
+    }
+
+    if let Some(action @ (Action::NextField | Action::PreviousField | Action::Copy)) = action {
+        if app.history.inspection.is_some() {
+            handle_inspection_field(app, action);
+            return true;
+        }
+    }
+
+    if let Some(ref mut inspection) = app.history.inspection {
+        match action {
+            Some(Action::Up) => inspection.scroll = inspection.scroll.saturating_sub(1),
+            Some(Action::Down) => {
+                inspection.scroll = (inspection.scroll + 1).min(app.scroll_limit.get());
+            }
+            Some(Action::First) => inspection.scroll = 0,
+            Some(Action::Last) => inspection.scroll = app.scroll_limit.get(),
+            Some(Action::PageUp) => {
+                inspection.scroll = inspection.scroll.saturating_sub(SCROLL_PAGE);
+            }
+            Some(Action::PageDown) => {
+                inspection.scroll = (inspection.scroll + SCROLL_PAGE).min(app.scroll_limit.get());
+            }
+            Some(Action::Open) => {
+                let signature = inspection.signature.clone();
+                app.open_in_explorer(Target::Transaction(&signature));
+            }
+            Some(Action::Close) => app.history.inspection = None,
+            _ => {}
+        }
+        return true;
+    }
+
+    if app.history.searching {
+        match (action, key.code) {
+            (Some(Action::Confirm), _) => app.history.searching = false,
+            (Some(Action::Cancel), _) => {
+                app.history.searching = false;
+                app.history.search.clear();
+            }
+            (_, KeyCode::Char(c)) => app.history.search.push(c),
+            (_, KeyCode::Backspace) => {
+                app.history.search.pop();
+            }
+            _ => {}
+        }
+        app.history.selected = 0;
+        return true;
+    }
+
+    match action {
+        Some(Action::Search) => {
+            app.history.searching = true;
+            app.history.search.clear();
+        }
+        Some(Action::Up) => app.history.select_previous(),
+        Some(Action::PageUp) => {
+            app.history.selected = app.history.selected.saturating_sub(SCROLL_PAGE.into());
+        }
+        Some(Action::First) => app.history.selected = 0,
+        Some(action @ (Action::Down | Action::PageDown | Action::Last)) => {
+            match action {
+                Action::Last => app.history.select_last(),
+                Action::PageDown => {
+                    for _ in 0..SCROLL_PAGE {
+                        app.history.select_next();
+                    }
+                }
+                _ => app.history.select_next(),
+            }
+            // Only fetch older pages once the user scrolls near the bottom
+            if app.history.wants_more() && app.history.error.is_none() {
+                app.start_history_page();
+            }
+        }
+        Some(Action::Details) => app.inspect_selected_transaction(),
+        Some(Action::Refresh) if app.history_task.is_none() => app.reload_history(),
+        Some(Action::Open) => {
+            if let Some(entry) = app.history.selected_entry() {
+                let signature = entry.signature.clone();
+                app.open_in_explorer(Target::Transaction(&signature));
+            }
+        }
+        Some(Action::Note) => {
+            if let Some(entry) = app.history.selected_entry() {
+                app.history.editing_note = Some(entry.note.clone().unwrap_or_default());
+            }
+        }
+        Some(Action::Resend) => {
+            if let Err(e) = app.resend_selected_transaction() {
+                app.toasts.error(format!("{:#}", e));
+            }
+        }
+        Some(Action::ExportHistory) => {
+            let file = match app.wallet_name() {
+                Some(name) => format!("solace-history-{}.csv", file_name_part(name)),
+                None => "solace-history.csv".to_string(),
+            };
+            app.history.export_path = Some(
+                dirs::home_dir()
+                    .map(|home| home.join(file).display().to_string())
+                    .unwrap_or_default(),
+            );
+        }
+        Some(Action::Filter(filter)) => {
+            app.history.toggle_filter(filter);
+            // A narrow filter may hide everything loaded so far; pull in a
+            // few more pages so there's something to show
+            app.history_prefetch = HISTORY_FILTER_PREFETCH_PAGES;
+            app.prefetch_history();
+        }
+        Some(Action::ClearFilters) => {
+            app.history.filters.clear();
+            app.history.selected = 0;
+        }
+        Some(Action::Back) if app.history_task.is_some() => {
+            app.history_task = None;
+            app.history_prefetch = 0;
+            app.toasts.error("Stopped loading transactions");
+        }
+        Some(Action::Back) => return false,
+        _ => {}
+    }
+    true
+}
+
+/// Lines moved by one notch of the mouse wheel
+const WHEEL_LINES: u16 = 3;
+
+/// Clicks pick menu items and list rows, and the wheel scrolls the open view
+pub fn handle_mouse(app: &mut App, mouse: MouseEvent) {
+    let position = Position::new(mouse.column, mouse.row);
+    match mouse.kind {
+        MouseEventKind::Down(MouseButton::Left) => click(app, position),
+        MouseEventKind::ScrollUp => wheel(app, false),
+        MouseEventKind::ScrollDown => wheel(app, true),
+        _ => {}
+    }
+}
+
+fn click(app: &mut App, position: Position) {
+    let context = app.key_context();
+    // Only where the menu keys work, so a click can't abandon a form
+    let menu_clickable = matches!(
+        context,
+        KeyContext::Home
+            | KeyContext::Dashboard
+            | KeyContext::Wallet
+            | KeyContext::Receive
+            | KeyContext::Settings
+            | KeyContext::Accounts
+            | KeyContext::Watch
+            | KeyContext::Requests
+            | KeyContext::Nonces
+            | KeyContext::Transactions
+            | KeyContext::Inspection
+            | KeyContext::Plugin
+    );
+    let menu = app.menu_area.get();
+    if menu_clickable && menu.contains(position) {
+        let item = usize::from(position.y.saturating_sub(menu.y + 1));
+        if position.y > menu.y && item < app.menu().len() {
+            app.selected_menu_item = item;
+            app.open_selected_menu_item();
+        }
+        return;
+    }
+
+    let area = app.content_area.get();
+    let inner = area.inner(Margin {
+        vertical: 1,
+        horizontal: 1,
+    });
+    if !inner.contains(position) {
+        return;
+    }
+    let line = usize::from(position.y - inner.y);
+    match context {
+        // A click selects a transaction, and a click on the selected one
+        // opens its details
+        KeyContext::Transactions => {
+            let rows = history_rows(app, area.height);
+            let list = app.history.list(&Clock::system());
+            let offset = app.history.scroll_offset(&list, rows);
+            let Some(row) = line.checked_sub(HISTORY_LIST_TOP).filter(|&row| row < rows) else {
+                return;
+            };
+            // Day headers aren't selectable
+            let Some(&ListRow::Entry(index)) = list.get(offset + row) else {
+                return;
+            };
+            if index == app.history.selected {
+                app.inspect_selected_transaction();
+            } else {
+                app.history.selected = index;
+            }
+        }
+        KeyContext::Accounts => {
+            let scroll = app.content_scroll.min(app.content_limit.get());
+            let index = (line + usize::from(scroll)).checked_sub(ACCOUNTS_LIST_TOP);
+            if let Some(index) = index.filter(|&i| i < app.accounts_state.accounts.len()) {
+                app.accounts_state.selected = index;
+            }
+        }
+        KeyContext::Dashboard => {
+            let scroll = app.content_scroll.min(app.content_limit.get());
+            let index = (line + usize::from(scroll)).checked_sub(DASHBOARD_LIST_TOP);
+            if let Some(index) = index.filter(|&i| i < app.dashboard.wallets.len()) {
+                app.dashboard.selected = index;
+            }
+        }
+        KeyContext::Watch => {
+            let scroll = app.content_scroll.min(app.content_limit.get());
+            let index = (line + usize::from(scroll)).checked_sub(WATCH_LIST_TOP);
+            if let Some(index) = index.filter(|&i| i < app.watch.addresses.len()) {
+                app.watch.selected = index;
+            }
+        }
+        KeyContext::Requests => {
+            let scroll = app.content_scroll.min(app.content_limit.get());
+            let index = (line + usize::from(scroll)).checked_sub(REQUESTS_LIST_TOP);
+            if let Some(index) = index.filter(|&i| i < app.requests.requests.len()) {
+                app.requests.selected = index;
+            }
+        }
+        KeyContext::Settings => {
+            let scroll = app.content_scroll.min(app.content_limit.get());
+            let index = (line + usize::from(scroll)).checked_sub(SETTINGS_LIST_TOP);
+            if let Some(index) = index.filter(|&i| i < Setting::ALL.len()) {
+                app.settings.selected = index;
+            }
+        }
+        KeyContext::Nonces => {
+            let scroll = app.content_scroll.min(app.content_limit.get());
+            let index = (line + usize::from(scroll)).checked_sub(NONCE_LIST_TOP);
+            if let Some(index) = index.filter(|&i| i < app.nonce_state.accounts.len()) {
+                app.nonce_state.selected = index;
+            }
+        }
+        _ => {}
+    }
+}
+
+fn wheel(app: &mut App, down: bool) {
+    let scroll = |offset: u16, limit: u16| {
+        if down {
+            (offset + WHEEL_LINES).min(limit)
+        } else {
+            offset.saturating_sub(WHEEL_LINES)
+        }
+    };
+    if let Some(ref mut offset) = app.help {
+        *offset = scroll(*offset, app.scroll_limit.get());
+        return;
+    }
+    if let Some(ref mut offset) = app.log_view.as_mut().or(app.audit_view.as_mut()) {
+        let limit = app.scroll_limit.get();
+        **offset = scroll((**offset).min(limit), limit);
+        return;
+    }
+    if app.state != AppState::Transactions {
+        app.content_scroll = scroll(app.content_scroll, app.content_limit.get());
+        return;
+    }
+    if let Some(ref mut inspection) = app.history.inspection {
+        inspection.scroll = scroll(inspection.scroll, app.scroll_limit.get());
+        return;
+    }
+    for _ in 0..WHEEL_LINES {
+        if down {
+            app.history.select_next();
+        } else {
+            app.history.select_previous();
+        }
+    }
+    if down && app.history.wants_more() && app.history.error.is_none() {
+        app.start_history_page();
+    }
+}
+
+/// Transaction rows that fit in a pane `height` tall. Borders, title,
+/// filters, the detail/footer block and the error panel (or a line kept for
+/// it) take the rest.
+pub(super) fn history_rows(app: &App, height: u16) -> usize {
+    let error = app.history.error.as_ref().map_or(1, |failure| {
+        failure.lines(&app.theme, app.retry_key()).len()
+    });
+    usize::from(height).saturating_sub(12 + error)
+}
+
+/// Select the next or previous of a transaction's fields, scrolling it into
+/// view, or copy the selected one
+fn handle_inspection_field(app: &mut App, action: Action) {
+    let Some(ref inspection) = app.history.inspection else {
+        return;
+    };
+    let entry = app.history.entry(&inspection.signature);
+    let (_, fields) = inspection_lines(inspection, entry, &app.theme);
+    if action == Action::Copy {
+        if let Some((_, value)) = fields.get(inspection.field) {
+            app.copy_to_clipboard(&value.clone());
+        }
+        return;
+    }
+
+    let field = cycle_field(inspection.field, fields.len(), action);
+    let Some(&(line, _)) = fields.get(field) else {
+        return;
+    };
+    let rows = usize::from(app.content_area.get().height.saturating_sub(2)).max(1);
+    let limit = app.scroll_limit.get();
+    let Some(ref mut inspection) = app.history.inspection else {
+        return;
+    };
+    let top = usize::from(inspection.scroll);
+    let top = if line < top {
+        line
+    } else if line >= top + rows {
+        line + 1 - rows
+    } else {
+        top
+    };
+    inspection.field = field;
+    inspection.scroll = u16::try_from(top).unwrap_or(u16::MAX).min(limit);
+}
+
+/// The field after or before `field` of `count`, wrapping around
+fn cycle_field(field: usize, count: usize, action: Action) -> usize {
+    match (action, count) {
+        (_, 0) => 0,
+        (Action::PreviousField, _) => (field + count - 1) % count,
+        _ => (field + 1) % count,
+    }
+}
+
+/// Lines above the first transaction row: title, blank line and filters
+const HISTORY_LIST_TOP: usize = 3;
+
+/// Lines above the first account: title and blank line
+const ACCOUNTS_LIST_TOP: usize = 2;
+
+/// Lines above the first dashboard wallet: title and blank line
+const DASHBOARD_LIST_TOP: usize = 2;
+
+/// Lines above the first watched address: title and blank line
+const WATCH_LIST_TOP: usize = 2;
+
+/// Lines above the first payment request: title and blank line
+const REQUESTS_LIST_TOP: usize = 2;
+
+/// Lines above the first setting: title and blank line
+const SETTINGS_LIST_TOP: usize = 2;
+
+/// Lines above the first nonce account: title and blank line
+const NONCE_LIST_TOP: usize = 2;
+
+/// Lines moved by PageUp and PageDown
+const SCROLL_PAGE: u16 = 10;
+
+/// Pages to fetch at most when a filter is toggled
+const HISTORY_FILTER_PREFETCH_PAGES: usize = 4;
+
+/// Handle a key on the vanity address screen, returning whether to stay on it
+fn handle_vanity_input(app: &mut App, key: KeyEvent, action: Option<Action>) -> bool {
+    let vanity = &mut app.vanity_state;
+    match vanity.step {
+        VanityStep::EnteringPrefix | VanityStep::EnteringSuffix => {
+            let input = if vanity.step == VanityStep::EnteringPrefix {
+                &mut vanity.prefix
+            } else {
+                &mut vanity.suffix
+            };
+            match (action, key.code) {
+                (Some(Action::ToggleCase), _) => vanity.ignore_case = !vanity.ignore_case,
+                (_, KeyCode::Char(c)) => input.push(c),
+                (_, KeyCode::Backspace) => {
+                    input.pop();
+                }
+                (Some(Action::Confirm), _) if vanity.step == VanityStep::EnteringPrefix => {
+                    vanity.step = VanityStep::EnteringSuffix;
+                }
+                (Some(Action::Confirm), _) => {
+                    match GrindPattern::new(&vanity.prefix, &vanity.suffix, vanity.ignore_case) {
+                        Ok(pattern) => {
+                            vanity.grinder = Some(Grinder::start(pattern));
+                            vanity.step = VanityStep::Searching;
+                            vanity.error = None;
+                        }
+                        Err(e) => vanity.error = Some(Failure::new(&e)),
+                    }
+                }
+                (Some(Action::Back), _) if vanity.step == VanityStep::EnteringSuffix => {
+                    vanity.step = VanityStep::EnteringPrefix;
+                }
+                (Some(Action::Back), _) => return false,
+                _ => {}
+            }
+        }
+        VanityStep::Searching => {
+            if action == Some(Action::Back) {
+                // Dropping the grinder stops the search
+                *vanity = VanityState::default();
+            }
+        }
+        VanityStep::Saving => match (action, key.code) {
+            (_, KeyCode::Char(c)) => vanity.path_input.push(c),
+            (_, KeyCode::Backspace) => {
+                vanity.path_input.pop();
+            }
+            (Some(Action::Confirm), _) if !vanity.path_input.is_empty() => {
+                if let Some(ref keypair) = vanity.keypair {
+                    let path = config::expand_tilde(std::path::Path::new(&vanity.path_input));
+                    match keypair.write_to_file(&path) {
+                        Ok(()) => {
+                            vanity.status =
+                                Some(format!("Saved {} to {}", keypair.pubkey(), path.display()));
+                            vanity.error = None;
+                        }
+                        Err(e) => vanity.error = Some(Failure::new(&e)),
+                    }
+                }
+            }
+            (Some(Action::Back), _) => return false,
+            _ => {}
+        },
+    }
+    true
+}
+
+/// Handle a key on the import screen, returning whether to stay on it
+/// Handle a key on the Sign screen, returning false to leave it
+fn handle_sign_input(app: &mut App, key: KeyEvent, action: Option<Action>) -> bool {
+    let sign = &mut app.sign_state;
+    match action {
+        Some(Action::Confirm) => {
+            let signed = app
+                .wallet
+                .keypair
+                .as_deref()
+                .context("Wallet is locked")
+                .and_then(|keypair| message::sign(keypair, sign.message.as_bytes()));
+            match signed {
+                Ok(signature) => {
+                    sign.signature = Some(signature);
+                    sign.error = None;
+                }
+                Err(e) => sign.error = Some(Failure::new(&e)),
+            }
+        }
+        Some(Action::Back) => return false,
+        _ => {
+            if sign.message.handle_key(&key, |_| true) {
+                sign.signature = None;
+            }
+        }
+    }
+    true
+}
+
+/// Handle a key on the Verify screen, returning false to leave it
+fn handle_verify_input(verify: &mut VerifyState, key: KeyEvent, action: Option<Action>) -> bool {
+    match (verify.step, action) {
+        (VerifyStep::Message, Some(Action::Confirm)) => {
+            verify.step = VerifyStep::Address;
+        }
+        (VerifyStep::Address, Some(Action::Confirm)) => {
+            verify.step = VerifyStep::Signature;
+        }
+        (VerifyStep::Signature, Some(Action::Confirm)) => match verify.check() {
+            Ok(address) => {
+                verify.verified = Some(address);
+                verify.error = None;
+            }
+            Err(e) => {
+                verify.verified = None;
+                verify.error = Some(Failure::new(&e));
+            }
+        },
+        (VerifyStep::Message, Some(Action::Back)) => return false,
+        (VerifyStep::Address, Some(Action::Back)) => {
+            verify.step = VerifyStep::Message;
+        }
+        (VerifyStep::Signature, Some(Action::Back)) => {
+            verify.step = VerifyStep::Address;
+        }
+        (step, _) => {
+            let field = match step {
+                VerifyStep::Message => &mut verify.message,
+                VerifyStep::Address => &mut verify.address,
+                VerifyStep::Signature => &mut verify.signature,
+            };
+            if field.handle_key(&key, |_| true) {
+                verify.verified = None;
+                verify.error = None;
+            }
+        }
+    }
+    true
+}
+
+/// Handle a key on the Sign In screen, returning false to leave it. Enter
+/// reads the request, then signs it once reviewed.
+fn handle_sign_in_input(app: &mut App, key: KeyEvent, action: Option<Action>) -> bool {
+    let sign_in = &mut app.sign_in_state;
+    match (action, sign_in.request.take()) {
+        (Some(Action::Confirm), None) if sign_in.response.is_none() => {
+            match sign_in.read_request(&app.wallet.address) {
+                Ok(request) => {
+                    sign_in.request = Some(request);
+                    sign_in.error = None;
+                }
+                Err(e) => sign_in.error = Some(Failure::new(&e)),
+            }
+        }
+        (Some(Action::Confirm), Some(request)) => {
+            let signed = app
+                .wallet
+                .keypair
+                .as_deref()
+                .context("Wallet is locked")
+                .and_then(|keypair| request.sign(keypair));
+            match signed {
+                Ok(response) => {
+                    sign_in.response = serde_json::to_string_pretty(&response).ok();
+                    sign_in.error = None;
+                }
+                Err(e) => {
+                    sign_in.request = Some(request);
+                    sign_in.error = Some(Failure::new(&e));
+                }
+            }
+        }
+        (Some(Action::Back), None) if sign_in.response.is_none() => return false,
+        (Some(Action::Back), _) => {
+            // Back to the input, to fix the request or answer another
+            sign_in.response = None;
+            sign_in.error = None;
+        }
+        (_, Some(request)) => sign_in.request = Some(request),
+        (_, None) => {
+            if sign_in.response.is_none() && sign_in.input.handle_key(&key, |_| true) {
+                sign_in.error = None;
+            }
+        }
+    }
+    true
+}
+
+/// Handle a key on the Inspect screen, returning false to leave it. Enter
+/// decodes the transaction, then signs and sends it once reviewed.
+fn handle_inspect_input(app: &mut App, key: KeyEvent, action: Option<Action>) -> bool {
+    if app.inspect_task.is_some() {
+        return true;
+    }
+    let reviewing = app.inspect_state.inspection.is_some();
+    match action {
+        Some(Action::Confirm) if reviewing => {
+            if let Err(e) = app.send_inspected() {
+                app.inspect_state.error = Some(Failure::new(&e));
+            }
+        }
+        Some(Action::Confirm) => {
+            let inspected = app
+                .inspect_state
+                .read_transaction()
+                .and_then(|transaction| {
+                    inspector::inspect(&*app.rpc_client, transaction, &mut app.idls)
+                });
+            match inspected {
+                Ok(inspection) => {
+                    app.inspect_state.inspection = Some(inspection);
+                    app.inspect_state.error = None;
+                }
+                Err(e) => app.inspect_state.error = Some(Failure::new(&e)),
+            }
+        }
+        // Back to the input, to paste another
+        Some(Action::Back) if reviewing => {
+            app.inspect_state.inspection = None;
+            app.inspect_state.partially_signed = None;
+            app.inspect_state.error = None;
+        }
+        Some(Action::Back) => return false,
+        _ => {
+            if !reviewing && app.inspect_state.input.handle_key(&key, |_| true) {
+                app.inspect_state.error = None;
+            }
+        }
+    }
+    true
+}
+
+/// Handle a key on the nonce screen, returning whether to stay on it
+fn handle_nonce_input(app: &mut App, key: KeyEvent, action: Option<Action>) -> bool {
+    if app.nonce_task.is_some() {
+        return true;
+    }
+    let state = &mut app.nonce_state;
+    match state.step {
+        NonceStep::List => match action {
+            Some(Action::Up) => state.selected = state.selected.saturating_sub(1),
+            Some(Action::Down) if state.selected + 1 < state.accounts.len() => {
+                state.selected += 1;
+            }
+            Some(Action::First) => state.selected = 0,
+            Some(Action::Last) => state.selected = state.accounts.len().saturating_sub(1),
+            Some(Action::Refresh) => app.load_nonce_accounts(),
+            Some(Action::CreateNonce) => match app.prepare_nonce_account() {
+                Ok(action) => {
+                    app.nonce_state.step = NonceStep::Confirming(action);
+                    app.nonce_state.error = None;
+                }
+                Err(e) => app.nonce_state.error = Some(Failure::new(&e)),
+            },
+            Some(Action::AdvanceNonce) if state.selected_account().is_some() => {
+                state.step = NonceStep::Confirming(NonceAction::Advance);
+                state.error = None;
+            }
+            Some(Action::WithdrawNonce) => {
+                if let Some(account) = state.selected_account() {
+                    // All of it by default, which closes the account
+                    state.amount.set(app.amounts.input(account.lamports));
+                    state.step = NonceStep::EnteringWithdrawal;
+                    state.error = None;
+                }
+            }
+            Some(Action::FundNonce) => app.fund_selected_nonce_account(),
+            Some(Action::Back) => return false,
+            _ => {}
+        },
+        NonceStep::EnteringWithdrawal => match action {
+            Some(Action::Confirm) => match app.prepare_nonce_withdrawal() {
+                Ok(action) => {
+                    app.nonce_state.step = NonceStep::Confirming(action);
+                    app.nonce_state.error = None;
+                }
+                Err(e) => app.nonce_state.error = Some(Failure::new(&e)),
+            },
+            Some(Action::Back) => {
+                state.step = NonceStep::List;
+                state.error = None;
+            }
+            _ => {
+                state
+                    .amount
+                    .handle_key(&key, |c| c.is_ascii_digit() || c == '.');
+            }
+        },
+        NonceStep::Confirming(nonce_action) => match action {
+            Some(Action::Confirm) => {
+                if let Err(e) = app.start_nonce_action(nonce_action) {
+                    app.nonce_state.error = Some(Failure::new(&e));
+                }
+            }
+            Some(Action::Cancel) => state.step = NonceStep::List,
+            _ => {}
+        },
+    }
+    true
+}
+
+fn handle_import_input(app: &mut App, key: KeyEvent, action: Option<Action>) -> bool {
+    let import = &mut app.import_state;
+    match import.step {
+        ImportStep::EnteringKey => match (action, key.code) {
+            (_, KeyCode::Char(c)) => import.key_input.push(c),
+            (_, KeyCode::Backspace) => {
+                import.key_input.pop();
+            }
+            (Some(Action::Confirm), _) => match SecretKeypair::from_base58(&import.key_input) {
+                Ok(keypair) => {
+                    import.path_input = keys::default_import_path(&keypair.pubkey())
+                        .map(|path| path.display().to_string())
+                        .unwrap_or_default();
+                    import.keypair = Some(keypair);
+                    import.key_input = Zeroizing::new(String::new());
+                    import.step = ImportStep::EnteringPath;
+                    import.error = None;
+                }
+                Err(e) => import.error = Some(Failure::new(&e)),
+            },
+            (Some(Action::Back), _) => return false,
+            _ => {}
+        },
+        ImportStep::EnteringPath => match (action, key.code) {
+            (_, KeyCode::Char(c)) => import.path_input.push(c),
+            (_, KeyCode::Backspace) => {
+                import.path_input.pop();
+            }
+            (Some(Action::Confirm), _) if !import.path_input.is_empty() => {
+                if let Some(ref keypair) = import.keypair {
+                    let path = config::expand_tilde(std::path::Path::new(&import.path_input));
+                    match keypair.write_to_file(&path) {
+                        Ok(()) => {
+                            import.status = Some(tr_fmt(
+                                "Saved {} to {}. Start solace with --keypair {} to use it.",
+                                &[&keypair.pubkey(), &path.display(), &path.display()],
+                            ));
+                            import.error = None;
+                        }
+                        Err(e) => import.error = Some(Failure::new(&e)),
+                    }
+                }
+            }
+            (Some(Action::Back), _) => {
+                *import = ImportState::default();
+            }
+            _ => {}
+        },
+    }
+    true
+}
+/// `name` lowercased, with anything but letters and digits turned into
+/// dashes, to go in a file name
+/// dashes, to go in a file name
+pub(super) fn file_name_part(name: &str) -> String {
+    name.split(|c: char| !c.is_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+        .to_lowercase()
+}
+
+/// Handle a key on the export screen, returning whether to stay on it
+fn handle_export_input(app: &mut App, key: KeyEvent, action: Option<Action>) -> bool {
+    let export = &mut app.export_state;
+    match export.step {
+        ExportStep::Choosing => match action {
+            Some(Action::ExportPrivateKey) => {
+                export.kind = ExportKind::PrivateKey;
+                export.step = ExportStep::Confirming;
+            }
+            Some(Action::ExportSeedPhrase) => {
+                export.kind = ExportKind::SeedPhrase;
+                export.step = ExportStep::Confirming;
+            }
+            Some(Action::ExportPaperWallet) => {
+                export.kind = ExportKind::PaperWallet;
+                export.step = ExportStep::Confirming;
+            }
+            Some(Action::ExportShares) => {
+                export.kind = ExportKind::Shares;
+                export.step = ExportStep::Confirming;
+            }
+            Some(Action::Back) => return false,
+            _ => {}
+        },
+        ExportStep::Confirming => match (action, key.code) {
+            (_, KeyCode::Char(c)) => export.confirm_input.push(c),
+            (_, KeyCode::Backspace) => {
+                export.confirm_input.pop();
+            }
+            (Some(Action::Confirm), _) => {
+                let confirmed = export.confirm_input == EXPORT_ACK_WORD;
+                export.confirm_input.clear();
+                if !confirmed {
+                    export.error = Some(Failure::message(tr_fmt(
+                        "Type {} to continue",
+                        &[&EXPORT_ACK_WORD],
+                    )));
+                } else if let Err(e) = app.reveal_secret() {
+                    app.export_state.error = Some(Failure::new(&e));
+                } else {
+                    app.export_state.error = None;
+                }
+            }
+            (Some(Action::Back), _) => {
+                export.confirm_input.clear();
+                export.step = ExportStep::Choosing;
+            }
+            _ => {}
+        },
+        // Any key hides the secret again
+        ExportStep::Revealed => return false,
+        ExportStep::SavingPaper => match (action, key.code) {
+            (_, KeyCode::Char(c)) => export.path_input.push(c),
+            (_, KeyCode::Backspace) => {
+                export.path_input.pop();
+            }
+            (Some(Action::Confirm), _) if !export.path_input.is_empty() => {
+                let path = config::expand_tilde(std::path::Path::new(&export.path_input));
+                let result = app
+                    .wallet
+                    .keypair
+                    .as_ref()
+                    .context("Wallet is locked")
+                    .and_then(|keypair| {
+                        let name = app.config.wallet_name(&keypair.pubkey());
+                        paper::write_to_file(keypair, name, &path)
+                    });
+                match result {
+                    Ok(()) => {
+                        export.status = Some(tr_fmt(
+                            "Paper wallet saved to {}. Print it, then delete the file.",
+                            &[&path.display()],
+                        ));
+                        export.error = None;
+                    }
+                    Err(e) => export.error = Some(Failure::new(&e)),
+                }
+            }
+            (Some(Action::Back), _) => return false,
+            _ => {}
+        },
+        ExportStep::SavingShares => {
+            let input = if export.editing_shares {
+                &mut export.shares_input
+            } else {
+                &mut export.path_input
+            };
+            match (action, key.code) {
+                (Some(Action::SwitchField), _) => export.editing_shares = !export.editing_shares,
+                (_, KeyCode::Char(c)) => input.push(c),
+                (_, KeyCode::Backspace) => {
+                    input.pop();
+                }
+                (Some(Action::Confirm), _) if !export.path_input.is_empty() => {
+                    let dir = config::expand_tilde(std::path::Path::new(&export.path_input));
+                    let result =
+                        parse_share_counts(&export.shares_input).and_then(|(threshold, count)| {
+                            let keypair =
+                                app.wallet.keypair.as_ref().context("Wallet is locked")?;
+                            let shares = shamir::split(keypair, threshold, count)?;
+                            shamir::write_to_dir(keypair, &shares, &dir)
+                        });
+                    match result {
+                        Ok(paths) => {
+                            export.status = Some(tr_fmt(
+                                "Wrote {} shares to {}. Move each one to a different place.",
+                                &[&paths.len(), &dir.display()],
+                            ));
+                            export.error = None;
+                        }
+                        Err(e) => export.error = Some(Failure::new(&e)),
+                    }
+                }
+                (Some(Action::Back), _) => return false,
+                _ => {}
+            }
+        }
+    }
+    true
+}
+
+/// Parse `<threshold> of <count>` (or `<threshold>/<count>`)
+fn parse_share_counts(input: &str) -> Result<(u8, u8)> {
+    let (threshold, count) = input
+        .split_once(" of ")
+        .or_else(|| input.split_once('/'))
+        .context("Enter the shares as `<needed> of <total>`, e.g. `2 of 3`")?;
+    Ok((
+        threshold
+            .trim()
+            .parse()
+            .context("Invalid number of shares needed")?,
+        count
+            .trim()
+            .parse()
+            .context("Invalid total number of shares")?,
+    ))
+}
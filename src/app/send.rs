@@ -0,0 +1,593 @@
+//! The Send screen: the form, the review before signing and the
+//! transfer as it lands
+
+use super::{state::InspectState, App, AppState};
+use crate::input::TextInput;
+use anyhow::{bail, Context, Result};
+use crossterm::event::KeyEvent;
+use solace::{
+    amount::AmountFormat,
+    config::Config,
+    failure::Failure,
+    i18n::{tr, tr_fmt},
+    inspector::{self, Simulation},
+    keymap::Action,
+    limits::LimitCheck,
+    output::Refused,
+    pay, priority, rent,
+    sender::{Landed, Pipeline, Tracker},
+    task::Task,
+    wallet::{sender_rent_message, transfer, transfer_pipeline, typed_threshold},
+};
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Signature, Signer},
+};
+use std::{str::FromStr, sync::Arc};
+
+#[derive(Debug, Clone)]
+pub(super) struct SendState {
+    pub(super) recipient: TextInput,
+    pub(super) amount: TextInput,
+    pub(super) input_mode: SendInputMode,
+    pub(super) error: Option<Failure>,
+    /// Rent-exempt minimum (in lamports) when the recipient account does not
+    /// exist yet and the entered amount is below it
+    pub(super) rent_exempt_minimum: Option<u64>,
+    /// Why the recipient is blocklisted, if it is
+    pub(super) blocklist_reason: Option<String>,
+    /// Which spending limit the amount exceeds, if any
+    pub(super) limit_warning: Option<String>,
+    /// Text typed to acknowledge a warning before confirming
+    pub(super) confirm_input: TextInput,
+    /// Whether the final confirmation requires retyping instead of a keypress
+    pub(super) typed_confirmation: bool,
+    /// The priority fee suggested from recent fees, in micro-lamports per
+    /// compute unit, or why there isn't one
+    pub(super) suggested_fee: Option<Result<u64, String>>,
+    /// The priority fee entered instead of the suggestion
+    pub(super) fee_override: Option<u64>,
+    pub(super) fee_input: TextInput,
+    /// Whether to add a reference key, if toggled from `[payments]
+    /// reference`
+    pub(super) reference_override: Option<bool>,
+    /// The reference key of a transfer being sent again, kept so the
+    /// recipient still matches it
+    pub(super) reference_key: Option<Pubkey>,
+    /// The failed transaction this sends again, recorded as resent once
+    /// this goes out
+    pub(super) resending: Option<Signature>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum SendInputMode {
+    EditingRecipient,
+    EditingAmount,
+    AcknowledgingBlocklist,
+    ConfirmingLimit,
+    Confirming,
+    EditingPriorityFee,
+}
+
+/// Word that must be typed to send to a blocklisted address
+pub(super) const BLOCKLIST_ACK_WORD: &str = "SEND";
+
+/// Number of leading recipient characters accepted as a typed confirmation
+const TYPED_CONFIRM_PREFIX_LEN: usize = 4;
+
+impl Default for SendState {
+    fn default() -> Self {
+        Self {
+            recipient: TextInput::default(),
+            amount: TextInput::default(),
+            input_mode: SendInputMode::EditingRecipient,
+            error: None,
+            rent_exempt_minimum: None,
+            blocklist_reason: None,
+            limit_warning: None,
+            confirm_input: TextInput::default(),
+            typed_confirmation: false,
+            suggested_fee: None,
+            fee_override: None,
+            fee_input: TextInput::default(),
+            reference_override: None,
+            reference_key: None,
+            resending: None,
+        }
+    }
+}
+
+impl SendState {
+    /// The amount entered, in the unit amounts are shown in
+    pub(super) fn lamports(&self, amounts: &AmountFormat) -> Result<u64> {
+        amounts.parse(&self.amount)
+    }
+
+    /// The priority fee the send will pay, the entered one over the
+    /// suggestion
+    pub(super) fn compute_unit_price(&self) -> Option<u64> {
+        self.fee_override
+            .or_else(|| self.suggested_fee.clone()?.ok())
+    }
+
+    /// Whether the transfer gets a reference key, as toggled or else as
+    /// configured
+    pub(super) fn reference(&self, config: &Config) -> bool {
+        self.reference_override.unwrap_or(config.payments.reference)
+    }
+
+    /// Move past the current step to the next warning that applies, or to the
+    /// final confirmation
+    pub(super) fn advance(&mut self) {
+        use SendInputMode::*;
+
+        let mut next = match self.input_mode {
+            EditingRecipient => EditingAmount,
+            EditingAmount => AcknowledgingBlocklist,
+            AcknowledgingBlocklist => ConfirmingLimit,
+            ConfirmingLimit | Confirming | EditingPriorityFee => Confirming,
+        };
+        if next == AcknowledgingBlocklist && self.blocklist_reason.is_none() {
+            next = ConfirmingLimit;
+        }
+        if next == ConfirmingLimit && self.limit_warning.is_none() {
+            next = Confirming;
+        }
+
+        self.input_mode = next;
+        self.confirm_input.clear();
+        self.error = None;
+    }
+
+    /// Whether the typed text matches the amount or the start of the recipient
+    fn typed_confirmation_matches(&self) -> bool {
+        let input = self.confirm_input.trim();
+        input == self.amount.as_str()
+            || (input.len() == TYPED_CONFIRM_PREFIX_LEN && self.recipient.starts_with(input))
+    }
+}
+
+/// A transfer being signed, sent and confirmed
+pub(super) struct PendingSend {
+    pub(super) task: Task<Landed>,
+    pub(super) tracker: Tracker,
+    pub(super) recipient: Pubkey,
+    pub(super) lamports: u64,
+    pub(super) reference: Option<Pubkey>,
+    pub(super) resends: Option<Signature>,
+}
+
+/// An inspected transaction being sent, with what it takes from the wallet
+/// for the spending limits
+pub(super) struct PendingInspected {
+    pub(super) task: Task<Landed>,
+    pub(super) tracker: Tracker,
+    pub(super) lamports: u64,
+}
+
+impl App {
+    /// Warn when sending to an account that doesn't exist yet with less than
+    /// the rent-exempt minimum, since such a transfer would leave the account
+    /// unusable (or be rejected by the runtime).
+    fn check_recipient_rent(&mut self) -> Result<()> {
+        let recipient =
+            Pubkey::from_str(&self.send_state.recipient).context("Invalid recipient address")?;
+        let lamports = self.send_state.lamports(&self.amounts)?;
+        self.send_state.rent_exempt_minimum =
+            rent::recipient_shortfall(&*self.rpc_client, &recipient, lamports)?;
+        Ok(())
+    }
+
+    /// Refuse amounts that would leave the wallet with less than the
+    /// rent-exempt minimum, since the network rejects them
+    fn check_sender_rent(&self) -> Result<()> {
+        let lamports = self.send_state.lamports(&self.amounts)?;
+        if let Some(minimum) = rent::sender_shortfall(
+            &*self.rpc_client,
+            &self.wallet.address,
+            &self.wallet.fee_payer(),
+            lamports,
+        )? {
+            bail!(sender_rent_message(minimum));
+        }
+        Ok(())
+    }
+
+    fn check_recipient_blocklist(&mut self) {
+        self.send_state.blocklist_reason = Pubkey::from_str(&self.send_state.recipient)
+            .ok()
+            .and_then(|recipient| self.blocklist.reason(&recipient))
+            .map(str::to_string);
+    }
+
+    /// Check the entered recipient and amount, and move on to the first
+    /// warning that applies or to the confirmation
+    pub(super) fn review_send(&mut self) -> Result<()> {
+        self.check_spending_limits()?;
+        self.check_recipient_rent()?;
+        self.check_sender_rent()?;
+        self.check_recipient_blocklist();
+        self.check_large_send()?;
+        self.suggest_priority_fee()?;
+        self.send_state.advance();
+        Ok(())
+    }
+
+    /// Review the send again after it changed on the confirmation, staying
+    /// on the amount if it no longer passes
+    fn revise_send(&mut self) -> Result<()> {
+        self.send_state.input_mode = SendInputMode::EditingAmount;
+        self.review_send()
+    }
+
+    fn check_spending_limits(&mut self) -> Result<()> {
+        let lamports = self.send_state.lamports(&self.amounts)?;
+        self.send_state.limit_warning = match self.spending.check(&self.config.limits, lamports) {
+            LimitCheck::Within => None,
+            LimitCheck::Exceeded(reason) => Some(reason),
+            LimitCheck::Blocked(reason) => anyhow::bail!("Send blocked: amount {}", reason),
+        };
+        Ok(())
+    }
+
+    /// Suggest a priority fee from what was recently paid to write to the
+    /// wallet and the recipient. Without one the send goes without.
+    fn suggest_priority_fee(&mut self) -> Result<()> {
+        let recipient =
+            Pubkey::from_str(&self.send_state.recipient).context("Invalid recipient address")?;
+        let suggested = priority::suggest(
+            &*self.rpc_client,
+            &self.config.priority_fee,
+            &[self.wallet.address, recipient],
+        );
+        self.send_state.suggested_fee = Some(suggested.map_err(|e| format!("{:#}", e)));
+        Ok(())
+    }
+    /// Large mainnet sends are confirmed by retyping rather than a single key
+    fn check_large_send(&mut self) -> Result<()> {
+        let lamports = self.send_state.lamports(&self.amounts)?;
+        self.send_state.typed_confirmation =
+            lamports >= typed_threshold(&self.config) && self.is_mainnet();
+        Ok(())
+    }
+
+    /// Sign and send the transfer in the background, once the pre-send
+    /// hooks approve it; `poll_tasks` picks up the confirmation
+    pub(super) fn send_transaction(&mut self) -> Result<()> {
+        let recipient =
+            Pubkey::from_str(&self.send_state.recipient).context("Invalid recipient address")?;
+        let lamports = self.send_state.lamports(&self.amounts)?;
+        let keypair = self.signer()?;
+        let payer = self.wallet.address;
+        let rpc_client = self.rpc_client.clone();
+        let mainnet = self.is_mainnet();
+        let fee_payer = self.wallet.fee_payer.clone();
+        let mut pipeline = transfer_pipeline(&self.config, false, None, fee_payer, || mainnet)?;
+        pipeline.compute_unit_price = self.send_state.compute_unit_price();
+        let retried = self.send_state.reference_key;
+        pipeline.reference = self
+            .send_state
+            .reference(&self.config)
+            .then(|| retried.unwrap_or_else(pay::new_reference));
+        let reference = pipeline.reference;
+        // Typing the confirmation word is what lets a blocklisted recipient through
+        if self.send_state.blocklist_reason.is_some() {
+            pipeline.guard = pipeline
+                .guard
+                .map(|guard| Arc::new(guard.acknowledging(&recipient)));
+        }
+        let tracker = Tracker::default();
+
+        let progress = tracker.clone();
+        let task = Task::spawn(move || {
+            transfer(
+                &*rpc_client,
+                &pipeline,
+                &keypair,
+                &payer,
+                &recipient,
+                lamports,
+                &mut |signature, status| progress.update(signature, status),
+            )
+        });
+        self.send_state.error = None;
+        self.send_task = Some(PendingSend {
+            task,
+            tracker,
+            recipient,
+            lamports,
+            reference,
+            resends: self.send_state.resending,
+        });
+        Ok(())
+    }
+
+    /// Sign the reviewed transaction and send it in the background, if the
+    /// simulation didn't fail and what it takes is within the limits
+    pub(super) fn send_inspected(&mut self) -> Result<()> {
+        let inspection = self
+            .inspect_state
+            .inspection
+            .as_ref()
+            .context("No transaction to send")?;
+        if let Ok(Simulation {
+            error: Some(ref error),
+            ..
+        }) = inspection.simulation
+        {
+            bail!("It failed in the simulation: {}", error);
+        }
+        let lamports = inspection
+            .balance_change(&self.wallet.address)
+            .map_or(0, |change| u64::try_from(-change).unwrap_or(0));
+        if let LimitCheck::Exceeded(reason) | LimitCheck::Blocked(reason) =
+            self.spending.check(&self.config.limits, lamports)
+        {
+            return Err(Refused(format!("Send blocked: amount {}", reason)).into());
+        }
+        let keypair = self.signer()?;
+        let mut signers: Vec<&dyn Signer> = vec![&*keypair];
+        if let Some(ref fee_payer) = self.wallet.fee_payer {
+            signers.push(&**fee_payer);
+        }
+        let transaction = inspector::sign_partial(&inspection.transaction, &signers)?;
+        let missing = inspector::missing_signers(&transaction);
+        if !missing.is_empty() {
+            // Passed on for the others to sign, rather than sent
+            let encoded = inspector::encode(&transaction)?;
+            self.inspect_state.partially_signed = Some((encoded, missing));
+            self.inspect_state.error = None;
+            return Ok(());
+        }
+        let rpc_client = self.rpc_client.clone();
+        let pipeline = self.pipeline();
+        let tracker = Tracker::default();
+
+        let progress = tracker.clone();
+        let task = Task::spawn(move || {
+            pipeline
+                .submit(&*rpc_client, &transaction, &mut |signature, status| {
+                    progress.update(signature, status)
+                })?
+                .into_result()
+        });
+        self.inspect_state.error = None;
+        self.inspect_task = Some(PendingInspected {
+            task,
+            tracker,
+            lamports,
+        });
+        Ok(())
+    }
+
+    /// How the screens' sends are followed, going by `[confirm] finalized`
+    fn pipeline(&self) -> Pipeline {
+        Pipeline::finalized(self.config.confirm.finalized)
+    }
+
+    fn confirmed_message(&self, landed: &Landed) -> String {
+        format!(
+            "Transaction {} at slot {}: {}",
+            if self.config.confirm.finalized {
+                "finalized"
+            } else {
+                "confirmed"
+            },
+            landed.slot,
+            landed.signature
+        )
+    }
+
+    pub(super) fn finish_inspected_send(&mut self, landed: Landed, lamports: u64) {
+        self.last_tx_signature = Some(landed.signature);
+        self.toasts.success(self.confirmed_message(&landed));
+        if let Err(e) = self.spending.record(lamports) {
+            self.toasts.error(format!("{:#}", e));
+        }
+        self.refresh_balance(false);
+
+        if self.state == AppState::InspectTx {
+            self.state = AppState::Wallet;
+            self.inspect_state = InspectState::default();
+        }
+    }
+
+    pub(super) fn finish_send(&mut self, landed: Landed, send: &PendingSend) {
+        let (recipient, lamports) = (send.recipient, send.lamports);
+        self.last_tx_signature = Some(landed.signature);
+        if let Some(ref hooks) = self.hooks {
+            hooks.sent(
+                &self.wallet.address,
+                &landed.signature,
+                &recipient,
+                lamports,
+            );
+        }
+        self.toasts.success(self.confirmed_message(&landed));
+
+        if let Err(e) = self.spending.record(lamports) {
+            self.toasts.error(format!("{:#}", e));
+        }
+        if let Some(ref reference) = send.reference {
+            let address = self.wallet.address;
+            let signature = landed.signature.to_string();
+            if let Err(e) = self.cluster_id().and_then(|cluster| {
+                self.open_history_db()?
+                    .save_reference(&address, &cluster, &signature, reference)
+            }) {
+                self.toasts.error(format!("{:#}", e));
+            }
+        }
+        self.record_resend(send, &landed.signature);
+
+        // Refresh balance
+        self.refresh_balance(false);
+
+        if self.state == AppState::Send {
+            self.state = AppState::Wallet;
+            self.send_state = SendState::default();
+        }
+    }
+
+    /// Remember that the failed transaction `send` went out again as
+    /// `signature`, so it isn't sent a third time while that may land
+    pub(super) fn record_resend(&mut self, send: &PendingSend, signature: &Signature) {
+        let Some(resent) = send.resends else {
+            return;
+        };
+        let address = self.wallet.address;
+        if let Err(e) = self.cluster_id().and_then(|cluster| {
+            self.open_history_db()?.save_resend(
+                &address,
+                &cluster,
+                &resent.to_string(),
+                &signature.to_string(),
+            )
+        }) {
+            self.toasts.error(format!("{:#}", e));
+        }
+    }
+}
+
+pub(super) fn handle_send_input(
+    app: &mut App,
+    key: KeyEvent,
+    action: Option<Action>,
+) -> Result<bool> {
+    match app.send_state.input_mode {
+        SendInputMode::EditingRecipient => match (action, key.code) {
+            (Some(Action::Confirm), _) => {
+                if !app.send_state.recipient.is_empty() {
+                    app.send_state.advance();
+                }
+            }
+            (Some(Action::Back), _) => return Ok(false),
+            _ => {
+                app.send_state.recipient.handle_key(&key, |_| true);
+            }
+        },
+        SendInputMode::EditingAmount => match (action, key.code) {
+            (Some(Action::Confirm), _) => {
+                if !app.send_state.amount.is_empty() {
+                    app.review_send()?;
+                }
+            }
+            (Some(Action::Back), _) => {
+                app.send_state.input_mode = SendInputMode::EditingRecipient;
+            }
+            _ => {
+                app.send_state
+                    .amount
+                    .handle_key(&key, |c| c.is_ascii_digit() || c == '.');
+            }
+        },
+        SendInputMode::AcknowledgingBlocklist => match (action, key.code) {
+            (Some(Action::Confirm), _) => {
+                if app.send_state.confirm_input.as_str() == BLOCKLIST_ACK_WORD {
+                    app.send_state.advance();
+                } else {
+                    app.send_state.error = Some(Failure::message(tr_fmt(
+                        "Type {} to continue",
+                        &[&BLOCKLIST_ACK_WORD],
+                    )));
+                    app.send_state.confirm_input.clear();
+                }
+            }
+            (Some(Action::Back), _) => {
+                app.send_state.confirm_input.clear();
+                app.send_state.input_mode = SendInputMode::EditingAmount;
+            }
+            _ => {
+                app.send_state.confirm_input.handle_key(&key, |_| true);
+            }
+        },
+        SendInputMode::ConfirmingLimit => match (action, key.code) {
+            (Some(Action::Confirm), _) => {
+                if app.send_state.confirm_input.as_str() == app.send_state.amount.as_str() {
+                    app.send_state.advance();
+                } else {
+                    app.send_state.error = Some(Failure::message(tr("Amount does not match")));
+                    app.send_state.confirm_input.clear();
+                }
+            }
+            (Some(Action::Back), _) => {
+                app.send_state.confirm_input.clear();
+                app.send_state.input_mode = SendInputMode::EditingAmount;
+            }
+            _ => {
+                app.send_state
+                    .confirm_input
+                    .handle_key(&key, |c| c.is_ascii_digit() || c == '.');
+            }
+        },
+        SendInputMode::Confirming if app.send_state.typed_confirmation => {
+            match (action, key.code) {
+                (Some(Action::Confirm), _) => {
+                    if app.send_state.typed_confirmation_matches() {
+                        app.send_transaction()?;
+                    } else {
+                        app.send_state.error =
+                            Some(Failure::message(tr("Confirmation does not match")));
+                        app.send_state.confirm_input.clear();
+                    }
+                }
+                (Some(Action::Back), _) => {
+                    app.send_state.confirm_input.clear();
+                    app.send_state.input_mode = SendInputMode::EditingAmount;
+                }
+                _ => {
+                    app.send_state.confirm_input.handle_key(&key, |_| true);
+                }
+            }
+        }
+        SendInputMode::Confirming => match action {
+            Some(Action::Confirm) => {
+                app.send_transaction()?;
+            }
+            Some(Action::BumpAmount) => {
+                // Bump the amount up to the rent-exempt minimum
+                if let Some(minimum) = app.send_state.rent_exempt_minimum.take() {
+                    app.send_state.amount.set(app.amounts.input(minimum));
+                    app.revise_send()?;
+                }
+            }
+            Some(Action::ToggleReference) => {
+                let reference = app.send_state.reference(&app.config);
+                app.send_state.reference_override = Some(!reference);
+                app.revise_send()?;
+            }
+            Some(Action::SetPriorityFee) => {
+                let price = app.send_state.compute_unit_price().unwrap_or(0);
+                app.send_state.fee_input.set(price.to_string());
+                app.send_state.input_mode = SendInputMode::EditingPriorityFee;
+            }
+            Some(Action::Cancel) => {
+                app.send_state.input_mode = SendInputMode::EditingAmount;
+            }
+            _ => {}
+        },
+        SendInputMode::EditingPriorityFee => {
+            match (action, key.code) {
+                (Some(Action::Confirm), _) => {
+                    // Clearing the field goes back to the suggestion
+                    app.send_state.fee_override =
+                        if app.send_state.fee_input.is_empty() {
+                            None
+                        } else {
+                            Some(app.send_state.fee_input.parse::<u64>().context(
+                                "Enter the priority fee in micro-lamports per compute unit",
+                            )?)
+                        };
+                    app.send_state.advance();
+                }
+                (Some(Action::Back), _) => app.send_state.advance(),
+                _ => {
+                    app.send_state
+                        .fee_input
+                        .handle_key(&key, |c| c.is_ascii_digit());
+                }
+            }
+        }
+    }
+    Ok(true)
+}
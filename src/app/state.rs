@@ -0,0 +1,549 @@
+//! What each screen holds between key presses
+
+use super::App;
+use crate::input::TextInput;
+use anyhow::{bail, Context, Result};
+use solace::{
+    accountinfo::{self, AccountInfo},
+    config::{self, Config},
+    dashboard::Dashboard,
+    explorer::Explorer,
+    failure::Failure,
+    grind::Grinder,
+    inspector::{self, Inspection},
+    keys::{Derivation, SecretKeypair},
+    message,
+    nonce::NonceAccount,
+    pay::{PaymentRequest, SavedRequest},
+    siws::SignInRequest,
+    task::Task,
+    theme::ThemePreset,
+    watch::Watched,
+};
+use solana_sdk::{
+    commitment_config::CommitmentLevel, pubkey::Pubkey, signature::Signature,
+    transaction::VersionedTransaction,
+};
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Instant,
+};
+use zeroize::Zeroizing;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum VanityStep {
+    EnteringPrefix,
+    EnteringSuffix,
+    Searching,
+    Saving,
+}
+
+pub(super) struct VanityState {
+    pub(super) step: VanityStep,
+    pub(super) prefix: String,
+    pub(super) suffix: String,
+    pub(super) ignore_case: bool,
+    pub(super) grinder: Option<Grinder>,
+    pub(super) keypair: Option<SecretKeypair>,
+    pub(super) path_input: String,
+    pub(super) status: Option<String>,
+    pub(super) error: Option<Failure>,
+}
+
+impl Default for VanityState {
+    fn default() -> Self {
+        Self {
+            step: VanityStep::EnteringPrefix,
+            prefix: String::new(),
+            suffix: String::new(),
+            ignore_case: false,
+            grinder: None,
+            keypair: None,
+            path_input: String::new(),
+            status: None,
+            error: None,
+        }
+    }
+}
+
+pub(super) struct DerivedAccount {
+    pub(super) derivation: Derivation,
+    pub(super) address: Pubkey,
+    pub(super) lamports: u64,
+}
+
+/// What the Settings screen can change
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum Setting {
+    Cluster,
+    Commitment,
+    Explorer,
+    Theme,
+    Refresh,
+    FeePercentile,
+    FeeMax,
+    PriceAbove,
+    PriceBelow,
+}
+
+impl Setting {
+    pub(super) const ALL: [Self; 9] = [
+        Self::Cluster,
+        Self::Commitment,
+        Self::Explorer,
+        Self::Theme,
+        Self::Refresh,
+        Self::FeePercentile,
+        Self::FeeMax,
+        Self::PriceAbove,
+        Self::PriceBelow,
+    ];
+
+    pub(super) fn label(self) -> &'static str {
+        match self {
+            Self::Cluster => "Cluster",
+            Self::Commitment => "Commitment",
+            Self::Explorer => "Explorer",
+            Self::Theme => "Theme",
+            Self::Refresh => "Refresh seconds",
+            Self::FeePercentile => "Priority fee percentile",
+            Self::FeeMax => "Priority fee max",
+            Self::PriceAbove => "SOL price above",
+            Self::PriceBelow => "SOL price below",
+        }
+    }
+
+    /// The section and key it's saved under in the config file
+    pub(super) fn key(self) -> (&'static str, &'static str) {
+        match self {
+            Self::Cluster => ("cluster", "name"),
+            Self::Commitment => ("cluster", "commitment"),
+            Self::Explorer => ("explorer", "provider"),
+            Self::Theme => ("theme", "preset"),
+            Self::Refresh => ("refresh", "seconds"),
+            Self::FeePercentile => ("priority_fee", "percentile"),
+            Self::FeeMax => ("priority_fee", "max"),
+            Self::PriceAbove => ("price_alerts", "sol_above"),
+            Self::PriceBelow => ("price_alerts", "sol_below"),
+        }
+    }
+
+    /// The value in use, as it's written in the config
+    pub(super) fn value(self, app: &App) -> String {
+        let config = &app.config;
+        match self {
+            Self::Cluster => app.rpc_url.clone(),
+            Self::Commitment => config.cluster.commitment.to_string(),
+            Self::Explorer => config.explorer.provider.name().to_string(),
+            Self::Theme => config.theme.preset.name().to_string(),
+            Self::Refresh => config.refresh.seconds.to_string(),
+            Self::FeePercentile => config.priority_fee.percentile.to_string(),
+            Self::FeeMax => config.priority_fee.max.to_string(),
+            Self::PriceAbove => config
+                .price_alerts
+                .sol_above
+                .map_or_else(String::new, |price| price.to_string()),
+            Self::PriceBelow => config
+                .price_alerts
+                .sol_below
+                .map_or_else(String::new, |price| price.to_string()),
+        }
+    }
+
+    /// The value after the one in use, for settings picked from a few
+    /// choices rather than typed
+    pub(super) fn next(self, config: &Config) -> Option<String> {
+        match self {
+            Self::Commitment => Some(
+                next_choice(
+                    &[
+                        CommitmentLevel::Processed,
+                        CommitmentLevel::Confirmed,
+                        CommitmentLevel::Finalized,
+                    ],
+                    config.cluster.commitment,
+                )
+                .to_string(),
+            ),
+            Self::Explorer => Some(
+                next_choice(&Explorer::ALL, config.explorer.provider)
+                    .name()
+                    .to_string(),
+            ),
+            Self::Theme => Some(
+                next_choice(&ThemePreset::ALL, config.theme.preset)
+                    .name()
+                    .to_string(),
+            ),
+            Self::Cluster
+            | Self::Refresh
+            | Self::FeePercentile
+            | Self::FeeMax
+            | Self::PriceAbove
+            | Self::PriceBelow => None,
+        }
+    }
+}
+
+/// The choice after `current`, wrapping around
+fn next_choice<T: Copy + PartialEq>(choices: &[T], current: T) -> T {
+    let i = choices
+        .iter()
+        .position(|&choice| choice == current)
+        .map_or(0, |i| (i + 1) % choices.len());
+    choices[i]
+}
+
+/// What's being typed on the Settings screen
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum SettingsInput {
+    /// A new value for the selected setting
+    Value,
+    /// The path of a keypair file to switch to
+    Keypair,
+    /// The wallet's display name
+    Name,
+}
+
+#[derive(Default)]
+pub(super) struct SettingsState {
+    pub(super) selected: usize,
+    /// The prompt open, and what's been typed into it
+    pub(super) input: Option<(SettingsInput, String)>,
+    pub(super) error: Option<Failure>,
+}
+
+/// The Home dashboard, shown when `[[profiles]]` are configured
+#[derive(Default)]
+pub(super) struct DashboardState {
+    /// The address in each profile's keypair file, once they've been read
+    pub(super) profiles: Option<Vec<(Pubkey, PathBuf)>>,
+    /// The rows: the open wallet unless it's a profile, then the profiles,
+    /// with the keypair file to switch to
+    pub(super) wallets: Vec<(Pubkey, Option<PathBuf>)>,
+    pub(super) balances: Option<Dashboard>,
+    pub(super) task: Option<Task<Dashboard>>,
+    pub(super) selected: usize,
+    pub(super) error: Option<Failure>,
+}
+
+#[derive(Default)]
+pub(super) struct WatchState {
+    /// The rows, as the config lists them
+    pub(super) addresses: Vec<Pubkey>,
+    pub(super) watched: Vec<Watched>,
+    pub(super) task: Option<Task<Vec<Watched>>>,
+    pub(super) selected: usize,
+    /// The address being typed to watch
+    pub(super) input: Option<String>,
+    pub(super) error: Option<Failure>,
+}
+
+#[derive(Default)]
+pub(super) struct ReceiveState {
+    /// Where to save the QR code, while it's being entered
+    pub(super) save_path: Option<String>,
+    /// The QR code is shown on the whole screen
+    pub(super) fullscreen: bool,
+    /// The amount being typed for a payment request
+    pub(super) amount: Option<String>,
+    /// The point-of-sale request shown instead of the address
+    pub(super) request: Option<PaymentRequest>,
+    /// The signature that paid the request
+    pub(super) paid: Option<String>,
+    pub(super) task: Option<Task<Option<String>>>,
+    /// When the chain was last looked at for the payment
+    pub(super) checked: Option<Instant>,
+    pub(super) error: Option<Failure>,
+}
+
+impl ReceiveState {
+    /// What the QR code holds: the request, or the address
+    pub(super) fn payload(&self, address: &Pubkey) -> String {
+        self.request
+            .as_ref()
+            .map_or_else(|| address.to_string(), PaymentRequest::uri)
+    }
+}
+
+#[derive(Default)]
+pub(super) struct RequestsState {
+    /// The saved requests, newest first
+    pub(super) requests: Vec<SavedRequest>,
+    /// Looking for the payments of the pending ones
+    pub(super) task: Option<Task<Vec<(Pubkey, String)>>>,
+    pub(super) selected: usize,
+    pub(super) error: Option<Failure>,
+}
+
+#[derive(Default)]
+pub(super) struct AccountsState {
+    pub(super) accounts: Vec<DerivedAccount>,
+    pub(super) selected: usize,
+    pub(super) error: Option<Failure>,
+    /// Search text while the search prompt is open
+    pub(super) search: Option<String>,
+}
+
+impl AccountsState {
+    /// Move the selection to the first account matching the search
+    pub(super) fn select_match(&mut self) {
+        let Some(query) = self.search.as_deref().map(str::to_ascii_lowercase) else {
+            return;
+        };
+        if let Some(i) = self.accounts.iter().position(|account| {
+            account
+                .address
+                .to_string()
+                .to_ascii_lowercase()
+                .contains(&query)
+                || account.derivation.to_string().contains(&query)
+        }) {
+            self.selected = i;
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum ExportKind {
+    PrivateKey,
+    SeedPhrase,
+    PaperWallet,
+    Shares,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum ExportStep {
+    /// Explain the risk and pick what to export
+    Choosing,
+    /// Type the confirmation word
+    Confirming,
+    /// Secret is on screen until the timeout
+    Revealed,
+    /// Choose where to write the paper wallet
+    SavingPaper,
+    /// Choose the share counts and where to write them
+    SavingShares,
+}
+
+/// Word that must be typed before a secret is shown
+pub(super) const EXPORT_ACK_WORD: &str = "EXPORT";
+
+pub(super) struct ExportState {
+    pub(super) step: ExportStep,
+    pub(super) kind: ExportKind,
+    pub(super) confirm_input: String,
+    pub(super) secret: Option<Zeroizing<String>>,
+    pub(super) revealed_at: Option<Instant>,
+    pub(super) path_input: String,
+    /// `<threshold> of <count>` for backup shares
+    pub(super) shares_input: String,
+    pub(super) editing_shares: bool,
+    pub(super) status: Option<String>,
+    pub(super) error: Option<Failure>,
+}
+
+impl Default for ExportState {
+    fn default() -> Self {
+        Self {
+            step: ExportStep::Choosing,
+            kind: ExportKind::PrivateKey,
+            confirm_input: String::new(),
+            secret: None,
+            revealed_at: None,
+            path_input: String::new(),
+            shares_input: String::from("2 of 3"),
+            editing_shares: true,
+            status: None,
+            error: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum ImportStep {
+    EnteringKey,
+    EnteringPath,
+}
+
+#[derive(Default)]
+pub(super) struct SignState {
+    pub(super) message: TextInput,
+    /// Signature of `message`, until it's edited
+    pub(super) signature: Option<Signature>,
+    pub(super) error: Option<Failure>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub(super) enum VerifyStep {
+    #[default]
+    Message,
+    Address,
+    Signature,
+}
+
+#[derive(Default)]
+pub(super) struct VerifyState {
+    pub(super) step: VerifyStep,
+    pub(super) message: TextInput,
+    pub(super) address: TextInput,
+    pub(super) signature: TextInput,
+    /// The signer, once the signature checked out
+    pub(super) verified: Option<Pubkey>,
+    pub(super) error: Option<Failure>,
+}
+
+impl VerifyState {
+    pub(super) fn check(&self) -> Result<Pubkey> {
+        let address = Pubkey::from_str(self.address.trim()).context("Invalid signer address")?;
+        let signature = Signature::from_str(self.signature.trim()).context("Invalid signature")?;
+        if !message::verify(&address, self.message.as_bytes(), &signature)? {
+            bail!("The signature does not match this message and address");
+        }
+        Ok(address)
+    }
+}
+
+#[derive(Default)]
+pub(super) struct SignInState {
+    pub(super) input: TextInput,
+    /// The parsed request, while it's reviewed
+    pub(super) request: Option<SignInRequest>,
+    /// The signed response, pretty-printed
+    pub(super) response: Option<String>,
+    pub(super) error: Option<Failure>,
+}
+
+impl SignInState {
+    /// The request typed or pasted as JSON, or read from the file named
+    pub(super) fn read_request(&self, wallet: &Pubkey) -> Result<SignInRequest> {
+        let input = self.input.trim();
+        let request = if input.starts_with('{') || input.starts_with("solana-action:") {
+            SignInRequest::parse(input)?
+        } else {
+            let path = config::expand_tilde(Path::new(input));
+            let text = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            SignInRequest::parse(&text)?
+        };
+        request.check(wallet)?;
+        Ok(request)
+    }
+}
+
+#[derive(Default)]
+pub(super) struct InspectState {
+    pub(super) input: TextInput,
+    /// The decoded transaction, while it's reviewed
+    pub(super) inspection: Option<Inspection>,
+    /// It with the wallet's signature, base64 encoded, and the signers that
+    /// still have to add theirs
+    pub(super) partially_signed: Option<(String, Vec<Pubkey>)>,
+    pub(super) error: Option<Failure>,
+}
+
+impl InspectState {
+    /// The transaction pasted, or read from the file named
+    pub(super) fn read_transaction(&self) -> Result<VersionedTransaction> {
+        inspector::read_transaction(self.input.trim())
+    }
+}
+#[derive(Default)]
+pub(super) struct LookupState {
+    pub(super) input: TextInput,
+    pub(super) result: Option<LookupResult>,
+    pub(super) error: Option<Failure>,
+    /// Index into the result's `fields`, for copying
+    pub(super) field: usize,
+}
+
+pub(super) enum LookupResult {
+    /// The address looked up, with its account if there is one
+    Account(Pubkey, Option<AccountInfo>),
+    /// A data length entered instead, with its rent-exempt minimum and the
+    /// minimums of the common account sizes
+    Rent {
+        data_len: usize,
+        minimum: u64,
+        common: Vec<(&'static str, usize, u64)>,
+    },
+}
+
+impl LookupResult {
+    /// The values that can be copied, in the order they're shown: the
+    /// address, then an account's owner and decoded fields
+    pub(super) fn fields(&self) -> Vec<String> {
+        let Self::Account(address, ref account) = *self else {
+            return Vec::new();
+        };
+        let mut fields = vec![address.to_string()];
+        if let Some(info) = account {
+            fields.push(info.account.owner.to_string());
+            if let Some((_, ref parsed)) = info.parsed {
+                fields.extend(
+                    accountinfo::fields(parsed)
+                        .into_iter()
+                        .map(|(_, value)| value),
+                );
+            }
+        }
+        fields
+    }
+}
+
+#[derive(Default)]
+pub(super) struct NonceState {
+    pub(super) accounts: Vec<NonceAccount>,
+    /// The first of the wallet's nonce seeds with no account yet
+    pub(super) free: Option<u32>,
+    pub(super) selected: usize,
+    pub(super) step: NonceStep,
+    /// SOL to withdraw from the selected account
+    pub(super) amount: TextInput,
+    pub(super) error: Option<Failure>,
+}
+
+impl NonceState {
+    pub(super) fn selected_account(&self) -> Option<&NonceAccount> {
+        self.accounts.get(self.selected)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub(super) enum NonceStep {
+    #[default]
+    List,
+    EnteringWithdrawal,
+    Confirming(NonceAction),
+}
+
+/// Something done to a nonce account, with the lamports it moves
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum NonceAction {
+    Create(u64),
+    Advance,
+    Withdraw(u64),
+}
+
+pub(super) struct ImportState {
+    pub(super) step: ImportStep,
+    pub(super) key_input: Zeroizing<String>,
+    pub(super) keypair: Option<SecretKeypair>,
+    pub(super) path_input: String,
+    pub(super) status: Option<String>,
+    pub(super) error: Option<Failure>,
+}
+
+impl Default for ImportState {
+    fn default() -> Self {
+        Self {
+            step: ImportStep::EnteringKey,
+            key_input: Zeroizing::new(String::new()),
+            keypair: None,
+            path_input: String::new(),
+            status: None,
+            error: None,
+        }
+    }
+}
@@ -0,0 +1,996 @@
+use super::{input::file_name_part, *};
+use crate::input::TextInput;
+use crossterm::event::{KeyCode, KeyEvent};
+use solace::{
+    inspector,
+    rpc::{MockRpc, MOCK_BLOCK_TIME, MOCK_RENT_MINIMUM, MOCK_SLOT},
+};
+use solana_sdk::{signature::Keypair, transaction::VersionedTransaction};
+
+fn app_on(rpc: &Arc<MockRpc>) -> App {
+    let keypair = SecretKeypair::from_keypair(Keypair::new());
+    let wallet = WalletInfo {
+        address: keypair.pubkey(),
+        keypair: Some(Arc::new(keypair)),
+        keypair_source: KeypairSource::Env,
+        fee_payer: None,
+        fee_payer_source: None,
+        lamports: 0,
+    };
+    App::new(
+        wallet,
+        rpc.clone(),
+        "http://localhost:8899".to_string(),
+        Config::default(),
+        Blocklist::default(),
+        SpendingLedger::default(),
+        SessionLock::disabled(),
+    )
+}
+
+fn press(app: &mut App, code: KeyCode) {
+    assert!(handle_key(app, KeyEvent::from(code)));
+}
+
+fn type_text(app: &mut App, text: &str) {
+    text.chars().for_each(|c| press(app, KeyCode::Char(c)));
+}
+
+/// Poll until the background requests are done, as the event loop would
+fn settle(app: &mut App) {
+    let started = Instant::now();
+    while app.busy() {
+        assert!(started.elapsed() < Duration::from_secs(5), "task hung");
+        std::thread::sleep(Duration::from_millis(1));
+        app.poll_tasks();
+    }
+}
+
+/// Fill in the send form up to the final confirmation
+fn enter_send(app: &mut App, recipient: &Pubkey, amount: &str) {
+    app.state = AppState::Send;
+    type_text(app, &recipient.to_string());
+    press(app, KeyCode::Enter);
+    assert_eq!(app.send_state.input_mode, SendInputMode::EditingAmount);
+    type_text(app, amount);
+    press(app, KeyCode::Enter);
+    assert_eq!(app.send_state.input_mode, SendInputMode::Confirming);
+    assert_eq!(app.key_context(), KeyContext::SendConfirm);
+}
+
+#[test]
+fn sends_after_confirmation() {
+    let rpc = Arc::new(MockRpc::default());
+    let mut app = app_on(&rpc);
+    let recipient = Pubkey::new_unique();
+    rpc.set_balance(recipient, LAMPORTS_PER_SOL);
+    rpc.set_balance(app.wallet.address, 2 * LAMPORTS_PER_SOL);
+
+    enter_send(&mut app, &recipient, "0.5");
+    assert_eq!(app.send_state.rent_exempt_minimum, None);
+    press(&mut app, KeyCode::Char('y'));
+    assert!(app.send_task.is_some());
+    settle(&mut app);
+
+    let sent = rpc.sent();
+    assert_eq!(sent.len(), 1);
+    assert_eq!(app.last_tx_signature, Some(sent[0].signatures[0]));
+    assert_eq!(app.state, AppState::Wallet);
+    assert_eq!(app.send_state.input_mode, SendInputMode::EditingRecipient);
+    // The balance is refreshed once the send is confirmed
+    assert_eq!(app.wallet.lamports, 2 * LAMPORTS_PER_SOL);
+}
+
+#[test]
+fn sends_with_a_reference_key_and_records_it() {
+    let rpc = Arc::new(MockRpc::default());
+    let mut app = app_on(&rpc);
+    let path = std::env::temp_dir().join(format!("solace-test-{}.sqlite3", Pubkey::new_unique()));
+    app.history_db = Some(HistoryDb::open_at(&path).unwrap());
+    let recipient = Pubkey::new_unique();
+    rpc.set_balance(recipient, LAMPORTS_PER_SOL);
+    rpc.set_balance(app.wallet.address, 2 * LAMPORTS_PER_SOL);
+
+    enter_send(&mut app, &recipient, "0.5");
+    assert!(!app.send_state.reference(&app.config));
+    press(&mut app, KeyCode::Char('r'));
+    assert!(app.send_state.reference(&app.config));
+    press(&mut app, KeyCode::Char('y'));
+    settle(&mut app);
+
+    // The transfer carries the key, read-only and unsigned
+    let sent = rpc.sent();
+    let message = &sent[0].message;
+    let keys = message.static_account_keys();
+    let transfer = message
+        .instructions()
+        .iter()
+        .find(|ix| keys[usize::from(ix.program_id_index)] == solana_sdk::system_program::id())
+        .unwrap();
+    assert_eq!(transfer.accounts.len(), 3);
+    let index = usize::from(transfer.accounts[2]);
+    assert!(!message.is_signer(index));
+    assert!(!message.is_maybe_writable(index, None));
+
+    let cluster = app.cluster_id().unwrap();
+    let references = app
+        .history_db
+        .as_ref()
+        .unwrap()
+        .load_references(&app.wallet.address, &cluster)
+        .unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(
+        references.get(&sent[0].signatures[0].to_string()),
+        Some(&keys[index].to_string())
+    );
+}
+
+#[test]
+fn sends_failed_transfers_again_from_the_history() {
+    let rpc = Arc::new(MockRpc::default());
+    let mut app = app_on(&rpc);
+    let recipient = Pubkey::new_unique();
+    rpc.set_balance(recipient, LAMPORTS_PER_SOL);
+    rpc.set_balance(app.wallet.address, 2 * LAMPORTS_PER_SOL);
+    enter_send(&mut app, &recipient, "0.5");
+    press(&mut app, KeyCode::Char('y'));
+    settle(&mut app);
+    let first = rpc.sent()[0].signatures[0];
+
+    let entry = |failed, block_time| HistoryEntry {
+        signature: first.to_string(),
+        slot: MOCK_SLOT,
+        block_time,
+        failed,
+        memo: None,
+        note: None,
+        reference: None,
+        details: None,
+    };
+    app.state = AppState::Transactions;
+    // Listed, it landed, block time or not
+    app.history.entries = vec![entry(false, None)];
+    assert!(!app.action_available(Action::Resend));
+    press(&mut app, KeyCode::Char('R'));
+    assert_eq!(app.state, AppState::Transactions);
+
+    // Out of the recent status cache, the ledger still has it as landed,
+    // whatever the list says
+    rpc.age_transactions();
+    assert_eq!(
+        rpc.get_signature_statuses(&[first]).unwrap().value,
+        vec![None]
+    );
+    app.history.entries = vec![entry(true, None)];
+    press(&mut app, KeyCode::Char('R'));
+    assert_eq!(app.state, AppState::Transactions);
+    assert_eq!(rpc.sent().len(), 1);
+
+    rpc.fail_transaction(first);
+    app.history.entries = vec![entry(true, Some(MOCK_BLOCK_TIME))];
+    press(&mut app, KeyCode::Char('R'));
+    assert_eq!(app.state, AppState::Send);
+    assert_eq!(app.send_state.input_mode, SendInputMode::Confirming);
+    assert_eq!(app.send_state.recipient.as_str(), recipient.to_string());
+    assert_eq!(
+        app.send_state.lamports(&app.amounts).unwrap(),
+        LAMPORTS_PER_SOL / 2
+    );
+    press(&mut app, KeyCode::Char('y'));
+    settle(&mut app);
+    let sent = rpc.sent();
+    assert_eq!(sent.len(), 2);
+    assert_ne!(sent[1].signatures[0], first);
+}
+
+#[test]
+fn read_only_mode_refuses_to_sign() {
+    let rpc = Arc::new(MockRpc::default());
+    let mut app = app_on(&rpc);
+    app.read_only = true;
+    app.wallet.keypair = None;
+
+    app.selected_menu_item = MENU_ITEMS
+        .iter()
+        .position(|(_, state)| *state == AppState::Send)
+        .unwrap();
+    app.open_selected_menu_item();
+    assert_eq!(app.state, AppState::Home);
+
+    app.state = AppState::Settings;
+    assert!(!app.action_available(Action::ExportKey));
+    press(&mut app, KeyCode::Char('x'));
+    assert_eq!(app.state, AppState::Settings);
+
+    let refused = app.signer().unwrap_err();
+    assert!(refused.is::<Refused>());
+    assert!(rpc.sent().is_empty());
+}
+
+#[test]
+fn sends_with_the_chosen_priority_fee() {
+    let rpc = Arc::new(MockRpc::default());
+    let mut app = app_on(&rpc);
+    let recipient = Pubkey::new_unique();
+    rpc.set_balance(recipient, LAMPORTS_PER_SOL);
+    rpc.set_balance(app.wallet.address, 2 * LAMPORTS_PER_SOL);
+    rpc.set_prioritization_fees(&[0, 2_000, 3_000]);
+
+    enter_send(&mut app, &recipient, "0.5");
+    assert_eq!(app.send_state.compute_unit_price(), Some(2_000));
+    press(&mut app, KeyCode::Char('p'));
+    assert_eq!(app.send_state.input_mode, SendInputMode::EditingPriorityFee);
+    press(&mut app, KeyCode::Backspace);
+    type_text(&mut app, "5");
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.send_state.input_mode, SendInputMode::Confirming);
+    assert_eq!(app.send_state.compute_unit_price(), Some(2_005));
+
+    press(&mut app, KeyCode::Char('y'));
+    settle(&mut app);
+    let sent = rpc.sent();
+    let [limit, price, ..] = sent[0].message.instructions() else {
+        panic!("Expected the compute budget instructions and the transfer");
+    };
+    for instruction in [limit, price] {
+        assert_eq!(
+            sent[0].message.static_account_keys()[usize::from(instruction.program_id_index)],
+            solana_sdk::compute_budget::ID
+        );
+    }
+    // What the simulation used, with the margin
+    assert_eq!(limit.data[..], [2, 0x1c, 0x02, 0, 0]);
+    assert_eq!(price.data[..], [3, 0xd5, 0x07, 0, 0, 0, 0, 0, 0]);
+}
+
+#[test]
+fn a_separate_fee_payer_pays_and_signs() {
+    let rpc = Arc::new(MockRpc::default());
+    let mut app = app_on(&rpc);
+    let fee_payer = SecretKeypair::from_keypair(Keypair::new());
+    let fee_payer_address = fee_payer.pubkey();
+    app.wallet.fee_payer = Some(Arc::new(fee_payer));
+    let recipient = Pubkey::new_unique();
+    rpc.set_balance(recipient, LAMPORTS_PER_SOL);
+    // Everything but the rent-exempt minimum, with nothing kept for the fee
+    rpc.set_balance(app.wallet.address, LAMPORTS_PER_SOL + MOCK_RENT_MINIMUM);
+
+    enter_send(&mut app, &recipient, "1");
+    assert_eq!(app.send_state.input_mode, SendInputMode::Confirming);
+    press(&mut app, KeyCode::Char('y'));
+    settle(&mut app);
+    let sent = rpc.sent();
+    assert_eq!(sent.len(), 1);
+    let keys = sent[0].message.static_account_keys();
+    assert_eq!(keys[0], fee_payer_address);
+    assert_eq!(keys[1], app.wallet.address);
+    assert_eq!(sent[0].signatures.len(), 2);
+    assert!(sent[0].verify_with_results().iter().all(|&valid| valid));
+}
+
+#[test]
+fn cancelling_sends_nothing() {
+    let rpc = Arc::new(MockRpc::default());
+    let mut app = app_on(&rpc);
+    let recipient = Pubkey::new_unique();
+    rpc.set_balance(recipient, LAMPORTS_PER_SOL);
+
+    enter_send(&mut app, &recipient, "0.5");
+    press(&mut app, KeyCode::Char('n'));
+    assert_eq!(app.send_state.input_mode, SendInputMode::EditingAmount);
+    press(&mut app, KeyCode::Esc);
+    assert_eq!(app.send_state.input_mode, SendInputMode::EditingRecipient);
+    press(&mut app, KeyCode::Esc);
+    assert_ne!(app.state, AppState::Send);
+    assert!(!app.busy());
+    assert!(rpc.sent().is_empty());
+}
+
+#[test]
+fn bumped_amounts_are_reviewed_again() {
+    let rpc = Arc::new(MockRpc::default());
+    let mut app = app_on(&rpc);
+    app.config.limits.per_transaction = Some(0.0005);
+    app.config.limits.restricted = true;
+    let recipient = Pubkey::new_unique();
+
+    // Within the limit, but below the new account's rent-exempt minimum
+    enter_send(&mut app, &recipient, "0.0001");
+    assert_eq!(app.send_state.rent_exempt_minimum, Some(MOCK_RENT_MINIMUM));
+
+    // The minimum is over the limit, so it doesn't get to the confirmation
+    press(&mut app, KeyCode::Char('b'));
+    assert_eq!(app.send_state.input_mode, SendInputMode::EditingAmount);
+    assert!(app.send_state.error.is_some());
+    press(&mut app, KeyCode::Char('y'));
+    settle(&mut app);
+    assert!(rpc.sent().is_empty());
+}
+
+#[test]
+fn failed_send_can_be_retried() {
+    let rpc = Arc::new(MockRpc::default());
+    let mut app = app_on(&rpc);
+    let recipient = Pubkey::new_unique();
+
+    // The recipient doesn't exist, so a small amount is flagged
+    enter_send(&mut app, &recipient, "0.0001");
+    assert_eq!(app.send_state.rent_exempt_minimum, Some(MOCK_RENT_MINIMUM));
+
+    rpc.fail("get_latest_blockhash");
+    press(&mut app, KeyCode::Char('y'));
+    settle(&mut app);
+    assert_eq!(app.state, AppState::Send);
+    let error = app.send_state.error.as_ref().expect("send error");
+    assert_eq!(error.retry, Some(Retry::Send));
+    assert!(rpc.sent().is_empty());
+
+    rpc.recover("get_latest_blockhash");
+    app.retry();
+    settle(&mut app);
+    assert_eq!(rpc.sent().len(), 1);
+    assert_eq!(app.state, AppState::Wallet);
+}
+
+#[test]
+fn sends_that_may_have_landed_are_not_retried() {
+    let rpc = Arc::new(MockRpc::default());
+    let mut app = app_on(&rpc);
+    let recipient = Pubkey::new_unique();
+    rpc.set_balance(recipient, LAMPORTS_PER_SOL);
+
+    enter_send(&mut app, &recipient, "0.5");
+    rpc.fail("send_transaction_with_config");
+    press(&mut app, KeyCode::Char('y'));
+    settle(&mut app);
+    let error = app.send_state.error.as_ref().expect("send error");
+    assert_eq!(error.retry, None);
+    assert_eq!(app.last_tx_signature, None);
+}
+
+#[test]
+fn refreshes_the_balance() {
+    let rpc = Arc::new(MockRpc::default());
+    let mut app = app_on(&rpc);
+    rpc.set_balance(app.wallet.address, 3 * LAMPORTS_PER_SOL / 2);
+
+    app.refresh_balance(true);
+    settle(&mut app);
+    assert_eq!(app.wallet.lamports, 1_500_000_000);
+    assert!(app.balance_error.is_none());
+
+    rpc.fail("get_balance");
+    rpc.set_balance(app.wallet.address, 0);
+    app.refresh_balance(true);
+    settle(&mut app);
+    // The last known balance stays up with the error
+    assert_eq!(app.wallet.lamports, 1_500_000_000);
+    let error = app.balance_error.as_ref().expect("balance error");
+    assert_eq!(error.retry, Some(Retry::RefreshBalance));
+
+    rpc.recover("get_balance");
+    app.state = AppState::Wallet;
+    app.retry();
+    settle(&mut app);
+    assert_eq!(app.wallet.lamports, 0);
+    assert!(app.balance_error.is_none());
+}
+
+#[test]
+fn fetches_what_the_first_screens_need_at_startup() {
+    let rpc = Arc::new(MockRpc::default());
+    let mut app = app_on(&rpc);
+    rpc.set_balance(app.wallet.address, 2 * LAMPORTS_PER_SOL);
+    let rpc_client: Arc<dyn RpcApi> = rpc.clone();
+    app.start(Startup::spawn(&rpc_client, app.wallet.address));
+    settle(&mut app);
+    assert_eq!(app.wallet.lamports, 2 * LAMPORTS_PER_SOL);
+    assert_eq!(
+        app.cluster_id().unwrap(),
+        rpc.get_genesis_hash().unwrap().to_string()
+    );
+    assert!(app.cluster_task.is_none());
+    // Held until the Transactions screen opens
+    assert!(app.history_head.is_some());
+}
+
+#[test]
+fn the_cluster_is_only_known_by_its_genesis_hash() {
+    let rpc = Arc::new(MockRpc::default());
+    let mut app = app_on(&rpc);
+    rpc.fail("get_genesis_hash");
+    // Not the RPC URL in its place, which may hold an API key
+    assert!(app.cluster_id().is_err());
+    assert!(app.cluster_id.is_none());
+    // A custom RPC could be mainnet, so large sends are retyped until it's
+    // known not to be
+    assert!(app.is_mainnet());
+    assert!(app.mainnet.is_none());
+    rpc.recover("get_genesis_hash");
+    assert!(!app.is_mainnet());
+    assert_eq!(app.mainnet, Some(false));
+    assert_eq!(
+        app.cluster_id().unwrap(),
+        rpc.get_genesis_hash().unwrap().to_string()
+    );
+}
+
+#[test]
+fn opens_the_log_at_its_newest_line() {
+    let rpc = Arc::new(MockRpc::default());
+    let mut app = app_on(&rpc);
+    press(&mut app, KeyCode::Char('L'));
+    assert_eq!(app.key_context(), KeyContext::Logs);
+    // As drawn with five lines more than fit
+    app.scroll_limit.set(5);
+    press(&mut app, KeyCode::Up);
+    assert_eq!(app.log_view, Some(4));
+    press(&mut app, KeyCode::Esc);
+    assert_eq!(app.log_view, None);
+    assert_eq!(app.key_context(), KeyContext::Home);
+}
+
+#[test]
+fn restores_the_screen_it_was_left_on() {
+    let rpc = Arc::new(MockRpc::default());
+    let mut app = app_on(&rpc);
+    app.state = AppState::SignIn;
+    app.content_scroll = 4;
+    app.save_session().unwrap();
+    assert_eq!(app.session.screen.as_deref(), Some("sign_in"));
+    let saved = &app.session.wallets[&app.wallet.address.to_string()];
+    assert_eq!(saved.account, None);
+
+    let mut restored = app_on(&rpc);
+    restored.restore(app.session.clone());
+    assert_eq!(restored.state, AppState::SignIn);
+    assert_eq!(restored.menu()[restored.selected_menu_item].0, "Sign In");
+    assert_eq!(restored.content_scroll, 4);
+
+    app.config.session.restore = false;
+    app.state = AppState::Wallet;
+    app.save_session().unwrap();
+    assert_eq!(app.session.screen.as_deref(), Some("sign_in"));
+}
+
+#[test]
+fn changes_settings_and_saves_them() {
+    let rpc = Arc::new(MockRpc::default());
+    let mut app = app_on(&rpc);
+    let path = std::env::temp_dir().join(format!("solace-test-{}.toml", Pubkey::new_unique()));
+    std::fs::write(&path, "# My settings\n[refresh]\nseconds = 30\n").unwrap();
+    app.config_path = Some(path.clone());
+    app.state = AppState::Settings;
+
+    // Picked from a list: each Enter moves on to the next choice
+    (0..3).for_each(|_| press(&mut app, KeyCode::Down));
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.config.theme.preset, ThemePreset::Light);
+    assert_eq!(app.theme, Theme::preset(ThemePreset::Light));
+
+    press(&mut app, KeyCode::Down);
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.key_context(), KeyContext::Prompt);
+    (0..2).for_each(|_| press(&mut app, KeyCode::Backspace));
+    type_text(&mut app, "45");
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.config.refresh.seconds, 45);
+
+    press(&mut app, KeyCode::Enter);
+    type_text(&mut app, "s");
+    press(&mut app, KeyCode::Enter);
+    assert!(app.settings.error.is_some());
+    assert_eq!(app.config.refresh.seconds, 45);
+
+    let saved = std::fs::read_to_string(&path).unwrap();
+    let loaded = Config::load(Some(&path));
+    std::fs::remove_file(&path).unwrap();
+    assert!(saved.starts_with("# My settings\n"));
+    let loaded = loaded.unwrap();
+    assert_eq!(loaded.refresh.seconds, 45);
+    assert_eq!(loaded.theme.preset, ThemePreset::Light);
+}
+
+#[test]
+fn sets_price_alerts_in_settings() {
+    let rpc = Arc::new(MockRpc::default());
+    let mut app = app_on(&rpc);
+    app.config.price_alerts.url = "http://127.0.0.1:9/price".to_string();
+    let path = std::env::temp_dir().join(format!("solace-test-{}.toml", Pubkey::new_unique()));
+    app.config_path = Some(path.clone());
+    app.state = AppState::Settings;
+    assert!(app.prices.is_none());
+
+    (0..7).for_each(|_| press(&mut app, KeyCode::Down));
+    press(&mut app, KeyCode::Enter);
+    type_text(&mut app, "250");
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.config.price_alerts.sol_above, Some(250.0));
+    assert!(app.prices.is_some());
+    let saved = Config::load(Some(&path)).unwrap();
+    assert_eq!(saved.price_alerts.sol_above, Some(250.0));
+
+    press(&mut app, KeyCode::Enter);
+    type_text(&mut app, "x");
+    press(&mut app, KeyCode::Enter);
+    assert!(app.settings.error.is_some());
+    assert_eq!(app.config.price_alerts.sol_above, Some(250.0));
+
+    // An empty price turns the alert off
+    press(&mut app, KeyCode::Enter);
+    (0..3).for_each(|_| press(&mut app, KeyCode::Backspace));
+    press(&mut app, KeyCode::Enter);
+    let saved = Config::load(Some(&path));
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(app.config.price_alerts.sol_above, None);
+    assert!(app.prices.is_none());
+    assert_eq!(saved.unwrap().price_alerts.sol_above, None);
+}
+
+#[test]
+fn switches_to_another_keypair_file() {
+    let rpc = Arc::new(MockRpc::default());
+    let mut app = app_on(&rpc);
+    settle(&mut app);
+    let keypair = Keypair::new();
+    let path = std::env::temp_dir().join(format!("solace-test-{}.json", keypair.pubkey()));
+    std::fs::write(&path, format!("{:?}", &keypair.to_bytes()[..])).unwrap();
+    rpc.set_balance(keypair.pubkey(), 3 * LAMPORTS_PER_SOL);
+    app.state = AppState::Settings;
+    app.last_tx_signature = Some(Signature::default());
+
+    press(&mut app, KeyCode::Char('w'));
+    type_text(&mut app, "-");
+    press(&mut app, KeyCode::Enter);
+    assert!(app.settings.error.is_some());
+    assert_ne!(app.wallet.address, keypair.pubkey());
+
+    press(&mut app, KeyCode::Char('w'));
+    type_text(&mut app, &path.display().to_string());
+    press(&mut app, KeyCode::Enter);
+    settle(&mut app);
+    std::fs::remove_file(&path).unwrap();
+    assert!(app.settings.error.is_none());
+    assert_eq!(app.wallet.address, keypair.pubkey());
+    assert_eq!(app.wallet.keypair_source, KeypairSource::File(path));
+    assert_eq!(app.wallet.lamports, 3 * LAMPORTS_PER_SOL);
+    assert_eq!(app.last_tx_signature, None);
+}
+
+#[test]
+fn names_the_wallet() {
+    let rpc = Arc::new(MockRpc::default());
+    let mut app = app_on(&rpc);
+    let path = std::env::temp_dir().join(format!("solace-test-{}.toml", Pubkey::new_unique()));
+    app.config_path = Some(path.clone());
+    app.state = AppState::Settings;
+
+    press(&mut app, KeyCode::Char('n'));
+    type_text(&mut app, "Ops Treasury");
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.wallet_name(), Some("Ops Treasury"));
+    let saved = Config::load(Some(&path)).unwrap();
+    assert_eq!(saved.wallet_name(&app.wallet.address), Some("Ops Treasury"));
+    assert_eq!(file_name_part("Ops Treasury"), "ops-treasury");
+
+    // Starts from the name, so clearing it takes it away again
+    press(&mut app, KeyCode::Char('n'));
+    (0..12).for_each(|_| press(&mut app, KeyCode::Backspace));
+    press(&mut app, KeyCode::Enter);
+    let saved = Config::load(Some(&path));
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(app.wallet_name(), None);
+    assert!(saved.unwrap().names.is_empty());
+}
+
+#[test]
+fn watches_addresses_and_saves_them() {
+    let rpc = Arc::new(MockRpc::default());
+    let mut app = app_on(&rpc);
+    let path = std::env::temp_dir().join(format!("solace-test-{}.toml", Pubkey::new_unique()));
+    app.config_path = Some(path.clone());
+    let deposit = Pubkey::new_unique();
+    rpc.set_balance(deposit, 2 * LAMPORTS_PER_SOL);
+    app.selected_menu_item = app
+        .menu()
+        .iter()
+        .position(|&(_, state)| state == AppState::Watch)
+        .unwrap();
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.key_context(), KeyContext::Watch);
+
+    press(&mut app, KeyCode::Char('a'));
+    type_text(&mut app, "exchange");
+    press(&mut app, KeyCode::Enter);
+    assert!(app.watch.error.is_some());
+
+    press(&mut app, KeyCode::Char('a'));
+    type_text(&mut app, &deposit.to_string());
+    press(&mut app, KeyCode::Enter);
+    settle(&mut app);
+    assert!(app.watch.error.is_none());
+    assert_eq!(app.watch.watched[0].balance.lamports, 2 * LAMPORTS_PER_SOL);
+    let saved = Config::load(Some(&path)).unwrap();
+    assert_eq!(saved.watch.addresses, [deposit.to_string()]);
+
+    press(&mut app, KeyCode::Char('d'));
+    let saved = Config::load(Some(&path));
+    std::fs::remove_file(&path).unwrap();
+    assert!(app.watch.addresses.is_empty());
+    assert!(saved.unwrap().watch.addresses.is_empty());
+}
+
+#[test]
+fn requests_a_payment_on_receive() {
+    let rpc = Arc::new(MockRpc::default());
+    let mut app = app_on(&rpc);
+    let path = std::env::temp_dir().join(format!("solace-test-{}.sqlite3", Pubkey::new_unique()));
+    app.history_db = Some(HistoryDb::open_at(&path).unwrap());
+    app.state = AppState::Receive;
+
+    press(&mut app, KeyCode::Char('p'));
+    assert_eq!(app.key_context(), KeyContext::Prompt);
+    type_text(&mut app, "0");
+    press(&mut app, KeyCode::Enter);
+    assert!(app.receive.error.is_some());
+    assert!(app.receive.request.is_none());
+
+    press(&mut app, KeyCode::Char('p'));
+    type_text(&mut app, "1.5");
+    press(&mut app, KeyCode::Enter);
+    let request = app.receive.request.clone().unwrap();
+    assert_eq!(request.lamports, 1_500_000_000);
+    assert_eq!(app.receive.payload(&app.wallet.address), request.uri());
+
+    // Nothing has the reference yet
+    app.on_tick();
+    assert!(app.receive.task.is_some());
+    settle(&mut app);
+    assert!(app.receive.paid.is_none());
+    assert!(app.receive.error.is_none());
+
+    press(&mut app, KeyCode::Esc);
+    assert_eq!(app.state, AppState::Receive);
+    assert!(app.receive.request.is_none());
+    press(&mut app, KeyCode::Esc);
+    assert_eq!(app.state, AppState::Wallet);
+
+    // Kept under Requests, and checked again there
+    app.selected_menu_item = app
+        .menu()
+        .iter()
+        .position(|&(_, state)| state == AppState::Requests)
+        .unwrap();
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.key_context(), KeyContext::Requests);
+    assert_eq!(app.requests.requests.len(), 1);
+    assert_eq!(app.requests.requests[0].request, request);
+    assert!(app.requests.task.is_some());
+    settle(&mut app);
+    assert!(app.requests.requests[0].signature.is_none());
+    assert!(app.requests.error.is_none());
+
+    app.record_payment(&request, "paid");
+    app.open_selected_menu_item();
+    let saved = &app.requests.requests[0];
+    assert_eq!(saved.signature.as_deref(), Some("paid"));
+    assert!(app.requests.task.is_none());
+
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.state, AppState::Receive);
+    assert_eq!(app.receive.request.as_ref(), Some(&request));
+    assert_eq!(app.receive.paid.as_deref(), Some("paid"));
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn sums_profiles_on_the_dashboard_and_opens_them() {
+    let rpc = Arc::new(MockRpc::default());
+    let mut app = app_on(&rpc);
+    let keypair = Keypair::new();
+    let path = std::env::temp_dir().join(format!("solace-test-{}.json", keypair.pubkey()));
+    std::fs::write(&path, format!("{:?}", &keypair.to_bytes()[..])).unwrap();
+    rpc.set_balance(app.wallet.address, LAMPORTS_PER_SOL);
+    rpc.set_balance(keypair.pubkey(), 3 * LAMPORTS_PER_SOL);
+    app.config.profiles = vec![config::ProfileConfig {
+        keypair: path.clone(),
+    }];
+    assert_eq!(app.key_context(), KeyContext::Dashboard);
+
+    press(&mut app, KeyCode::Char('r'));
+    settle(&mut app);
+    let balances = app.dashboard.balances.as_ref().unwrap();
+    assert_eq!(balances.wallets.len(), 2);
+    assert_eq!(balances.total_lamports(), 4 * LAMPORTS_PER_SOL);
+
+    press(&mut app, KeyCode::Down);
+    press(&mut app, KeyCode::Enter);
+    settle(&mut app);
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(app.state, AppState::Wallet);
+    assert_eq!(app.wallet.address, keypair.pubkey());
+    assert_eq!(app.wallet.lamports, 3 * LAMPORTS_PER_SOL);
+}
+
+#[test]
+fn refreshes_the_open_screen_on_its_interval() {
+    let rpc = Arc::new(MockRpc::default());
+    let mut app = app_on(&rpc);
+    app.config.refresh.screens.insert("sign_in".to_string(), 0);
+    app.config
+        .refresh
+        .screens
+        .insert("transactions".to_string(), 120);
+    let refreshed = |app: &mut App, state, ago| {
+        app.state = state;
+        app.balance_task = None;
+        app.last_refresh = Instant::now() - Duration::from_secs(ago);
+        app.on_tick();
+        app.balance_task.is_some()
+    };
+
+    assert!(refreshed(&mut app, AppState::Wallet, 30));
+    assert!(!refreshed(&mut app, AppState::Wallet, 10));
+    assert!(!refreshed(&mut app, AppState::SignIn, 600));
+    assert!(!refreshed(&mut app, AppState::Transactions, 60));
+    assert!(refreshed(&mut app, AppState::Transactions, 120));
+
+    app.config.refresh.seconds = 0;
+    assert!(!refreshed(&mut app, AppState::Wallet, 600));
+}
+
+#[test]
+fn signed_messages_verify() {
+    let rpc = Arc::new(MockRpc::default());
+    let mut app = app_on(&rpc);
+    app.state = AppState::SignMessage;
+    type_text(&mut app, "gm");
+    press(&mut app, KeyCode::Enter);
+    let signature = app.sign_state.signature.expect("signed");
+
+    app.state = AppState::VerifyMessage;
+    for field in [
+        "gm",
+        &app.wallet.address.to_string(),
+        &signature.to_string(),
+    ] {
+        type_text(&mut app, field);
+        press(&mut app, KeyCode::Enter);
+    }
+    assert_eq!(app.verify_state.verified, Some(app.wallet.address));
+
+    press(&mut app, KeyCode::Esc);
+    press(&mut app, KeyCode::Esc);
+    press(&mut app, KeyCode::Char('!'));
+    assert_eq!(app.verify_state.verified, None);
+    for _ in 0..3 {
+        press(&mut app, KeyCode::Enter);
+    }
+    assert!(app.verify_state.error.is_some());
+}
+
+#[test]
+fn sign_in_is_reviewed_before_signing() {
+    let rpc = Arc::new(MockRpc::default());
+    let mut app = app_on(&rpc);
+    app.state = AppState::SignIn;
+    type_text(&mut app, r#"{"domain": "example.com", "nonce": "1"}"#);
+    press(&mut app, KeyCode::Enter);
+    assert!(app.sign_in_state.request.is_some());
+    assert!(app.sign_in_state.response.is_none());
+    press(&mut app, KeyCode::Enter);
+    let response = app.sign_in_state.response.as_deref().expect("signed");
+    assert!(response.contains(&app.wallet.address.to_string()));
+
+    // Requests for another wallet don't get as far as the review
+    press(&mut app, KeyCode::Esc);
+    app.sign_in_state.input.set(format!(
+        r#"{{"domain": "example.com", "address": "{}"}}"#,
+        Pubkey::new_unique()
+    ));
+    press(&mut app, KeyCode::Enter);
+    assert!(app.sign_in_state.request.is_none());
+    assert!(app.sign_in_state.error.is_some());
+}
+
+#[test]
+fn inspected_transactions_are_reviewed_before_sending() {
+    use base64::Engine;
+
+    let rpc = Arc::new(MockRpc::default());
+    let mut app = app_on(&rpc);
+    let keypair = app.wallet.keypair.clone().unwrap();
+    let recipient = Pubkey::new_unique();
+    let mut transaction =
+        wallet::sign_transfer(&*rpc, &keypair, &app.wallet.address, &recipient, 1_000).unwrap();
+    transaction.signatures = vec![Signature::default()];
+    let encoded =
+        base64::engine::general_purpose::STANDARD.encode(bincode::serialize(&transaction).unwrap());
+
+    app.state = AppState::InspectTx;
+    type_text(&mut app, &encoded);
+    press(&mut app, KeyCode::Enter);
+    let inspection = app.inspect_state.inspection.as_ref().expect("decoded");
+    assert_eq!(inspection.fee_payer(), Some(app.wallet.address));
+    // The compute unit limit and the transfer
+    assert_eq!(inspection.instructions.len(), 2);
+    assert!(rpc.sent().is_empty());
+
+    press(&mut app, KeyCode::Enter);
+    settle(&mut app);
+    let sent = rpc.sent();
+    assert_eq!(sent.len(), 1);
+    assert!(sent[0].verify_with_results()[0]);
+    assert_eq!(app.state, AppState::Wallet);
+
+    app.state = AppState::InspectTx;
+    type_text(&mut app, "not a transaction");
+    press(&mut app, KeyCode::Enter);
+    assert!(app.inspect_state.inspection.is_none());
+    assert!(app.inspect_state.error.is_some());
+}
+
+#[test]
+fn transactions_other_signers_sign_are_passed_on() {
+    #[allow(deprecated)]
+    use solana_sdk::system_instruction;
+    use solana_sdk::{
+        hash::Hash,
+        message::{Message, VersionedMessage},
+        signature::Keypair,
+    };
+
+    let rpc = Arc::new(MockRpc::default());
+    let mut app = app_on(&rpc);
+    let other = Keypair::new();
+    let recipient = Pubkey::new_unique();
+    let instructions = [
+        system_instruction::transfer(&app.wallet.address, &recipient, 1_000),
+        system_instruction::transfer(&other.pubkey(), &recipient, 1_000),
+    ];
+    let message =
+        Message::new_with_blockhash(&instructions, Some(&app.wallet.address), &Hash::default());
+    let transaction = VersionedTransaction {
+        signatures: vec![Signature::default(); 2],
+        message: VersionedMessage::Legacy(message),
+    };
+
+    app.state = AppState::InspectTx;
+    type_text(&mut app, &inspector::encode(&transaction).unwrap());
+    press(&mut app, KeyCode::Enter);
+    press(&mut app, KeyCode::Enter);
+    let (encoded, missing) = app.inspect_state.partially_signed.clone().expect("signed");
+    assert_eq!(missing, [other.pubkey()]);
+    assert!(rpc.sent().is_empty());
+
+    // Their copy, back with both signatures
+    let theirs = inspector::sign(&inspector::decode(&encoded).unwrap(), &other).unwrap();
+    press(&mut app, KeyCode::Esc);
+    app.inspect_state.input = TextInput::default();
+    type_text(&mut app, &inspector::encode(&theirs).unwrap());
+    press(&mut app, KeyCode::Enter);
+    press(&mut app, KeyCode::Enter);
+    settle(&mut app);
+    assert_eq!(rpc.sent(), [theirs]);
+}
+
+#[test]
+fn looks_up_any_account() {
+    let rpc = Arc::new(MockRpc::default());
+    let mut app = app_on(&rpc);
+    let address = Pubkey::new_unique();
+    rpc.set_balance(address, LAMPORTS_PER_SOL);
+
+    app.state = AppState::Lookup;
+    type_text(&mut app, &address.to_string());
+    press(&mut app, KeyCode::Enter);
+    let Some(LookupResult::Account(_, Some(ref info))) = app.lookup_state.result else {
+        panic!("no account looked up");
+    };
+    assert_eq!(info.account.lamports, LAMPORTS_PER_SOL);
+    assert_eq!(info.rent_exempt(), Some(true));
+
+    // Tab moves across the address and the owner, wrapping around
+    let fields = app.lookup_state.result.as_ref().unwrap().fields();
+    assert_eq!(
+        fields,
+        [address.to_string(), info.account.owner.to_string()]
+    );
+    press(&mut app, KeyCode::Tab);
+    assert_eq!(app.lookup_state.field, 1);
+    press(&mut app, KeyCode::Tab);
+    assert_eq!(app.lookup_state.field, 0);
+    press(&mut app, KeyCode::BackTab);
+    assert_eq!(app.lookup_state.field, 1);
+
+    rpc.fail("get_account_with_commitment");
+    press(&mut app, KeyCode::Enter);
+    let error = app.lookup_state.error.as_ref().expect("lookup error");
+    assert_eq!(error.retry, Some(Retry::LookupAccount));
+    rpc.recover("get_account_with_commitment");
+    app.lookup_state.input.set(Pubkey::new_unique().to_string());
+    app.retry();
+    assert!(app.lookup_state.error.is_none());
+    assert!(matches!(
+        app.lookup_state.result,
+        Some(LookupResult::Account(_, None))
+    ));
+
+    // A number is a data length to price
+    app.lookup_state.input.set("165");
+    press(&mut app, KeyCode::Enter);
+    let Some(LookupResult::Rent { minimum, .. }) = app.lookup_state.result else {
+        panic!("no rent calculated");
+    };
+    assert_eq!(minimum, MOCK_RENT_MINIMUM);
+}
+
+#[test]
+fn creates_nonce_accounts_after_confirmation() {
+    let rpc = Arc::new(MockRpc::default());
+    let mut app = app_on(&rpc);
+    rpc.set_balance(app.wallet.address, LAMPORTS_PER_SOL);
+    app.selected_menu_item = MENU_ITEMS
+        .iter()
+        .position(|&(_, state)| state == AppState::Nonce)
+        .unwrap();
+    app.open_selected_menu_item();
+    assert_eq!(app.key_context(), KeyContext::Nonces);
+    assert!(app.nonce_state.accounts.is_empty());
+    assert_eq!(app.nonce_state.free, Some(0));
+
+    press(&mut app, KeyCode::Char('c'));
+    assert_eq!(
+        app.nonce_state.step,
+        NonceStep::Confirming(NonceAction::Create(MOCK_RENT_MINIMUM))
+    );
+    press(&mut app, KeyCode::Char('n'));
+    assert_eq!(app.nonce_state.step, NonceStep::List);
+    assert!(rpc.sent().is_empty());
+
+    press(&mut app, KeyCode::Char('c'));
+    press(&mut app, KeyCode::Char('y'));
+    settle(&mut app);
+    assert!(app.nonce_state.error.is_none());
+    let sent = rpc.sent();
+    assert_eq!(sent.len(), 1);
+    assert_eq!(
+        sent[0].message.static_account_keys()[1],
+        nonce::address(&app.wallet.address, 0).unwrap()
+    );
+    // Creating and initializing the account
+    assert_eq!(sent[0].message.instructions().len(), 2);
+
+    // Nothing to advance or withdraw from yet
+    press(&mut app, KeyCode::Char('w'));
+    assert_eq!(app.nonce_state.step, NonceStep::List);
+}
+
+#[cfg(feature = "epoch-plugin")]
+#[test]
+fn plugin_screens_open_from_the_menu() {
+    let rpc = Arc::new(MockRpc::default());
+    let mut app = app_on(&rpc);
+    for _ in 0..MENU_ITEMS.len() {
+        press(&mut app, KeyCode::Down);
+    }
+    assert_eq!(app.menu()[app.selected_menu_item].0, "Epoch");
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.state, AppState::Plugin(0));
+    assert_eq!(app.key_context(), KeyContext::Plugin);
+    settle(&mut app);
+    assert!(app.failure().is_none());
+
+    rpc.fail("get_epoch_info");
+    press(&mut app, KeyCode::Char('r'));
+    settle(&mut app);
+    assert_eq!(app.failure().and_then(|f| f.retry), Some(Retry::Plugin));
+
+    rpc.recover("get_epoch_info");
+    app.retry();
+    settle(&mut app);
+    assert!(app.failure().is_none());
+    press(&mut app, KeyCode::Esc);
+    assert_eq!(app.state, AppState::Wallet);
+}
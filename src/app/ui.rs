@@ -0,0 +1,2638 @@
+//! Drawing the screens
+
+use super::{
+    input::history_rows,
+    send::{SendInputMode, BLOCKLIST_ACK_WORD},
+    state::{
+        ExportKind, ExportStep, ImportStep, LookupResult, NonceAction, NonceStep, Setting,
+        SettingsInput, VanityStep, VerifyStep, EXPORT_ACK_WORD,
+    },
+    App, AppState, HistoryFetch,
+};
+use crate::hyperlink;
+use ratatui::{
+    layout::{Alignment, Constraint, Direction, Flex, Layout, Margin, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{
+        Block, Borders, Clear, List, ListItem, Paragraph, Scrollbar, ScrollbarOrientation,
+        ScrollbarState, Wrap,
+    },
+    Frame,
+};
+use solace::{
+    accountinfo,
+    amount::AmountFormat,
+    dashboard::TokenBalance,
+    history::{self, Clock, HistoryEntry, HistoryFilter, ListRow, TxInspection, TxKind},
+    i18n::{tr, tr_fmt},
+    keymap::Keymap,
+    pay::{self, RequestStatus},
+    priority, qr,
+    report::ReportFormat,
+    sender::{self, Landed, Status, Tracker},
+    task::Task,
+    theme::Theme,
+};
+use solana_sdk::{pubkey::Pubkey, signature::Signer};
+use std::cell::Cell;
+
+pub(super) fn ui(f: &mut Frame, app: &App) {
+    let [body, footer] = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(1)])
+        .areas(f.area());
+
+    // Nothing from the wallet is shown while locked
+    if app.session_lock.is_locked() {
+        f.render_widget(render_lock(app), body);
+        f.render_widget(render_footer(app), footer);
+        return;
+    }
+
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(30), Constraint::Percentage(70)].as_ref())
+        .split(body);
+
+    let menu_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Min(0)].as_ref())
+        .split(chunks[0]);
+
+    let mut title = if app.plain.ascii {
+        "SOLACE".to_string()
+    } else {
+        "☀️ SOLACE".to_string()
+    };
+    if let Some(name) = app.wallet_name() {
+        title.push_str(&format!(" - {}", name));
+    }
+    let mut title = vec![Span::raw(title)];
+    let banners = [(sender::dry_run(), "DRY RUN"), (app.read_only, "READ-ONLY")];
+    for (_, banner) in banners.into_iter().filter(|(shown, _)| *shown) {
+        title.push(Span::raw(" "));
+        title.push(Span::styled(
+            tr(banner),
+            Style::default()
+                .fg(app.theme.error)
+                .add_modifier(Modifier::REVERSED),
+        ));
+    }
+    // Colored by cluster, so a mainnet session can't pass for a devnet one
+    let cluster = app.cluster();
+    let border = Style::default().fg(app.theme.cluster(&cluster));
+    let title = Paragraph::new(Line::from(title))
+        .style(
+            Style::default()
+                .fg(app.theme.heading)
+                .add_modifier(Modifier::BOLD),
+        )
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(border)
+                .title(Span::styled(
+                    format!(" {} ", cluster.name().to_uppercase()),
+                    border.add_modifier(Modifier::BOLD | Modifier::REVERSED),
+                )),
+        );
+    f.render_widget(title, menu_chunks[0]);
+
+    let menu: Vec<ListItem> = app
+        .menu()
+        .into_iter()
+        .enumerate()
+        .map(|(i, (item, state))| {
+            // Plugins name their own screens
+            let item = match state {
+                AppState::Plugin(_) => item,
+                _ => tr(item),
+            };
+            let style = if i == app.selected_menu_item {
+                Style::default()
+                    .fg(app.theme.accent)
+                    .add_modifier(Modifier::BOLD)
+            } else if app.read_only && state.signs() {
+                Style::default().fg(app.theme.muted)
+            } else {
+                Style::default().fg(app.theme.text)
+            };
+            ListItem::new(Line::from(Span::styled(item.to_string(), style)))
+        })
+        .collect();
+
+    let menu_list = List::new(menu).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(border)
+            .title(tr("Menu")),
+    );
+    f.render_widget(menu_list, menu_chunks[1]);
+
+    let area = chunks[1];
+    app.menu_area.set(menu_chunks[1]);
+    app.content_area.set(area);
+    let content = match app.state {
+        AppState::Home if app.has_dashboard() => Some(render_dashboard(app)),
+        AppState::Home => Some(render_home(&app.theme, app.plain.ascii)),
+        AppState::Wallet => Some(render_wallet(app)),
+        AppState::Send => Some(render_send(app)),
+        AppState::Receive => Some(render_receive(app, area)),
+        AppState::Transactions => {
+            // The list only builds the rows that fit, and the details view
+            // scrolls itself
+            f.render_widget(render_transactions(app, area.height), area);
+            match app.history.inspection {
+                Some(ref inspection) => {
+                    let limit = app.scroll_limit.get();
+                    render_scrollbar(
+                        f,
+                        area,
+                        usize::from(limit) + 1,
+                        usize::from(inspection.scroll.min(limit)),
+                    );
+                }
+                None => {
+                    render_scrollbar(f, area, app.history.visible().len(), app.history.selected)
+                }
+            }
+            None
+        }
+        AppState::Accounts => Some(render_accounts(app)),
+        AppState::Watch => Some(render_watch(app)),
+        AppState::Requests => Some(render_requests(app)),
+        AppState::Vanity => Some(render_vanity(app)),
+        AppState::Settings => Some(render_settings(app)),
+        AppState::Export => Some(render_export(app)),
+        AppState::Import => Some(render_import(app)),
+        AppState::SignMessage => Some(render_sign(app)),
+        AppState::VerifyMessage => Some(render_verify(app)),
+        AppState::SignIn => Some(render_sign_in(app)),
+        AppState::InspectTx => Some(render_inspect(app)),
+        AppState::Lookup => Some(render_lookup(app)),
+        AppState::Nonce => Some(render_nonce(app)),
+        AppState::Plugin(index) => {
+            if let Some(plugin) = app.plugins.get(index) {
+                plugin.render(f, area, &app.plugin_context());
+            }
+            None
+        }
+    };
+    if let Some(content) = content {
+        let lines = u16::try_from(content.line_count(area.width)).unwrap_or(u16::MAX);
+        let limit = lines.saturating_sub(area.height);
+        app.content_limit.set(limit);
+        let scroll = app.content_scroll.min(limit);
+        f.render_widget(content.scroll((scroll, 0)), area);
+        render_scrollbar(f, area, usize::from(limit) + 1, usize::from(scroll));
+    }
+
+    if app.hyperlinks {
+        for (text, url) in app.links() {
+            hyperlink::apply(f.buffer_mut(), chunks[1], &text, &url);
+        }
+    }
+    if app.state == AppState::Receive && app.receive.fullscreen {
+        f.render_widget(Clear, body);
+        f.render_widget(render_fullscreen_qr(app, body), body);
+    }
+    if let Some(scroll) = app.help.or(app.log_view).or(app.audit_view) {
+        let [area] = Layout::horizontal([Constraint::Percentage(80)])
+            .flex(Flex::Center)
+            .areas(body);
+        let [area] = Layout::vertical([Constraint::Percentage(90)])
+            .flex(Flex::Center)
+            .areas(area);
+        f.render_widget(Clear, area);
+        let overlay = if app.help.is_some() {
+            render_help(
+                &app.keymap,
+                &app.theme,
+                scroll,
+                area.height,
+                &app.scroll_limit,
+            )
+        } else if app.log_view.is_some() {
+            render_logs(
+                app.log_buffer.lines(),
+                &app.theme,
+                scroll,
+                area.height,
+                &app.scroll_limit,
+            )
+        } else {
+            let limit = max_scroll(app.audit_lines.len(), area.height);
+            app.scroll_limit.set(limit);
+            Paragraph::new(app.audit_lines.clone())
+                .style(Style::default().fg(app.theme.text))
+                .scroll((scroll.min(limit), 0))
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .title(tr("Audit log")),
+                )
+        };
+        f.render_widget(overlay, area);
+        let limit = app.scroll_limit.get();
+        render_scrollbar(
+            f,
+            area,
+            usize::from(limit) + 1,
+            usize::from(scroll.min(limit)),
+        );
+    }
+
+    f.render_widget(render_footer(app), footer);
+    app.toasts.render(f, body, &app.theme);
+}
+
+/// The receive QR code as large as the screen allows, centered
+fn render_fullscreen_qr(app: &App, area: Rect) -> Paragraph<'static> {
+    let payload = app.receive.payload(&app.wallet.address);
+    let lines = match qr::fit(&payload, area.width, area.height, app.plain.ascii) {
+        Some(qr) => {
+            let qr_style = Style::default().fg(Color::White).bg(Color::Black);
+            let top = area.height.saturating_sub(qr.len() as u16) / 2;
+            std::iter::repeat_n(Line::from(""), usize::from(top))
+                .chain(
+                    qr.into_iter()
+                        .map(|line| Line::from(Span::styled(line, qr_style))),
+                )
+                .collect()
+        }
+        None => vec![Line::from(tr("Enlarge the terminal to show the QR code"))],
+    };
+    Paragraph::new(lines)
+        .style(Style::default().fg(app.theme.text))
+        .alignment(Alignment::Center)
+}
+
+/// Every screen and its keys, straight from the keymap
+fn render_help(
+    keymap: &Keymap,
+    theme: &Theme,
+    scroll: u16,
+    height: u16,
+    scroll_limit: &Cell<u16>,
+) -> Paragraph<'static> {
+    let heading = Style::default()
+        .fg(theme.heading)
+        .add_modifier(Modifier::BOLD);
+    let key_style = Style::default()
+        .fg(theme.accent)
+        .add_modifier(Modifier::BOLD);
+    let summary = Style::default().fg(theme.muted);
+
+    let mut lines = Vec::new();
+    for (context, keys) in keymap.help() {
+        if !lines.is_empty() {
+            lines.push(Line::from(""));
+        }
+        lines.push(Line::from(vec![
+            Span::styled(context.title(), heading),
+            Span::styled(format!(" [{}]", context.name()), summary),
+            Span::styled(format!("  {}", context.summary()), summary),
+        ]));
+        for (keys, action) in keys {
+            lines.push(Line::from(vec![
+                Span::styled(format!("  {:<14}", keys), key_style),
+                Span::raw(action.description()),
+                Span::styled(format!("  {}", action.name()), summary),
+            ]));
+        }
+    }
+
+    let limit = max_scroll(lines.len(), height);
+    scroll_limit.set(limit);
+    Paragraph::new(lines)
+        .style(Style::default().fg(theme.text))
+        .scroll((scroll.min(limit), 0))
+        .block(Block::default().borders(Borders::ALL).title(tr("Keys")))
+}
+
+/// The latest log lines, oldest first, warnings and errors standing out
+fn render_logs(
+    lines: Vec<String>,
+    theme: &Theme,
+    scroll: u16,
+    height: u16,
+    scroll_limit: &Cell<u16>,
+) -> Paragraph<'static> {
+    let lines: Vec<Line> = if lines.is_empty() {
+        vec![Line::from(Span::styled(
+            tr("Nothing logged yet"),
+            Style::default().fg(theme.muted),
+        ))]
+    } else {
+        lines
+            .into_iter()
+            .map(|line| {
+                // After the date and time
+                let color = match line.split_whitespace().nth(2) {
+                    Some("ERROR" | "WARN") => theme.error,
+                    Some("DEBUG" | "TRACE") => theme.muted,
+                    _ => theme.text,
+                };
+                Line::from(Span::styled(line, Style::default().fg(color)))
+            })
+            .collect()
+    };
+
+    let limit = max_scroll(lines.len(), height);
+    scroll_limit.set(limit);
+    Paragraph::new(lines)
+        .style(Style::default().fg(theme.text))
+        .scroll((scroll.min(limit), 0))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(tr("Debug log")),
+        )
+}
+
+/// Draw a scrollbar over the right border of `area` when there is more
+/// than one position to scroll to
+fn render_scrollbar(f: &mut Frame, area: Rect, positions: usize, position: usize) {
+    if positions <= 1 {
+        return;
+    }
+    let mut state = ScrollbarState::new(positions).position(position);
+    f.render_stateful_widget(
+        Scrollbar::new(ScrollbarOrientation::VerticalRight),
+        area.inner(Margin {
+            vertical: 1,
+            horizontal: 0,
+        }),
+        &mut state,
+    );
+}
+
+/// How far `lines` can scroll in a bordered block `height` rows tall
+/// before the last line leaves the bottom
+fn max_scroll(lines: usize, height: u16) -> u16 {
+    let rows = usize::from(height.saturating_sub(2));
+    u16::try_from(lines.saturating_sub(rows)).unwrap_or(u16::MAX)
+}
+
+/// One line listing the keys that work on the current screen
+fn render_footer(app: &App) -> Paragraph<'static> {
+    let key_style = Style::default()
+        .fg(app.theme.accent)
+        .add_modifier(Modifier::BOLD);
+    let hint_style = Style::default().fg(app.theme.muted);
+    let hints = app
+        .keymap
+        .hints(app.key_context(), |action| app.action_available(action));
+
+    let mut spans = Vec::new();
+    if app.throttled() {
+        spans.push(Span::styled(
+            tr("RPC node throttled, retrying… "),
+            Style::default().fg(app.theme.error),
+        ));
+    }
+    for (i, (keys, hint)) in hints.into_iter().enumerate() {
+        if i > 0 {
+            spans.push(Span::styled(" · ", hint_style));
+        }
+        spans.push(Span::styled(keys, key_style));
+        spans.push(Span::styled(format!(" {}", hint), hint_style));
+    }
+    Paragraph::new(Line::from(spans))
+}
+
+/// The priority fee on the Send screen's confirmation, and where it's from
+fn priority_fee_line(app: &App) -> String {
+    let send = &app.send_state;
+    let price = |price| priority::format_price(price, app.plain.ascii);
+    match (send.fee_override, &send.suggested_fee) {
+        (Some(fee), _) => tr_fmt("Priority fee: {} (entered)", &[&price(fee)]),
+        (None, Some(Ok(0))) => tr("Priority fee: none, recent fees were 0").to_string(),
+        (None, Some(Ok(fee))) => tr_fmt(
+            "Priority fee: {} ({}th percentile of recent fees)",
+            &[&price(*fee), &app.config.priority_fee.percentile],
+        ),
+        (None, Some(Err(ref error))) => tr_fmt("Priority fee: none ({})", &[error]),
+        (None, None) => tr("Priority fee: none").to_string(),
+    }
+}
+
+/// A send's progress: the spinner, then the signature and each commitment
+/// level up to the one waited for, with the slot once it's reached
+fn confirmation_lines(app: &App, task: &Task<Landed>, tracker: &Tracker) -> Vec<Line<'static>> {
+    let finalized = app.config.confirm.finalized;
+    let mut lines = vec![Line::from(Span::styled(
+        tr_fmt(
+            if finalized {
+                "{} Sending and waiting for finalization..."
+            } else {
+                "{} Sending and waiting for confirmation..."
+            },
+            &[&task.progress(app.plain.ascii)],
+        ),
+        Style::default()
+            .fg(app.theme.accent)
+            .add_modifier(Modifier::BOLD),
+    ))];
+    let Some((signature, status)) = tracker.get() else {
+        return lines;
+    };
+    lines.push(Line::from(tr_fmt("Signature: {}", &[&signature])));
+    let (done, waiting) = if app.plain.ascii {
+        ("[x]", "[ ]")
+    } else {
+        ("✓", "·")
+    };
+    let levels = if finalized { 3 } else { 2 };
+    for (i, level) in Status::LEVELS.iter().take(levels).enumerate() {
+        lines.push(if status.level() > i {
+            Line::styled(
+                format!(
+                    "  {} {:<10} {}",
+                    done,
+                    level,
+                    tr_fmt("slot {}", &[&status.slot().unwrap_or_default()])
+                ),
+                Style::default().fg(app.theme.success),
+            )
+        } else {
+            Line::styled(
+                format!("  {} {}", waiting, level),
+                Style::default().fg(app.theme.muted),
+            )
+        });
+    }
+    lines
+}
+
+fn render_lock(app: &App) -> Paragraph<'static> {
+    let mut lines = vec![
+        Line::from(""),
+        Line::from(Span::styled(
+            if app.plain.ascii {
+                tr("Session locked").to_string()
+            } else {
+                format!("🔒 {}", tr("Session locked"))
+            },
+            Style::default()
+                .fg(app.theme.heading)
+                .add_modifier(Modifier::BOLD),
+        )),
+        Line::from(""),
+        Line::from(tr("Enter your session PIN to unlock:")),
+        Line::from(Span::styled(
+            format!(
+                "{}{}",
+                "*".repeat(app.session_lock.pin_input.chars().count()),
+                app.plain.cursor()
+            ),
+            Style::default().fg(app.theme.accent),
+        )),
+    ];
+
+    if let Some(ref error) = app.lock_error {
+        lines.push(Line::from(""));
+        lines.extend(error.lines(&app.theme, app.retry_key()));
+    }
+
+    Paragraph::new(lines)
+        .style(Style::default().fg(app.theme.text))
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::ALL).title(tr("Locked")))
+}
+
+fn render_home(theme: &Theme, ascii: bool) -> Paragraph<'static> {
+    Paragraph::new(vec![
+        Line::from(if ascii {
+            tr("Welcome to Solace!").to_string()
+        } else {
+            format!("{} 🚀", tr("Welcome to Solace!"))
+        }),
+        Line::from(""),
+        Line::from(tr("A Solana wallet with a terminal UI")),
+        Line::from(""),
+        Line::from(tr("The keys for each screen are listed at the bottom")),
+    ])
+    .style(Style::default().fg(theme.text))
+    .block(Block::default().borders(Borders::ALL).title(tr("Home")))
+}
+
+fn render_dashboard(app: &App) -> Paragraph<'static> {
+    let dashboard = &app.dashboard;
+    let mut title = vec![Span::raw(tr("Dashboard"))];
+    if let Some(ref task) = dashboard.task {
+        title.push(Span::styled(
+            tr_fmt("  {} refreshing", &[&task.progress(app.plain.ascii)]),
+            Style::default().fg(app.theme.muted),
+        ));
+    }
+    let mut lines = vec![Line::from(title), Line::from("")];
+
+    for (i, &(address, _)) in dashboard.wallets.iter().enumerate() {
+        let balance = dashboard
+            .balances
+            .as_ref()
+            .and_then(|balances| balances.wallets.iter().find(|w| w.address == address));
+        let label = app
+            .config
+            .wallet_name(&address)
+            .map_or_else(|| address.to_string(), str::to_string);
+        let style = if i == dashboard.selected {
+            Style::default()
+                .fg(app.theme.accent)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(app.theme.text)
+        };
+        lines.push(Line::from(Span::styled(
+            balance_row(
+                &app.amounts,
+                if address == app.wallet.address {
+                    "*"
+                } else {
+                    " "
+                },
+                &label,
+                balance.map(|balance| balance.lamports),
+                balance.map_or(&[], |balance| &balance.tokens),
+            ),
+            style,
+        )));
+    }
+
+    if let Some(ref balances) = dashboard.balances {
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(
+            balance_row(
+                &app.amounts,
+                " ",
+                tr("Total"),
+                Some(balances.total_lamports()),
+                &balances.total_tokens(),
+            ),
+            Style::default()
+                .fg(app.theme.heading)
+                .add_modifier(Modifier::BOLD),
+        )));
+    }
+    if !dashboard.wallets.is_empty() {
+        lines.push(Line::from(""));
+        lines.push(Line::from(tr("* open wallet")));
+    }
+
+    if let Some(ref error) = dashboard.error {
+        lines.push(Line::from(""));
+        lines.extend(error.lines(&app.theme, app.retry_key()));
+    }
+
+    Paragraph::new(lines)
+        .style(Style::default().fg(app.theme.text))
+        .block(Block::default().borders(Borders::ALL).title(tr("Home")))
+}
+
+/// A wallet's label, SOL and tokens in columns; `-` for SOL not fetched yet
+fn balance_row(
+    amounts: &AmountFormat,
+    marker: &str,
+    label: &str,
+    lamports: Option<u64>,
+    tokens: &[TokenBalance],
+) -> String {
+    let sol = lamports.map_or("-".to_string(), |lamports| amounts.sol(lamports));
+    let tokens = tokens
+        .iter()
+        .map(|balance| amounts.token(balance))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("{} {:<44} {:>22}  {}", marker, label, sol, tokens)
+}
+
+fn render_watch(app: &App) -> Paragraph<'static> {
+    let watch = &app.watch;
+    let mut title = vec![Span::raw(tr("Watched Addresses"))];
+    if let Some(ref task) = watch.task {
+        title.push(Span::styled(
+            tr_fmt("  {} refreshing", &[&task.progress(app.plain.ascii)]),
+            Style::default().fg(app.theme.muted),
+        ));
+    }
+    let mut lines = vec![Line::from(title), Line::from("")];
+    if watch.addresses.is_empty() {
+        lines.push(Line::from(tr("No addresses watched yet")));
+    }
+
+    let label = |address: &Pubkey| {
+        app.config
+            .wallet_name(address)
+            .map_or_else(|| address.to_string(), str::to_string)
+    };
+    let watched = |address: &Pubkey| {
+        watch
+            .watched
+            .iter()
+            .find(|watched| watched.balance.address == *address)
+    };
+    for (i, address) in watch.addresses.iter().enumerate() {
+        let balance = watched(address).map(|watched| &watched.balance);
+        let style = if i == watch.selected {
+            Style::default()
+                .fg(app.theme.accent)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(app.theme.text)
+        };
+        lines.push(Line::from(Span::styled(
+            balance_row(
+                &app.amounts,
+                " ",
+                &label(address),
+                balance.map(|balance| balance.lamports),
+                balance.map_or(&[], |balance| &balance.tokens),
+            ),
+            style,
+        )));
+    }
+
+    if let Some(address) = watch.addresses.get(watch.selected) {
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(
+            tr_fmt("Latest transactions of {}", &[&label(address)]),
+            Style::default().fg(app.theme.heading),
+        )));
+        match watched(address) {
+            Some(watched) if watched.recent.is_empty() => {
+                lines.push(Line::from(tr("No transactions yet")));
+            }
+            Some(watched) => {
+                for entry in &watched.recent {
+                    let time = entry
+                        .block_time
+                        .map(history::format_timestamp)
+                        .unwrap_or_else(|| format!("{:<16}", tr("pending")));
+                    let status = if entry.failed { tr("failed") } else { tr("ok") };
+                    let mut text = format!(
+                        "{}  {:<6}  {}…{}",
+                        time,
+                        status,
+                        &entry.signature[..8],
+                        &entry.signature[entry.signature.len() - 8..]
+                    );
+                    if let Some(ref details) = entry.details {
+                        text.push_str(&format!(
+                            "  {:<12}  {}",
+                            tr(details.label()),
+                            app.amounts.sol_change(details.lamports_delta)
+                        ));
+                        for delta in &details.token_deltas {
+                            text.push_str(&format!("  {}", app.amounts.token_change(delta)));
+                        }
+                    }
+                    let color = if entry.failed {
+                        app.theme.error
+                    } else {
+                        app.theme.history
+                    };
+                    lines.push(Line::from(Span::styled(text, Style::default().fg(color))));
+                }
+            }
+            None => lines.push(Line::from(tr("Loading transactions..."))),
+        }
+    }
+
+    if let Some(ref address) = watch.input {
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(
+            format!(
+                "{}{}",
+                tr_fmt("Address to watch: {}", &[address]),
+                app.plain.cursor()
+            ),
+            Style::default().fg(app.theme.accent),
+        )));
+    }
+    if let Some(ref error) = watch.error {
+        lines.push(Line::from(""));
+        lines.extend(error.lines(&app.theme, app.retry_key()));
+    }
+
+    Paragraph::new(lines)
+        .style(Style::default().fg(app.theme.text))
+        .block(Block::default().borders(Borders::ALL).title(tr("Watch")))
+}
+
+fn render_requests(app: &App) -> Paragraph<'static> {
+    let requests = &app.requests;
+    let mut title = vec![Span::raw(tr("Payment Requests"))];
+    if let Some(ref task) = requests.task {
+        title.push(Span::styled(
+            tr_fmt("  {} checking", &[&task.progress(app.plain.ascii)]),
+            Style::default().fg(app.theme.muted),
+        ));
+    }
+    let mut lines = vec![Line::from(title), Line::from("")];
+    if requests.requests.is_empty() {
+        lines.push(Line::from(tr("No payment requests yet")));
+    }
+
+    let (now, expire_hours) = (pay::now(), app.config.payments.expire_hours);
+    for (i, saved) in requests.requests.iter().enumerate() {
+        let status = saved.status(now, expire_hours);
+        let color = match status {
+            RequestStatus::Pending => app.theme.text,
+            RequestStatus::Paid(_) => app.theme.success,
+            RequestStatus::Expired => app.theme.muted,
+        };
+        let mut style = Style::default().fg(color);
+        if i == requests.selected {
+            style = style.fg(app.theme.accent).add_modifier(Modifier::BOLD);
+        }
+        let request = &saved.request;
+        let mut text = format!(
+            " {}  {:<7}  {}",
+            history::format_timestamp(request.created_at),
+            tr(status.label()),
+            app.amounts.sol(request.lamports)
+        );
+        if let Some(ref label) = request.label {
+            text.push_str(&format!("  {}", label));
+        }
+        lines.push(Line::from(Span::styled(text, style)));
+    }
+
+    if let Some(saved) = requests.requests.get(requests.selected) {
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(
+            tr_fmt("Reference {}", &[&saved.request.reference]),
+            Style::default().fg(app.theme.muted),
+        )));
+        if let Some(ref signature) = saved.signature {
+            lines.push(Line::from(tr_fmt("Paid by {}", &[signature])));
+        }
+    }
+
+    if let Some(ref error) = requests.error {
+        lines.push(Line::from(""));
+        lines.extend(error.lines(&app.theme, app.retry_key()));
+    }
+
+    Paragraph::new(lines)
+        .style(Style::default().fg(app.theme.text))
+        .block(Block::default().borders(Borders::ALL).title(tr("Requests")))
+}
+
+fn render_wallet(app: &App) -> Paragraph<'static> {
+    let mut lines = vec![Line::from(tr("Wallet Overview")), Line::from("")];
+    if let Some(name) = app.wallet_name() {
+        lines.push(Line::from(tr_fmt("Name: {}", &[&name])));
+    }
+    lines.push(Line::from(tr_fmt("Address: {}", &[&app.wallet.address])));
+    let mut balance = vec![Span::raw(tr_fmt(
+        "Balance: {}",
+        &[&app.amounts.sol(app.wallet.lamports)],
+    ))];
+    if let Some(ref task) = app.balance_task {
+        balance.push(Span::styled(
+            tr_fmt(
+                if app.throttled() {
+                    "  {} throttled, retrying, Esc to stop"
+                } else {
+                    "  {} refreshing, Esc to stop"
+                },
+                &[&task.progress(app.plain.ascii)],
+            ),
+            Style::default().fg(app.theme.muted),
+        ));
+    }
+    lines.push(Line::from(balance));
+    if let Some(signature) = app.last_tx_signature {
+        lines.push(Line::from(tr_fmt("Last sent: {}", &[&signature])));
+    }
+    if let Some(ref error) = app.balance_error {
+        lines.push(Line::from(""));
+        lines.extend(error.lines(&app.theme, app.retry_key()));
+    }
+
+    Paragraph::new(lines)
+        .style(Style::default().fg(app.theme.wallet))
+        .block(Block::default().borders(Borders::ALL).title(tr("Wallet")))
+}
+
+fn render_transactions(app: &App, height: u16) -> Paragraph<'static> {
+    let history = &app.history;
+    if let Some(ref inspection) = history.inspection {
+        let entry = history.entry(&inspection.signature);
+        return render_inspection(inspection, entry, &app.theme, height, &app.scroll_limit);
+    }
+    let highlight = app.theme.search_match();
+    let mut lines = vec![Line::from(tr("Recent Transactions")), Line::from("")];
+
+    let filters: Vec<Span> = HistoryFilter::ALL
+        .iter()
+        .map(|filter| {
+            let style = if history.filters.contains(filter) {
+                Style::default()
+                    .fg(app.theme.heading)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(app.theme.muted)
+            };
+            Span::styled(format!("[{}] ", tr(filter.label())), style)
+        })
+        .collect();
+    lines.push(Line::from(filters));
+
+    let visible = history.visible();
+    if visible.is_empty() {
+        lines.push(Line::from(tr(if history.error.is_some() {
+            "Could not load transactions"
+        } else if !history.entries.is_empty() {
+            "No transactions match the filters"
+        } else if app.history_task.is_some() {
+            "Loading transactions..."
+        } else {
+            "No transactions yet"
+        })));
+    }
+
+    // Only the rows that fit are built
+    let rows = history_rows(app, height);
+    let clock = Clock::system();
+    let list = history.list(&clock);
+    let offset = history.scroll_offset(&list, rows);
+    for row in list.iter().skip(offset).take(rows) {
+        let i = match *row {
+            ListRow::Day(ref label) => {
+                lines.push(Line::from(Span::styled(
+                    label.clone(),
+                    Style::default()
+                        .fg(app.theme.heading)
+                        .add_modifier(Modifier::BOLD),
+                )));
+                continue;
+            }
+            ListRow::Entry(i) => i,
+        };
+        let entry = visible[i];
+        let time = entry.block_time.map_or_else(
+            || tr("pending").to_string(),
+            |time| format!("{}  {:>8}", clock.time_of_day(time), clock.ago(time)),
+        );
+        let status = if entry.failed { tr("failed") } else { tr("ok") };
+        let kind = entry
+            .details
+            .as_ref()
+            .map_or("", |details| tr(details.label()));
+        let text = format!(
+            "  {:<15}  {:<6}  {:<12}  {}…{}",
+            time,
+            status,
+            kind,
+            &entry.signature[..8],
+            &entry.signature[entry.signature.len() - 8..]
+        );
+        let selected = i == history.selected;
+        let style = if selected {
+            Style::default()
+                .fg(app.theme.accent)
+                .add_modifier(Modifier::BOLD)
+        } else if entry.failed {
+            Style::default().fg(app.theme.error)
+        } else {
+            Style::default().fg(app.theme.text)
+        };
+
+        let mut spans = highlight_spans(&text, &history.search, style, highlight);
+        if let Some(ref details) = entry.details {
+            let changes = std::iter::once((
+                details.lamports_delta.signum(),
+                app.amounts.sol_change(details.lamports_delta),
+            ))
+            .chain(details.token_deltas.iter().map(|delta| {
+                (
+                    delta.amount.signum() as i64,
+                    app.amounts.token_change(delta),
+                )
+            }));
+            for (sign, change) in changes {
+                let color = if sign < 0 {
+                    app.theme.error
+                } else {
+                    app.theme.success
+                };
+                let mut change_style = Style::default().fg(color);
+                if selected {
+                    change_style = change_style.add_modifier(Modifier::BOLD);
+                }
+                spans.push(Span::raw("  "));
+                spans.push(Span::styled(change, change_style));
+            }
+        }
+        if let Some(ref memo) = entry.memo {
+            spans.push(Span::raw("  "));
+            spans.extend(highlight_spans(memo, &history.search, style, highlight));
+        }
+        if let Some(ref note) = entry.note {
+            spans.push(Span::raw("  "));
+            spans.extend(highlight_spans(
+                note,
+                &history.search,
+                style.fg(app.theme.heading).add_modifier(Modifier::ITALIC),
+                highlight,
+            ));
+        }
+        lines.push(Line::from(spans));
+    }
+
+    lines.push(Line::from(""));
+    if let Some(ref note) = history.editing_note {
+        lines.push(Line::from(vec![
+            Span::raw(tr("Note (empty to remove): ")),
+            Span::styled(
+                format!("{}{}", note, app.plain.cursor()),
+                Style::default().fg(app.theme.accent),
+            ),
+        ]));
+    }
+    if let Some(ref path) = history.export_path {
+        let format = history
+            .export_format
+            .map_or(tr("by extension"), ReportFormat::label);
+        lines.push(Line::from(vec![
+            Span::raw(tr_fmt("Export as {} (Tab to change) to: ", &[&format])),
+            Span::styled(
+                format!("{}{}", path, app.plain.cursor()),
+                Style::default().fg(app.theme.accent),
+            ),
+        ]));
+    }
+    if history.searching || !history.search.is_empty() {
+        let cursor = if history.searching {
+            app.plain.cursor()
+        } else {
+            ""
+        };
+        lines.push(Line::from(vec![
+            Span::raw(tr("Search: ")),
+            Span::styled(
+                format!("{}{}", history.search, cursor),
+                Style::default().fg(app.theme.accent),
+            ),
+        ]));
+    }
+    if let Some(entry) = history.selected_entry() {
+        lines.push(highlight_matches(
+            tr_fmt("Signature: {}", &[&entry.signature]),
+            &history.search,
+            Style::default(),
+            highlight,
+        ));
+        match entry.block_time {
+            Some(time) => lines.push(Line::from(tr_fmt(
+                "Time: {} ({})  Slot: {}",
+                &[&clock.datetime(time), &clock.ago(time), &entry.slot],
+            ))),
+            None => lines.push(Line::from(tr_fmt("Slot: {}", &[&entry.slot]))),
+        }
+        if let Some(ref note) = entry.note {
+            lines.push(highlight_matches(
+                tr_fmt("Note: {}", &[note]),
+                &history.search,
+                Style::default(),
+                highlight,
+            ));
+        }
+        if let Some(ref reference) = entry.reference {
+            lines.push(highlight_matches(
+                tr_fmt("Reference: {}", &[reference]),
+                &history.search,
+                Style::default(),
+                highlight,
+            ));
+        }
+        if let Some(ref details) = entry.details {
+            let mut detail = tr_fmt("Fee: {}", &[&app.amounts.sol(details.fee)]);
+            if let Some(counterparty) = details.counterparty {
+                detail.push_str(&tr_fmt("  Counterparty: {}", &[&counterparty]));
+            }
+            if let TxKind::Program(program) = details.kind {
+                match details.program_name() {
+                    Some(name) => {
+                        detail.push_str(&tr_fmt("  Program: {} ({})", &[&name, &program]))
+                    }
+                    None => detail.push_str(&tr_fmt("  Program: {}", &[&program])),
+                }
+            }
+            lines.push(highlight_matches(
+                detail,
+                &history.search,
+                Style::default(),
+                highlight,
+            ));
+        }
+    }
+    let mut status = vec![Span::raw(tr_fmt(
+        if history.exhausted || app.history_task.is_some() {
+            "{} of {} loaded"
+        } else {
+            "{} of {} loaded, scroll down for more"
+        },
+        &[&visible.len(), &history.entries.len()],
+    ))];
+    if let Some(HistoryFetch { ref task, .. }) = app.history_task {
+        status.push(Span::styled(
+            tr_fmt(
+                ", {} loading, Esc to stop",
+                &[&task.progress(app.plain.ascii)],
+            ),
+            Style::default().fg(app.theme.muted),
+        ));
+    }
+    lines.push(Line::from(status));
+
+    if let Some(ref error) = history.error {
+        lines.extend(error.lines(&app.theme, app.retry_key()));
+    }
+
+    Paragraph::new(lines)
+        .style(Style::default().fg(app.theme.history))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(tr("Transactions")),
+        )
+}
+
+/// Instruction-by-instruction view of one transaction
+fn render_inspection(
+    inspection: &TxInspection,
+    entry: Option<&HistoryEntry>,
+    theme: &Theme,
+    height: u16,
+    scroll_limit: &Cell<u16>,
+) -> Paragraph<'static> {
+    let (lines, _) = inspection_lines(inspection, entry, theme);
+    let limit = max_scroll(lines.len(), height);
+    scroll_limit.set(limit);
+    Paragraph::new(lines)
+        .style(Style::default().fg(theme.history))
+        .scroll((inspection.scroll.min(limit), 0))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(tr("Transaction Details")),
+        )
+}
+
+/// The lines of a transaction's details, and the line and value of each
+/// field that can be copied, in order
+pub(super) fn inspection_lines(
+    inspection: &TxInspection,
+    entry: Option<&HistoryEntry>,
+    theme: &Theme,
+) -> (Vec<Line<'static>>, Vec<(usize, String)>) {
+    let label = Style::default().fg(theme.heading);
+    let heading = Style::default()
+        .fg(theme.accent)
+        .add_modifier(Modifier::BOLD);
+    let selected = Style::default()
+        .fg(theme.accent)
+        .add_modifier(Modifier::BOLD | Modifier::REVERSED);
+    let mut lines: Vec<Line<'static>> = Vec::new();
+    let mut fields = Vec::new();
+    // A line of `before`, the field's value and `after`, the value in
+    // `style` unless it's the selected field
+    let mut field = |lines: &mut Vec<Line<'static>>,
+                     before: Vec<Span<'static>>,
+                     value: String,
+                     style: Style,
+                     after: Vec<Span<'static>>| {
+        let style = if fields.len() == inspection.field {
+            selected
+        } else {
+            style
+        };
+        fields.push((lines.len(), value.clone()));
+        let mut spans = before;
+        spans.push(Span::styled(value, style));
+        spans.extend(after);
+        lines.push(Line::from(spans));
+    };
+
+    let plain = Style::default();
+    field(
+        &mut lines,
+        vec![Span::raw(tr("Signature: "))],
+        inspection.signature.clone(),
+        plain,
+        Vec::new(),
+    );
+    if let Some(memo) = entry.and_then(|entry| entry.memo.clone()) {
+        field(
+            &mut lines,
+            vec![Span::raw(tr("Memo: "))],
+            memo,
+            plain,
+            Vec::new(),
+        );
+    }
+    if let Some(note) = entry.and_then(|entry| entry.note.clone()) {
+        field(
+            &mut lines,
+            vec![Span::raw(tr("Note: "))],
+            note,
+            plain,
+            Vec::new(),
+        );
+    }
+    lines.push(Line::from(tr_fmt("Version: {}", &[&inspection.version])));
+    for table in &inspection.lookup_tables {
+        field(
+            &mut lines,
+            vec![Span::styled(tr("Lookup table: "), label)],
+            table.to_string(),
+            plain,
+            Vec::new(),
+        );
+    }
+
+    for (i, instruction) in inspection.instructions.iter().enumerate() {
+        lines.push(Line::from(""));
+        let mut before = vec![Span::styled(format!("#{} ", i + 1), heading)];
+        let mut after = Vec::new();
+        if let Some(ref name) = instruction.program_name {
+            before.push(Span::styled(format!("{} (", name), heading));
+            after.push(Span::styled(")", heading));
+        }
+        let decoded = instruction.decoded.as_ref();
+        match decoded {
+            Some(decoded) => after.push(Span::raw(format!("  {}", decoded.name))),
+            None => after.push(Span::styled(
+                tr_fmt("  {} bytes of data, no IDL", &[&instruction.data_len]),
+                Style::default().fg(theme.muted),
+            )),
+        }
+        field(
+            &mut lines,
+            before,
+            instruction.program.to_string(),
+            heading,
+            after,
+        );
+
+        for (name, value) in decoded.map_or(&[][..], |decoded| decoded.args.as_slice()) {
+            field(
+                &mut lines,
+                vec![Span::styled(format!("    {}: ", name), label)],
+                value.clone(),
+                plain,
+                Vec::new(),
+            );
+        }
+        for (j, account) in instruction.accounts.iter().enumerate() {
+            let name = decoded
+                .and_then(|decoded| decoded.account_names.get(j))
+                .cloned()
+                .unwrap_or_else(|| tr_fmt("account {}", &[&(j + 1)]));
+            field(
+                &mut lines,
+                vec![Span::styled(format!("    {}: ", name), label)],
+                account.to_string(),
+                plain,
+                Vec::new(),
+            );
+        }
+    }
+    (lines, fields)
+}
+
+/// Split `text` into a line, highlighting every match of `query`
+fn highlight_matches(text: String, query: &str, style: Style, highlight: Style) -> Line<'static> {
+    Line::from(highlight_spans(&text, query, style, highlight))
+}
+
+fn highlight_spans(text: &str, query: &str, style: Style, highlight: Style) -> Vec<Span<'static>> {
+    let mut spans = Vec::new();
+    let mut rest = text;
+    while let Some(start) = history::find_ignore_case(rest, query) {
+        let end = start + query.len();
+        spans.push(Span::styled(rest[..start].to_string(), style));
+        spans.push(Span::styled(rest[start..end].to_string(), highlight));
+        rest = &rest[end..];
+    }
+    spans.push(Span::styled(rest.to_string(), style));
+    spans
+}
+
+fn render_send(app: &App) -> Paragraph<'static> {
+    let mut lines = vec![Line::from(tr("Send SOL")), Line::from("")];
+
+    match app.send_state.input_mode {
+        SendInputMode::EditingRecipient => {
+            lines.push(Line::from(tr("Enter recipient address:")));
+            lines.push(Line::from(
+                app.send_state
+                    .recipient
+                    .spans(Style::default().fg(app.theme.accent), app.plain.cursor()),
+            ));
+            lines.push(Line::from(""));
+            lines.push(Line::from(tr("Press Enter to continue, Esc to cancel")));
+        }
+        SendInputMode::EditingAmount => {
+            lines.push(Line::from(tr_fmt("To: {}", &[&app.send_state.recipient])));
+            lines.push(Line::from(""));
+            lines.push(Line::from(tr_fmt(
+                "Enter amount ({}):",
+                &[&app.amounts.unit().name()],
+            )));
+            lines.push(Line::from(
+                app.send_state
+                    .amount
+                    .spans(Style::default().fg(app.theme.accent), app.plain.cursor()),
+            ));
+            lines.push(Line::from(""));
+            lines.push(Line::from(tr_fmt(
+                "Available balance: {}",
+                &[&app.amounts.sol(app.wallet.lamports)],
+            )));
+            lines.push(Line::from(""));
+            lines.push(Line::from(tr("Press Enter to continue, Esc to go back")));
+        }
+        SendInputMode::AcknowledgingBlocklist => {
+            lines.push(Line::from(Span::styled(
+                tr("WARNING: recipient is on your blocklist"),
+                Style::default()
+                    .fg(app.theme.error)
+                    .add_modifier(Modifier::BOLD),
+            )));
+            lines.push(Line::from(""));
+            lines.push(Line::from(tr_fmt("To: {}", &[&app.send_state.recipient])));
+            if let Some(ref reason) = app.send_state.blocklist_reason {
+                lines.push(Line::from(Span::styled(
+                    tr_fmt("Reason: {}", &[reason]),
+                    Style::default().fg(app.theme.error),
+                )));
+            }
+            lines.push(Line::from(""));
+            lines.push(Line::from(tr_fmt(
+                "Type {} and press Enter to continue anyway:",
+                &[&BLOCKLIST_ACK_WORD],
+            )));
+            lines.push(Line::from(
+                app.send_state
+                    .confirm_input
+                    .spans(Style::default().fg(app.theme.accent), app.plain.cursor()),
+            ));
+            lines.push(Line::from(""));
+            lines.push(Line::from(tr("Press Esc to go back")));
+        }
+        SendInputMode::ConfirmingLimit => {
+            if let Some(ref reason) = app.send_state.limit_warning {
+                lines.push(Line::from(Span::styled(
+                    tr_fmt("WARNING: this send {}", &[reason]),
+                    Style::default()
+                        .fg(app.theme.error)
+                        .add_modifier(Modifier::BOLD),
+                )));
+            }
+            lines.push(Line::from(""));
+            lines.push(Line::from(tr_fmt("To: {}", &[&app.send_state.recipient])));
+            lines.push(Line::from(tr_fmt(
+                "Amount: {} {}",
+                &[&app.send_state.amount, &app.amounts.unit().name()],
+            )));
+            lines.push(Line::from(""));
+            lines.push(Line::from(tr("Type the exact amount to continue:")));
+            lines.push(Line::from(
+                app.send_state
+                    .confirm_input
+                    .spans(Style::default().fg(app.theme.accent), app.plain.cursor()),
+            ));
+            lines.push(Line::from(""));
+            lines.push(Line::from(tr("Press Esc to go back")));
+        }
+        SendInputMode::Confirming => {
+            lines.push(Line::from(tr("Confirm Transaction")));
+            lines.push(Line::from(""));
+            lines.push(Line::from(tr_fmt("To: {}", &[&app.send_state.recipient])));
+            lines.push(Line::from(tr_fmt(
+                "Amount: {} {}",
+                &[&app.send_state.amount, &app.amounts.unit().name()],
+            )));
+            lines.push(Line::from(priority_fee_line(app)));
+            if app.send_state.reference(&app.config) {
+                lines.push(Line::from(tr(
+                    "Reference: a new key, recorded in the history",
+                )));
+            }
+            if app.wallet.fee_payer.is_some() {
+                lines.push(Line::from(tr_fmt(
+                    "Fee payer: {}",
+                    &[&app.wallet.fee_payer()],
+                )));
+            }
+            if app.config.jito.enabled {
+                lines.push(Line::from(tr_fmt(
+                    "Via Jito, with a tip of {} SOL",
+                    &[&app.config.jito.tip],
+                )));
+            }
+            if sender::dry_run() {
+                lines.push(Line::from(Span::styled(
+                    tr("Dry run: the transaction is only simulated, nothing is sent"),
+                    Style::default()
+                        .fg(app.theme.error)
+                        .add_modifier(Modifier::BOLD),
+                )));
+            }
+            lines.push(Line::from(""));
+            if let Some(minimum) = app.send_state.rent_exempt_minimum {
+                let minimum = app.amounts.exact().sol(minimum);
+                lines.push(Line::from(Span::styled(
+                    tr("Warning: recipient account does not exist yet"),
+                    Style::default()
+                        .fg(app.theme.error)
+                        .add_modifier(Modifier::BOLD),
+                )));
+                lines.push(Line::from(Span::styled(
+                    tr_fmt(
+                        "Amounts below the rent-exempt minimum ({}) may be lost or unusable",
+                        &[&minimum],
+                    ),
+                    Style::default().fg(app.theme.error),
+                )));
+                if !app.send_state.typed_confirmation {
+                    lines.push(Line::from(tr_fmt(
+                        "Press B to bump the amount to {}",
+                        &[&minimum],
+                    )));
+                }
+                lines.push(Line::from(""));
+            }
+            if let Some(ref send) = app.send_task {
+                lines.extend(confirmation_lines(app, &send.task, &send.tracker));
+                lines.push(Line::from(tr("Press Esc to stop waiting")));
+            } else if app.send_state.typed_confirmation {
+                lines.push(Line::from(Span::styled(
+                    tr("Large mainnet send: type the amount or the first 4 characters of the recipient"),
+                    Style::default()
+                        .fg(app.theme.accent)
+                        .add_modifier(Modifier::BOLD),
+                )));
+                lines.push(Line::from(
+                    app.send_state
+                        .confirm_input
+                        .spans(Style::default().fg(app.theme.accent), app.plain.cursor()),
+                ));
+                lines.push(Line::from(""));
+                lines.push(Line::from(tr("Press Enter to confirm, Esc to cancel")));
+            } else {
+                lines.push(Line::from(Span::styled(
+                    tr("Press Y to confirm, N to cancel"),
+                    Style::default()
+                        .fg(app.theme.accent)
+                        .add_modifier(Modifier::BOLD),
+                )));
+                lines.push(Line::from(tr("Press P to change the priority fee")));
+            }
+        }
+        SendInputMode::EditingPriorityFee => {
+            lines.push(Line::from(tr_fmt("To: {}", &[&app.send_state.recipient])));
+            lines.push(Line::from(tr_fmt(
+                "Amount: {} {}",
+                &[&app.send_state.amount, &app.amounts.unit().name()],
+            )));
+            lines.push(Line::from(""));
+            lines.push(Line::from(tr(
+                "Priority fee (micro-lamports per compute unit):",
+            )));
+            lines.push(Line::from(
+                app.send_state
+                    .fee_input
+                    .spans(Style::default().fg(app.theme.accent), app.plain.cursor()),
+            ));
+            lines.push(Line::from(""));
+            lines.push(Line::from(tr(
+                "Press Enter to use it (empty for the suggestion), Esc to go back",
+            )));
+        }
+    }
+
+    if let Some(ref error) = app.send_state.error {
+        lines.push(Line::from(""));
+        lines.extend(error.lines(&app.theme, app.retry_key()));
+    }
+
+    Paragraph::new(lines)
+        .style(Style::default().fg(app.theme.text))
+        .block(Block::default().borders(Borders::ALL).title(tr("Send SOL")))
+}
+
+fn render_receive(app: &App, area: Rect) -> Paragraph<'static> {
+    let receive = &app.receive;
+    let mut lines = match receive.request {
+        Some(ref request) => vec![
+            Line::from(tr("Payment request")),
+            Line::from(""),
+            Line::from(Span::styled(
+                app.amounts.exact().sol(request.lamports),
+                Style::default()
+                    .fg(app.theme.wallet)
+                    .add_modifier(Modifier::BOLD),
+            )),
+            Line::from(Span::styled(
+                tr_fmt("Reference {}", &[&request.reference]),
+                Style::default().fg(app.theme.muted),
+            )),
+            Line::from(""),
+        ],
+        None => vec![
+            Line::from(tr("Receive SOL")),
+            Line::from(""),
+            Line::from(tr("Your wallet address:")),
+            Line::from(Span::styled(
+                app.wallet.address.to_string(),
+                Style::default().fg(app.theme.wallet),
+            )),
+            Line::from(""),
+        ],
+    };
+
+    if let Some(ref signature) = receive.paid {
+        lines.push(Line::from(Span::styled(
+            if app.plain.ascii {
+                tr("PAID").to_string()
+            } else {
+                format!("{} ✔", tr("PAID"))
+            },
+            Style::default()
+                .fg(app.theme.success)
+                .add_modifier(Modifier::BOLD),
+        )));
+        lines.push(Line::from(signature.clone()));
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(
+            tr("Press Esc to go back to the address"),
+            Style::default().fg(app.theme.muted),
+        )));
+    } else {
+        // Whatever's left inside the borders, under the address and above
+        // the status and prompts
+        let below = 2 * u16::from(receive.request.is_some())
+            + 2 * u16::from(receive.save_path.is_some() || receive.amount.is_some());
+        let height = area.height.saturating_sub(2 + lines.len() as u16 + below);
+        match qr::fit(
+            &receive.payload(&app.wallet.address),
+            area.width.saturating_sub(2),
+            height,
+            app.plain.ascii,
+        ) {
+            Some(qr) => {
+                // Light on dark whatever the theme, so scanners read it the
+                // right way round
+                let qr_style = Style::default().fg(Color::White).bg(Color::Black);
+                for line in qr {
+                    lines.push(Line::from(Span::styled(line, qr_style)));
+                }
+            }
+            None => {
+                lines.push(Line::from(Span::styled(
+                    tr("Too small for the QR code, press f to show it full screen"),
+                    Style::default().fg(app.theme.muted),
+                )));
+            }
+        }
+        if receive.request.is_some() {
+            lines.push(Line::from(""));
+            lines.push(Line::from(match receive.task {
+                Some(ref task) => tr_fmt(
+                    "Waiting for the payment {}",
+                    &[&task.progress(app.plain.ascii)],
+                ),
+                None => tr("Waiting for the payment").to_string(),
+            }));
+        }
+    }
+    if let Some(ref path) = receive.save_path {
+        lines.push(Line::from(""));
+        lines.push(Line::from(vec![
+            Span::raw(tr("Save as PNG, or SVG for .svg, to: ")),
+            Span::styled(
+                format!("{}{}", path, app.plain.cursor()),
+                Style::default().fg(app.theme.accent),
+            ),
+        ]));
+    }
+    if let Some(ref amount) = receive.amount {
+        lines.push(Line::from(""));
+        lines.push(Line::from(vec![
+            Span::raw(tr_fmt(
+                "Amount to ask for ({}): ",
+                &[&app.amounts.unit().name()],
+            )),
+            Span::styled(
+                format!("{}{}", amount, app.plain.cursor()),
+                Style::default().fg(app.theme.accent),
+            ),
+        ]));
+    }
+    if let Some(ref error) = receive.error {
+        lines.push(Line::from(""));
+        lines.extend(error.lines(&app.theme, app.retry_key()));
+    }
+
+    Paragraph::new(lines)
+        .style(Style::default().fg(app.theme.text))
+        .alignment(Alignment::Center)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(tr("Receive SOL")),
+        )
+}
+
+fn render_settings(app: &App) -> Paragraph<'static> {
+    let network = tr(if app.rpc_url.contains("mainnet") {
+        "Mainnet Beta"
+    } else if app.rpc_url.contains("testnet") {
+        "Testnet"
+    } else if app.rpc_url.contains("devnet") {
+        "Devnet"
+    } else if app.rpc_url.contains("localhost") || app.rpc_url.contains("127.0.0.1") {
+        "Localnet"
+    } else {
+        "Custom"
+    });
+
+    let keypair_source = match app
+        .wallet
+        .keypair
+        .as_ref()
+        .and_then(|keypair| keypair.derivation())
+    {
+        Some(derivation) => format!("{} ({})", app.wallet.keypair_source, derivation),
+        None => app.wallet.keypair_source.to_string(),
+    };
+
+    let settings = &app.settings;
+    let mut lines = vec![Line::from(tr("Settings")), Line::from("")];
+    for (i, setting) in Setting::ALL.into_iter().enumerate() {
+        let value = match settings.input {
+            Some((SettingsInput::Value, ref value)) if i == settings.selected => {
+                format!("{}{}", value, app.plain.cursor())
+            }
+            _ if setting == Setting::Cluster => format!("{} ({})", app.rpc_url, network),
+            _ => match setting.value(app) {
+                value if value.is_empty() => tr("off").to_string(),
+                value => value,
+            },
+        };
+        let style = if i == settings.selected {
+            Style::default()
+                .fg(app.theme.accent)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(app.theme.settings)
+        };
+        lines.push(Line::from(Span::styled(
+            format!("{:<24} {}", tr(setting.label()), value),
+            style,
+        )));
+    }
+    let prompt = match settings.input {
+        Some((SettingsInput::Keypair, ref path)) => Some((tr("Keypair file to open"), path)),
+        Some((SettingsInput::Name, ref name)) => Some((tr("Name for this wallet"), name)),
+        Some((SettingsInput::Value, _)) | None => None,
+    };
+    if let Some((label, text)) = prompt {
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(
+            format!("{}: {}{}", label, text, app.plain.cursor()),
+            Style::default().fg(app.theme.accent),
+        )));
+    }
+    if let Some(ref error) = settings.error {
+        lines.push(Line::from(""));
+        lines.extend(error.lines(&app.theme, app.retry_key()));
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(match app.config_path {
+        Some(ref path) => tr_fmt("Changes are saved to {}", &[&path.display()]),
+        None => tr("Changes last until solace exits").to_string(),
+    }));
+
+    lines.extend([
+        Line::from(""),
+        Line::from(match app.wallet_name() {
+            Some(name) => tr_fmt("Wallet: {} ({})", &[&app.wallet.address, &name]),
+            None => tr_fmt("Wallet: {}", &[&app.wallet.address]),
+        }),
+        Line::from(tr_fmt("Keypair: {}", &[&keypair_source])),
+        Line::from(""),
+        Line::from(tr_fmt("Blocklist: {} addresses", &[&app.blocklist.len()])),
+        Line::from(tr_fmt("Anchor IDLs: {} loaded", &[&app.idls.len()])),
+        Line::from(tr_fmt(
+            "Key bindings: {} profile, {} remapped (? lists them)",
+            &[&app.config.keys.profile.name(), &app.keymap.remapped()],
+        )),
+    ]);
+
+    Paragraph::new(lines)
+        .style(Style::default().fg(app.theme.settings))
+        .block(Block::default().borders(Borders::ALL).title(tr("Settings")))
+}
+
+fn render_export(app: &App) -> Paragraph<'static> {
+    let export = &app.export_state;
+    let warning = Style::default()
+        .fg(app.theme.error)
+        .add_modifier(Modifier::BOLD);
+    let mut lines = vec![Line::from(tr("Export Secret")), Line::from("")];
+
+    match export.step {
+        ExportStep::Choosing => {
+            lines.push(Line::from(Span::styled(
+                tr("Anyone who sees your private key or seed phrase can take all of your funds."),
+                warning,
+            )));
+            lines.push(Line::from(tr(
+                "Make sure nobody is watching and the screen is not being shared or recorded.",
+            )));
+            lines.push(Line::from(""));
+            lines.push(Line::from(tr("Press K to export the private key (base58)")));
+            let has_seed = app
+                .wallet
+                .keypair
+                .as_ref()
+                .is_some_and(|keypair| keypair.seed_phrase().is_some());
+            if has_seed {
+                lines.push(Line::from(tr("Press S to export the seed phrase")));
+            }
+            lines.push(Line::from(tr(
+                "Press P to save a printable paper wallet (.svg or .txt)",
+            )));
+            lines.push(Line::from(tr(
+                "Press B to split the private key into M-of-N backup shares",
+            )));
+            lines.push(Line::from(""));
+            lines.push(Line::from(tr("Press Esc to go back")));
+        }
+        ExportStep::Confirming => {
+            let exposure = match export.kind {
+                ExportKind::PrivateKey => "Your private key will be shown in plain text.",
+                ExportKind::SeedPhrase => "Your seed phrase will be shown in plain text.",
+                ExportKind::PaperWallet => {
+                    "Your private key and seed phrase will be written to unencrypted files."
+                }
+                ExportKind::Shares => {
+                    "Your private key shares will be written to unencrypted files."
+                }
+            };
+            lines.push(Line::from(Span::styled(tr(exposure), warning)));
+            lines.push(Line::from(""));
+            lines.push(Line::from(tr_fmt(
+                "Type {} and press Enter to continue:",
+                &[&EXPORT_ACK_WORD],
+            )));
+            lines.push(Line::from(Span::styled(
+                format!("{}{}", export.confirm_input, app.plain.cursor()),
+                Style::default().fg(app.theme.accent),
+            )));
+            lines.push(Line::from(""));
+            lines.push(Line::from(tr("Press Esc to go back")));
+        }
+        ExportStep::Revealed => {
+            if let Some(ref secret) = export.secret {
+                lines.push(Line::from(Span::styled(
+                    secret.to_string(),
+                    Style::default().fg(app.theme.accent),
+                )));
+            }
+            lines.push(Line::from(""));
+            let remaining = export.revealed_at.map_or(0, |at| {
+                app.config
+                    .export
+                    .reveal_seconds
+                    .saturating_sub(at.elapsed().as_secs())
+            });
+            lines.push(Line::from(tr_fmt(
+                "This will be cleared in {}s. Press any key to clear it now.",
+                &[&remaining],
+            )));
+        }
+        ExportStep::SavingPaper => {
+            lines.push(Line::from(tr(
+                "Save paper wallet to (.svg for a printable image, anything else for text):",
+            )));
+            lines.push(Line::from(Span::styled(
+                format!("{}{}", export.path_input, app.plain.cursor()),
+                Style::default().fg(app.theme.accent),
+            )));
+            lines.push(Line::from(""));
+            lines.push(Line::from(tr(
+                "Print it on a printer that isn't networked, then delete the file.",
+            )));
+            lines.push(Line::from(tr("Press Enter to save, Esc to go back")));
+        }
+        ExportStep::SavingShares => {
+            let field = |value: &str, editing: bool| {
+                if editing {
+                    Span::styled(
+                        format!("{}{}", value, app.plain.cursor()),
+                        Style::default().fg(app.theme.accent),
+                    )
+                } else {
+                    Span::raw(value.to_string())
+                }
+            };
+            lines.push(Line::from(vec![
+                Span::raw(tr("Shares (needed of total): ")),
+                field(&export.shares_input, export.editing_shares),
+            ]));
+            lines.push(Line::from(vec![
+                Span::raw(tr("Directory: ")),
+                field(&export.path_input, !export.editing_shares),
+            ]));
+            lines.push(Line::from(""));
+            lines.push(Line::from(tr("Each share is written to its own file. Fewer than the needed number reveal nothing.")));
+            lines.push(Line::from(tr("Only the private key is split, not the seed phrase. Restore with `solace restore`.")));
+            lines.push(Line::from(tr(
+                "Press Enter to save, Tab to switch fields, Esc to go back",
+            )));
+        }
+    }
+
+    if let Some(ref status) = export.status {
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(
+            status.clone(),
+            Style::default().fg(app.theme.success),
+        )));
+    }
+
+    if let Some(ref error) = export.error {
+        lines.push(Line::from(""));
+        lines.extend(error.lines(&app.theme, app.retry_key()));
+    }
+
+    Paragraph::new(lines)
+        .style(Style::default().fg(app.theme.text))
+        .wrap(Wrap { trim: false })
+        .block(Block::default().borders(Borders::ALL).title(tr("Export")))
+}
+
+fn render_sign(app: &App) -> Paragraph<'static> {
+    let sign = &app.sign_state;
+    let mut lines = vec![
+        Line::from(tr("Sign Message")),
+        Line::from(""),
+        Line::from(tr(
+            "Signs text with the wallet key as a Solana off-chain message, to prove you own",
+        )),
+        Line::from(tr_fmt(
+            "{} without sending a transaction.",
+            &[&app.wallet.address],
+        )),
+        Line::from(""),
+        Line::from(tr("Message:")),
+        Line::from(
+            sign.message
+                .spans(Style::default().fg(app.theme.accent), app.plain.cursor()),
+        ),
+        Line::from(""),
+    ];
+    match sign.signature {
+        Some(signature) => {
+            lines.push(Line::from(tr("Signature:")));
+            lines.push(Line::from(Span::styled(
+                signature.to_string(),
+                Style::default().fg(app.theme.success),
+            )));
+            lines.push(Line::from(""));
+            lines.push(Line::from(tr(
+                "Edit the message to sign another, Esc to leave",
+            )));
+        }
+        None => lines.push(Line::from(tr("Press Enter to sign, Esc to cancel"))),
+    }
+
+    if let Some(ref error) = sign.error {
+        lines.push(Line::from(""));
+        lines.extend(error.lines(&app.theme, app.retry_key()));
+    }
+
+    Paragraph::new(lines)
+        .style(Style::default().fg(app.theme.text))
+        .wrap(Wrap { trim: false })
+        .block(Block::default().borders(Borders::ALL).title(tr("Sign")))
+}
+
+fn render_verify(app: &App) -> Paragraph<'static> {
+    let verify = &app.verify_state;
+    let mut lines = vec![
+        Line::from(tr("Verify Message")),
+        Line::from(""),
+        Line::from(tr(
+            "Checks an off-chain message signature, such as an ownership proof.",
+        )),
+        Line::from(""),
+    ];
+    let fields = [
+        ("Message:", &verify.message, VerifyStep::Message),
+        ("Signed by:", &verify.address, VerifyStep::Address),
+        ("Signature:", &verify.signature, VerifyStep::Signature),
+    ];
+    for (label, field, step) in fields {
+        lines.push(Line::from(tr(label)));
+        lines.push(Line::from(if step == verify.step {
+            field.spans(Style::default().fg(app.theme.accent), app.plain.cursor())
+        } else {
+            vec![Span::raw(field.to_string())]
+        }));
+        lines.push(Line::from(""));
+    }
+    lines.push(Line::from(tr(match verify.step {
+        VerifyStep::Message => "Press Enter to continue, Esc to cancel",
+        VerifyStep::Address => "Press Enter to continue, Esc to go back",
+        VerifyStep::Signature => "Press Enter to verify, Esc to go back",
+    })));
+
+    if let Some(address) = verify.verified {
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(
+            tr_fmt("Valid: this message was signed by {}", &[&address]),
+            Style::default()
+                .fg(app.theme.success)
+                .add_modifier(Modifier::BOLD),
+        )));
+    }
+    if let Some(ref error) = verify.error {
+        lines.push(Line::from(""));
+        lines.extend(error.lines(&app.theme, app.retry_key()));
+    }
+
+    Paragraph::new(lines)
+        .style(Style::default().fg(app.theme.text))
+        .wrap(Wrap { trim: false })
+        .block(Block::default().borders(Borders::ALL).title(tr("Verify")))
+}
+
+fn render_sign_in(app: &App) -> Paragraph<'static> {
+    let sign_in = &app.sign_in_state;
+    let mut lines = vec![Line::from(tr("Sign In With Solana")), Line::from("")];
+
+    if let Some(ref response) = sign_in.response {
+        lines.push(Line::from(Span::styled(
+            tr("Signed. Give this response to the site:"),
+            Style::default().fg(app.theme.success),
+        )));
+        lines.push(Line::from(""));
+        lines.extend(response.lines().map(|line| Line::from(line.to_string())));
+        lines.push(Line::from(""));
+        lines.push(Line::from(tr("Press Esc to answer another request")));
+    } else if let Some(ref request) = sign_in.request {
+        lines.push(Line::from(Span::styled(
+            tr_fmt("{} wants you to sign in", &[&request.domain]),
+            Style::default()
+                .fg(app.theme.heading)
+                .add_modifier(Modifier::BOLD),
+        )));
+        lines.push(Line::from(tr_fmt("as {}", &[&app.wallet.address])));
+        lines.push(Line::from(""));
+        if let Some(ref statement) = request.statement {
+            lines.push(Line::from(format!("\"{}\"", statement)));
+            lines.push(Line::from(""));
+        }
+        let fields = [
+            ("URI", &request.uri),
+            ("Chain", &request.chain_id),
+            ("Nonce", &request.nonce),
+            ("Issued at", &request.issued_at),
+            ("Expires", &request.expiration_time),
+            ("Not before", &request.not_before),
+            ("Request ID", &request.request_id),
+        ];
+        for (name, value) in fields {
+            if let Some(value) = value {
+                lines.push(Line::from(format!("{}: {}", tr(name), value)));
+            }
+        }
+        for resource in &request.resources {
+            lines.push(Line::from(tr_fmt("Resource: {}", &[resource])));
+        }
+        for warning in request.warnings() {
+            lines.push(Line::from(""));
+            lines.push(Line::from(Span::styled(
+                tr_fmt("Warning: {}", &[&warning]),
+                Style::default()
+                    .fg(app.theme.error)
+                    .add_modifier(Modifier::BOLD),
+            )));
+        }
+        lines.push(Line::from(""));
+        lines.push(Line::from(tr("Press Enter to sign in, Esc to cancel")));
+    } else {
+        lines.push(Line::from(tr(
+            "Paste a sign-in request as JSON, or the path of a file holding one:",
+        )));
+        lines.push(Line::from(
+            sign_in
+                .input
+                .spans(Style::default().fg(app.theme.accent), app.plain.cursor()),
+        ));
+        lines.push(Line::from(""));
+        lines.push(Line::from(tr("Press Enter to review it, Esc to cancel")));
+    }
+
+    if let Some(ref error) = sign_in.error {
+        lines.push(Line::from(""));
+        lines.extend(error.lines(&app.theme, app.retry_key()));
+    }
+
+    Paragraph::new(lines)
+        .style(Style::default().fg(app.theme.text))
+        .wrap(Wrap { trim: false })
+        .block(Block::default().borders(Borders::ALL).title(tr("Sign In")))
+}
+
+fn render_inspect(app: &App) -> Paragraph<'static> {
+    let inspect = &app.inspect_state;
+    let heading = Style::default()
+        .fg(app.theme.heading)
+        .add_modifier(Modifier::BOLD);
+    let muted = Style::default().fg(app.theme.muted);
+    let warning = Style::default()
+        .fg(app.theme.error)
+        .add_modifier(Modifier::BOLD);
+    let mut lines = vec![Line::from(tr("Inspect Transaction")), Line::from("")];
+
+    let Some(ref inspection) = inspect.inspection else {
+        lines.push(Line::from(tr(
+            "Paste a base64 or base58 encoded transaction, or the path of a file holding one:",
+        )));
+        lines.push(Line::from(
+            inspect
+                .input
+                .spans(Style::default().fg(app.theme.accent), app.plain.cursor()),
+        ));
+        lines.push(Line::from(""));
+        lines.push(Line::from(tr("Press Enter to inspect it, Esc to cancel")));
+        if let Some(ref error) = inspect.error {
+            lines.push(Line::from(""));
+            lines.extend(error.lines(&app.theme, app.retry_key()));
+        }
+        return Paragraph::new(lines)
+            .style(Style::default().fg(app.theme.text))
+            .wrap(Wrap { trim: false })
+            .block(Block::default().borders(Borders::ALL).title(tr("Inspect")));
+    };
+
+    let wallet = app.wallet.address;
+    let you = |address: &Pubkey| {
+        if *address == wallet {
+            tr(" (this wallet)")
+        } else {
+            ""
+        }
+    };
+    if let Some(payer) = inspection.fee_payer() {
+        lines.push(Line::from(tr_fmt(
+            "Fee payer: {}{}",
+            &[&payer, &you(&payer)],
+        )));
+    }
+    // What's about to be signed is shown in full
+    let amounts = app.amounts.exact();
+    let fee = inspection
+        .fee
+        .map_or(tr("unknown").to_string(), |fee| amounts.sol(fee));
+    lines.push(Line::from(tr_fmt(
+        "Fee: {}, {} transaction",
+        &[&fee, &inspection.version()],
+    )));
+
+    lines.push(Line::from(""));
+    lines.push(Line::styled(tr("Accounts"), heading));
+    for account in &inspection.accounts {
+        let mut role = vec![tr(if account.writable {
+            "writable"
+        } else {
+            "read-only"
+        })];
+        if account.signer {
+            role.push(tr(if account.signed { "signed" } else { "signer" }));
+        }
+        let table = account
+            .lookup_table
+            .map(|table| tr_fmt(", from lookup table {}", &[&table]))
+            .unwrap_or_default();
+        lines.push(Line::from(vec![
+            Span::raw(format!("  {}{} ", account.address, you(&account.address))),
+            Span::styled(role.join(", ") + table.as_str(), muted),
+        ]));
+        if let Some(reason) = app.blocklist.reason(&account.address) {
+            lines.push(Line::styled(
+                tr_fmt("    On the blocklist: {}", &[&reason]),
+                warning,
+            ));
+        }
+    }
+
+    for (i, instruction) in inspection.instructions.iter().enumerate() {
+        lines.push(Line::from(""));
+        let program = match instruction.program_name {
+            Some(ref name) => format!("{} ({})", name, instruction.program),
+            None => instruction.program.to_string(),
+        };
+        let mut title = vec![Span::styled(format!("#{} {}", i + 1, program), heading)];
+        match instruction.decoded {
+            Some(ref decoded) => title.push(Span::raw(format!("  {}", decoded.name))),
+            None => title.push(Span::styled(
+                tr_fmt("  {} bytes of data, no IDL", &[&instruction.data_len]),
+                muted,
+            )),
+        }
+        lines.push(Line::from(title));
+        let decoded = instruction.decoded.as_ref();
+        for (name, value) in decoded.map_or(&[][..], |decoded| decoded.args.as_slice()) {
+            lines.push(Line::from(format!("    {}: {}", name, value)));
+        }
+        for (j, account) in instruction.accounts.iter().enumerate() {
+            let name = decoded
+                .and_then(|decoded| decoded.account_names.get(j))
+                .cloned()
+                .unwrap_or_else(|| tr_fmt("account {}", &[&(j + 1)]));
+            lines.push(Line::from(format!("    {}: {}", name, account)));
+        }
+    }
+
+    lines.push(Line::from(""));
+    lines.push(Line::styled(tr("Simulation"), heading));
+    match inspection.simulation {
+        Ok(ref simulation) => {
+            match simulation.error {
+                Some(ref error) => {
+                    lines.push(Line::styled(tr_fmt("  Fails: {}", &[error]), warning));
+                }
+                None => lines.push(Line::styled(
+                    tr("  Succeeds"),
+                    Style::default().fg(app.theme.success),
+                )),
+            }
+            if let Some(units) = simulation.units_consumed {
+                lines.push(Line::from(tr_fmt("  Compute units: {}", &[&units])));
+            }
+            for (address, change) in &simulation.balance_changes {
+                lines.push(Line::from(format!(
+                    "  {}{}: {} SOL",
+                    address,
+                    you(address),
+                    amounts.change(*change, history::SOL_DECIMALS)
+                )));
+            }
+            if simulation.balance_changes.is_empty() {
+                lines.push(Line::from(tr("  No SOL balances change")));
+            }
+            for log in &simulation.logs {
+                lines.push(Line::styled(format!("  {}", log), muted));
+            }
+        }
+        Err(ref error) => {
+            lines.push(Line::styled(
+                tr_fmt("  Not simulated: {}", &[error]),
+                warning,
+            ));
+        }
+    }
+
+    lines.push(Line::from(""));
+    let signed = inspection
+        .accounts
+        .iter()
+        .all(|account| !account.signer || account.signed);
+    let fee_payer = app.wallet.fee_payer();
+    let others_sign = inspection.accounts.iter().any(|account| {
+        account.signer
+            && !account.signed
+            && account.address != wallet
+            && account.address != fee_payer
+    });
+    if let Some(ref send) = app.inspect_task {
+        lines.extend(confirmation_lines(app, &send.task, &send.tracker));
+    } else if let Some((ref encoded, ref missing)) = inspect.partially_signed {
+        lines.push(Line::from(tr(
+            "Signed by this wallet. It still needs signatures from:",
+        )));
+        for key in missing {
+            lines.push(Line::from(format!("  {}", key)));
+        }
+        lines.push(Line::from(""));
+        lines.push(Line::from(tr(
+            "Pass this on to them, then paste the copy they sign here to send it:",
+        )));
+        lines.push(Line::styled(encoded.clone(), muted));
+        lines.push(Line::from(""));
+        lines.push(Line::from(tr("Press Esc to inspect another")));
+    } else if signed {
+        lines.push(Line::from(tr(
+            "Already signed by every signer. Press Enter to send it, Esc to inspect another",
+        )));
+    } else if others_sign {
+        lines.push(Line::from(tr(
+            "Other signers sign it too. Press Enter to sign it for them, Esc to inspect another",
+        )));
+    } else {
+        lines.push(Line::from(tr(
+            "Press Enter to sign and send it, Esc to inspect another",
+        )));
+    }
+    if let Some(ref error) = inspect.error {
+        lines.push(Line::from(""));
+        lines.extend(error.lines(&app.theme, app.retry_key()));
+    }
+
+    Paragraph::new(lines)
+        .style(Style::default().fg(app.theme.text))
+        .wrap(Wrap { trim: false })
+        .block(Block::default().borders(Borders::ALL).title(tr("Inspect")))
+}
+
+/// Most of the data shown as hex; the rest is counted
+const DUMP_LINES: usize = 64;
+
+fn render_lookup(app: &App) -> Paragraph<'static> {
+    let lookup = &app.lookup_state;
+    let label = Style::default().fg(app.theme.heading);
+    let mut lines = vec![
+        Line::from(tr("Account Lookup")),
+        Line::from(""),
+        Line::from(tr(
+            "Address, or a data length in bytes for its rent-exempt minimum:",
+        )),
+        Line::from(
+            lookup
+                .input
+                .spans(Style::default().fg(app.theme.accent), app.plain.cursor()),
+        ),
+        Line::from(""),
+    ];
+
+    let sol = |lamports: u64| app.amounts.sol(lamports);
+    // The field Ctrl+Y copies stands out; the order is `LookupResult::fields`
+    let field_style = |field: usize| {
+        if field == lookup.field {
+            Style::default()
+                .fg(app.theme.accent)
+                .add_modifier(Modifier::BOLD | Modifier::REVERSED)
+        } else {
+            Style::default()
+        }
+    };
+    match lookup.result {
+        Some(LookupResult::Rent {
+            data_len,
+            minimum,
+            ref common,
+        }) => {
+            lines.push(Line::from(vec![
+                Span::styled(tr_fmt("{} bytes: ", &[&data_len]), label),
+                Span::raw(tr_fmt(
+                    "{} ({} lamports) to be rent exempt",
+                    &[&sol(minimum), &minimum],
+                )),
+            ]));
+            lines.push(Line::from(""));
+            lines.push(Line::styled(
+                tr("Common accounts"),
+                label.add_modifier(Modifier::BOLD),
+            ));
+            for &(name, data_len, minimum) in common {
+                lines.push(Line::from(format!(
+                    "  {:<16}{:>6} {}  {}",
+                    tr(name),
+                    data_len,
+                    tr("bytes"),
+                    sol(minimum)
+                )));
+            }
+            lines.push(Line::from(""));
+        }
+        Some(LookupResult::Account(address, None)) => {
+            lines.push(Line::from(vec![
+                Span::raw(tr("No account at ")),
+                Span::styled(address.to_string(), field_style(0)),
+            ]));
+            lines.push(Line::from(""));
+        }
+        Some(LookupResult::Account(address, Some(ref info))) => {
+            let account = &info.account;
+            let owner = match info.owner_name {
+                Some(name) => format!("{} ({})", name, account.owner),
+                None => account.owner.to_string(),
+            };
+            let rent = match info.rent_exempt_minimum {
+                Some(minimum) if account.lamports >= minimum => {
+                    tr_fmt("rent exempt, minimum {}", &[&sol(minimum)])
+                }
+                Some(minimum) => tr_fmt("not rent exempt, minimum {}", &[&sol(minimum)]),
+                None => tr("rent exemption unknown").to_string(),
+            };
+            let rows = [
+                ("Address", address.to_string(), field_style(0)),
+                (
+                    "Balance",
+                    tr_fmt(
+                        "{} ({} lamports, {})",
+                        &[&sol(account.lamports), &account.lamports, &rent],
+                    ),
+                    Style::default(),
+                ),
+                ("Owner", owner, field_style(1)),
+                (
+                    "Executable",
+                    account.executable.to_string(),
+                    Style::default(),
+                ),
+                (
+                    "Rent epoch",
+                    account.rent_epoch.to_string(),
+                    Style::default(),
+                ),
+                (
+                    "Data",
+                    tr_fmt("{} bytes", &[&account.data.len()]),
+                    Style::default(),
+                ),
+            ];
+            for (name, value, style) in rows {
+                lines.push(Line::from(vec![
+                    Span::styled(format!("{}: ", tr(name)), label),
+                    Span::styled(value, style),
+                ]));
+            }
+
+            if let Some((ref layout, ref parsed)) = info.parsed {
+                lines.push(Line::from(""));
+                lines.push(Line::styled(
+                    tr_fmt("Decoded as {}", &[layout]),
+                    label.add_modifier(Modifier::BOLD),
+                ));
+                for (i, (name, value)) in accountinfo::fields(parsed).into_iter().enumerate() {
+                    lines.push(Line::from(vec![
+                        Span::styled(format!("  {}: ", name), label),
+                        Span::styled(value, field_style(i + 2)),
+                    ]));
+                }
+            }
+
+            if !account.data.is_empty() {
+                lines.push(Line::from(""));
+                let dump = accountinfo::hex_dump(&account.data);
+                let shown = dump.len().min(DUMP_LINES);
+                lines.extend(
+                    dump.into_iter()
+                        .take(shown)
+                        .map(|line| Line::styled(line, Style::default().fg(app.theme.muted))),
+                );
+                let hidden = account
+                    .data
+                    .len()
+                    .saturating_sub(shown * accountinfo::DUMP_WIDTH);
+                if hidden > 0 {
+                    lines.push(Line::from(tr_fmt("... {} more bytes", &[&hidden])));
+                }
+            }
+            lines.push(Line::from(""));
+        }
+        None => {}
+    }
+    lines.push(Line::from(tr("Press Enter to look it up, Esc to leave")));
+
+    if let Some(ref error) = lookup.error {
+        lines.push(Line::from(""));
+        lines.extend(error.lines(&app.theme, app.retry_key()));
+    }
+
+    Paragraph::new(lines)
+        .style(Style::default().fg(app.theme.text))
+        .wrap(Wrap { trim: false })
+        .block(Block::default().borders(Borders::ALL).title(tr("Lookup")))
+}
+
+fn render_nonce(app: &App) -> Paragraph<'static> {
+    let state = &app.nonce_state;
+    let muted = Style::default().fg(app.theme.muted);
+    let mut lines = vec![Line::from(tr("Nonce Accounts")), Line::from("")];
+
+    for (i, account) in state.accounts.iter().enumerate() {
+        let style = if i == state.selected {
+            Style::default()
+                .fg(app.theme.accent)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(app.theme.text)
+        };
+        lines.push(Line::styled(
+            format!(
+                "{:>2}  {}  {}",
+                account.index.unwrap_or_default(),
+                account.address,
+                app.amounts.sol(account.lamports)
+            ),
+            style,
+        ));
+    }
+    if state.accounts.is_empty() && state.error.is_none() {
+        lines.push(Line::styled(
+            tr("No nonce accounts yet. Signing offline needs one: its stored nonce takes the place of a recent blockhash, which expires within minutes."),
+            muted,
+        ));
+    }
+
+    if let Some(account) = state.selected_account() {
+        lines.push(Line::from(""));
+        lines.push(Line::from(vec![
+            Span::raw(tr("Nonce:     ")),
+            Span::styled(
+                account.nonce.to_string(),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+        ]));
+        let authority = if account.authority == app.wallet.address {
+            tr_fmt("{} (this wallet)", &[&account.authority])
+        } else {
+            account.authority.to_string()
+        };
+        lines.push(Line::from(tr_fmt("Authority: {}", &[&authority])));
+        lines.push(Line::from(tr_fmt(
+            "Fee:       {} lamports per signature",
+            &[&account.lamports_per_signature],
+        )));
+        lines.push(Line::from(""));
+        lines.push(Line::styled(tr("Sign a transfer with it offline:"), muted));
+        lines.push(Line::styled(
+            format!(
+                "  solace send --to <ADDRESS> --amount <SOL> --sign-only --nonce {} --blockhash {}",
+                account.address, account.nonce
+            ),
+            muted,
+        ));
+        lines.push(Line::styled(
+            tr("then send it from a connected machine with `solace broadcast` or the Inspect screen."),
+            muted,
+        ));
+    }
+
+    let confirm = |text: String| {
+        Line::styled(
+            text,
+            Style::default()
+                .fg(app.theme.accent)
+                .add_modifier(Modifier::BOLD),
+        )
+    };
+    match state.step {
+        NonceStep::List => {}
+        NonceStep::EnteringWithdrawal => {
+            lines.push(Line::from(""));
+            let mut spans = vec![Span::raw(tr_fmt(
+                "{} to withdraw to the wallet: ",
+                &[&app.amounts.unit().name()],
+            ))];
+            spans.extend(
+                state
+                    .amount
+                    .spans(Style::default().fg(app.theme.text), app.plain.cursor()),
+            );
+            lines.push(Line::from(spans));
+            lines.push(Line::styled(
+                tr("Withdrawing everything closes the account"),
+                muted,
+            ));
+        }
+        NonceStep::Confirming(action) => {
+            lines.push(Line::from(""));
+            let amounts = app.amounts.exact();
+            lines.push(confirm(match action {
+                NonceAction::Create(lamports) => tr_fmt(
+                    "Create nonce account #{} with {} (the rent-exempt minimum)? [y/N]",
+                    &[&state.free.unwrap_or_default(), &amounts.sol(lamports)],
+                ),
+                NonceAction::Advance => tr(
+                    "Advance the nonce? Anything signed with the current one can't be sent after this. [y/N]",
+                )
+                .to_string(),
+                NonceAction::Withdraw(lamports) => {
+                    tr_fmt("Withdraw {} to the wallet? [y/N]", &[&amounts.sol(lamports)])
+                }
+            }));
+        }
+    }
+    if app.nonce_task.is_some() {
+        lines.push(Line::from(""));
+        lines.push(Line::from(tr("Sending...")));
+    }
+
+    if let Some(ref error) = state.error {
+        lines.push(Line::from(""));
+        lines.extend(error.lines(&app.theme, app.retry_key()));
+    }
+
+    Paragraph::new(lines)
+        .style(Style::default().fg(app.theme.text))
+        .wrap(Wrap { trim: false })
+        .block(Block::default().borders(Borders::ALL).title(tr("Nonce")))
+}
+
+fn render_import(app: &App) -> Paragraph<'static> {
+    let import = &app.import_state;
+    let mut lines = vec![Line::from(tr("Import Private Key")), Line::from("")];
+
+    match import.step {
+        ImportStep::EnteringKey => {
+            lines.push(Line::from(tr(
+                "Paste the base58 private key exported from Phantom or Solflare:",
+            )));
+            lines.push(Line::from(Span::styled(
+                format!(
+                    "{}{}",
+                    "*".repeat(import.key_input.chars().count()),
+                    app.plain.cursor()
+                ),
+                Style::default().fg(app.theme.accent),
+            )));
+            lines.push(Line::from(""));
+            lines.push(Line::from(tr("Press Enter to continue, Esc to cancel")));
+        }
+        ImportStep::EnteringPath => {
+            if let Some(ref keypair) = import.keypair {
+                lines.push(Line::from(tr_fmt("Address: {}", &[&keypair.pubkey()])));
+                lines.push(Line::from(""));
+            }
+            lines.push(Line::from(tr("Save keypair file to:")));
+            lines.push(Line::from(Span::styled(
+                format!("{}{}", import.path_input, app.plain.cursor()),
+                Style::default().fg(app.theme.accent),
+            )));
+            lines.push(Line::from(""));
+            lines.push(Line::from(tr("Press Enter to save, Esc to start over")));
+        }
+    }
+
+    if let Some(ref error) = import.error {
+        lines.push(Line::from(""));
+        lines.extend(error.lines(&app.theme, app.retry_key()));
+    }
+
+    if let Some(ref status) = import.status {
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(
+            status.clone(),
+            Style::default().fg(app.theme.success),
+        )));
+    }
+
+    Paragraph::new(lines)
+        .style(Style::default().fg(app.theme.text))
+        .wrap(Wrap { trim: false })
+        .block(Block::default().borders(Borders::ALL).title(tr("Import")))
+}
+
+fn render_accounts(app: &App) -> Paragraph<'static> {
+    let accounts = &app.accounts_state;
+    let mut lines = vec![Line::from(tr("Seed Phrase Accounts")), Line::from("")];
+
+    for (i, account) in accounts.accounts.iter().enumerate() {
+        let active = account.address == app.wallet.address;
+        let style = if i == accounts.selected {
+            Style::default()
+                .fg(app.theme.accent)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(app.theme.text)
+        };
+        lines.push(Line::from(Span::styled(
+            format!(
+                "{} {:<18} {}  {}",
+                if active { "*" } else { " " },
+                account.derivation.to_string(),
+                account.address,
+                app.amounts.sol(account.lamports)
+            ),
+            style,
+        )));
+    }
+
+    if !accounts.accounts.is_empty() {
+        lines.push(Line::from(""));
+        lines.push(Line::from(tr("* active account")));
+    }
+
+    if let Some(ref query) = accounts.search {
+        lines.push(Line::from(tr_fmt(
+            "Search: {}{}",
+            &[query, &app.plain.cursor()],
+        )));
+    }
+
+    if let Some(ref error) = accounts.error {
+        lines.push(Line::from(""));
+        lines.extend(error.lines(&app.theme, app.retry_key()));
+    }
+
+    Paragraph::new(lines)
+        .style(Style::default().fg(app.theme.text))
+        .block(Block::default().borders(Borders::ALL).title(tr("Accounts")))
+}
+
+fn render_vanity(app: &App) -> Paragraph<'static> {
+    let vanity = &app.vanity_state;
+    let mut lines = vec![Line::from(tr("Vanity Address Generator")), Line::from("")];
+
+    match vanity.step {
+        VanityStep::EnteringPrefix | VanityStep::EnteringSuffix => {
+            let editing_prefix = vanity.step == VanityStep::EnteringPrefix;
+            let field = |value: &str, editing: bool| {
+                if editing {
+                    Span::styled(
+                        format!("{}{}", value, app.plain.cursor()),
+                        Style::default().fg(app.theme.accent),
+                    )
+                } else {
+                    Span::raw(value.to_string())
+                }
+            };
+            lines.push(Line::from(vec![
+                Span::raw(tr("Starts with: ")),
+                field(&vanity.prefix, editing_prefix),
+            ]));
+            lines.push(Line::from(vec![
+                Span::raw(tr("Ends with:   ")),
+                field(&vanity.suffix, !editing_prefix),
+            ]));
+            lines.push(Line::from(tr_fmt(
+                "Ignore case: {}",
+                &[&tr(if vanity.ignore_case { "yes" } else { "no" })],
+            )));
+            lines.push(Line::from(""));
+            lines.push(Line::from(tr(
+                "Each extra character makes the search ~58x longer",
+            )));
+            lines.push(Line::from(""));
+            lines.push(Line::from(tr(
+                "Press Enter to continue, Tab to toggle case, Esc to go back",
+            )));
+        }
+        VanityStep::Searching => {
+            if let Some(ref grinder) = vanity.grinder {
+                lines.push(Line::from(tr_fmt("Pattern: {}", &[&grinder.pattern()])));
+                lines.push(Line::from(tr_fmt("Threads: {}", &[&grinder.threads()])));
+                lines.push(Line::from(tr_fmt(
+                    "Searched: {} keys ({} keys/s)",
+                    &[&grinder.attempts(), &format!("{:.0}", grinder.rate())],
+                )));
+                lines.push(Line::from(tr_fmt(
+                    "Expected: ~{} keys",
+                    &[&format!("{:.0}", grinder.pattern().expected_attempts())],
+                )));
+            }
+            lines.push(Line::from(""));
+            lines.push(Line::from(tr("Press Esc to stop")));
+        }
+        VanityStep::Saving => {
+            if let Some(ref keypair) = vanity.keypair {
+                lines.push(Line::from(Span::styled(
+                    tr_fmt("Found: {}", &[&keypair.pubkey()]),
+                    Style::default()
+                        .fg(app.theme.success)
+                        .add_modifier(Modifier::BOLD),
+                )));
+                lines.push(Line::from(""));
+            }
+            lines.push(Line::from(tr("Save keypair file to:")));
+            lines.push(Line::from(Span::styled(
+                format!("{}{}", vanity.path_input, app.plain.cursor()),
+                Style::default().fg(app.theme.accent),
+            )));
+            lines.push(Line::from(""));
+            lines.push(Line::from(tr("Press Enter to save, Esc to go back")));
+        }
+    }
+
+    if let Some(ref error) = vanity.error {
+        lines.push(Line::from(""));
+        lines.extend(error.lines(&app.theme, app.retry_key()));
+    }
+
+    if let Some(ref status) = vanity.status {
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(
+            status.clone(),
+            Style::default().fg(app.theme.success),
+        )));
+    }
+
+    Paragraph::new(lines)
+        .style(Style::default().fg(app.theme.text))
+        .block(Block::default().borders(Borders::ALL).title(tr("Vanity")))
+}
@@ -0,0 +1,401 @@
+//! The audit log: every signature the wallet's keys make, with what was
+//! signed, appended to `<data dir>/solace/audit.jsonl`. Each record holds
+//! the hash of the one before it, so a record edited or removed breaks the
+//! chain from there on; noting down the latest hash elsewhere also catches
+//! the whole file being rewritten.
+//!
+//! Signing goes through `SecretKeypair`, which records here once `init` has
+//! run, so nothing that signs can leave it out. A record that can't be
+//! written doesn't stop the signature, but is counted and shown with the
+//! log. The TUI, the daemon and the CLI may all be signing at once, so each
+//! record is appended under a lock on the file, chained to the last record
+//! in it rather than the last this process wrote.
+
+use crate::{
+    config::data_dir,
+    history::format_datetime,
+    inspector::{sol_transfers, SolTransfer},
+    report::csv_field,
+};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use solana_sdk::{hash::hashv, message::VersionedMessage, pubkey::Pubkey, signature::Signature};
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex, OnceLock,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// What comes before the first record
+const GENESIS: &str = "";
+
+static LOG: OnceLock<AuditLog> = OnceLock::new();
+
+/// What a signature was for
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Signed {
+    /// A transaction sending SOL
+    Transfer,
+    /// Any other transaction
+    Transaction,
+    /// An off-chain message or sign-in
+    Message,
+}
+
+impl Signed {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Transfer => "transfer",
+            Self::Transaction => "transaction",
+            Self::Message => "message",
+        }
+    }
+}
+
+/// One signature, as a line of the log
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    /// Position in the log, from 0
+    pub seq: u64,
+    pub timestamp: i64,
+    pub signer: String,
+    pub signature: String,
+    pub signed: Signed,
+    /// For transfers, the first recipient and the SOL sent to all of them
+    pub recipient: Option<String>,
+    pub lamports: Option<u64>,
+    /// The cluster the wallet was connected to, by name or, for a custom
+    /// RPC, the host of its URL
+    pub cluster: String,
+    /// The hash of the record before, empty for the first
+    pub prev: String,
+    /// SHA-256 of `prev` and the record with this left empty
+    pub hash: String,
+}
+
+impl AuditRecord {
+    fn digest(&self) -> String {
+        let body = serde_json::to_string(&Self {
+            hash: String::new(),
+            ..self.clone()
+        })
+        .unwrap_or_default();
+        hashv(&[self.prev.as_bytes(), body.as_bytes()]).to_string()
+    }
+}
+
+/// Whether the log is as it was written
+#[derive(Debug, Clone, PartialEq)]
+pub enum Chain {
+    Intact,
+    /// The first record whose hash or link doesn't match, by position
+    Broken(usize),
+}
+
+struct AuditLog {
+    path: PathBuf,
+    cluster: Mutex<String>,
+    /// Signatures made that couldn't be recorded
+    missed: AtomicUsize,
+}
+
+/// Start recording signatures to the log in the data directory, made while
+/// connected to `cluster`. A log that can't be read, such as one with a
+/// corrupt or partly written line, is an error: records appended to it
+/// couldn't be chained, so signing mustn't go on as if they were.
+pub fn init(cluster: &str) -> Result<()> {
+    let path = data_dir()?.join("audit.jsonl");
+    load_from(&path)?;
+    let log = AuditLog {
+        path,
+        cluster: Mutex::new(cluster.to_string()),
+        missed: AtomicUsize::new(0),
+    };
+    // Already recording if it ran before
+    let _ = LOG.set(log);
+    Ok(())
+}
+
+/// Record a signature `signer` made over `message`, if the log was started
+pub(crate) fn record(signer: &Pubkey, signature: &Signature, message: &[u8]) {
+    let Some(log) = LOG.get() else {
+        return;
+    };
+    if let Err(e) = log.append(signer, signature, message) {
+        log.missed.fetch_add(1, Ordering::Relaxed);
+        tracing::error!(%signature, error = %format!("{:#}", e), "Failed to record signature");
+    }
+}
+
+/// Record the signatures from now on as made while connected to `cluster`
+pub fn set_cluster(cluster: &str) {
+    if let Some(log) = LOG.get() {
+        *log.cluster.lock().unwrap_or_else(|e| e.into_inner()) = cluster.to_string();
+    }
+}
+
+impl AuditLog {
+    fn append(&self, signer: &Pubkey, signature: &Signature, message: &[u8]) -> Result<()> {
+        let (signed, recipient, lamports) = describe(signer, message);
+        // Held until the file is closed at the end, which unlocks it
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("Failed to open the audit log: {}", self.path.display()))?;
+        file.lock()
+            .with_context(|| format!("Failed to lock the audit log: {}", self.path.display()))?;
+        let (seq, prev) = last_record(&self.path)?
+            .map_or((0, GENESIS.to_string()), |last| (last.seq + 1, last.hash));
+        let mut record = AuditRecord {
+            seq,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs() as i64),
+            signer: signer.to_string(),
+            signature: signature.to_string(),
+            signed,
+            recipient: recipient.map(|recipient| recipient.to_string()),
+            lamports,
+            cluster: self
+                .cluster
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .clone(),
+            prev,
+            hash: String::new(),
+        };
+        record.hash = record.digest();
+        writeln!(file, "{}", serde_json::to_string(&record)?)
+            .and_then(|()| file.sync_data())
+            .with_context(|| format!("Failed to write the audit log: {}", self.path.display()))
+    }
+}
+
+/// The record at the end of the log, which the next one is chained to
+fn last_record(path: &Path) -> Result<Option<AuditRecord>> {
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read the audit log: {}", path.display()))?;
+    contents
+        .lines()
+        .rev()
+        .find(|line| !line.trim().is_empty())
+        .map(|line| {
+            serde_json::from_str(line).with_context(|| {
+                format!(
+                    "The last line of the audit log is corrupt: {}",
+                    path.display()
+                )
+            })
+        })
+        .transpose()
+}
+
+/// What `message` is, and for transfers where the SOL goes
+fn describe(signer: &Pubkey, message: &[u8]) -> (Signed, Option<Pubkey>, Option<u64>) {
+    // Anything else that happens to parse wouldn't serialize back the same
+    let Some(parsed) = bincode::deserialize::<VersionedMessage>(message)
+        .ok()
+        .filter(|parsed| bincode::serialize(parsed).is_ok_and(|bytes| bytes == message))
+    else {
+        return (Signed::Message, None, None);
+    };
+    let transfers: Vec<SolTransfer> = sol_transfers(&parsed)
+        .into_iter()
+        .filter(|transfer| transfer.from == *signer)
+        .collect();
+    let recipient = transfers.first().map(|transfer| transfer.to);
+    let lamports = transfers.iter().map(|transfer| transfer.lamports).sum();
+    match recipient {
+        Some(recipient) => (Signed::Transfer, Some(recipient), Some(lamports)),
+        None => (Signed::Transaction, None, None),
+    }
+}
+
+/// The records in the log in the data directory, oldest first
+pub fn load() -> Result<Vec<AuditRecord>> {
+    load_from(&data_dir()?.join("audit.jsonl"))
+}
+
+fn load_from(path: &Path) -> Result<Vec<AuditRecord>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read the audit log: {}", path.display()))?;
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            serde_json::from_str(line).with_context(|| {
+                format!(
+                    "Line {} of the audit log is corrupt: {}",
+                    i + 1,
+                    path.display()
+                )
+            })
+        })
+        .collect()
+}
+
+/// Check every record's hash and its link to the one before
+pub fn verify(records: &[AuditRecord]) -> Chain {
+    let mut prev = GENESIS;
+    for (i, record) in records.iter().enumerate() {
+        if record.seq != i as u64 || record.prev != prev || record.hash != record.digest() {
+            return Chain::Broken(i);
+        }
+        prev = &record.hash;
+    }
+    Chain::Intact
+}
+
+/// Signatures this run made that couldn't be recorded
+pub fn missed() -> usize {
+    LOG.get()
+        .map_or(0, |log| log.missed.load(Ordering::Relaxed))
+}
+
+/// Whether the chain holds, with the hash to note down elsewhere
+pub fn summary(records: &[AuditRecord], chain: &Chain) -> String {
+    match (chain, records.last()) {
+        (Chain::Broken(i), _) => format!(
+            "The chain is broken at record {}: it or one before it was changed or removed",
+            i
+        ),
+        (Chain::Intact, None) => "No signatures recorded yet".to_string(),
+        (Chain::Intact, Some(last)) => format!(
+            "{} signatures, chain intact; latest hash {}",
+            records.len(),
+            last.hash
+        ),
+    }
+}
+
+/// One line per record for the terminal
+pub fn format_record(record: &AuditRecord) -> String {
+    let what = match (&record.recipient, record.lamports) {
+        (Some(recipient), Some(lamports)) => format!(
+            "{} SOL to {}",
+            crate::token::format_amount(lamports, crate::history::SOL_DECIMALS),
+            recipient
+        ),
+        _ => record.signed.label().to_string(),
+    };
+    format!(
+        "{:>4}  {}  {}  {}  {}",
+        record.seq,
+        format_datetime(record.timestamp),
+        what,
+        record.signature,
+        record.cluster
+    )
+}
+
+/// The records as CSV, or as a JSON array for `.json` paths
+pub fn export(records: &[AuditRecord], path: &Path) -> Result<()> {
+    let json = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+    let contents = if json {
+        serde_json::to_string_pretty(records)?
+    } else {
+        let mut lines = vec![
+            "seq,time,signer,signature,signed,recipient,lamports,cluster,prev,hash".to_string(),
+        ];
+        lines.extend(records.iter().map(|record| {
+            [
+                record.seq.to_string(),
+                format_datetime(record.timestamp),
+                record.signer.clone(),
+                record.signature.clone(),
+                record.signed.label().to_string(),
+                record.recipient.clone().unwrap_or_default(),
+                record.lamports.map(|l| l.to_string()).unwrap_or_default(),
+                record.cluster.clone(),
+                record.prev.clone(),
+                record.hash.clone(),
+            ]
+            .iter()
+            .map(|field| csv_field(field))
+            .collect::<Vec<_>>()
+            .join(",")
+        }));
+        lines.join("\n") + "\n"
+    };
+    fs::write(path, contents).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[allow(deprecated)]
+    use solana_sdk::system_instruction;
+    use solana_sdk::{
+        hash::Hash,
+        message::Message,
+        signature::{Keypair, Signer},
+    };
+
+    #[test]
+    fn chains_records_so_edits_show() {
+        let path = std::env::temp_dir().join(format!("solace-test-{}.jsonl", Pubkey::new_unique()));
+        let keypair = Keypair::new();
+        let recipient = Pubkey::new_unique();
+        let open = || AuditLog {
+            path: path.clone(),
+            cluster: Mutex::new("http://localhost:8899".to_string()),
+            missed: AtomicUsize::new(0),
+        };
+        // Two processes signing, like the TUI and the daemon
+        let log = open();
+        let other = open();
+        let transfer = Message::new_with_blockhash(
+            &[system_instruction::transfer(
+                &keypair.pubkey(),
+                &recipient,
+                5,
+            )],
+            Some(&keypair.pubkey()),
+            &Hash::default(),
+        );
+        let bytes = VersionedMessage::Legacy(transfer).serialize();
+        log.append(&keypair.pubkey(), &keypair.sign_message(&bytes), &bytes)
+            .unwrap();
+        let text = b"Sign in to example.com";
+        other
+            .append(&keypair.pubkey(), &keypair.sign_message(text), text)
+            .unwrap();
+
+        let mut records = load_from(&path).unwrap();
+
+        // A line cut short, as by a crash while writing it
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        write!(file, "{{\"seq\":2,").unwrap();
+        assert!(load_from(&path).is_err());
+        assert!(log
+            .append(&keypair.pubkey(), &keypair.sign_message(text), text)
+            .is_err());
+        fs::remove_file(&path).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].signed, Signed::Transfer);
+        assert_eq!(records[0].recipient, Some(recipient.to_string()));
+        assert_eq!(records[0].lamports, Some(5));
+        assert_eq!(records[1].signed, Signed::Message);
+        assert_eq!(records[1].prev, records[0].hash);
+        assert_eq!(verify(&records), Chain::Intact);
+
+        records[0].lamports = Some(1);
+        assert_eq!(verify(&records), Chain::Broken(0));
+        records[0].lamports = Some(5);
+        records.remove(0);
+        assert_eq!(verify(&records), Chain::Broken(0));
+    }
+}
@@ -0,0 +1,134 @@
+use crate::config::{expand_tilde, BlocklistConfig};
+use crate::task::Task;
+use anyhow::{Context, Result};
+use solana_sdk::pubkey::Pubkey;
+use std::{collections::HashMap, fs, str::FromStr, time::Duration};
+
+const DEFAULT_REASON: &str = "Listed in blocklist";
+
+/// Addresses that must not receive funds without an explicit extra confirmation
+#[derive(Debug, Clone, Default)]
+pub struct Blocklist {
+    entries: HashMap<Pubkey, String>,
+    remote_url: Option<String>,
+}
+
+impl Blocklist {
+    /// Build the blocklist from the inline config entries and the local file.
+    /// The remote list (if configured) is fetched separately with
+    /// [`Blocklist::refresh_remote`] so an unreachable endpoint is not fatal.
+    pub fn load(config: &BlocklistConfig) -> Result<Self> {
+        let mut blocklist = Self {
+            entries: HashMap::new(),
+            remote_url: config.url.clone(),
+        };
+
+        for entry in &config.addresses {
+            let address = Pubkey::from_str(&entry.address)
+                .with_context(|| format!("Invalid blocklist address: {}", entry.address))?;
+            let reason = entry.reason.as_deref().unwrap_or(DEFAULT_REASON);
+            blocklist.entries.insert(address, reason.to_string());
+        }
+
+        if let Some(ref file) = config.file {
+            let path = expand_tilde(file);
+            let contents = fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read blocklist file: {}", path.display()))?;
+            blocklist.extend_from_text(&contents);
+        }
+
+        Ok(blocklist)
+    }
+
+    /// Take `address` off the list
+    pub fn remove(&mut self, address: &Pubkey) {
+        self.entries.remove(address);
+    }
+
+    /// Fetch the remote blocklist and merge it in, returning the number of
+    /// entries it contained
+    pub fn refresh_remote(&mut self) -> Result<usize> {
+        let Some(ref url) = self.remote_url else {
+            return Ok(0);
+        };
+
+        // The blocking client can't be used, or even dropped, on the async
+        // runtime's threads, which the headless commands and daemon run on
+        let url = url.clone();
+        let contents = Task::spawn(move || fetch(&url)).wait()?;
+
+        Ok(self.extend_from_text(&contents))
+    }
+
+    pub fn has_remote(&self) -> bool {
+        self.remote_url.is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Reason an address is blocklisted, if it is
+    pub fn reason(&self, address: &Pubkey) -> Option<&str> {
+        self.entries.get(address).map(String::as_str)
+    }
+
+    /// Parse `<address> [reason]` lines, skipping blanks, `#` comments and
+    /// malformed addresses
+    fn extend_from_text(&mut self, contents: &str) -> usize {
+        let mut count = 0;
+        for line in contents.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (address, reason) = match line.split_once(char::is_whitespace) {
+                Some((address, reason)) => (address, reason.trim()),
+                None => (line, ""),
+            };
+            let Ok(address) = Pubkey::from_str(address) else {
+                continue;
+            };
+            let reason = if reason.is_empty() {
+                DEFAULT_REASON
+            } else {
+                reason
+            };
+            self.entries.insert(address, reason.to_string());
+            count += 1;
+        }
+        count
+    }
+}
+
+/// The remote blocklist's text
+fn fetch(url: &str) -> Result<String> {
+    reqwest::blocking::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()?
+        .get(url)
+        .send()
+        .and_then(|response| response.error_for_status())
+        .and_then(|response| response.text())
+        .with_context(|| format!("Failed to fetch remote blocklist: {}", url))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn fetches_outside_the_async_runtime() {
+        let mut blocklist = Blocklist {
+            entries: HashMap::new(),
+            remote_url: Some("http://127.0.0.1:9/blocklist.txt".to_string()),
+        };
+        // Nothing listens there; the error comes back instead of a panic
+        assert!(blocklist.refresh_remote().is_err());
+    }
+}
@@ -0,0 +1,344 @@
+//! Reads that don't need a roundtrip every time, kept in memory in front of
+//! the RPC client so screens stay quick on slow public nodes without
+//! sending them more requests than needed.
+//!
+//! What never changes (the genesis hash, rent minimums, block times, past
+//! epochs' rewards) is kept for the session. Accounts read with
+//! `get_account`, such as mints, lookup tables, IDLs and DAO accounts, and
+//! program account lookups are kept for the configured time, and dropped
+//! whenever a transaction is sent, since it may have changed them.
+//! Balances, account reads at a commitment, epoch info, blockhashes and
+//! statuses always go to the node. The cache sits in front of a single
+//! endpoint, so nothing cached on one cluster is seen on another.
+
+// `ClientError` is large, but it's what `RpcClient` returns
+#![allow(clippy::result_large_err)]
+
+use crate::rpc::RpcApi;
+use solana_client::{
+    client_error::Result as ClientResult,
+    rpc_client::GetConfirmedSignaturesForAddress2Config,
+    rpc_config::{
+        RpcProgramAccountsConfig, RpcSendTransactionConfig, RpcSimulateTransactionConfig,
+        RpcTransactionConfig,
+    },
+    rpc_response::{
+        RpcConfirmedTransactionStatusWithSignature, RpcInflationReward, RpcPrioritizationFee,
+        RpcResult, RpcSimulateTransactionResult,
+    },
+};
+use solana_sdk::{
+    account::Account,
+    clock::{Epoch, Slot, UnixTimestamp},
+    commitment_config::CommitmentConfig,
+    epoch_info::EpochInfo,
+    hash::Hash,
+    message::VersionedMessage,
+    pubkey::Pubkey,
+    signature::Signature,
+    transaction::VersionedTransaction,
+};
+use solana_transaction_status::{EncodedConfirmedTransactionWithStatusMeta, TransactionStatus};
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// An RPC client with a cache in front of it
+pub struct CachedRpc<R> {
+    inner: R,
+    /// How long accounts are kept; zero keeps only what never changes
+    ttl: Duration,
+    state: Mutex<CacheState>,
+}
+
+/// What a program account lookup found
+type ProgramAccounts = Vec<(Pubkey, Account)>;
+
+#[derive(Default)]
+struct CacheState {
+    genesis_hash: Option<Hash>,
+    /// By data length
+    rent_minimums: HashMap<usize, u64>,
+    block_times: HashMap<Slot, UnixTimestamp>,
+    inflation_rewards: HashMap<(Epoch, Vec<Pubkey>), Vec<Option<RpcInflationReward>>>,
+    accounts: HashMap<Pubkey, (Instant, Account)>,
+    /// By program and the lookup's config as JSON
+    program_accounts: HashMap<(Pubkey, String), (Instant, ProgramAccounts)>,
+}
+
+impl<R: RpcApi> CachedRpc<R> {
+    pub fn new(inner: R, ttl: Duration) -> Self {
+        Self {
+            inner,
+            ttl,
+            state: Mutex::new(CacheState::default()),
+        }
+    }
+
+    pub fn inner(&self) -> &R {
+        &self.inner
+    }
+
+    /// Drop the accounts and program account lookups, so the next reads
+    /// see what changed
+    pub fn invalidate(&self) {
+        let mut state = self.state();
+        state.accounts.clear();
+        state.program_accounts.clear();
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, CacheState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn fresh(&self, at: Instant) -> bool {
+        at.elapsed() < self.ttl
+    }
+}
+
+impl<R: RpcApi> RpcApi for CachedRpc<R> {
+    fn commitment(&self) -> CommitmentConfig {
+        self.inner.commitment()
+    }
+
+    fn get_balance(&self, pubkey: &Pubkey) -> ClientResult<u64> {
+        self.inner.get_balance(pubkey)
+    }
+
+    fn get_account(&self, pubkey: &Pubkey) -> ClientResult<Account> {
+        if let Some((at, account)) = self.state().accounts.get(pubkey) {
+            if self.fresh(*at) {
+                return Ok(account.clone());
+            }
+        }
+        let account = self.inner.get_account(pubkey)?;
+        if !self.ttl.is_zero() {
+            self.state()
+                .accounts
+                .insert(*pubkey, (Instant::now(), account.clone()));
+        }
+        Ok(account)
+    }
+
+    fn get_account_with_commitment(
+        &self,
+        pubkey: &Pubkey,
+        commitment_config: CommitmentConfig,
+    ) -> RpcResult<Option<Account>> {
+        self.inner
+            .get_account_with_commitment(pubkey, commitment_config)
+    }
+
+    fn get_multiple_accounts(&self, pubkeys: &[Pubkey]) -> ClientResult<Vec<Option<Account>>> {
+        self.inner.get_multiple_accounts(pubkeys)
+    }
+
+    fn get_program_accounts_with_config(
+        &self,
+        pubkey: &Pubkey,
+        config: RpcProgramAccountsConfig,
+    ) -> ClientResult<ProgramAccounts> {
+        let key = (*pubkey, serde_json::to_string(&config).unwrap_or_default());
+        if let Some((at, accounts)) = self.state().program_accounts.get(&key) {
+            if self.fresh(*at) {
+                return Ok(accounts.clone());
+            }
+        }
+        let accounts = self
+            .inner
+            .get_program_accounts_with_config(pubkey, config)?;
+        if !self.ttl.is_zero() {
+            self.state()
+                .program_accounts
+                .insert(key, (Instant::now(), accounts.clone()));
+        }
+        Ok(accounts)
+    }
+
+    fn get_minimum_balance_for_rent_exemption(&self, data_len: usize) -> ClientResult<u64> {
+        if let Some(&lamports) = self.state().rent_minimums.get(&data_len) {
+            return Ok(lamports);
+        }
+        let lamports = self
+            .inner
+            .get_minimum_balance_for_rent_exemption(data_len)?;
+        self.state().rent_minimums.insert(data_len, lamports);
+        Ok(lamports)
+    }
+
+    fn get_genesis_hash(&self) -> ClientResult<Hash> {
+        if let Some(hash) = self.state().genesis_hash {
+            return Ok(hash);
+        }
+        let hash = self.inner.get_genesis_hash()?;
+        self.state().genesis_hash = Some(hash);
+        Ok(hash)
+    }
+
+    fn get_slot_with_commitment(&self, commitment_config: CommitmentConfig) -> ClientResult<Slot> {
+        self.inner.get_slot_with_commitment(commitment_config)
+    }
+
+    fn get_latest_blockhash(&self) -> ClientResult<Hash> {
+        self.inner.get_latest_blockhash()
+    }
+
+    fn get_epoch_info(&self) -> ClientResult<EpochInfo> {
+        self.inner.get_epoch_info()
+    }
+
+    fn get_inflation_reward(
+        &self,
+        addresses: &[Pubkey],
+        epoch: Option<Epoch>,
+    ) -> ClientResult<Vec<Option<RpcInflationReward>>> {
+        // Without an epoch it's the last one, which moves on
+        let Some(epoch) = epoch else {
+            return self.inner.get_inflation_reward(addresses, None);
+        };
+        let key = (epoch, addresses.to_vec());
+        if let Some(rewards) = self.state().inflation_rewards.get(&key) {
+            return Ok(rewards.clone());
+        }
+        let rewards = self.inner.get_inflation_reward(addresses, Some(epoch))?;
+        self.state().inflation_rewards.insert(key, rewards.clone());
+        Ok(rewards)
+    }
+
+    fn get_block_time(&self, slot: Slot) -> ClientResult<UnixTimestamp> {
+        if let Some(&time) = self.state().block_times.get(&slot) {
+            return Ok(time);
+        }
+        let time = self.inner.get_block_time(slot)?;
+        self.state().block_times.insert(slot, time);
+        Ok(time)
+    }
+
+    fn get_fee_for_message(&self, message: &VersionedMessage) -> ClientResult<u64> {
+        self.inner.get_fee_for_message(message)
+    }
+
+    fn get_recent_prioritization_fees(
+        &self,
+        addresses: &[Pubkey],
+    ) -> ClientResult<Vec<RpcPrioritizationFee>> {
+        self.inner.get_recent_prioritization_fees(addresses)
+    }
+
+    fn simulate_transaction_with_config(
+        &self,
+        transaction: &VersionedTransaction,
+        config: RpcSimulateTransactionConfig,
+    ) -> RpcResult<RpcSimulateTransactionResult> {
+        self.inner
+            .simulate_transaction_with_config(transaction, config)
+    }
+
+    fn send_transaction_with_config(
+        &self,
+        transaction: &VersionedTransaction,
+        config: RpcSendTransactionConfig,
+    ) -> ClientResult<Signature> {
+        // Dropped even if sending fails, as it may have reached the node
+        self.invalidate();
+        self.inner.send_transaction_with_config(transaction, config)
+    }
+
+    fn get_signature_statuses(
+        &self,
+        signatures: &[Signature],
+    ) -> RpcResult<Vec<Option<TransactionStatus>>> {
+        self.inner.get_signature_statuses(signatures)
+    }
+
+    fn get_signature_statuses_with_history(
+        &self,
+        signatures: &[Signature],
+    ) -> RpcResult<Vec<Option<TransactionStatus>>> {
+        self.inner.get_signature_statuses_with_history(signatures)
+    }
+
+    fn is_blockhash_valid(
+        &self,
+        blockhash: &Hash,
+        commitment: CommitmentConfig,
+    ) -> ClientResult<bool> {
+        self.inner.is_blockhash_valid(blockhash, commitment)
+    }
+
+    fn get_signatures_for_address_with_config(
+        &self,
+        address: &Pubkey,
+        config: GetConfirmedSignaturesForAddress2Config,
+    ) -> ClientResult<Vec<RpcConfirmedTransactionStatusWithSignature>> {
+        self.inner
+            .get_signatures_for_address_with_config(address, config)
+    }
+
+    fn get_transaction_with_config(
+        &self,
+        signature: &Signature,
+        config: RpcTransactionConfig,
+    ) -> ClientResult<EncodedConfirmedTransactionWithStatusMeta> {
+        self.inner.get_transaction_with_config(signature, config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::MockRpc;
+    use solana_sdk::{
+        signature::{Keypair, Signer},
+        transaction::Transaction,
+    };
+
+    fn mint(lamports: u64) -> Account {
+        Account {
+            lamports,
+            data: vec![1; 82],
+            owner: spl_token::ID,
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    #[test]
+    fn keeps_accounts_until_a_send() {
+        let rpc = CachedRpc::new(MockRpc::default(), Duration::from_secs(60));
+        let address = Pubkey::new_unique();
+        rpc.inner().set_account(address, mint(1));
+        assert_eq!(rpc.get_account(&address).unwrap().lamports, 1);
+
+        rpc.inner().set_account(address, mint(2));
+        assert_eq!(rpc.get_account(&address).unwrap().lamports, 1);
+        // Balances and reads at a commitment are always current
+        assert_eq!(rpc.get_balance(&address).unwrap(), 2);
+
+        let payer = Keypair::new();
+        let transaction = Transaction::new_signed_with_payer(
+            &[],
+            Some(&payer.pubkey()),
+            &[&payer],
+            Hash::default(),
+        );
+        rpc.send_transaction_with_config(
+            &VersionedTransaction::from(transaction),
+            RpcSendTransactionConfig::default(),
+        )
+        .unwrap();
+        assert_eq!(rpc.get_account(&address).unwrap().lamports, 2);
+
+        // Without a time to keep them, accounts aren't, but the genesis
+        // hash still is
+        let rpc = CachedRpc::new(MockRpc::default(), Duration::ZERO);
+        rpc.inner().set_account(address, mint(1));
+        rpc.get_account(&address).unwrap();
+        rpc.inner().set_account(address, mint(2));
+        assert_eq!(rpc.get_account(&address).unwrap().lamports, 2);
+        let genesis = rpc.get_genesis_hash().unwrap();
+        assert_eq!(rpc.get_genesis_hash().unwrap(), genesis);
+    }
+}
@@ -0,0 +1,1894 @@
+//! The commands run without the TUI: each prints what it did, in text or
+//! JSON, and asks on the terminal before signing unless told not to
+
+use crate::{
+    audit::{self, Chain},
+    blocklist::Blocklist,
+    config::{self, Config},
+    governance::{self, ProposalState},
+    grind::{GrindPattern, Grinder},
+    history::{self, HistoryEntry},
+    idl::IdlRegistry,
+    inspector::{self, Inspection, Simulation},
+    keys::{self, SecretKeypair},
+    limits::{LimitCheck, SpendingLedger},
+    lookup_table,
+    nonce::{self, NonceAccount},
+    output::{Output, Refused},
+    priority, programs, rent,
+    report::{self, ReportFormat},
+    rpc::RpcApi,
+    seeded::{self, SeededAccount, SeededAccounts},
+    sender::{Pipeline, Status},
+    shamir,
+    siws::SignInRequest,
+    staking, token,
+    wallet::{
+        check_send, confirm_send, fetch_history, is_mainnet, parse_send, send_and_record,
+        sender_rent_message, sol_to_lamports, suggest_priority_fee, transfer_pipeline,
+        typed_threshold,
+    },
+};
+use anyhow::{bail, Context, Result};
+use clap::Subcommand;
+use solana_sdk::{
+    hash::Hash,
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    signature::{Signature, Signer},
+    transaction::VersionedTransaction,
+};
+use std::{
+    io::{self, IsTerminal},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+    time::Duration,
+};
+use zeroize::Zeroizing;
+
+#[derive(Subcommand, Debug)]
+pub enum SeededCommand {
+    /// List the seeded accounts created or funded from this wallet, with
+    /// their balances
+    List,
+    /// Print the address derived from the wallet with a seed
+    Address {
+        seed: String,
+
+        /// Owner program (defaults to the System Program)
+        #[arg(long)]
+        owner: Option<String>,
+    },
+    /// Create the account derived with a seed
+    Create {
+        seed: String,
+
+        /// Owner program (defaults to the System Program)
+        #[arg(long)]
+        owner: Option<String>,
+
+        /// Bytes of data to allocate
+        #[arg(long, default_value_t = 0)]
+        space: u64,
+
+        /// SOL to put in it (defaults to the rent-exempt minimum)
+        #[arg(long)]
+        amount: Option<f64>,
+
+        /// Create it without asking for confirmation
+        #[arg(long)]
+        yes: bool,
+    },
+    /// Send SOL to the account derived with a seed, with the same checks as
+    /// `send`
+    Fund {
+        seed: String,
+
+        /// Owner program (defaults to the System Program)
+        #[arg(long)]
+        owner: Option<String>,
+
+        /// Amount in SOL
+        #[arg(long)]
+        amount: f64,
+
+        /// Send without asking for confirmation
+        #[arg(long)]
+        yes: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum NonceCommand {
+    /// List the wallet's nonce accounts with their stored nonces
+    List,
+    /// Create a nonce account with the wallet as its authority
+    Create {
+        /// SOL to put in it (defaults to the rent-exempt minimum)
+        #[arg(long)]
+        amount: Option<f64>,
+
+        /// Create it without asking for confirmation
+        #[arg(long)]
+        yes: bool,
+    },
+    /// Print a nonce account's stored nonce and authority, to sign with
+    /// offline
+    Show {
+        /// Nonce account address, or the number of one of the wallet's
+        account: String,
+    },
+    /// Store a new nonce, so nothing signed with the current one can be sent
+    Advance {
+        /// Nonce account address, or the number of one of the wallet's
+        account: String,
+
+        /// Advance it without asking for confirmation
+        #[arg(long)]
+        yes: bool,
+    },
+    /// Send SOL to a nonce account, with the same checks as `send`
+    Fund {
+        /// Nonce account address, or the number of one of the wallet's
+        account: String,
+
+        /// Amount in SOL
+        #[arg(long)]
+        amount: f64,
+
+        /// Send without asking for confirmation
+        #[arg(long)]
+        yes: bool,
+    },
+    /// Move SOL from a nonce account back to the wallet
+    Withdraw {
+        /// Nonce account address, or the number of one of the wallet's
+        account: String,
+
+        /// SOL to withdraw (defaults to all of it, which closes the account)
+        #[arg(long)]
+        amount: Option<f64>,
+
+        /// Withdraw without asking for confirmation
+        #[arg(long)]
+        yes: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum LookupTableCommand {
+    /// Create an empty lookup table with the wallet as its authority
+    Create {
+        /// Create it without asking for confirmation
+        #[arg(long)]
+        yes: bool,
+    },
+    /// Print a lookup table's authority, status and addresses
+    Show {
+        /// Lookup table address
+        table: String,
+    },
+    /// Add addresses to a lookup table, 20 per transaction
+    Extend {
+        /// Lookup table address
+        table: String,
+
+        /// Addresses to add
+        #[arg(required = true)]
+        addresses: Vec<String>,
+
+        /// Extend it without asking for confirmation
+        #[arg(long)]
+        yes: bool,
+    },
+    /// Stop a lookup table from being used or extended, so it can be closed
+    Deactivate {
+        /// Lookup table address
+        table: String,
+
+        /// Deactivate it without asking for confirmation
+        #[arg(long)]
+        yes: bool,
+    },
+    /// Delete a deactivated lookup table, returning its rent to the wallet
+    Close {
+        /// Lookup table address
+        table: String,
+
+        /// Close it without asking for confirmation
+        #[arg(long)]
+        yes: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum GovernanceCommand {
+    /// List the realms the wallet has deposited community or council
+    /// tokens in
+    Memberships,
+    /// List the proposals being voted on in realms
+    Proposals {
+        /// Realm addresses (defaults to `[governance] realms`, or the ones
+        /// the wallet is a member of)
+        realms: Vec<String>,
+
+        /// Include proposals that aren't being voted on
+        #[arg(long)]
+        all: bool,
+    },
+    /// Vote on a proposal with the wallet's deposit
+    Vote {
+        /// Proposal address
+        proposal: String,
+
+        /// yes, no or abstain
+        vote: String,
+
+        /// Vote without asking for confirmation
+        #[arg(long)]
+        yes: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum TokenCommand {
+    /// Create a mint, asking on the terminal for the settings not given
+    CreateMint {
+        /// Decimal places of the token (default 9)
+        #[arg(long)]
+        decimals: Option<u8>,
+
+        /// Who can mint the token (defaults to the wallet)
+        #[arg(long, value_name = "ADDRESS")]
+        mint_authority: Option<String>,
+
+        /// Who can freeze accounts holding it, or `none` (the default)
+        #[arg(long, value_name = "ADDRESS")]
+        freeze_authority: Option<String>,
+
+        /// Name in its Metaplex metadata; without one it gets none
+        #[arg(long)]
+        name: Option<String>,
+
+        /// Ticker symbol in its metadata
+        #[arg(long, requires = "name")]
+        symbol: Option<String>,
+
+        /// Link to the metadata's JSON file, with the token's image
+        #[arg(long, requires = "name")]
+        uri: Option<String>,
+
+        /// Create it without asking for anything
+        #[arg(long)]
+        yes: bool,
+    },
+    /// List the mints the wallet is the mint authority of
+    Mints,
+    /// Mint tokens of a mint the wallet is the authority of, creating the
+    /// recipient's token account if needed
+    MintTo {
+        /// Mint address
+        mint: String,
+
+        /// Amount in whole tokens, like 1.5
+        amount: String,
+
+        /// Owner of the account to mint to (defaults to the wallet)
+        #[arg(long, value_name = "ADDRESS")]
+        to: Option<String>,
+
+        /// Mint without asking for confirmation
+        #[arg(long)]
+        yes: bool,
+    },
+    /// Burn tokens from the wallet's account
+    Burn {
+        /// Mint address
+        mint: String,
+
+        /// Amount in whole tokens, like 1.5
+        amount: String,
+
+        /// Burn without asking for confirmation
+        #[arg(long)]
+        yes: bool,
+    },
+    /// List the wallet's token accounts others are approved to spend from
+    Delegations,
+    /// Let a delegate spend up to an amount from the wallet's token account
+    Approve {
+        /// Mint address
+        mint: String,
+
+        /// Address allowed to spend
+        delegate: String,
+
+        /// Most it can spend, in whole tokens
+        amount: String,
+
+        /// Approve without asking for confirmation
+        #[arg(long)]
+        yes: bool,
+    },
+    /// Take away the delegates of token accounts
+    Revoke {
+        /// Token account addresses, as listed by `token delegations`
+        #[arg(required_unless_present = "all", conflicts_with = "all")]
+        accounts: Vec<String>,
+
+        /// Revoke every delegation on the wallet's token accounts
+        #[arg(long)]
+        all: bool,
+
+        /// Revoke without asking for confirmation
+        #[arg(long)]
+        yes: bool,
+    },
+    /// List the token accounts holding a mint, with whether they're frozen
+    Holders {
+        /// Mint address
+        mint: String,
+    },
+    /// Freeze token accounts holding a mint the wallet is the freeze
+    /// authority of, so nothing moves in or out of them
+    Freeze {
+        /// Mint address
+        mint: String,
+
+        /// Token account addresses, as listed by `token holders`
+        #[arg(required = true)]
+        accounts: Vec<String>,
+
+        /// Freeze without asking for confirmation
+        #[arg(long)]
+        yes: bool,
+    },
+    /// Thaw frozen token accounts holding a mint the wallet is the freeze
+    /// authority of
+    Thaw {
+        /// Mint address
+        mint: String,
+
+        /// Token account addresses, as listed by `token holders`
+        #[arg(required = true)]
+        accounts: Vec<String>,
+
+        /// Thaw without asking for confirmation
+        #[arg(long)]
+        yes: bool,
+    },
+}
+
+/// Answer a sign-in request without the TUI, after showing what it's for
+/// and, unless `yes` is given, asking on the terminal
+pub fn sign_in_headless(
+    keypair: &SecretKeypair,
+    text: &str,
+    yes: bool,
+    output: Output,
+) -> Result<()> {
+    let request = SignInRequest::parse(text)?;
+    request.check(&keypair.pubkey())?;
+    eprintln!("{} wants you to sign in", request.domain);
+    if let Some(ref statement) = request.statement {
+        eprintln!("  \"{}\"", statement);
+    }
+    if let Some(ref uri) = request.uri {
+        eprintln!("  URI: {}", uri);
+    }
+    for warning in request.warnings() {
+        eprintln!("Warning: {}", warning);
+    }
+    if !yes {
+        if !io::stdin().is_terminal() {
+            bail!(Refused(
+                "Not signing in without confirmation; pass --yes to skip it".to_string()
+            ));
+        }
+        eprint!("Sign in to {}? [y/N] ", request.domain);
+        let mut answer = String::new();
+        io::stdin().read_line(&mut answer)?;
+        if !answer.trim().eq_ignore_ascii_case("y") {
+            bail!(Refused("Cancelled; nothing was signed".to_string()));
+        }
+    }
+    let response = request.sign(keypair)?;
+    output.print(
+        serde_json::to_string_pretty(&response).expect("JSON values serialize"),
+        response,
+    );
+    Ok(())
+}
+
+/// Run a `seeded` subcommand
+#[allow(clippy::too_many_arguments)]
+pub fn seeded_headless(
+    rpc_client: &dyn RpcApi,
+    rpc_url: &str,
+    config: &Config,
+    blocklist: &Blocklist,
+    keypair: &SecretKeypair,
+    fee_payer: Option<Arc<SecretKeypair>>,
+    command: SeededCommand,
+    output: Output,
+) -> Result<()> {
+    let wallet = keypair.pubkey();
+    let account = |seed: &str, owner: Option<String>| {
+        let owner = owner
+            .map(|owner| Pubkey::from_str(&owner).context("Invalid owner program"))
+            .transpose()?;
+        SeededAccount::new(wallet, seed, owner)
+    };
+    let mut seeded = SeededAccounts::load()?;
+
+    match command {
+        SeededCommand::List => {
+            let accounts: Vec<SeededAccount> = seeded.of(&wallet).cloned().collect();
+            let addresses = accounts
+                .iter()
+                .map(SeededAccount::address)
+                .collect::<Result<Vec<_>>>()?;
+            let balances = rpc_client
+                .get_multiple_accounts(&addresses)
+                .context("Failed to fetch account balances")?;
+            let mut text = Vec::new();
+            let mut json = Vec::new();
+            for ((account, address), found) in accounts.iter().zip(&addresses).zip(balances) {
+                let lamports = found.map(|found| found.lamports);
+                let owner = programs::name(&account.owner)
+                    .map_or_else(|| account.owner.to_string(), str::to_string);
+                text.push(format!(
+                    "{}  {:?}  {}  {}",
+                    address,
+                    account.seed,
+                    owner,
+                    lamports.map_or("not created".to_string(), |lamports| format!(
+                        "{:.9} SOL",
+                        lamports as f64 / LAMPORTS_PER_SOL as f64
+                    ))
+                ));
+                json.push(serde_json::json!({
+                    "address": address.to_string(),
+                    "seed": account.seed,
+                    "owner": account.owner.to_string(),
+                    "lamports": lamports,
+                }));
+            }
+            if text.is_empty() {
+                text.push("No seeded accounts yet; make one with `solace seeded create`".into());
+            }
+            output.print(text.join("\n"), serde_json::Value::from(json));
+        }
+        SeededCommand::Address { seed, owner } => {
+            let account = account(&seed, owner)?;
+            let address = account.address()?;
+            output.print(
+                address,
+                serde_json::json!({
+                    "address": address.to_string(),
+                    "seed": account.seed,
+                    "owner": account.owner.to_string(),
+                }),
+            );
+        }
+        SeededCommand::Create {
+            seed,
+            owner,
+            space,
+            amount,
+            yes,
+        } => {
+            let account = account(&seed, owner)?;
+            let address = account.address()?;
+            let data_len = usize::try_from(space).context("Too much space")?;
+            let minimum = rent::minimum(rpc_client, data_len)?;
+            let lamports = amount.map_or(minimum, sol_to_lamports);
+            if lamports < minimum {
+                bail!(Refused(format!(
+                    "Accounts with {} bytes of data need at least {} SOL to be rent exempt",
+                    space,
+                    minimum as f64 / LAMPORTS_PER_SOL as f64
+                )));
+            }
+            let existing = rpc_client
+                .get_account_with_commitment(&address, rpc_client.commitment())
+                .context("Failed to fetch account")?
+                .value;
+            if existing.is_some() {
+                seeded.add(account)?;
+                bail!("{} already exists; it's in the list now", address);
+            }
+            let mut spending = SpendingLedger::load()?;
+            let exceeded = match spending.check(&config.limits, lamports) {
+                LimitCheck::Within => false,
+                LimitCheck::Exceeded(reason) => {
+                    eprintln!("Warning: this send {}", reason);
+                    true
+                }
+                LimitCheck::Blocked(reason) => {
+                    bail!(Refused(format!("Send blocked: amount {}", reason)))
+                }
+            };
+            if let Some(minimum) = rent::sender_shortfall(rpc_client, &wallet, &wallet, lamports)? {
+                bail!(Refused(sender_rent_message(minimum)));
+            }
+            if !yes || exceeded {
+                if !io::stdin().is_terminal() {
+                    bail!(Refused(
+                        "Not creating the account without confirmation; pass --yes to skip it"
+                            .to_string()
+                    ));
+                }
+                eprint!(
+                    "Create {} (seed {:?}, owner {}) with {} SOL? [y/N] ",
+                    address,
+                    account.seed,
+                    account.owner,
+                    lamports as f64 / LAMPORTS_PER_SOL as f64
+                );
+                let mut answer = String::new();
+                io::stdin().read_line(&mut answer)?;
+                if !answer.trim().eq_ignore_ascii_case("y") {
+                    bail!(Refused("Cancelled; nothing was sent".to_string()));
+                }
+            }
+
+            let signature = seeded::create(rpc_client, keypair, &account, lamports, space)?;
+            if let Err(e) = spending.record(lamports) {
+                eprintln!("Warning: {:#}", e);
+            }
+            let json = serde_json::json!({
+                "signature": signature.to_string(),
+                "address": address.to_string(),
+                "seed": account.seed,
+                "owner": account.owner.to_string(),
+                "lamports": lamports,
+                "space": space,
+            });
+            seeded.add(account)?;
+            output.print(signature, json);
+        }
+        SeededCommand::Fund {
+            seed,
+            owner,
+            amount,
+            yes,
+        } => {
+            let account = account(&seed, owner)?;
+            let address = account.address()?.to_string();
+            send_headless(
+                rpc_client,
+                transfer_pipeline(config, false, None, fee_payer, || {
+                    is_mainnet(rpc_client, rpc_url)
+                })?,
+                rpc_url,
+                config,
+                blocklist,
+                keypair,
+                &address,
+                amount,
+                yes,
+                output,
+            )?;
+            seeded.add(account)?;
+        }
+    }
+    Ok(())
+}
+
+/// Run a `nonce` subcommand
+#[allow(clippy::too_many_arguments)]
+pub fn nonce_headless(
+    rpc_client: &dyn RpcApi,
+    rpc_url: &str,
+    config: &Config,
+    blocklist: &Blocklist,
+    keypair: &SecretKeypair,
+    fee_payer: Option<Arc<SecretKeypair>>,
+    command: NonceCommand,
+    output: Output,
+) -> Result<()> {
+    let wallet = keypair.pubkey();
+    let sol = |lamports: u64| lamports as f64 / LAMPORTS_PER_SOL as f64;
+    // One of the wallet's by number, or any by address
+    let address = |account: &str| match account.parse::<u32>() {
+        Ok(index) => nonce::address(&wallet, index),
+        Err(_) => Pubkey::from_str(account).context("Invalid nonce account address"),
+    };
+
+    match command {
+        NonceCommand::List => {
+            let (accounts, _) = nonce::list(rpc_client, &wallet)?;
+            let mut text: Vec<String> = accounts
+                .iter()
+                .map(|account| {
+                    format!(
+                        "{}  {}  {:.9} SOL  nonce {}",
+                        account.index.unwrap_or_default(),
+                        account.address,
+                        sol(account.lamports),
+                        account.nonce
+                    )
+                })
+                .collect();
+            if text.is_empty() {
+                text.push("No nonce accounts yet; make one with `solace nonce create`".into());
+            }
+            let json: Vec<serde_json::Value> = accounts.iter().map(NonceAccount::to_json).collect();
+            output.print(text.join("\n"), serde_json::Value::from(json));
+        }
+        NonceCommand::Create { amount, yes } => {
+            let minimum = rent::minimum(rpc_client, nonce::ACCOUNT_SIZE)?;
+            let lamports = amount.map_or(minimum, sol_to_lamports);
+            if lamports < minimum {
+                bail!(Refused(format!(
+                    "Nonce accounts need at least {} SOL to be rent exempt",
+                    sol(minimum)
+                )));
+            }
+            let (_, free) = nonce::list(rpc_client, &wallet)?;
+            let index = free.with_context(|| {
+                format!(
+                    "All {} nonce account seeds are in use; withdraw from one to close it",
+                    nonce::MAX_ACCOUNTS
+                )
+            })?;
+            if let Some(minimum) = rent::sender_shortfall(rpc_client, &wallet, &wallet, lamports)? {
+                bail!(Refused(sender_rent_message(minimum)));
+            }
+            let address = nonce::address(&wallet, index)?;
+            if !yes {
+                confirm_on_terminal(
+                    &format!(
+                        "Create nonce account {} with {} SOL?",
+                        address,
+                        sol(lamports)
+                    ),
+                    "creating the account",
+                )?;
+            }
+            // Not a send for the spending limits: the wallet can take it all
+            // back out
+            let (address, signature) = nonce::create(rpc_client, keypair, index, lamports)?;
+            output.print(
+                signature,
+                serde_json::json!({
+                    "signature": signature.to_string(),
+                    "address": address.to_string(),
+                    "lamports": lamports,
+                }),
+            );
+        }
+        NonceCommand::Show { account } => {
+            let account = nonce::fetch(rpc_client, &address(&account)?)?;
+            output.print(
+                format!(
+                    "Address: {}\nAuthority: {}\nNonce: {}\nBalance: {:.9} SOL\nFee: {} lamports per signature",
+                    account.address,
+                    account.authority,
+                    account.nonce,
+                    sol(account.lamports),
+                    account.lamports_per_signature
+                ),
+                account.to_json(),
+            );
+        }
+        NonceCommand::Advance { account, yes } => {
+            let account = nonce::fetch(rpc_client, &address(&account)?)?;
+            if !yes {
+                confirm_on_terminal(
+                    &format!(
+                        "Advance the nonce of {}? Anything signed with the current one can't be sent after this.",
+                        account.address
+                    ),
+                    "advancing the nonce",
+                )?;
+            }
+            let signature = nonce::advance(rpc_client, keypair, &account)?;
+            let account = nonce::fetch(rpc_client, &account.address)?;
+            output.print(
+                format!("{}\nNew nonce: {}", signature, account.nonce),
+                serde_json::json!({
+                    "signature": signature.to_string(),
+                    "address": account.address.to_string(),
+                    "nonce": account.nonce.to_string(),
+                }),
+            );
+        }
+        NonceCommand::Fund {
+            account,
+            amount,
+            yes,
+        } => {
+            let address = address(&account)?.to_string();
+            send_headless(
+                rpc_client,
+                transfer_pipeline(config, false, None, fee_payer, || {
+                    is_mainnet(rpc_client, rpc_url)
+                })?,
+                rpc_url,
+                config,
+                blocklist,
+                keypair,
+                &address,
+                amount,
+                yes,
+                output,
+            )?;
+        }
+        NonceCommand::Withdraw {
+            account,
+            amount,
+            yes,
+        } => {
+            let account = nonce::fetch(rpc_client, &address(&account)?)?;
+            let lamports = amount.map_or(account.lamports, sol_to_lamports);
+            if lamports == 0 {
+                bail!("The amount must be more than 0 SOL");
+            }
+            if !yes {
+                confirm_on_terminal(
+                    &format!(
+                        "Withdraw {} SOL from {} to the wallet{}?",
+                        sol(lamports),
+                        account.address,
+                        if lamports == account.lamports {
+                            ", closing it"
+                        } else {
+                            ""
+                        }
+                    ),
+                    "withdrawing",
+                )?;
+            }
+            let signature = nonce::withdraw(rpc_client, keypair, &account, &wallet, lamports)?;
+            output.print(
+                signature,
+                serde_json::json!({
+                    "signature": signature.to_string(),
+                    "address": account.address.to_string(),
+                    "to": wallet.to_string(),
+                    "lamports": lamports,
+                }),
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Run a `lookup-table` subcommand
+pub fn lookup_table_headless(
+    rpc_client: &dyn RpcApi,
+    keypair: &SecretKeypair,
+    command: LookupTableCommand,
+    output: Output,
+) -> Result<()> {
+    let wallet = keypair.pubkey();
+    let fetch = |table: &str| {
+        lookup_table::fetch(
+            rpc_client,
+            &Pubkey::from_str(table).context("Invalid lookup table address")?,
+        )
+    };
+    let signed = |signature: Signature, table: &Pubkey| {
+        output.print(
+            signature,
+            serde_json::json!({
+                "signature": signature.to_string(),
+                "address": table.to_string(),
+            }),
+        );
+    };
+
+    match command {
+        LookupTableCommand::Create { yes } => {
+            if !yes {
+                confirm_on_terminal(
+                    "Create a lookup table with this wallet as its authority?",
+                    "creating the table",
+                )?;
+            }
+            let (address, signature) = lookup_table::create(rpc_client, keypair)?;
+            signed(signature, &address);
+        }
+        LookupTableCommand::Show { table } => {
+            let table = fetch(&table)?;
+            let authority = table
+                .authority
+                .map_or("none (frozen)".to_string(), |authority| {
+                    authority.to_string()
+                });
+            let status = match (table.deactivation_slot, table.closable_at()) {
+                (Some(slot), Some(closable)) => format!(
+                    "deactivated at slot {}, closable from about slot {}",
+                    slot, closable
+                ),
+                _ => "active".to_string(),
+            };
+            let mut text = vec![
+                format!("Address: {}", table.address),
+                format!("Authority: {}", authority),
+                format!("Status: {}", status),
+                format!(
+                    "Balance: {:.9} SOL",
+                    table.lamports as f64 / LAMPORTS_PER_SOL as f64
+                ),
+                format!("Addresses: {}", table.addresses.len()),
+            ];
+            text.extend(
+                table
+                    .addresses
+                    .iter()
+                    .enumerate()
+                    .map(|(i, address)| format!("{:>5}  {}", i, address)),
+            );
+            output.print(text.join("\n"), table.to_json());
+        }
+        LookupTableCommand::Extend {
+            table,
+            addresses,
+            yes,
+        } => {
+            let table = fetch(&table)?;
+            let addresses = addresses
+                .iter()
+                .map(|address| {
+                    Pubkey::from_str(address)
+                        .with_context(|| format!("Invalid address {:?}", address))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            if !yes {
+                confirm_on_terminal(
+                    &format!(
+                        "Add {} addresses to lookup table {}, paying for the space?",
+                        addresses.len(),
+                        table.address
+                    ),
+                    "extending the table",
+                )?;
+            }
+            let signatures = lookup_table::extend(rpc_client, keypair, &table, &addresses)?;
+            let text: Vec<String> = signatures.iter().map(Signature::to_string).collect();
+            output.print(
+                text.join("\n"),
+                serde_json::json!({
+                    "signatures": text,
+                    "address": table.address.to_string(),
+                }),
+            );
+        }
+        LookupTableCommand::Deactivate { table, yes } => {
+            let table = fetch(&table)?;
+            if !yes {
+                confirm_on_terminal(
+                    &format!(
+                        "Deactivate lookup table {}? Transactions can't use it after this, and it can't be turned back on.",
+                        table.address
+                    ),
+                    "deactivating the table",
+                )?;
+            }
+            signed(
+                lookup_table::deactivate(rpc_client, keypair, &table)?,
+                &table.address,
+            );
+        }
+        LookupTableCommand::Close { table, yes } => {
+            let table = fetch(&table)?;
+            if !yes {
+                confirm_on_terminal(
+                    &format!(
+                        "Close lookup table {}, returning {:.9} SOL to the wallet?",
+                        table.address,
+                        table.lamports as f64 / LAMPORTS_PER_SOL as f64
+                    ),
+                    "closing the table",
+                )?;
+            }
+            signed(
+                lookup_table::close(rpc_client, keypair, &table, &wallet)?,
+                &table.address,
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Run a `governance` subcommand
+pub fn governance_headless(
+    rpc_client: &dyn RpcApi,
+    config: &Config,
+    keypair: &SecretKeypair,
+    command: GovernanceCommand,
+    output: Output,
+) -> Result<()> {
+    let wallet = keypair.pubkey();
+    let program =
+        Pubkey::from_str(&config.governance.program).context("Invalid governance program")?;
+    // Weights and deposits in whole tokens, where the mint can be read
+    let amount = |mint: &Pubkey, amount: u64| {
+        token::fetch_mint(rpc_client, mint).map_or(amount.to_string(), |mint| {
+            token::format_amount(amount, mint.decimals)
+        })
+    };
+    match command {
+        GovernanceCommand::Memberships => {
+            let memberships = governance::memberships(rpc_client, &program, &wallet)?;
+            let mut text = Vec::new();
+            let mut json = Vec::new();
+            for membership in &memberships {
+                let realm = governance::fetch_realm(rpc_client, &membership.realm).ok();
+                let name = realm.as_ref().map(|realm| realm.name.clone());
+                let role = realm
+                    .as_ref()
+                    .map_or("unknown", |realm| realm.role(&membership.mint));
+                text.push(format!(
+                    "{} ({})  {}, {} deposited",
+                    name.as_deref().unwrap_or("Unknown realm"),
+                    membership.realm,
+                    role,
+                    amount(&membership.mint, membership.deposit)
+                ));
+                json.push(serde_json::json!({
+                    "realm": membership.realm.to_string(),
+                    "name": name,
+                    "role": role,
+                    "mint": membership.mint.to_string(),
+                    "deposit": membership.deposit,
+                    "record": membership.record.to_string(),
+                }));
+            }
+            if text.is_empty() {
+                text.push("The wallet has no governance deposits".to_string());
+            }
+            output.print(text.join("\n"), serde_json::Value::Array(json));
+        }
+        GovernanceCommand::Proposals { realms, all } => {
+            let mut realms = if realms.is_empty() {
+                config.governance.realms.clone()
+            } else {
+                realms
+            }
+            .iter()
+            .map(|realm| {
+                Pubkey::from_str(realm)
+                    .with_context(|| format!("Invalid realm address {:?}", realm))
+            })
+            .collect::<Result<Vec<_>>>()?;
+            if realms.is_empty() {
+                realms = governance::memberships(rpc_client, &program, &wallet)?
+                    .into_iter()
+                    .map(|membership| membership.realm)
+                    .collect();
+                realms.sort();
+                realms.dedup();
+            }
+            let mut text = Vec::new();
+            let mut json = Vec::new();
+            for address in &realms {
+                let realm = governance::fetch_realm(rpc_client, address)?;
+                let mut proposals = governance::proposals(rpc_client, &program, address)?;
+                proposals.retain(|proposal| all || proposal.state == ProposalState::Voting);
+                text.push(format!("{} ({})", realm.name, realm.address));
+                if proposals.is_empty() {
+                    text.push("  No proposals being voted on".to_string());
+                }
+                for proposal in &proposals {
+                    let mint = &proposal.governing_token_mint;
+                    let tally = match (proposal.is_yes_no(), proposal.deny_vote_weight) {
+                        (true, Some(no)) => format!(
+                            "yes {} / no {}",
+                            amount(mint, proposal.options[0].1),
+                            amount(mint, no)
+                        ),
+                        _ => proposal
+                            .options
+                            .iter()
+                            .map(|(label, weight)| format!("{} {}", label, amount(mint, *weight)))
+                            .collect::<Vec<_>>()
+                            .join(" / "),
+                    };
+                    text.push(format!(
+                        "  {}  {} ({}, {} vote), {}",
+                        proposal.address,
+                        proposal.name,
+                        proposal.state,
+                        realm.role(mint),
+                        tally
+                    ));
+                    json.push(serde_json::json!({
+                        "realm": realm.address.to_string(),
+                        "address": proposal.address.to_string(),
+                        "name": proposal.name,
+                        "description_link": proposal.description_link,
+                        "state": proposal.state.to_string(),
+                        "governing_token_mint": mint.to_string(),
+                        "options": proposal.options.iter().map(|(label, weight)| {
+                            serde_json::json!({ "label": label, "vote_weight": weight })
+                        }).collect::<Vec<_>>(),
+                        "deny_vote_weight": proposal.deny_vote_weight,
+                        "voting_at": proposal.voting_at,
+                    }));
+                }
+            }
+            if realms.is_empty() {
+                text.push(
+                    "No realms to look in; name one or list them under [governance]".to_string(),
+                );
+            }
+            output.print(text.join("\n"), serde_json::Value::Array(json));
+        }
+        GovernanceCommand::Vote {
+            proposal,
+            vote,
+            yes,
+        } => {
+            let address = Pubkey::from_str(&proposal).context("Invalid proposal address")?;
+            let proposal = governance::fetch_proposal(rpc_client, &address)?;
+            let vote: governance::Vote = vote.parse()?;
+            if !yes {
+                confirm_on_terminal(
+                    &format!("Vote {} on \"{}\"?", vote, proposal.name),
+                    "voting",
+                )?;
+            }
+            let signature = governance::cast_vote(rpc_client, &program, keypair, &proposal, vote)?;
+            output.print(
+                signature,
+                serde_json::json!({
+                    "signature": signature.to_string(),
+                    "proposal": address.to_string(),
+                    "vote": vote.to_string(),
+                }),
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Run a `token` subcommand
+pub fn token_headless(
+    rpc_client: &dyn RpcApi,
+    keypair: &SecretKeypair,
+    command: TokenCommand,
+    output: Output,
+) -> Result<()> {
+    let wallet = keypair.pubkey();
+    let fetch = |mint: &str| {
+        let address = Pubkey::from_str(mint).context("Invalid mint address")?;
+        Ok::<_, anyhow::Error>((address, token::fetch_mint(rpc_client, &address)?))
+    };
+    let signed = |signature: Signature| {
+        output.print(
+            signature,
+            serde_json::json!({ "signature": signature.to_string() }),
+        );
+    };
+    // Freezing and thawing differ only in this
+    let frozen = matches!(command, TokenCommand::Freeze { .. });
+    match command {
+        TokenCommand::CreateMint {
+            decimals,
+            mint_authority,
+            freeze_authority,
+            name,
+            symbol,
+            uri,
+            yes,
+        } => {
+            // What isn't given is asked for, unless there's no one to ask
+            let guided = !yes && io::stdin().is_terminal();
+            let ask = |question: &str, given: Option<String>, default: &str| match given {
+                Some(value) => Ok(value),
+                None if guided => ask_on_terminal(question, default),
+                None => Ok(default.to_string()),
+            };
+            let decimals = ask("Decimals", decimals.map(|d| d.to_string()), "9")?
+                .parse()
+                .context("Invalid number of decimals")?;
+            let mint_authority = ask("Mint authority", mint_authority, &wallet.to_string())?;
+            let mint_authority =
+                Pubkey::from_str(&mint_authority).context("Invalid mint authority")?;
+            let freeze_authority = match ask(
+                "Freeze authority (an address, or none)",
+                freeze_authority,
+                "none",
+            )?
+            .as_str()
+            {
+                "" | "none" => None,
+                address => Some(Pubkey::from_str(address).context("Invalid freeze authority")?),
+            };
+            let name = ask("Name, for its metadata (leave empty for none)", name, "")?;
+            let metadata = if name.is_empty() {
+                None
+            } else {
+                let symbol = ask("Symbol", symbol, "")?;
+                let uri = ask("Metadata URI", uri, "")?;
+                Some(token::Metadata::new(&name, &symbol, &uri)?)
+            };
+
+            let new = token::NewMint {
+                decimals,
+                mint_authority,
+                freeze_authority,
+                metadata,
+            };
+            if !yes {
+                let named = new.metadata.as_ref().map_or(String::new(), |metadata| {
+                    format!(" named {} ({})", metadata.name, metadata.symbol)
+                });
+                confirm_on_terminal(
+                    &format!(
+                        "Create a token{} with {} decimals, minted by {}?",
+                        named, new.decimals, new.mint_authority
+                    ),
+                    "creating the mint",
+                )?;
+            }
+            let (mint, signature) = token::create_mint(rpc_client, keypair, &new)?;
+            output.print(
+                mint,
+                serde_json::json!({
+                    "mint": mint.to_string(),
+                    "signature": signature.to_string(),
+                    "decimals": new.decimals,
+                    "mint_authority": new.mint_authority.to_string(),
+                    "freeze_authority": new.freeze_authority.map(|key| key.to_string()),
+                    "metadata": new.metadata.map(|metadata| serde_json::json!({
+                        "address": token::metadata_address(&mint).to_string(),
+                        "name": metadata.name,
+                        "symbol": metadata.symbol,
+                        "uri": metadata.uri,
+                    })),
+                }),
+            );
+        }
+        TokenCommand::Mints => {
+            let mints = token::minted_by(rpc_client, &wallet)?;
+            let text = if mints.is_empty() {
+                "The wallet is not the mint authority of any mint".to_string()
+            } else {
+                mints
+                    .iter()
+                    .map(|(address, mint)| {
+                        format!(
+                            "{}  supply {}, {} decimals",
+                            address,
+                            token::format_amount(mint.supply, mint.decimals),
+                            mint.decimals
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            };
+            let json: Vec<_> = mints
+                .iter()
+                .map(|(address, mint)| {
+                    serde_json::json!({
+                        "mint": address.to_string(),
+                        "supply": mint.supply,
+                        "decimals": mint.decimals,
+                        "freeze_authority":
+                            Option::<Pubkey>::from(mint.freeze_authority).map(|key| key.to_string()),
+                    })
+                })
+                .collect();
+            output.print(text, serde_json::Value::Array(json));
+        }
+        TokenCommand::MintTo {
+            mint,
+            amount,
+            to,
+            yes,
+        } => {
+            let (address, mint) = fetch(&mint)?;
+            let amount = token::parse_amount(&amount, mint.decimals)?;
+            let owner = match to {
+                Some(to) => Pubkey::from_str(&to).context("Invalid recipient address")?,
+                None => wallet,
+            };
+            if !yes {
+                confirm_on_terminal(
+                    &format!(
+                        "Mint {} of {} to {}?",
+                        token::format_amount(amount, mint.decimals),
+                        address,
+                        owner
+                    ),
+                    "minting",
+                )?;
+            }
+            signed(token::mint_to(
+                rpc_client, keypair, &address, &mint, &owner, amount,
+            )?);
+        }
+        TokenCommand::Burn { mint, amount, yes } => {
+            let (address, mint) = fetch(&mint)?;
+            let amount = token::parse_amount(&amount, mint.decimals)?;
+            if !yes {
+                confirm_on_terminal(
+                    &format!(
+                        "Burn {} of {} from the wallet?",
+                        token::format_amount(amount, mint.decimals),
+                        address
+                    ),
+                    "burning",
+                )?;
+            }
+            signed(token::burn(rpc_client, keypair, &address, &mint, amount)?);
+        }
+        TokenCommand::Delegations => {
+            let delegations = token::delegations(rpc_client, &wallet)?;
+            let mut text = Vec::new();
+            let mut json = Vec::new();
+            for (address, account) in &delegations {
+                let delegate = Option::<Pubkey>::from(account.delegate).unwrap_or_default();
+                // The amounts are shown in whole tokens where the mint's
+                // decimals can be had
+                let decimals = token::fetch_mint(rpc_client, &account.mint)
+                    .ok()
+                    .map(|mint| mint.decimals);
+                let allowance = decimals.map_or(account.delegated_amount.to_string(), |decimals| {
+                    token::format_amount(account.delegated_amount, decimals)
+                });
+                text.push(format!(
+                    "{}  mint {}, delegate {} may spend {}",
+                    address, account.mint, delegate, allowance
+                ));
+                json.push(serde_json::json!({
+                    "account": address.to_string(),
+                    "mint": account.mint.to_string(),
+                    "delegate": delegate.to_string(),
+                    "delegated_amount": account.delegated_amount,
+                    "decimals": decimals,
+                }));
+            }
+            if text.is_empty() {
+                text.push("No token account of the wallet has a delegate".to_string());
+            }
+            output.print(text.join("\n"), serde_json::Value::Array(json));
+        }
+        TokenCommand::Approve {
+            mint,
+            delegate,
+            amount,
+            yes,
+        } => {
+            let (address, mint) = fetch(&mint)?;
+            let delegate = Pubkey::from_str(&delegate).context("Invalid delegate address")?;
+            let amount = token::parse_amount(&amount, mint.decimals)?;
+            if !yes {
+                confirm_on_terminal(
+                    &format!(
+                        "Let {} spend up to {} of {} from the wallet?",
+                        delegate,
+                        token::format_amount(amount, mint.decimals),
+                        address
+                    ),
+                    "approving",
+                )?;
+            }
+            signed(token::approve(
+                rpc_client, keypair, &address, &mint, &delegate, amount,
+            )?);
+        }
+        TokenCommand::Revoke { accounts, all, yes } => {
+            let accounts = if all {
+                token::delegations(rpc_client, &wallet)?
+                    .into_iter()
+                    .map(|(address, _)| address)
+                    .collect()
+            } else {
+                accounts
+                    .iter()
+                    .map(|account| {
+                        Pubkey::from_str(account)
+                            .with_context(|| format!("Invalid token account {:?}", account))
+                    })
+                    .collect::<Result<Vec<_>>>()?
+            };
+            if accounts.is_empty() {
+                bail!("No token account of the wallet has a delegate");
+            }
+            if !yes {
+                confirm_on_terminal(
+                    &format!("Revoke the delegates of {} token accounts?", accounts.len()),
+                    "revoking",
+                )?;
+            }
+            signed(token::revoke(rpc_client, keypair, &accounts)?);
+        }
+        TokenCommand::Holders { mint } => {
+            let (address, mint) = fetch(&mint)?;
+            let holders = token::holders(rpc_client, &address)?;
+            let mut text: Vec<String> = holders
+                .iter()
+                .map(|(holder, account)| {
+                    format!(
+                        "{}  owner {}, {}{}",
+                        holder,
+                        account.owner,
+                        token::format_amount(account.amount, mint.decimals),
+                        if account.is_frozen() { ", frozen" } else { "" }
+                    )
+                })
+                .collect();
+            if text.is_empty() {
+                text.push(format!("No token account holds {}", address));
+            }
+            let json = holders
+                .iter()
+                .map(|(holder, account)| {
+                    serde_json::json!({
+                        "account": holder.to_string(),
+                        "owner": account.owner.to_string(),
+                        "amount": account.amount,
+                        "frozen": account.is_frozen(),
+                    })
+                })
+                .collect();
+            output.print(text.join("\n"), serde_json::Value::Array(json));
+        }
+        TokenCommand::Freeze {
+            mint,
+            accounts,
+            yes,
+        }
+        | TokenCommand::Thaw {
+            mint,
+            accounts,
+            yes,
+        } => {
+            let (address, mint) = fetch(&mint)?;
+            let accounts = accounts
+                .iter()
+                .map(|account| {
+                    Pubkey::from_str(account)
+                        .with_context(|| format!("Invalid token account {:?}", account))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            if !yes {
+                confirm_on_terminal(
+                    &format!(
+                        "{} {} token accounts holding {}?",
+                        if frozen { "Freeze" } else { "Thaw" },
+                        accounts.len(),
+                        address
+                    ),
+                    if frozen { "freezing" } else { "thawing" },
+                )?;
+            }
+            signed(token::set_frozen(
+                rpc_client, keypair, &address, &mint, &accounts, frozen,
+            )?);
+        }
+    }
+    Ok(())
+}
+
+/// Sign a transfer with a durable nonce and print it, without connecting to
+/// the cluster. Only the checks that need nothing from it apply: the local
+/// blocklist and the spending limits, which count the transfer as soon as
+/// it's signed since it can be broadcast at any time after.
+#[allow(clippy::too_many_arguments)]
+pub fn sign_offline_headless(
+    config: &Config,
+    keypair: &SecretKeypair,
+    to: &str,
+    amount: f64,
+    nonce_account: &str,
+    blockhash: &str,
+    yes: bool,
+    output: Output,
+) -> Result<()> {
+    let (recipient, lamports) = parse_send(to, amount)?;
+    let nonce_account = Pubkey::from_str(nonce_account).context("Invalid nonce account address")?;
+    let nonce = Hash::from_str(blockhash).context("Invalid nonce")?;
+    let blocklist = Blocklist::load(&config.blocklist)?;
+    if let Some(reason) = blocklist.reason(&recipient) {
+        bail!(Refused(format!(
+            "{} is on your blocklist: {}",
+            recipient, reason
+        )));
+    }
+    let mut spending = SpendingLedger::load()?;
+    match spending.check(&config.limits, lamports) {
+        LimitCheck::Within => {}
+        LimitCheck::Exceeded(reason) => eprintln!("Warning: this send {}", reason),
+        LimitCheck::Blocked(reason) => bail!(Refused(format!("Send blocked: amount {}", reason))),
+    }
+    if !yes {
+        // There's no telling which cluster it's for, so large sends are
+        // always retyped
+        confirm_send(&recipient, amount, lamports >= typed_threshold(config))?;
+    }
+
+    let transaction =
+        nonce::sign_transfer(keypair, &nonce_account, nonce, &recipient, lamports).into();
+    if let Err(e) = spending.record(lamports) {
+        eprintln!("Warning: {:#}", e);
+    }
+    let encoded = inspector::encode(&transaction)?;
+    output.print(
+        &encoded,
+        serde_json::json!({
+            "transaction": encoded,
+            "from": keypair.pubkey().to_string(),
+            "to": recipient.to_string(),
+            "lamports": lamports,
+            "nonce_account": nonce_account.to_string(),
+            "nonce": nonce.to_string(),
+        }),
+    );
+    Ok(())
+}
+
+/// Send a transaction every signer has already signed, once the simulation
+/// shows it would go through
+pub fn broadcast_headless(
+    rpc_client: &dyn RpcApi,
+    pipeline: &Pipeline,
+    config: &Config,
+    text: &str,
+    yes: bool,
+    output: Output,
+) -> Result<()> {
+    let transaction = inspector::decode(text)?;
+    if let Some(key) = inspector::missing_signers(&transaction).first() {
+        bail!(
+            "It still needs a signature from {}; add it with `solace sign-transaction` \
+             or `solace merge-signatures`",
+            key
+        );
+    }
+    if !transaction.verify_with_results().iter().all(|&valid| valid) {
+        bail!("Its signatures do not match the transaction");
+    }
+    let mut idls = IdlRegistry::load(&config.idl)?;
+    let inspection = inspector::inspect(rpc_client, transaction, &mut idls)?;
+    print_simulation(&inspection)?;
+    let fee_payer = inspection.fee_payer().unwrap_or_default();
+    if !yes {
+        confirm_on_terminal(
+            &format!(
+                "Send this transaction ({} instructions, paid for by {})?",
+                inspection.instructions.len(),
+                fee_payer
+            ),
+            "sending",
+        )?;
+    }
+    let landed = pipeline
+        .submit(rpc_client, &inspection.transaction, &mut print_status)?
+        .into_result()?;
+    output.print(
+        landed.signature,
+        serde_json::json!({
+            "signature": landed.signature.to_string(),
+            "slot": landed.slot,
+            "fee_payer": fee_payer.to_string(),
+        }),
+    );
+    Ok(())
+}
+
+/// The SOL balance changes of a headless transaction's simulation on
+/// stderr, refusing one that fails
+fn print_simulation(inspection: &Inspection) -> Result<()> {
+    match inspection.simulation {
+        Ok(Simulation {
+            error: Some(ref error),
+            ..
+        }) => bail!(Refused(format!("It failed in the simulation: {}", error))),
+        Ok(ref simulation) => {
+            for (address, change) in &simulation.balance_changes {
+                eprintln!(
+                    "  {}  {:+.9} SOL",
+                    address,
+                    *change as f64 / LAMPORTS_PER_SOL as f64
+                );
+            }
+        }
+        Err(ref error) => eprintln!("Warning: not simulated: {}", error),
+    }
+    Ok(())
+}
+
+/// Add the signatures of the wallet and the fee payer to a transaction
+/// others sign too, once the simulation shows it would go through
+pub fn sign_transaction_headless(
+    rpc_client: &dyn RpcApi,
+    config: &Config,
+    signers: &[&dyn Signer],
+    text: &str,
+    yes: bool,
+    output: Output,
+) -> Result<()> {
+    let transaction = inspector::decode(text)?;
+    let mut idls = IdlRegistry::load(&config.idl)?;
+    let inspection = inspector::inspect(rpc_client, transaction, &mut idls)?;
+    print_simulation(&inspection)?;
+    if !yes {
+        confirm_on_terminal(
+            &format!(
+                "Sign this transaction ({} instructions, paid for by {})?",
+                inspection.instructions.len(),
+                inspection.fee_payer().unwrap_or_default()
+            ),
+            "signing",
+        )?;
+    }
+    let transaction = inspector::sign_partial(&inspection.transaction, signers)?;
+    print_partially_signed(&transaction, output)
+}
+
+/// A transaction collecting signatures, with the signers it still needs on
+/// stderr
+pub fn print_partially_signed(transaction: &VersionedTransaction, output: Output) -> Result<()> {
+    let missing = inspector::missing_signers(transaction);
+    if missing.is_empty() {
+        eprintln!("Every signer has signed; send it with `solace broadcast`");
+    } else {
+        eprintln!("Still needs signatures from:");
+        for key in &missing {
+            eprintln!("  {}", key);
+        }
+    }
+    let encoded = inspector::encode(transaction)?;
+    output.print(
+        &encoded,
+        serde_json::json!({
+            "transaction": encoded,
+            "missing_signers": missing.iter().map(Pubkey::to_string).collect::<Vec<_>>(),
+        }),
+    );
+    Ok(())
+}
+
+/// Follow a headless send's progress on stderr
+fn print_status(signature: &Signature, status: Status) {
+    if status == Status::Submitted {
+        eprintln!("Sent {}", signature);
+    } else {
+        eprintln!("{}", status);
+    }
+}
+
+/// Ask for a value on the terminal, taking `default` for an empty answer
+fn ask_on_terminal(question: &str, default: &str) -> Result<String> {
+    if default.is_empty() {
+        eprint!("{}: ", question);
+    } else {
+        eprint!("{} [{}]: ", question, default);
+    }
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    let answer = answer.trim();
+    Ok(if answer.is_empty() { default } else { answer }.to_string())
+}
+
+/// Ask a yes/no question on the terminal, failing unless the answer is yes
+fn confirm_on_terminal(question: &str, doing: &str) -> Result<()> {
+    if !io::stdin().is_terminal() {
+        bail!(Refused(format!(
+            "Not {} without confirmation; pass --yes to skip it",
+            doing
+        )));
+    }
+    eprint!("{} [y/N] ", question);
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    if !answer.trim().eq_ignore_ascii_case("y") {
+        bail!(Refused("Cancelled; nothing was sent".to_string()));
+    }
+    Ok(())
+}
+
+/// Send SOL without the TUI, after `check_send`. Unless `yes` is given the
+/// send is confirmed on the terminal, by retyping the amount for large
+/// mainnet sends.
+#[allow(clippy::too_many_arguments)]
+pub fn send_headless(
+    rpc_client: &dyn RpcApi,
+    mut pipeline: Pipeline,
+    rpc_url: &str,
+    config: &Config,
+    blocklist: &Blocklist,
+    keypair: &SecretKeypair,
+    to: &str,
+    amount: f64,
+    yes: bool,
+    output: Output,
+) -> Result<()> {
+    let (recipient, lamports) = parse_send(to, amount)?;
+    let mut spending = SpendingLedger::load()?;
+    let fee_payer = pipeline.fee_payer_of(&keypair.pubkey());
+    let exceeded = check_send(
+        rpc_client,
+        config,
+        blocklist,
+        &spending,
+        &keypair.pubkey(),
+        &fee_payer,
+        &recipient,
+        lamports,
+    )?;
+    if let Some(reason) = exceeded {
+        eprintln!("Warning: this send {}", reason);
+    }
+    let price = *pipeline.compute_unit_price.get_or_insert_with(|| {
+        suggest_priority_fee(rpc_client, config, &keypair.pubkey(), &recipient)
+    });
+    if price > 0 {
+        eprintln!("Priority fee: {}", priority::format_price(price, false));
+    }
+    if fee_payer != keypair.pubkey() {
+        eprintln!("Fee payer: {}", fee_payer);
+    }
+    if let Some(reference) = pipeline.reference {
+        eprintln!("Reference: {}", reference);
+    }
+    if pipeline.dry_run {
+        eprintln!("Dry run: the transaction will only be simulated");
+    }
+
+    if !yes {
+        let typed = lamports >= typed_threshold(config) && is_mainnet(rpc_client, rpc_url);
+        confirm_send(&recipient, amount, typed)?;
+    }
+
+    let sent = send_and_record(
+        rpc_client,
+        &pipeline,
+        keypair,
+        &mut spending,
+        &recipient,
+        lamports,
+        &mut print_status,
+    )?;
+    output.print(sent.signature, sent.to_json());
+    Ok(())
+}
+
+/// `grind` subcommand: search for a vanity address, showing progress on stderr
+pub fn grind_to_file(pattern: GrindPattern, outfile: Option<PathBuf>) -> Result<()> {
+    let grinder = Grinder::start(pattern);
+    eprintln!(
+        "Searching for {} on {} threads (~{:.0} keys expected)",
+        grinder.pattern(),
+        grinder.threads(),
+        grinder.pattern().expected_attempts()
+    );
+
+    let keypair = loop {
+        if let Some(keypair) = grinder.take_result() {
+            break keypair;
+        }
+        eprint!(
+            "\rSearched {} keys ({:.0} keys/s)",
+            grinder.attempts(),
+            grinder.rate()
+        );
+        std::thread::sleep(Duration::from_millis(500));
+    };
+    eprintln!();
+
+    let path = match outfile {
+        Some(path) => config::expand_tilde(&path),
+        None => PathBuf::from(format!("{}.json", keypair.pubkey())),
+    };
+    keypair.write_to_file(&path)?;
+    println!("Saved {} to {}", keypair.pubkey(), path.display());
+    Ok(())
+}
+
+/// Bring the local history index up to date and write the newest `limit`
+/// transactions (or all of them) as a report, with the staking rewards of
+/// the last `reward_epochs` epochs
+pub fn export_history(
+    rpc_client: &dyn RpcApi,
+    address: &Pubkey,
+    path: Option<PathBuf>,
+    format: Option<ReportFormat>,
+    limit: Option<usize>,
+    reward_epochs: u64,
+    output: Output,
+) -> Result<()> {
+    // Other formats can't be nested in the JSON result
+    if output == Output::Json && path.is_none() && format.is_some_and(|f| f != ReportFormat::Json) {
+        bail!("--output json prints the history as JSON; use --export to write other formats");
+    }
+    let wanted = limit.unwrap_or(usize::MAX);
+    let history = fetch_history(rpc_client, address, wanted, &mut |fetched| {
+        eprint!("\rFetched {} transactions", fetched)
+    })?;
+    eprintln!("\rFetched {} transactions", history.entries.len());
+    let entries: Vec<&HistoryEntry> = history.entries.iter().take(wanted).collect();
+    let (_, rewards) = staking::wallet_rewards(rpc_client, address, reward_epochs)?;
+    match path {
+        Some(path) => {
+            let path = config::expand_tilde(&path);
+            let format = format.unwrap_or_else(|| ReportFormat::from_path(&path));
+            report::write_to_file(&entries, &rewards, format, &path)?;
+            match output {
+                Output::Text => eprintln!("Wrote {}", path.display()),
+                Output::Json => println!(
+                    "{}",
+                    serde_json::json!({
+                        "path": path.display().to_string(),
+                        "format": format.label(),
+                        "transactions": entries.len(),
+                        "rewards": rewards.len(),
+                    })
+                ),
+            }
+        }
+        None => match output {
+            Output::Text => print!(
+                "{}",
+                report::render(&entries, &rewards, format.unwrap_or(ReportFormat::Csv))?
+            ),
+            Output::Json => println!(
+                "{}",
+                serde_json::json!({
+                    "address": address.to_string(),
+                    "transactions": report::to_json(&entries, &rewards)?,
+                })
+            ),
+        },
+    }
+    Ok(())
+}
+
+/// `rewards` subcommand: the wallet's staking rewards per epoch, newest
+/// first, with the running total
+pub fn show_rewards(
+    rpc_client: &dyn RpcApi,
+    address: &Pubkey,
+    epochs: u64,
+    output: Output,
+) -> Result<()> {
+    let (accounts, rewards) = staking::wallet_rewards(rpc_client, address, epochs)?;
+    let per_epoch = staking::per_epoch(&rewards);
+    let total = per_epoch.first().map_or(0, |epoch| epoch.cumulative);
+    let sol = |lamports| {
+        format!(
+            "{} SOL",
+            token::format_amount(lamports, history::SOL_DECIMALS)
+        )
+    };
+    let text = if accounts.is_empty() {
+        "The wallet is not the staker or withdrawer of any stake account".to_string()
+    } else {
+        let mut lines = vec![format!(
+            "{:>6}  {:>18}  {:>8}  {:>18}",
+            "Epoch", "Reward", "Accounts", "Total"
+        )];
+        lines.extend(per_epoch.iter().map(|epoch| {
+            format!(
+                "{:>6}  {:>18}  {:>8}  {:>18}",
+                epoch.epoch,
+                sol(epoch.lamports),
+                epoch.accounts,
+                sol(epoch.cumulative)
+            )
+        }));
+        lines.push(format!(
+            "{} over the last {} epochs, across {} stake accounts",
+            sol(total),
+            epochs,
+            accounts.len()
+        ));
+        lines.join("\n")
+    };
+    let json = serde_json::json!({
+        "stake_accounts": accounts.iter().map(Pubkey::to_string).collect::<Vec<_>>(),
+        "epochs": per_epoch
+            .iter()
+            .map(|epoch| {
+                serde_json::json!({
+                    "epoch": epoch.epoch,
+                    "lamports": epoch.lamports,
+                    "accounts": epoch.accounts,
+                    "cumulative": epoch.cumulative,
+                })
+            })
+            .collect::<Vec<_>>(),
+        "rewards": rewards
+            .iter()
+            .map(|reward| {
+                serde_json::json!({
+                    "epoch": reward.epoch,
+                    "stake_account": reward.stake_account.to_string(),
+                    "lamports": reward.lamports,
+                    "post_balance": reward.post_balance,
+                    "commission": reward.commission,
+                    "slot": reward.slot,
+                    "block_time": reward.block_time,
+                })
+            })
+            .collect::<Vec<_>>(),
+        "total": total,
+    });
+    output.print(text, json);
+    Ok(())
+}
+
+/// `audit` subcommand: the signatures in the audit log and whether its
+/// chain holds, or the log written to a file
+pub fn show_audit(export: Option<&Path>, output: Output) -> Result<()> {
+    let records = audit::load()?;
+    let chain = audit::verify(&records);
+    if let Some(path) = export {
+        audit::export(&records, path)?;
+        eprintln!(
+            "Wrote {} audit records to {}",
+            records.len(),
+            path.display()
+        );
+    } else {
+        let mut lines: Vec<String> = records.iter().map(audit::format_record).collect();
+        lines.push(audit::summary(&records, &chain));
+        output.print(
+            lines.join("\n"),
+            serde_json::json!({
+                "records": records,
+                "intact": chain == Chain::Intact,
+                "latest_hash": records.last().map(|record| &record.hash),
+            }),
+        );
+    }
+    if let Chain::Broken(i) = chain {
+        bail!("The audit log was changed at record {}", i);
+    }
+    Ok(())
+}
+
+/// `restore` subcommand: rebuild a keypair from share files, or from share
+/// lines typed on the terminal
+pub fn restore_from_shares(files: &[PathBuf], outfile: Option<PathBuf>) -> Result<()> {
+    let mut shares = Vec::new();
+    for file in files {
+        let text = Zeroizing::new(
+            std::fs::read_to_string(file)
+                .with_context(|| format!("Failed to read share: {}", file.display()))?,
+        );
+        shares.extend(shamir::parse_shares(&text)?);
+    }
+    if files.is_empty() {
+        println!("Enter one share per line, then an empty line to finish");
+        loop {
+            let line = Zeroizing::new(rpassword::prompt_password(format!(
+                "Share {}: ",
+                shares.len() + 1
+            ))?);
+            if line.trim().is_empty() {
+                break;
+            }
+            let share: shamir::Share = line.parse()?;
+            println!("Read share {}", share.index());
+            shares.push(share);
+        }
+    }
+
+    let keypair = shamir::combine(&shares)?;
+    let path = match outfile {
+        Some(path) => config::expand_tilde(&path),
+        None => keys::default_import_path(&keypair.pubkey())
+            .context("Could not determine home directory, pass --outfile")?,
+    };
+    keypair.write_to_file(&path)?;
+    println!("Saved {} to {}", keypair.pubkey(), path.display());
+    Ok(())
+}
+
+/// Read a base58 private key from the terminal and save it as a keypair file
+pub fn import_key_to_file(path: &Path) -> Result<()> {
+    let encoded = Zeroizing::new(rpassword::prompt_password("Base58 private key: ")?);
+    let keypair = SecretKeypair::from_base58(&encoded)?;
+    keypair.write_to_file(path)?;
+    println!("Saved {} to {}", keypair.pubkey(), path.display());
+    Ok(())
+}
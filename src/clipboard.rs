@@ -0,0 +1,42 @@
+//! Copying to the system clipboard with OSC 52, which the terminal carries
+//! out, so it works over SSH and without a display server
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use std::{
+    env,
+    io::{self, Write},
+};
+
+/// The escape sequence that puts `text` on the clipboard. Inside tmux it
+/// is wrapped for tmux to pass on to the outer terminal.
+pub fn sequence(text: &str, tmux: bool) -> String {
+    let osc = format!("\x1B]52;c;{}\x07", STANDARD.encode(text));
+    if tmux {
+        // Escapes inside the passthrough are doubled
+        format!("\x1BPtmux;\x1B{}\x1B\\", osc)
+    } else {
+        osc
+    }
+}
+
+/// Ask the terminal to put `text` on the clipboard. Terminals that don't
+/// allow OSC 52 ignore it, and there's no telling whether one did.
+pub fn copy(text: &str) -> io::Result<()> {
+    let mut stdout = io::stdout();
+    stdout.write_all(sequence(text, env::var_os("TMUX").is_some()).as_bytes())?;
+    stdout.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_and_wraps_for_tmux() {
+        assert_eq!(sequence("hi", false), "\x1B]52;c;aGk=\x07");
+        assert_eq!(
+            sequence("hi", true),
+            "\x1BPtmux;\x1B\x1B]52;c;aGk=\x07\x1B\\"
+        );
+    }
+}
@@ -0,0 +1,727 @@
+use crate::{
+    amount::Unit, daemon::SendPolicy, explorer::Explorer, keymap::KeyProfile, theme::ThemePreset,
+};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use solana_sdk::{commitment_config::CommitmentLevel, pubkey::Pubkey};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// User configuration loaded from `~/.config/solace/config.toml`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub cluster: ClusterConfig,
+    pub grpc: GrpcConfig,
+    /// Display names for wallets, by address, e.g. `"7xKX...AsU" = "Treasury"`
+    pub names: BTreeMap<String, String>,
+    /// Other wallets summed with this one on the Home dashboard
+    pub profiles: Vec<ProfileConfig>,
+    pub watch: WatchConfig,
+    pub price_alerts: PriceAlertConfig,
+    pub blocklist: BlocklistConfig,
+    pub limits: LimitsConfig,
+    pub confirm: ConfirmConfig,
+    pub jito: JitoConfig,
+    pub priority_fee: PriorityFeeConfig,
+    pub lookup_tables: LookupTablesConfig,
+    pub governance: GovernanceConfig,
+    pub staking: StakingConfig,
+    pub refresh: RefreshConfig,
+    pub cache: CacheConfig,
+    pub rate_limit: RateLimitConfig,
+    pub lock: LockConfig,
+    pub session: SessionConfig,
+    pub log: LogConfig,
+    pub audit: AuditConfig,
+    pub export: ExportConfig,
+    pub payments: PaymentsConfig,
+    pub accounts: AccountsConfig,
+    pub idl: IdlConfig,
+    pub explorer: ExplorerConfig,
+    pub webhook: WebhookConfig,
+    pub daemon: DaemonConfig,
+    pub keys: KeysConfig,
+    pub theme: ThemeConfig,
+    pub display: DisplayConfig,
+}
+
+/// The cluster connected to when `--cluster` isn't given
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClusterConfig {
+    /// `mainnet`, `testnet`, `devnet`, `localhost` or an RPC URL
+    pub name: String,
+    /// `processed`, `confirmed` or `finalized`: how settled the state read
+    /// from the node must be
+    pub commitment: CommitmentLevel,
+}
+
+impl Default for ClusterConfig {
+    fn default() -> Self {
+        Self {
+            name: "mainnet".to_string(),
+            commitment: CommitmentLevel::Confirmed,
+        }
+    }
+}
+
+/// A Yellowstone (Geyser) gRPC endpoint the wallet is followed over, in
+/// place of polling the RPC
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GrpcConfig {
+    /// e.g. `https://grpc.example.com:10000`
+    pub url: Option<String>,
+    /// Sent as `x-token`, for endpoints that need one
+    pub x_token: Option<String>,
+}
+
+/// A wallet shown on the Home dashboard, by its keypair file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileConfig {
+    pub keypair: PathBuf,
+}
+
+/// Addresses whose balances and activity the Watch screen follows, without
+/// their keys
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WatchConfig {
+    pub addresses: Vec<String>,
+    /// When to alert about each address, by address
+    pub alerts: BTreeMap<String, AlertConfig>,
+    /// How alerts are delivered
+    pub notify: Vec<Notify>,
+}
+
+impl Default for WatchConfig {
+    fn default() -> Self {
+        Self {
+            addresses: Vec::new(),
+            alerts: BTreeMap::new(),
+            notify: vec![Notify::Toast],
+        }
+    }
+}
+
+/// Thresholds for the alerts on one watched address, in SOL; either left
+/// out turns that alert off
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AlertConfig {
+    /// Each new transaction moving at least this much SOL in or out, 0 for
+    /// all of them
+    pub transaction: Option<f64>,
+    /// The balance moving by at least this much between refreshes
+    pub balance_change: Option<f64>,
+}
+
+/// USD prices of SOL and tokens that raise an alert when crossed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PriceAlertConfig {
+    /// Alert when SOL rises past this price
+    pub sol_above: Option<f64>,
+    /// Alert when SOL falls past this price
+    pub sol_below: Option<f64>,
+    /// Thresholds for tokens, by mint
+    pub tokens: BTreeMap<String, PriceThreshold>,
+    /// Seconds between checks of the prices
+    pub seconds: u64,
+    /// A Jupiter Price API v3 endpoint
+    pub url: String,
+    /// Also send alerts to the desktop's notification center
+    pub desktop: bool,
+}
+
+impl Default for PriceAlertConfig {
+    fn default() -> Self {
+        Self {
+            sol_above: None,
+            sol_below: None,
+            tokens: BTreeMap::new(),
+            seconds: 60,
+            url: "https://lite-api.jup.ag/price/v3".to_string(),
+            desktop: false,
+        }
+    }
+}
+
+/// The prices, in USD, one token is alerted at; either left out turns that
+/// alert off
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PriceThreshold {
+    pub above: Option<f64>,
+    pub below: Option<f64>,
+}
+
+/// Where an alert goes
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Notify {
+    /// A notification in the TUI
+    Toast,
+    /// The desktop's notification center
+    Desktop,
+    /// The `[webhook]` URL and command
+    Webhook,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BlocklistConfig {
+    /// Addresses listed inline in the config file
+    pub addresses: Vec<BlocklistEntry>,
+    /// Local blocklist file, one `<address> [reason]` per line
+    pub file: Option<PathBuf>,
+    /// Remote blocklist in the same format, fetched at startup and on demand
+    pub url: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlocklistEntry {
+    pub address: String,
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+/// Spending guardrails, in SOL
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LimitsConfig {
+    /// Maximum amount for a single transaction
+    pub per_transaction: Option<f64>,
+    /// Maximum total sent over the last 24 hours
+    pub per_day: Option<f64>,
+    /// Block sends that exceed a limit instead of asking to type the amount
+    pub restricted: bool,
+}
+
+/// Confirmation behaviour for outgoing transfers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConfirmConfig {
+    /// Mainnet sends of at least this many SOL must be confirmed by retyping
+    /// the amount or the first characters of the recipient
+    pub typed_threshold: f64,
+    /// Only report sends as done once they're finalized, rather than at the
+    /// RPC client's commitment
+    pub finalized: bool,
+}
+
+impl Default for ConfirmConfig {
+    fn default() -> Self {
+        Self {
+            typed_threshold: 1.0,
+            finalized: false,
+        }
+    }
+}
+
+/// Sending transfers through Jito's block engine
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct JitoConfig {
+    /// Send every transfer as a bundle with a tip (mainnet only)
+    pub enabled: bool,
+    pub block_engine: String,
+    /// Tip per transfer, in SOL
+    pub tip: f64,
+}
+
+impl Default for JitoConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            block_engine: crate::jito::DEFAULT_BLOCK_ENGINE.to_string(),
+            tip: 0.00001,
+        }
+    }
+}
+
+/// The priority fee suggested for transfers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PriorityFeeConfig {
+    /// Which percentile of the fees recently paid to write to the same
+    /// accounts to suggest; 0 turns priority fees off
+    pub percentile: u8,
+    /// Highest price suggested, in micro-lamports per compute unit
+    pub max: u64,
+}
+
+impl Default for PriorityFeeConfig {
+    fn default() -> Self {
+        Self {
+            percentile: 50,
+            max: 100_000,
+        }
+    }
+}
+
+/// Address lookup tables transfers can load addresses from
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LookupTablesConfig {
+    /// Table addresses; a transfer is sent as a v0 transaction when one of
+    /// them makes it smaller
+    pub addresses: Vec<String>,
+}
+
+/// DAOs on SPL Governance (Realms)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GovernanceConfig {
+    /// The governance program the realms run on
+    pub program: String,
+    /// Realm addresses whose proposals are listed
+    pub realms: Vec<String>,
+}
+
+impl Default for GovernanceConfig {
+    fn default() -> Self {
+        Self {
+            program: crate::governance::DEFAULT_PROGRAM_ID.to_string(),
+            realms: Vec::new(),
+        }
+    }
+}
+
+/// Rewards paid to the wallet's stake accounts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StakingConfig {
+    /// How many of the latest completed epochs the rewards table and
+    /// history exports cover; 0 leaves rewards out of exports
+    pub reward_epochs: u64,
+}
+
+impl Default for StakingConfig {
+    fn default() -> Self {
+        Self { reward_epochs: 10 }
+    }
+}
+
+/// Refreshing the balance and the open screen in the background
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RefreshConfig {
+    /// Seconds between refreshes; 0 leaves it to the refresh key
+    pub seconds: u64,
+    /// Overrides for single screens, by their name in the side menu, e.g.
+    /// `transactions = 120` or `home = 0`
+    pub screens: BTreeMap<String, u64>,
+}
+
+impl Default for RefreshConfig {
+    fn default() -> Self {
+        Self {
+            seconds: 30,
+            screens: BTreeMap::new(),
+        }
+    }
+}
+
+/// Keeping RPC reads of accounts that rarely change, such as mints and
+/// lookup tables, for a while
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CacheConfig {
+    /// How long, in seconds; 0 reads them every time
+    pub seconds: u64,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self { seconds: 60 }
+    }
+}
+
+/// Keeping to the request rate the RPC node allows
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    /// Requests per second; by default 10 on Solana's public endpoints and
+    /// no limit on others. 0 for no limit.
+    pub requests_per_second: Option<f64>,
+    /// Rates for single endpoints by URL, over the one above
+    pub endpoints: BTreeMap<String, f64>,
+    /// Times a request the node throttled (HTTP 429) or that timed out is
+    /// tried again, waiting twice as long each time
+    pub retries: u32,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            requests_per_second: None,
+            endpoints: BTreeMap::new(),
+            retries: 4,
+        }
+    }
+}
+
+/// Session auto-lock
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LockConfig {
+    /// Lock after this many minutes without input (disabled when unset)
+    pub timeout_minutes: Option<u64>,
+}
+
+/// Restoring where the TUI was left at the next start
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionConfig {
+    pub restore: bool,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self { restore: true }
+    }
+}
+
+/// The debug log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogConfig {
+    /// `error`, `warn`, `info`, `debug` (adds every RPC request), `trace`
+    /// or `off`
+    pub level: String,
+    /// Days of log files to keep
+    pub keep_days: usize,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            level: "info".to_string(),
+            keep_days: 7,
+        }
+    }
+}
+
+/// The audit log of signatures
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AuditConfig {
+    pub enabled: bool,
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+/// Private key / seed phrase export
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExportConfig {
+    /// Seconds an exported secret stays on screen before being cleared
+    pub reveal_seconds: u64,
+}
+
+impl Default for ExportConfig {
+    fn default() -> Self {
+        Self { reveal_seconds: 30 }
+    }
+}
+
+/// Payment requests made on the Receive screen, and references on payments
+/// sent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PaymentsConfig {
+    /// Hours an unpaid request stays pending before it's shown as expired
+    /// and no longer looked for; 0 keeps them pending
+    pub expire_hours: u64,
+    /// Add a new reference key to each transfer sent, recorded in the local
+    /// history, for recipients that match payments by it
+    pub reference: bool,
+}
+
+impl Default for PaymentsConfig {
+    fn default() -> Self {
+        Self {
+            expire_hours: 24,
+            reference: false,
+        }
+    }
+}
+
+/// Accounts derived from a seed phrase
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AccountsConfig {
+    /// How many `m/44'/501'/<n>'/0'` accounts to list
+    pub count: u32,
+}
+
+impl Default for AccountsConfig {
+    fn default() -> Self {
+        Self { count: 10 }
+    }
+}
+
+/// Anchor IDLs for decoding instructions in the transaction details
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IdlConfig {
+    /// IDL JSON files; each must record its program address
+    pub files: Vec<PathBuf>,
+    /// Look for a program's IDL account on chain when no file covers it
+    pub onchain: bool,
+}
+
+impl Default for IdlConfig {
+    fn default() -> Self {
+        Self {
+            files: Vec::new(),
+            onchain: true,
+        }
+    }
+}
+
+/// Where transactions and addresses are opened with 'o'
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExplorerConfig {
+    /// `solscan`, `solana-explorer`, `solanafm` or `xray`
+    pub provider: Explorer,
+    /// Make signatures and addresses clickable with OSC 8 hyperlinks;
+    /// detected from the terminal when unset
+    pub hyperlinks: Option<bool>,
+}
+
+/// Key bindings
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeysConfig {
+    /// `default`, or `vim` to add h/j/k/l, gg/G and '/' in list views
+    pub profile: KeyProfile,
+    /// Overrides on top of the profile, by context and then action, e.g.
+    /// `[keys.bindings.transactions] filter_incoming = ["I", "ctrl+i"]`
+    pub bindings: BTreeMap<String, BTreeMap<String, KeyList>>,
+}
+
+/// UI colors
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ThemeConfig {
+    /// `dark`, `light` or `high-contrast`
+    pub preset: ThemePreset,
+    /// Single colors replaced on top of the preset, e.g. `accent = "#ff8800"`
+    pub colors: BTreeMap<String, String>,
+}
+
+/// How balances and amounts are written in the TUI
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DisplayConfig {
+    /// Whose language and separators to use, e.g. `pt_BR` or `de`; taken
+    /// from `LC_ALL`, `LC_MESSAGES` or `LC_NUMERIC`, or `LANG`, when left
+    /// out
+    pub locale: Option<String>,
+    /// Decimal places to round to; as many as the amount needs when left
+    /// out
+    pub decimals: Option<u8>,
+    /// Group the digits of whole numbers in thousands
+    pub thousands: bool,
+    /// Shorten amounts of a thousand or more to `1.2K`, `3.4M`, `5.6B` or
+    /// `7.8T`
+    pub compact: bool,
+    /// `sol` or `lamports`: what SOL amounts are shown and typed in
+    pub unit: Unit,
+}
+
+impl Default for DisplayConfig {
+    fn default() -> Self {
+        Self {
+            locale: None,
+            decimals: None,
+            thousands: true,
+            compact: false,
+            unit: Unit::Sol,
+        }
+    }
+}
+
+/// One key or a list of them; an empty list unbinds the action
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum KeyList {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl KeyList {
+    pub fn iter(&self) -> std::slice::Iter<'_, String> {
+        match self {
+            Self::One(key) => std::slice::from_ref(key).iter(),
+            Self::Many(keys) => keys.iter(),
+        }
+    }
+}
+
+/// Hooks run when a transfer is received or a sent transaction confirms
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WebhookConfig {
+    /// URL the event is POSTed to as JSON
+    pub url: Option<String>,
+    /// Shell command run with the event JSON on stdin
+    pub command: Option<String>,
+    /// Shell command asked about each send before it's signed; a non-zero
+    /// exit refuses the send
+    pub pre_send: Option<String>,
+    /// Rhai script whose `on_receive`, `on_confirm` and `pre_send`
+    /// functions are called with events and sends
+    pub script: Option<String>,
+    /// Seconds between checks for incoming transfers
+    pub poll_seconds: u64,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            url: None,
+            command: None,
+            pre_send: None,
+            script: None,
+            poll_seconds: 30,
+        }
+    }
+}
+
+/// The local API served by `solace daemon`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DaemonConfig {
+    /// Port on 127.0.0.1
+    pub port: u16,
+    /// `prompt` to confirm sends on the daemon's terminal, `allow` to send
+    /// without asking or `deny` to refuse them
+    pub send: SendPolicy,
+}
+
+impl Default for DaemonConfig {
+    fn default() -> Self {
+        Self {
+            port: 7878,
+            send: SendPolicy::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Default config location (`<config dir>/solace/config.toml`)
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("solace").join("config.toml"))
+    }
+
+    /// Load the config from `path`, or from the default location if none is
+    /// given. A missing default config file is not an error.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let (path, required) = match path {
+            Some(path) => (path.to_path_buf(), true),
+            None => match Self::default_path() {
+                Some(path) => (path, false),
+                None => return Ok(Self::default()),
+            },
+        };
+
+        if !required && !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+        toml::from_str(&contents)
+            .with_context(|| format!("Failed to parse config file: {}", path.display()))
+    }
+
+    /// The name given to the wallet at `address`, if any
+    pub fn wallet_name(&self, address: &Pubkey) -> Option<&str> {
+        self.names
+            .get(&address.to_string())
+            .map(String::as_str)
+            .filter(|name| !name.is_empty())
+    }
+
+    /// Write one setting, `key` under `[section]`, to the config file at
+    /// `path`, leaving the rest of the file and its comments as they were
+    pub fn save_setting(
+        path: &Path,
+        section: &str,
+        key: &str,
+        value: impl Into<toml_edit::Value>,
+    ) -> Result<()> {
+        Self::edit_file(path, section, |table| {
+            table.insert(key, toml_edit::value(value));
+        })
+    }
+
+    /// Remove `key` under `[section]` from the config file at `path`, so
+    /// the default applies again
+    pub fn remove_setting(path: &Path, section: &str, key: &str) -> Result<()> {
+        Self::edit_file(path, section, |table| {
+            table.remove(key);
+        })
+    }
+
+    fn edit_file(
+        path: &Path,
+        section: &str,
+        edit: impl FnOnce(&mut dyn toml_edit::TableLike),
+    ) -> Result<()> {
+        let contents = if path.exists() {
+            fs::read_to_string(path)
+                .with_context(|| format!("Failed to read config file: {}", path.display()))?
+        } else {
+            String::new()
+        };
+        let mut document: toml_edit::DocumentMut = contents
+            .parse()
+            .with_context(|| format!("Failed to parse config file: {}", path.display()))?;
+        let table = document
+            .entry(section)
+            .or_insert_with(toml_edit::table)
+            .as_table_like_mut()
+            .with_context(|| format!("[{}] in {} isn't a table", section, path.display()))?;
+        edit(table);
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create directory: {}", dir.display()))?;
+        }
+        fs::write(path, document.to_string())
+            .with_context(|| format!("Failed to write config file: {}", path.display()))
+    }
+}
+
+/// Directory for state the wallet keeps between runs (`<data dir>/solace`)
+pub fn data_dir() -> Result<PathBuf> {
+    let dir = dirs::data_dir()
+        .context("Could not find data directory")?
+        .join("solace");
+    fs::create_dir_all(&dir)
+        .with_context(|| format!("Failed to create data directory: {}", dir.display()))?;
+    Ok(dir)
+}
+
+/// Expand a leading `~` to the user's home directory
+pub fn expand_tilde(path: &Path) -> PathBuf {
+    if let Ok(rest) = path.strip_prefix("~") {
+        if let Some(home) = dirs::home_dir() {
+            return home.join(rest);
+        }
+    }
+    path.to_path_buf()
+}
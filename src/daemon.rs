@@ -0,0 +1,591 @@
+//! `solace daemon`: keeps the wallet unlocked and its hooks running, and
+//! answers JSON-RPC requests from local tools over HTTP on localhost, so
+//! they can use the wallet without ever holding the key
+
+use crate::{
+    blocklist::Blocklist,
+    config::{self, Config},
+    historydb::HistoryDb,
+    keys::{self, SecretKeypair},
+    limits::SpendingLedger,
+    output::{self, Refused},
+    report,
+    rpc::RpcApi,
+    wallet::{
+        balance_json, check_send, confirm_send, fetch_history, is_mainnet, parse_send,
+        send_and_record, suggest_priority_fee, transfer_pipeline, typed_threshold,
+    },
+    webhook::{Hooks, Notice},
+};
+use anyhow::{bail, Context, Result};
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use solana_sdk::{pubkey::Pubkey, signature::Signer};
+use std::{
+    fmt::Write as _,
+    fs,
+    io::{self, BufRead, BufReader, ErrorKind, Read, Write},
+    net::{Ipv4Addr, TcpListener, TcpStream},
+    path::PathBuf,
+    str::FromStr,
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Largest request body accepted
+const MAX_BODY: usize = 64 * 1024;
+
+/// Longest request or header line accepted
+const MAX_LINE: usize = 8 * 1024;
+
+/// Most headers accepted in a request
+const MAX_HEADERS: usize = 64;
+
+/// How long a client gets to send its whole request, however slowly it
+/// sends each part of it
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How often hook errors are reported while no requests come in
+const IDLE_POLL: Duration = Duration::from_millis(250);
+
+/// Whether and how sends requested over the API are confirmed
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SendPolicy {
+    /// Refuse every send
+    Deny,
+    /// Ask on the daemon's terminal, by retyping the amount for large mainnet
+    /// sends
+    #[default]
+    Prompt,
+    /// Send without asking, unless the send exceeds a soft spending limit
+    Allow,
+}
+
+/// JSON-RPC error codes for the application's errors, by `output::classify`
+/// kind
+fn error_code(kind: &str) -> i64 {
+    match kind {
+        "rpc" => -32001,
+        "refused" => -32002,
+        _ => -32000,
+    }
+}
+
+#[derive(Deserialize)]
+struct Request {
+    #[serde(default)]
+    id: Value,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Deserialize)]
+struct SendParams {
+    to: String,
+    /// SOL
+    amount: f64,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct HistoryParams {
+    limit: Option<usize>,
+}
+
+pub struct Daemon {
+    rpc_client: Arc<dyn RpcApi>,
+    rpc_url: String,
+    config: Config,
+    keypair: SecretKeypair,
+    /// Pays the fees of sends instead of the wallet
+    fee_payer: Option<Arc<SecretKeypair>>,
+    blocklist: Blocklist,
+    spending: SpendingLedger,
+    hooks: Option<Hooks>,
+    token: String,
+}
+
+impl Daemon {
+    pub fn new(
+        rpc_client: Arc<dyn RpcApi>,
+        rpc_url: String,
+        config: Config,
+        keypair: SecretKeypair,
+        fee_payer: Option<Arc<SecretKeypair>>,
+        blocklist: Blocklist,
+    ) -> Result<Self> {
+        let hooks = Hooks::start(&config.webhook, &rpc_url, keypair.pubkey());
+        Ok(Self {
+            rpc_client,
+            rpc_url,
+            blocklist,
+            spending: SpendingLedger::load()?,
+            hooks,
+            token: new_token(),
+            config,
+            keypair,
+            fee_payer,
+        })
+    }
+
+    /// Write the token for clients and serve requests until the process is
+    /// stopped. Requests are handled one at a time, so a send waiting for
+    /// confirmation holds up the others.
+    pub fn serve(mut self) -> Result<()> {
+        let port = self.config.daemon.port;
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))
+            .with_context(|| format!("Failed to listen on 127.0.0.1:{}", port))?;
+        listener.set_nonblocking(true)?;
+        let token_path = write_token(&self.token)?;
+        eprintln!(
+            "Listening on http://127.0.0.1:{} (token in {})",
+            port,
+            token_path.display()
+        );
+
+        loop {
+            if let Some(ref hooks) = self.hooks {
+                for error in hooks.take_errors() {
+                    eprintln!("Warning: {}", error);
+                }
+                for notice in hooks.take_notices() {
+                    if let Err(e) = self.handle_notice(notice) {
+                        eprintln!("Warning: {:#}", e);
+                    }
+                }
+            }
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    if let Err(e) = self.handle_connection(stream) {
+                        eprintln!("Warning: {:#}", e);
+                    }
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => thread::sleep(IDLE_POLL),
+                Err(e) => return Err(e).context("Failed to accept a connection"),
+            }
+        }
+    }
+
+    /// Show what the hook script notified, and save its labels as notes
+    fn handle_notice(&self, notice: Notice) -> Result<()> {
+        match notice {
+            Notice::Message(text) => eprintln!("{}", text),
+            Notice::Label {
+                wallet,
+                signature,
+                label,
+            } => {
+                let wallet = Pubkey::from_str(&wallet).context("Invalid wallet address")?;
+                let cluster = self
+                    .rpc_client
+                    .get_genesis_hash()
+                    .context("Failed to fetch the genesis hash")?;
+                HistoryDb::open()?.set_note(&wallet, &cluster.to_string(), &signature, &label)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_connection(&mut self, mut stream: TcpStream) -> Result<()> {
+        stream.set_nonblocking(false)?;
+        let (status, body) = match read_request(&stream) {
+            Ok(http) => self.respond(&http),
+            Err(e) => (400, rpc_error(Value::Null, -32600, &format!("{:#}", e))),
+        };
+        let body = body.to_string();
+        write!(
+            stream,
+            "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+             Connection: close\r\n\r\n{}",
+            status_line(status),
+            body.len(),
+            body
+        )
+        .context("Failed to write the response")
+    }
+
+    fn respond(&mut self, http: &HttpRequest) -> (u16, Value) {
+        if http.method != "POST" {
+            return (
+                405,
+                rpc_error(Value::Null, -32600, "Only POST is supported"),
+            );
+        }
+        let authorized = http
+            .authorization
+            .as_deref()
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .is_some_and(|token| same_token(token.trim(), &self.token));
+        if !authorized {
+            return (
+                401,
+                rpc_error(Value::Null, -32600, "Missing or wrong token"),
+            );
+        }
+        let request: Request = match serde_json::from_slice(&http.body) {
+            Ok(request) => request,
+            Err(e) => return (200, rpc_error(Value::Null, -32700, &e.to_string())),
+        };
+        let id = request.id.clone();
+        let response = match self.call(&request.method, request.params) {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(Call::UnknownMethod) => {
+                rpc_error(id, -32601, &format!("Unknown method: {}", request.method))
+            }
+            Err(Call::InvalidParams(e)) => rpc_error(id, -32602, &e.to_string()),
+            Err(Call::Failed(e)) => {
+                eprintln!("{} failed: {:#}", request.method, e);
+                json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "error": {
+                        "code": error_code(output::classify(&e).0),
+                        "message": e.to_string(),
+                        "data": output::error_json(&e),
+                    }
+                })
+            }
+        };
+        (200, response)
+    }
+
+    /// Run a method; results have the same fields as `--output json`
+    fn call(&mut self, method: &str, params: Value) -> Result<Value, Call> {
+        let address = self.keypair.pubkey();
+        match method {
+            "address" => Ok(json!({ "address": address.to_string() })),
+            "balance" => {
+                let lamports = self
+                    .rpc_client
+                    .get_balance(&address)
+                    .context("Failed to fetch balance")?;
+                Ok(balance_json(&address, lamports))
+            }
+            "send" => {
+                let params: SendParams = params_from(params)?;
+                Ok(self.send(&params)?)
+            }
+            "history" => {
+                let params: HistoryParams = if params.is_null() {
+                    HistoryParams::default()
+                } else {
+                    params_from(params)?
+                };
+                let wanted = params.limit.unwrap_or(usize::MAX);
+                let history = fetch_history(&*self.rpc_client, &address, wanted, &mut |_| {})?;
+                let entries: Vec<_> = history.entries.iter().take(wanted).collect();
+                Ok(json!({
+                    "address": address.to_string(),
+                    "transactions": report::to_json(&entries, &[])?,
+                }))
+            }
+            _ => Err(Call::UnknownMethod),
+        }
+    }
+
+    /// A send with the Send screen's checks, confirmed as the policy says
+    fn send(&mut self, params: &SendParams) -> Result<Value> {
+        let policy = self.config.daemon.send;
+        if policy == SendPolicy::Deny {
+            bail!(Refused(
+                "Sends are turned off for the daemon (daemon.send = \"deny\")".to_string()
+            ));
+        }
+        let (recipient, lamports) = parse_send(&params.to, params.amount)?;
+        let mut pipeline =
+            transfer_pipeline(&self.config, false, None, self.fee_payer.clone(), || {
+                is_mainnet(&*self.rpc_client, &self.rpc_url)
+            })?;
+        let exceeded = check_send(
+            &*self.rpc_client,
+            &self.config,
+            &self.blocklist,
+            &self.spending,
+            &self.keypair.pubkey(),
+            &pipeline.fee_payer_of(&self.keypair.pubkey()),
+            &recipient,
+            lamports,
+        )?;
+        if let Some(ref reason) = exceeded {
+            eprintln!("Warning: this send {}", reason);
+        }
+        if policy == SendPolicy::Prompt || exceeded.is_some() {
+            self.confirm(&recipient, lamports, params.amount, exceeded.is_some())?;
+        }
+        pipeline.compute_unit_price = Some(suggest_priority_fee(
+            &*self.rpc_client,
+            &self.config,
+            &self.keypair.pubkey(),
+            &recipient,
+        ));
+        let sent = send_and_record(
+            &*self.rpc_client,
+            &pipeline,
+            &self.keypair,
+            &mut self.spending,
+            &recipient,
+            lamports,
+            &mut |_, _| {},
+        )?;
+        eprintln!(
+            "Sent {} SOL to {}: {}",
+            params.amount, recipient, sent.signature
+        );
+        if let Some(ref hooks) = self.hooks {
+            hooks.sent(&sent.from, &sent.signature, &recipient, lamports);
+        }
+        Ok(sent.to_json())
+    }
+
+    /// Ask on the daemon's terminal. Over-limit and large mainnet sends need
+    /// the amount retyped.
+    fn confirm(
+        &self,
+        recipient: &Pubkey,
+        lamports: u64,
+        amount: f64,
+        over_limit: bool,
+    ) -> Result<()> {
+        let typed = over_limit
+            || (lamports >= typed_threshold(&self.config)
+                && is_mainnet(&*self.rpc_client, &self.rpc_url));
+        eprintln!("A local client asked to send SOL");
+        confirm_send(recipient, amount, typed)
+    }
+}
+
+/// Why a method call didn't produce a result
+enum Call {
+    UnknownMethod,
+    InvalidParams(serde_json::Error),
+    Failed(anyhow::Error),
+}
+
+impl From<anyhow::Error> for Call {
+    fn from(error: anyhow::Error) -> Self {
+        Self::Failed(error)
+    }
+}
+
+fn params_from<T: for<'de> Deserialize<'de>>(params: Value) -> Result<T, Call> {
+    serde_json::from_value(params).map_err(Call::InvalidParams)
+}
+
+fn rpc_error(id: Value, code: i64, message: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message },
+    })
+}
+
+fn status_line(status: u16) -> &'static str {
+    match status {
+        200 => "200 OK",
+        400 => "400 Bad Request",
+        401 => "401 Unauthorized",
+        _ => "405 Method Not Allowed",
+    }
+}
+
+/// The parts of an HTTP request the API looks at
+struct HttpRequest {
+    method: String,
+    authorization: Option<String>,
+    body: Vec<u8>,
+}
+
+fn read_request(stream: &TcpStream) -> Result<HttpRequest> {
+    let mut reader = BufReader::new(Deadline {
+        stream,
+        until: Instant::now() + READ_TIMEOUT,
+    });
+    let mut line = String::new();
+    read_line(&mut reader, &mut line)?;
+    let method = line
+        .split_whitespace()
+        .next()
+        .context("Empty request")?
+        .to_string();
+
+    let mut authorization = None;
+    let mut length = 0;
+    let mut headers = 0;
+    loop {
+        if read_line(&mut reader, &mut line)? == 0 {
+            bail!("Connection closed in the headers");
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        headers += 1;
+        if headers > MAX_HEADERS {
+            bail!("Request has more than {} headers", MAX_HEADERS);
+        }
+        let Some((name, value)) = header.split_once(':') else {
+            bail!("Malformed header");
+        };
+        let value = value.trim();
+        if name.eq_ignore_ascii_case("authorization") {
+            authorization = Some(value.to_string());
+        } else if name.eq_ignore_ascii_case("content-length") {
+            length = value.parse().context("Invalid Content-Length")?;
+        }
+    }
+    if length > MAX_BODY {
+        bail!("Request body is larger than {} bytes", MAX_BODY);
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    Ok(HttpRequest {
+        method,
+        authorization,
+        body,
+    })
+}
+
+/// Read a line into `line`, refusing ones longer than `MAX_LINE`
+fn read_line(reader: &mut impl BufRead, line: &mut String) -> Result<usize> {
+    line.clear();
+    let read = reader.take(MAX_LINE as u64).read_line(line)?;
+    if read == MAX_LINE && !line.ends_with('\n') {
+        bail!("Request line or header is longer than {} bytes", MAX_LINE);
+    }
+    Ok(read)
+}
+
+/// The stream, timing out once the request has taken `READ_TIMEOUT` in all,
+/// so a client sending a byte at a time can't hold up the server
+struct Deadline<'a> {
+    stream: &'a TcpStream,
+    until: Instant,
+}
+
+impl Read for Deadline<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let left = self
+            .until
+            .checked_duration_since(Instant::now())
+            .filter(|left| !left.is_zero())
+            .ok_or_else(|| io::Error::new(ErrorKind::TimedOut, "Request took too long"))?;
+        self.stream.set_read_timeout(Some(left))?;
+        (&mut &*self.stream).read(buf)
+    }
+}
+
+/// A fresh random token for this run, as hex
+fn new_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().fold(String::new(), |mut hex, byte| {
+        let _ = write!(hex, "{:02x}", byte);
+        hex
+    })
+}
+
+/// Save the token where local clients read it, readable only by the owner
+fn write_token(token: &str) -> Result<PathBuf> {
+    let path = config::data_dir()?.join("daemon.token");
+    match fs::remove_file(&path) {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::NotFound => {}
+        Err(e) => {
+            return Err(e).with_context(|| format!("Failed to replace {}", path.display()));
+        }
+    }
+    keys::create_secret_file(&path)?
+        .write_all(token.as_bytes())
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+/// Compare without returning early, so response times don't give away how
+/// much of a guess was right
+fn same_token(given: &str, token: &str) -> bool {
+    given.len() == token.len()
+        && given
+            .bytes()
+            .zip(token.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_request_and_checks_the_token() {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let client = thread::spawn(move || {
+            let mut stream = TcpStream::connect((Ipv4Addr::LOCALHOST, port)).unwrap();
+            let body = r#"{"jsonrpc":"2.0","id":1,"method":"address"}"#;
+            write!(
+                stream,
+                "POST / HTTP/1.1\r\nAuthorization: Bearer abc\r\nContent-Length: {}\r\n\r\n{}",
+                body.len(),
+                body
+            )
+            .unwrap();
+        });
+        let (stream, _) = listener.accept().unwrap();
+        let request = read_request(&stream).unwrap();
+        client.join().unwrap();
+
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.authorization.as_deref(), Some("Bearer abc"));
+        let request: Request = serde_json::from_slice(&request.body).unwrap();
+        assert_eq!((request.id, request.method.as_str()), (json!(1), "address"));
+
+        assert!(same_token("abc", "abc"));
+        assert!(!same_token("abd", "abc"));
+        assert!(!same_token("ab", "abc"));
+        assert_eq!(new_token().len(), 64);
+    }
+
+    fn read(request: String) -> Result<HttpRequest> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let client = thread::spawn(move || {
+            let mut stream = TcpStream::connect((Ipv4Addr::LOCALHOST, port)).unwrap();
+            // The server may stop reading and hang up part way
+            let _ = stream.write_all(request.as_bytes());
+        });
+        let (stream, _) = listener.accept().unwrap();
+        let request = read_request(&stream);
+        client.join().unwrap();
+        request
+    }
+
+    #[test]
+    fn refuses_oversized_requests() {
+        let long = format!(
+            "POST / HTTP/1.1\r\nX-Long: {}\r\n\r\n",
+            "a".repeat(MAX_LINE)
+        );
+        assert!(read(long).is_err());
+
+        let many = format!(
+            "POST / HTTP/1.1\r\n{}\r\n",
+            "X-Header: a\r\n".repeat(MAX_HEADERS + 1)
+        );
+        assert!(read(many).is_err());
+        let enough = format!(
+            "POST / HTTP/1.1\r\n{}\r\n",
+            "X-Header: a\r\n".repeat(MAX_HEADERS)
+        );
+        assert!(read(enough).is_ok());
+
+        let large = format!(
+            "POST / HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+            MAX_BODY + 1
+        );
+        assert!(read(large).is_err());
+    }
+}
@@ -0,0 +1,165 @@
+//! The Home dashboard for wallets with `[[profiles]]` configured: the SOL
+//! and tokens each of them holds, and the totals across all of them.
+
+use crate::{history::mint_symbol, rpc::RpcApi, token};
+use anyhow::{Context, Result};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::BTreeMap;
+
+/// An amount of one token
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenBalance {
+    pub mint: Pubkey,
+    /// In the smallest units
+    pub amount: u64,
+    pub decimals: u8,
+}
+
+impl std::fmt::Display for TokenBalance {
+    /// `25.5 USDC`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {}",
+            token::format_amount(self.amount, self.decimals),
+            mint_symbol(&self.mint)
+        )
+    }
+}
+
+/// What one wallet holds
+#[derive(Debug, Clone, PartialEq)]
+pub struct WalletBalance {
+    pub address: Pubkey,
+    pub lamports: u64,
+    /// By mint, leaving out empty token accounts
+    pub tokens: Vec<TokenBalance>,
+}
+
+/// What every wallet holds, in the order they were asked for
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dashboard {
+    pub wallets: Vec<WalletBalance>,
+}
+
+impl Dashboard {
+    pub fn total_lamports(&self) -> u64 {
+        self.wallets.iter().map(|wallet| wallet.lamports).sum()
+    }
+
+    /// Each token summed across the wallets, by mint
+    pub fn total_tokens(&self) -> Vec<TokenBalance> {
+        let mut totals: BTreeMap<Pubkey, TokenBalance> = BTreeMap::new();
+        for balance in self.wallets.iter().flat_map(|wallet| &wallet.tokens) {
+            totals
+                .entry(balance.mint)
+                .and_modify(|total| total.amount = total.amount.saturating_add(balance.amount))
+                .or_insert_with(|| balance.clone());
+        }
+        totals.into_values().collect()
+    }
+}
+
+/// Fetch the SOL of all `addresses` in one request, then the token accounts
+/// of each and the mints they hold
+pub fn fetch(rpc_client: &dyn RpcApi, addresses: &[Pubkey]) -> Result<Dashboard> {
+    let accounts = rpc_client
+        .get_multiple_accounts(addresses)
+        .context("Failed to fetch the wallets' balances")?;
+    let mut decimals: BTreeMap<Pubkey, u8> = BTreeMap::new();
+    let mut wallets = Vec::with_capacity(addresses.len());
+    for (address, account) in addresses.iter().zip(accounts) {
+        let mut held: BTreeMap<Pubkey, u64> = BTreeMap::new();
+        for (_, account) in token::accounts_of(rpc_client, address)? {
+            if account.amount > 0 {
+                let amount = held.entry(account.mint).or_default();
+                *amount = amount.saturating_add(account.amount);
+            }
+        }
+        let mut tokens = Vec::with_capacity(held.len());
+        for (mint, amount) in held {
+            let places = match decimals.get(&mint) {
+                Some(&places) => places,
+                None => {
+                    let places = token::fetch_mint(rpc_client, &mint)?.decimals;
+                    *decimals.entry(mint).or_insert(places)
+                }
+            };
+            tokens.push(TokenBalance {
+                mint,
+                amount,
+                decimals: places,
+            });
+        }
+        wallets.push(WalletBalance {
+            address: *address,
+            lamports: account.map_or(0, |account| account.lamports),
+            tokens,
+        });
+    }
+    Ok(Dashboard { wallets })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::MockRpc;
+    use solana_sdk::{account::Account, program_pack::Pack};
+    use spl_token::state::{Account as TokenAccount, AccountState, Mint};
+
+    fn packed<T: Pack>(state: T, owner: Pubkey) -> Account {
+        let mut data = vec![0; T::LEN];
+        state.pack_into_slice(&mut data);
+        Account {
+            lamports: 2_039_280,
+            data,
+            owner,
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    #[test]
+    fn sums_balances_across_wallets() {
+        let rpc = MockRpc::default();
+        let (treasury, ops) = (Pubkey::new_unique(), Pubkey::new_unique());
+        rpc.set_balance(treasury, 5_000_000_000);
+        rpc.set_balance(ops, 1_500_000_000);
+        let mint = Pubkey::new_unique();
+        rpc.set_account(
+            mint,
+            packed(
+                Mint {
+                    decimals: 6,
+                    is_initialized: true,
+                    ..Mint::default()
+                },
+                spl_token::ID,
+            ),
+        );
+        for (owner, amount) in [(treasury, 2_000_000), (ops, 500_000), (ops, 0)] {
+            rpc.set_account(
+                Pubkey::new_unique(),
+                packed(
+                    TokenAccount {
+                        mint,
+                        owner,
+                        amount,
+                        state: AccountState::Initialized,
+                        ..TokenAccount::default()
+                    },
+                    spl_token::ID,
+                ),
+            );
+        }
+
+        let dashboard = fetch(&rpc, &[treasury, ops]).unwrap();
+        assert_eq!(dashboard.wallets[0].tokens[0].amount, 2_000_000);
+        assert_eq!(dashboard.wallets[1].tokens.len(), 1);
+        assert_eq!(dashboard.total_lamports(), 6_500_000_000);
+        let totals = dashboard.total_tokens();
+        assert_eq!(totals.len(), 1);
+        assert_eq!(totals[0].amount, 2_500_000);
+        assert_eq!(totals[0].to_string(), format!("2.5 {}", mint_symbol(&mint)));
+    }
+}
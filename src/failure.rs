@@ -0,0 +1,209 @@
+//! Errors shown on a screen: the whole cause chain, a hint at what to do
+//! about it, and the operation to run again if retrying makes sense
+
+use crate::output;
+use crate::theme::Theme;
+use ratatui::{
+    style::{Modifier, Style},
+    text::Line,
+};
+use std::fmt;
+
+/// Something that failed and can be run again
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Retry {
+    RefreshBalance,
+    /// Reopen the transaction history from the cache
+    LoadHistory,
+    /// Fetch the next older page of history
+    HistoryPage,
+    /// Fetch transactions newer than the cached ones
+    HistorySync,
+    /// Fetch the details of the selected transaction
+    Inspect,
+    LoadAccounts,
+    /// Look up the address on the Lookup screen again
+    LookupAccount,
+    LoadNonceAccounts,
+    /// Fetch the watched addresses' balances and transactions again
+    Watch,
+    /// Load the payment requests and look for their payments again
+    Requests,
+    /// Fetch the balances on the Home dashboard again
+    Dashboard,
+    /// Reopen the plugin screen that's showing
+    Plugin,
+    /// Sign and send the transfer again; only offered when it never left
+    Send,
+}
+
+#[derive(Debug, Clone)]
+pub struct Failure {
+    /// The error followed by its causes, outermost first
+    chain: Vec<String>,
+    remedy: Option<&'static str>,
+    /// Program logs of a failed transaction
+    logs: Vec<String>,
+    pub retry: Option<Retry>,
+}
+
+/// Log lines shown under a failed transaction, the last ones being where it
+/// failed
+const SHOWN_LOGS: usize = 8;
+
+impl Failure {
+    pub fn new(error: &anyhow::Error) -> Self {
+        let chain: Vec<String> = error.chain().map(|cause| cause.to_string()).collect();
+        let text = chain.join(": ");
+        tracing::warn!(error = %text, "Error shown");
+        let logs = output::logs(error).to_vec();
+        Self {
+            remedy: remedy(&text.to_lowercase()),
+            chain,
+            logs,
+            retry: None,
+        }
+    }
+
+    /// A problem with what was entered rather than an error
+    pub fn message(message: impl Into<String>) -> Self {
+        Self {
+            chain: vec![message.into()],
+            remedy: None,
+            logs: Vec::new(),
+            retry: None,
+        }
+    }
+
+    pub fn retry(mut self, retry: Retry) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    /// The panel's lines. The retry hint names `retry_key`, and is left out
+    /// if there's nothing to retry or no key to do it.
+    pub fn lines(&self, theme: &Theme, retry_key: Option<String>) -> Vec<Line<'static>> {
+        let style = Style::default().fg(theme.error);
+        let mut lines = vec![Line::styled(
+            format!("Error: {}", self.chain[0]),
+            style.add_modifier(Modifier::BOLD),
+        )];
+        for cause in &self.chain[1..] {
+            lines.push(Line::styled(format!("  caused by: {}", cause), style));
+        }
+        let skipped = self.logs.len().saturating_sub(SHOWN_LOGS);
+        if skipped > 0 {
+            lines.push(Line::styled(
+                format!("  ({} earlier log lines)", skipped),
+                Style::default().fg(theme.muted),
+            ));
+        }
+        for log in &self.logs[skipped..] {
+            lines.push(Line::styled(
+                format!("  {}", log),
+                Style::default().fg(theme.muted),
+            ));
+        }
+        if let Some(remedy) = self.remedy {
+            lines.push(Line::styled(
+                format!("Try: {}", remedy),
+                Style::default().fg(theme.muted),
+            ));
+        }
+        if let (Some(_), Some(key)) = (self.retry, retry_key) {
+            lines.push(Line::styled(
+                format!("Press {} to retry", key),
+                Style::default().fg(theme.accent),
+            ));
+        }
+        lines
+    }
+}
+
+/// Like `{:#}` on the original error
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.chain.join(": "))
+    }
+}
+
+/// A suggestion for the errors with a likely cause, going by the text of the
+/// lowercased error chain
+fn remedy(chain: &str) -> Option<&'static str> {
+    const REMEDIES: &[(&[&str], &str)] = &[
+        (
+            &["insufficient funds", "insufficient lamports"],
+            "the balance has to cover the amount plus the fee",
+        ),
+        (
+            &[
+                "blockhash not found",
+                "block height exceeded",
+                "expired before",
+            ],
+            "the network may be congested; retrying signs with a fresh blockhash",
+        ),
+        (
+            &["429", "too many requests", "rate limit"],
+            "the RPC node is rate limiting; wait a moment or use another endpoint",
+        ),
+        (
+            &[
+                "error sending request",
+                "connection refused",
+                "timed out",
+                "dns error",
+                "tcp connect",
+            ],
+            "check the RPC endpoint (--cluster) and your network connection",
+        ),
+        (&["wallet is locked"], "unlock the session first"),
+        (
+            &["permission denied"],
+            "check the permissions of the file and its directory",
+        ),
+        (
+            &["no such file or directory"],
+            "check the path; parent directories must exist",
+        ),
+    ];
+    REMEDIES
+        .iter()
+        .find(|(patterns, _)| patterns.iter().any(|pattern| chain.contains(pattern)))
+        .map(|&(_, remedy)| remedy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+
+    #[test]
+    fn shows_the_chain_and_a_remedy() {
+        let error =
+            anyhow!("tcp connect error: Connection refused").context("Failed to fetch balance");
+        let failure = Failure::new(&error).retry(Retry::RefreshBalance);
+        assert_eq!(
+            failure.to_string(),
+            "Failed to fetch balance: tcp connect error: Connection refused"
+        );
+        let lines = failure.lines(&Theme::default(), Some("Ctrl+r".to_string()));
+        let text: Vec<String> = lines.iter().map(Line::to_string).collect();
+        assert_eq!(
+            text,
+            [
+                "Error: Failed to fetch balance",
+                "  caused by: tcp connect error: Connection refused",
+                "Try: check the RPC endpoint (--cluster) and your network connection",
+                "Press Ctrl+r to retry",
+            ]
+        );
+
+        assert_eq!(
+            Failure::message("Amount does not match")
+                .lines(&Theme::default(), None)
+                .len(),
+            1
+        );
+    }
+}
@@ -0,0 +1,637 @@
+//! SPL Governance, the program behind Realms DAOs: the wallet's deposits
+//! in realms, the proposals being voted on, and casting its vote. Its
+//! accounts are Borsh encoded and read here field by field, as far as the
+//! wallet needs them.
+
+use crate::rpc::RpcApi;
+use crate::sender;
+use anyhow::{anyhow, bail, Context, Result};
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::{
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+    rpc_filter::{Memcmp, RpcFilterType},
+};
+#[allow(deprecated)]
+use solana_sdk::system_program;
+use solana_sdk::{
+    account::Account,
+    instruction::{AccountMeta, Instruction},
+    pubkey,
+    pubkey::Pubkey,
+    signature::{Signature, Signer},
+};
+use std::{fmt, str::FromStr};
+
+/// The governance program Realms deploys; some DAOs run their own copy
+pub const DEFAULT_PROGRAM_ID: Pubkey = pubkey!("GovER5Lthms3bLBqWub97yVrMmEogzX7xNjdXpPPCVZw");
+
+/// The first byte of each account, saying what it is
+const REALM_V1: u8 = 1;
+const TOKEN_OWNER_RECORD_V1: u8 = 2;
+const REALM_V2: u8 = 16;
+const TOKEN_OWNER_RECORD_V2: u8 = 17;
+const GOVERNANCE_V2: u8 = 18;
+const PROPOSAL_V2: u8 = 14;
+
+/// `CastVote` in the program's instruction enum
+const CAST_VOTE: u8 = 13;
+
+/// A DAO
+#[derive(Debug, Clone, PartialEq)]
+pub struct Realm {
+    pub address: Pubkey,
+    pub name: String,
+    pub community_mint: Pubkey,
+    pub council_mint: Option<Pubkey>,
+}
+
+impl Realm {
+    /// Which of the realm's tokens `mint` is
+    pub fn role(&self, mint: &Pubkey) -> &'static str {
+        if self.council_mint == Some(*mint) {
+            "council"
+        } else {
+            "community"
+        }
+    }
+}
+
+/// Tokens deposited in a realm, which give their owner a vote
+#[derive(Debug, Clone, PartialEq)]
+pub struct Membership {
+    /// The token owner record holding the deposit
+    pub record: Pubkey,
+    pub realm: Pubkey,
+    pub mint: Pubkey,
+    /// In the mint's smallest units
+    pub deposit: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProposalState {
+    Draft,
+    SigningOff,
+    Voting,
+    Succeeded,
+    Executing,
+    Completed,
+    Cancelled,
+    Defeated,
+    ExecutingWithErrors,
+    Vetoed,
+}
+
+impl fmt::Display for ProposalState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Draft => "draft",
+            Self::SigningOff => "signing off",
+            Self::Voting => "voting",
+            Self::Succeeded => "succeeded",
+            Self::Executing => "executing",
+            Self::Completed => "completed",
+            Self::Cancelled => "cancelled",
+            Self::Defeated => "defeated",
+            Self::ExecutingWithErrors => "executing with errors",
+            Self::Vetoed => "vetoed",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Proposal {
+    pub address: Pubkey,
+    pub governance: Pubkey,
+    /// The token it's voted on with, community or council
+    pub governing_token_mint: Pubkey,
+    pub state: ProposalState,
+    /// The record of whoever made it
+    pub token_owner_record: Pubkey,
+    pub name: String,
+    pub description_link: String,
+    /// Each option's label and the weight voted for it
+    pub options: Vec<(String, u64)>,
+    /// The weight voted against, for a yes/no proposal
+    pub deny_vote_weight: Option<u64>,
+    /// When voting started, as a Unix timestamp
+    pub voting_at: Option<i64>,
+}
+
+impl Proposal {
+    /// A single yes/no question, the only kind voted on here
+    pub fn is_yes_no(&self) -> bool {
+        self.options.len() == 1 && self.deny_vote_weight.is_some()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Vote {
+    Yes,
+    No,
+    Abstain,
+}
+
+impl FromStr for Vote {
+    type Err = anyhow::Error;
+
+    fn from_str(text: &str) -> Result<Self> {
+        match text.to_lowercase().as_str() {
+            "yes" | "approve" => Ok(Self::Yes),
+            "no" | "deny" => Ok(Self::No),
+            "abstain" => Ok(Self::Abstain),
+            _ => bail!("Invalid vote {:?}; vote yes, no or abstain", text),
+        }
+    }
+}
+
+impl fmt::Display for Vote {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Yes => "yes",
+            Self::No => "no",
+            Self::Abstain => "abstain",
+        })
+    }
+}
+
+pub fn fetch_realm(rpc_client: &dyn RpcApi, address: &Pubkey) -> Result<Realm> {
+    let account = rpc_client
+        .get_account(address)
+        .with_context(|| format!("Failed to fetch realm {}", address))?;
+    parse_realm(*address, &account.data).with_context(|| format!("{} is not a realm", address))
+}
+
+pub fn fetch_proposal(rpc_client: &dyn RpcApi, address: &Pubkey) -> Result<Proposal> {
+    let account = rpc_client
+        .get_account(address)
+        .with_context(|| format!("Failed to fetch proposal {}", address))?;
+    parse_proposal(*address, &account.data)
+        .with_context(|| format!("{} is not a proposal", address))
+}
+
+/// The realms `owner` has deposited tokens in, once per token
+pub fn memberships(
+    rpc_client: &dyn RpcApi,
+    program: &Pubkey,
+    owner: &Pubkey,
+) -> Result<Vec<Membership>> {
+    // The owner follows the type, the realm and the mint
+    let accounts = find(rpc_client, program, &[(65, owner.to_bytes().to_vec())])
+        .context("Failed to fetch the wallet's governance deposits")?;
+    Ok(accounts
+        .into_iter()
+        .filter_map(|(address, account)| parse_membership(address, &account.data).ok())
+        .filter(|membership| membership.deposit > 0)
+        .collect())
+}
+
+/// The proposals of `realm`, across its governances
+pub fn proposals(
+    rpc_client: &dyn RpcApi,
+    program: &Pubkey,
+    realm: &Pubkey,
+) -> Result<Vec<Proposal>> {
+    let governances = find(
+        rpc_client,
+        program,
+        &[(0, vec![GOVERNANCE_V2]), (1, realm.to_bytes().to_vec())],
+    )
+    .context("Failed to fetch the realm's governances")?;
+    let mut proposals = Vec::new();
+    for (governance, _) in governances {
+        let accounts = find(
+            rpc_client,
+            program,
+            &[(0, vec![PROPOSAL_V2]), (1, governance.to_bytes().to_vec())],
+        )
+        .context("Failed to fetch the realm's proposals")?;
+        proposals.extend(
+            accounts
+                .into_iter()
+                .filter_map(|(address, account)| parse_proposal(address, &account.data).ok()),
+        );
+    }
+    Ok(proposals)
+}
+
+/// Vote on `proposal` with the tokens `signer` deposited in its realm.
+/// Only yes/no proposals in their voting period are voted on, once.
+pub fn cast_vote(
+    rpc_client: &dyn RpcApi,
+    program: &Pubkey,
+    signer: &dyn Signer,
+    proposal: &Proposal,
+    vote: Vote,
+) -> Result<Signature> {
+    if proposal.state != ProposalState::Voting {
+        bail!("Proposal {} is not being voted on", proposal.address);
+    }
+    if !proposal.is_yes_no() {
+        bail!("Only yes/no proposals can be voted on here");
+    }
+    let wallet = signer.pubkey();
+    let governance = rpc_client
+        .get_account(&proposal.governance)
+        .context("Failed to fetch the proposal's governance")?;
+    let realm = Pubkey::try_from(governance.data.get(1..33).unwrap_or_default())
+        .map_err(|_| anyhow!("Invalid governance account {}", proposal.governance))?;
+
+    let voter_record =
+        token_owner_record_address(program, &realm, &proposal.governing_token_mint, &wallet);
+    let deposited = rpc_client
+        .get_account(&voter_record)
+        .ok()
+        .and_then(|account| parse_membership(voter_record, &account.data).ok())
+        .is_some_and(|membership| membership.deposit > 0);
+    if !deposited {
+        bail!(
+            "The wallet has no {} deposited in this realm to vote with",
+            proposal.governing_token_mint
+        );
+    }
+    let vote_record = Pubkey::find_program_address(
+        &[
+            b"governance",
+            proposal.address.as_ref(),
+            voter_record.as_ref(),
+        ],
+        program,
+    )
+    .0;
+    if rpc_client
+        .get_account_with_commitment(&vote_record, rpc_client.commitment())
+        .is_ok_and(|response| response.value.is_some())
+    {
+        bail!("The wallet has already voted on this proposal");
+    }
+    let realm_config = Pubkey::find_program_address(&[b"realm-config", realm.as_ref()], program).0;
+
+    let mut data = vec![CAST_VOTE];
+    match vote {
+        // Approve the one option with all the weight
+        Vote::Yes => data.extend_from_slice(&[0, 1, 0, 0, 0, 0, 100]),
+        Vote::No => data.push(1),
+        Vote::Abstain => data.push(2),
+    }
+    let instruction = Instruction {
+        program_id: *program,
+        accounts: vec![
+            AccountMeta::new_readonly(realm, false),
+            AccountMeta::new(proposal.governance, false),
+            AccountMeta::new(proposal.address, false),
+            AccountMeta::new(proposal.token_owner_record, false),
+            AccountMeta::new(voter_record, false),
+            AccountMeta::new_readonly(wallet, true),
+            AccountMeta::new(vote_record, false),
+            AccountMeta::new_readonly(proposal.governing_token_mint, false),
+            AccountMeta::new(wallet, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(realm_config, false),
+        ],
+        data,
+    };
+    sender::send_instructions(rpc_client, &[signer], &[instruction])
+        .context("Failed to cast the vote")
+}
+
+/// Where `owner`'s deposit of `mint` in `realm` is recorded
+pub fn token_owner_record_address(
+    program: &Pubkey,
+    realm: &Pubkey,
+    mint: &Pubkey,
+    owner: &Pubkey,
+) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"governance", realm.as_ref(), mint.as_ref(), owner.as_ref()],
+        program,
+    )
+    .0
+}
+
+fn parse_realm(address: Pubkey, data: &[u8]) -> Result<Realm> {
+    let mut reader = Reader(data);
+    if !matches!(reader.u8()?, REALM_V1 | REALM_V2) {
+        bail!("Not a realm account");
+    }
+    let community_mint = reader.pubkey()?;
+    // The config: two legacy bytes, six reserved, the minimum weight to
+    // create a governance and the max voter weight source
+    reader.take(2 + 6 + 8 + 1 + 8)?;
+    let council_mint = reader.option(Reader::pubkey)?;
+    // Reserved, then a legacy count
+    reader.take(6 + 2)?;
+    reader.option(Reader::pubkey)?;
+    Ok(Realm {
+        address,
+        name: reader.string()?,
+        community_mint,
+        council_mint,
+    })
+}
+
+fn parse_membership(record: Pubkey, data: &[u8]) -> Result<Membership> {
+    let mut reader = Reader(data);
+    if !matches!(reader.u8()?, TOKEN_OWNER_RECORD_V1 | TOKEN_OWNER_RECORD_V2) {
+        bail!("Not a token owner record");
+    }
+    let realm = reader.pubkey()?;
+    let mint = reader.pubkey()?;
+    reader.pubkey()?;
+    Ok(Membership {
+        record,
+        realm,
+        mint,
+        deposit: reader.u64()?,
+    })
+}
+
+fn parse_proposal(address: Pubkey, data: &[u8]) -> Result<Proposal> {
+    let mut reader = Reader(data);
+    if reader.u8()? != PROPOSAL_V2 {
+        bail!("Not a proposal account");
+    }
+    let governance = reader.pubkey()?;
+    let governing_token_mint = reader.pubkey()?;
+    let state = match reader.u8()? {
+        0 => ProposalState::Draft,
+        1 => ProposalState::SigningOff,
+        2 => ProposalState::Voting,
+        3 => ProposalState::Succeeded,
+        4 => ProposalState::Executing,
+        5 => ProposalState::Completed,
+        6 => ProposalState::Cancelled,
+        7 => ProposalState::Defeated,
+        8 => ProposalState::ExecutingWithErrors,
+        9 => ProposalState::Vetoed,
+        state => bail!("Unknown proposal state {}", state),
+    };
+    let token_owner_record = reader.pubkey()?;
+    // Signatories, and signed off
+    reader.take(2)?;
+    // Single choice, or multiple choice with its four settings
+    if reader.u8()? == 1 {
+        reader.take(4)?;
+    }
+    let options = (0..reader.u32()?)
+        .map(|_| {
+            let label = reader.string()?;
+            let weight = reader.u64()?;
+            // The result, then the counts of transactions executed, in
+            // total and next
+            reader.take(1 + 2 + 2 + 2)?;
+            Ok((label, weight))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let deny_vote_weight = reader.option(Reader::u64)?;
+    reader.take(1)?;
+    // Abstain weight, when voting is to start, drafted, signing off
+    reader.option(Reader::u64)?;
+    reader.option(Reader::u64)?;
+    reader.u64()?;
+    reader.option(Reader::u64)?;
+    let voting_at = reader.option(Reader::u64)?.map(|at| at as i64);
+    // The slot voting started at, when it completed, executing, closed
+    for _ in 0..4 {
+        reader.option(Reader::u64)?;
+    }
+    // Execution flags, the max vote weight and voting time
+    reader.take(1)?;
+    reader.option(Reader::u64)?;
+    reader.option(Reader::u32)?;
+    // The vote threshold, a percentage unless it's disabled
+    if reader.u8()? == 1 && reader.u8()? < 2 {
+        reader.take(1)?;
+    }
+    reader.take(64)?;
+    Ok(Proposal {
+        address,
+        governance,
+        governing_token_mint,
+        state,
+        token_owner_record,
+        name: reader.string()?,
+        description_link: reader.string()?,
+        options,
+        deny_vote_weight,
+        voting_at,
+    })
+}
+
+/// The program's accounts with each of the `(offset, bytes)` in their data
+fn find(
+    rpc_client: &dyn RpcApi,
+    program: &Pubkey,
+    matching: &[(usize, Vec<u8>)],
+) -> Result<Vec<(Pubkey, Account)>> {
+    Ok(rpc_client.get_program_accounts_with_config(
+        program,
+        RpcProgramAccountsConfig {
+            filters: Some(
+                matching
+                    .iter()
+                    .map(|(offset, bytes)| {
+                        RpcFilterType::Memcmp(Memcmp::new_raw_bytes(*offset, bytes.clone()))
+                    })
+                    .collect(),
+            ),
+            account_config: RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                ..RpcAccountInfoConfig::default()
+            },
+            ..RpcProgramAccountsConfig::default()
+        },
+    )?)
+}
+
+/// Borsh encoded fields, read from the front
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.0.len() < len {
+            bail!("Account data ends early");
+        }
+        let (taken, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into()?))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into()?))
+    }
+
+    fn pubkey(&mut self) -> Result<Pubkey> {
+        Ok(Pubkey::try_from(self.take(32)?)?)
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let len = self.u32()? as usize;
+        Ok(String::from_utf8_lossy(self.take(len)?).into_owned())
+    }
+
+    fn option<T>(&mut self, read: fn(&mut Self) -> Result<T>) -> Result<Option<T>> {
+        match self.u8()? {
+            0 => Ok(None),
+            _ => read(self).map(Some),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::MockRpc;
+    use solana_sdk::signature::Keypair;
+
+    fn governance_account(data: Vec<u8>) -> Account {
+        Account {
+            lamports: 1,
+            data,
+            owner: DEFAULT_PROGRAM_ID,
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    fn string(data: &mut Vec<u8>, text: &str) {
+        data.extend_from_slice(&(text.len() as u32).to_le_bytes());
+        data.extend_from_slice(text.as_bytes());
+    }
+
+    fn realm(community_mint: &Pubkey, council_mint: &Pubkey, name: &str) -> Vec<u8> {
+        let mut data = vec![REALM_V2];
+        data.extend_from_slice(community_mint.as_ref());
+        data.extend_from_slice(&[0; 2 + 6 + 8 + 1 + 8]);
+        data.push(1);
+        data.extend_from_slice(council_mint.as_ref());
+        data.extend_from_slice(&[0; 6 + 2]);
+        data.push(0);
+        string(&mut data, name);
+        data.extend_from_slice(&[0; 128]);
+        data
+    }
+
+    fn record(realm: &Pubkey, mint: &Pubkey, owner: &Pubkey, deposit: u64) -> Vec<u8> {
+        let mut data = vec![TOKEN_OWNER_RECORD_V2];
+        for key in [realm, mint, owner] {
+            data.extend_from_slice(key.as_ref());
+        }
+        data.extend_from_slice(&deposit.to_le_bytes());
+        data.extend_from_slice(&[0; 8 + 1 + 1 + 6 + 1 + 128]);
+        data
+    }
+
+    fn proposal(governance: &Pubkey, mint: &Pubkey, owner_record: &Pubkey) -> Vec<u8> {
+        let mut data = vec![PROPOSAL_V2];
+        data.extend_from_slice(governance.as_ref());
+        data.extend_from_slice(mint.as_ref());
+        data.push(2);
+        data.extend_from_slice(owner_record.as_ref());
+        data.extend_from_slice(&[1, 1, 0]);
+        data.extend_from_slice(&1u32.to_le_bytes());
+        string(&mut data, "Approve");
+        data.extend_from_slice(&700u64.to_le_bytes());
+        data.extend_from_slice(&[0; 7]);
+        data.push(1);
+        data.extend_from_slice(&300u64.to_le_bytes());
+        data.extend_from_slice(&[0, 0, 0]);
+        data.extend_from_slice(&1_700_000_000u64.to_le_bytes());
+        data.push(0);
+        data.push(1);
+        data.extend_from_slice(&1_700_000_100u64.to_le_bytes());
+        data.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0]);
+        data.extend_from_slice(&[1, 0, 60]);
+        data.extend_from_slice(&[0; 64]);
+        string(&mut data, "Fund the grants program");
+        string(&mut data, "https://example.com/proposal");
+        data.extend_from_slice(&0u64.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn finds_memberships_and_votes_on_proposals() {
+        let rpc = MockRpc::default();
+        let wallet = Keypair::new();
+        let program = DEFAULT_PROGRAM_ID;
+        let (realm_address, governance, proposal_address) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let (community, council) = (Pubkey::new_unique(), Pubkey::new_unique());
+        rpc.set_account(
+            realm_address,
+            governance_account(realm(&community, &council, "Test DAO")),
+        );
+        let mut governance_data = vec![GOVERNANCE_V2];
+        governance_data.extend_from_slice(realm_address.as_ref());
+        governance_data.extend_from_slice(&[0; 100]);
+        rpc.set_account(governance, governance_account(governance_data));
+        let voter_record =
+            token_owner_record_address(&program, &realm_address, &council, &wallet.pubkey());
+        rpc.set_account(
+            voter_record,
+            governance_account(record(&realm_address, &council, &wallet.pubkey(), 1)),
+        );
+        let author = Pubkey::new_unique();
+        rpc.set_account(
+            proposal_address,
+            governance_account(proposal(&governance, &council, &author)),
+        );
+
+        let realm = fetch_realm(&rpc, &realm_address).unwrap();
+        assert_eq!(realm.name, "Test DAO");
+        assert_eq!(realm.council_mint, Some(council));
+        assert_eq!(realm.role(&council), "council");
+
+        let memberships = memberships(&rpc, &program, &wallet.pubkey()).unwrap();
+        assert_eq!(
+            memberships,
+            [Membership {
+                record: voter_record,
+                realm: realm_address,
+                mint: council,
+                deposit: 1,
+            }]
+        );
+
+        let proposals = proposals(&rpc, &program, &realm_address).unwrap();
+        assert_eq!(proposals.len(), 1);
+        let proposal = &proposals[0];
+        assert_eq!(proposal.name, "Fund the grants program");
+        assert_eq!(proposal.description_link, "https://example.com/proposal");
+        assert_eq!(proposal.state, ProposalState::Voting);
+        assert_eq!(proposal.options, [("Approve".to_string(), 700)]);
+        assert_eq!(proposal.deny_vote_weight, Some(300));
+        assert_eq!(proposal.voting_at, Some(1_700_000_100));
+        assert_eq!(proposal.token_owner_record, author);
+        assert_eq!(fetch_proposal(&rpc, &proposal_address).unwrap(), *proposal);
+
+        cast_vote(&rpc, &program, &wallet, proposal, "yes".parse().unwrap()).unwrap();
+        let sent = rpc.sent();
+        let instruction = &sent[0].message.instructions()[0];
+        assert_eq!(instruction.data, [CAST_VOTE, 0, 1, 0, 0, 0, 0, 100]);
+        assert!(sent[0]
+            .message
+            .static_account_keys()
+            .contains(&voter_record));
+
+        // Without a deposit there's nothing to vote with
+        let outsider = Keypair::new();
+        assert!(cast_vote(&rpc, &program, &outsider, proposal, Vote::No).is_err());
+        let closed = Proposal {
+            state: ProposalState::Completed,
+            ..proposal.clone()
+        };
+        assert!(cast_vote(&rpc, &program, &wallet, &closed, Vote::No).is_err());
+        assert!("maybe".parse::<Vote>().is_err());
+    }
+}
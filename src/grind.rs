@@ -0,0 +1,164 @@
+use crate::keys::SecretKeypair;
+use anyhow::{bail, Result};
+use solana_sdk::signature::{Keypair, Signer};
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::Instant,
+};
+
+const BASE58_ALPHABET: &str = "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+/// Attempts counted locally by each worker before updating the shared total
+const ATTEMPT_BATCH: u64 = 256;
+
+/// What a vanity address should start and/or end with
+#[derive(Debug, Clone)]
+pub struct GrindPattern {
+    prefix: String,
+    suffix: String,
+    ignore_case: bool,
+}
+
+impl GrindPattern {
+    pub fn new(prefix: &str, suffix: &str, ignore_case: bool) -> Result<Self> {
+        if prefix.is_empty() && suffix.is_empty() {
+            bail!("Enter a prefix, a suffix, or both");
+        }
+        if let Some(c) = prefix
+            .chars()
+            .chain(suffix.chars())
+            .find(|c| !BASE58_ALPHABET.contains(*c))
+        {
+            bail!(
+                "'{}' can never appear in an address (base58 excludes 0, O, I and l)",
+                c
+            );
+        }
+
+        let normalize = |s: &str| {
+            if ignore_case {
+                s.to_lowercase()
+            } else {
+                s.to_string()
+            }
+        };
+        Ok(Self {
+            prefix: normalize(prefix),
+            suffix: normalize(suffix),
+            ignore_case,
+        })
+    }
+
+    pub fn matches(&self, address: &str) -> bool {
+        if self.ignore_case {
+            let address = address.to_lowercase();
+            address.starts_with(&self.prefix) && address.ends_with(&self.suffix)
+        } else {
+            address.starts_with(&self.prefix) && address.ends_with(&self.suffix)
+        }
+    }
+
+    /// Expected number of keys to try before finding a match
+    pub fn expected_attempts(&self) -> f64 {
+        let chars = (self.prefix.len() + self.suffix.len()) as i32;
+        let alphabet = if self.ignore_case { 34.0 } else { 58.0 };
+        f64::powi(alphabet, chars)
+    }
+}
+
+impl std::fmt::Display for GrindPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}…{}", self.prefix, self.suffix)?;
+        if self.ignore_case {
+            write!(f, " (any case)")?;
+        }
+        Ok(())
+    }
+}
+
+/// Background search for a keypair matching a [`GrindPattern`] on all cores.
+/// The search stops when a match is found or this is dropped.
+pub struct Grinder {
+    pattern: GrindPattern,
+    attempts: Arc<AtomicU64>,
+    stop: Arc<AtomicBool>,
+    found: Arc<Mutex<Option<SecretKeypair>>>,
+    started: Instant,
+    threads: usize,
+}
+
+impl Grinder {
+    pub fn start(pattern: GrindPattern) -> Self {
+        let threads = thread::available_parallelism().map_or(1, |n| n.get());
+        let attempts = Arc::new(AtomicU64::new(0));
+        let stop = Arc::new(AtomicBool::new(false));
+        let found = Arc::new(Mutex::new(None));
+
+        for _ in 0..threads {
+            let pattern = pattern.clone();
+            let attempts = Arc::clone(&attempts);
+            let stop = Arc::clone(&stop);
+            let found = Arc::clone(&found);
+            thread::spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    for _ in 0..ATTEMPT_BATCH {
+                        let keypair = Keypair::new();
+                        if pattern.matches(&keypair.pubkey().to_string()) {
+                            if !stop.swap(true, Ordering::Relaxed) {
+                                *found.lock().unwrap() = Some(SecretKeypair::from_keypair(keypair));
+                            }
+                            break;
+                        }
+                    }
+                    attempts.fetch_add(ATTEMPT_BATCH, Ordering::Relaxed);
+                }
+            });
+        }
+
+        Self {
+            pattern,
+            attempts,
+            stop,
+            found,
+            started: Instant::now(),
+            threads,
+        }
+    }
+
+    pub fn pattern(&self) -> &GrindPattern {
+        &self.pattern
+    }
+
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+
+    pub fn attempts(&self) -> u64 {
+        self.attempts.load(Ordering::Relaxed)
+    }
+
+    /// Keys tried per second so far
+    pub fn rate(&self) -> f64 {
+        let elapsed = self.started.elapsed().as_secs_f64();
+        if elapsed > 0.0 {
+            self.attempts() as f64 / elapsed
+        } else {
+            0.0
+        }
+    }
+
+    /// The matching keypair, once one has been found
+    pub fn take_result(&self) -> Option<SecretKeypair> {
+        self.found.lock().unwrap().take()
+    }
+}
+
+impl Drop for Grinder {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,317 @@
+//! Following the wallet over a Yellowstone (Geyser) gRPC stream, for those
+//! running their own infrastructure: balance changes and transactions are
+//! pushed as they land rather than found at the next poll of the RPC
+
+use crate::config::GrpcConfig;
+use anyhow::{Context, Result};
+use futures::{SinkExt, StreamExt};
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use yellowstone_grpc_client::{ClientTlsConfig, GeyserGrpcClient};
+use yellowstone_grpc_proto::prelude::{
+    subscribe_update::UpdateOneof, CommitmentLevel, SubscribeRequest,
+    SubscribeRequestFilterAccounts, SubscribeRequestFilterTransactions, SubscribeRequestPing,
+    SubscribeUpdate,
+};
+
+/// How long connecting may take before it counts as failed
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Longest wait between attempts to reconnect
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Name of the subscription's filters, one for the account and one for its
+/// transactions
+const FILTER: &str = "wallet";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Update {
+    /// The followed address's balance is now `lamports`
+    Balance(u64),
+    /// A transaction involving the followed address landed
+    Transaction { signature: Signature, failed: bool },
+}
+
+/// Handle on the background thread holding the stream, which reconnects
+/// with a growing delay when it drops. The thread exits when this is
+/// dropped.
+pub struct Stream {
+    addresses: UnboundedSender<Pubkey>,
+    updates: Receiver<(Pubkey, Update)>,
+    errors: Receiver<String>,
+    live: Arc<AtomicBool>,
+}
+
+impl Stream {
+    /// Start following `address`, or `None` if no endpoint is configured
+    pub fn start(config: &GrpcConfig, address: Pubkey) -> Option<Self> {
+        let url = config.url.clone()?;
+        let (addresses, inbox) = unbounded_channel();
+        let (updates, received) = mpsc::channel();
+        let (report, errors) = mpsc::channel();
+        let live = Arc::new(AtomicBool::new(false));
+        let worker = Worker {
+            url,
+            x_token: config.x_token.clone(),
+            address,
+            updates,
+            report,
+            live: Arc::clone(&live),
+        };
+        thread::spawn(move || {
+            match tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+            {
+                Ok(runtime) => runtime.block_on(worker.run(inbox)),
+                Err(e) => {
+                    let _ = worker
+                        .report
+                        .send(format!("Failed to start the gRPC stream: {}", e));
+                }
+            }
+        });
+        Some(Self {
+            addresses,
+            updates: received,
+            errors,
+            live,
+        })
+    }
+
+    /// Follow the wallet after switching accounts
+    pub fn watch(&self, address: Pubkey) {
+        let _ = self.addresses.send(address);
+    }
+
+    /// Whether the stream is up, so polling the RPC can wait
+    pub fn is_live(&self) -> bool {
+        self.live.load(Ordering::Relaxed)
+    }
+
+    /// Updates since the last call, with the address they're for
+    pub fn take_updates(&self) -> Vec<(Pubkey, Update)> {
+        self.updates.try_iter().collect()
+    }
+
+    /// Why the stream dropped, since the last call
+    pub fn take_errors(&self) -> Vec<String> {
+        self.errors.try_iter().collect()
+    }
+}
+
+struct Worker {
+    url: String,
+    x_token: Option<String>,
+    address: Pubkey,
+    updates: Sender<(Pubkey, Update)>,
+    report: Sender<String>,
+    live: Arc<AtomicBool>,
+}
+
+impl Worker {
+    async fn run(mut self, mut inbox: UnboundedReceiver<Pubkey>) {
+        let mut backoff = Duration::from_secs(1);
+        loop {
+            let error = match self.follow(&mut inbox).await {
+                Ok(()) => return,
+                Err(e) => format!("{:#}", e),
+            };
+            // A stream that was up starts over from the shortest delay
+            if self.live.swap(false, Ordering::Relaxed) {
+                backoff = Duration::from_secs(1);
+            }
+            tracing::warn!(error, "gRPC stream dropped");
+            let _ = self.report.send(error);
+
+            let sleep = tokio::time::sleep(backoff);
+            tokio::pin!(sleep);
+            loop {
+                tokio::select! {
+                    _ = &mut sleep => break,
+                    address = inbox.recv() => match address {
+                        Some(address) => self.address = address,
+                        None => return,
+                    },
+                }
+            }
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        }
+    }
+
+    /// Subscribe and pass updates on until the stream fails, or `Ok` once
+    /// the handle is dropped
+    async fn follow(&mut self, inbox: &mut UnboundedReceiver<Pubkey>) -> Result<()> {
+        let mut builder = GeyserGrpcClient::build_from_shared(self.url.clone())
+            .context("Invalid gRPC URL")?
+            .x_token(self.x_token.clone())
+            .context("Invalid gRPC token")?
+            .connect_timeout(CONNECT_TIMEOUT);
+        if self.url.starts_with("https://") {
+            builder = builder.tls_config(ClientTlsConfig::new().with_native_roots())?;
+        }
+        let mut client = builder
+            .connect()
+            .await
+            .context("Failed to connect to the gRPC endpoint")?;
+        let (mut sink, mut stream) = client
+            .subscribe_with_request(Some(subscription(&self.address)))
+            .await
+            .context("Failed to subscribe over gRPC")?;
+        self.live.store(true, Ordering::Relaxed);
+        tracing::info!(address = %self.address, "Following the wallet over gRPC");
+
+        loop {
+            tokio::select! {
+                address = inbox.recv() => {
+                    let Some(address) = address else {
+                        return Ok(());
+                    };
+                    self.address = address;
+                    sink.send(subscription(&address))
+                        .await
+                        .context("Failed to follow the new address over gRPC")?;
+                }
+                update = stream.next() => {
+                    let update = update
+                        .context("The gRPC stream ended")?
+                        .context("The gRPC stream failed")?;
+                    // Answered to keep load balancers from closing the stream
+                    if let Some(UpdateOneof::Ping(_)) = update.update_oneof {
+                        sink.send(SubscribeRequest {
+                            ping: Some(SubscribeRequestPing { id: 1 }),
+                            ..Default::default()
+                        })
+                        .await
+                        .context("Failed to answer the gRPC ping")?;
+                    } else if let Some(update) = parse(&self.address, update) {
+                        let _ = self.updates.send((self.address, update));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The account and transaction updates of `address`, once confirmed
+fn subscription(address: &Pubkey) -> SubscribeRequest {
+    SubscribeRequest {
+        accounts: HashMap::from([(
+            FILTER.to_string(),
+            SubscribeRequestFilterAccounts {
+                account: vec![address.to_string()],
+                ..Default::default()
+            },
+        )]),
+        transactions_status: HashMap::from([(
+            FILTER.to_string(),
+            SubscribeRequestFilterTransactions {
+                vote: Some(false),
+                account_include: vec![address.to_string()],
+                ..Default::default()
+            },
+        )]),
+        commitment: Some(CommitmentLevel::Confirmed as i32),
+        ..Default::default()
+    }
+}
+
+/// What `update` says about `address`, if anything
+fn parse(address: &Pubkey, update: SubscribeUpdate) -> Option<Update> {
+    match update.update_oneof? {
+        UpdateOneof::Account(update) => {
+            let account = update.account?;
+            (account.pubkey == address.as_ref()).then_some(Update::Balance(account.lamports))
+        }
+        UpdateOneof::TransactionStatus(status) => Some(Update::Transaction {
+            signature: Signature::try_from(status.signature.as_slice()).ok()?,
+            failed: status.err.is_some(),
+        }),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yellowstone_grpc_proto::prelude::{
+        SubscribeUpdateAccount, SubscribeUpdateAccountInfo, SubscribeUpdateTransactionStatus,
+    };
+
+    #[test]
+    fn updates_are_read_for_the_followed_address() {
+        let address = Pubkey::new_unique();
+        let account = |pubkey: Pubkey| SubscribeUpdate {
+            update_oneof: Some(UpdateOneof::Account(SubscribeUpdateAccount {
+                account: Some(SubscribeUpdateAccountInfo {
+                    pubkey: pubkey.to_bytes().to_vec(),
+                    lamports: 42,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            })),
+            ..Default::default()
+        };
+        assert_eq!(parse(&address, account(address)), Some(Update::Balance(42)));
+        // Left over from the address followed before
+        assert_eq!(parse(&address, account(Pubkey::new_unique())), None);
+
+        let signature = Signature::from([7; 64]);
+        let status = SubscribeUpdate {
+            update_oneof: Some(UpdateOneof::TransactionStatus(
+                SubscribeUpdateTransactionStatus {
+                    signature: signature.as_ref().to_vec(),
+                    ..Default::default()
+                },
+            )),
+            ..Default::default()
+        };
+        assert_eq!(
+            parse(&address, status),
+            Some(Update::Transaction {
+                signature,
+                failed: false
+            })
+        );
+    }
+
+    #[test]
+    fn subscriptions_follow_one_address() {
+        let address = Pubkey::new_unique();
+        let request = subscription(&address);
+        assert_eq!(request.accounts[FILTER].account, [address.to_string()]);
+        let transactions = &request.transactions_status[FILTER];
+        assert_eq!(transactions.account_include, [address.to_string()]);
+        assert_eq!(transactions.vote, Some(false));
+        assert!(Stream::start(&GrpcConfig::default(), address).is_none());
+    }
+
+    #[test]
+    fn unreachable_endpoints_are_reported() {
+        let config = GrpcConfig {
+            url: Some("http://127.0.0.1:9".to_string()),
+            x_token: None,
+        };
+        let stream = Stream::start(&config, Pubkey::new_unique()).unwrap();
+        let started = std::time::Instant::now();
+        let errors = loop {
+            let errors = stream.take_errors();
+            if !errors.is_empty() || started.elapsed() > CONNECT_TIMEOUT * 2 {
+                break errors;
+            }
+            thread::sleep(Duration::from_millis(20));
+        };
+        assert!(errors[0].starts_with("Failed to connect"), "{:?}", errors);
+        assert!(!stream.is_live());
+    }
+}
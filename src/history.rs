@@ -0,0 +1,1148 @@
+//! Transaction history for the wallet address, loaded a page at a time
+
+use crate::failure::Failure;
+use crate::i18n::{tr, tr_fmt};
+use crate::idl::{DecodedInstruction, IdlRegistry};
+use crate::programs::{self, Category, MEMO_PROGRAM_ID};
+use crate::report::ReportFormat;
+use crate::rpc::RpcApi;
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use solana_client::{
+    rpc_client::GetConfirmedSignaturesForAddress2Config, rpc_config::RpcTransactionConfig,
+};
+use solana_sdk::transaction::VersionedTransaction;
+use solana_sdk::{
+    commitment_config::CommitmentConfig, hash::Hash, message::VersionedMessage, pubkey,
+    pubkey::Pubkey, signature::Signature,
+};
+#[allow(deprecated)]
+use solana_sdk::{compute_budget, stake, system_instruction::SystemInstruction, system_program};
+use solana_transaction_status::{
+    EncodedConfirmedTransactionWithStatusMeta, TransactionStatus, UiLoadedAddresses,
+    UiTransactionEncoding, UiTransactionStatusMeta, UiTransactionTokenBalance,
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Signatures fetched per RPC request
+pub const PAGE_SIZE: usize = 25;
+
+/// Start loading the next page when the selection is this close to the end
+const PREFETCH_ROWS: usize = 5;
+
+/// Pages fetched at most when catching up with new transactions. Beyond this
+/// the cache is dropped rather than left with a gap.
+const SYNC_MAX_PAGES: usize = 8;
+
+const TOKEN_2022_PROGRAM_ID: Pubkey = pubkey!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+
+/// Symbols for the most common mints; others are shown by address
+pub const KNOWN_MINTS: &[(Pubkey, &str)] = &[
+    (
+        pubkey!("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v"),
+        "USDC",
+    ),
+    (
+        pubkey!("Es9vMFrzaCERmJfrF4H2FYD4KCoNkY11McCe8BenwNYB"),
+        "USDT",
+    ),
+    (
+        pubkey!("So11111111111111111111111111111111111111112"),
+        "wSOL",
+    ),
+    (
+        pubkey!("mSoLzYCxHdYgdzU16g5QSh3i5K3z3KZK7ytfqcJm7So"),
+        "mSOL",
+    ),
+    (
+        pubkey!("J1toso1uCk3RLmjorhTtrVwY9HJ7X8V9yYac6Y7kGCPn"),
+        "jitoSOL",
+    ),
+    (
+        pubkey!("JUPyiwrYJFskUPiHa7hkeR8VUtAeFoSYbKedZNsDvCN"),
+        "JUP",
+    ),
+    (
+        pubkey!("DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263"),
+        "BONK",
+    ),
+];
+
+pub const SOL_DECIMALS: u8 = 9;
+
+/// What a transaction mostly did, judged by the programs it called
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TxKind {
+    /// Only the system program (plus compute budget)
+    Transfer,
+    Token,
+    Stake,
+    /// Anything else, with the first program that isn't one of the above
+    Program(Pubkey),
+}
+
+/// Decoded details from `getTransaction`
+#[derive(Debug, Clone)]
+pub struct TxDetails {
+    pub kind: TxKind,
+    /// Change in the wallet's SOL balance, including the fee if it paid it
+    pub lamports_delta: i64,
+    pub fee: u64,
+    /// The wallet was the first signer, so the fee came out of its balance
+    pub fee_payer: bool,
+    /// The account whose balance moved the most the other way
+    pub counterparty: Option<Pubkey>,
+    /// Net change per mint across token accounts the wallet owns
+    pub token_deltas: Vec<TokenDelta>,
+}
+
+impl TxDetails {
+    /// Human-readable category, e.g. "Swap" or "NFT sale"
+    pub fn label(&self) -> &'static str {
+        match self.kind {
+            TxKind::Transfer => "Transfer",
+            TxKind::Token => "Token",
+            TxKind::Stake => "Stake",
+            TxKind::Program(program) => match programs::lookup(&program).map(|p| p.category) {
+                Some(Category::Swap) => "Swap",
+                Some(Category::Liquidity) => "Liquidity",
+                Some(Category::Nft) if self.lamports_delta > 0 => "NFT sale",
+                Some(Category::Nft) if self.token_deltas.iter().any(|d| d.amount > 0) => {
+                    "NFT purchase"
+                }
+                Some(Category::Nft) => "NFT",
+                Some(Category::Lending) => "Lending",
+                Some(Category::LiquidStaking) => "Liquid stake",
+                None => "Program",
+            },
+        }
+    }
+
+    /// Lamports the wallet paid in fees
+    pub fn paid_fee(&self) -> u64 {
+        if self.fee_payer {
+            self.fee
+        } else {
+            0
+        }
+    }
+
+    /// Name of the main program called, if it's one we know
+    pub fn program_name(&self) -> Option<&'static str> {
+        match self.kind {
+            TxKind::Program(program) => programs::lookup(&program).map(|p| p.name),
+            _ => None,
+        }
+    }
+
+    /// `+1.5 SOL`, with the fee included when the wallet paid it
+    pub fn sol_change(&self) -> String {
+        format!(
+            "{} SOL",
+            format_amount(i128::from(self.lamports_delta), SOL_DECIMALS)
+        )
+    }
+}
+
+/// Net change in the wallet's balance of one mint, in base units
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenDelta {
+    pub mint: Pubkey,
+    pub amount: i128,
+    pub decimals: u8,
+}
+
+impl TokenDelta {
+    pub fn symbol(&self) -> String {
+        mint_symbol(&self.mint)
+    }
+}
+
+/// The symbol of a known mint, or the start of its address
+pub fn mint_symbol(mint: &Pubkey) -> String {
+    KNOWN_MINTS
+        .iter()
+        .find(|(known, _)| known == mint)
+        .map(|(_, symbol)| symbol.to_string())
+        .unwrap_or_else(|| {
+            let mint = mint.to_string();
+            format!("{}…", &mint[..4])
+        })
+}
+
+impl std::fmt::Display for TokenDelta {
+    /// `-25 USDC`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {}",
+            format_amount(self.amount, self.decimals),
+            self.symbol()
+        )
+    }
+}
+
+/// History filters, toggled independently in the Transactions view
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HistoryFilter {
+    Incoming,
+    Outgoing,
+    Failed,
+    Token,
+    Staking,
+    #[serde(rename = "other")]
+    OtherProgram,
+}
+
+impl HistoryFilter {
+    pub const ALL: [HistoryFilter; 6] = [
+        Self::Incoming,
+        Self::Outgoing,
+        Self::Failed,
+        Self::Token,
+        Self::Staking,
+        Self::OtherProgram,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Incoming => "incoming",
+            Self::Outgoing => "outgoing",
+            Self::Failed => "failed",
+            Self::Token => "token",
+            Self::Staking => "staking",
+            Self::OtherProgram => "other",
+        }
+    }
+
+    fn matches(self, entry: &HistoryEntry) -> bool {
+        let details = entry.details.as_ref();
+        match self {
+            Self::Failed => entry.failed,
+            Self::Incoming => details.is_some_and(|d| d.lamports_delta > 0),
+            Self::Outgoing => details.is_some_and(|d| d.lamports_delta < -(d.fee as i64)),
+            Self::Token => details.is_some_and(|d| d.kind == TxKind::Token),
+            Self::Staking => details.is_some_and(|d| d.kind == TxKind::Stake),
+            Self::OtherProgram => details.is_some_and(|d| matches!(d.kind, TxKind::Program(_))),
+        }
+    }
+}
+
+/// One signature from `getSignaturesForAddress`
+#[derive(Debug, Clone)]
+pub struct HistoryEntry {
+    pub signature: String,
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    pub failed: bool,
+    pub memo: Option<String>,
+    /// The user's own label for the transaction, kept in the local index
+    pub note: Option<String>,
+    /// The reference key a transfer the wallet sent carried, kept in the
+    /// local index
+    pub reference: Option<String>,
+    /// `None` if the transaction couldn't be fetched or decoded
+    pub details: Option<TxDetails>,
+}
+
+impl HistoryEntry {
+    /// ASCII case-insensitive substring match on the searchable fields
+    fn matches_search(&self, query: &str) -> bool {
+        let counterparty = self
+            .details
+            .as_ref()
+            .and_then(|details| details.counterparty)
+            .map(|key| key.to_string());
+        let matched = [
+            Some(self.signature.as_str()),
+            counterparty.as_deref(),
+            self.memo.as_deref(),
+            self.note.as_deref(),
+            self.reference.as_deref(),
+        ]
+        .into_iter()
+        .flatten()
+        .any(|field| find_ignore_case(field, query).is_some());
+        matched
+    }
+
+    /// Failed on chain, so it didn't move anything and can be sent again.
+    /// Everything listed has landed, with or without a block time.
+    pub fn retryable(&self) -> bool {
+        self.failed
+    }
+}
+
+/// Byte offset of the first ASCII case-insensitive match of `query`
+pub fn find_ignore_case(text: &str, query: &str) -> Option<usize> {
+    if query.is_empty() {
+        return None;
+    }
+    text.to_ascii_lowercase().find(&query.to_ascii_lowercase())
+}
+
+#[derive(Debug, Default)]
+pub struct History {
+    pub entries: Vec<HistoryEntry>,
+    /// Position of the selection among the filtered entries
+    pub selected: usize,
+    /// Active filters; an entry is shown if it matches any of them
+    pub filters: Vec<HistoryFilter>,
+    /// Substring to match against signature, counterparty, memo or note
+    pub search: String,
+    /// The `/` prompt is open and keys edit `search`
+    pub searching: bool,
+    /// Instruction breakdown of the selected transaction, when open
+    pub inspection: Option<TxInspection>,
+    /// Note being typed for the selected entry, while the prompt is open
+    pub editing_note: Option<String>,
+    /// Path typed at the export prompt, while it is open
+    pub export_path: Option<String>,
+    /// Format picked at the export prompt; by the file extension if unset
+    pub export_format: Option<ReportFormat>,
+    /// The RPC returned a short page, so there is nothing older to load
+    pub exhausted: bool,
+    /// Showing cached entries that haven't been checked for newer ones yet
+    pub pending_sync: bool,
+    pub error: Option<Failure>,
+}
+
+impl History {
+    /// Start from entries cached locally, checking for newer ones later
+    pub fn from_cache(entries: Vec<HistoryEntry>, complete: bool) -> Self {
+        Self {
+            pending_sync: !entries.is_empty(),
+            exhausted: complete,
+            entries,
+            ..Self::default()
+        }
+    }
+
+    /// Fetch the page of signatures older than the last one loaded, returning
+    /// the new entries
+    pub fn load_page(
+        &mut self,
+        rpc_client: &dyn RpcApi,
+        address: &Pubkey,
+    ) -> Result<&[HistoryEntry]> {
+        let page = fetch_page(rpc_client, address, self.oldest()?, None)?;
+        Ok(self.append_page(page))
+    }
+
+    /// Signature of the oldest entry loaded, where the next page starts
+    pub fn oldest(&self) -> Result<Option<Signature>> {
+        self.entries
+            .last()
+            .map(|entry| Signature::from_str(&entry.signature))
+            .transpose()
+            .context("Invalid signature cursor")
+    }
+
+    /// Signature of the newest entry loaded, where syncing stops
+    pub fn newest(&self) -> Result<Option<Signature>> {
+        self.entries
+            .first()
+            .map(|entry| Signature::from_str(&entry.signature))
+            .transpose()
+            .context("Invalid signature cursor")
+    }
+
+    /// Add a page fetched from `oldest()` below the loaded entries,
+    /// returning them
+    pub fn append_page(&mut self, page: Vec<HistoryEntry>) -> &[HistoryEntry] {
+        self.exhausted = page.len() < PAGE_SIZE;
+        let start = self.entries.len();
+        self.entries.extend(page);
+        &self.entries[start..]
+    }
+
+    /// Fetch signatures newer than the newest one shown and put them on top.
+    /// Returns the new entries and whether they replaced everything because
+    /// there were too many to catch up with.
+    pub fn sync_new(
+        &mut self,
+        rpc_client: &dyn RpcApi,
+        address: &Pubkey,
+    ) -> Result<(&[HistoryEntry], bool)> {
+        self.pending_sync = false;
+        let (new, caught_up) = fetch_newer(rpc_client, address, self.newest()?)?;
+        Ok(self.prepend_newer(new, caught_up))
+    }
+
+    /// Put entries fetched by `fetch_newer` on top, as `sync_new` does
+    pub fn prepend_newer(
+        &mut self,
+        mut new: Vec<HistoryEntry>,
+        caught_up: bool,
+    ) -> (&[HistoryEntry], bool) {
+        let count = new.len();
+        let replaced = !caught_up;
+        if replaced {
+            self.exhausted = false;
+            self.entries = new;
+        } else {
+            new.append(&mut self.entries);
+            self.entries = new;
+        }
+        // Keep the selection on the same entry when rows are added above it
+        if !replaced && self.filters.is_empty() && self.search.is_empty() {
+            self.selected += count;
+        }
+        (&self.entries[..count], replaced)
+    }
+
+    /// Entries that pass the active filters and search
+    pub fn visible(&self) -> Vec<&HistoryEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| {
+                self.filters.is_empty() || self.filters.iter().any(|filter| filter.matches(entry))
+            })
+            .filter(|entry| self.search.is_empty() || entry.matches_search(&self.search))
+            .collect()
+    }
+
+    /// Whether staking rewards belong in an export of the visible entries:
+    /// they're incoming staking payouts, so with no search and either no
+    /// filters or the incoming or staking one
+    pub fn shows_rewards(&self) -> bool {
+        self.search.is_empty()
+            && (self.filters.is_empty()
+                || self.filters.iter().any(|filter| {
+                    matches!(filter, HistoryFilter::Incoming | HistoryFilter::Staking)
+                }))
+    }
+
+    /// Attach saved notes to entries fetched from the RPC
+    pub fn apply_notes(&mut self, notes: &HashMap<String, String>) {
+        for entry in &mut self.entries {
+            if let Some(note) = notes.get(&entry.signature) {
+                entry.note = Some(note.clone());
+            }
+        }
+    }
+
+    /// Attach the recorded reference keys to entries fetched from the RPC
+    pub fn apply_references(&mut self, references: &HashMap<String, String>) {
+        for entry in &mut self.entries {
+            if let Some(reference) = references.get(&entry.signature) {
+                entry.reference = Some(reference.clone());
+            }
+        }
+    }
+
+    pub fn toggle_filter(&mut self, filter: HistoryFilter) {
+        if let Some(i) = self.filters.iter().position(|f| *f == filter) {
+            self.filters.remove(i);
+        } else {
+            self.filters.push(filter);
+        }
+        self.selected = 0;
+    }
+
+    /// Whether the selection is close enough to the end to load more
+    pub fn wants_more(&self) -> bool {
+        !self.exhausted && self.selected + PREFETCH_ROWS >= self.visible().len()
+    }
+
+    pub fn select_next(&mut self) {
+        if self.selected + 1 < self.visible().len() {
+            self.selected += 1;
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    /// Select the last row loaded so far
+    pub fn select_last(&mut self) {
+        self.selected = self.visible().len().saturating_sub(1);
+    }
+
+    /// The loaded entry with `signature`
+    pub fn entry(&self, signature: &str) -> Option<&HistoryEntry> {
+        self.entries
+            .iter()
+            .find(|entry| entry.signature == signature)
+    }
+
+    pub fn selected_entry(&self) -> Option<&HistoryEntry> {
+        self.visible().get(self.selected).copied()
+    }
+
+    /// The visible entries under a header for each local day, newest first
+    pub fn list(&self, clock: &Clock) -> Vec<ListRow> {
+        let mut list = Vec::new();
+        let mut day = None;
+        for (i, entry) in self.visible().into_iter().enumerate() {
+            let entry_day = entry.block_time.map(|time| clock.day(time));
+            if i == 0 || entry_day != day {
+                list.push(ListRow::Day(match entry.block_time {
+                    Some(time) => clock.day_label(time),
+                    None => "Time unknown".to_string(),
+                }));
+                day = entry_day;
+            }
+            list.push(ListRow::Entry(i));
+        }
+        list
+    }
+
+    /// Index in `list` of the first line to draw so the selection stays
+    /// in view
+    pub fn scroll_offset(&self, list: &[ListRow], rows: usize) -> usize {
+        let line = list
+            .iter()
+            .position(|row| *row == ListRow::Entry(self.selected))
+            .unwrap_or(0);
+        (line + 1).saturating_sub(rows.max(1))
+    }
+}
+
+/// A line of the transaction list
+#[derive(Debug, Clone, PartialEq)]
+pub enum ListRow {
+    /// The header of a day's transactions
+    Day(String),
+    /// The visible entry at this index
+    Entry(usize),
+}
+
+/// Fetch the signatures newer than `until`, up to `SYNC_MAX_PAGES` pages,
+/// and whether that reached it
+pub fn fetch_newer(
+    rpc_client: &dyn RpcApi,
+    address: &Pubkey,
+    until: Option<Signature>,
+) -> Result<(Vec<HistoryEntry>, bool)> {
+    let mut new = Vec::new();
+    for _ in 0..SYNC_MAX_PAGES {
+        let before = new
+            .last()
+            .map(|entry: &HistoryEntry| Signature::from_str(&entry.signature))
+            .transpose()
+            .context("Invalid signature cursor")?;
+        let page = fetch_page(rpc_client, address, before, until)?;
+        let short = page.len() < PAGE_SIZE;
+        new.extend(page);
+        if short {
+            return Ok((new, true));
+        }
+    }
+    Ok((new, false))
+}
+
+pub fn fetch_page(
+    rpc_client: &dyn RpcApi,
+    address: &Pubkey,
+    before: Option<Signature>,
+    until: Option<Signature>,
+) -> Result<Vec<HistoryEntry>> {
+    fetch_entries(rpc_client, address, before, until, PAGE_SIZE)
+}
+
+/// The `limit` newest transactions of `address`
+pub fn fetch_latest(
+    rpc_client: &dyn RpcApi,
+    address: &Pubkey,
+    limit: usize,
+) -> Result<Vec<HistoryEntry>> {
+    fetch_entries(rpc_client, address, None, None, limit)
+}
+
+fn fetch_entries(
+    rpc_client: &dyn RpcApi,
+    address: &Pubkey,
+    before: Option<Signature>,
+    until: Option<Signature>,
+    limit: usize,
+) -> Result<Vec<HistoryEntry>> {
+    let page = rpc_client
+        .get_signatures_for_address_with_config(
+            address,
+            GetConfirmedSignaturesForAddress2Config {
+                before,
+                until,
+                limit: Some(limit),
+                commitment: Some(CommitmentConfig::confirmed()),
+            },
+        )
+        .context("Failed to fetch transaction history")?;
+
+    Ok(page
+        .into_iter()
+        .map(|status| {
+            let details = Signature::from_str(&status.signature)
+                .ok()
+                .and_then(|signature| fetch_details(rpc_client, &signature, address));
+            HistoryEntry {
+                signature: status.signature,
+                slot: status.slot,
+                block_time: status.block_time,
+                failed: status.err.is_some(),
+                memo: status.memo,
+                note: None,
+                reference: None,
+                details,
+            }
+        })
+        .collect())
+}
+
+/// One top-level instruction of an inspected transaction
+#[derive(Debug, Clone)]
+pub struct InstructionView {
+    pub program: Pubkey,
+    pub program_name: Option<String>,
+    /// Name and arguments from the program's Anchor IDL, if one was found
+    pub decoded: Option<DecodedInstruction>,
+    pub accounts: Vec<Pubkey>,
+    pub data_len: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct TxInspection {
+    pub signature: String,
+    pub version: &'static str,
+    /// The lookup tables it loads addresses from
+    pub lookup_tables: Vec<Pubkey>,
+    pub instructions: Vec<InstructionView>,
+    pub scroll: u16,
+    /// Index of the field selected for copying, in the order they're shown
+    pub field: usize,
+}
+
+/// Fetch a transaction and break it down per instruction, decoding with
+/// Anchor IDLs where available
+pub fn inspect(
+    rpc_client: &dyn RpcApi,
+    signature: &str,
+    idls: &mut IdlRegistry,
+) -> Result<TxInspection> {
+    let parsed = Signature::from_str(signature).context("Invalid signature")?;
+    let tx = fetch_transaction(rpc_client, &parsed).context("Failed to fetch transaction")?;
+    let transaction = tx
+        .transaction
+        .transaction
+        .decode()
+        .context("Failed to decode transaction")?;
+    let keys = account_keys(&transaction, tx.transaction.meta.as_ref());
+
+    Ok(TxInspection {
+        signature: signature.to_string(),
+        version: version(&transaction.message),
+        lookup_tables: transaction
+            .message
+            .address_table_lookups()
+            .unwrap_or_default()
+            .iter()
+            .map(|lookup| lookup.account_key)
+            .collect(),
+        instructions: instruction_views(rpc_client, &transaction, &keys, idls),
+        scroll: 0,
+        field: 0,
+    })
+}
+
+/// A SOL transfer read back from a transaction, to build it again
+#[derive(Debug, Clone, PartialEq)]
+pub struct SentTransfer {
+    pub recipient: Pubkey,
+    pub lamports: u64,
+    /// The blockhash it was signed with, which it can land until it expires
+    pub blockhash: Hash,
+}
+
+/// The first SOL transfer `wallet` made in a transaction
+pub fn sent_transfer(
+    rpc_client: &dyn RpcApi,
+    signature: &Signature,
+    wallet: &Pubkey,
+) -> Result<SentTransfer> {
+    let tx = fetch_transaction(rpc_client, signature).context("Failed to fetch transaction")?;
+    let transaction = tx
+        .transaction
+        .transaction
+        .decode()
+        .context("Failed to decode transaction")?;
+    let keys = account_keys(&transaction, tx.transaction.meta.as_ref());
+    let blockhash = *transaction.message.recent_blockhash();
+    transaction
+        .message
+        .instructions()
+        .iter()
+        .find_map(|instruction| {
+            if keys.get(usize::from(instruction.program_id_index)) != Some(&system_program::ID) {
+                return None;
+            }
+            let Ok(SystemInstruction::Transfer { lamports }) =
+                bincode::deserialize(&instruction.data)
+            else {
+                return None;
+            };
+            let account = |i: usize| {
+                instruction
+                    .accounts
+                    .get(i)
+                    .and_then(|&index| keys.get(usize::from(index)))
+            };
+            (account(0) == Some(wallet)).then_some(SentTransfer {
+                recipient: *account(1)?,
+                lamports,
+                blockhash,
+            })
+        })
+        .context("It isn't a SOL transfer from this wallet")
+}
+
+/// Refuse to send a transfer again unless that can't pay twice: the ledger
+/// records it as failed, or has no record of it and its blockhash has
+/// expired, so it never will land. The recent status cache alone isn't
+/// enough, since older transactions have left it.
+pub fn check_resend(
+    rpc_client: &dyn RpcApi,
+    signature: &Signature,
+    blockhash: &Hash,
+) -> Result<()> {
+    match ledger_status(rpc_client, signature)? {
+        Some(status) if status.err.is_none() => {
+            bail!("It landed, so sending it again would pay twice")
+        }
+        Some(_) => Ok(()),
+        None if rpc_client
+            .is_blockhash_valid(blockhash, CommitmentConfig::processed())
+            .context("Failed to check the transaction's blockhash")? =>
+        {
+            bail!("It may still land until its blockhash expires")
+        }
+        None => Ok(()),
+    }
+}
+
+/// Refuse to send a transfer again once it was already sent again as
+/// `resent_as`, unless that one failed on chain, which is the only way to be
+/// sure it can't pay. One that was never seen may still land.
+pub fn check_resent(rpc_client: &dyn RpcApi, resent_as: &Signature) -> Result<()> {
+    match ledger_status(rpc_client, resent_as)? {
+        Some(status) if status.err.is_some() => Ok(()),
+        Some(_) => bail!("It was sent again as {}, which landed", resent_as),
+        None => bail!("It was sent again as {}, which may still land", resent_as),
+    }
+}
+
+/// What the ledger says about `signature`, old transactions included
+fn ledger_status(
+    rpc_client: &dyn RpcApi,
+    signature: &Signature,
+) -> Result<Option<TransactionStatus>> {
+    Ok(rpc_client
+        .get_signature_statuses_with_history(&[*signature])
+        .context("Failed to check the transaction")?
+        .value
+        .pop()
+        .flatten())
+}
+
+pub fn version(message: &VersionedMessage) -> &'static str {
+    match message {
+        VersionedMessage::Legacy(_) => "legacy",
+        VersionedMessage::V0(_) => "v0",
+    }
+}
+
+/// The top-level instructions of `transaction`, with `keys` the full list of
+/// its account keys (lookup table addresses included)
+pub fn instruction_views(
+    rpc_client: &dyn RpcApi,
+    transaction: &VersionedTransaction,
+    keys: &[Pubkey],
+    idls: &mut IdlRegistry,
+) -> Vec<InstructionView> {
+    transaction
+        .message
+        .instructions()
+        .iter()
+        .filter_map(|instruction| {
+            let program = *keys.get(instruction.program_id_index as usize)?;
+            let decoded = idls.decode(rpc_client, &program, &instruction.data);
+            let program_name = programs::name(&program)
+                .map(str::to_string)
+                .or_else(|| idls.name(&program).map(str::to_string));
+            Some(InstructionView {
+                program,
+                program_name,
+                decoded,
+                accounts: instruction
+                    .accounts
+                    .iter()
+                    .filter_map(|&i| keys.get(i as usize).copied())
+                    .collect(),
+                data_len: instruction.data.len(),
+            })
+        })
+        .collect()
+}
+
+fn fetch_transaction(
+    rpc_client: &dyn RpcApi,
+    signature: &Signature,
+) -> Result<EncodedConfirmedTransactionWithStatusMeta> {
+    Ok(rpc_client.get_transaction_with_config(
+        signature,
+        RpcTransactionConfig {
+            encoding: Some(UiTransactionEncoding::Base64),
+            commitment: Some(CommitmentConfig::confirmed()),
+            max_supported_transaction_version: Some(0),
+        },
+    )?)
+}
+
+/// Static keys, then addresses loaded from lookup tables (writable first)
+fn account_keys(
+    transaction: &VersionedTransaction,
+    meta: Option<&UiTransactionStatusMeta>,
+) -> Vec<Pubkey> {
+    let mut keys = transaction.message.static_account_keys().to_vec();
+    if let Some(loaded) =
+        meta.and_then(|meta| Option::<&UiLoadedAddresses>::from(meta.loaded_addresses.as_ref()))
+    {
+        keys.extend(
+            loaded
+                .writable
+                .iter()
+                .chain(&loaded.readonly)
+                .filter_map(|key| Pubkey::from_str(key).ok()),
+        );
+    }
+    keys
+}
+
+/// Fetch a transaction and work out what it did to the wallet. Failures are
+/// not fatal, the entry is just shown without details.
+pub fn fetch_details(
+    rpc_client: &dyn RpcApi,
+    signature: &Signature,
+    address: &Pubkey,
+) -> Option<TxDetails> {
+    let tx = fetch_transaction(rpc_client, signature).ok()?;
+    decode_details(&tx, address)
+}
+
+fn decode_details(
+    tx: &EncodedConfirmedTransactionWithStatusMeta,
+    address: &Pubkey,
+) -> Option<TxDetails> {
+    let transaction = tx.transaction.transaction.decode()?;
+    let meta = tx.transaction.meta.as_ref()?;
+    let keys = account_keys(&transaction, Some(meta));
+
+    let deltas: Vec<i64> = meta
+        .pre_balances
+        .iter()
+        .zip(&meta.post_balances)
+        .map(|(pre, post)| *post as i64 - *pre as i64)
+        .collect();
+    let wallet_index = keys.iter().position(|key| key == address);
+    let lamports_delta = wallet_index
+        .and_then(|i| deltas.get(i).copied())
+        .unwrap_or(0);
+    let counterparty = keys
+        .iter()
+        .zip(&deltas)
+        .enumerate()
+        .filter(|(i, (_, delta))| {
+            Some(*i) != wallet_index && **delta != 0 && delta.signum() != lamports_delta.signum()
+        })
+        .max_by_key(|(_, (_, delta))| delta.unsigned_abs())
+        .map(|(_, (key, _))| *key);
+
+    let mut kind = TxKind::Transfer;
+    for instruction in transaction.message.instructions() {
+        let Some(program) = keys.get(instruction.program_id_index as usize) else {
+            continue;
+        };
+        if *program == spl_token::ID
+            || *program == TOKEN_2022_PROGRAM_ID
+            || *program == spl_associated_token_account::ID
+        {
+            kind = TxKind::Token;
+        } else if *program == stake::program::ID {
+            kind = TxKind::Stake;
+        } else if *program != system_program::ID
+            && *program != compute_budget::ID
+            && *program != MEMO_PROGRAM_ID
+        {
+            kind = TxKind::Program(*program);
+            break;
+        }
+    }
+
+    let token_deltas = token_deltas(
+        Option::<&Vec<_>>::from(meta.pre_token_balances.as_ref()).map_or(&[][..], Vec::as_slice),
+        Option::<&Vec<_>>::from(meta.post_token_balances.as_ref()).map_or(&[][..], Vec::as_slice),
+        address,
+    );
+
+    Some(TxDetails {
+        kind,
+        lamports_delta,
+        fee: meta.fee,
+        fee_payer: wallet_index == Some(0),
+        counterparty,
+        token_deltas,
+    })
+}
+
+/// Sum the change per mint over the token accounts owned by `owner`.
+/// Accounts opened or closed in the transaction only appear on one side.
+fn token_deltas(
+    pre: &[UiTransactionTokenBalance],
+    post: &[UiTransactionTokenBalance],
+    owner: &Pubkey,
+) -> Vec<TokenDelta> {
+    let owner = owner.to_string();
+    let mut by_mint: BTreeMap<String, (i128, u8)> = BTreeMap::new();
+    for (balances, sign) in [(pre, -1), (post, 1)] {
+        for balance in balances {
+            if Option::<&String>::from(balance.owner.as_ref()) != Some(&owner) {
+                continue;
+            }
+            let Ok(amount) = balance.ui_token_amount.amount.parse::<i128>() else {
+                continue;
+            };
+            let entry = by_mint
+                .entry(balance.mint.clone())
+                .or_insert((0, balance.ui_token_amount.decimals));
+            entry.0 += sign * amount;
+        }
+    }
+
+    by_mint
+        .into_iter()
+        .filter(|(_, (amount, _))| *amount != 0)
+        .filter_map(|(mint, (amount, decimals))| {
+            Some(TokenDelta {
+                mint: Pubkey::from_str(&mint).ok()?,
+                amount,
+                decimals,
+            })
+        })
+        .collect()
+}
+
+/// Signed decimal amount from base units, without trailing zeros: `+1.5`
+pub fn format_amount(amount: i128, decimals: u8) -> String {
+    let sign = if amount < 0 { "-" } else { "+" };
+    let Some(scale) = 10u128.checked_pow(u32::from(decimals)) else {
+        // Too many places to scale by; the smallest units as they are
+        return format!("{}{}", sign, amount.unsigned_abs());
+    };
+    let whole = amount.unsigned_abs() / scale;
+    let fraction = amount.unsigned_abs() % scale;
+    if fraction == 0 {
+        return format!("{}{}", sign, whole);
+    }
+    let fraction = format!("{:0width$}", fraction, width = decimals as usize);
+    format!("{}{}.{}", sign, whole, fraction.trim_end_matches('0'))
+}
+
+/// Format a Unix timestamp as `YYYY-MM-DD HH:MM` (UTC)
+pub fn format_timestamp(timestamp: i64) -> String {
+    let mut formatted = format_datetime(timestamp);
+    formatted.truncate(16);
+    formatted
+}
+
+/// Format a Unix timestamp as `YYYY-MM-DD HH:MM:SS` (UTC)
+pub fn format_datetime(timestamp: i64) -> String {
+    let days = timestamp.div_euclid(86_400);
+    let seconds = timestamp.rem_euclid(86_400);
+
+    // Civil-from-days, from Howard Hinnant's date algorithms
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        seconds / 3_600,
+        seconds % 3_600 / 60,
+        seconds % 60
+    )
+}
+
+/// Times shown relative to now, in the local time zone
+#[derive(Clone, Copy)]
+pub struct Clock {
+    /// Unix time now
+    pub now: i64,
+    /// Seconds the local time zone is ahead of UTC at a Unix time
+    pub utc_offset: fn(i64) -> i64,
+}
+
+impl Clock {
+    /// The clock at the current time, in the system time zone
+    pub fn system() -> Self {
+        Self {
+            now: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_secs() as i64),
+            utc_offset: local_utc_offset,
+        }
+    }
+
+    /// The local calendar day of `timestamp`, in days since the epoch
+    pub fn day(&self, timestamp: i64) -> i64 {
+        (timestamp + (self.utc_offset)(timestamp)).div_euclid(86_400)
+    }
+
+    /// `Today`, `Yesterday`, or the weekday and local date
+    pub fn day_label(&self, timestamp: i64) -> String {
+        let day = self.day(timestamp);
+        match self.day(self.now) - day {
+            0 => tr("Today").to_string(),
+            1 => tr("Yesterday").to_string(),
+            _ => {
+                // The epoch was a Thursday
+                let weekday =
+                    ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"][day.rem_euclid(7) as usize];
+                let mut date = format_datetime(day * 86_400);
+                date.truncate(10);
+                format!("{} {}", tr(weekday), date)
+            }
+        }
+    }
+
+    /// `YYYY-MM-DD HH:MM:SS` in the local time zone
+    pub fn datetime(&self, timestamp: i64) -> String {
+        format_datetime(timestamp + (self.utc_offset)(timestamp))
+    }
+
+    /// `HH:MM` in the local time zone
+    pub fn time_of_day(&self, timestamp: i64) -> String {
+        self.datetime(timestamp)[11..16].to_string()
+    }
+
+    /// How long before now, in the largest whole unit: `5m ago`, `3h ago`
+    pub fn ago(&self, timestamp: i64) -> String {
+        let elapsed = self.now - timestamp;
+        let (count, unit) = match elapsed {
+            ..60 => return tr("just now").to_string(),
+            60..3_600 => (elapsed / 60, "m"),
+            3_600..86_400 => (elapsed / 3_600, "h"),
+            86_400..2_592_000 => (elapsed / 86_400, "d"),
+            2_592_000..31_536_000 => (elapsed / 2_592_000, "mo"),
+            _ => (elapsed / 31_536_000, "y"),
+        };
+        tr_fmt("{}{} ago", &[&count, &unit])
+    }
+}
+
+/// The local time zone's offset from UTC at `timestamp`, daylight saving
+/// included, or 0 where it can't be read
+fn local_utc_offset(timestamp: i64) -> i64 {
+    #[cfg(unix)]
+    {
+        let time = timestamp as libc::time_t;
+        // SAFETY: tm is plain data, and both pointers are valid for the call
+        let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+        if !unsafe { libc::localtime_r(&time, &mut tm) }.is_null() {
+            return tm.tm_gmtoff as i64;
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = timestamp;
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn amounts_are_signed_and_trimmed() {
+        assert_eq!(format_amount(1_500_000_000, 9), "+1.5");
+        assert_eq!(format_amount(-25_000_000, 6), "-25");
+        assert_eq!(format_amount(-5_000, 9), "-0.000005");
+        assert_eq!(format_amount(0, 0), "+0");
+        assert_eq!(format_amount(-7, 255), "-7");
+    }
+
+    #[test]
+    fn timestamps_format_as_utc() {
+        assert_eq!(format_timestamp(0), "1970-01-01 00:00");
+        assert_eq!(format_timestamp(1_709_251_199), "2024-02-29 23:59");
+    }
+
+    #[test]
+    fn groups_by_local_day_with_relative_times() {
+        // 2024-03-01 01:00 UTC, which is still February 29th at UTC-3
+        let now = 1_709_254_800;
+        let clock = Clock {
+            now,
+            utc_offset: |_| -3 * 3_600,
+        };
+        assert_eq!(clock.day_label(now), "Today");
+        assert_eq!(clock.datetime(now), "2024-02-29 22:00:00");
+        assert_eq!(clock.time_of_day(now - 600), "21:50");
+        assert_eq!(clock.day_label(now - 86_400), "Yesterday");
+        assert_eq!(clock.day_label(now - 2 * 86_400), "Tue 2024-02-27");
+        assert_eq!(clock.ago(now + 5), "just now");
+        assert_eq!(clock.ago(now - 600), "10m ago");
+        assert_eq!(clock.ago(now - 7_200), "2h ago");
+        assert_eq!(clock.ago(now - 3 * 86_400), "3d ago");
+        assert_eq!(clock.ago(now - 400 * 86_400), "1y ago");
+
+        let entry = |signature: &str, block_time| HistoryEntry {
+            signature: signature.to_string(),
+            slot: 0,
+            block_time,
+            failed: false,
+            memo: None,
+            note: None,
+            reference: None,
+            details: None,
+        };
+        let history = History {
+            entries: vec![
+                entry("pending", None),
+                entry("tonight", Some(now - 600)),
+                // 01:00 locally, though the day before in UTC
+                entry("morning", Some(now - 75_600)),
+                entry("late", Some(now - 82_800)),
+            ],
+            selected: 3,
+            ..History::default()
+        };
+        let list = history.list(&clock);
+        assert_eq!(
+            list,
+            [
+                ListRow::Day("Time unknown".to_string()),
+                ListRow::Entry(0),
+                ListRow::Day("Today".to_string()),
+                ListRow::Entry(1),
+                ListRow::Entry(2),
+                ListRow::Day("Yesterday".to_string()),
+                ListRow::Entry(3),
+            ]
+        );
+        assert_eq!(history.scroll_offset(&list, 3), 4);
+        assert_eq!(history.scroll_offset(&list, 10), 0);
+    }
+}
@@ -0,0 +1,669 @@
+//! Local SQLite index of transaction history, keyed by wallet and cluster, so
+//! the Transactions view opens instantly and only new signatures are fetched.
+//! The payment requests made on Receive, the reference keys of transfers
+//! sent and which failed transfers were sent again are kept alongside.
+
+use crate::config::data_dir;
+use crate::history::{HistoryEntry, TokenDelta, TxDetails, TxKind};
+use crate::pay::{PaymentRequest, SavedRequest};
+use anyhow::{bail, Context, Result};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use solana_sdk::pubkey::Pubkey;
+use std::{collections::HashMap, path::Path, str::FromStr};
+
+/// Schema changes, applied in order. `PRAGMA user_version` records how many
+/// have run; append new ones, never edit old ones.
+const MIGRATIONS: &[&str] = &[
+    "
+    CREATE TABLE transactions (
+        wallet TEXT NOT NULL,
+        cluster TEXT NOT NULL,
+        signature TEXT NOT NULL,
+        slot INTEGER NOT NULL,
+        block_time INTEGER,
+        failed INTEGER NOT NULL,
+        memo TEXT,
+        kind TEXT,
+        program TEXT,
+        lamports_delta INTEGER,
+        fee INTEGER,
+        counterparty TEXT,
+        PRIMARY KEY (wallet, cluster, signature)
+    );
+    CREATE INDEX transactions_by_slot ON transactions (wallet, cluster, slot DESC);
+    CREATE TABLE sync_state (
+        wallet TEXT NOT NULL,
+        cluster TEXT NOT NULL,
+        complete INTEGER NOT NULL,
+        PRIMARY KEY (wallet, cluster)
+    );
+",
+    "
+    CREATE TABLE token_deltas (
+        wallet TEXT NOT NULL,
+        cluster TEXT NOT NULL,
+        signature TEXT NOT NULL,
+        mint TEXT NOT NULL,
+        amount TEXT NOT NULL,
+        decimals INTEGER NOT NULL,
+        PRIMARY KEY (wallet, cluster, signature, mint)
+    );
+    -- Rows cached before token changes were recorded would look SOL-only,
+    -- so fetch them again
+    DELETE FROM transactions;
+    DELETE FROM sync_state;
+",
+    "
+    ALTER TABLE transactions ADD COLUMN fee_payer INTEGER NOT NULL DEFAULT 0;
+    -- Who paid the fee wasn't recorded before, so fetch everything again
+    DELETE FROM transactions;
+    DELETE FROM sync_state;
+    DELETE FROM token_deltas;
+",
+    "
+    -- The user's notes, unlike the rest, can't be fetched again, so they live
+    -- apart from the cached transactions and survive clearing them
+    CREATE TABLE notes (
+        wallet TEXT NOT NULL,
+        cluster TEXT NOT NULL,
+        signature TEXT NOT NULL,
+        note TEXT NOT NULL,
+        PRIMARY KEY (wallet, cluster, signature)
+    );
+",
+    "
+    -- Like notes, requests can't be fetched again and survive clearing
+    CREATE TABLE payment_requests (
+        wallet TEXT NOT NULL,
+        cluster TEXT NOT NULL,
+        reference TEXT NOT NULL,
+        lamports INTEGER NOT NULL,
+        label TEXT,
+        created_at INTEGER NOT NULL,
+        signature TEXT,
+        PRIMARY KEY (wallet, cluster, reference)
+    );
+",
+    "
+    -- The reference key each transfer sent with one carried
+    CREATE TABLE transfer_references (
+        wallet TEXT NOT NULL,
+        cluster TEXT NOT NULL,
+        signature TEXT NOT NULL,
+        reference TEXT NOT NULL,
+        PRIMARY KEY (wallet, cluster, signature)
+    );
+",
+    "
+    -- The failed transfers sent again from the history, and the signature
+    -- the new one went out with, so they aren't sent a second time
+    CREATE TABLE resends (
+        wallet TEXT NOT NULL,
+        cluster TEXT NOT NULL,
+        signature TEXT NOT NULL,
+        resent_as TEXT NOT NULL,
+        PRIMARY KEY (wallet, cluster, signature)
+    );
+",
+];
+
+pub struct HistoryDb {
+    conn: Connection,
+}
+
+impl HistoryDb {
+    /// Open (or create) the index in the data directory
+    pub fn open() -> Result<Self> {
+        Self::open_at(&data_dir()?.join("history.sqlite3"))
+    }
+
+    pub fn open_at(path: &Path) -> Result<Self> {
+        let mut conn = Connection::open(path)
+            .with_context(|| format!("Failed to open history database: {}", path.display()))?;
+        migrate(&mut conn)
+            .with_context(|| format!("Failed to migrate history database: {}", path.display()))?;
+        Ok(Self { conn })
+    }
+
+    /// Cached entries, newest first, and whether they reach back to the
+    /// wallet's first transaction
+    pub fn load(&self, wallet: &Pubkey, cluster: &str) -> Result<(Vec<HistoryEntry>, bool)> {
+        let wallet = wallet.to_string();
+        let mut statement = self.conn.prepare(
+            "SELECT t.signature, slot, block_time, failed, memo, kind, program,
+                    lamports_delta, fee, counterparty, fee_payer, note, reference
+             FROM transactions t
+             LEFT JOIN notes n USING (wallet, cluster, signature)
+             LEFT JOIN transfer_references r USING (wallet, cluster, signature)
+             WHERE t.wallet = ?1 AND t.cluster = ?2
+             ORDER BY slot DESC, t.signature",
+        )?;
+        let mut entries = statement
+            .query_map(params![wallet, cluster], entry_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .context("Failed to read cached history")?;
+
+        let mut statement = self.conn.prepare(
+            "SELECT signature, mint, amount, decimals FROM token_deltas
+             WHERE wallet = ?1 AND cluster = ?2
+             ORDER BY mint",
+        )?;
+        let mut token_deltas: HashMap<String, Vec<TokenDelta>> = HashMap::new();
+        let rows = statement.query_map(params![wallet, cluster], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, u8>(3)?,
+            ))
+        })?;
+        for row in rows {
+            let (signature, mint, amount, decimals) =
+                row.context("Failed to read cached history")?;
+            if let (Ok(mint), Ok(amount)) = (Pubkey::from_str(&mint), amount.parse()) {
+                token_deltas.entry(signature).or_default().push(TokenDelta {
+                    mint,
+                    amount,
+                    decimals,
+                });
+            }
+        }
+        for entry in &mut entries {
+            if let (Some(details), Some(deltas)) = (
+                entry.details.as_mut(),
+                token_deltas.remove(&entry.signature),
+            ) {
+                details.token_deltas = deltas;
+            }
+        }
+
+        let complete = self
+            .conn
+            .query_row(
+                "SELECT complete FROM sync_state WHERE wallet = ?1 AND cluster = ?2",
+                params![wallet, cluster],
+                |row| row.get(0),
+            )
+            .optional()?
+            .unwrap_or(false);
+        Ok((entries, complete))
+    }
+
+    pub fn insert(
+        &mut self,
+        wallet: &Pubkey,
+        cluster: &str,
+        entries: &[HistoryEntry],
+    ) -> Result<()> {
+        let wallet = wallet.to_string();
+        let tx = self.conn.transaction()?;
+        {
+            let mut statement = tx.prepare(
+                "INSERT OR REPLACE INTO transactions
+                 (wallet, cluster, signature, slot, block_time, failed, memo, kind, program,
+                  lamports_delta, fee, counterparty, fee_payer)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+            )?;
+            let mut clear_deltas = tx.prepare(
+                "DELETE FROM token_deltas WHERE wallet = ?1 AND cluster = ?2 AND signature = ?3",
+            )?;
+            let mut insert_delta = tx.prepare(
+                "INSERT INTO token_deltas (wallet, cluster, signature, mint, amount, decimals)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )?;
+            for entry in entries {
+                let details = entry.details.as_ref();
+                let (kind, program) = match details.map(|d| d.kind) {
+                    Some(TxKind::Transfer) => (Some("transfer"), None),
+                    Some(TxKind::Token) => (Some("token"), None),
+                    Some(TxKind::Stake) => (Some("stake"), None),
+                    Some(TxKind::Program(program)) => (Some("program"), Some(program.to_string())),
+                    None => (None, None),
+                };
+                statement.execute(params![
+                    wallet,
+                    cluster,
+                    entry.signature,
+                    entry.slot as i64,
+                    entry.block_time,
+                    entry.failed,
+                    entry.memo,
+                    kind,
+                    program,
+                    details.map(|d| d.lamports_delta),
+                    details.map(|d| d.fee as i64),
+                    details
+                        .and_then(|d| d.counterparty)
+                        .map(|key| key.to_string()),
+                    details.is_some_and(|d| d.fee_payer),
+                ])?;
+
+                clear_deltas.execute(params![wallet, cluster, entry.signature])?;
+                for delta in details.map_or(&[][..], |d| d.token_deltas.as_slice()) {
+                    insert_delta.execute(params![
+                        wallet,
+                        cluster,
+                        entry.signature,
+                        delta.mint.to_string(),
+                        delta.amount.to_string(),
+                        delta.decimals,
+                    ])?;
+                }
+            }
+        }
+        tx.commit().context("Failed to save history")
+    }
+
+    /// Notes attached to the wallet's transactions, by signature
+    pub fn load_notes(&self, wallet: &Pubkey, cluster: &str) -> Result<HashMap<String, String>> {
+        let mut statement = self
+            .conn
+            .prepare("SELECT signature, note FROM notes WHERE wallet = ?1 AND cluster = ?2")?;
+        let notes = statement
+            .query_map(params![wallet.to_string(), cluster], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })?
+            .collect::<rusqlite::Result<_>>()
+            .context("Failed to read notes")?;
+        Ok(notes)
+    }
+
+    /// Attach a note to a transaction, or remove it if `note` is empty
+    pub fn set_note(
+        &self,
+        wallet: &Pubkey,
+        cluster: &str,
+        signature: &str,
+        note: &str,
+    ) -> Result<()> {
+        let wallet = wallet.to_string();
+        if note.is_empty() {
+            self.conn.execute(
+                "DELETE FROM notes WHERE wallet = ?1 AND cluster = ?2 AND signature = ?3",
+                params![wallet, cluster, signature],
+            )?;
+        } else {
+            self.conn.execute(
+                "INSERT OR REPLACE INTO notes (wallet, cluster, signature, note)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![wallet, cluster, signature, note],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Reference keys of the transfers the wallet sent with one, by
+    /// signature
+    pub fn load_references(
+        &self,
+        wallet: &Pubkey,
+        cluster: &str,
+    ) -> Result<HashMap<String, String>> {
+        let mut statement = self.conn.prepare(
+            "SELECT signature, reference FROM transfer_references
+             WHERE wallet = ?1 AND cluster = ?2",
+        )?;
+        let references = statement
+            .query_map(params![wallet.to_string(), cluster], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })?
+            .collect::<rusqlite::Result<_>>()
+            .context("Failed to read reference keys")?;
+        Ok(references)
+    }
+
+    pub fn save_reference(
+        &self,
+        wallet: &Pubkey,
+        cluster: &str,
+        signature: &str,
+        reference: &Pubkey,
+    ) -> Result<()> {
+        self.conn
+            .execute(
+                "INSERT OR REPLACE INTO transfer_references (wallet, cluster, signature, reference)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    wallet.to_string(),
+                    cluster,
+                    signature,
+                    reference.to_string()
+                ],
+            )
+            .context("Failed to record the reference key")?;
+        Ok(())
+    }
+
+    /// The signature the transaction `signature` was last sent again as
+    pub fn resent_as(
+        &self,
+        wallet: &Pubkey,
+        cluster: &str,
+        signature: &str,
+    ) -> Result<Option<String>> {
+        self.conn
+            .query_row(
+                "SELECT resent_as FROM resends
+                 WHERE wallet = ?1 AND cluster = ?2 AND signature = ?3",
+                params![wallet.to_string(), cluster, signature],
+                |row| row.get(0),
+            )
+            .optional()
+            .context("Failed to read resends")
+    }
+
+    pub fn save_resend(
+        &self,
+        wallet: &Pubkey,
+        cluster: &str,
+        signature: &str,
+        resent_as: &str,
+    ) -> Result<()> {
+        self.conn
+            .execute(
+                "INSERT OR REPLACE INTO resends (wallet, cluster, signature, resent_as)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![wallet.to_string(), cluster, signature, resent_as],
+            )
+            .context("Failed to record the resend")?;
+        Ok(())
+    }
+
+    /// The payment requests made to `wallet`, newest first
+    pub fn load_requests(&self, wallet: &Pubkey, cluster: &str) -> Result<Vec<SavedRequest>> {
+        let mut statement = self.conn.prepare(
+            "SELECT reference, lamports, label, created_at, signature FROM payment_requests
+             WHERE wallet = ?1 AND cluster = ?2
+             ORDER BY created_at DESC, reference",
+        )?;
+        let rows = statement.query_map(params![wallet.to_string(), cluster], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get(2)?,
+                row.get(3)?,
+                row.get(4)?,
+            ))
+        })?;
+        let mut requests = Vec::new();
+        for row in rows {
+            let (reference, lamports, label, created_at, signature) =
+                row.context("Failed to read payment requests")?;
+            if let Ok(reference) = Pubkey::from_str(&reference) {
+                requests.push(SavedRequest {
+                    request: PaymentRequest {
+                        recipient: *wallet,
+                        lamports: lamports as u64,
+                        reference,
+                        label,
+                        created_at,
+                    },
+                    signature,
+                });
+            }
+        }
+        Ok(requests)
+    }
+
+    pub fn save_request(&self, cluster: &str, request: &PaymentRequest) -> Result<()> {
+        self.conn
+            .execute(
+                "INSERT INTO payment_requests
+                 (wallet, cluster, reference, lamports, label, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    request.recipient.to_string(),
+                    cluster,
+                    request.reference.to_string(),
+                    request.lamports as i64,
+                    request.label,
+                    request.created_at,
+                ],
+            )
+            .context("Failed to save the payment request")?;
+        Ok(())
+    }
+
+    /// Record the transaction that paid the request with `reference`
+    pub fn set_request_paid(
+        &self,
+        wallet: &Pubkey,
+        cluster: &str,
+        reference: &Pubkey,
+        signature: &str,
+    ) -> Result<()> {
+        self.conn.execute(
+            "UPDATE payment_requests SET signature = ?4
+             WHERE wallet = ?1 AND cluster = ?2 AND reference = ?3",
+            params![
+                wallet.to_string(),
+                cluster,
+                reference.to_string(),
+                signature
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn set_complete(&self, wallet: &Pubkey, cluster: &str, complete: bool) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO sync_state (wallet, cluster, complete) VALUES (?1, ?2, ?3)",
+            params![wallet.to_string(), cluster, complete],
+        )?;
+        Ok(())
+    }
+
+    /// Forget everything cached for a wallet, e.g. when it can't be kept
+    /// contiguous with the chain. Notes are kept.
+    pub fn clear(&self, wallet: &Pubkey, cluster: &str) -> Result<()> {
+        let wallet = wallet.to_string();
+        self.conn.execute(
+            "DELETE FROM transactions WHERE wallet = ?1 AND cluster = ?2",
+            params![wallet, cluster],
+        )?;
+        self.conn.execute(
+            "DELETE FROM sync_state WHERE wallet = ?1 AND cluster = ?2",
+            params![wallet, cluster],
+        )?;
+        self.conn.execute(
+            "DELETE FROM token_deltas WHERE wallet = ?1 AND cluster = ?2",
+            params![wallet, cluster],
+        )?;
+        Ok(())
+    }
+}
+
+fn migrate(conn: &mut Connection) -> Result<()> {
+    let version: usize = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    if version > MIGRATIONS.len() {
+        bail!(
+            "History database has schema version {}, this build only knows {}",
+            version,
+            MIGRATIONS.len()
+        );
+    }
+    for (i, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(migration)?;
+        tx.pragma_update(None, "user_version", i + 1)?;
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+fn entry_from_row(row: &Row) -> rusqlite::Result<HistoryEntry> {
+    let kind: Option<String> = row.get(5)?;
+    let program: Option<String> = row.get(6)?;
+    let kind = match kind.as_deref() {
+        Some("transfer") => Some(TxKind::Transfer),
+        Some("token") => Some(TxKind::Token),
+        Some("stake") => Some(TxKind::Stake),
+        Some("program") => program
+            .as_deref()
+            .and_then(|program| Pubkey::from_str(program).ok())
+            .map(TxKind::Program),
+        _ => None,
+    };
+    let lamports_delta: Option<i64> = row.get(7)?;
+    let fee: Option<i64> = row.get(8)?;
+    let counterparty: Option<String> = row.get(9)?;
+    let fee_payer: bool = row.get(10)?;
+    let details = match (kind, lamports_delta, fee) {
+        (Some(kind), Some(lamports_delta), Some(fee)) => Some(TxDetails {
+            kind,
+            lamports_delta,
+            fee: fee as u64,
+            fee_payer,
+            counterparty: counterparty.and_then(|key| Pubkey::from_str(&key).ok()),
+            token_deltas: Vec::new(),
+        }),
+        _ => None,
+    };
+
+    Ok(HistoryEntry {
+        signature: row.get(0)?,
+        slot: row.get::<_, i64>(1)? as u64,
+        block_time: row.get(2)?,
+        failed: row.get(3)?,
+        memo: row.get(4)?,
+        note: row.get(11)?,
+        reference: row.get(12)?,
+        details,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_round_trip_per_wallet_and_cluster() {
+        let path =
+            std::env::temp_dir().join(format!("solace-test-{}.sqlite3", Pubkey::new_unique()));
+        let wallet = Pubkey::new_unique();
+        let program = Pubkey::new_unique();
+        let entries = vec![
+            HistoryEntry {
+                signature: "older".to_string(),
+                slot: 10,
+                block_time: Some(1_700_000_000),
+                failed: false,
+                memo: None,
+                note: None,
+                reference: None,
+                details: Some(TxDetails {
+                    kind: TxKind::Program(program),
+                    lamports_delta: -5_000,
+                    fee: 5_000,
+                    fee_payer: true,
+                    counterparty: None,
+                    token_deltas: vec![TokenDelta {
+                        mint: Pubkey::new_unique(),
+                        amount: -25_000_000,
+                        decimals: 6,
+                    }],
+                }),
+            },
+            HistoryEntry {
+                signature: "newer".to_string(),
+                slot: 20,
+                block_time: None,
+                failed: true,
+                memo: Some("[4] rent".to_string()),
+                note: None,
+                reference: None,
+                details: None,
+            },
+        ];
+
+        let mut db = HistoryDb::open_at(&path).unwrap();
+        db.insert(&wallet, "devnet", &entries).unwrap();
+        db.set_complete(&wallet, "devnet", true).unwrap();
+        db.set_note(&wallet, "devnet", "older", "March rent")
+            .unwrap();
+        let reference = Pubkey::new_unique();
+        db.save_reference(&wallet, "devnet", "newer", &reference)
+            .unwrap();
+        // Notes outlive the cached transactions they belong to
+        db.clear(&wallet, "devnet").unwrap();
+        db.insert(&wallet, "devnet", &entries).unwrap();
+        db.set_complete(&wallet, "devnet", true).unwrap();
+        drop(db);
+
+        // Reopening must not rerun migrations
+        let db = HistoryDb::open_at(&path).unwrap();
+        let (loaded, complete) = db.load(&wallet, "devnet").unwrap();
+        let (other_cluster, _) = db.load(&wallet, "mainnet").unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(complete);
+        assert!(other_cluster.is_empty());
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded[0].signature, "newer");
+        assert!(loaded[0].failed && loaded[0].details.is_none());
+        assert_eq!(loaded[0].note, None);
+        assert_eq!(loaded[1].note.as_deref(), Some("March rent"));
+        assert_eq!(loaded[0].reference, Some(reference.to_string()));
+        assert_eq!(loaded[1].reference, None);
+        let details = loaded[1].details.as_ref().unwrap();
+        assert_eq!(details.kind, TxKind::Program(program));
+        assert_eq!(details.lamports_delta, -5_000);
+        assert!(details.fee_payer);
+        assert_eq!(details.token_deltas.len(), 1);
+        assert_eq!(details.token_deltas[0].amount, -25_000_000);
+    }
+
+    #[test]
+    fn payment_requests_round_trip() {
+        let path =
+            std::env::temp_dir().join(format!("solace-test-{}.sqlite3", Pubkey::new_unique()));
+        let wallet = Pubkey::new_unique();
+        let mut older = PaymentRequest::new(wallet, 1_000, None);
+        older.created_at -= 60;
+        let newer = PaymentRequest::new(wallet, 2_000, Some("Coffee".to_string()));
+
+        let db = HistoryDb::open_at(&path).unwrap();
+        db.save_request("devnet", &older).unwrap();
+        db.save_request("devnet", &newer).unwrap();
+        db.set_request_paid(&wallet, "devnet", &older.reference, "paid")
+            .unwrap();
+        let loaded = db.load_requests(&wallet, "devnet").unwrap();
+        let other_cluster = db.load_requests(&wallet, "mainnet").unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(other_cluster.is_empty());
+        assert_eq!(
+            loaded,
+            [
+                SavedRequest {
+                    request: newer,
+                    signature: None
+                },
+                SavedRequest {
+                    request: older,
+                    signature: Some("paid".to_string())
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn resends_keep_the_latest_signature() {
+        let path =
+            std::env::temp_dir().join(format!("solace-test-{}.sqlite3", Pubkey::new_unique()));
+        let wallet = Pubkey::new_unique();
+        let db = HistoryDb::open_at(&path).unwrap();
+        db.save_resend(&wallet, "devnet", "failed", "first")
+            .unwrap();
+        db.save_resend(&wallet, "devnet", "failed", "second")
+            .unwrap();
+        let resent = db.resent_as(&wallet, "devnet", "failed").unwrap();
+        let other_cluster = db.resent_as(&wallet, "mainnet", "failed").unwrap();
+        let never = db.resent_as(&wallet, "devnet", "landed").unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(resent.as_deref(), Some("second"));
+        assert_eq!(other_cluster, None);
+        assert_eq!(never, None);
+    }
+}
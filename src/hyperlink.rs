@@ -0,0 +1,108 @@
+//! OSC 8 hyperlinks, for terminals that make them clickable
+
+use ratatui::{buffer::Buffer, layout::Rect};
+use std::env;
+
+/// Whether the terminal is known to handle OSC 8. Unknown terminals get
+/// plain text, as some print the escape sequence instead of hiding it.
+pub fn supported() -> bool {
+    let var = |name| env::var(name).unwrap_or_default();
+    let term = var("TERM");
+
+    // Multiplexers drop or mangle the sequence unless configured to pass it on
+    if env::var_os("TMUX").is_some() || term.starts_with("screen") {
+        return false;
+    }
+    if env::var_os("WT_SESSION").is_some() {
+        return true;
+    }
+    if matches!(
+        var("TERM_PROGRAM").as_str(),
+        "iTerm.app" | "WezTerm" | "vscode" | "ghostty"
+    ) {
+        return true;
+    }
+    if matches!(
+        term.as_str(),
+        "xterm-kitty" | "foot" | "alacritty" | "xterm-ghostty"
+    ) {
+        return true;
+    }
+    // GNOME Terminal, Tilix and other VTE terminals since 0.50
+    var("VTE_VERSION")
+        .parse::<u32>()
+        .is_ok_and(|version| version >= 5000)
+}
+
+/// Turn every occurrence of `text` inside `area` into a link to `url`
+pub fn apply(buffer: &mut Buffer, area: Rect, text: &str, url: &str) {
+    let area = area.intersection(buffer.area);
+    if text.is_empty() || !text.is_ascii() {
+        return;
+    }
+    for y in area.top()..area.bottom() {
+        // The row as a string, with the column each cell starts at
+        let mut row = String::new();
+        let mut columns = Vec::new();
+        for x in area.left()..area.right() {
+            columns.push((row.len(), x));
+            row.push_str(buffer[(x, y)].symbol());
+        }
+
+        let starts: Vec<u16> = row
+            .match_indices(text)
+            .filter_map(|(start, _)| {
+                let i = columns
+                    .binary_search_by_key(&start, |(offset, _)| *offset)
+                    .ok()?;
+                // Only plain one-column cells can be rewritten safely
+                let end = columns
+                    .get(i + text.len())
+                    .map_or(row.len(), |(offset, _)| *offset);
+                (end == start + text.len()).then_some(columns[i].1)
+            })
+            .collect();
+
+        for x in starts {
+            link_cells(buffer, x, y, text, url);
+        }
+    }
+}
+
+/// Ratatui measures a cell by the printable width of its symbol, escape
+/// sequence included, and then skips the cell after it. Putting the link
+/// into every other cell, two characters at a time, keeps the layout intact.
+/// An odd last character stays plain so the cell after the text isn't lost.
+fn link_cells(buffer: &mut Buffer, x: u16, y: u16, text: &str, url: &str) {
+    for (i, pair) in text.as_bytes().chunks_exact(2).enumerate() {
+        let pair = std::str::from_utf8(pair).unwrap_or_default();
+        let symbol = format!("\x1B]8;;{}\x07{}\x1B]8;;\x07", url, pair);
+        buffer[(x + 2 * i as u16, y)].set_symbol(&symbol);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn links_every_other_cell_of_each_match() {
+        let area = Rect::new(0, 0, 12, 1);
+        let mut buffer = Buffer::empty(area);
+        buffer.set_string(0, 0, "to AB123 end", ratatui::style::Style::default());
+        apply(&mut buffer, area, "AB123", "https://x/AB123");
+
+        assert_eq!(
+            buffer[(3, 0)].symbol(),
+            "\x1B]8;;https://x/AB123\x07AB\x1B]8;;\x07"
+        );
+        assert_eq!(buffer[(4, 0)].symbol(), "B");
+        assert_eq!(
+            buffer[(5, 0)].symbol(),
+            "\x1B]8;;https://x/AB123\x0712\x1B]8;;\x07"
+        );
+        // The odd last character and everything after it stay plain
+        assert_eq!(buffer[(7, 0)].symbol(), "3");
+        assert_eq!(buffer[(8, 0)].symbol(), " ");
+    }
+}
@@ -0,0 +1,211 @@
+//! Translations of the TUI's text. Strings stay in English in the code and
+//! are looked up by that text in the catalog for the language, gettext
+//! style, so anything not translated yet shows in English. The catalogs are
+//! `locales/<language>.po`, built in.
+
+use anyhow::{anyhow, bail, Context, Result};
+use std::{collections::HashMap, fmt::Display, sync::OnceLock};
+
+/// The languages there are catalogs for, besides English
+const CATALOGS: [(&str, &str); 1] = [("pt", include_str!("../locales/pt.po"))];
+
+static CATALOG: OnceLock<HashMap<String, String>> = OnceLock::new();
+
+/// The configured locale, or else the one the environment sets for
+/// `category` (`LC_MESSAGES`, `LC_NUMERIC`, …)
+pub fn locale(configured: Option<&str>, category: &str) -> Option<String> {
+    match configured {
+        Some(locale) => Some(locale.to_string()),
+        None => ["LC_ALL", category, "LANG"]
+            .iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .find(|locale| !locale.is_empty()),
+    }
+}
+
+/// The language of a locale such as `pt_BR.UTF-8`, lowercased
+pub fn language(locale: &str) -> String {
+    locale
+        .split(['_', '-', '.', '@'])
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase()
+}
+
+/// Show the TUI in the language of the configured locale, or of the
+/// environment's. English, and languages without a catalog, need nothing.
+pub fn init(configured: Option<&str>) -> Result<()> {
+    let Some(locale) = locale(configured, "LC_MESSAGES") else {
+        return Ok(());
+    };
+    let language = language(&locale);
+    if let Some(&(_, text)) = CATALOGS.iter().find(|(name, _)| *name == language) {
+        // Already translating if it ran before
+        let _ = CATALOG.set(
+            parse(text).with_context(|| format!("The {} translations are corrupt", language))?,
+        );
+    }
+    Ok(())
+}
+
+/// `text` in the TUI's language
+pub fn tr(text: &str) -> &str {
+    CATALOG
+        .get()
+        .and_then(|catalog| catalog.get(text))
+        .map_or(text, String::as_str)
+}
+
+/// `text` in the TUI's language with each `{}` replaced by the next of
+/// `args`
+pub fn tr_fmt(text: &str, args: &[&dyn Display]) -> String {
+    let mut args = args.iter();
+    let mut parts = tr(text).split("{}");
+    let mut formatted = parts.next().unwrap_or_default().to_string();
+    for part in parts {
+        if let Some(arg) = args.next() {
+            formatted.push_str(&arg.to_string());
+        }
+        formatted.push_str(part);
+    }
+    formatted
+}
+
+/// The translated entries of a `.po` catalog, by their English text
+fn parse(text: &str) -> Result<HashMap<String, String>> {
+    let mut messages = HashMap::new();
+    let (mut id, mut translation) = (String::new(), String::new());
+    // Whether the lines being read continue the msgstr rather than the msgid
+    let mut in_translation = None;
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let rest = if let Some(rest) = line.strip_prefix("msgid ") {
+            if in_translation == Some(true) {
+                add(&mut messages, &mut id, &mut translation);
+            }
+            in_translation = Some(false);
+            rest
+        } else if let Some(rest) = line.strip_prefix("msgstr ") {
+            if in_translation != Some(false) {
+                bail!("Line {} has a msgstr without a msgid", i + 1);
+            }
+            in_translation = Some(true);
+            rest
+        } else if in_translation.is_some() {
+            line
+        } else {
+            bail!("Line {} is outside of an entry", i + 1);
+        };
+        let value = unquote(rest).ok_or_else(|| anyhow!("Line {} isn't a quoted string", i + 1))?;
+        match in_translation {
+            Some(true) => translation.push_str(&value),
+            _ => id.push_str(&value),
+        }
+    }
+    match in_translation {
+        Some(false) => bail!("The last msgid has no msgstr"),
+        Some(true) => add(&mut messages, &mut id, &mut translation),
+        None => {}
+    }
+    Ok(messages)
+}
+
+/// Keep an entry, leaving out the header (the empty msgid) and those still
+/// to be translated
+fn add(messages: &mut HashMap<String, String>, id: &mut String, translation: &mut String) {
+    let (id, translation) = (std::mem::take(id), std::mem::take(translation));
+    if !id.is_empty() && !translation.is_empty() {
+        messages.insert(id, translation);
+    }
+}
+
+/// The contents of a `"…"` string, with `\"`, `\\`, `\n` and `\t` escapes
+fn unquote(quoted: &str) -> Option<String> {
+    let inner = quoted.strip_prefix('"')?.strip_suffix('"')?;
+    let mut value = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+        value.push(match chars.next()? {
+            'n' => '\n',
+            't' => '\t',
+            c @ ('"' | '\\') => c,
+            _ => return None,
+        });
+    }
+    Some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keymap::{Action, KeyContext};
+
+    #[test]
+    fn parses_entries_and_skips_the_untranslated() {
+        let catalog = parse(
+            "# header\nmsgid \"\"\nmsgstr \"Language: pt\\n\"\n\n\
+             msgid \"Quit\"\nmsgstr \"Sair\"\n\n\
+             msgid \"Balance: {}\"\nmsgstr \"\"\n\"Saldo: \"\n\"{}\"\n\n\
+             msgid \"Go back\"\nmsgstr \"\"\n",
+        )
+        .unwrap();
+        assert_eq!(catalog.len(), 2);
+        assert_eq!(catalog["Quit"], "Sair");
+        assert_eq!(catalog["Balance: {}"], "Saldo: {}");
+        assert!(parse("msgid \"Quit\"\n").is_err());
+        assert!(parse("msgstr \"Sair\"\n").is_err());
+    }
+
+    #[test]
+    fn catalogs_cover_the_keys_and_keep_placeholders() {
+        let sources = [
+            include_str!("keymap.rs"),
+            include_str!("app.rs"),
+            include_str!("app/input.rs"),
+            include_str!("app/send.rs"),
+            include_str!("app/state.rs"),
+            include_str!("app/ui.rs"),
+            include_str!("history.rs"),
+            include_str!("pay.rs"),
+            include_str!("rent.rs"),
+        ];
+        for (language, text) in CATALOGS {
+            let catalog = parse(text).unwrap();
+            for (id, translation) in &catalog {
+                assert_eq!(
+                    id.matches("{}").count(),
+                    translation.matches("{}").count(),
+                    "{}: {:?}",
+                    language,
+                    id
+                );
+                // Nothing left over from text that changed
+                let quoted = format!("{:?}", id);
+                assert!(
+                    sources.iter().any(|source| source.contains(&quoted)),
+                    "{}: {:?} isn't in the code",
+                    language,
+                    id
+                );
+            }
+            let keys = KeyContext::ALL
+                .iter()
+                .flat_map(|context| [context.title(), context.summary()])
+                .chain(
+                    Action::ALL
+                        .iter()
+                        .flat_map(|action| [action.hint(), action.description()]),
+                );
+            for text in keys {
+                assert!(catalog.contains_key(text), "{}: {:?}", language, text);
+            }
+        }
+    }
+}
@@ -0,0 +1,515 @@
+//! Anchor IDLs, loaded from files or the program's on-chain IDL account, used
+//! to decode instruction names and arguments
+
+use crate::config::{expand_tilde, IdlConfig};
+use crate::rpc::RpcApi;
+use anyhow::{bail, Context, Result};
+use serde_json::Value;
+use solana_sdk::{hash::hash, pubkey::Pubkey};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    io::Read,
+    str::FromStr,
+};
+
+/// Seed Anchor uses to derive a program's IDL account
+const IDL_SEED: &str = "anchor:idl";
+
+/// Anchor IDL account header: discriminator, authority, data length
+const IDL_HEADER_LENGTH: usize = 8 + 32 + 4;
+
+/// Nested types deeper than this are assumed to be malformed
+const MAX_TYPE_DEPTH: usize = 16;
+
+/// Elements shown for vectors and arrays before the rest are elided
+const MAX_LIST_ITEMS: usize = 16;
+
+pub struct Idl {
+    pub name: String,
+    instructions: Vec<IdlInstruction>,
+    types: HashMap<String, Value>,
+}
+
+struct IdlInstruction {
+    name: String,
+    discriminator: [u8; 8],
+    accounts: Vec<String>,
+    args: Vec<(String, Value)>,
+}
+
+/// An instruction decoded with its program's IDL
+#[derive(Debug, Clone)]
+pub struct DecodedInstruction {
+    pub name: String,
+    pub args: Vec<(String, String)>,
+    /// IDL names for the instruction's accounts, in order
+    pub account_names: Vec<String>,
+}
+
+impl Idl {
+    /// Parse an IDL in either the Anchor 0.30+ format (explicit discriminators
+    /// and `address`) or the older format. Returns the program address if the
+    /// IDL records one.
+    pub fn parse(json: &str) -> Result<(Self, Option<Pubkey>)> {
+        let value: Value = serde_json::from_str(json).context("IDL is not valid JSON")?;
+        let address = value
+            .get("address")
+            .or_else(|| value.pointer("/metadata/address"))
+            .and_then(Value::as_str)
+            .map(Pubkey::from_str)
+            .transpose()
+            .context("IDL has an invalid program address")?;
+        let name = value
+            .get("name")
+            .or_else(|| value.pointer("/metadata/name"))
+            .and_then(Value::as_str)
+            .unwrap_or("unknown")
+            .to_string();
+
+        let instructions = value
+            .get("instructions")
+            .and_then(Value::as_array)
+            .context("IDL has no instructions")?
+            .iter()
+            .map(parse_instruction)
+            .collect::<Result<_>>()?;
+        let types = value
+            .get("types")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(|ty| {
+                Some((
+                    ty.get("name")?.as_str()?.to_string(),
+                    ty.get("type")?.clone(),
+                ))
+            })
+            .collect();
+
+        Ok((
+            Self {
+                name,
+                instructions,
+                types,
+            },
+            address,
+        ))
+    }
+
+    pub fn decode(&self, data: &[u8]) -> Option<DecodedInstruction> {
+        if data.len() < 8 {
+            return None;
+        }
+        let instruction = self
+            .instructions
+            .iter()
+            .find(|instruction| instruction.discriminator[..] == data[..8])?;
+
+        let mut rest = &data[8..];
+        let mut args = Vec::with_capacity(instruction.args.len());
+        for (name, ty) in &instruction.args {
+            let value = self
+                .decode_value(ty, &mut rest, 0)
+                .unwrap_or_else(|e| format!("<{}>", e));
+            args.push((name.clone(), value));
+        }
+        Some(DecodedInstruction {
+            name: instruction.name.clone(),
+            args,
+            account_names: instruction.accounts.clone(),
+        })
+    }
+
+    /// Borsh-decode one value of the given IDL type, formatted for display
+    fn decode_value(&self, ty: &Value, data: &mut &[u8], depth: usize) -> Result<String> {
+        if depth > MAX_TYPE_DEPTH {
+            bail!("type nested too deeply");
+        }
+        if let Some(name) = ty.as_str() {
+            return decode_primitive(name, data);
+        }
+
+        if let Some(inner) = ty.get("vec") {
+            let len = u32::from_le_bytes(take(data, 4)?.try_into()?) as usize;
+            return self.decode_list(inner, len, data, depth);
+        }
+        if let Some(inner) = ty.get("option").or_else(|| ty.get("coption")) {
+            let present = if ty.get("coption").is_some() {
+                u32::from_le_bytes(take(data, 4)?.try_into()?) != 0
+            } else {
+                take(data, 1)?[0] != 0
+            };
+            return if present {
+                self.decode_value(inner, data, depth + 1)
+            } else {
+                Ok("None".to_string())
+            };
+        }
+        if let Some(array) = ty.get("array").and_then(Value::as_array) {
+            let (Some(inner), Some(len)) = (array.first(), array.get(1).and_then(Value::as_u64))
+            else {
+                bail!("unsupported array type");
+            };
+            return self.decode_list(inner, len as usize, data, depth);
+        }
+        if let Some(defined) = ty.get("defined") {
+            let name = defined
+                .as_str()
+                .or_else(|| defined.get("name").and_then(Value::as_str))
+                .context("unsupported defined type")?;
+            let definition = self
+                .types
+                .get(name)
+                .with_context(|| format!("unknown type {}", name))?;
+            return self.decode_defined(definition, data, depth + 1);
+        }
+        bail!("unsupported type {}", ty)
+    }
+
+    fn decode_list(
+        &self,
+        inner: &Value,
+        len: usize,
+        data: &mut &[u8],
+        depth: usize,
+    ) -> Result<String> {
+        // Byte vectors read better as hex
+        if inner.as_str() == Some("u8") {
+            let bytes = take(data, len)?;
+            return Ok(format!("0x{}", hex(bytes)));
+        }
+        let mut items = Vec::new();
+        for i in 0..len {
+            let item = self.decode_value(inner, data, depth + 1)?;
+            if i < MAX_LIST_ITEMS {
+                items.push(item);
+            }
+        }
+        if len > MAX_LIST_ITEMS {
+            items.push(format!("… {} more", len - MAX_LIST_ITEMS));
+        }
+        Ok(format!("[{}]", items.join(", ")))
+    }
+
+    fn decode_defined(&self, definition: &Value, data: &mut &[u8], depth: usize) -> Result<String> {
+        match definition.get("kind").and_then(Value::as_str) {
+            Some("struct") => {
+                let fields = definition.get("fields").and_then(Value::as_array);
+                self.decode_fields(fields.map_or(&[][..], Vec::as_slice), data, depth)
+            }
+            Some("enum") => {
+                let variants = definition
+                    .get("variants")
+                    .and_then(Value::as_array)
+                    .context("enum has no variants")?;
+                let index = take(data, 1)?[0] as usize;
+                let variant = variants
+                    .get(index)
+                    .with_context(|| format!("invalid enum variant {}", index))?;
+                let name = variant.get("name").and_then(Value::as_str).unwrap_or("?");
+                match variant.get("fields").and_then(Value::as_array) {
+                    Some(fields) if !fields.is_empty() => Ok(format!(
+                        "{} {}",
+                        name,
+                        self.decode_fields(fields, data, depth)?
+                    )),
+                    _ => Ok(name.to_string()),
+                }
+            }
+            // Type aliases in 0.30+ IDLs
+            Some("type") => {
+                let alias = definition.get("alias").context("alias has no type")?;
+                self.decode_value(alias, data, depth)
+            }
+            _ => bail!("unsupported type definition"),
+        }
+    }
+
+    /// Named fields (`{ name, type }`) or tuple fields (bare types)
+    fn decode_fields(&self, fields: &[Value], data: &mut &[u8], depth: usize) -> Result<String> {
+        let mut parts = Vec::with_capacity(fields.len());
+        for field in fields {
+            match (field.get("name").and_then(Value::as_str), field.get("type")) {
+                (Some(name), Some(ty)) => {
+                    parts.push(format!("{}: {}", name, self.decode_value(ty, data, depth)?))
+                }
+                _ => parts.push(self.decode_value(field, data, depth)?),
+            }
+        }
+        Ok(format!("{{ {} }}", parts.join(", ")))
+    }
+}
+
+fn parse_instruction(value: &Value) -> Result<IdlInstruction> {
+    let name = value
+        .get("name")
+        .and_then(Value::as_str)
+        .context("IDL instruction has no name")?
+        .to_string();
+
+    let discriminator = match value.get("discriminator").and_then(Value::as_array) {
+        Some(bytes) => {
+            let bytes: Vec<u8> = bytes
+                .iter()
+                .map(|byte| byte.as_u64().and_then(|b| u8::try_from(b).ok()))
+                .collect::<Option<_>>()
+                .context("IDL instruction has an invalid discriminator")?;
+            bytes
+                .try_into()
+                .map_err(|_| anyhow::anyhow!("IDL instruction discriminator must be 8 bytes"))?
+        }
+        // Older IDLs derive it from the snake_case name
+        None => {
+            let preimage = format!("global:{}", to_snake_case(&name));
+            let mut discriminator = [0u8; 8];
+            discriminator.copy_from_slice(&hash(preimage.as_bytes()).to_bytes()[..8]);
+            discriminator
+        }
+    };
+
+    let mut accounts = Vec::new();
+    flatten_accounts(value.get("accounts"), "", &mut accounts);
+    let args = value
+        .get("args")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|arg| {
+            Some((
+                arg.get("name")?.as_str()?.to_string(),
+                arg.get("type")?.clone(),
+            ))
+        })
+        .collect();
+
+    Ok(IdlInstruction {
+        name,
+        discriminator,
+        accounts,
+        args,
+    })
+}
+
+/// Account lists can nest composite groups; flatten them to `group.account`
+fn flatten_accounts(accounts: Option<&Value>, prefix: &str, out: &mut Vec<String>) {
+    for account in accounts.and_then(Value::as_array).into_iter().flatten() {
+        let name = account.get("name").and_then(Value::as_str).unwrap_or("?");
+        let name = if prefix.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}.{}", prefix, name)
+        };
+        match account.get("accounts") {
+            Some(nested) => flatten_accounts(Some(nested), &name, out),
+            None => out.push(name),
+        }
+    }
+}
+
+fn to_snake_case(name: &str) -> String {
+    let mut snake = String::with_capacity(name.len() + 4);
+    for (i, c) in name.chars().enumerate() {
+        if c.is_ascii_uppercase() {
+            if i > 0 {
+                snake.push('_');
+            }
+            snake.push(c.to_ascii_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
+
+fn take<'a>(data: &mut &'a [u8], len: usize) -> Result<&'a [u8]> {
+    if data.len() < len {
+        bail!("instruction data too short");
+    }
+    let (head, tail) = data.split_at(len);
+    *data = tail;
+    Ok(head)
+}
+
+fn decode_primitive(name: &str, data: &mut &[u8]) -> Result<String> {
+    macro_rules! int {
+        ($ty:ty) => {
+            Ok(
+                <$ty>::from_le_bytes(take(data, std::mem::size_of::<$ty>())?.try_into()?)
+                    .to_string(),
+            )
+        };
+    }
+    match name {
+        "bool" => Ok((take(data, 1)?[0] != 0).to_string()),
+        "u8" => int!(u8),
+        "i8" => int!(i8),
+        "u16" => int!(u16),
+        "i16" => int!(i16),
+        "u32" => int!(u32),
+        "i32" => int!(i32),
+        "u64" => int!(u64),
+        "i64" => int!(i64),
+        "u128" => int!(u128),
+        "i128" => int!(i128),
+        "f32" => int!(f32),
+        "f64" => int!(f64),
+        "publicKey" | "pubkey" => Ok(Pubkey::try_from(take(data, 32)?)?.to_string()),
+        "string" => {
+            let len = u32::from_le_bytes(take(data, 4)?.try_into()?) as usize;
+            Ok(format!("{:?}", String::from_utf8_lossy(take(data, len)?)))
+        }
+        "bytes" => {
+            let len = u32::from_le_bytes(take(data, 4)?.try_into()?) as usize;
+            Ok(format!("0x{}", hex(take(data, len)?)))
+        }
+        _ => bail!("unsupported type {}", name),
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// IDLs by program, from configured files and (optionally) fetched on chain
+#[derive(Default)]
+pub struct IdlRegistry {
+    idls: HashMap<Pubkey, Idl>,
+    /// Programs whose on-chain IDL has already been looked for
+    fetched: HashSet<Pubkey>,
+    fetch_onchain: bool,
+}
+
+impl IdlRegistry {
+    pub fn load(config: &IdlConfig) -> Result<Self> {
+        let mut registry = Self {
+            fetch_onchain: config.onchain,
+            ..Self::default()
+        };
+        for path in &config.files {
+            let path = expand_tilde(path);
+            let json = fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read IDL: {}", path.display()))?;
+            let (idl, address) = Idl::parse(&json)
+                .with_context(|| format!("Failed to parse IDL: {}", path.display()))?;
+            let address = address
+                .with_context(|| format!("IDL has no program address: {}", path.display()))?;
+            registry.idls.insert(address, idl);
+        }
+        Ok(registry)
+    }
+
+    pub fn len(&self) -> usize {
+        self.idls.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.idls.is_empty()
+    }
+
+    /// Decode with the program's IDL, fetching it from the chain the first
+    /// time an unknown program is seen
+    pub fn decode(
+        &mut self,
+        rpc_client: &dyn RpcApi,
+        program: &Pubkey,
+        data: &[u8],
+    ) -> Option<DecodedInstruction> {
+        if self.fetch_onchain && !self.idls.contains_key(program) && self.fetched.insert(*program) {
+            if let Ok(idl) = fetch_onchain_idl(rpc_client, program) {
+                self.idls.insert(*program, idl);
+            }
+        }
+        self.idls.get(program)?.decode(data)
+    }
+
+    pub fn name(&self, program: &Pubkey) -> Option<&str> {
+        self.idls.get(program).map(|idl| idl.name.as_str())
+    }
+}
+
+/// Address of the account Anchor's `idl init` writes a program's IDL to
+pub fn idl_address(program: &Pubkey) -> Result<Pubkey> {
+    let (base, _) = Pubkey::find_program_address(&[], program);
+    Pubkey::create_with_seed(&base, IDL_SEED, program).map_err(|e| anyhow::anyhow!("{}", e))
+}
+
+fn fetch_onchain_idl(rpc_client: &dyn RpcApi, program: &Pubkey) -> Result<Idl> {
+    let account = rpc_client
+        .get_account(&idl_address(program)?)
+        .context("Program has no on-chain IDL")?;
+    let data = account.data;
+    if data.len() < IDL_HEADER_LENGTH {
+        bail!("IDL account is too short");
+    }
+    let len = u32::from_le_bytes(data[40..44].try_into()?) as usize;
+    let compressed = data
+        .get(IDL_HEADER_LENGTH..IDL_HEADER_LENGTH + len)
+        .context("IDL account is truncated")?;
+    let mut json = String::new();
+    flate2::read::ZlibDecoder::new(compressed)
+        .read_to_string(&mut json)
+        .context("Failed to decompress IDL")?;
+    Ok(Idl::parse(&json)?.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEGACY_IDL: &str = r#"{
+        "version": "0.1.0",
+        "name": "counter",
+        "instructions": [{
+            "name": "setCount",
+            "accounts": [
+                {"name": "counter", "isMut": true, "isSigner": false},
+                {"name": "auth", "accounts": [{"name": "owner", "isMut": false, "isSigner": true}]}
+            ],
+            "args": [
+                {"name": "count", "type": "u64"},
+                {"name": "label", "type": {"option": "string"}},
+                {"name": "mode", "type": {"defined": "Mode"}}
+            ]
+        }],
+        "types": [{
+            "name": "Mode",
+            "type": {"kind": "enum", "variants": [{"name": "Fast"}, {"name": "Slow", "fields": ["u8"]}]}
+        }],
+        "metadata": {"address": "Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS"}
+    }"#;
+
+    #[test]
+    fn decodes_legacy_idl_instruction() {
+        let (idl, address) = Idl::parse(LEGACY_IDL).unwrap();
+        assert_eq!(
+            address.unwrap().to_string(),
+            "Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS"
+        );
+
+        let mut data = hash(b"global:set_count").to_bytes()[..8].to_vec();
+        data.extend_from_slice(&42u64.to_le_bytes());
+        data.push(1);
+        data.extend_from_slice(&2u32.to_le_bytes());
+        data.extend_from_slice(b"hi");
+        data.extend_from_slice(&[1, 7]);
+
+        let decoded = idl.decode(&data).unwrap();
+        assert_eq!(decoded.name, "setCount");
+        assert_eq!(decoded.account_names, ["counter", "auth.owner"]);
+        assert_eq!(
+            decoded.args,
+            [
+                ("count".to_string(), "42".to_string()),
+                ("label".to_string(), "\"hi\"".to_string()),
+                ("mode".to_string(), "Slow { 7 }".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_discriminator_is_not_decoded() {
+        let (idl, _) = Idl::parse(LEGACY_IDL).unwrap();
+        assert!(idl.decode(&[0; 16]).is_none());
+    }
+}
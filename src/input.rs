@@ -0,0 +1,158 @@
+//! Single-line text field with a movable cursor
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{
+    style::{Modifier, Style},
+    text::Span,
+};
+use std::{fmt, ops::Deref};
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TextInput {
+    value: String,
+    /// Byte offset into `value`, always on a character boundary
+    cursor: usize,
+}
+
+impl TextInput {
+    /// Replace the text, with the cursor at the end
+    pub fn set(&mut self, value: impl Into<String>) {
+        self.value = value.into();
+        self.cursor = self.value.len();
+    }
+
+    pub fn clear(&mut self) {
+        self.set(String::new());
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.value
+    }
+
+    /// Apply an editing key, returning whether it was one. Typed characters
+    /// are only inserted if `accept` allows them.
+    pub fn handle_key(&mut self, key: &KeyEvent, accept: impl Fn(char) -> bool) -> bool {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let alt = key.modifiers.contains(KeyModifiers::ALT);
+        match key.code {
+            KeyCode::Char('u') if ctrl => self.clear(),
+            KeyCode::Left if ctrl || alt => self.cursor = self.word_start(),
+            KeyCode::Right if ctrl || alt => self.cursor = self.word_end(),
+            KeyCode::Char('b') if alt => self.cursor = self.word_start(),
+            KeyCode::Char('f') if alt => self.cursor = self.word_end(),
+            KeyCode::Char(c) if !ctrl && !alt => {
+                if !accept(c) {
+                    return false;
+                }
+                self.value.insert(self.cursor, c);
+                self.cursor += c.len_utf8();
+            }
+            KeyCode::Backspace => {
+                if let Some(c) = self.value[..self.cursor].chars().next_back() {
+                    self.cursor -= c.len_utf8();
+                    self.value.remove(self.cursor);
+                }
+            }
+            KeyCode::Delete if self.cursor < self.value.len() => {
+                self.value.remove(self.cursor);
+            }
+            KeyCode::Left => {
+                if let Some(c) = self.value[..self.cursor].chars().next_back() {
+                    self.cursor -= c.len_utf8();
+                }
+            }
+            KeyCode::Right => {
+                if let Some(c) = self.value[self.cursor..].chars().next() {
+                    self.cursor += c.len_utf8();
+                }
+            }
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = self.value.len(),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Start of the word before the cursor
+    fn word_start(&self) -> usize {
+        let before = self.value[..self.cursor].trim_end_matches(is_separator);
+        before.rfind(is_separator).map_or(0, |i| {
+            i + before[i..].chars().next().map_or(1, char::len_utf8)
+        })
+    }
+
+    /// End of the word after the cursor
+    fn word_end(&self) -> usize {
+        let after = &self.value[self.cursor..];
+        let word = after.trim_start_matches(is_separator);
+        let skipped = after.len() - word.len();
+        self.cursor + skipped + word.find(is_separator).unwrap_or(word.len())
+    }
+
+    /// The text with the cursor drawn in: `cursor` after the end, or the
+    /// character under it reversed
+    pub fn spans(&self, style: Style, cursor: &'static str) -> Vec<Span<'static>> {
+        let (before, after) = self.value.split_at(self.cursor);
+        let mut rest = after.chars();
+        match rest.next() {
+            None => vec![
+                Span::styled(before.to_string(), style),
+                Span::styled(cursor, style),
+            ],
+            Some(under) => vec![
+                Span::styled(before.to_string(), style),
+                Span::styled(under.to_string(), style.add_modifier(Modifier::REVERSED)),
+                Span::styled(rest.as_str().to_string(), style),
+            ],
+        }
+    }
+}
+
+fn is_separator(c: char) -> bool {
+    c.is_whitespace() || c == '/' || c == '.'
+}
+
+impl Deref for TextInput {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.value
+    }
+}
+
+impl fmt::Display for TextInput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(input: &mut TextInput, code: KeyCode, modifiers: KeyModifiers) {
+        input.handle_key(&KeyEvent::new(code, modifiers), |_| true);
+    }
+
+    #[test]
+    fn edits_at_the_cursor() {
+        let mut input = TextInput::default();
+        input.set("send 1.5 sol");
+        press(&mut input, KeyCode::Left, KeyModifiers::CONTROL);
+        assert_eq!(input.cursor, 9);
+        press(&mut input, KeyCode::Left, KeyModifiers::CONTROL);
+        assert_eq!(input.cursor, 7);
+        press(&mut input, KeyCode::Char('2'), KeyModifiers::NONE);
+        assert_eq!(input.as_str(), "send 1.25 sol");
+
+        press(&mut input, KeyCode::Home, KeyModifiers::NONE);
+        press(&mut input, KeyCode::Delete, KeyModifiers::NONE);
+        press(&mut input, KeyCode::Right, KeyModifiers::ALT);
+        assert_eq!((input.as_str(), input.cursor), ("end 1.25 sol", 3));
+
+        // Characters the field doesn't take are left to the caller
+        assert!(!input.handle_key(&KeyEvent::from(KeyCode::Char('x')), |c| c.is_ascii_digit()));
+        press(&mut input, KeyCode::Char('u'), KeyModifiers::CONTROL);
+        assert!(input.is_empty());
+    }
+}
@@ -0,0 +1,564 @@
+//! Transactions built somewhere else, such as by a dApp, pasted in to be
+//! decoded and simulated before the wallet adds its signature
+
+use crate::config;
+use crate::history::{self, InstructionView};
+use crate::idl::IdlRegistry;
+use crate::lookup_table;
+use crate::rpc::RpcApi;
+use anyhow::{anyhow, bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::rpc_config::{
+    RpcSimulateTransactionAccountsConfig, RpcSimulateTransactionConfig,
+};
+use solana_sdk::{
+    message::VersionedMessage,
+    pubkey::Pubkey,
+    signature::{Signature, Signer},
+    transaction::VersionedTransaction,
+};
+#[allow(deprecated)]
+use solana_sdk::{system_instruction::SystemInstruction, system_program};
+use std::path::Path;
+
+/// One of the accounts a transaction uses
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccountUse {
+    pub address: Pubkey,
+    pub signer: bool,
+    pub writable: bool,
+    /// Whether a signature for it is already on the transaction
+    pub signed: bool,
+    /// The table it's loaded from, if it isn't in the message itself
+    pub lookup_table: Option<Pubkey>,
+}
+
+/// SOL a System Program transfer in a message moves
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SolTransfer {
+    pub from: Pubkey,
+    pub to: Pubkey,
+    pub lamports: u64,
+}
+
+/// What the node says the transaction would do
+#[derive(Debug, Clone)]
+pub struct Simulation {
+    /// Why it would fail, if it would
+    pub error: Option<String>,
+    pub logs: Vec<String>,
+    pub units_consumed: Option<u64>,
+    /// Lamports gained or lost by the writable accounts that change
+    pub balance_changes: Vec<(Pubkey, i128)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Inspection {
+    pub transaction: VersionedTransaction,
+    /// Static keys first, the fee payer at the front, then the addresses
+    /// loaded from lookup tables
+    pub accounts: Vec<AccountUse>,
+    pub instructions: Vec<InstructionView>,
+    pub fee: Option<u64>,
+    /// The simulation, or why it couldn't be run
+    pub simulation: Result<Simulation, String>,
+}
+
+impl Inspection {
+    pub fn fee_payer(&self) -> Option<Pubkey> {
+        self.accounts.first().map(|account| account.address)
+    }
+
+    pub fn version(&self) -> &'static str {
+        history::version(&self.transaction.message)
+    }
+
+    /// How much `address` gains (or, negative, loses) in the simulation
+    pub fn balance_change(&self, address: &Pubkey) -> Option<i128> {
+        let simulation = self.simulation.as_ref().ok()?;
+        Some(
+            simulation
+                .balance_changes
+                .iter()
+                .find(|(changed, _)| changed == address)
+                .map_or(0, |&(_, change)| change),
+        )
+    }
+}
+
+/// The System Program transfers in `message`, in order. Those using accounts
+/// from lookup tables aren't counted, as the tables aren't fetched.
+pub fn sol_transfers(message: &VersionedMessage) -> Vec<SolTransfer> {
+    let keys = message.static_account_keys();
+    message
+        .instructions()
+        .iter()
+        .filter(|instruction| {
+            keys.get(usize::from(instruction.program_id_index)) == Some(&system_program::ID)
+        })
+        .filter_map(|instruction| {
+            let Ok(SystemInstruction::Transfer { lamports }) =
+                bincode::deserialize(&instruction.data)
+            else {
+                return None;
+            };
+            let account = |i: usize| {
+                instruction
+                    .accounts
+                    .get(i)
+                    .and_then(|&index| keys.get(usize::from(index)))
+                    .copied()
+            };
+            Some(SolTransfer {
+                from: account(0)?,
+                to: account(1)?,
+                lamports,
+            })
+        })
+        .collect()
+}
+
+/// Decode a transaction pasted as base64 (what dApps and most tools hand
+/// out) or base58. Whitespace is ignored, so wrapped text can be pasted.
+pub fn decode(text: &str) -> Result<VersionedTransaction> {
+    let text: String = text.split_whitespace().collect();
+    if text.is_empty() {
+        bail!("Paste a base64 or base58 encoded transaction");
+    }
+    let mut transaction = STANDARD
+        .decode(&text)
+        .ok()
+        .and_then(|bytes| deserialize(&bytes))
+        .or_else(|| {
+            bs58::decode(&text)
+                .into_vec()
+                .ok()
+                .and_then(|bytes| deserialize(&bytes))
+        })
+        .context("Not a base64 or base58 encoded transaction")?;
+
+    // Unsigned transactions may come without room for their signatures
+    let required = usize::from(transaction.message.header().num_required_signatures);
+    if transaction.signatures.len() < required {
+        transaction
+            .signatures
+            .resize(required, Signature::default());
+    }
+    transaction
+        .sanitize()
+        .map_err(|e| anyhow!("Malformed transaction: {}", e))?;
+    Ok(transaction)
+}
+
+/// The transaction encoded in `input`, or in the file it names
+pub fn read_transaction(input: &str) -> Result<VersionedTransaction> {
+    let path = config::expand_tilde(Path::new(input));
+    if !path.is_file() {
+        return decode(input);
+    }
+    let text = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    decode(&text)
+}
+
+/// Base64, the way `decode` and most tools take it
+pub fn encode(transaction: &VersionedTransaction) -> Result<String> {
+    Ok(STANDARD.encode(bincode::serialize(transaction)?))
+}
+
+/// A whole serialized transaction, with nothing left over
+fn deserialize(bytes: &[u8]) -> Option<VersionedTransaction> {
+    let transaction: VersionedTransaction = bincode::deserialize(bytes).ok()?;
+    let length = bincode::serialized_size(&transaction).ok()?;
+    (usize::try_from(length).ok()? == bytes.len()).then_some(transaction)
+}
+
+/// Break a transaction down into its accounts and instructions, decoding
+/// with Anchor IDLs where available, and simulate it
+pub fn inspect(
+    rpc_client: &dyn RpcApi,
+    transaction: VersionedTransaction,
+    idls: &mut IdlRegistry,
+) -> Result<Inspection> {
+    let message = &transaction.message;
+    let accounts: Vec<AccountUse> = account_keys(rpc_client, &transaction)?
+        .into_iter()
+        .enumerate()
+        .map(|(i, (address, lookup_table))| AccountUse {
+            address,
+            signer: message.is_signer(i),
+            writable: message.is_maybe_writable(i, None),
+            signed: transaction
+                .signatures
+                .get(i)
+                .is_some_and(|signature| *signature != Signature::default()),
+            lookup_table,
+        })
+        .collect();
+    let keys: Vec<Pubkey> = accounts.iter().map(|account| account.address).collect();
+    let instructions = history::instruction_views(rpc_client, &transaction, &keys, idls);
+    // Only reported, like the fee of a transfer
+    let fee = rpc_client.get_fee_for_message(message).ok();
+
+    let writable: Vec<Pubkey> = accounts
+        .iter()
+        .filter(|account| account.writable)
+        .map(|account| account.address)
+        .collect();
+    let simulation = simulate(rpc_client, &transaction, &writable).map_err(|e| format!("{:#}", e));
+
+    Ok(Inspection {
+        transaction,
+        accounts,
+        instructions,
+        fee,
+        simulation,
+    })
+}
+
+/// Static keys, then the addresses loaded from lookup tables: the writable
+/// ones of every table, then the read-only ones, as the runtime orders them.
+/// Each comes with the table it's loaded from, if any.
+fn account_keys(
+    rpc_client: &dyn RpcApi,
+    transaction: &VersionedTransaction,
+) -> Result<Vec<(Pubkey, Option<Pubkey>)>> {
+    let mut keys: Vec<(Pubkey, Option<Pubkey>)> = transaction
+        .message
+        .static_account_keys()
+        .iter()
+        .map(|&key| (key, None))
+        .collect();
+    let mut writable = Vec::new();
+    let mut readonly = Vec::new();
+    for lookup in transaction
+        .message
+        .address_table_lookups()
+        .unwrap_or_default()
+    {
+        let table = lookup_table::fetch(rpc_client, &lookup.account_key)?;
+        let address = |&index: &u8| {
+            table
+                .addresses
+                .get(usize::from(index))
+                .map(|&address| (address, Some(table.address)))
+                .with_context(|| format!("Lookup table {} has no address {}", table.address, index))
+        };
+        for index in &lookup.writable_indexes {
+            writable.push(address(index)?);
+        }
+        for index in &lookup.readonly_indexes {
+            readonly.push(address(index)?);
+        }
+    }
+    keys.extend(writable);
+    keys.extend(readonly);
+    Ok(keys)
+}
+
+fn simulate(
+    rpc_client: &dyn RpcApi,
+    transaction: &VersionedTransaction,
+    writable: &[Pubkey],
+) -> Result<Simulation> {
+    let before = rpc_client
+        .get_multiple_accounts(writable)
+        .context("Failed to fetch the accounts it writes")?;
+    let result = rpc_client
+        .simulate_transaction_with_config(
+            transaction,
+            RpcSimulateTransactionConfig {
+                // It isn't signed yet, and a stale blockhash shouldn't stop
+                // it from being looked at. A durable nonce has to be kept.
+                sig_verify: false,
+                replace_recent_blockhash: !transaction.uses_durable_nonce(),
+                commitment: Some(rpc_client.commitment()),
+                accounts: Some(RpcSimulateTransactionAccountsConfig {
+                    encoding: Some(UiAccountEncoding::Base64),
+                    addresses: writable.iter().map(Pubkey::to_string).collect(),
+                }),
+                ..RpcSimulateTransactionConfig::default()
+            },
+        )
+        .context("Failed to simulate the transaction")?
+        .value;
+
+    let after = result.accounts.unwrap_or_default();
+    let balance_changes = writable
+        .iter()
+        .zip(before)
+        .zip(after)
+        .filter_map(|((&address, before), after)| {
+            let before = before.map_or(0, |account| account.lamports);
+            let after = after.map_or(0, |account| account.lamports);
+            (before != after).then(|| (address, i128::from(after) - i128::from(before)))
+        })
+        .collect();
+    Ok(Simulation {
+        error: result.err.map(|e| e.to_string()),
+        logs: result.logs.unwrap_or_default(),
+        units_consumed: result.units_consumed,
+        balance_changes,
+    })
+}
+
+/// Add the signer's signature. Every other signer must have signed already,
+/// with signatures that hold, or the transaction couldn't be sent. One
+/// signed elsewhere by everyone, such as offline with a durable nonce, is
+/// only checked.
+pub fn sign(
+    transaction: &VersionedTransaction,
+    signer: &dyn Signer,
+) -> Result<VersionedTransaction> {
+    let transaction = sign_partial(transaction, &[signer])?;
+    if let Some(key) = missing_signers(&transaction).first() {
+        bail!("The transaction also needs a signature from {}", key);
+    }
+    Ok(transaction)
+}
+
+/// Add the signatures of those of `signers` the transaction needs, leaving
+/// the others' for them to add, such as with [`merge`]. The signatures
+/// already on it must hold.
+pub fn sign_partial(
+    transaction: &VersionedTransaction,
+    signers: &[&dyn Signer],
+) -> Result<VersionedTransaction> {
+    let mut transaction = transaction.clone();
+    let message = transaction.message.serialize();
+    let mut signed = false;
+    for signer in signers {
+        let index = required_signers(&transaction)
+            .iter()
+            .position(|key| *key == signer.pubkey());
+        if let Some(index) = index {
+            transaction.signatures[index] = signer.try_sign_message(&message)?;
+            signed = true;
+        }
+    }
+    if !signed && !missing_signers(&transaction).is_empty() {
+        bail!("The wallet is not one of the signers of this transaction");
+    }
+    check_signatures(&transaction)?;
+    Ok(transaction)
+}
+
+/// Combine copies of one transaction signed by different signers, taking
+/// each signature from the first copy that has it
+pub fn merge(transactions: &[VersionedTransaction]) -> Result<VersionedTransaction> {
+    let (first, rest) = transactions
+        .split_first()
+        .context("No transactions to merge")?;
+    let mut merged = first.clone();
+    for (i, transaction) in transactions.iter().enumerate() {
+        if transaction.message != merged.message {
+            bail!(
+                "Transaction {} is a different transaction from the first",
+                i + 1
+            );
+        }
+        check_signatures(transaction).with_context(|| format!("In transaction {}", i + 1))?;
+    }
+    for transaction in rest {
+        for (merged, signature) in merged.signatures.iter_mut().zip(&transaction.signatures) {
+            if *merged == Signature::default() {
+                *merged = *signature;
+            }
+        }
+    }
+    Ok(merged)
+}
+
+/// The signers whose signatures the transaction is still missing
+pub fn missing_signers(transaction: &VersionedTransaction) -> Vec<Pubkey> {
+    required_signers(transaction)
+        .iter()
+        .zip(&transaction.signatures)
+        .filter(|(_, signature)| **signature == Signature::default())
+        .map(|(key, _)| *key)
+        .collect()
+}
+
+fn required_signers(transaction: &VersionedTransaction) -> &[Pubkey] {
+    let required = usize::from(transaction.message.header().num_required_signatures);
+    let keys = transaction.message.static_account_keys();
+    &keys[..required.min(keys.len())]
+}
+
+/// Fails on a signature that doesn't match, skipping the missing ones
+fn check_signatures(transaction: &VersionedTransaction) -> Result<()> {
+    let message = transaction.message.serialize();
+    for (key, signature) in required_signers(transaction)
+        .iter()
+        .zip(&transaction.signatures)
+    {
+        if *signature != Signature::default() && !signature.verify(key.as_ref(), &message) {
+            bail!("The signature of {} does not match the transaction", key);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::MockRpc;
+    #[allow(deprecated)]
+    use solana_sdk::system_instruction;
+    use solana_sdk::{
+        address_lookup_table::AddressLookupTableAccount,
+        hash::Hash,
+        message::{Message, VersionedMessage},
+        native_token::LAMPORTS_PER_SOL,
+        signature::Keypair,
+    };
+
+    fn transfer(from: &Pubkey, to: &Pubkey) -> VersionedTransaction {
+        let instruction = system_instruction::transfer(from, to, LAMPORTS_PER_SOL);
+        let message = Message::new_with_blockhash(&[instruction], Some(from), &Hash::new_unique());
+        VersionedTransaction {
+            signatures: Vec::new(),
+            message: VersionedMessage::Legacy(message),
+        }
+    }
+
+    #[test]
+    fn decodes_base64_and_base58() {
+        let transaction = transfer(&Pubkey::new_unique(), &Pubkey::new_unique());
+        let bytes = bincode::serialize(&transaction).unwrap();
+
+        for text in [STANDARD.encode(&bytes), bs58::encode(&bytes).into_string()] {
+            let decoded = decode(&text).unwrap();
+            assert_eq!(decoded.message, transaction.message);
+            // Room is made for the missing signature
+            assert_eq!(decoded.signatures, [Signature::default()]);
+        }
+
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert!(decode(&STANDARD.encode(&trailing)).is_err());
+        assert!(decode("not a transaction").is_err());
+    }
+
+    #[test]
+    fn signs_only_what_it_can_complete() {
+        let wallet = Keypair::new();
+        let recipient = Pubkey::new_unique();
+        let rpc = MockRpc::default();
+        rpc.set_balance(wallet.pubkey(), 2 * LAMPORTS_PER_SOL);
+
+        let transaction = decode(
+            &STANDARD.encode(bincode::serialize(&transfer(&wallet.pubkey(), &recipient)).unwrap()),
+        )
+        .unwrap();
+        let inspection = inspect(&rpc, transaction, &mut IdlRegistry::default()).unwrap();
+        assert_eq!(inspection.fee_payer(), Some(wallet.pubkey()));
+        assert_eq!(
+            inspection.accounts[0],
+            AccountUse {
+                address: wallet.pubkey(),
+                signer: true,
+                writable: true,
+                signed: false,
+                lookup_table: None,
+            }
+        );
+        assert!(inspection.simulation.is_ok());
+
+        let signed = sign(&inspection.transaction, &wallet).unwrap();
+        assert!(signed.verify_with_results().iter().all(|&valid| valid));
+        assert!(sign(&inspection.transaction, &Keypair::new()).is_err());
+        // Already signed, so any wallet can send it
+        assert!(sign(&signed, &Keypair::new()).is_ok());
+        let tampered = VersionedTransaction {
+            signatures: vec![Signature::new_unique()],
+            ..signed.clone()
+        };
+        assert!(sign(&tampered, &Keypair::new()).is_err());
+        assert_eq!(decode(&encode(&signed).unwrap()).unwrap(), signed);
+
+        // A second signer that hasn't signed yet
+        let other = Pubkey::new_unique();
+        let instructions = [
+            system_instruction::transfer(&wallet.pubkey(), &recipient, 1),
+            system_instruction::transfer(&other, &recipient, 1),
+        ];
+        let message =
+            Message::new_with_blockhash(&instructions, Some(&wallet.pubkey()), &Hash::default());
+        let transaction = VersionedTransaction {
+            signatures: vec![Signature::default(); 2],
+            message: VersionedMessage::Legacy(message),
+        };
+        let error = sign(&transaction, &wallet).unwrap_err();
+        assert!(error.to_string().contains(&other.to_string()));
+    }
+
+    #[test]
+    fn merges_signatures_from_every_signer() {
+        let wallet = Keypair::new();
+        let other = Keypair::new();
+        let recipient = Pubkey::new_unique();
+        let instructions = [
+            system_instruction::transfer(&wallet.pubkey(), &recipient, 1),
+            system_instruction::transfer(&other.pubkey(), &recipient, 1),
+        ];
+        let message =
+            Message::new_with_blockhash(&instructions, Some(&wallet.pubkey()), &Hash::default());
+        let transaction = VersionedTransaction {
+            signatures: vec![Signature::default(); 2],
+            message: VersionedMessage::Legacy(message),
+        };
+        assert!(sign_partial(&transaction, &[&Keypair::new()]).is_err());
+
+        let ours = sign_partial(&transaction, &[&wallet]).unwrap();
+        assert_eq!(missing_signers(&ours), [other.pubkey()]);
+        let theirs = sign_partial(&transaction, &[&other]).unwrap();
+        let merged = merge(&[ours.clone(), theirs]).unwrap();
+        assert!(missing_signers(&merged).is_empty());
+        assert!(merged.verify_with_results().iter().all(|&valid| valid));
+        // Signing what the wallet already signed completes it too
+        assert_eq!(sign(&ours, &other).unwrap(), merged);
+
+        let elsewhere = transfer(&wallet.pubkey(), &recipient);
+        assert!(merge(&[ours.clone(), elsewhere]).is_err());
+        let mut forged = ours.clone();
+        forged.signatures[1] = Signature::new_unique();
+        let error = merge(&[ours, forged]).unwrap_err();
+        assert!(format!("{:#}", error).contains(&other.pubkey().to_string()));
+    }
+
+    #[test]
+    fn resolves_addresses_from_lookup_tables() {
+        let rpc = MockRpc::default();
+        let wallet = Pubkey::new_unique();
+        let recipients: Vec<Pubkey> = (0..3).map(|_| Pubkey::new_unique()).collect();
+        let table = Pubkey::new_unique();
+        rpc.set_account(table, lookup_table::account(None, &recipients));
+        let tables = [AddressLookupTableAccount {
+            key: table,
+            addresses: recipients.clone(),
+        }];
+
+        let instructions: Vec<_> = recipients
+            .iter()
+            .map(|recipient| system_instruction::transfer(&wallet, recipient, 1))
+            .collect();
+        let message =
+            lookup_table::compile(&wallet, &instructions, Hash::default(), &tables).unwrap();
+        let transaction = VersionedTransaction {
+            signatures: vec![Signature::default()],
+            message,
+        };
+        let inspection = inspect(&rpc, transaction, &mut IdlRegistry::default()).unwrap();
+        assert_eq!(inspection.version(), "v0");
+        let loaded: Vec<_> = inspection
+            .accounts
+            .iter()
+            .filter(|account| account.lookup_table == Some(table))
+            .collect();
+        assert_eq!(loaded.len(), 3);
+        assert!(loaded.iter().all(|account| account.writable));
+        assert_eq!(inspection.instructions[2].accounts, [wallet, recipients[2]]);
+    }
+}
@@ -0,0 +1,157 @@
+//! Sending through Jito's block engine, which hands transactions to the
+//! validators running the Jito client as bundles. Those validators order
+//! bundles by their tips, so this lands transfers when priority fees alone
+//! don't. Only mainnet has it.
+
+use crate::config::JitoConfig;
+use crate::task::Task;
+use crate::wallet::sol_to_lamports;
+use anyhow::{bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use rand::seq::SliceRandom;
+use serde_json::{json, Value};
+#[allow(deprecated)]
+use solana_sdk::system_instruction;
+use solana_sdk::{
+    instruction::Instruction, native_token::LAMPORTS_PER_SOL, pubkey::Pubkey,
+    transaction::VersionedTransaction,
+};
+use std::{str::FromStr, time::Duration};
+
+pub const DEFAULT_BLOCK_ENGINE: &str = "https://mainnet.block-engine.jito.wtf";
+
+/// The smallest tip the block engine accepts, in lamports
+pub const MIN_TIP: u64 = 1_000;
+
+/// The accounts tips are paid to. Any one will do; picking one at random
+/// spreads the write locks.
+pub const TIP_ACCOUNTS: [&str; 8] = [
+    "96gYZGLnJYVFmbjzopPSU6QiEV5fGqZNyN9nmNhvrZU5",
+    "HFqU5x63VTqvQss8hp11i4wVV8bD44PvwucfZ2bU7gRe",
+    "Cw8CFyM9FkoMi7K7Crf6HNQqf4uEMzpKw6QNghXLvLkY",
+    "ADaUMid9yfUytqMBgopwjb2DTLSokTSzL1zt6iGPaS49",
+    "DfXygSm4jCyNCybVYYK6DwvWqjKee8pbDmJGcLWNDXjh",
+    "ADuUkR4vqLUMWXxW9gh6D6L8pMSawimctcNZ5pGwDcEt",
+    "DttWaMuVvTiduZRnguLF7jNxTgiMBZ1hyAumKUiL2KRL",
+    "3AVi9Tg9Uo68tJfuvoKvqKNWKkC5wPdSSdeBnizKZ6jT",
+];
+
+/// Where bundles go and what each one tips
+#[derive(Debug, Clone, PartialEq)]
+pub struct Jito {
+    pub block_engine: String,
+    /// Lamports
+    pub tip: u64,
+}
+
+impl Jito {
+    /// The `[jito]` settings, with `tip` in SOL replacing the configured one
+    pub fn from_config(config: &JitoConfig, tip: Option<f64>) -> Result<Self> {
+        let tip = sol_to_lamports(tip.unwrap_or(config.tip));
+        if tip < MIN_TIP {
+            bail!(
+                "The Jito tip must be at least {} SOL",
+                MIN_TIP as f64 / LAMPORTS_PER_SOL as f64
+            );
+        }
+        Ok(Self {
+            block_engine: config.block_engine.trim_end_matches('/').to_string(),
+            tip,
+        })
+    }
+
+    /// The transfer of the tip from `payer`, to add to the transaction it
+    /// pays for
+    pub fn tip_instruction(&self, payer: &Pubkey) -> Instruction {
+        let account = TIP_ACCOUNTS
+            .choose(&mut rand::thread_rng())
+            .and_then(|account| Pubkey::from_str(account).ok())
+            .unwrap_or_default();
+        system_instruction::transfer(payer, &account, self.tip)
+    }
+
+    /// Submit `transactions` as one bundle, landing all or none of them in
+    /// order, and return the bundle's ID
+    pub fn send_bundle(&self, transactions: &[VersionedTransaction]) -> Result<String> {
+        let url = format!("{}/api/v1/bundles", self.block_engine);
+        let request = bundle_request(transactions)?;
+        // Like the blocklist's, the blocking client can't run on the async
+        // runtime's threads, which sends reach from the command line
+        let response = Task::spawn(move || post(&url, &request)).wait()?;
+        if let Some(error) = response.get("error") {
+            bail!(
+                "The Jito block engine turned the bundle down: {}",
+                error["message"].as_str().unwrap_or("unknown error")
+            );
+        }
+        response["result"]
+            .as_str()
+            .map(str::to_string)
+            .context("Invalid response from the Jito block engine")
+    }
+}
+
+fn post(url: &str, request: &Value) -> Result<Value> {
+    reqwest::blocking::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()?
+        .post(url)
+        .json(request)
+        .send()
+        .and_then(|response| response.error_for_status())
+        .context("Failed to reach the Jito block engine")?
+        .json()
+        .context("Invalid response from the Jito block engine")
+}
+
+/// The `sendBundle` request, with the transactions base64 encoded
+fn bundle_request(transactions: &[VersionedTransaction]) -> Result<Value> {
+    let encoded = transactions
+        .iter()
+        .map(|transaction| Ok(STANDARD.encode(bincode::serialize(transaction)?)))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "sendBundle",
+        "params": [encoded, {"encoding": "base64"}],
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::system_instruction::SystemInstruction;
+
+    #[test]
+    fn tips_one_of_the_tip_accounts() {
+        let config = JitoConfig::default();
+        let jito = Jito::from_config(&config, Some(0.0001)).unwrap();
+        assert_eq!(jito.tip, 100_000);
+        assert!(Jito::from_config(&config, Some(0.0000001)).is_err());
+
+        let payer = Pubkey::new_unique();
+        let instruction = jito.tip_instruction(&payer);
+        assert_eq!(instruction.accounts[0].pubkey, payer);
+        assert!(TIP_ACCOUNTS.contains(&instruction.accounts[1].pubkey.to_string().as_str()));
+        assert_eq!(
+            bincode::deserialize::<SystemInstruction>(&instruction.data).unwrap(),
+            SystemInstruction::Transfer { lamports: 100_000 }
+        );
+
+        let request = bundle_request(&[VersionedTransaction::default()]).unwrap();
+        assert_eq!(request["method"], "sendBundle");
+        assert_eq!(request["params"][1]["encoding"], "base64");
+    }
+
+    #[tokio::test]
+    async fn sends_bundles_outside_the_async_runtime() {
+        let jito = Jito {
+            block_engine: "http://127.0.0.1:9".to_string(),
+            tip: MIN_TIP,
+        };
+        assert!(jito
+            .send_bundle(&[VersionedTransaction::default()])
+            .is_err());
+    }
+}
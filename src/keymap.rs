@@ -0,0 +1,1528 @@
+//! Which key does what on each screen. Input handlers look actions up here
+//! and the footer lists the same bindings, so the two can't disagree.
+
+use crate::{config::KeysConfig, history::HistoryFilter, i18n::tr};
+use anyhow::{anyhow, bail, Context, Result};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// Sets of bindings to choose from in the config
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyProfile {
+    /// Arrow keys, Enter and Esc
+    #[default]
+    Default,
+    /// The defaults plus h/j/k/l, gg/G and '/' in list views
+    Vim,
+}
+
+impl KeyProfile {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Default => "default",
+            Self::Vim => "vim",
+        }
+    }
+}
+
+/// Where a key is pressed. Each context also accepts its parent's keys.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KeyContext {
+    /// Keys that work on every screen that isn't taking text input
+    Global,
+    /// Side menu navigation, shared by the plain screens
+    Menu,
+    Home,
+    /// Home with `[[profiles]]` configured: the balances of every wallet
+    Dashboard,
+    Wallet,
+    Receive,
+    Settings,
+    Accounts,
+    /// Addresses watched without their keys
+    Watch,
+    /// Saved payment requests and whether they were paid
+    Requests,
+    /// The wallet's durable nonce accounts
+    Nonces,
+    Transactions,
+    /// Instruction view of one transaction
+    Inspection,
+    /// A single-line text prompt, e.g. history search or a note
+    Prompt,
+    /// The history export path prompt
+    ExportPrompt,
+    /// Text steps of multi-step forms such as Send and Import
+    Form,
+    /// The address entry on Lookup, with the account found
+    Lookup,
+    /// Prefix and suffix entry on the Vanity screen
+    VanityPattern,
+    /// Output directory and share counts for backup shares
+    SharesForm,
+    /// The final Y/N step of a send
+    SendConfirm,
+    /// Picking what to export
+    ExportChoice,
+    /// Screens added by plugins
+    Plugin,
+    /// Session PIN entry
+    Lock,
+    /// The '?' overlay
+    Help,
+    /// The debug and audit log overlays
+    Logs,
+}
+
+impl KeyContext {
+    fn parent(self) -> Option<Self> {
+        match self {
+            Self::Home | Self::Wallet | Self::Receive | Self::Plugin => Some(Self::Menu),
+            Self::Menu
+            | Self::Dashboard
+            | Self::Settings
+            | Self::Accounts
+            | Self::Watch
+            | Self::Requests
+            | Self::Nonces
+            | Self::Transactions
+            | Self::Inspection => Some(Self::Global),
+            Self::ExportPrompt => Some(Self::Prompt),
+            Self::Lookup | Self::VanityPattern | Self::SharesForm => Some(Self::Form),
+            Self::Global
+            | Self::Prompt
+            | Self::Form
+            | Self::SendConfirm
+            | Self::ExportChoice
+            | Self::Lock
+            | Self::Help
+            | Self::Logs => None,
+        }
+    }
+
+    /// In the order the help overlay lists them
+    pub const ALL: [Self; 25] = [
+        Self::Global,
+        Self::Menu,
+        Self::Home,
+        Self::Dashboard,
+        Self::Wallet,
+        Self::Receive,
+        Self::Settings,
+        Self::Accounts,
+        Self::Watch,
+        Self::Requests,
+        Self::Nonces,
+        Self::Transactions,
+        Self::Inspection,
+        Self::Prompt,
+        Self::ExportPrompt,
+        Self::Form,
+        Self::Lookup,
+        Self::VanityPattern,
+        Self::SharesForm,
+        Self::SendConfirm,
+        Self::ExportChoice,
+        Self::Plugin,
+        Self::Lock,
+        Self::Help,
+        Self::Logs,
+    ];
+
+    /// Name of the context's table under `[keys.bindings]`
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Global => "global",
+            Self::Menu => "menu",
+            Self::Home => "home",
+            Self::Dashboard => "dashboard",
+            Self::Wallet => "wallet",
+            Self::Receive => "receive",
+            Self::Settings => "settings",
+            Self::Accounts => "accounts",
+            Self::Watch => "watch",
+            Self::Requests => "requests",
+            Self::Nonces => "nonces",
+            Self::Transactions => "transactions",
+            Self::Inspection => "inspection",
+            Self::Prompt => "prompt",
+            Self::ExportPrompt => "export_prompt",
+            Self::Form => "form",
+            Self::Lookup => "lookup",
+            Self::VanityPattern => "vanity_pattern",
+            Self::SharesForm => "shares_form",
+            Self::SendConfirm => "send_confirm",
+            Self::ExportChoice => "export_choice",
+            Self::Plugin => "plugin",
+            Self::Lock => "lock",
+            Self::Help => "help",
+            Self::Logs => "logs",
+        }
+    }
+
+    pub fn title(self) -> &'static str {
+        tr(match self {
+            Self::Global => "Everywhere",
+            Self::Menu => "Menu",
+            Self::Home => "Home",
+            Self::Dashboard => "Dashboard",
+            Self::Wallet => "Wallet",
+            Self::Receive => "Receive",
+            Self::Settings => "Settings",
+            Self::Accounts => "Accounts",
+            Self::Watch => "Watch",
+            Self::Requests => "Payment requests",
+            Self::Nonces => "Nonce accounts",
+            Self::Transactions => "Transactions",
+            Self::Inspection => "Transaction details",
+            Self::Prompt => "Text prompts",
+            Self::ExportPrompt => "History export",
+            Self::Form => "Forms",
+            Self::Lookup => "Account lookup",
+            Self::VanityPattern => "Vanity pattern",
+            Self::SharesForm => "Backup shares",
+            Self::SendConfirm => "Send confirmation",
+            Self::ExportChoice => "Export",
+            Self::Plugin => "Plugins",
+            Self::Lock => "Lock screen",
+            Self::Help => "Help",
+            Self::Logs => "Logs",
+        })
+    }
+
+    /// What the screen is for, and where its keys apply
+    pub fn summary(self) -> &'static str {
+        tr(match self {
+            Self::Global => "On every screen except while typing",
+            Self::Menu => "Home, Wallet and Receive move through the side menu",
+            Self::Home => "Start screen",
+            Self::Dashboard => "Home with profiles: SOL and tokens of each wallet, and the totals",
+            Self::Wallet => "Address, balance and the last sent transaction",
+            Self::Receive => "Wallet address and its QR code, or a payment request's",
+            Self::Settings => "Cluster, explorer, theme, refresh and fee settings; keypair switching, import and export",
+            Self::Accounts => "Accounts derived from the seed phrase, with balances",
+            Self::Watch => "Balances and latest transactions of addresses watched without their keys",
+            Self::Requests => "Payment requests made on Receive, newest first: pending, paid or expired",
+            Self::Nonces => "The wallet's durable nonce accounts, for signing offline",
+            Self::Transactions => "History, newest first; scrolling down loads older pages",
+            Self::Inspection => "Instructions of one transaction, decoded where possible",
+            Self::Prompt => "Searches and notes",
+            Self::ExportPrompt => "Path to export the history to",
+            Self::Form => {
+                "Typing steps of Send, Sign, Verify, Sign In, Inspect, Lookup, Nonce, Vanity, Import and Export"
+            }
+            Self::Lookup => "The address or data length to look up, and the fields of the account found",
+            Self::VanityPattern => "The prefix and suffix to search for",
+            Self::SharesForm => "Where to write the shares, and how many",
+            Self::SendConfirm => "Last look at a send or nonce action before it's signed",
+            Self::ExportChoice => "The secret to export",
+            Self::Plugin => "Screens added by plugins; each may bind more keys",
+            Self::Lock => "Entering the session PIN",
+            Self::Help => "This overlay",
+            Self::Logs => "The debug and audit logs, newest at the bottom",
+        })
+    }
+
+    /// Contexts where letters are typed rather than looked up
+    fn takes_text(self) -> bool {
+        self.chain()
+            .any(|context| matches!(context, Self::Prompt | Self::Form | Self::Lock))
+    }
+
+    /// This context followed by its parents
+    fn chain(self) -> impl Iterator<Item = Self> {
+        std::iter::successors(Some(self), |context| context.parent())
+    }
+}
+
+/// Something a key does
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    Quit,
+    Back,
+    Up,
+    Down,
+    First,
+    Last,
+    PageUp,
+    PageDown,
+    /// Open the highlighted menu item
+    Select,
+    Refresh,
+    /// Run whatever the shown error came from again
+    Retry,
+    OpenAddress,
+    OpenLastTransaction,
+    /// Save the receive QR code as a PNG or SVG file
+    SaveQr,
+    /// Show the receive QR code on the whole screen, or close it
+    FullscreenQr,
+    /// Show a Solana Pay request for an amount on Receive
+    RequestPayment,
+    /// Show the selected payment request on Receive again
+    ShowRequest,
+    RefreshBlocklist,
+    ImportKey,
+    ExportKey,
+    OpenKeypair,
+    NameWallet,
+    /// Switch to the selected dashboard wallet and show it
+    OpenWallet,
+    SwitchAccount,
+    EditSetting,
+    AddWatch,
+    RemoveWatch,
+    /// Show the selected transaction's instructions
+    Details,
+    /// Open the selected transaction in the explorer
+    Open,
+    Search,
+    Filter(HistoryFilter),
+    ClearFilters,
+    Note,
+    /// Send the selected failed transfer again
+    Resend,
+    ExportHistory,
+    Close,
+    Confirm,
+    Cancel,
+    CycleFormat,
+    Help,
+    /// Show the debug log
+    Logs,
+    /// Show the signatures the wallet has made
+    AuditLog,
+    /// Show and enter SOL in lamports, or in SOL again
+    ToggleUnit,
+    ToggleCase,
+    SwitchField,
+    /// Raise a send to the recipient's rent-exempt minimum
+    BumpAmount,
+    /// Enter a send's priority fee instead of the suggested one
+    SetPriorityFee,
+    /// Add a reference key to the transfer being confirmed, or leave it off
+    ToggleReference,
+    /// Select the next copyable field of a details view
+    NextField,
+    PreviousField,
+    /// Copy the selected field to the clipboard
+    Copy,
+    ExportPrivateKey,
+    ExportSeedPhrase,
+    ExportPaperWallet,
+    ExportShares,
+    CreateNonce,
+    AdvanceNonce,
+    WithdrawNonce,
+    FundNonce,
+}
+
+impl Action {
+    pub const ALL: [Self; 64] = [
+        Self::Quit,
+        Self::Back,
+        Self::Up,
+        Self::Down,
+        Self::First,
+        Self::Last,
+        Self::PageUp,
+        Self::PageDown,
+        Self::Select,
+        Self::Refresh,
+        Self::Retry,
+        Self::OpenAddress,
+        Self::OpenLastTransaction,
+        Self::SaveQr,
+        Self::FullscreenQr,
+        Self::RequestPayment,
+        Self::ShowRequest,
+        Self::RefreshBlocklist,
+        Self::ImportKey,
+        Self::ExportKey,
+        Self::OpenKeypair,
+        Self::NameWallet,
+        Self::OpenWallet,
+        Self::SwitchAccount,
+        Self::EditSetting,
+        Self::AddWatch,
+        Self::RemoveWatch,
+        Self::Details,
+        Self::Open,
+        Self::Search,
+        Self::Filter(HistoryFilter::Incoming),
+        Self::Filter(HistoryFilter::Outgoing),
+        Self::Filter(HistoryFilter::Failed),
+        Self::Filter(HistoryFilter::Token),
+        Self::Filter(HistoryFilter::Staking),
+        Self::Filter(HistoryFilter::OtherProgram),
+        Self::ClearFilters,
+        Self::Note,
+        Self::Resend,
+        Self::ExportHistory,
+        Self::Close,
+        Self::Confirm,
+        Self::Cancel,
+        Self::CycleFormat,
+        Self::Help,
+        Self::Logs,
+        Self::AuditLog,
+        Self::ToggleUnit,
+        Self::ToggleCase,
+        Self::SwitchField,
+        Self::BumpAmount,
+        Self::SetPriorityFee,
+        Self::ToggleReference,
+        Self::NextField,
+        Self::PreviousField,
+        Self::Copy,
+        Self::ExportPrivateKey,
+        Self::ExportSeedPhrase,
+        Self::ExportPaperWallet,
+        Self::ExportShares,
+        Self::CreateNonce,
+        Self::AdvanceNonce,
+        Self::WithdrawNonce,
+        Self::FundNonce,
+    ];
+
+    /// Name used in the config
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Quit => "quit",
+            Self::Back => "back",
+            Self::Up => "up",
+            Self::Down => "down",
+            Self::First => "first",
+            Self::Last => "last",
+            Self::PageUp => "page_up",
+            Self::PageDown => "page_down",
+            Self::Select => "select",
+            Self::Refresh => "refresh",
+            Self::Retry => "retry",
+            Self::OpenAddress => "open_address",
+            Self::OpenLastTransaction => "open_last_transaction",
+            Self::SaveQr => "save_qr",
+            Self::FullscreenQr => "fullscreen_qr",
+            Self::RequestPayment => "request_payment",
+            Self::ShowRequest => "show_request",
+            Self::RefreshBlocklist => "refresh_blocklist",
+            Self::ImportKey => "import_key",
+            Self::ExportKey => "export_key",
+            Self::OpenKeypair => "open_keypair",
+            Self::NameWallet => "name_wallet",
+            Self::OpenWallet => "open_wallet",
+            Self::SwitchAccount => "switch_account",
+            Self::EditSetting => "edit_setting",
+            Self::AddWatch => "add_watch",
+            Self::RemoveWatch => "remove_watch",
+            Self::Details => "details",
+            Self::Open => "open",
+            Self::Search => "search",
+            Self::Filter(HistoryFilter::Incoming) => "filter_incoming",
+            Self::Filter(HistoryFilter::Outgoing) => "filter_outgoing",
+            Self::Filter(HistoryFilter::Failed) => "filter_failed",
+            Self::Filter(HistoryFilter::Token) => "filter_token",
+            Self::Filter(HistoryFilter::Staking) => "filter_staking",
+            Self::Filter(HistoryFilter::OtherProgram) => "filter_other_program",
+            Self::ClearFilters => "clear_filters",
+            Self::Note => "note",
+            Self::Resend => "resend",
+            Self::ExportHistory => "export_history",
+            Self::Close => "close",
+            Self::Confirm => "confirm",
+            Self::Cancel => "cancel",
+            Self::CycleFormat => "cycle_format",
+            Self::Help => "help",
+            Self::Logs => "logs",
+            Self::AuditLog => "audit_log",
+            Self::ToggleUnit => "toggle_unit",
+            Self::ToggleCase => "toggle_case",
+            Self::SwitchField => "switch_field",
+            Self::BumpAmount => "bump_amount",
+            Self::SetPriorityFee => "set_priority_fee",
+            Self::ToggleReference => "toggle_reference",
+            Self::NextField => "next_field",
+            Self::PreviousField => "previous_field",
+            Self::Copy => "copy",
+            Self::ExportPrivateKey => "export_private_key",
+            Self::ExportSeedPhrase => "export_seed_phrase",
+            Self::ExportPaperWallet => "export_paper_wallet",
+            Self::ExportShares => "export_shares",
+            Self::CreateNonce => "create_nonce",
+            Self::AdvanceNonce => "advance_nonce",
+            Self::WithdrawNonce => "withdraw_nonce",
+            Self::FundNonce => "fund_nonce",
+        }
+    }
+
+    /// Short label for the footer
+    pub fn hint(self) -> &'static str {
+        tr(match self {
+            Self::Quit => "quit",
+            Self::Back => "back",
+            Self::Up => "up",
+            Self::Down => "down",
+            Self::First => "top",
+            Self::Last => "bottom",
+            Self::PageUp | Self::PageDown => "page",
+            Self::Select => "select",
+            Self::Refresh => "refresh",
+            Self::Retry => "retry",
+            Self::OpenAddress => "open address",
+            Self::OpenLastTransaction => "open last tx",
+            Self::SaveQr => "save QR",
+            Self::FullscreenQr => "full screen",
+            Self::RequestPayment => "request payment",
+            Self::ShowRequest => "show QR",
+            Self::RefreshBlocklist => "update blocklist",
+            Self::ImportKey => "import key",
+            Self::ExportKey => "export key",
+            Self::OpenKeypair => "open keypair",
+            Self::NameWallet => "name wallet",
+            Self::OpenWallet => "open wallet",
+            Self::SwitchAccount => "switch",
+            Self::EditSetting => "change",
+            Self::AddWatch => "watch address",
+            Self::RemoveWatch => "stop watching",
+            Self::Details => "details",
+            Self::Open => "open",
+            Self::Search => "search",
+            Self::Filter(_) => "filter",
+            Self::ClearFilters => "clear filters",
+            Self::Note => "note",
+            Self::Resend => "send again",
+            Self::ExportHistory => "export",
+            Self::Close => "close",
+            Self::Confirm => "confirm",
+            Self::Cancel => "cancel",
+            Self::CycleFormat => "format",
+            Self::Help => "help",
+            Self::Logs => "log",
+            Self::AuditLog => "audit log",
+            Self::ToggleUnit => "SOL/lamports",
+            Self::ToggleCase => "case",
+            Self::SwitchField => "next field",
+            Self::BumpAmount => "bump to minimum",
+            Self::SetPriorityFee => "priority fee",
+            Self::ToggleReference => "reference",
+            Self::NextField | Self::PreviousField => "field",
+            Self::Copy => "copy",
+            Self::ExportPrivateKey => "private key",
+            Self::ExportSeedPhrase => "seed phrase",
+            Self::ExportPaperWallet => "paper wallet",
+            Self::ExportShares => "backup shares",
+            Self::CreateNonce => "create",
+            Self::AdvanceNonce => "advance",
+            Self::WithdrawNonce => "withdraw",
+            Self::FundNonce => "fund",
+        })
+    }
+
+    /// Full sentence for the help overlay
+    pub fn description(self) -> &'static str {
+        tr(match self {
+            Self::Quit => "Quit",
+            Self::Back => "Go back",
+            Self::Up => "Move up",
+            Self::Down => "Move down",
+            Self::First => "Jump to the top",
+            Self::Last => "Jump to the bottom",
+            Self::PageUp => "Scroll up a page",
+            Self::PageDown => "Scroll down a page",
+            Self::Select => "Open the highlighted screen",
+            Self::Refresh => "Fetch again from the RPC",
+            Self::Retry => "Run what failed again",
+            Self::OpenAddress => "Open the wallet address in the block explorer",
+            Self::OpenLastTransaction => "Open the last sent transaction in the block explorer",
+            Self::SaveQr => "Save the QR code as a PNG, or SVG for a .svg path",
+            Self::FullscreenQr => "Show the QR code on the whole screen, or close it",
+            Self::RequestPayment => {
+                "Ask for an amount with a Solana Pay QR code and wait for the payment"
+            }
+            Self::ShowRequest => "Show the selected request's QR code on Receive again",
+            Self::RefreshBlocklist => "Fetch the remote blocklist again",
+            Self::ImportKey => "Import a base58 private key",
+            Self::ExportKey => "Export the private key or seed phrase",
+            Self::OpenKeypair => "Load another keypair file and switch the wallet to it",
+            Self::NameWallet => "Give the wallet a name to show instead of its address",
+            Self::OpenWallet => "Switch to the selected wallet and show it",
+            Self::SwitchAccount => "Sign with the selected account",
+            Self::EditSetting => "Change the selected setting and save it to the config file",
+            Self::AddWatch => "Watch another address, saving it to the config file",
+            Self::RemoveWatch => "Stop watching the selected address",
+            Self::Details => "Show the selected transaction's instructions",
+            Self::Open => "Open the transaction in the block explorer",
+            Self::Search => "Search the list",
+            Self::Filter(HistoryFilter::Incoming) => "Toggle the incoming SOL filter",
+            Self::Filter(HistoryFilter::Outgoing) => "Toggle the outgoing SOL filter",
+            Self::Filter(HistoryFilter::Failed) => "Toggle the failed transactions filter",
+            Self::Filter(HistoryFilter::Token) => "Toggle the token transfers filter",
+            Self::Filter(HistoryFilter::Staking) => "Toggle the staking filter",
+            Self::Filter(HistoryFilter::OtherProgram) => "Toggle the other programs filter",
+            Self::ClearFilters => "Clear all filters",
+            Self::Note => "Add or edit the transaction's note",
+            Self::Resend => {
+                "Send a failed transfer again, with a new blockhash and the current fees"
+            }
+            Self::ExportHistory => "Export the history as CSV, JSON or for tax software",
+            Self::Close => "Close",
+            Self::Confirm => "Confirm",
+            Self::Cancel => "Cancel",
+            Self::CycleFormat => "Switch the export format",
+            Self::Help => "Show all keys",
+            Self::Logs => "Show the debug log",
+            Self::AuditLog => "Show the audit log of signatures",
+            Self::ToggleUnit => "Show and enter SOL in lamports, or in SOL again",
+            Self::ToggleCase => "Toggle case-insensitive matching",
+            Self::SwitchField => "Switch between the fields",
+            Self::BumpAmount => "Raise the amount to the recipient's rent-exempt minimum",
+            Self::SetPriorityFee => "Enter the priority fee instead of the suggested one",
+            Self::ToggleReference => {
+                "Add a new reference key to the transfer, for the recipient to match it by, or leave it off"
+            }
+            Self::NextField => "Select the next field to copy",
+            Self::PreviousField => "Select the previous field to copy",
+            Self::Copy => "Copy the selected field to the clipboard",
+            Self::ExportPrivateKey => "Export the private key (base58)",
+            Self::ExportSeedPhrase => "Export the seed phrase",
+            Self::ExportPaperWallet => "Save a printable paper wallet",
+            Self::ExportShares => "Split the private key into M-of-N backup shares",
+            Self::CreateNonce => "Create a nonce account with the wallet as its authority",
+            Self::AdvanceNonce => "Store a new nonce in the selected account",
+            Self::WithdrawNonce => "Withdraw SOL from the selected account to the wallet",
+            Self::FundNonce => "Send SOL to the selected account",
+        })
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|action| action.name() == name)
+    }
+}
+
+/// A key with the modifiers that matter (Ctrl and Alt; Shift is already in
+/// the character)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Key {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl Key {
+    const fn plain(code: KeyCode) -> Self {
+        Self {
+            code,
+            modifiers: KeyModifiers::NONE,
+        }
+    }
+
+    pub fn from_event(event: &KeyEvent) -> Self {
+        Self {
+            code: event.code,
+            modifiers: event
+                .modifiers
+                .intersection(KeyModifiers::CONTROL | KeyModifiers::ALT),
+        }
+    }
+
+    /// `q`, `Q`, `enter`, `ctrl+c`, `f5`, …
+    fn parse(text: &str) -> Result<Self> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = text;
+        loop {
+            let lower = rest.to_ascii_lowercase();
+            if let Some(stripped) = lower.strip_prefix("ctrl+").filter(|s| !s.is_empty()) {
+                modifiers |= KeyModifiers::CONTROL;
+                rest = &rest[rest.len() - stripped.len()..];
+            } else if let Some(stripped) = lower.strip_prefix("alt+").filter(|s| !s.is_empty()) {
+                modifiers |= KeyModifiers::ALT;
+                rest = &rest[rest.len() - stripped.len()..];
+            } else {
+                break;
+            }
+        }
+
+        let mut chars = rest.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match rest.to_ascii_lowercase().as_str() {
+                "space" => KeyCode::Char(' '),
+                "enter" => KeyCode::Enter,
+                "esc" => KeyCode::Esc,
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "backspace" => KeyCode::Backspace,
+                "delete" => KeyCode::Delete,
+                "insert" => KeyCode::Insert,
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                name => match name.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                    Some(n @ 1..=24) => KeyCode::F(n),
+                    _ => bail!("Unknown key `{}`", text),
+                },
+            },
+        };
+        Ok(Self { code, modifiers })
+    }
+}
+
+impl std::fmt::Display for Key {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "Ctrl+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "Alt+")?;
+        }
+        write!(f, "{}", key_label(self.code))
+    }
+}
+
+/// A key, or two pressed one after the other like `gg`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Keys {
+    pub first: Key,
+    pub second: Option<Key>,
+}
+
+impl Keys {
+    /// One key, or two separated by a space (`g g`)
+    fn parse(text: &str) -> Result<Self> {
+        let mut parts = text.split(' ').filter(|part| !part.is_empty());
+        let first = parts.next().context("Empty key binding")?;
+        let keys = Self {
+            first: Key::parse(first)?,
+            second: parts.next().map(Key::parse).transpose()?,
+        };
+        if parts.next().is_some() {
+            bail!("Key binding `{}` has more than two keys", text);
+        }
+        Ok(keys)
+    }
+}
+
+impl std::fmt::Display for Keys {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.first)?;
+        if let Some(second) = self.second {
+            write!(f, "{}", second)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Binding {
+    pub context: KeyContext,
+    pub keys: Keys,
+    pub action: Action,
+}
+
+const fn bind(context: KeyContext, key: KeyCode, action: Action) -> Binding {
+    Binding {
+        context,
+        keys: Keys {
+            first: Key::plain(key),
+            second: None,
+        },
+        action,
+    }
+}
+
+const fn bind_ctrl(context: KeyContext, key: char, action: Action) -> Binding {
+    Binding {
+        context,
+        keys: Keys {
+            first: Key {
+                code: KeyCode::Char(key),
+                modifiers: KeyModifiers::CONTROL,
+            },
+            second: None,
+        },
+        action,
+    }
+}
+
+const fn bind_pair(context: KeyContext, first: char, second: char, action: Action) -> Binding {
+    Binding {
+        context,
+        keys: Keys {
+            first: Key::plain(KeyCode::Char(first)),
+            second: Some(Key::plain(KeyCode::Char(second))),
+        },
+        action,
+    }
+}
+
+/// What a key press amounts to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Lookup {
+    Action(Action),
+    /// The first key of a pair; wait for the next one
+    Pending,
+    Unbound,
+}
+
+/// Default bindings, in the order the footer lists them
+const DEFAULT_BINDINGS: &[Binding] = &[
+    bind(KeyContext::Global, KeyCode::PageUp, Action::PageUp),
+    bind(KeyContext::Global, KeyCode::PageDown, Action::PageDown),
+    bind_ctrl(KeyContext::Global, 'r', Action::Retry),
+    bind(KeyContext::Global, KeyCode::Char('?'), Action::Help),
+    bind(KeyContext::Global, KeyCode::Char('L'), Action::Logs),
+    bind(KeyContext::Global, KeyCode::Char('A'), Action::AuditLog),
+    bind(KeyContext::Global, KeyCode::Char('U'), Action::ToggleUnit),
+    bind(KeyContext::Global, KeyCode::Char('q'), Action::Quit),
+    bind(KeyContext::Menu, KeyCode::Up, Action::Up),
+    bind(KeyContext::Menu, KeyCode::Down, Action::Down),
+    bind(KeyContext::Menu, KeyCode::Enter, Action::Select),
+    bind(KeyContext::Wallet, KeyCode::Char('r'), Action::Refresh),
+    bind(KeyContext::Wallet, KeyCode::Esc, Action::Cancel),
+    bind(KeyContext::Wallet, KeyCode::Char('o'), Action::OpenAddress),
+    bind(
+        KeyContext::Wallet,
+        KeyCode::Char('O'),
+        Action::OpenLastTransaction,
+    ),
+    bind(KeyContext::Receive, KeyCode::Char('o'), Action::OpenAddress),
+    bind(KeyContext::Receive, KeyCode::Char('s'), Action::SaveQr),
+    bind(
+        KeyContext::Receive,
+        KeyCode::Char('f'),
+        Action::FullscreenQr,
+    ),
+    bind(
+        KeyContext::Receive,
+        KeyCode::Char('p'),
+        Action::RequestPayment,
+    ),
+    bind(KeyContext::Receive, KeyCode::Esc, Action::Back),
+    bind(KeyContext::Requests, KeyCode::Up, Action::Up),
+    bind(KeyContext::Requests, KeyCode::Down, Action::Down),
+    bind(KeyContext::Requests, KeyCode::Home, Action::First),
+    bind(KeyContext::Requests, KeyCode::End, Action::Last),
+    bind(KeyContext::Requests, KeyCode::Enter, Action::ShowRequest),
+    bind(KeyContext::Requests, KeyCode::Char('o'), Action::Open),
+    bind(KeyContext::Requests, KeyCode::Char('r'), Action::Refresh),
+    bind(KeyContext::Requests, KeyCode::Esc, Action::Back),
+    bind(KeyContext::Plugin, KeyCode::Char('r'), Action::Refresh),
+    bind(KeyContext::Plugin, KeyCode::Esc, Action::Back),
+    bind(
+        KeyContext::Settings,
+        KeyCode::Char('u'),
+        Action::RefreshBlocklist,
+    ),
+    bind(KeyContext::Settings, KeyCode::Char('i'), Action::ImportKey),
+    bind(KeyContext::Settings, KeyCode::Char('x'), Action::ExportKey),
+    bind(
+        KeyContext::Settings,
+        KeyCode::Char('w'),
+        Action::OpenKeypair,
+    ),
+    bind(KeyContext::Settings, KeyCode::Char('n'), Action::NameWallet),
+    bind(KeyContext::Settings, KeyCode::Up, Action::Up),
+    bind(KeyContext::Settings, KeyCode::Down, Action::Down),
+    bind(KeyContext::Settings, KeyCode::Home, Action::First),
+    bind(KeyContext::Settings, KeyCode::End, Action::Last),
+    bind(KeyContext::Settings, KeyCode::Enter, Action::EditSetting),
+    bind(KeyContext::Settings, KeyCode::Esc, Action::Back),
+    bind(KeyContext::Dashboard, KeyCode::Up, Action::Up),
+    bind(KeyContext::Dashboard, KeyCode::Down, Action::Down),
+    bind(KeyContext::Dashboard, KeyCode::Home, Action::First),
+    bind(KeyContext::Dashboard, KeyCode::End, Action::Last),
+    bind(KeyContext::Dashboard, KeyCode::Enter, Action::OpenWallet),
+    bind(KeyContext::Dashboard, KeyCode::Char('r'), Action::Refresh),
+    bind(KeyContext::Dashboard, KeyCode::Esc, Action::Back),
+    bind(KeyContext::Accounts, KeyCode::Up, Action::Up),
+    bind(KeyContext::Accounts, KeyCode::Down, Action::Down),
+    bind(KeyContext::Accounts, KeyCode::Home, Action::First),
+    bind(KeyContext::Accounts, KeyCode::End, Action::Last),
+    bind(KeyContext::Accounts, KeyCode::Char('/'), Action::Search),
+    bind(KeyContext::Accounts, KeyCode::Enter, Action::SwitchAccount),
+    bind(KeyContext::Accounts, KeyCode::Char('r'), Action::Refresh),
+    bind(KeyContext::Accounts, KeyCode::Esc, Action::Back),
+    bind(KeyContext::Watch, KeyCode::Up, Action::Up),
+    bind(KeyContext::Watch, KeyCode::Down, Action::Down),
+    bind(KeyContext::Watch, KeyCode::Home, Action::First),
+    bind(KeyContext::Watch, KeyCode::End, Action::Last),
+    bind(KeyContext::Watch, KeyCode::Char('a'), Action::AddWatch),
+    bind(KeyContext::Watch, KeyCode::Char('d'), Action::RemoveWatch),
+    bind(KeyContext::Watch, KeyCode::Char('o'), Action::OpenAddress),
+    bind(KeyContext::Watch, KeyCode::Char('r'), Action::Refresh),
+    bind(KeyContext::Watch, KeyCode::Esc, Action::Back),
+    bind(KeyContext::Nonces, KeyCode::Up, Action::Up),
+    bind(KeyContext::Nonces, KeyCode::Down, Action::Down),
+    bind(KeyContext::Nonces, KeyCode::Home, Action::First),
+    bind(KeyContext::Nonces, KeyCode::End, Action::Last),
+    bind(KeyContext::Nonces, KeyCode::Char('c'), Action::CreateNonce),
+    bind(KeyContext::Nonces, KeyCode::Char('a'), Action::AdvanceNonce),
+    bind(
+        KeyContext::Nonces,
+        KeyCode::Char('w'),
+        Action::WithdrawNonce,
+    ),
+    bind(KeyContext::Nonces, KeyCode::Char('f'), Action::FundNonce),
+    bind(KeyContext::Nonces, KeyCode::Char('r'), Action::Refresh),
+    bind(KeyContext::Nonces, KeyCode::Esc, Action::Back),
+    bind(KeyContext::Transactions, KeyCode::Up, Action::Up),
+    bind(KeyContext::Transactions, KeyCode::Char('k'), Action::Up),
+    bind(KeyContext::Transactions, KeyCode::Down, Action::Down),
+    bind(KeyContext::Transactions, KeyCode::Char('j'), Action::Down),
+    bind(KeyContext::Transactions, KeyCode::Home, Action::First),
+    bind(KeyContext::Transactions, KeyCode::End, Action::Last),
+    bind(KeyContext::Transactions, KeyCode::Enter, Action::Details),
+    bind(KeyContext::Transactions, KeyCode::Char('/'), Action::Search),
+    bind(
+        KeyContext::Transactions,
+        KeyCode::Char('i'),
+        Action::Filter(HistoryFilter::Incoming),
+    ),
+    bind(
+        KeyContext::Transactions,
+        KeyCode::Char('d'),
+        Action::Filter(HistoryFilter::Outgoing),
+    ),
+    bind(
+        KeyContext::Transactions,
+        KeyCode::Char('f'),
+        Action::Filter(HistoryFilter::Failed),
+    ),
+    bind(
+        KeyContext::Transactions,
+        KeyCode::Char('t'),
+        Action::Filter(HistoryFilter::Token),
+    ),
+    bind(
+        KeyContext::Transactions,
+        KeyCode::Char('s'),
+        Action::Filter(HistoryFilter::Staking),
+    ),
+    bind(
+        KeyContext::Transactions,
+        KeyCode::Char('p'),
+        Action::Filter(HistoryFilter::OtherProgram),
+    ),
+    bind(
+        KeyContext::Transactions,
+        KeyCode::Char('c'),
+        Action::ClearFilters,
+    ),
+    bind(KeyContext::Transactions, KeyCode::Char('o'), Action::Open),
+    bind(KeyContext::Transactions, KeyCode::Char('n'), Action::Note),
+    bind(KeyContext::Transactions, KeyCode::Char('R'), Action::Resend),
+    bind(
+        KeyContext::Transactions,
+        KeyCode::Char('e'),
+        Action::ExportHistory,
+    ),
+    bind(
+        KeyContext::Transactions,
+        KeyCode::Char('r'),
+        Action::Refresh,
+    ),
+    bind(KeyContext::Transactions, KeyCode::Esc, Action::Back),
+    bind(KeyContext::Inspection, KeyCode::Up, Action::Up),
+    bind(KeyContext::Inspection, KeyCode::Char('k'), Action::Up),
+    bind(KeyContext::Inspection, KeyCode::Down, Action::Down),
+    bind(KeyContext::Inspection, KeyCode::Char('j'), Action::Down),
+    bind(KeyContext::Inspection, KeyCode::Home, Action::First),
+    bind(KeyContext::Inspection, KeyCode::End, Action::Last),
+    bind(KeyContext::Inspection, KeyCode::Char('o'), Action::Open),
+    bind(KeyContext::Inspection, KeyCode::Tab, Action::NextField),
+    bind(
+        KeyContext::Inspection,
+        KeyCode::BackTab,
+        Action::PreviousField,
+    ),
+    bind(KeyContext::Inspection, KeyCode::Char('y'), Action::Copy),
+    bind(KeyContext::Inspection, KeyCode::Esc, Action::Close),
+    bind(KeyContext::Inspection, KeyCode::Enter, Action::Close),
+    bind(KeyContext::ExportPrompt, KeyCode::Tab, Action::CycleFormat),
+    bind(KeyContext::Prompt, KeyCode::Enter, Action::Confirm),
+    bind(KeyContext::Prompt, KeyCode::Esc, Action::Cancel),
+    bind(KeyContext::Form, KeyCode::Enter, Action::Confirm),
+    bind(KeyContext::Form, KeyCode::Esc, Action::Back),
+    bind(KeyContext::Form, KeyCode::PageUp, Action::PageUp),
+    bind(KeyContext::Form, KeyCode::PageDown, Action::PageDown),
+    bind_ctrl(KeyContext::Form, 'r', Action::Retry),
+    bind(KeyContext::Lookup, KeyCode::Tab, Action::NextField),
+    bind(KeyContext::Lookup, KeyCode::BackTab, Action::PreviousField),
+    bind_ctrl(KeyContext::Lookup, 'y', Action::Copy),
+    bind(KeyContext::VanityPattern, KeyCode::Tab, Action::ToggleCase),
+    bind(KeyContext::SharesForm, KeyCode::Tab, Action::SwitchField),
+    bind(KeyContext::SendConfirm, KeyCode::Char('y'), Action::Confirm),
+    bind(KeyContext::SendConfirm, KeyCode::Char('Y'), Action::Confirm),
+    bind(
+        KeyContext::SendConfirm,
+        KeyCode::Char('b'),
+        Action::BumpAmount,
+    ),
+    bind(
+        KeyContext::SendConfirm,
+        KeyCode::Char('B'),
+        Action::BumpAmount,
+    ),
+    bind(
+        KeyContext::SendConfirm,
+        KeyCode::Char('p'),
+        Action::SetPriorityFee,
+    ),
+    bind(
+        KeyContext::SendConfirm,
+        KeyCode::Char('P'),
+        Action::SetPriorityFee,
+    ),
+    bind(
+        KeyContext::SendConfirm,
+        KeyCode::Char('r'),
+        Action::ToggleReference,
+    ),
+    bind(
+        KeyContext::SendConfirm,
+        KeyCode::Char('R'),
+        Action::ToggleReference,
+    ),
+    bind(KeyContext::SendConfirm, KeyCode::Char('n'), Action::Cancel),
+    bind(KeyContext::SendConfirm, KeyCode::Char('N'), Action::Cancel),
+    bind(KeyContext::SendConfirm, KeyCode::Esc, Action::Cancel),
+    bind_ctrl(KeyContext::SendConfirm, 'r', Action::Retry),
+    bind(KeyContext::SendConfirm, KeyCode::PageUp, Action::PageUp),
+    bind(KeyContext::SendConfirm, KeyCode::PageDown, Action::PageDown),
+    bind(
+        KeyContext::ExportChoice,
+        KeyCode::Char('k'),
+        Action::ExportPrivateKey,
+    ),
+    bind(
+        KeyContext::ExportChoice,
+        KeyCode::Char('K'),
+        Action::ExportPrivateKey,
+    ),
+    bind(
+        KeyContext::ExportChoice,
+        KeyCode::Char('s'),
+        Action::ExportSeedPhrase,
+    ),
+    bind(
+        KeyContext::ExportChoice,
+        KeyCode::Char('S'),
+        Action::ExportSeedPhrase,
+    ),
+    bind(
+        KeyContext::ExportChoice,
+        KeyCode::Char('p'),
+        Action::ExportPaperWallet,
+    ),
+    bind(
+        KeyContext::ExportChoice,
+        KeyCode::Char('P'),
+        Action::ExportPaperWallet,
+    ),
+    bind(
+        KeyContext::ExportChoice,
+        KeyCode::Char('b'),
+        Action::ExportShares,
+    ),
+    bind(
+        KeyContext::ExportChoice,
+        KeyCode::Char('B'),
+        Action::ExportShares,
+    ),
+    bind(KeyContext::ExportChoice, KeyCode::Esc, Action::Back),
+    bind(KeyContext::ExportChoice, KeyCode::PageUp, Action::PageUp),
+    bind(
+        KeyContext::ExportChoice,
+        KeyCode::PageDown,
+        Action::PageDown,
+    ),
+    bind(KeyContext::Lock, KeyCode::Enter, Action::Confirm),
+    bind_ctrl(KeyContext::Lock, 'c', Action::Quit),
+    bind(KeyContext::Help, KeyCode::Up, Action::Up),
+    bind(KeyContext::Help, KeyCode::Down, Action::Down),
+    bind(KeyContext::Help, KeyCode::Home, Action::First),
+    bind(KeyContext::Help, KeyCode::End, Action::Last),
+    bind(KeyContext::Help, KeyCode::PageUp, Action::PageUp),
+    bind(KeyContext::Help, KeyCode::PageDown, Action::PageDown),
+    bind(KeyContext::Help, KeyCode::Esc, Action::Close),
+    bind(KeyContext::Help, KeyCode::Char('?'), Action::Close),
+    bind(KeyContext::Logs, KeyCode::Up, Action::Up),
+    bind(KeyContext::Logs, KeyCode::Down, Action::Down),
+    bind(KeyContext::Logs, KeyCode::Home, Action::First),
+    bind(KeyContext::Logs, KeyCode::End, Action::Last),
+    bind(KeyContext::Logs, KeyCode::PageUp, Action::PageUp),
+    bind(KeyContext::Logs, KeyCode::PageDown, Action::PageDown),
+    bind(KeyContext::Logs, KeyCode::Esc, Action::Close),
+    bind(KeyContext::Logs, KeyCode::Char('L'), Action::Close),
+    bind(KeyContext::Logs, KeyCode::Char('A'), Action::Close),
+];
+
+/// Added on top of the defaults by the vim profile
+const VIM_BINDINGS: &[Binding] = &[
+    bind(KeyContext::Menu, KeyCode::Char('k'), Action::Up),
+    bind(KeyContext::Menu, KeyCode::Char('j'), Action::Down),
+    bind_pair(KeyContext::Menu, 'g', 'g', Action::First),
+    bind(KeyContext::Menu, KeyCode::Char('G'), Action::Last),
+    bind(KeyContext::Menu, KeyCode::Char('l'), Action::Select),
+    bind(KeyContext::Receive, KeyCode::Char('h'), Action::Back),
+    bind(KeyContext::Dashboard, KeyCode::Char('k'), Action::Up),
+    bind(KeyContext::Dashboard, KeyCode::Char('j'), Action::Down),
+    bind_pair(KeyContext::Dashboard, 'g', 'g', Action::First),
+    bind(KeyContext::Dashboard, KeyCode::Char('G'), Action::Last),
+    bind(
+        KeyContext::Dashboard,
+        KeyCode::Char('l'),
+        Action::OpenWallet,
+    ),
+    bind(KeyContext::Dashboard, KeyCode::Char('h'), Action::Back),
+    bind(KeyContext::Accounts, KeyCode::Char('k'), Action::Up),
+    bind(KeyContext::Accounts, KeyCode::Char('j'), Action::Down),
+    bind_pair(KeyContext::Accounts, 'g', 'g', Action::First),
+    bind(KeyContext::Accounts, KeyCode::Char('G'), Action::Last),
+    bind(
+        KeyContext::Accounts,
+        KeyCode::Char('l'),
+        Action::SwitchAccount,
+    ),
+    bind(KeyContext::Accounts, KeyCode::Char('h'), Action::Back),
+    bind(KeyContext::Watch, KeyCode::Char('k'), Action::Up),
+    bind(KeyContext::Watch, KeyCode::Char('j'), Action::Down),
+    bind_pair(KeyContext::Watch, 'g', 'g', Action::First),
+    bind(KeyContext::Watch, KeyCode::Char('G'), Action::Last),
+    bind(KeyContext::Watch, KeyCode::Char('h'), Action::Back),
+    bind(KeyContext::Requests, KeyCode::Char('k'), Action::Up),
+    bind(KeyContext::Requests, KeyCode::Char('j'), Action::Down),
+    bind_pair(KeyContext::Requests, 'g', 'g', Action::First),
+    bind(KeyContext::Requests, KeyCode::Char('G'), Action::Last),
+    bind(
+        KeyContext::Requests,
+        KeyCode::Char('l'),
+        Action::ShowRequest,
+    ),
+    bind(KeyContext::Requests, KeyCode::Char('h'), Action::Back),
+    bind(KeyContext::Settings, KeyCode::Char('k'), Action::Up),
+    bind(KeyContext::Settings, KeyCode::Char('j'), Action::Down),
+    bind_pair(KeyContext::Settings, 'g', 'g', Action::First),
+    bind(KeyContext::Settings, KeyCode::Char('G'), Action::Last),
+    bind(
+        KeyContext::Settings,
+        KeyCode::Char('l'),
+        Action::EditSetting,
+    ),
+    bind(KeyContext::Settings, KeyCode::Char('h'), Action::Back),
+    bind_pair(KeyContext::Transactions, 'g', 'g', Action::First),
+    bind(KeyContext::Transactions, KeyCode::Char('G'), Action::Last),
+    bind(
+        KeyContext::Transactions,
+        KeyCode::Char('l'),
+        Action::Details,
+    ),
+    bind(KeyContext::Transactions, KeyCode::Char('h'), Action::Back),
+    bind_pair(KeyContext::Inspection, 'g', 'g', Action::First),
+    bind(KeyContext::Inspection, KeyCode::Char('G'), Action::Last),
+    bind(KeyContext::Inspection, KeyCode::Char('h'), Action::Close),
+    bind(KeyContext::Help, KeyCode::Char('k'), Action::Up),
+    bind(KeyContext::Help, KeyCode::Char('j'), Action::Down),
+    bind_pair(KeyContext::Help, 'g', 'g', Action::First),
+    bind(KeyContext::Help, KeyCode::Char('G'), Action::Last),
+    bind(KeyContext::Help, KeyCode::Char('h'), Action::Close),
+    bind(KeyContext::Logs, KeyCode::Char('k'), Action::Up),
+    bind(KeyContext::Logs, KeyCode::Char('j'), Action::Down),
+    bind_pair(KeyContext::Logs, 'g', 'g', Action::First),
+    bind(KeyContext::Logs, KeyCode::Char('G'), Action::Last),
+    bind(KeyContext::Logs, KeyCode::Char('h'), Action::Close),
+];
+
+pub struct Keymap {
+    bindings: Vec<Binding>,
+    /// How many actions the config rebinds
+    remapped: usize,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::new(KeyProfile::Default)
+    }
+}
+
+impl Keymap {
+    pub fn new(profile: KeyProfile) -> Self {
+        let mut bindings = DEFAULT_BINDINGS.to_vec();
+        if profile == KeyProfile::Vim {
+            bindings.extend_from_slice(VIM_BINDINGS);
+        }
+        Self {
+            bindings,
+            remapped: 0,
+        }
+    }
+
+    /// The profile with the config's `[keys.bindings]` applied. Fails on
+    /// unknown names or keys, and on bindings that get in each other's way.
+    pub fn from_config(config: &KeysConfig) -> Result<Self> {
+        let mut keymap = Self::new(config.profile);
+        for (context_name, actions) in &config.bindings {
+            let context = KeyContext::ALL
+                .into_iter()
+                .find(|context| context.name() == context_name)
+                .ok_or_else(|| anyhow!("Unknown key context [keys.bindings.{}]", context_name))?;
+            for (action_name, keys) in actions {
+                let action = Action::from_name(action_name).ok_or_else(|| {
+                    anyhow!(
+                        "Unknown action `{}` in [keys.bindings.{}]",
+                        action_name,
+                        context_name
+                    )
+                })?;
+                let keys = keys
+                    .iter()
+                    .map(|text| Keys::parse(text))
+                    .collect::<Result<Vec<_>>>()
+                    .with_context(|| {
+                        format!(
+                            "Invalid keys for {} in [keys.bindings.{}]",
+                            action_name, context_name
+                        )
+                    })?;
+                keymap.rebind(context, action, &keys);
+            }
+        }
+
+        let conflicts = keymap.conflicts();
+        if !conflicts.is_empty() {
+            bail!("Conflicting key bindings:\n  {}", conflicts.join("\n  "));
+        }
+        Ok(keymap)
+    }
+
+    /// Replace what triggers `action` in `context`, keeping its place in
+    /// the footer. No keys unbinds it.
+    fn rebind(&mut self, context: KeyContext, action: Action, keys: &[Keys]) {
+        let is_target = |binding: &Binding| binding.context == context && binding.action == action;
+        let at = self
+            .bindings
+            .iter()
+            .position(is_target)
+            .unwrap_or(self.bindings.len());
+        self.bindings.retain(|binding| !is_target(binding));
+        let new = keys.iter().map(|&keys| Binding {
+            context,
+            keys,
+            action,
+        });
+        self.bindings.splice(at..at, new);
+        self.remapped += 1;
+    }
+
+    pub fn remapped(&self) -> usize {
+        self.remapped
+    }
+
+    /// Bindings that can't all work: the same keys doing different things
+    /// on one screen, a key that hides a pair starting with it, and letters
+    /// bound where they are typed as text
+    pub fn conflicts(&self) -> Vec<String> {
+        let mut conflicts = BTreeSet::new();
+        for context in KeyContext::ALL {
+            let bindings: Vec<&Binding> = self.bindings(context).collect();
+            for (i, a) in bindings.iter().enumerate() {
+                for b in &bindings[i + 1..] {
+                    if a.keys == b.keys && a.action != b.action {
+                        conflicts.insert(format!(
+                            "{} is bound to both {} in [{}] and {} in [{}]",
+                            a.keys,
+                            a.action.name(),
+                            a.context.name(),
+                            b.action.name(),
+                            b.context.name()
+                        ));
+                    }
+                    for (single, pair) in [(a, b), (b, a)] {
+                        if single.keys.second.is_none()
+                            && pair.keys.second.is_some()
+                            && single.keys.first == pair.keys.first
+                        {
+                            conflicts.insert(format!(
+                                "{} ({} in [{}]) hides {} ({} in [{}])",
+                                single.keys,
+                                single.action.name(),
+                                single.context.name(),
+                                pair.keys,
+                                pair.action.name(),
+                                pair.context.name()
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+
+        let typed =
+            |key: Key| matches!(key.code, KeyCode::Char(_)) && key.modifiers == KeyModifiers::NONE;
+        for binding in &self.bindings {
+            if binding.context.takes_text() && typed(binding.keys.first) {
+                conflicts.insert(format!(
+                    "{} can't be bound to {} in [{}], where it's typed as text",
+                    binding.keys,
+                    binding.action.name(),
+                    binding.context.name()
+                ));
+            }
+        }
+        conflicts.into_iter().collect()
+    }
+
+    /// What pressing `key` in `context` does, after `pending` if the
+    /// previous key started a pair. The context's own bindings win over its
+    /// parents', and a pair that doesn't complete starts over from `key`.
+    pub fn lookup(&self, context: KeyContext, pending: Option<Key>, key: Key) -> Lookup {
+        if let Some(first) = pending {
+            let pair = Keys {
+                first,
+                second: Some(key),
+            };
+            if let Some(binding) = self.bindings(context).find(|b| b.keys == pair) {
+                return Lookup::Action(binding.action);
+            }
+        }
+        let single = Keys {
+            first: key,
+            second: None,
+        };
+        if let Some(binding) = self.bindings(context).find(|b| b.keys == single) {
+            return Lookup::Action(binding.action);
+        }
+        if self.bindings(context).any(|b| b.keys.first == key) {
+            return Lookup::Pending;
+        }
+        Lookup::Unbound
+    }
+
+    /// Every binding that applies in `context`, its own first
+    pub fn bindings(&self, context: KeyContext) -> impl Iterator<Item = &Binding> {
+        context.chain().flat_map(move |context| {
+            self.bindings
+                .iter()
+                .filter(move |binding| binding.context == context)
+        })
+    }
+
+    /// `(keys, hint)` pairs for the bindings passing `available`, with
+    /// keys that share a hint merged into `i/d/f filter`
+    pub fn hints(
+        &self,
+        context: KeyContext,
+        available: impl Fn(Action) -> bool,
+    ) -> Vec<(String, &'static str)> {
+        merge(
+            self.bindings(context)
+                .filter(|binding| available(binding.action)),
+            Action::hint,
+        )
+        .into_iter()
+        .map(|(keys, action)| (keys, action.hint()))
+        .collect()
+    }
+
+    /// The keys bound to `action` in `context`, to name in prompts
+    pub fn keys_for(&self, context: KeyContext, action: Action) -> Option<String> {
+        self.bindings(context)
+            .find(|binding| binding.action == action)
+            .map(|binding| binding.keys.to_string())
+    }
+
+    /// Each context with `(keys, action)` for its own bindings, for the
+    /// help overlay
+    pub fn help(&self) -> Vec<(KeyContext, Vec<(String, Action)>)> {
+        KeyContext::ALL
+            .iter()
+            .map(|&context| {
+                let own = self
+                    .bindings
+                    .iter()
+                    .filter(|binding| binding.context == context);
+                (context, merge(own, Action::description))
+            })
+            .collect()
+    }
+}
+
+/// List keys with the same label together, under the first one's action
+fn merge<'a>(
+    bindings: impl Iterator<Item = &'a Binding>,
+    label: fn(Action) -> &'static str,
+) -> Vec<(String, Action)> {
+    let mut merged: Vec<(String, Action)> = Vec::new();
+    for binding in bindings {
+        let key = binding.keys.to_string();
+        let text = label(binding.action);
+        match merged
+            .iter_mut()
+            .find(|(_, existing)| label(*existing) == text)
+        {
+            Some((keys, _)) => {
+                keys.push('/');
+                keys.push_str(&key);
+            }
+            None => merged.push((key, binding.action)),
+        }
+    }
+    merged
+}
+
+/// How a key is written in the footer
+pub fn key_label(key: KeyCode) -> String {
+    match key {
+        KeyCode::Char(' ') => "Space".to_string(),
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::Up => "↑".to_string(),
+        KeyCode::Down => "↓".to_string(),
+        KeyCode::Left => "←".to_string(),
+        KeyCode::Right => "→".to_string(),
+        KeyCode::Enter => "Enter".to_string(),
+        KeyCode::Esc => "Esc".to_string(),
+        KeyCode::Tab => "Tab".to_string(),
+        KeyCode::BackTab => "Shift+Tab".to_string(),
+        KeyCode::Home => "Home".to_string(),
+        KeyCode::End => "End".to_string(),
+        KeyCode::PageUp => "PgUp".to_string(),
+        KeyCode::PageDown => "PgDn".to_string(),
+        KeyCode::Backspace => "Backspace".to_string(),
+        KeyCode::Delete => "Del".to_string(),
+        KeyCode::F(n) => format!("F{}", n),
+        other => format!("{:?}", other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::KeyList;
+
+    fn key(c: char) -> Key {
+        Key::plain(KeyCode::Char(c))
+    }
+
+    #[test]
+    fn contexts_inherit_and_hints_merge() {
+        let keymap = Keymap::default();
+        assert_eq!(
+            keymap.lookup(KeyContext::Wallet, None, key('q')),
+            Lookup::Action(Action::Quit)
+        );
+        // Prompts take text, so letters fall through
+        assert_eq!(
+            keymap.lookup(KeyContext::Prompt, None, key('q')),
+            Lookup::Unbound
+        );
+        assert_eq!(
+            keymap.lookup(KeyContext::ExportPrompt, None, Key::plain(KeyCode::Enter)),
+            Lookup::Action(Action::Confirm)
+        );
+
+        let hints = keymap.hints(KeyContext::Transactions, |_| true);
+        assert!(hints.contains(&("i/d/f/t/s/p".to_string(), "filter")));
+        assert_eq!(hints.last(), Some(&("q".to_string(), "quit")));
+
+        // Help has each filter on its own line, but one line for ↑/k
+        let help = keymap.help();
+        let (_, transactions) = help
+            .iter()
+            .find(|(context, _)| *context == KeyContext::Transactions)
+            .unwrap();
+        assert!(transactions.contains(&("i".to_string(), Action::Filter(HistoryFilter::Incoming))));
+        assert!(transactions.contains(&("↑/k".to_string(), Action::Up)));
+    }
+
+    #[test]
+    fn vim_profile_adds_pairs() {
+        let vim = Keymap::new(KeyProfile::Vim);
+        let g = key('g');
+        assert_eq!(vim.lookup(KeyContext::Accounts, None, g), Lookup::Pending);
+        assert_eq!(
+            vim.lookup(KeyContext::Accounts, Some(g), g),
+            Lookup::Action(Action::First)
+        );
+        // A pair that doesn't complete is read as the second key alone
+        assert_eq!(
+            vim.lookup(KeyContext::Accounts, Some(g), key('j')),
+            Lookup::Action(Action::Down)
+        );
+        let hints = vim.hints(KeyContext::Wallet, |_| true);
+        assert!(hints.contains(&("Enter/l".to_string(), "select")));
+
+        assert_eq!(
+            Keymap::default().lookup(KeyContext::Accounts, None, g),
+            Lookup::Unbound
+        );
+    }
+
+    fn config(bindings: &[(&str, &str, &[&str])]) -> KeysConfig {
+        let mut config = KeysConfig::default();
+        for &(context, action, keys) in bindings {
+            config
+                .bindings
+                .entry(context.to_string())
+                .or_default()
+                .insert(
+                    action.to_string(),
+                    KeyList::Many(keys.iter().map(|key| key.to_string()).collect()),
+                );
+        }
+        config
+    }
+
+    #[test]
+    fn config_rebinds_and_rejects_conflicts() {
+        assert!(Keymap::default().conflicts().is_empty());
+        assert!(Keymap::new(KeyProfile::Vim).conflicts().is_empty());
+
+        let keymap = Keymap::from_config(&config(&[
+            ("transactions", "filter_incoming", &["I", "ctrl+i"]),
+            ("global", "quit", &[]),
+            ("menu", "first", &["g g"]),
+        ]))
+        .unwrap();
+        assert_eq!(keymap.remapped(), 3);
+        let ctrl_i = Key {
+            code: KeyCode::Char('i'),
+            modifiers: KeyModifiers::CONTROL,
+        };
+        assert_eq!(
+            keymap.lookup(KeyContext::Transactions, None, ctrl_i),
+            Lookup::Action(Action::Filter(HistoryFilter::Incoming))
+        );
+        assert_eq!(
+            keymap.lookup(KeyContext::Transactions, None, key('i')),
+            Lookup::Unbound
+        );
+        assert_eq!(
+            keymap.lookup(KeyContext::Wallet, None, key('q')),
+            Lookup::Unbound
+        );
+        // Still first among the filters in the footer
+        let hints = keymap.hints(KeyContext::Transactions, |_| true);
+        assert!(hints.contains(&("I/Ctrl+i/d/f/t/s/p".to_string(), "filter")));
+
+        let error = |bindings| {
+            format!(
+                "{:#}",
+                Keymap::from_config(&config(bindings)).err().unwrap()
+            )
+        };
+        // Shadows quit from [global]
+        assert!(error(&[("wallet", "refresh", &["q"])]).contains("quit in [global]"));
+        assert!(
+            error(&[("menu", "select", &["g"]), ("menu", "first", &["g g"])]).contains("hides gg")
+        );
+        assert!(error(&[("form", "back", &["x"])]).contains("typed as text"));
+        assert!(error(&[("form", "bogus", &["x"])]).contains("Unknown action"));
+        assert!(error(&[("form", "back", &["hyper+x"])]).contains("Unknown key"));
+    }
+}
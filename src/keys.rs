@@ -0,0 +1,537 @@
+use crate::audit;
+use crate::memlock::{self, LockedRegion};
+use anyhow::{bail, Context, Result};
+use serde::de::{self, Deserialize, Deserializer, SeqAccess, Visitor};
+use solana_sdk::{
+    derivation_path::DerivationPath,
+    pubkey::Pubkey,
+    signature::{Keypair, Signature, Signer, SignerError},
+    signer::keypair::{keypair_from_seed, keypair_from_seed_and_derivation_path},
+};
+use std::{
+    env, fmt,
+    fs::{File, OpenOptions},
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use zeroize::Zeroizing;
+
+const KEYPAIR_LENGTH: usize = 64;
+
+/// Environment variable holding a keypair (JSON bytes) or a keypair source
+pub const KEYPAIR_ENV_VAR: &str = "FURRYBAIT_KEYPAIR";
+
+const PROMPT_SCHEME: &str = "prompt:";
+
+/// Where the wallet's signing key is loaded from
+#[derive(Debug, Clone, PartialEq)]
+pub enum KeypairSource {
+    /// Solana CLI JSON keypair file
+    File(PathBuf),
+    /// JSON keypair bytes read from stdin (`-`)
+    Stdin,
+    /// JSON or base58 keypair stored directly in `FURRYBAIT_KEYPAIR`
+    Env,
+    /// Seed phrase typed at an interactive prompt (`prompt:`)
+    Prompt,
+    /// Only the wallet's address, which read-only mode can get by with
+    Address(Pubkey),
+}
+
+impl KeypairSource {
+    /// Parse a `--keypair` value: a file path, `-` or `prompt:`
+    pub fn parse(spec: &str) -> Self {
+        if spec == "-" {
+            Self::Stdin
+        } else if spec == PROMPT_SCHEME || spec == "prompt://" {
+            Self::Prompt
+        } else {
+            Self::File(crate::config::expand_tilde(Path::new(spec)))
+        }
+    }
+
+    /// Pick the source from `--keypair`, then `FURRYBAIT_KEYPAIR`, then the
+    /// Solana CLI default location
+    pub fn resolve(arg: Option<&str>) -> Result<Self> {
+        if let Some(spec) = arg {
+            return Ok(Self::parse(spec));
+        }
+
+        if let Ok(value) = env::var(KEYPAIR_ENV_VAR) {
+            let value = Zeroizing::new(value);
+            return Ok(if parse_keypair_contents(value.as_bytes()).is_ok() {
+                Self::Env
+            } else {
+                Self::parse(value.trim())
+            });
+        }
+
+        let mut default_path = dirs::home_dir().context("Could not find home directory")?;
+        default_path.push(".config");
+        default_path.push("solana");
+        default_path.push("id.json");
+        Ok(Self::File(default_path))
+    }
+
+    pub fn load(&self) -> Result<SecretKeypair> {
+        match self {
+            Self::File(path) => load_keypair(path),
+            Self::Stdin => {
+                let mut contents = Zeroizing::new(Vec::with_capacity(1024));
+                io::stdin()
+                    .read_to_end(&mut contents)
+                    .context("Failed to read keypair from stdin")?;
+                parse_keypair_contents(&contents).context("Failed to parse keypair from stdin")
+            }
+            Self::Env => {
+                let contents = Zeroizing::new(
+                    env::var(KEYPAIR_ENV_VAR)
+                        .with_context(|| format!("{} is not set", KEYPAIR_ENV_VAR))?,
+                );
+                parse_keypair_contents(contents.as_bytes())
+                    .with_context(|| format!("Failed to parse keypair from {}", KEYPAIR_ENV_VAR))
+            }
+            Self::Prompt => Arc::new(SeedPhrase::prompt()?).derive(&Derivation::Root),
+            Self::Address(address) => {
+                bail!("Only the address of {} is known, not its key", address)
+            }
+        }
+    }
+
+    /// Whether the key can be loaded again without user interaction
+    pub fn is_reloadable(&self) -> bool {
+        matches!(self, Self::File(_) | Self::Env)
+    }
+}
+
+impl fmt::Display for KeypairSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::File(path) => write!(f, "{}", path.display()),
+            Self::Stdin => write!(f, "stdin"),
+            Self::Env => write!(f, "${}", KEYPAIR_ENV_VAR),
+            Self::Prompt => write!(f, "seed phrase"),
+            Self::Address(address) => write!(f, "address {}", address),
+        }
+    }
+}
+
+/// The wallet's signing key.
+///
+/// The underlying ed25519 secret is zeroized when this is dropped, it can't be
+/// cloned, and its `Debug` output only shows the public key. The key lives in
+/// its own heap allocation, locked into RAM where the OS allows it.
+pub struct SecretKeypair {
+    // Dropped (and zeroized) before the pages are unlocked
+    keypair: Box<Keypair>,
+    /// Seed phrase and path the key was derived from, if any
+    seed: Option<(Arc<SeedPhrase>, Derivation)>,
+    _locked: Option<LockedRegion>,
+}
+
+impl SecretKeypair {
+    /// Build from the 64-byte `secret || public` representation
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() != KEYPAIR_LENGTH {
+            bail!(
+                "Expected {} keypair bytes, found {}",
+                KEYPAIR_LENGTH,
+                bytes.len()
+            );
+        }
+        let keypair =
+            Box::new(Keypair::try_from(bytes).map_err(|_| anyhow::anyhow!("Invalid keypair"))?);
+        let locked = memlock::lock(&*keypair);
+        Ok(Self {
+            keypair,
+            seed: None,
+            _locked: locked,
+        })
+    }
+
+    /// Build from the 32-byte ed25519 secret alone
+    pub fn from_secret(secret: &[u8; 32]) -> Result<Self> {
+        let keypair = keypair_from_seed(&secret[..])
+            .map_err(|e| anyhow::anyhow!("Invalid secret key: {}", e))?;
+        Ok(Self::with_seed(keypair, None))
+    }
+
+    /// Parse the base58-encoded 64-byte secret exported by Phantom and Solflare
+    pub fn from_base58(encoded: &str) -> Result<Self> {
+        let mut bytes = Zeroizing::new([0u8; KEYPAIR_LENGTH]);
+        let len = bs58::decode(encoded.trim())
+            .onto(&mut bytes[..])
+            .map_err(|_| anyhow::anyhow!("Invalid base58 private key"))?;
+        Self::from_bytes(&bytes[..len])
+    }
+
+    /// Write the key as a Solana CLI JSON keypair file, readable only by the
+    /// owner. Refuses to overwrite an existing file.
+    pub fn write_to_file(&self, path: &Path) -> Result<()> {
+        let mut file = create_secret_file(path)?;
+
+        let bytes = Zeroizing::new(self.keypair.to_bytes());
+        let mut json = Zeroizing::new(String::with_capacity(KEYPAIR_LENGTH * 4 + 2));
+        json.push('[');
+        for (i, byte) in bytes.iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+            json.push_str(&byte.to_string());
+        }
+        json.push(']');
+
+        file.write_all(json.as_bytes())
+            .with_context(|| format!("Failed to write keypair file: {}", path.display()))
+    }
+
+    /// Take ownership of a freshly generated keypair
+    pub fn from_keypair(keypair: Keypair) -> Self {
+        Self::with_seed(keypair, None)
+    }
+
+    fn with_seed(keypair: Keypair, seed: Option<(Arc<SeedPhrase>, Derivation)>) -> Self {
+        let keypair = Box::new(keypair);
+        let locked = memlock::lock(&*keypair);
+        Self {
+            keypair,
+            seed,
+            _locked: locked,
+        }
+    }
+
+    /// Base58 encoding of the 64-byte secret, as used by Phantom and Solflare
+    pub fn to_base58(&self) -> Zeroizing<String> {
+        Zeroizing::new(self.keypair.to_base58_string())
+    }
+
+    /// The 32-byte ed25519 secret
+    pub fn secret_bytes(&self) -> &[u8; 32] {
+        self.keypair.secret_bytes()
+    }
+
+    pub fn seed_phrase(&self) -> Option<&str> {
+        self.seed.as_ref().map(|(seed, _)| seed.phrase())
+    }
+
+    pub fn derivation(&self) -> Option<&Derivation> {
+        self.seed.as_ref().map(|(_, derivation)| derivation)
+    }
+
+    /// The seed phrase this key came from, for deriving sibling accounts
+    pub fn seed_and_derivation(&self) -> Option<(&Arc<SeedPhrase>, &Derivation)> {
+        self.seed
+            .as_ref()
+            .map(|(seed, derivation)| (seed, derivation))
+    }
+}
+
+impl fmt::Debug for SecretKeypair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("SecretKeypair")
+            .field(&self.keypair.pubkey())
+            .finish()
+    }
+}
+
+impl Signer for SecretKeypair {
+    fn try_pubkey(&self) -> Result<Pubkey, SignerError> {
+        self.keypair.try_pubkey()
+    }
+
+    fn try_sign_message(&self, message: &[u8]) -> Result<Signature, SignerError> {
+        let signature = self.keypair.try_sign_message(message)?;
+        audit::record(&self.keypair.pubkey(), &signature, message);
+        Ok(signature)
+    }
+
+    fn is_interactive(&self) -> bool {
+        false
+    }
+}
+
+/// Keypair bytes parsed straight from the JSON array into zeroized storage,
+/// without going through an intermediate `Vec<u8>`
+struct KeypairBytes(Zeroizing<[u8; KEYPAIR_LENGTH]>);
+
+impl<'de> Deserialize<'de> for KeypairBytes {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct BytesVisitor;
+
+        impl<'de> Visitor<'de> for BytesVisitor {
+            type Value = KeypairBytes;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "an array of {} bytes", KEYPAIR_LENGTH)
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let mut bytes = Zeroizing::new([0u8; KEYPAIR_LENGTH]);
+                for (i, byte) in bytes.iter_mut().enumerate() {
+                    *byte = seq
+                        .next_element()?
+                        .ok_or_else(|| de::Error::invalid_length(i, &self))?;
+                }
+                if seq.next_element::<u8>()?.is_some() {
+                    return Err(de::Error::invalid_length(KEYPAIR_LENGTH + 1, &self));
+                }
+                Ok(KeypairBytes(bytes))
+            }
+        }
+
+        deserializer.deserialize_seq(BytesVisitor)
+    }
+}
+
+/// `~/.config/solana/id.json` if it's free, otherwise a file named after the
+/// address next to it
+pub fn default_import_path(address: &Pubkey) -> Option<PathBuf> {
+    let dir = dirs::home_dir()?.join(".config").join("solana");
+    let id = dir.join("id.json");
+    Some(if id.exists() {
+        dir.join(format!("{}.json", address))
+    } else {
+        id
+    })
+}
+
+/// Create a new file readable only by the owner, refusing to overwrite an
+/// existing one
+pub fn create_secret_file(path: &Path) -> Result<File> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+    }
+
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options
+        .open(path)
+        .with_context(|| format!("Failed to create file: {}", path.display()))
+}
+
+/// Load a keypair from a Solana CLI JSON keypair file
+pub fn load_keypair(path: &Path) -> Result<SecretKeypair> {
+    let mut file = File::open(path)
+        .with_context(|| format!("Failed to open keypair file: {}", path.display()))?;
+
+    // Reserve enough up front that reading never reallocates and leaves a
+    // stray copy of the contents behind
+    let len = file.metadata().map(|m| m.len() as usize).unwrap_or(0);
+    let mut contents = Zeroizing::new(Vec::with_capacity(len + 64));
+    file.read_to_end(&mut contents)
+        .with_context(|| format!("Failed to read keypair file: {}", path.display()))?;
+
+    parse_keypair_contents(&contents)
+        .with_context(|| format!("Invalid keypair in file: {}", path.display()))
+}
+
+/// Parse either the Solana CLI JSON byte array or a base58-encoded secret
+fn parse_keypair_contents(contents: &[u8]) -> Result<SecretKeypair> {
+    let text = std::str::from_utf8(contents).context("Keypair is not valid UTF-8")?;
+    if text.trim_start().starts_with('[') {
+        let bytes: KeypairBytes = serde_json::from_str(text)?;
+        SecretKeypair::from_bytes(&bytes.0[..])
+    } else {
+        SecretKeypair::from_base58(text)
+    }
+}
+
+/// How a keypair is derived from a seed phrase
+#[derive(Debug, Clone, PartialEq)]
+pub enum Derivation {
+    /// The seed itself, as `solana-keygen` does when no path is given
+    Root,
+    /// A BIP44 path such as `m/44'/501'/0'/0'` (Phantom, Solflare, Ledger)
+    Path(String),
+}
+
+impl Derivation {
+    /// The `m/44'/501'/<index>'/0'` account used by most wallets
+    pub fn account(index: u32) -> Self {
+        Self::Path(format!("m/44'/501'/{}'/0'", index))
+    }
+
+    pub fn parse(path: &str) -> Result<Self> {
+        DerivationPath::from_absolute_path_str(path).map_err(|e| anyhow::anyhow!("{}", e))?;
+        Ok(Self::Path(path.to_string()))
+    }
+}
+
+impl fmt::Display for Derivation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Root => write!(f, "root"),
+            Self::Path(path) => write!(f, "{}", path),
+        }
+    }
+}
+
+/// A BIP39 seed phrase, kept in memory so accounts can be derived from it
+pub struct SeedPhrase {
+    phrase: Zeroizing<String>,
+    seed: Zeroizing<[u8; 64]>,
+}
+
+impl SeedPhrase {
+    pub fn new(phrase: &str, passphrase: &str) -> Result<Self> {
+        let mnemonic = bip39::Mnemonic::parse_normalized(phrase.trim())
+            .map_err(|e| anyhow::anyhow!("Invalid seed phrase: {}", e))?;
+        Ok(Self {
+            phrase: Zeroizing::new(mnemonic.to_string()),
+            seed: Zeroizing::new(mnemonic.to_seed(passphrase)),
+        })
+    }
+
+    /// Ask for the phrase (and optional passphrase) on the terminal
+    pub fn prompt() -> Result<Self> {
+        let phrase = Zeroizing::new(rpassword::prompt_password("Seed phrase: ")?);
+        let passphrase = Zeroizing::new(rpassword::prompt_password(
+            "Seed passphrase (empty for none): ",
+        )?);
+        Self::new(&phrase, &passphrase)
+    }
+
+    pub fn phrase(&self) -> &str {
+        &self.phrase
+    }
+
+    pub fn derive(self: &Arc<Self>, derivation: &Derivation) -> Result<SecretKeypair> {
+        let keypair = match derivation {
+            Derivation::Root => keypair_from_seed(&self.seed[..]),
+            Derivation::Path(path) => keypair_from_seed_and_derivation_path(
+                &self.seed[..],
+                Some(
+                    DerivationPath::from_absolute_path_str(path)
+                        .map_err(|e| anyhow::anyhow!("{}", e))?,
+                ),
+            ),
+        }
+        .map_err(|e| anyhow::anyhow!("Failed to derive keypair: {}", e))?;
+
+        Ok(SecretKeypair::with_seed(
+            keypair,
+            Some((Arc::clone(self), derivation.clone())),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn secret_strings(keypair: &Keypair) -> Vec<String> {
+        let bytes = keypair.to_bytes();
+        vec![
+            format!("{:?}", &bytes[..32]),
+            format!("{:?}", &bytes[..]),
+            keypair.to_base58_string(),
+        ]
+    }
+
+    #[test]
+    fn debug_output_hides_secret() {
+        let keypair = Keypair::new();
+        let secret = SecretKeypair::from_bytes(&keypair.to_bytes()).unwrap();
+
+        let debug = format!("{:?}", secret);
+        let pretty = format!("{:#?}", secret);
+        for leaked in secret_strings(&keypair) {
+            assert!(!debug.contains(&leaked));
+            assert!(!pretty.contains(&leaked));
+        }
+        assert!(debug.contains(&keypair.pubkey().to_string()));
+    }
+
+    #[test]
+    fn load_keypair_round_trip() {
+        let keypair = Keypair::new();
+        let path = std::env::temp_dir().join(format!("solace-test-{}.json", keypair.pubkey()));
+        let mut file = File::create(&path).unwrap();
+        write!(file, "{:?}", &keypair.to_bytes()[..]).unwrap();
+        drop(file);
+
+        let loaded = load_keypair(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.unwrap().pubkey(), keypair.pubkey());
+    }
+
+    #[test]
+    fn base58_import_round_trip() {
+        let keypair = Keypair::new();
+        let imported = SecretKeypair::from_base58(&keypair.to_base58_string()).unwrap();
+        assert_eq!(imported.pubkey(), keypair.pubkey());
+
+        let path =
+            std::env::temp_dir().join(format!("solace-test-import-{}.json", keypair.pubkey()));
+        imported.write_to_file(&path).unwrap();
+        assert!(imported.write_to_file(&path).is_err());
+        let loaded = load_keypair(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.unwrap().pubkey(), keypair.pubkey());
+    }
+
+    #[test]
+    fn derive_bip44_accounts() {
+        // Well-known test vector from the BIP39 spec
+        let phrase = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let seed = Arc::new(SeedPhrase::new(phrase, "").unwrap());
+
+        let first = seed.derive(&Derivation::account(0)).unwrap();
+        let second = seed.derive(&Derivation::account(1)).unwrap();
+        let root = seed.derive(&Derivation::Root).unwrap();
+        assert_ne!(first.pubkey(), second.pubkey());
+        assert_ne!(first.pubkey(), root.pubkey());
+        assert_eq!(
+            first.pubkey(),
+            seed.derive(&Derivation::parse("m/44'/501'/0'/0'").unwrap())
+                .unwrap()
+                .pubkey()
+        );
+        assert_eq!(first.seed_phrase(), Some(phrase));
+        assert!(SeedPhrase::new("not a seed phrase", "").is_err());
+    }
+
+    #[test]
+    fn parse_keypair_source() {
+        assert_eq!(KeypairSource::parse("-"), KeypairSource::Stdin);
+        assert_eq!(KeypairSource::parse("prompt:"), KeypairSource::Prompt);
+        assert_eq!(
+            KeypairSource::parse("/tmp/id.json"),
+            KeypairSource::File(PathBuf::from("/tmp/id.json"))
+        );
+    }
+
+    #[test]
+    fn load_keypair_rejects_wrong_length() {
+        let path = std::env::temp_dir().join("solace-test-short-keypair.json");
+        std::fs::write(&path, "[1, 2, 3]").unwrap();
+
+        let loaded = load_keypair(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert!(loaded.is_err());
+    }
+
+    #[test]
+    fn load_error_does_not_echo_contents() {
+        let keypair = Keypair::new();
+        let path = std::env::temp_dir().join(format!("solace-test-bad-{}.json", keypair.pubkey()));
+        // Valid bytes followed by a trailing element
+        let mut contents = format!("{:?}", &keypair.to_bytes()[..]);
+        contents.insert_str(contents.len() - 1, ", 0");
+        std::fs::write(&path, &contents).unwrap();
+
+        let error = format!("{:?}", load_keypair(&path).unwrap_err());
+        std::fs::remove_file(&path).unwrap();
+        for leaked in secret_strings(&keypair) {
+            assert!(!error.contains(&leaked));
+        }
+    }
+}
@@ -0,0 +1,57 @@
+//! Solace, a Solana wallet for the terminal. The binary is the TUI and the
+//! command line; this library holds everything they share, so the daemon,
+//! the headless commands and tests can use it without a terminal.
+
+pub mod accountinfo;
+pub mod amount;
+pub mod audit;
+pub mod blocklist;
+pub mod cache;
+pub mod cli;
+pub mod config;
+pub mod daemon;
+pub mod dashboard;
+pub mod explorer;
+pub mod failure;
+pub mod governance;
+pub mod grind;
+pub mod grpc;
+pub mod history;
+pub mod historydb;
+pub mod i18n;
+pub mod idl;
+pub mod inspector;
+pub mod jito;
+pub mod keymap;
+pub mod keys;
+pub mod limits;
+pub mod logging;
+pub mod lookup_table;
+pub mod memlock;
+pub mod message;
+pub mod nonce;
+pub mod notify;
+pub mod output;
+pub mod paper;
+pub mod pay;
+pub mod price;
+pub mod priority;
+pub mod programs;
+pub mod qr;
+pub mod rent;
+pub mod report;
+pub mod rpc;
+pub mod script;
+pub mod seeded;
+pub mod sender;
+pub mod shamir;
+pub mod siws;
+pub mod staking;
+pub mod task;
+pub mod theme;
+pub mod throttle;
+pub mod token;
+pub mod validator;
+pub mod wallet;
+pub mod watch;
+pub mod webhook;
@@ -0,0 +1,218 @@
+use crate::config::{data_dir, LimitsConfig};
+use crate::wallet::sol_to_lamports;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const DAY_SECS: u64 = 24 * 60 * 60;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SpendRecord {
+    timestamp: u64,
+    lamports: u64,
+}
+
+/// Record of recent outgoing transfers, used to enforce the per-day limit
+#[derive(Debug, Default)]
+pub struct SpendingLedger {
+    path: Option<PathBuf>,
+    records: Vec<SpendRecord>,
+}
+
+/// How a pending send relates to the configured limits
+#[derive(Debug, Clone, PartialEq)]
+pub enum LimitCheck {
+    Within,
+    /// Exceeds a limit; the user must type the exact amount to continue
+    Exceeded(String),
+    /// Exceeds a limit in restricted mode; the send is not allowed
+    Blocked(String),
+}
+
+impl SpendingLedger {
+    /// Load the ledger from the data directory, dropping entries older than a day
+    pub fn load() -> Result<Self> {
+        let path = data_dir()?.join("spending.json");
+        let records = read_records(&path)?;
+        Ok(Self {
+            path: Some(path),
+            records,
+        })
+    }
+
+    /// Lamports sent over the last 24 hours, by this process or any other
+    /// sharing the ledger
+    pub fn spent_last_day(&self) -> u64 {
+        let cutoff = now().saturating_sub(DAY_SECS);
+        let on_disk = self.path.as_deref().map(read_records);
+        let records = match on_disk {
+            Some(Ok(ref records)) => records,
+            _ => &self.records,
+        };
+        records
+            .iter()
+            .filter(|record| record.timestamp >= cutoff)
+            .map(|record| record.lamports)
+            .sum()
+    }
+
+    /// Add a send to the ledger. The TUI, the daemon and the CLI may all be
+    /// sending, so the file is reread and replaced under a lock rather than
+    /// overwritten with what this process loaded.
+    pub fn record(&mut self, lamports: u64) -> Result<()> {
+        let record = SpendRecord {
+            timestamp: now(),
+            lamports,
+        };
+        let Some(ref path) = self.path else {
+            self.records.push(record);
+            return Ok(());
+        };
+
+        let lock_path = path.with_extension("lock");
+        let lock = File::create(&lock_path)
+            .with_context(|| format!("Failed to open {}", lock_path.display()))?;
+        lock.lock()
+            .with_context(|| format!("Failed to lock {}", lock_path.display()))?;
+        let mut records = read_records(path)?;
+        records.push(record);
+        let temp = path.with_extension("json.tmp");
+        fs::write(&temp, serde_json::to_string(&records)?)
+            .and_then(|()| fs::rename(&temp, path))
+            .with_context(|| format!("Failed to write spending ledger: {}", path.display()))?;
+        self.records = records;
+        Ok(())
+    }
+
+    pub fn check(&self, limits: &LimitsConfig, lamports: u64) -> LimitCheck {
+        let violation = if let Some(max) = limits
+            .per_transaction
+            .filter(|max| lamports > sol_to_lamports(*max))
+        {
+            Some(format!("exceeds the per-transaction limit of {} SOL", max))
+        } else {
+            limits
+                .per_day
+                .filter(|max| self.spent_last_day() + lamports > sol_to_lamports(*max))
+                .map(|max| format!("exceeds the daily limit of {} SOL", max))
+        };
+
+        match violation {
+            None => LimitCheck::Within,
+            Some(reason) if limits.restricted => LimitCheck::Blocked(reason),
+            Some(reason) => LimitCheck::Exceeded(reason),
+        }
+    }
+}
+
+/// The ledger's entries from the last day, or none if it doesn't exist yet
+fn read_records(path: &Path) -> Result<Vec<SpendRecord>> {
+    let mut records: Vec<SpendRecord> = if path.exists() {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read spending ledger: {}", path.display()))?;
+        serde_json::from_str(&contents)
+            .with_context(|| format!("Failed to parse spending ledger: {}", path.display()))?
+    } else {
+        Vec::new()
+    };
+    let cutoff = now().saturating_sub(DAY_SECS);
+    records.retain(|record| record.timestamp >= cutoff);
+    Ok(records)
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::native_token::LAMPORTS_PER_SOL;
+
+    fn ledger(records: &[(u64, u64)]) -> SpendingLedger {
+        SpendingLedger {
+            path: None,
+            records: records
+                .iter()
+                .map(|&(age, lamports)| SpendRecord {
+                    timestamp: now() - age,
+                    lamports,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn checks_the_per_transaction_limit() {
+        let limits = LimitsConfig {
+            per_transaction: Some(1.005),
+            ..LimitsConfig::default()
+        };
+        let ledger = ledger(&[]);
+        assert_eq!(ledger.check(&limits, 1_005_000_000), LimitCheck::Within);
+        assert_eq!(
+            ledger.check(&limits, 1_005_000_001),
+            LimitCheck::Exceeded("exceeds the per-transaction limit of 1.005 SOL".to_string())
+        );
+
+        let restricted = LimitsConfig {
+            restricted: true,
+            ..limits
+        };
+        assert!(matches!(
+            ledger.check(&restricted, 2 * LAMPORTS_PER_SOL),
+            LimitCheck::Blocked(_)
+        ));
+    }
+
+    #[test]
+    fn checks_the_daily_limit_over_the_last_day() {
+        let limits = LimitsConfig {
+            per_day: Some(1.0),
+            ..LimitsConfig::default()
+        };
+        let ledger = ledger(&[
+            (60, LAMPORTS_PER_SOL / 2),
+            // Out of the window, so not counted
+            (DAY_SECS + 60, LAMPORTS_PER_SOL),
+        ]);
+        assert_eq!(ledger.spent_last_day(), LAMPORTS_PER_SOL / 2);
+        assert_eq!(
+            ledger.check(&limits, LAMPORTS_PER_SOL / 2),
+            LimitCheck::Within
+        );
+        assert_eq!(
+            ledger.check(&limits, LAMPORTS_PER_SOL / 2 + 1),
+            LimitCheck::Exceeded("exceeds the daily limit of 1 SOL".to_string())
+        );
+    }
+
+    #[test]
+    fn sends_from_other_processes_count_toward_the_daily_limit() {
+        let dir = std::env::temp_dir().join(format!(
+            "solace-test-{}",
+            solana_sdk::pubkey::Pubkey::new_unique()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("spending.json");
+        let open = || SpendingLedger {
+            path: Some(path.clone()),
+            records: read_records(&path).unwrap(),
+        };
+        // Both loaded before either sent, like the TUI and the daemon
+        let mut tui = open();
+        let mut daemon = open();
+        tui.record(LAMPORTS_PER_SOL).unwrap();
+        daemon.record(2 * LAMPORTS_PER_SOL).unwrap();
+        assert_eq!(tui.spent_last_day(), 3 * LAMPORTS_PER_SOL);
+        assert_eq!(open().spent_last_day(), 3 * LAMPORTS_PER_SOL);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,180 @@
+use solana_sdk::hash::{hashv, Hash};
+use std::time::{Duration, Instant};
+use zeroize::Zeroize;
+
+const PIN_DOMAIN: &[u8] = b"solace-session-pin";
+
+/// Wrong PINs allowed before each further try has to wait
+const FREE_ATTEMPTS: u32 = 3;
+
+/// The longest wait between tries, which the wait doubles up to
+const MAX_DELAY: Duration = Duration::from_secs(300);
+
+/// How a try at the PIN went
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Unlock {
+    Unlocked,
+    /// The PIN didn't match, and the next try has to wait this long if at all
+    Wrong(Option<Duration>),
+    /// Too many wrong PINs; the PIN wasn't checked, and trying again has to
+    /// wait this long
+    TooSoon(Duration),
+}
+
+/// Inactivity tracking and PIN check for the session auto-lock
+pub struct SessionLock {
+    timeout: Option<Duration>,
+    pin_hash: Option<Hash>,
+    /// Random for the session, so the hash in memory can't be looked up
+    salt: [u8; 32],
+    failures: u32,
+    retry_at: Option<Instant>,
+    last_activity: Instant,
+    locked: bool,
+    /// PIN typed on the lock screen
+    pub pin_input: String,
+}
+
+impl SessionLock {
+    /// Auto-lock after `timeout` of inactivity, unlocking with `pin`. Without
+    /// a timeout the session never locks.
+    pub fn new(timeout: Option<Duration>, pin: Option<&str>) -> Self {
+        let salt: [u8; 32] = rand::random();
+        Self {
+            timeout,
+            pin_hash: pin.map(|pin| hash_pin(&salt, pin)),
+            salt,
+            failures: 0,
+            retry_at: None,
+            last_activity: Instant::now(),
+            locked: false,
+            pin_input: String::new(),
+        }
+    }
+
+    pub fn disabled() -> Self {
+        Self::new(None, None)
+    }
+
+    /// Record user activity, postponing the auto-lock
+    pub fn touch(&mut self) {
+        self.last_activity = Instant::now();
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+
+    /// Whether the inactivity timeout has elapsed on an unlocked session
+    pub fn should_lock(&self) -> bool {
+        match self.timeout {
+            Some(timeout) => !self.locked && self.last_activity.elapsed() >= timeout,
+            None => false,
+        }
+    }
+
+    pub fn lock(&mut self) {
+        self.locked = true;
+        self.pin_input.zeroize();
+    }
+
+    /// Check the typed PIN, unlocking on a match. After a few wrong ones,
+    /// each try has to wait twice as long as the last.
+    pub fn try_unlock(&mut self) -> Unlock {
+        if let Some(wait) = self
+            .retry_at
+            .and_then(|at| at.checked_duration_since(Instant::now()))
+        {
+            self.pin_input.zeroize();
+            return Unlock::TooSoon(wait);
+        }
+        let matches = self.pin_hash == Some(hash_pin(&self.salt, &self.pin_input));
+        self.pin_input.zeroize();
+        if matches {
+            self.locked = false;
+            self.failures = 0;
+            self.retry_at = None;
+            self.touch();
+            return Unlock::Unlocked;
+        }
+
+        self.failures += 1;
+        let wait = (self.failures >= FREE_ATTEMPTS).then(|| {
+            let doublings = (self.failures - FREE_ATTEMPTS).min(16);
+            (Duration::from_secs(1) * 2u32.pow(doublings)).min(MAX_DELAY)
+        });
+        self.retry_at = wait.map(|wait| Instant::now() + wait);
+        Unlock::Wrong(wait)
+    }
+}
+
+fn hash_pin(salt: &[u8], pin: &str) -> Hash {
+    hashv(&[PIN_DOMAIN, salt, pin.as_bytes()])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn locked(pin: &str) -> SessionLock {
+        let mut lock = SessionLock::new(Some(Duration::from_secs(60)), Some(pin));
+        lock.lock();
+        lock
+    }
+
+    fn attempt(lock: &mut SessionLock, pin: &str) -> Unlock {
+        lock.pin_input.push_str(pin);
+        lock.try_unlock()
+    }
+
+    #[test]
+    fn unlocks_with_the_pin() {
+        let mut lock = locked("1234");
+        assert!(lock.is_locked());
+        assert_eq!(attempt(&mut lock, "4321"), Unlock::Wrong(None));
+        assert!(lock.is_locked());
+        assert!(lock.pin_input.is_empty());
+        assert_eq!(attempt(&mut lock, "1234"), Unlock::Unlocked);
+        assert!(!lock.is_locked());
+
+        // Salted per session
+        assert_ne!(locked("1234").pin_hash, lock.pin_hash);
+    }
+
+    #[test]
+    fn waits_longer_after_each_wrong_pin() {
+        let mut lock = locked("1234");
+        for _ in 1..FREE_ATTEMPTS {
+            assert_eq!(attempt(&mut lock, "0000"), Unlock::Wrong(None));
+        }
+        assert_eq!(
+            attempt(&mut lock, "0000"),
+            Unlock::Wrong(Some(Duration::from_secs(1)))
+        );
+        // Not even the right PIN is checked until the wait is over
+        assert!(matches!(attempt(&mut lock, "1234"), Unlock::TooSoon(_)));
+        assert!(lock.is_locked());
+
+        lock.retry_at = None;
+        assert_eq!(
+            attempt(&mut lock, "0000"),
+            Unlock::Wrong(Some(Duration::from_secs(2)))
+        );
+        lock.failures = 100;
+        lock.retry_at = None;
+        assert_eq!(attempt(&mut lock, "0000"), Unlock::Wrong(Some(MAX_DELAY)));
+
+        lock.retry_at = None;
+        assert_eq!(attempt(&mut lock, "1234"), Unlock::Unlocked);
+        lock.lock();
+        assert_eq!(attempt(&mut lock, "0000"), Unlock::Wrong(None));
+    }
+
+    #[test]
+    fn never_unlocks_without_a_pin() {
+        let mut lock = SessionLock::disabled();
+        lock.lock();
+        assert_eq!(attempt(&mut lock, ""), Unlock::Wrong(None));
+        assert!(lock.is_locked());
+    }
+}
@@ -0,0 +1,279 @@
+//! The debug log: RPC requests, sends and their statuses, errors and what
+//! the TUI does, recorded with `tracing`. Lines go to a file per day under
+//! `<data dir>/solace/logs`, keeping the last few days, and the latest are
+//! kept in memory for the log view.
+
+use crate::{config::LogConfig, history::format_datetime};
+use anyhow::{Context, Result};
+use std::{
+    collections::VecDeque,
+    fmt::{self, Write as _},
+    fs::{self, File, OpenOptions},
+    io::Write as _,
+    path::PathBuf,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tracing::{
+    field::{Field, Visit},
+    level_filters::LevelFilter,
+    span, Event, Level, Metadata, Subscriber,
+};
+
+/// Lines kept in memory for the log view
+const BUFFER_LINES: usize = 1000;
+
+/// The latest log lines, oldest first, shared with the log view
+#[derive(Debug, Clone, Default)]
+pub struct LogBuffer(Arc<Mutex<VecDeque<String>>>);
+
+impl LogBuffer {
+    pub fn lines(&self) -> Vec<String> {
+        self.lock().iter().cloned().collect()
+    }
+
+    fn push(&self, line: String) {
+        let mut lines = self.lock();
+        if lines.len() == BUFFER_LINES {
+            lines.pop_front();
+        }
+        lines.push_back(line);
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, VecDeque<String>> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Log files named by the day they cover, a new one started at midnight
+/// (UTC)
+struct DailyFile {
+    dir: PathBuf,
+    keep_days: usize,
+    /// The day the open file is for, as days since the epoch
+    day: i64,
+    file: Option<File>,
+}
+
+impl DailyFile {
+    fn write(&mut self, timestamp: i64, line: &str) {
+        let day = timestamp.div_euclid(86_400);
+        if self.file.is_none() || day != self.day {
+            self.day = day;
+            self.file = self.open(timestamp).ok();
+        }
+        if let Some(ref mut file) = self.file {
+            // There's nowhere to report a failed write to
+            let _ = writeln!(file, "{}", line);
+        }
+    }
+
+    fn open(&self, timestamp: i64) -> Result<File> {
+        let path = self.dir.join(file_name(timestamp));
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("Failed to open log file: {}", path.display()))?;
+        self.prune();
+        Ok(file)
+    }
+
+    /// Remove all but the newest `keep_days` files
+    fn prune(&self) {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return;
+        };
+        let mut logs: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| name.starts_with("solace-") && name.ends_with(".log"))
+            })
+            .collect();
+        // The dates sort as they're named
+        logs.sort();
+        let excess = logs.len().saturating_sub(self.keep_days);
+        for path in logs.into_iter().take(excess) {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// `solace-<date>.log`
+fn file_name(timestamp: i64) -> String {
+    let mut date = format_datetime(timestamp);
+    date.truncate(10);
+    format!("solace-{}.log", date)
+}
+
+/// Writes events as lines to the log file and the buffer. Spans aren't
+/// used, so they're accepted and left out.
+struct Logger {
+    level: LevelFilter,
+    file: Option<Mutex<DailyFile>>,
+    buffer: LogBuffer,
+}
+
+impl Subscriber for Logger {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        // Dependencies' chatter only when something went wrong
+        let ours = metadata.target().starts_with("solace");
+        *metadata.level() <= self.level && (ours || *metadata.level() <= Level::WARN)
+    }
+
+    fn max_level_hint(&self) -> Option<LevelFilter> {
+        Some(self.level)
+    }
+
+    fn new_span(&self, _: &span::Attributes<'_>) -> span::Id {
+        span::Id::from_u64(1)
+    }
+
+    fn record(&self, _: &span::Id, _: &span::Record<'_>) {}
+
+    fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs() as i64);
+        let line = format_line(timestamp, event);
+        if let Some(ref file) = self.file {
+            file.lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .write(timestamp, &line);
+        }
+        self.buffer.push(line);
+    }
+
+    fn enter(&self, _: &span::Id) {}
+
+    fn exit(&self, _: &span::Id) {}
+}
+
+/// `<date> <time> <LEVEL> <target>: <message> <field>=<value>...`
+fn format_line(timestamp: i64, event: &Event<'_>) -> String {
+    let metadata = event.metadata();
+    let mut fields = Fields::default();
+    event.record(&mut fields);
+    format!(
+        "{} {:<5} {}: {}{}",
+        format_datetime(timestamp),
+        metadata.level(),
+        metadata.target(),
+        fields.message,
+        fields.rest
+    )
+}
+
+/// An event's message, and its other fields as ` name=value`
+#[derive(Default)]
+struct Fields {
+    message: String,
+    rest: String,
+}
+
+impl Visit for Fields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message = value.to_string();
+        } else {
+            let _ = write!(self.rest, " {}={}", field.name(), value);
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.message = format!("{:?}", value);
+        } else {
+            let _ = write!(self.rest, " {}={:?}", field.name(), value);
+        }
+    }
+}
+
+/// Start logging as configured, returning the buffer for the log view.
+/// Without a data directory the lines are only kept in memory.
+pub fn init(config: &LogConfig) -> Result<LogBuffer> {
+    let level = LevelFilter::from_str(&config.level)
+        .map_err(|_| anyhow::anyhow!("Unknown log level: {}", config.level))?;
+    let buffer = LogBuffer::default();
+    let file = match crate::config::data_dir() {
+        Ok(dir) if level != LevelFilter::OFF => {
+            let dir = dir.join("logs");
+            fs::create_dir_all(&dir)
+                .with_context(|| format!("Failed to create log directory: {}", dir.display()))?;
+            Some(Mutex::new(DailyFile {
+                dir,
+                keep_days: config.keep_days.max(1),
+                day: 0,
+                file: None,
+            }))
+        }
+        _ => None,
+    };
+    let logger = Logger {
+        level,
+        file,
+        buffer: buffer.clone(),
+    };
+    tracing::subscriber::set_global_default(logger).context("Logging was already started")?;
+    Ok(buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::pubkey::Pubkey;
+
+    #[test]
+    fn writes_a_file_per_day_and_keeps_the_last_ones() {
+        let dir = std::env::temp_dir().join(format!("solace-test-{}", Pubkey::new_unique()));
+        fs::create_dir_all(&dir).unwrap();
+        let day = 1_727_000_000;
+        let mut file = DailyFile {
+            dir: dir.clone(),
+            keep_days: 2,
+            day: 0,
+            file: None,
+        };
+        for days in 0..3 {
+            file.write(day + days * 86_400, "line");
+        }
+        let contents = fs::read_to_string(dir.join(file_name(day + 2 * 86_400))).unwrap();
+        let mut names: Vec<String> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(contents, "line\n");
+        assert_eq!(
+            names,
+            [file_name(day + 86_400), file_name(day + 2 * 86_400)]
+        );
+    }
+
+    #[test]
+    fn formats_events_with_their_fields() {
+        let buffer = LogBuffer::default();
+        let logger = Logger {
+            level: LevelFilter::INFO,
+            file: None,
+            buffer: buffer.clone(),
+        };
+        tracing::subscriber::with_default(logger, || {
+            tracing::info!(signature = "5abc", slot = 7, "Sent transaction");
+            tracing::debug!("Too detailed");
+            tracing::info!(target: "hyper", "Connection closed");
+            tracing::warn!(target: "hyper", "Connection reset");
+        });
+        let lines = buffer.lines();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0]
+            .ends_with(" INFO  solace::logging::tests: Sent transaction signature=5abc slot=7"));
+        assert!(lines[1].ends_with(" WARN  hyper: Connection reset"));
+    }
+}
@@ -0,0 +1,343 @@
+//! Address lookup tables: lists of up to 256 addresses kept on chain, which
+//! a v0 transaction points into with one-byte indexes instead of carrying
+//! every address in full. Tables the wallet is the authority of can be
+//! created, extended, and deactivated then closed to get their rent back.
+
+use crate::rpc::RpcApi;
+use crate::sender;
+use anyhow::{anyhow, bail, Context, Result};
+#[allow(deprecated)]
+use solana_sdk::address_lookup_table::{
+    self,
+    instruction::{
+        close_lookup_table, create_lookup_table, deactivate_lookup_table, extend_lookup_table,
+    },
+    state::{estimate_last_valid_slot, AddressLookupTable, LookupTableMeta},
+    AddressLookupTableAccount,
+};
+use solana_sdk::{
+    account::Account,
+    clock::Slot,
+    commitment_config::CommitmentConfig,
+    hash::Hash,
+    instruction::Instruction,
+    message::{v0, Message, VersionedMessage},
+    pubkey::Pubkey,
+    signature::{Signature, Signer},
+};
+
+pub use solana_sdk::address_lookup_table::state::LOOKUP_TABLE_MAX_ADDRESSES as MAX_ADDRESSES;
+
+/// Addresses added per transaction when extending a table, as many as fit
+/// in one with room to spare
+pub const EXTEND_BATCH: usize = 20;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LookupTable {
+    pub address: Pubkey,
+    pub lamports: u64,
+    /// Who signs to change it; nobody once it's frozen
+    pub authority: Option<Pubkey>,
+    /// When it was deactivated, if it was
+    pub deactivation_slot: Option<Slot>,
+    pub last_extended_slot: Slot,
+    pub addresses: Vec<Pubkey>,
+}
+
+impl LookupTable {
+    /// The table as a message compiles against it
+    pub fn lookup_account(&self) -> AddressLookupTableAccount {
+        AddressLookupTableAccount {
+            key: self.address,
+            addresses: self.addresses.clone(),
+        }
+    }
+
+    /// The first slot it can be closed in, once it's deactivated. Until the
+    /// deactivation slot leaves the recent slot hashes transactions can
+    /// still load from it; skipped slots make this an upper bound.
+    pub fn closable_at(&self) -> Option<Slot> {
+        self.deactivation_slot
+            .map(|slot| estimate_last_valid_slot(slot).saturating_add(1))
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "address": self.address.to_string(),
+            "lamports": self.lamports,
+            "authority": self.authority.map(|authority| authority.to_string()),
+            "deactivation_slot": self.deactivation_slot,
+            "last_extended_slot": self.last_extended_slot,
+            "addresses": self.addresses.iter().map(Pubkey::to_string).collect::<Vec<_>>(),
+        })
+    }
+}
+
+/// Read the table stored in `account`
+pub fn parse(address: Pubkey, account: &Account) -> Result<LookupTable> {
+    if account.owner != address_lookup_table::program::id() {
+        bail!("{} is not a lookup table", address);
+    }
+    let table = AddressLookupTable::deserialize(&account.data)
+        .map_err(|e| anyhow!("{} is not a lookup table: {}", address, e))?;
+    Ok(LookupTable {
+        address,
+        lamports: account.lamports,
+        authority: table.meta.authority,
+        deactivation_slot: Some(table.meta.deactivation_slot).filter(|&slot| slot != Slot::MAX),
+        last_extended_slot: table.meta.last_extended_slot,
+        addresses: table.addresses.to_vec(),
+    })
+}
+
+/// Look up the table at `address`
+pub fn fetch(rpc_client: &dyn RpcApi, address: &Pubkey) -> Result<LookupTable> {
+    let account = rpc_client
+        .get_account(address)
+        .with_context(|| format!("Failed to fetch lookup table {}", address))?;
+    parse(*address, &account)
+}
+
+/// A table with `authority` holding `addresses`, as the program stores it
+pub fn account(authority: Option<Pubkey>, addresses: &[Pubkey]) -> Account {
+    let table = AddressLookupTable {
+        meta: LookupTableMeta {
+            authority,
+            ..LookupTableMeta::default()
+        },
+        addresses: addresses.into(),
+    };
+    Account {
+        lamports: 1,
+        data: table.serialize_for_tests().unwrap_or_default(),
+        owner: address_lookup_table::program::id(),
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+/// Create an empty table with `signer` as its authority, paying its rent
+pub fn create(rpc_client: &dyn RpcApi, signer: &dyn Signer) -> Result<(Pubkey, Signature)> {
+    let wallet = signer.pubkey();
+    // Its address is derived from a slot the program must find among the
+    // recent ones, so one that's finalized
+    let recent_slot = rpc_client
+        .get_slot_with_commitment(CommitmentConfig::finalized())
+        .context("Failed to fetch a recent slot")?;
+    let (instruction, address) = create_lookup_table(wallet, wallet, recent_slot);
+    let signature = sender::send_instructions(rpc_client, &[signer], &[instruction])
+        .context("Failed to create the lookup table")?;
+    Ok((address, signature))
+}
+
+/// Add `addresses` to `table`, `EXTEND_BATCH` per transaction, with
+/// `signer` paying for the extra space. Addresses it already holds are
+/// skipped; the signatures are in order, one per transaction.
+pub fn extend(
+    rpc_client: &dyn RpcApi,
+    signer: &dyn Signer,
+    table: &LookupTable,
+    addresses: &[Pubkey],
+) -> Result<Vec<Signature>> {
+    check_changeable(signer, table)?;
+    let mut new: Vec<Pubkey> = Vec::new();
+    for address in addresses {
+        if !table.addresses.contains(address) && !new.contains(address) {
+            new.push(*address);
+        }
+    }
+    if new.is_empty() {
+        bail!("Lookup table {} already holds every address", table.address);
+    }
+    if table.addresses.len() + new.len() > MAX_ADDRESSES {
+        bail!(
+            "Lookup table {} holds {} addresses; it can't take {} more, only {}",
+            table.address,
+            table.addresses.len(),
+            new.len(),
+            MAX_ADDRESSES - table.addresses.len()
+        );
+    }
+    let wallet = signer.pubkey();
+    new.chunks(EXTEND_BATCH)
+        .map(|batch| {
+            let instruction =
+                extend_lookup_table(table.address, wallet, Some(wallet), batch.to_vec());
+            sender::send_instructions(rpc_client, &[signer], &[instruction])
+                .context("Failed to extend the lookup table")
+        })
+        .collect()
+}
+
+/// Stop `table` from being extended or used, the first step to closing it
+pub fn deactivate(
+    rpc_client: &dyn RpcApi,
+    signer: &dyn Signer,
+    table: &LookupTable,
+) -> Result<Signature> {
+    check_changeable(signer, table)?;
+    let instruction = deactivate_lookup_table(table.address, signer.pubkey());
+    sender::send_instructions(rpc_client, &[signer], &[instruction])
+        .context("Failed to deactivate the lookup table")
+}
+
+/// Delete a deactivated `table`, sending its rent to `recipient`
+pub fn close(
+    rpc_client: &dyn RpcApi,
+    signer: &dyn Signer,
+    table: &LookupTable,
+    recipient: &Pubkey,
+) -> Result<Signature> {
+    check_authority(signer, table)?;
+    let Some(closable_at) = table.closable_at() else {
+        bail!(
+            "Lookup table {} is still active; deactivate it first",
+            table.address
+        );
+    };
+    let slot = rpc_client
+        .get_slot_with_commitment(CommitmentConfig::finalized())
+        .context("Failed to fetch the current slot")?;
+    if slot < closable_at {
+        bail!(
+            "Lookup table {} was only just deactivated; it can be closed in about {} slots",
+            table.address,
+            closable_at - slot
+        );
+    }
+    let instruction = close_lookup_table(table.address, signer.pubkey(), *recipient);
+    sender::send_instructions(rpc_client, &[signer], &[instruction])
+        .context("Failed to close the lookup table")
+}
+
+fn check_authority(signer: &dyn Signer, table: &LookupTable) -> Result<()> {
+    match table.authority {
+        None => bail!("Lookup table {} is frozen", table.address),
+        Some(authority) if authority != signer.pubkey() => bail!(
+            "The authority of lookup table {} is {}, not this wallet",
+            table.address,
+            authority
+        ),
+        Some(_) => Ok(()),
+    }
+}
+
+fn check_changeable(signer: &dyn Signer, table: &LookupTable) -> Result<()> {
+    check_authority(signer, table)?;
+    if table.deactivation_slot.is_some() {
+        bail!("Lookup table {} is deactivated", table.address);
+    }
+    Ok(())
+}
+
+/// The message for `instructions` paid for by `payer`: a v0 message loading
+/// addresses from `tables` if that makes it smaller, otherwise a legacy one.
+/// Signers and programs can't be loaded, so a transaction only gains from a
+/// table holding several of its other accounts.
+pub fn compile(
+    payer: &Pubkey,
+    instructions: &[Instruction],
+    recent_blockhash: Hash,
+    tables: &[AddressLookupTableAccount],
+) -> Result<VersionedMessage> {
+    let legacy = VersionedMessage::Legacy(Message::new_with_blockhash(
+        instructions,
+        Some(payer),
+        &recent_blockhash,
+    ));
+    if tables.is_empty() {
+        return Ok(legacy);
+    }
+    let v0 = VersionedMessage::V0(
+        v0::Message::try_compile(payer, instructions, tables, recent_blockhash)
+            .context("Failed to compile the transaction with its lookup tables")?,
+    );
+    Ok(
+        if bincode::serialized_size(&v0)? < bincode::serialized_size(&legacy)? {
+            v0
+        } else {
+            legacy
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::{MockRpc, MOCK_SLOT};
+    use solana_sdk::signature::Keypair;
+    #[allow(deprecated)]
+    use solana_sdk::{
+        address_lookup_table::instruction::derive_lookup_table_address, system_instruction,
+    };
+
+    #[test]
+    fn uses_a_table_only_when_it_saves_space() {
+        let payer = Pubkey::new_unique();
+        let recipients: Vec<Pubkey> = (0..4).map(|_| Pubkey::new_unique()).collect();
+        let tables = [AddressLookupTableAccount {
+            key: Pubkey::new_unique(),
+            addresses: recipients.clone(),
+        }];
+        let transfer = |recipient| system_instruction::transfer(&payer, recipient, 1);
+
+        let one = [transfer(&recipients[0])];
+        let message = compile(&payer, &one, Hash::default(), &tables).unwrap();
+        assert!(matches!(message, VersionedMessage::Legacy(_)));
+
+        let all: Vec<Instruction> = recipients.iter().map(transfer).collect();
+        let message = compile(&payer, &all, Hash::default(), &tables).unwrap();
+        let VersionedMessage::V0(ref message) = message else {
+            panic!("Expected a v0 message");
+        };
+        assert_eq!(message.address_table_lookups[0].writable_indexes.len(), 4);
+        assert!(!message.account_keys.contains(&recipients[0]));
+    }
+
+    #[test]
+    fn manages_the_wallets_tables() {
+        let rpc = MockRpc::default();
+        let wallet = Keypair::new();
+        let (address, _) = create(&rpc, &wallet).unwrap();
+        assert_eq!(
+            address,
+            derive_lookup_table_address(&wallet.pubkey(), MOCK_SLOT).0
+        );
+
+        let held = Pubkey::new_unique();
+        rpc.set_account(address, account(Some(wallet.pubkey()), &[held]));
+        let table = fetch(&rpc, &address).unwrap();
+        assert_eq!(table.authority, Some(wallet.pubkey()));
+        assert_eq!(table.deactivation_slot, None);
+        assert_eq!(table.addresses, [held]);
+
+        // The one it holds and the repeat are left out
+        let mut addresses: Vec<Pubkey> = (0..44).map(|_| Pubkey::new_unique()).collect();
+        addresses.extend([held, addresses[0]]);
+        assert_eq!(extend(&rpc, &wallet, &table, &addresses).unwrap().len(), 3);
+        assert_eq!(rpc.sent().len(), 4);
+        assert!(extend(&rpc, &wallet, &table, &[held]).is_err());
+        let too_many: Vec<Pubkey> = (0..MAX_ADDRESSES).map(|_| Pubkey::new_unique()).collect();
+        assert!(extend(&rpc, &wallet, &table, &too_many).is_err());
+        assert!(deactivate(&rpc, &Keypair::new(), &table).is_err());
+        let frozen = LookupTable {
+            authority: None,
+            ..table.clone()
+        };
+        assert!(deactivate(&rpc, &wallet, &frozen).is_err());
+
+        assert!(close(&rpc, &wallet, &table, &wallet.pubkey()).is_err());
+        let deactivating = LookupTable {
+            deactivation_slot: Some(MOCK_SLOT - 10),
+            ..table.clone()
+        };
+        assert!(close(&rpc, &wallet, &deactivating, &wallet.pubkey()).is_err());
+        assert!(extend(&rpc, &wallet, &deactivating, &addresses).is_err());
+        let deactivated = LookupTable {
+            deactivation_slot: Some(MOCK_SLOT - 1_000),
+            ..table
+        };
+        close(&rpc, &wallet, &deactivated, &wallet.pubkey()).unwrap();
+        assert_eq!(rpc.sent().len(), 5);
+    }
+}
@@ -1,245 +1,887 @@
-use anyhow::{Context, Result};
-use clap::Parser;
+mod app;
+mod clipboard;
+mod hyperlink;
+mod input;
+mod lock;
+mod plain;
+mod plugin;
+mod session;
+mod toast;
+
+use crate::{
+    app::{App, Startup, WalletInfo, READ_ONLY},
+    lock::SessionLock,
+    plain::PlainMode,
+    session::SessionState,
+};
+use anyhow::{bail, Context, Result};
+use clap::{Parser, Subcommand};
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent},
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use qrcode::{render::unicode, QrCode};
-use ratatui::{
-    backend::CrosstermBackend,
-    layout::{Alignment, Constraint, Direction, Layout},
-    style::{Color, Modifier, Style},
-    text::{Line, Span},
-    widgets::{Block, Borders, List, ListItem, Paragraph},
-    Frame, Terminal,
+use ratatui::{backend::CrosstermBackend, Terminal};
+use solace::{
+    amount::AmountFormat,
+    audit,
+    blocklist::Blocklist,
+    cli::{self, GovernanceCommand, LookupTableCommand, NonceCommand, SeededCommand, TokenCommand},
+    config::{self, Config},
+    daemon,
+    explorer::Cluster,
+    grind::GrindPattern,
+    i18n,
+    idl::IdlRegistry,
+    inspector,
+    keymap::Keymap,
+    keys::KeypairSource,
+    limits::SpendingLedger,
+    logging::{self, LogBuffer},
+    memlock, message,
+    output::{Output, Refused},
+    pay,
+    report::ReportFormat,
+    rpc,
+    sender::{self, Guard, Pipeline},
+    task,
+    theme::Theme,
+    validator::{self, LocalValidator},
+    wallet::{self, balance_json, is_mainnet, resolve_rpc_url, transfer_pipeline},
 };
-use solana_client::rpc_client::RpcClient;
-#[allow(deprecated)]
-use solana_sdk::system_instruction;
 use solana_sdk::{
-    commitment_config::CommitmentConfig,
     native_token::LAMPORTS_PER_SOL,
     pubkey::Pubkey,
-    signature::{Keypair, Signature, Signer},
-    transaction::Transaction,
-};
-use std::{
-    fs::File,
-    io::{self, BufReader},
-    path::PathBuf,
-    str::FromStr,
-    sync::Arc,
+    signature::{Signature, Signer},
 };
+use std::{io, path::PathBuf, process::ExitCode, str::FromStr, sync::Arc, time::Duration};
+use zeroize::Zeroizing;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
-    /// Path to keypair file (defaults to ~/.config/solana/id.json)
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Keypair file path, '-' to read it from stdin, or 'prompt:' to enter a
+    /// seed phrase (defaults to $FURRYBAIT_KEYPAIR, then ~/.config/solana/id.json)
     #[arg(short, long)]
-    keypair: Option<PathBuf>,
+    keypair: Option<String>,
 
-    /// Cluster to connect to (mainnet/testnet/devnet or custom RPC URL)
-    #[arg(short, long, default_value = "mainnet")]
-    cluster: String,
-}
+    /// Keypair file that pays the fees of transfers (and any Jito tip)
+    /// instead of the wallet, which then only signs for the SOL it sends
+    #[arg(long, value_name = "KEYPAIR")]
+    fee_payer: Option<String>,
+
+    /// Follow the wallet without being able to sign or send anything; the
+    /// keypair is only read for its address, and `--keypair` may also be
+    /// just the address
+    #[arg(long, conflicts_with = "fee_payer")]
+    read_only: bool,
 
-#[derive(Debug, Clone)]
-enum AppState {
-    Home,
-    Wallet,
-    Send,
-    Receive,
-    Transactions,
-    Settings,
+    /// Cluster to connect to (mainnet/testnet/devnet or custom RPC URL);
+    /// defaults to the one in the config, or mainnet
+    #[arg(short, long)]
+    cluster: Option<String>,
+
+    /// Yellowstone gRPC endpoint to follow the wallet over instead of
+    /// polling the RPC (defaults to `grpc.url` in the config)
+    #[arg(long, value_name = "URL")]
+    grpc_url: Option<String>,
+
+    /// Start solana-test-validator for the session and connect to it; it's
+    /// stopped on exit, and its ledger kept in the data directory
+    #[arg(long, conflicts_with = "cluster")]
+    start_local_validator: bool,
+
+    /// SOL to airdrop to the wallet once the local validator is up
+    #[arg(long, value_name = "SOL", requires = "start_local_validator")]
+    airdrop: Option<f64>,
+
+    /// Path to config file (defaults to ~/.config/solace/config.toml)
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// Allow core dumps and don't lock key memory into RAM
+    #[arg(long)]
+    no_memory_hardening: bool,
+
+    /// Import a base58 private key (Phantom/Solflare export format), save it
+    /// as a keypair file at the given path and exit
+    #[arg(long, value_name = "OUTFILE")]
+    import_key: Option<PathBuf>,
+
+    /// BIP44 derivation path for seed phrase keypairs, e.g. "m/44'/501'/0'/0'"
+    #[arg(long, conflicts_with = "account_index")]
+    derivation_path: Option<String>,
+
+    /// Use account m/44'/501'/<N>'/0' of a seed phrase keypair
+    #[arg(long, value_name = "N")]
+    account_index: Option<u32>,
+
+    /// Number of seed phrase accounts listed to choose from (defaults to
+    /// `accounts.count` in the config, or 10)
+    #[arg(long, value_name = "N")]
+    browse_accounts: Option<u32>,
+
+    /// Draw without colors (also set by a non-empty $NO_COLOR)
+    #[arg(long)]
+    no_color: bool,
+
+    /// Only draw ASCII characters: no box drawing, block cursor or emoji
+    #[arg(long)]
+    ascii: bool,
+
+    /// How the address, balance, send and history commands print results
+    /// and errors
+    #[arg(long, value_enum, global = true, default_value = "text")]
+    output: Output,
+
+    /// Only simulate transactions, showing whether they would have
+    /// succeeded, and never send them
+    #[arg(long, global = true)]
+    dry_run: bool,
 }
 
-#[derive(Debug, Clone)]
-struct SendState {
-    recipient: String,
-    amount: String,
-    input_mode: SendInputMode,
-    status: Option<String>,
-    error: Option<String>,
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Search for a keypair whose address starts and/or ends with the given text
+    Grind {
+        #[arg(long, default_value = "")]
+        starts_with: String,
+
+        #[arg(long, default_value = "")]
+        ends_with: String,
+
+        /// Match letters in any case (much faster)
+        #[arg(long)]
+        ignore_case: bool,
+
+        /// Where to save the keypair (defaults to ./<address>.json)
+        #[arg(short, long)]
+        outfile: Option<PathBuf>,
+    },
+    /// Write the wallet's decoded transaction history as CSV or JSON
+    History {
+        /// File to write (prints to stdout if not given)
+        #[arg(long, value_name = "PATH")]
+        export: Option<PathBuf>,
+
+        /// Report format (defaults to the export file's extension, or CSV)
+        #[arg(long, value_enum)]
+        format: Option<ReportFormat>,
+
+        /// Only the newest N transactions, fetching no more than needed
+        #[arg(long, value_name = "N")]
+        limit: Option<usize>,
+
+        /// Include staking rewards for the last N completed epochs, 0 for
+        /// none (defaults to `staking.reward_epochs`, 10)
+        #[arg(long, value_name = "N")]
+        reward_epochs: Option<u64>,
+    },
+    /// Show the rewards paid to the wallet's stake accounts per epoch, with
+    /// the running total
+    Rewards {
+        /// How many of the latest completed epochs (defaults to
+        /// `staking.reward_epochs`, 10)
+        #[arg(long, value_name = "N")]
+        epochs: Option<u64>,
+    },
+    /// List the signatures the wallet has made from the audit log and check
+    /// that it hasn't been changed since
+    Audit {
+        /// Write the log to a file instead, as CSV or as JSON for `.json`
+        #[arg(long, value_name = "FILE")]
+        export: Option<PathBuf>,
+    },
+    /// Print the wallet's address
+    Address,
+    /// Print the wallet's balance in SOL
+    Balance,
+    /// Send SOL, with the same checks as the Send screen
+    Send {
+        /// Recipient address
+        #[arg(long)]
+        to: String,
+
+        /// Amount in SOL
+        #[arg(long)]
+        amount: f64,
+
+        /// Send without asking for confirmation
+        #[arg(long)]
+        yes: bool,
+
+        /// Only sign, with the nonce of `--nonce` instead of a recent
+        /// blockhash, and print the transaction for `broadcast` instead of
+        /// sending it. Nothing is fetched from the cluster, so this works
+        /// offline.
+        #[arg(long, requires_all = ["nonce", "blockhash"])]
+        sign_only: bool,
+
+        /// Nonce account to sign with; the wallet must be its authority
+        #[arg(long, value_name = "ADDRESS", requires = "sign_only")]
+        nonce: Option<String>,
+
+        /// The nonce stored in it, as printed by `solace nonce show`
+        #[arg(long, value_name = "NONCE", requires = "sign_only")]
+        blockhash: Option<String>,
+
+        /// Wait for the transaction to be finalized, not just confirmed
+        #[arg(long, conflicts_with = "sign_only")]
+        finalized: bool,
+
+        /// Send through Jito's block engine, tipping this much SOL (mainnet
+        /// only; `[jito]` sets the default)
+        #[arg(long, value_name = "SOL", conflicts_with = "sign_only")]
+        tip: Option<f64>,
+
+        /// Priority fee in micro-lamports per compute unit, instead of the
+        /// one suggested from recent fees
+        #[arg(long, value_name = "MICRO_LAMPORTS", conflicts_with = "sign_only")]
+        priority_fee: Option<u64>,
+
+        /// Add a new reference key to the transfer and record it, as with
+        /// `[payments] reference`
+        #[arg(long, conflicts_with = "sign_only")]
+        reference: bool,
+    },
+    /// Send a transaction signed elsewhere, such as with `send --sign-only`,
+    /// after simulating it
+    Broadcast {
+        /// The transaction, base64 or base58 encoded
+        #[arg(required_unless_present = "file", conflicts_with = "file")]
+        transaction: Option<String>,
+
+        /// Read the transaction from a file instead
+        #[arg(long, value_name = "PATH")]
+        file: Option<PathBuf>,
+
+        /// Send without asking for confirmation
+        #[arg(long)]
+        yes: bool,
+
+        /// Wait for the transaction to be finalized, not just confirmed
+        #[arg(long)]
+        finalized: bool,
+    },
+    /// Add the wallet's signature to a transaction that other signers sign
+    /// too, printing it for them to add theirs
+    SignTransaction {
+        /// The transaction, base64 or base58 encoded
+        #[arg(required_unless_present = "file", conflicts_with = "file")]
+        transaction: Option<String>,
+
+        /// Read the transaction from a file instead
+        #[arg(long, value_name = "PATH")]
+        file: Option<PathBuf>,
+
+        /// Sign without asking for confirmation
+        #[arg(long)]
+        yes: bool,
+    },
+    /// Combine copies of a transaction signed by different signers into one,
+    /// printing it for `broadcast` or the signers still missing
+    MergeSignatures {
+        /// The copies, each base64 or base58 encoded or the path of a file
+        /// holding one
+        #[arg(required = true, num_args = 2..)]
+        transactions: Vec<String>,
+    },
+    /// Sign a message with the wallet key, as a Solana off-chain message, and
+    /// print the base58 signature
+    SignMessage {
+        /// Text to sign
+        #[arg(required_unless_present = "file", conflicts_with = "file")]
+        message: Option<String>,
+
+        /// Sign the contents of a file instead
+        #[arg(long, value_name = "PATH")]
+        file: Option<PathBuf>,
+    },
+    /// Check a signature made with `sign-message` or another wallet's
+    /// off-chain message signing; exits with an error if it isn't valid
+    VerifyMessage {
+        /// Text that was signed
+        #[arg(required_unless_present = "file", conflicts_with = "file")]
+        message: Option<String>,
+
+        /// Check the signature of a file's contents instead
+        #[arg(long, value_name = "PATH")]
+        file: Option<PathBuf>,
+
+        /// Address that signed it
+        #[arg(long)]
+        address: String,
+
+        /// Base58 signature
+        #[arg(long)]
+        signature: String,
+    },
+    /// Answer a Sign-In With Solana request from a dApp, printing the signed
+    /// response as JSON
+    SignIn {
+        /// The request, as JSON or the text of the sign-in message
+        #[arg(required_unless_present = "file", conflicts_with = "file")]
+        request: Option<String>,
+
+        /// Read the request from a file instead
+        #[arg(long, value_name = "PATH")]
+        file: Option<PathBuf>,
+
+        /// Sign in without asking for confirmation
+        #[arg(long)]
+        yes: bool,
+    },
+    /// Create, list and fund accounts derived from the wallet's address with
+    /// a seed (`create_account_with_seed`)
+    Seeded {
+        #[command(subcommand)]
+        command: SeededCommand,
+    },
+    /// Create, show, advance and withdraw from the wallet's durable nonce
+    /// accounts, which transactions signed offline use in place of a recent
+    /// blockhash
+    Nonce {
+        #[command(subcommand)]
+        command: NonceCommand,
+    },
+    /// Create, show, extend, deactivate and close address lookup tables
+    /// with the wallet as their authority
+    LookupTable {
+        #[command(subcommand)]
+        command: LookupTableCommand,
+    },
+    /// See the wallet's DAO memberships and proposals on SPL Governance
+    /// (Realms), and vote on them
+    Governance {
+        #[command(subcommand)]
+        command: GovernanceCommand,
+    },
+    /// Create SPL token mints, such as to test with on devnet
+    Token {
+        #[command(subcommand)]
+        command: TokenCommand,
+    },
+    /// Keep the wallet loaded and serve a JSON-RPC API for local tools on
+    /// 127.0.0.1, authenticated by a token written to the data directory
+    Daemon,
+    /// Rebuild a keypair from backup shares made in the export screen
+    Restore {
+        /// Share files (prompts for share lines if none are given)
+        shares: Vec<PathBuf>,
+
+        /// Where to save the keypair (defaults to ~/.config/solana/id.json if free)
+        #[arg(short, long)]
+        outfile: Option<PathBuf>,
+    },
 }
 
-#[derive(Debug, Clone, PartialEq)]
-enum SendInputMode {
-    EditingRecipient,
-    EditingAmount,
-    Confirming,
+impl Command {
+    /// Whether it signs with the wallet's keys or sends a transaction, which
+    /// read-only mode refuses
+    fn signs(&self) -> bool {
+        !matches!(
+            self,
+            Command::Grind { .. }
+                | Command::History { .. }
+                | Command::Rewards { .. }
+                | Command::Audit { .. }
+                | Command::Address
+                | Command::Balance
+                | Command::MergeSignatures { .. }
+                | Command::VerifyMessage { .. }
+                | Command::Restore { .. }
+                | Command::Seeded {
+                    command: SeededCommand::List | SeededCommand::Address { .. }
+                }
+                | Command::Nonce {
+                    command: NonceCommand::List | NonceCommand::Show { .. }
+                }
+                | Command::LookupTable {
+                    command: LookupTableCommand::Show { .. }
+                }
+                | Command::Governance {
+                    command: GovernanceCommand::Memberships | GovernanceCommand::Proposals { .. }
+                }
+                | Command::Token {
+                    command: TokenCommand::Mints
+                        | TokenCommand::Delegations
+                        | TokenCommand::Holders { .. }
+                }
+        )
+    }
 }
 
-impl Default for SendState {
-    fn default() -> Self {
-        Self {
-            recipient: String::new(),
-            amount: String::new(),
-            input_mode: SendInputMode::EditingRecipient,
-            status: None,
-            error: None,
+/// The blocklist, with the remote part fetched if it can be
+fn load_blocklist(config: &Config) -> Result<Blocklist> {
+    let mut blocklist = Blocklist::load(&config.blocklist)?;
+    if blocklist.has_remote() {
+        if let Err(e) = blocklist.refresh_remote() {
+            eprintln!("Warning: {:#}", e);
         }
     }
+    Ok(blocklist)
 }
 
-struct WalletInfo {
-    keypair: Arc<Keypair>,
-    address: Pubkey,
-    balance: f64,
+fn prompt_session_pin() -> Result<Zeroizing<String>> {
+    loop {
+        let pin = Zeroizing::new(rpassword::prompt_password("Choose a session PIN: ")?);
+        if pin.is_empty() {
+            eprintln!("The PIN must not be empty");
+            continue;
+        }
+        let repeated = Zeroizing::new(rpassword::prompt_password("Repeat the session PIN: ")?);
+        if repeated == pin {
+            return Ok(pin);
+        }
+        eprintln!("PINs do not match");
+    }
 }
 
-struct App {
-    state: AppState,
-    selected_menu_item: usize,
-    wallet: WalletInfo,
-    rpc_client: Arc<RpcClient>,
-    rpc_url: String,
-    send_state: SendState,
-    last_tx_signature: Option<Signature>,
+#[tokio::main]
+async fn main() -> ExitCode {
+    let args = Args::parse();
+    let output = args.output;
+    match run(args).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => output.fail(&e),
+    }
 }
 
-impl App {
-    fn new(wallet: WalletInfo, rpc_client: Arc<RpcClient>, rpc_url: String) -> Self {
-        Self {
-            state: AppState::Home,
-            selected_menu_item: 0,
-            wallet,
-            rpc_client,
-            rpc_url,
-            send_state: SendState::default(),
-            last_tx_signature: None,
-        }
+async fn run(args: Args) -> Result<()> {
+    let mut config = Config::load(args.config.as_deref())?;
+    if let Some(count) = args.browse_accounts {
+        config.accounts.count = count;
     }
-
-    async fn refresh_balance(&mut self) -> Result<()> {
-        let balance = self
-            .rpc_client
-            .get_balance(&self.wallet.address)
-            .context("Failed to fetch balance")?;
-        self.wallet.balance = balance as f64 / LAMPORTS_PER_SOL as f64;
-        Ok(())
+    if let Some(ref url) = args.grpc_url {
+        config.grpc.url = Some(url.clone());
     }
 
-    async fn send_transaction(&mut self) -> Result<()> {
-        let recipient =
-            Pubkey::from_str(&self.send_state.recipient).context("Invalid recipient address")?;
-
-        let amount = self
-            .send_state
-            .amount
-            .parse::<f64>()
-            .context("Invalid amount")?;
-
-        let lamports = (amount * LAMPORTS_PER_SOL as f64) as u64;
-
-        // Create transfer instruction
-        let transfer_ix = system_instruction::transfer(&self.wallet.address, &recipient, lamports);
-
-        // Get recent blockhash
-        let recent_blockhash = self
-            .rpc_client
-            .get_latest_blockhash()
-            .context("Failed to get recent blockhash")?;
-
-        // Build transaction
-        let transaction = Transaction::new_signed_with_payer(
-            &[transfer_ix],
-            Some(&self.wallet.address),
-            &[&*self.wallet.keypair],
-            recent_blockhash,
-        );
+    if args.dry_run {
+        sender::set_dry_run(true);
+    }
+    if args.no_memory_hardening {
+        memlock::set_enabled(false);
+    } else if let Err(e) = memlock::disable_core_dumps() {
+        eprintln!("Warning: failed to disable core dumps: {}", e);
+    }
+    let log_buffer = logging::init(&config.log).unwrap_or_else(|e| {
+        eprintln!("Warning: {:#}", e);
+        LogBuffer::default()
+    });
 
-        // Send transaction
-        let signature = self
-            .rpc_client
-            .send_and_confirm_transaction(&transaction)
-            .context("Failed to send transaction")?;
+    if let Some(ref path) = args.import_key {
+        return cli::import_key_to_file(&config::expand_tilde(path));
+    }
 
-        self.last_tx_signature = Some(signature);
-        self.send_state.status = Some(format!("Transaction sent: {}", signature));
+    // Commands that don't need a wallet
+    match args.command {
+        Some(Command::Grind {
+            ref starts_with,
+            ref ends_with,
+            ignore_case,
+            ref outfile,
+        }) => {
+            let pattern = GrindPattern::new(starts_with, ends_with, ignore_case)?;
+            return cli::grind_to_file(pattern, outfile.clone());
+        }
+        Some(Command::VerifyMessage {
+            ref message,
+            ref file,
+            ref address,
+            ref signature,
+        }) => {
+            let message = match file {
+                Some(path) => std::fs::read(path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?,
+                None => message.clone().unwrap_or_default().into_bytes(),
+            };
+            let address = Pubkey::from_str(address).context("Invalid signer address")?;
+            let signature = Signature::from_str(signature).context("Invalid signature")?;
+            if !message::verify(&address, &message, &signature)? {
+                bail!("The signature does not match this message and address");
+            }
+            args.output.print(
+                format!("Valid signature by {}", address),
+                serde_json::json!({ "address": address.to_string(), "valid": true }),
+            );
+            return Ok(());
+        }
+        Some(Command::Restore {
+            ref shares,
+            ref outfile,
+        }) => {
+            return cli::restore_from_shares(shares, outfile.clone());
+        }
+        Some(Command::MergeSignatures { ref transactions }) => {
+            let transactions = transactions
+                .iter()
+                .map(|input| inspector::read_transaction(input))
+                .collect::<Result<Vec<_>>>()?;
+            return cli::print_partially_signed(&inspector::merge(&transactions)?, args.output);
+        }
+        Some(Command::Audit { ref export }) => {
+            return cli::show_audit(export.as_deref(), args.output);
+        }
+        _ => {}
+    }
 
-        // Refresh balance
-        let _ = self.refresh_balance().await;
+    let validator = if args.start_local_validator {
+        eprintln!("Starting solana-test-validator...");
+        let validator = LocalValidator::start()?;
+        eprintln!(
+            "Local validator ready, ledger in {}",
+            validator.ledger().display()
+        );
+        Some(validator)
+    } else {
+        None
+    };
 
-        Ok(())
+    // Resolve RPC URL from cluster
+    let rpc_url = match validator {
+        Some(_) => validator::RPC_URL.to_string(),
+        None => resolve_rpc_url(args.cluster.as_deref().unwrap_or(&config.cluster.name)),
+    };
+    eprintln!("Connecting to RPC: {}", rpc_url);
+    if config.audit.enabled {
+        audit::init(&Cluster::label(&rpc_url)).context(
+            "Signatures can't be audited; move the audit log aside to start a new one, \
+             or turn [audit] off",
+        )?;
     }
-}
 
-fn load_keypair(path: &PathBuf) -> Result<Keypair> {
-    let file = File::open(path)
-        .with_context(|| format!("Failed to open keypair file: {}", path.display()))?;
-    let reader = BufReader::new(file);
-    let keypair_bytes: Vec<u8> = serde_json::from_reader(reader)
-        .with_context(|| format!("Failed to parse keypair file: {}", path.display()))?;
+    if args.read_only && args.command.as_ref().is_some_and(Command::signs) {
+        return Err(Refused(READ_ONLY.to_string()).into());
+    }
 
-    Keypair::try_from(&keypair_bytes[..])
-        .with_context(|| format!("Invalid keypair in file: {}", path.display()))
-}
+    let (rpc_client, backoff) = rpc::connect(&config, &rpc_url);
+
+    // Whatever a command sends is checked against these first; the TUI
+    // keeps its own blocklist, refreshed on demand
+    let blocklist = if args.command.as_ref().is_some_and(Command::signs) {
+        let blocklist = load_blocklist(&config)?;
+        sender::set_guard(Guard {
+            blocklist: blocklist.clone(),
+            hooks: config.webhook.clone(),
+            rpc_url: rpc_url.clone(),
+        });
+        blocklist
+    } else {
+        Blocklist::default()
+    };
 
-fn resolve_rpc_url(cluster: &str) -> String {
-    // Check if cluster is a known preset or a custom URL
-    match cluster.to_lowercase().as_str() {
-        "mainnet" | "mainnet-beta" => "https://api.mainnet-beta.solana.com".to_string(),
-        "testnet" => "https://api.testnet.solana.com".to_string(),
-        "devnet" => "https://api.devnet.solana.com".to_string(),
-        "localhost" | "localnet" => "http://localhost:8899".to_string(),
-        // If not a preset, assume it's a custom RPC URL
-        custom => {
-            // Add https:// if no protocol is specified
-            if custom.starts_with("http://") || custom.starts_with("https://") {
-                custom.to_string()
-            } else {
-                format!("https://{}", custom)
-            }
-        }
+    // Sending what was signed elsewhere needs no wallet either
+    if let Some(Command::Broadcast {
+        ref transaction,
+        ref file,
+        yes,
+        finalized,
+    }) = args.command
+    {
+        let text = match file {
+            Some(path) => std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read {}", path.display()))?,
+            None => transaction.clone().unwrap_or_default(),
+        };
+        let pipeline = Pipeline::finalized(finalized || config.confirm.finalized);
+        return cli::broadcast_headless(&*rpc_client, &pipeline, &config, &text, yes, args.output);
     }
-}
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    let args = Args::parse();
+    // Determine keypair source; read-only mode can follow a wallet by its
+    // address alone
+    let keypair_source = match args
+        .keypair
+        .as_deref()
+        .filter(|_| args.read_only)
+        .and_then(|keypair| Pubkey::from_str(keypair).ok())
+    {
+        Some(address) => KeypairSource::Address(address),
+        None => KeypairSource::resolve(args.keypair.as_deref())?,
+    };
+
+    // Load the keypair (required unless the address was given)
+    let keypair = match keypair_source {
+        KeypairSource::Address(_) => None,
+        _ => Some(wallet::load_keypair(
+            &keypair_source,
+            args.derivation_path.as_deref(),
+            args.account_index,
+            &*rpc_client,
+            config.accounts.count,
+        )?),
+    };
+    let signer = || {
+        keypair
+            .as_ref()
+            .context("Only the wallet's address was given, and this needs its keypair")
+    };
 
-    // Determine keypair path
-    let keypair_path = if let Some(path) = args.keypair {
-        path
+    let address = match keypair_source {
+        KeypairSource::Address(address) => address,
+        _ => signer()?.pubkey(),
+    };
+    if args.read_only {
+        eprintln!("Loaded wallet: {} (read-only)", address);
     } else {
-        // Use default Solana CLI path
-        let mut default_path = dirs::home_dir().context("Could not find home directory")?;
-        default_path.push(".config");
-        default_path.push("solana");
-        default_path.push("id.json");
-        default_path
+        eprintln!("Loaded wallet: {}", address);
+    }
+    if let (Some(ref validator), Some(sol)) = (&validator, args.airdrop) {
+        validator.airdrop(&address, sol)?;
+        eprintln!("Airdropped {} SOL to {}", sol, address);
+    }
+
+    let fee_payer_source = args.fee_payer.as_deref().map(KeypairSource::parse);
+    let fee_payer = match fee_payer_source {
+        Some(ref source) => {
+            let fee_payer = source
+                .load()
+                .with_context(|| format!("Failed to load the fee payer from {}", source))?;
+            eprintln!("Fees paid by: {}", fee_payer.pubkey());
+            Some(Arc::new(fee_payer))
+        }
+        None => None,
     };
 
-    // Load the keypair (required)
-    let keypair = load_keypair(&keypair_path).with_context(|| {
-        format!(
-            "Failed to load keypair from {}. 
-Please ensure the file exists and contains a valid Solana keypair.
-You can create one with: solana-keygen new -o {}",
-            keypair_path.display(),
-            keypair_path.display()
+    // Commands that run without the TUI
+    match args.command {
+        Some(Command::History {
+            export,
+            format,
+            limit,
+            reward_epochs,
+        }) => {
+            return cli::export_history(
+                &*rpc_client,
+                &address,
+                export,
+                format,
+                limit,
+                reward_epochs.unwrap_or(config.staking.reward_epochs),
+                args.output,
+            )
+        }
+        Some(Command::Rewards { epochs }) => {
+            return cli::show_rewards(
+                &*rpc_client,
+                &address,
+                epochs.unwrap_or(config.staking.reward_epochs),
+                args.output,
+            )
+        }
+        Some(Command::Address) => {
+            args.output.print(
+                address,
+                serde_json::json!({ "address": address.to_string() }),
+            );
+            return Ok(());
+        }
+        Some(Command::Balance) => {
+            let lamports = rpc_client
+                .get_balance(&address)
+                .context("Failed to fetch balance")?;
+            args.output.print(
+                format!("{:.9} SOL", lamports as f64 / LAMPORTS_PER_SOL as f64),
+                balance_json(&address, lamports),
+            );
+            return Ok(());
+        }
+        Some(Command::Send {
+            to,
+            amount,
+            yes,
+            sign_only: true,
+            nonce,
+            blockhash,
+            ..
+        }) => {
+            return cli::sign_offline_headless(
+                &config,
+                signer()?,
+                &to,
+                amount,
+                &nonce.unwrap_or_default(),
+                &blockhash.unwrap_or_default(),
+                yes,
+                args.output,
+            );
+        }
+        Some(Command::Send {
+            to,
+            amount,
+            yes,
+            finalized,
+            tip,
+            priority_fee,
+            reference,
+            ..
+        }) => {
+            let mut pipeline =
+                transfer_pipeline(&config, finalized, tip, fee_payer.clone(), || {
+                    is_mainnet(&*rpc_client, &rpc_url)
+                })?;
+            pipeline.compute_unit_price = priority_fee;
+            if reference {
+                pipeline.reference.get_or_insert_with(pay::new_reference);
+            }
+            return cli::send_headless(
+                &*rpc_client,
+                pipeline,
+                &rpc_url,
+                &config,
+                &blocklist,
+                signer()?,
+                &to,
+                amount,
+                yes,
+                args.output,
+            );
+        }
+        Some(Command::Seeded { command }) => {
+            return cli::seeded_headless(
+                &*rpc_client,
+                &rpc_url,
+                &config,
+                &blocklist,
+                signer()?,
+                fee_payer,
+                command,
+                args.output,
+            );
+        }
+        Some(Command::Nonce { command }) => {
+            return cli::nonce_headless(
+                &*rpc_client,
+                &rpc_url,
+                &config,
+                &blocklist,
+                signer()?,
+                fee_payer,
+                command,
+                args.output,
+            );
+        }
+        Some(Command::LookupTable { command }) => {
+            return cli::lookup_table_headless(&*rpc_client, signer()?, command, args.output);
+        }
+        Some(Command::Governance { command }) => {
+            return cli::governance_headless(
+                &*rpc_client,
+                &config,
+                signer()?,
+                command,
+                args.output,
+            );
+        }
+        Some(Command::Token { command }) => {
+            return cli::token_headless(&*rpc_client, signer()?, command, args.output);
+        }
+        Some(Command::SignMessage { message, file }) => {
+            let message = match file {
+                Some(path) => std::fs::read(&path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?,
+                None => message.unwrap_or_default().into_bytes(),
+            };
+            let signature = message::sign(signer()?, &message)?;
+            args.output.print(
+                signature,
+                serde_json::json!({
+                    "address": address.to_string(),
+                    "signature": signature.to_string(),
+                }),
+            );
+            return Ok(());
+        }
+        Some(Command::SignIn { request, file, yes }) => {
+            let text = match file {
+                Some(path) => std::fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?,
+                None => request.unwrap_or_default(),
+            };
+            return cli::sign_in_headless(signer()?, &text, yes, args.output);
+        }
+        Some(Command::SignTransaction {
+            transaction,
+            file,
+            yes,
+        }) => {
+            let text = match file {
+                Some(path) => std::fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?,
+                None => transaction.unwrap_or_default(),
+            };
+            let mut signers: Vec<&dyn Signer> = vec![signer()?];
+            if let Some(ref fee_payer) = fee_payer {
+                signers.push(&**fee_payer);
+            }
+            return cli::sign_transaction_headless(
+                &*rpc_client,
+                &config,
+                &signers,
+                &text,
+                yes,
+                args.output,
+            );
+        }
+        Some(Command::Daemon) => {
+            // Only read-only mode, which refuses it, goes without the keypair
+            let keypair = keypair.context(READ_ONLY)?;
+            return daemon::Daemon::new(
+                rpc_client, rpc_url, config, keypair, fee_payer, blocklist,
+            )?
+            .serve();
+        }
+        Some(
+            Command::Grind { .. }
+            | Command::Restore { .. }
+            | Command::VerifyMessage { .. }
+            | Command::MergeSignatures { .. }
+            | Command::Broadcast { .. }
+            | Command::Audit { .. },
         )
-    })?;
-
-    let address = keypair.pubkey();
-    eprintln!("Loaded wallet: {}", address);
+        | None => {}
+    }
 
     let wallet_info = WalletInfo {
-        keypair: Arc::new(keypair),
+        // Read-only mode only needed it for the address
+        keypair: keypair.filter(|_| !args.read_only).map(Arc::new),
+        keypair_source,
+        fee_payer,
+        fee_payer_source,
         address,
-        balance: 0.0,
+        lamports: 0,
     };
 
-    // Resolve RPC URL from cluster
-    let rpc_url = resolve_rpc_url(&args.cluster);
-    eprintln!("Connecting to RPC: {}", rpc_url);
-
-    // Create RPC client
-    let rpc_client = Arc::new(RpcClient::new_with_commitment(
-        rpc_url.clone(),
-        CommitmentConfig::confirmed(),
-    ));
+    // Started now so the roundtrips run alongside the remote blocklist fetch
+    // and the PIN prompt
+    let startup = Startup::spawn(&rpc_client, address);
+    let cluster = Cluster::label(&rpc_url);
+    tracing::info!(%cluster, %address, "Opened wallet");
+
+    let idls = IdlRegistry::load(&config.idl)?;
+    let session = if config.session.restore {
+        SessionState::load().unwrap_or_else(|e| {
+            eprintln!("Warning: {:#}", e);
+            SessionState::default()
+        })
+    } else {
+        SessionState::default()
+    };
+    let keymap = Keymap::from_config(&config.keys)?;
+    let theme = Theme::from_config(&config.theme)?;
+    let amounts = AmountFormat::from_config(&config.display)?;
+    i18n::init(config.display.locale.as_deref())?;
+    let blocklist = load_blocklist(&config)?;
+
+    let session_lock = match config.lock.timeout_minutes {
+        Some(minutes) => {
+            let pin = prompt_session_pin()?;
+            SessionLock::new(Some(Duration::from_secs(minutes * 60)), Some(&pin))
+        }
+        None => SessionLock::disabled(),
+    };
 
     // Initialize terminal
     enable_raw_mode()?;
@@ -248,10 +890,27 @@ You can create one with: solana-keygen new -o {}",
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    let mut app = App::new(wallet_info, rpc_client, rpc_url);
-
-    // Get initial balance
-    let _ = app.refresh_balance().await;
+    let mut app = App::new(
+        wallet_info,
+        rpc_client,
+        rpc_url,
+        config,
+        blocklist,
+        SpendingLedger::load()?,
+        session_lock,
+    );
+    app.idls = idls;
+    app.keymap = keymap;
+    app.theme = theme;
+    app.amounts = amounts;
+    app.plain = PlainMode::new(args.no_color, args.ascii);
+    app.read_only = args.read_only;
+    app.validator = validator;
+    app.backoff = Some(backoff);
+    app.log_buffer = log_buffer;
+    app.config_path = args.config.clone().or_else(Config::default_path);
+    app.start(startup);
+    app.restore(session);
 
     let res = run_app(&mut terminal, app).await;
 
@@ -275,340 +934,55 @@ async fn run_app<B: ratatui::backend::Backend>(
     mut app: App,
 ) -> Result<()> {
     loop {
-        terminal.draw(|f| ui(f, &app))?;
-
-        if let Event::Key(key) = event::read()? {
-            // Handle Send state input
-            if matches!(app.state, AppState::Send) {
-                match handle_send_input(&mut app, key).await {
-                    Ok(should_continue) => {
-                        if !should_continue {
-                            app.state = AppState::Wallet;
-                            app.send_state = SendState::default();
-                        }
-                    }
-                    Err(e) => {
-                        app.send_state.error = Some(e.to_string());
-                    }
-                }
-                continue;
-            }
+        app.poll_tasks();
+        terminal.draw(|f| app.draw(f))?;
+
+        // Redraw often enough to animate spinners while something runs
+        let timeout = if app.busy() {
+            task::SPINNER_FRAME
+        } else {
+            app::TICK_RATE
+        };
+        if !event::poll(timeout)? {
+            app.on_tick();
+            continue;
+        }
 
-            match key.code {
-                KeyCode::Char('q') => return Ok(()),
-                KeyCode::Char('r') if matches!(app.state, AppState::Wallet) => {
-                    // Refresh balance
-                    let _ = app.refresh_balance().await;
-                }
-                KeyCode::Esc if matches!(app.state, AppState::Receive) => {
-                    app.state = AppState::Wallet;
-                }
-                KeyCode::Up => {
-                    if app.selected_menu_item > 0 {
-                        app.selected_menu_item -= 1;
-                    }
-                }
-                KeyCode::Down => {
-                    if app.selected_menu_item < 5 {
-                        app.selected_menu_item += 1;
-                    }
-                }
-                KeyCode::Enter => {
-                    app.state = match app.selected_menu_item {
-                        0 => AppState::Home,
-                        1 => {
-                            // Refresh balance when entering wallet view
-                            let _ = app.refresh_balance().await;
-                            AppState::Wallet
-                        }
-                        2 => {
-                            app.send_state = SendState::default();
-                            AppState::Send
-                        }
-                        3 => AppState::Receive,
-                        4 => AppState::Transactions,
-                        5 => AppState::Settings,
-                        _ => AppState::Home,
-                    };
-                }
-                _ => {}
-            }
+        let event = event::read()?;
+        if let Event::Resize(..) = event {
+            // Not activity for the session lock; the next draw lays the
+            // screen out for the new size
+            terminal.autoresize()?;
+            continue;
         }
-    }
-}
+        app.session_lock.touch();
 
-async fn handle_send_input(app: &mut App, key: KeyEvent) -> Result<bool> {
-    match app.send_state.input_mode {
-        SendInputMode::EditingRecipient => match key.code {
-            KeyCode::Char(c) => {
-                app.send_state.recipient.push(c);
+        if let Event::Mouse(mouse) = event {
+            if !app.session_lock.is_locked() {
+                app::handle_mouse(&mut app, mouse);
             }
-            KeyCode::Backspace => {
-                app.send_state.recipient.pop();
-            }
-            KeyCode::Enter => {
-                if !app.send_state.recipient.is_empty() {
-                    app.send_state.input_mode = SendInputMode::EditingAmount;
-                    app.send_state.error = None;
-                }
-            }
-            KeyCode::Esc => return Ok(false),
-            _ => {}
-        },
-        SendInputMode::EditingAmount => match key.code {
-            KeyCode::Char(c) if c.is_ascii_digit() || c == '.' => {
-                app.send_state.amount.push(c);
-            }
-            KeyCode::Backspace => {
-                app.send_state.amount.pop();
-            }
-            KeyCode::Enter => {
-                if !app.send_state.amount.is_empty() {
-                    app.send_state.input_mode = SendInputMode::Confirming;
-                    app.send_state.error = None;
-                }
-            }
-            KeyCode::Esc => {
-                app.send_state.input_mode = SendInputMode::EditingRecipient;
-            }
-            _ => {}
-        },
-        SendInputMode::Confirming => match key.code {
-            KeyCode::Char('y') | KeyCode::Char('Y') => {
-                app.send_state.status = Some("Sending transaction...".to_string());
-                app.send_transaction().await?;
-                return Ok(false);
-            }
-            KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
-                app.send_state.input_mode = SendInputMode::EditingAmount;
-            }
-            _ => {}
-        },
-    }
-    Ok(true)
-}
-
-fn ui(f: &mut Frame, app: &App) {
-    let chunks = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([Constraint::Percentage(30), Constraint::Percentage(70)].as_ref())
-        .split(f.area());
-
-    let menu_chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([Constraint::Length(3), Constraint::Min(0)].as_ref())
-        .split(chunks[0]);
-
-    let title = Paragraph::new("☀️ SOLACE")
-        .style(
-            Style::default()
-                .fg(Color::Cyan)
-                .add_modifier(Modifier::BOLD),
-        )
-        .block(Block::default().borders(Borders::ALL));
-    f.render_widget(title, menu_chunks[0]);
-
-    let menu_items = [
-        "Home",
-        "Wallet",
-        "Send",
-        "Receive",
-        "Transactions",
-        "Settings",
-    ];
-    let menu: Vec<ListItem> = menu_items
-        .iter()
-        .enumerate()
-        .map(|(i, item)| {
-            let style = if i == app.selected_menu_item {
-                Style::default()
-                    .fg(Color::Yellow)
-                    .add_modifier(Modifier::BOLD)
-            } else {
-                Style::default().fg(Color::White)
-            };
-            ListItem::new(Line::from(Span::styled(*item, style)))
-        })
-        .collect();
-
-    let menu_list = List::new(menu).block(Block::default().borders(Borders::ALL).title("Menu"));
-    f.render_widget(menu_list, menu_chunks[1]);
-
-    let content = match app.state {
-        AppState::Home => render_home(),
-        AppState::Wallet => render_wallet(app),
-        AppState::Send => render_send(app),
-        AppState::Receive => render_receive(app),
-        AppState::Transactions => render_transactions(),
-        AppState::Settings => render_settings(app),
-    };
-    f.render_widget(content, chunks[1]);
-}
-
-fn render_home() -> Paragraph<'static> {
-    Paragraph::new(vec![
-        Line::from("Welcome to Solace! 🚀"),
-        Line::from(""),
-        Line::from("A Solana wallet with a terminal UI"),
-        Line::from(""),
-        Line::from("Navigate with ↑↓ arrows"),
-        Line::from("Press Enter to select"),
-        Line::from("Press 'q' to quit"),
-    ])
-    .style(Style::default().fg(Color::White))
-    .block(Block::default().borders(Borders::ALL).title("Home"))
-}
-
-fn render_wallet(app: &App) -> Paragraph<'static> {
-    let lines = vec![
-        Line::from("Wallet Overview"),
-        Line::from(""),
-        Line::from(format!("Address: {}", app.wallet.address)),
-        Line::from(format!("Balance: {:.9} SOL", app.wallet.balance)),
-        Line::from(""),
-        Line::from("Press 'r' to refresh balance"),
-    ];
-
-    Paragraph::new(lines)
-        .style(Style::default().fg(Color::Green))
-        .block(Block::default().borders(Borders::ALL).title("Wallet"))
-}
-
-fn render_transactions() -> Paragraph<'static> {
-    Paragraph::new(vec![
-        Line::from("Recent Transactions"),
-        Line::from(""),
-        Line::from("No transactions yet"),
-    ])
-    .style(Style::default().fg(Color::Blue))
-    .block(Block::default().borders(Borders::ALL).title("Transactions"))
-}
-
-fn render_send(app: &App) -> Paragraph<'static> {
-    let mut lines = vec![Line::from("Send SOL"), Line::from("")];
-
-    match app.send_state.input_mode {
-        SendInputMode::EditingRecipient => {
-            lines.push(Line::from("Enter recipient address:"));
-            lines.push(Line::from(Span::styled(
-                format!("{}█", app.send_state.recipient),
-                Style::default().fg(Color::Yellow),
-            )));
-            lines.push(Line::from(""));
-            lines.push(Line::from("Press Enter to continue, Esc to cancel"));
-        }
-        SendInputMode::EditingAmount => {
-            lines.push(Line::from(format!("To: {}", app.send_state.recipient)));
-            lines.push(Line::from(""));
-            lines.push(Line::from("Enter amount (SOL):"));
-            lines.push(Line::from(Span::styled(
-                format!("{}█", app.send_state.amount),
-                Style::default().fg(Color::Yellow),
-            )));
-            lines.push(Line::from(""));
-            lines.push(Line::from(format!(
-                "Available balance: {:.9} SOL",
-                app.wallet.balance
-            )));
-            lines.push(Line::from(""));
-            lines.push(Line::from("Press Enter to continue, Esc to go back"));
-        }
-        SendInputMode::Confirming => {
-            lines.push(Line::from("Confirm Transaction"));
-            lines.push(Line::from(""));
-            lines.push(Line::from(format!("To: {}", app.send_state.recipient)));
-            lines.push(Line::from(format!("Amount: {} SOL", app.send_state.amount)));
-            lines.push(Line::from(""));
-            lines.push(Line::from(Span::styled(
-                "Press Y to confirm, N to cancel",
-                Style::default()
-                    .fg(Color::Yellow)
-                    .add_modifier(Modifier::BOLD),
-            )));
+            continue;
         }
-    }
 
-    if let Some(ref error) = app.send_state.error {
-        lines.push(Line::from(""));
-        lines.push(Line::from(Span::styled(
-            format!("Error: {}", error),
-            Style::default().fg(Color::Red),
-        )));
-    }
-
-    if let Some(ref status) = app.send_state.status {
-        lines.push(Line::from(""));
-        lines.push(Line::from(Span::styled(
-            status.clone(),
-            Style::default().fg(Color::Green),
-        )));
-    }
-
-    Paragraph::new(lines)
-        .style(Style::default().fg(Color::White))
-        .block(Block::default().borders(Borders::ALL).title("Send SOL"))
-}
-
-fn render_receive(app: &App) -> Paragraph<'static> {
-    let mut lines = vec![
-        Line::from("Receive SOL"),
-        Line::from(""),
-        Line::from("Your wallet address:"),
-        Line::from(Span::styled(
-            app.wallet.address.to_string(),
-            Style::default().fg(Color::Green),
-        )),
-        Line::from(""),
-    ];
-
-    // Generate QR code
-    match QrCode::new(app.wallet.address.to_string()) {
-        Ok(code) => {
-            let qr = code
-                .render::<unicode::Dense1x2>()
-                .dark_color(unicode::Dense1x2::Light)
-                .light_color(unicode::Dense1x2::Dark)
-                .build();
-
-            for line in qr.lines() {
-                lines.push(Line::from(line.to_string()));
+        if let Event::Key(key) = event {
+            if !app::handle_key(&mut app, key) {
+                return app.save_session();
             }
         }
-        Err(_) => {
-            lines.push(Line::from("Failed to generate QR code"));
-        }
     }
-
-    lines.push(Line::from(""));
-    lines.push(Line::from("Press Esc to go back"));
-
-    Paragraph::new(lines)
-        .style(Style::default().fg(Color::White))
-        .alignment(Alignment::Center)
-        .block(Block::default().borders(Borders::ALL).title("Receive SOL"))
 }
 
-fn render_settings(app: &App) -> Paragraph<'static> {
-    let network = if app.rpc_url.contains("mainnet") {
-        "Mainnet Beta"
-    } else if app.rpc_url.contains("testnet") {
-        "Testnet"
-    } else if app.rpc_url.contains("devnet") {
-        "Devnet"
-    } else if app.rpc_url.contains("localhost") || app.rpc_url.contains("127.0.0.1") {
-        "Localnet"
-    } else {
-        "Custom"
-    };
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    Paragraph::new(vec![
-        Line::from("Settings"),
-        Line::from(""),
-        Line::from(format!("RPC Endpoint: {}", app.rpc_url)),
-        Line::from(format!("Network: {}", network)),
-        Line::from(""),
-        Line::from(format!("Wallet: {}", app.wallet.address)),
-    ])
-    .style(Style::default().fg(Color::Magenta))
-    .block(Block::default().borders(Borders::ALL).title("Settings"))
+    #[test]
+    fn read_only_mode_refuses_signing_commands() {
+        assert!(Command::Daemon.signs());
+        assert!(!Command::Balance.signs());
+        assert!(!Command::Nonce {
+            command: NonceCommand::List
+        }
+        .signs());
+    }
 }
@@ -0,0 +1,87 @@
+//! Best-effort protection against secret key material reaching disk, either
+//! through swap or through a core dump after a crash.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// Turn memory locking on or off for keys loaded from now on
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Pages locked into RAM for as long as this is alive
+pub struct LockedRegion {
+    #[cfg_attr(not(unix), allow(dead_code))]
+    addr: usize,
+    #[cfg_attr(not(unix), allow(dead_code))]
+    len: usize,
+}
+
+/// Lock the pages holding `value` so they are never swapped out. Returns
+/// `None` if locking is disabled or the OS refused (e.g. `RLIMIT_MEMLOCK`).
+pub fn lock<T>(value: &T) -> Option<LockedRegion> {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return None;
+    }
+
+    let region = LockedRegion {
+        addr: value as *const T as usize,
+        len: std::mem::size_of::<T>(),
+    };
+    sys::mlock(region.addr, region.len).then_some(region)
+}
+
+impl Drop for LockedRegion {
+    fn drop(&mut self) {
+        sys::munlock(self.addr, self.len);
+    }
+}
+
+/// Set the core file size limit to zero so a crash can't dump keys to disk
+pub fn disable_core_dumps() -> std::io::Result<()> {
+    sys::disable_core_dumps()
+}
+
+#[cfg(unix)]
+mod sys {
+    pub fn mlock(addr: usize, len: usize) -> bool {
+        // SAFETY: mlock only changes paging behaviour for the given range,
+        // which belongs to a live allocation owned by the caller
+        unsafe { libc::mlock(addr as *const libc::c_void, len) == 0 }
+    }
+
+    pub fn munlock(addr: usize, len: usize) {
+        // SAFETY: as above; unlocking a range can't invalidate memory
+        unsafe {
+            libc::munlock(addr as *const libc::c_void, len);
+        }
+    }
+
+    pub fn disable_core_dumps() -> std::io::Result<()> {
+        let limit = libc::rlimit {
+            rlim_cur: 0,
+            rlim_max: 0,
+        };
+        // SAFETY: setrlimit reads the struct we pass and has no other effects
+        // on our memory
+        if unsafe { libc::setrlimit(libc::RLIMIT_CORE, &limit) } == 0 {
+            Ok(())
+        } else {
+            Err(std::io::Error::last_os_error())
+        }
+    }
+}
+
+#[cfg(not(unix))]
+mod sys {
+    pub fn mlock(_addr: usize, _len: usize) -> bool {
+        false
+    }
+
+    pub fn munlock(_addr: usize, _len: usize) {}
+
+    pub fn disable_core_dumps() -> std::io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,60 @@
+//! Off-chain messages: text signed with the wallet key under the Solana
+//! off-chain message standard, to prove ownership of an address without
+//! sending a transaction, and checking such proofs from others
+
+use anyhow::{bail, Context, Result};
+use solana_offchain_message::{v0, OffchainMessage};
+use solana_sdk::{pubkey::Pubkey, signature::Signature, signer::Signer};
+
+/// Sign `message` as a version 0 off-chain message, the format of
+/// `solana sign-offchain-message`
+pub fn sign(signer: &dyn Signer, message: &[u8]) -> Result<Signature> {
+    encode(message)?
+        .sign(signer)
+        .context("Failed to sign message")
+}
+
+/// Whether `signature` is `signer`'s signature of `message`, signed as
+/// `sign` does
+pub fn verify(signer: &Pubkey, message: &[u8], signature: &Signature) -> Result<bool> {
+    encode(message)?
+        .verify(signer, signature)
+        .context("Failed to verify message")
+}
+
+fn encode(message: &[u8]) -> Result<OffchainMessage> {
+    if message.is_empty() {
+        bail!("Nothing to sign; the message is empty");
+    }
+    if message.len() > v0::OffchainMessage::MAX_LEN {
+        bail!(
+            "Message is too long to sign: {} bytes, at most {}",
+            message.len(),
+            v0::OffchainMessage::MAX_LEN
+        );
+    }
+    // Only long messages that aren't UTF-8 are left to fail here
+    OffchainMessage::new(0, message).context("Messages this long must be UTF-8 text")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::signature::Keypair;
+
+    #[test]
+    fn signs_and_verifies_the_standard_encoding() {
+        let keypair = Keypair::new();
+        let signature = sign(&keypair, b"I own this address").unwrap();
+        let mut expected = b"\xffsolana offchain\x00\x00".to_vec();
+        expected.extend(18u16.to_le_bytes());
+        expected.extend(b"I own this address");
+        assert!(signature.verify(keypair.pubkey().as_ref(), &expected));
+
+        assert!(sign(&keypair, b"").is_err());
+
+        assert!(verify(&keypair.pubkey(), b"I own this address", &signature).unwrap());
+        assert!(!verify(&keypair.pubkey(), b"I own this addresS", &signature).unwrap());
+        assert!(!verify(&Pubkey::new_unique(), b"I own this address", &signature).unwrap());
+    }
+}
@@ -0,0 +1,284 @@
+//! Durable nonce accounts, whose stored nonce stands in for a recent
+//! blockhash so a transaction can be signed offline and sent any time
+//! later. The wallet's own are derived from its address with the seeds
+//! `nonce-0`, `nonce-1`..., so they can be found again without a list.
+
+use crate::rent;
+use crate::rpc::RpcApi;
+use crate::seeded::SeededAccount;
+use crate::sender;
+use anyhow::{bail, Context, Result};
+use solana_sdk::{
+    account::Account,
+    hash::Hash,
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    signature::{Signature, Signer},
+    transaction::Transaction,
+};
+#[allow(deprecated)]
+use solana_sdk::{
+    nonce::state::{State, Versions},
+    system_instruction, system_program,
+};
+
+/// How many seeds are looked at for the wallet's nonce accounts
+pub const MAX_ACCOUNTS: u32 = 10;
+
+/// Bytes of data in a nonce account
+pub const ACCOUNT_SIZE: usize = State::size();
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NonceAccount {
+    pub address: Pubkey,
+    /// Which of the wallet's seeds it's at, if it's one of them
+    pub index: Option<u32>,
+    pub lamports: u64,
+    /// Who signs to advance it or withdraw from it
+    pub authority: Pubkey,
+    /// The blockhash for the next transaction that uses it
+    pub nonce: Hash,
+    pub lamports_per_signature: u64,
+}
+
+impl NonceAccount {
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "address": self.address.to_string(),
+            "index": self.index,
+            "lamports": self.lamports,
+            "authority": self.authority.to_string(),
+            "nonce": self.nonce.to_string(),
+            "lamports_per_signature": self.lamports_per_signature,
+        })
+    }
+}
+
+pub fn seed(index: u32) -> String {
+    format!("nonce-{}", index)
+}
+
+/// The address of the wallet's nonce account with the seed `index`
+pub fn address(base: &Pubkey, index: u32) -> Result<Pubkey> {
+    SeededAccount::new(*base, &seed(index), None)?.address()
+}
+
+/// Read the nonce state of `account`, which must be initialized
+pub fn parse(address: Pubkey, account: &Account) -> Result<NonceAccount> {
+    if account.owner != system_program::id() || account.data.len() != ACCOUNT_SIZE {
+        bail!("{} is not a nonce account", address);
+    }
+    let versions: Versions =
+        bincode::deserialize(&account.data).with_context(|| format!("{} is corrupt", address))?;
+    let State::Initialized(ref data) = *versions.state() else {
+        bail!("Nonce account {} is not initialized", address);
+    };
+    Ok(NonceAccount {
+        address,
+        index: None,
+        lamports: account.lamports,
+        authority: data.authority,
+        nonce: data.blockhash(),
+        lamports_per_signature: data.get_lamports_per_signature(),
+    })
+}
+
+/// Look up the nonce account at `address`
+pub fn fetch(rpc_client: &dyn RpcApi, address: &Pubkey) -> Result<NonceAccount> {
+    let account = rpc_client
+        .get_account_with_commitment(address, rpc_client.commitment())
+        .with_context(|| format!("Failed to fetch account {}", address))?
+        .value
+        .with_context(|| format!("There is no account at {}", address))?;
+    parse(*address, &account)
+}
+
+/// The wallet's nonce accounts, in seed order, and the first seed that's
+/// still free for a new one
+pub fn list(rpc_client: &dyn RpcApi, base: &Pubkey) -> Result<(Vec<NonceAccount>, Option<u32>)> {
+    let addresses = (0..MAX_ACCOUNTS)
+        .map(|index| address(base, index))
+        .collect::<Result<Vec<_>>>()?;
+    let found = rpc_client
+        .get_multiple_accounts(&addresses)
+        .context("Failed to fetch nonce accounts")?;
+    let mut accounts = Vec::new();
+    let mut free = None;
+    for ((index, address), account) in (0..).zip(addresses).zip(found) {
+        match account {
+            // Something else sitting at one of the seeds is skipped
+            Some(account) => {
+                if let Ok(nonce) = parse(address, &account) {
+                    accounts.push(NonceAccount {
+                        index: Some(index),
+                        ..nonce
+                    });
+                }
+            }
+            None => {
+                free.get_or_insert(index);
+            }
+        }
+    }
+    Ok((accounts, free))
+}
+
+/// Create a nonce account at seed `index` with `signer` as its authority,
+/// funded with `lamports` from it
+pub fn create(
+    rpc_client: &dyn RpcApi,
+    signer: &dyn Signer,
+    index: u32,
+    lamports: u64,
+) -> Result<(Pubkey, Signature)> {
+    let wallet = signer.pubkey();
+    let nonce_address = address(&wallet, index)?;
+    let instructions = system_instruction::create_nonce_account_with_seed(
+        &wallet,
+        &nonce_address,
+        &wallet,
+        &seed(index),
+        &wallet,
+        lamports,
+    );
+    let signature = sender::send_instructions(rpc_client, &[signer], &instructions)
+        .context("Failed to create the nonce account")?;
+    Ok((nonce_address, signature))
+}
+
+/// Store a new nonce in `account`, which makes anything signed with the
+/// current one impossible to send
+pub fn advance(
+    rpc_client: &dyn RpcApi,
+    signer: &dyn Signer,
+    account: &NonceAccount,
+) -> Result<Signature> {
+    check_authority(signer, account)?;
+    let instruction = system_instruction::advance_nonce_account(&account.address, &signer.pubkey());
+    sender::send_instructions(rpc_client, &[signer], &[instruction])
+        .context("Failed to advance the nonce")
+}
+
+/// Move `lamports` out of `account` to `recipient`. Taking everything
+/// closes the account; anything else has to leave the rent-exempt minimum.
+pub fn withdraw(
+    rpc_client: &dyn RpcApi,
+    signer: &dyn Signer,
+    account: &NonceAccount,
+    recipient: &Pubkey,
+    lamports: u64,
+) -> Result<Signature> {
+    check_authority(signer, account)?;
+    check_withdrawal(account, lamports, rent::minimum(rpc_client, ACCOUNT_SIZE)?)?;
+    let instruction = system_instruction::withdraw_nonce_account(
+        &account.address,
+        &signer.pubkey(),
+        recipient,
+        lamports,
+    );
+    sender::send_instructions(rpc_client, &[signer], &[instruction])
+        .context("Failed to withdraw from the nonce account")
+}
+
+/// Whether `lamports` can be taken out of `account`
+pub fn check_withdrawal(account: &NonceAccount, lamports: u64, minimum: u64) -> Result<()> {
+    let sol = |lamports| lamports as f64 / LAMPORTS_PER_SOL as f64;
+    let Some(left) = account.lamports.checked_sub(lamports) else {
+        bail!("The nonce account only holds {} SOL", sol(account.lamports));
+    };
+    if left != 0 && left < minimum {
+        bail!(
+            "Leave at least {} SOL in the nonce account, or withdraw all {} SOL to close it",
+            sol(minimum),
+            sol(account.lamports)
+        );
+    }
+    Ok(())
+}
+
+/// Sign a transfer that uses the nonce instead of a recent blockhash,
+/// without talking to the cluster. `signer` pays the fee and must be the
+/// nonce account's authority; `nonce` is its stored nonce, as shown by
+/// `fetch` on a connected machine.
+pub fn sign_transfer(
+    signer: &dyn Signer,
+    nonce_account: &Pubkey,
+    nonce: Hash,
+    recipient: &Pubkey,
+    lamports: u64,
+) -> Transaction {
+    let wallet = signer.pubkey();
+    // The runtime only takes the nonce if advancing it comes first
+    let instructions = [
+        system_instruction::advance_nonce_account(nonce_account, &wallet),
+        system_instruction::transfer(&wallet, recipient, lamports),
+    ];
+    Transaction::new_signed_with_payer(&instructions, Some(&wallet), &[signer], nonce)
+}
+
+fn check_authority(signer: &dyn Signer, account: &NonceAccount) -> Result<()> {
+    if account.authority != signer.pubkey() {
+        bail!(
+            "The authority of nonce account {} is {}, not this wallet",
+            account.address,
+            account.authority
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::{MockRpc, MOCK_RENT_MINIMUM};
+    #[allow(deprecated)]
+    use solana_sdk::nonce::state::{Data, DurableNonce};
+    use solana_sdk::signature::Keypair;
+
+    /// An initialized nonce account holding `lamports`
+    fn nonce_account(authority: &Pubkey, lamports: u64) -> Account {
+        let durable_nonce = DurableNonce::from_blockhash(&Hash::new_unique());
+        let state = Versions::new(State::Initialized(Data::new(
+            *authority,
+            durable_nonce,
+            5_000,
+        )));
+        Account {
+            lamports,
+            data: bincode::serialize(&state).unwrap(),
+            owner: system_program::id(),
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    #[test]
+    fn finds_the_wallets_nonce_accounts() {
+        let rpc = MockRpc::default();
+        let wallet = Keypair::new();
+        let first = address(&wallet.pubkey(), 0).unwrap();
+        rpc.set_account(first, nonce_account(&wallet.pubkey(), MOCK_RENT_MINIMUM));
+        // Taken by an account that isn't a nonce account
+        rpc.set_balance(address(&wallet.pubkey(), 1).unwrap(), 1);
+
+        let (accounts, free) = list(&rpc, &wallet.pubkey()).unwrap();
+        assert_eq!(accounts.len(), 1);
+        assert_eq!(accounts[0].address, first);
+        assert_eq!(accounts[0].index, Some(0));
+        assert_eq!(accounts[0].authority, wallet.pubkey());
+        assert_eq!(free, Some(2));
+
+        let account = &accounts[0];
+        assert!(check_withdrawal(account, account.lamports, MOCK_RENT_MINIMUM).is_ok());
+        assert!(check_withdrawal(account, 1, MOCK_RENT_MINIMUM).is_err());
+        assert!(check_withdrawal(account, account.lamports + 1, MOCK_RENT_MINIMUM).is_err());
+        assert!(advance(&rpc, &Keypair::new(), account).is_err());
+
+        let recipient = Pubkey::new_unique();
+        let transaction = sign_transfer(&wallet, &first, account.nonce, &recipient, 1);
+        assert_eq!(transaction.message.recent_blockhash, account.nonce);
+        assert!(
+            solana_sdk::transaction::VersionedTransaction::from(transaction).uses_durable_nonce()
+        );
+    }
+}
@@ -0,0 +1,62 @@
+//! Desktop notifications, through `notify-send` on Linux and the BSDs and
+//! `osascript` on macOS
+
+use anyhow::{Context, Result};
+use std::{process::Command, thread};
+
+/// Show a notification from solace with `message`. The notifier runs on its
+/// own, so a slow one doesn't hold up the TUI.
+pub fn desktop(message: &str) -> Result<()> {
+    let mut child = notifier(message)?
+        .spawn()
+        .context("Failed to show a desktop notification")?;
+    thread::spawn(move || {
+        if let Ok(status) = child.wait() {
+            if !status.success() {
+                tracing::warn!(%status, "Desktop notification failed");
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+fn notifier(message: &str) -> Result<Command> {
+    let mut command = Command::new("osascript");
+    command.arg("-e").arg(format!(
+        "display notification {} with title \"Solace\"",
+        applescript_string(message)
+    ));
+    Ok(command)
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn notifier(message: &str) -> Result<Command> {
+    let mut command = Command::new("notify-send");
+    command.arg("--app-name=solace").arg("Solace").arg(message);
+    Ok(command)
+}
+
+#[cfg(not(unix))]
+fn notifier(_: &str) -> Result<Command> {
+    anyhow::bail!("Desktop notifications aren't supported on this platform")
+}
+
+/// `text` as an AppleScript string literal
+#[cfg(any(target_os = "macos", test))]
+fn applescript_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes_messages_for_applescript() {
+        assert_eq!(
+            applescript_string(r#"Exchange: "hot" \ wallet"#),
+            r#""Exchange: \"hot\" \\ wallet""#
+        );
+    }
+}
@@ -0,0 +1,141 @@
+//! Results of the headless subcommands, as text for people or JSON for
+//! scripts, and the exit codes they end with
+
+use crate::sender::{DryRun, Expired, TransactionFailed};
+use serde_json::{json, Value};
+use solana_client::client_error::ClientError;
+use std::{error::Error, fmt, process::ExitCode};
+
+/// The RPC node couldn't be reached or turned the request down
+pub const EXIT_RPC: u8 = 3;
+
+/// A check or the confirmation prompt stopped the command
+pub const EXIT_REFUSED: u8 = 4;
+
+/// The transaction's blockhash expired before it landed, so nothing was
+/// sent and it's safe to send again
+pub const EXIT_EXPIRED: u8 = 5;
+
+/// The transaction failed on chain or in the node's simulation
+pub const EXIT_FAILED: u8 = 6;
+
+/// `--dry-run` only simulated the transaction, which would have succeeded
+pub const EXIT_DRY_RUN: u8 = 7;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, clap::ValueEnum)]
+pub enum Output {
+    #[default]
+    Text,
+    Json,
+}
+
+/// A send turned down by one of its checks or at the confirmation prompt
+#[derive(Debug)]
+pub struct Refused(pub String);
+
+impl fmt::Display for Refused {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Error for Refused {}
+
+impl Output {
+    /// Print a command's result, `text` or `json` depending on the mode
+    pub fn print(self, text: impl fmt::Display, json: Value) {
+        match self {
+            Self::Text => println!("{}", text),
+            Self::Json => println!("{}", json),
+        }
+    }
+
+    /// Report a failed command, on stderr as text or on stdout as JSON, and
+    /// pick its exit code
+    pub fn fail(self, error: &anyhow::Error) -> ExitCode {
+        let (_, code) = classify(error);
+        match self {
+            Self::Text => {
+                eprintln!("Error: {:?}", error);
+                for log in logs(error) {
+                    eprintln!("  {}", log);
+                }
+            }
+            Self::Json => println!("{}", json!({ "error": error_json(error) })),
+        }
+        ExitCode::from(code)
+    }
+}
+
+/// `{"kind", "message", "causes", "logs"}` for an error; `logs` are the
+/// program logs of a failed or dry run transaction
+pub fn error_json(error: &anyhow::Error) -> Value {
+    let causes: Vec<String> = error.chain().skip(1).map(|e| e.to_string()).collect();
+    json!({
+        "kind": classify(error).0,
+        "message": error.to_string(),
+        "causes": causes,
+        "logs": logs(error),
+    })
+}
+
+pub(crate) fn logs(error: &anyhow::Error) -> &[String] {
+    error
+        .chain()
+        .find_map(|e| {
+            e.downcast_ref::<TransactionFailed>()
+                .map(|failed| &failed.logs)
+                .or_else(|| e.downcast_ref::<DryRun>().map(|dry_run| &dry_run.logs))
+        })
+        .map_or(&[], Vec::as_slice)
+}
+
+/// The `kind` reported in JSON errors, with its exit code
+pub fn classify(error: &anyhow::Error) -> (&'static str, u8) {
+    if error.chain().any(|e| e.is::<Refused>()) {
+        ("refused", EXIT_REFUSED)
+    } else if error.chain().any(|e| e.is::<Expired>()) {
+        ("expired", EXIT_EXPIRED)
+    } else if error.chain().any(|e| e.is::<TransactionFailed>()) {
+        ("failed", EXIT_FAILED)
+    } else if error.chain().any(|e| e.is::<DryRun>()) {
+        ("dry_run", EXIT_DRY_RUN)
+    } else if error.chain().any(|e| e.is::<ClientError>()) {
+        ("rpc", EXIT_RPC)
+    } else {
+        ("error", 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+
+    #[test]
+    fn classifies_errors_through_context() {
+        let refused =
+            anyhow::Error::new(Refused("On the blocklist".to_string())).context("Send failed");
+        assert_eq!(classify(&refused), ("refused", EXIT_REFUSED));
+
+        let rpc = anyhow::Error::new(ClientError::from(std::io::Error::other("down")))
+            .context("Failed to fetch balance");
+        assert_eq!(classify(&rpc), ("rpc", EXIT_RPC));
+
+        let failed = anyhow::Error::new(TransactionFailed {
+            signature: Default::default(),
+            error: "custom program error: 0x1".to_string(),
+            logs: vec!["Program log: insufficient funds".to_string()],
+        });
+        assert_eq!(classify(&failed), ("failed", EXIT_FAILED));
+        assert_eq!(
+            error_json(&failed)["logs"],
+            json!(["Program log: insufficient funds"])
+        );
+
+        assert_eq!(
+            classify(&anyhow!("Invalid recipient address")),
+            ("error", 1)
+        );
+    }
+}
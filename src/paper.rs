@@ -0,0 +1,195 @@
+//! Printable paper wallet backups
+
+use crate::keys::{self, SecretKeypair};
+use anyhow::{Context, Result};
+use qrcode::{render::unicode, Color, QrCode};
+use solana_sdk::signature::Signer;
+use std::{fmt::Write as _, io::Write, path::Path};
+use zeroize::Zeroizing;
+
+/// Size of one QR module in the SVG output, in user units
+const SVG_MODULE: usize = 4;
+
+/// Quiet zone around each QR code, in modules
+const QUIET_ZONE: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PaperFormat {
+    Text,
+    Svg,
+}
+
+impl PaperFormat {
+    /// SVG for `.svg` files, plain text for anything else
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension() {
+            Some(ext) if ext.eq_ignore_ascii_case("svg") => Self::Svg,
+            _ => Self::Text,
+        }
+    }
+}
+
+/// Render a paper wallet with the wallet's name, if it has one, the address
+/// QR, the private key QR and, for seed-derived wallets, the numbered seed
+/// words
+pub fn render(
+    keypair: &SecretKeypair,
+    name: Option<&str>,
+    format: PaperFormat,
+) -> Result<Zeroizing<String>> {
+    let address = keypair.pubkey().to_string();
+    let private_key = keypair.to_base58();
+    let address_qr = QrCode::new(&address).context("Failed to encode address QR code")?;
+    let key_qr =
+        QrCode::new(private_key.as_bytes()).context("Failed to encode private key QR code")?;
+    let words: Vec<&str> = keypair
+        .seed_phrase()
+        .map(|phrase| phrase.split_whitespace().collect())
+        .unwrap_or_default();
+    let derivation = keypair.derivation().map(|d| d.to_string());
+
+    let mut out = Zeroizing::new(String::new());
+    match format {
+        PaperFormat::Text => {
+            let qr_text = |code: &QrCode| {
+                Zeroizing::new(code.render::<unicode::Dense1x2>().quiet_zone(true).build())
+            };
+            match name {
+                Some(name) => writeln!(out, "SOLANA PAPER WALLET - {}", name)?,
+                None => writeln!(out, "SOLANA PAPER WALLET")?,
+            }
+            writeln!(out)?;
+            writeln!(out, "Address (public, safe to share):")?;
+            writeln!(out, "{}", address)?;
+            writeln!(out, "{}", *qr_text(&address_qr))?;
+            writeln!(out)?;
+            writeln!(out, "PRIVATE KEY - keep secret, anyone with it can spend:")?;
+            writeln!(out, "{}", *private_key)?;
+            writeln!(out, "{}", *qr_text(&key_qr))?;
+            if !words.is_empty() {
+                writeln!(out)?;
+                writeln!(out, "SEED PHRASE - keep secret:")?;
+                for (i, word) in words.iter().enumerate() {
+                    writeln!(out, "{:>2}. {}", i + 1, word)?;
+                }
+                if let Some(ref derivation) = derivation {
+                    writeln!(out, "Derivation: {}", derivation)?;
+                }
+                writeln!(out, "A BIP39 passphrase, if you used one, is not included.")?;
+            }
+        }
+        PaperFormat::Svg => {
+            let address_size = qr_size(&address_qr);
+            let key_size = qr_size(&key_qr);
+            let width = 40 + address_size + 40 + key_size + 40;
+            let words_height = words.len().div_ceil(3) * 24;
+            let height = 80 + address_size.max(key_size) + 80 + words_height + 80;
+
+            writeln!(
+                out,
+                r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}" font-family="monospace">"#,
+                w = width,
+                h = height
+            )?;
+            writeln!(out, r#"<rect width="100%" height="100%" fill="white"/>"#)?;
+            writeln!(
+                out,
+                r#"<text x="40" y="40" font-size="20" font-weight="bold">Solana Paper Wallet{}</text>"#,
+                name.map(|name| format!(" - {}", escape_xml(name)))
+                    .unwrap_or_default()
+            )?;
+            writeln!(
+                out,
+                r#"<text x="40" y="64" font-size="12">Address (public, safe to share)</text>"#
+            )?;
+            write_svg_qr(&mut out, &address_qr, 40, 72)?;
+            let key_x = 40 + address_size + 40;
+            writeln!(
+                out,
+                r#"<text x="{}" y="64" font-size="12" fill="red">PRIVATE KEY - keep secret</text>"#,
+                key_x
+            )?;
+            write_svg_qr(&mut out, &key_qr, key_x, 72)?;
+
+            let mut y = 72 + address_size.max(key_size) + 24;
+            writeln!(
+                out,
+                r#"<text x="40" y="{}" font-size="10">{}</text>"#,
+                y, address
+            )?;
+            y += 16;
+            writeln!(
+                out,
+                r#"<text x="40" y="{}" font-size="10" fill="red">{}</text>"#,
+                y, *private_key
+            )?;
+
+            if !words.is_empty() {
+                y += 40;
+                writeln!(
+                    out,
+                    r#"<text x="40" y="{}" font-size="12" fill="red">SEED PHRASE - keep secret{}</text>"#,
+                    y,
+                    derivation
+                        .as_ref()
+                        .map(|d| format!(" (derivation {})", d))
+                        .unwrap_or_default()
+                )?;
+                let column = (width - 80) / 3;
+                for (i, word) in words.iter().enumerate() {
+                    writeln!(
+                        out,
+                        r#"<text x="{}" y="{}" font-size="14">{:>2}. {}</text>"#,
+                        40 + (i % 3) * column,
+                        y + 24 + (i / 3) * 24,
+                        i + 1,
+                        word
+                    )?;
+                }
+            }
+            writeln!(out, "</svg>")?;
+        }
+    }
+    Ok(out)
+}
+
+/// Write a paper wallet to a new owner-only file, picking the format from
+/// the extension
+pub fn write_to_file(keypair: &SecretKeypair, name: Option<&str>, path: &Path) -> Result<()> {
+    let contents = render(keypair, name, PaperFormat::from_path(path))?;
+    let mut file = keys::create_secret_file(path)?;
+    file.write_all(contents.as_bytes())
+        .with_context(|| format!("Failed to write paper wallet: {}", path.display()))
+}
+
+/// `text` with the characters that mean something in SVG escaped
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Rendered side length of a QR code including its quiet zone
+fn qr_size(code: &QrCode) -> usize {
+    (code.width() + 2 * QUIET_ZONE) * SVG_MODULE
+}
+
+/// Draw the dark modules of a QR code as a single path
+fn write_svg_qr(out: &mut String, code: &QrCode, x: usize, y: usize) -> std::fmt::Result {
+    let width = code.width();
+    write!(out, r#"<path fill="black" d=""#)?;
+    for (i, color) in code.to_colors().iter().enumerate() {
+        if *color == Color::Dark {
+            write!(
+                out,
+                "M{} {}h{}v{}h-{}z",
+                x + (QUIET_ZONE + i % width) * SVG_MODULE,
+                y + (QUIET_ZONE + i / width) * SVG_MODULE,
+                SVG_MODULE,
+                SVG_MODULE,
+                SVG_MODULE
+            )?;
+        }
+    }
+    writeln!(out, r#""/>"#)
+}
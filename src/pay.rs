@@ -0,0 +1,229 @@
+//! Solana Pay transfer requests: a QR code for a set amount that carries a
+//! new reference key, the search for the payment that includes it, and
+//! whether saved requests were paid
+
+use crate::{
+    history::{self, SOL_DECIMALS},
+    rpc::RpcApi,
+    token::format_amount,
+};
+use anyhow::{Context, Result};
+use solana_client::rpc_client::GetConfirmedSignaturesForAddress2Config;
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    pubkey::Pubkey,
+    signature::{Keypair, Signature, Signer},
+};
+use std::{
+    fmt::Write,
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Transactions with the reference looked through for the payment
+const SEARCH_LIMIT: usize = 10;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaymentRequest {
+    pub recipient: Pubkey,
+    pub lamports: u64,
+    /// A key only this request uses, which the payer's wallet adds to the
+    /// transfer so it can be found
+    pub reference: Pubkey,
+    /// Who's asking, shown by the payer's wallet
+    pub label: Option<String>,
+    /// Unix time the request was made
+    pub created_at: i64,
+}
+
+impl PaymentRequest {
+    /// A request for `lamports` with a fresh reference key
+    pub fn new(recipient: Pubkey, lamports: u64, label: Option<String>) -> Self {
+        Self {
+            recipient,
+            lamports,
+            reference: new_reference(),
+            label,
+            created_at: now(),
+        }
+    }
+
+    /// `solana:<recipient>?amount=1.5&reference=<reference>&label=<label>`
+    pub fn uri(&self) -> String {
+        let mut uri = format!(
+            "solana:{}?amount={}&reference={}",
+            self.recipient,
+            format_amount(self.lamports, SOL_DECIMALS),
+            self.reference
+        );
+        if let Some(ref label) = self.label {
+            uri.push_str("&label=");
+            uri.push_str(&percent_encode(label));
+        }
+        uri
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RequestStatus {
+    Pending,
+    /// By the transaction with this signature
+    Paid(String),
+    /// Unpaid past its lifetime
+    Expired,
+}
+
+impl RequestStatus {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Paid(_) => "paid",
+            Self::Expired => "expired",
+        }
+    }
+}
+
+/// A request kept for invoicing, with the payment once it's found
+#[derive(Debug, Clone, PartialEq)]
+pub struct SavedRequest {
+    pub request: PaymentRequest,
+    pub signature: Option<String>,
+}
+
+impl SavedRequest {
+    /// Paid if a payment was found, else expired once `expire_hours` have
+    /// passed since it was made (never for 0)
+    pub fn status(&self, now: i64, expire_hours: u64) -> RequestStatus {
+        match self.signature {
+            Some(ref signature) => RequestStatus::Paid(signature.clone()),
+            None if expire_hours > 0
+                && now - self.request.created_at >= expire_hours as i64 * 3600 =>
+            {
+                RequestStatus::Expired
+            }
+            None => RequestStatus::Pending,
+        }
+    }
+}
+
+/// A key for one payment, never used to sign anything
+pub fn new_reference() -> Pubkey {
+    Keypair::new().pubkey()
+}
+
+/// Unix time now, as requests are dated
+pub fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs() as i64)
+}
+
+/// Look for the payments of `requests`, returning the references of those
+/// paid with the signature that paid each
+pub fn check_requests(
+    rpc_client: &dyn RpcApi,
+    requests: &[PaymentRequest],
+) -> Result<Vec<(Pubkey, String)>> {
+    let mut paid = Vec::new();
+    for request in requests {
+        if let Some(signature) = find_payment(rpc_client, request)? {
+            paid.push((request.reference, signature));
+        }
+    }
+    Ok(paid)
+}
+
+/// The signature of a transaction that paid `request` in full, if one has
+/// landed
+pub fn find_payment(rpc_client: &dyn RpcApi, request: &PaymentRequest) -> Result<Option<String>> {
+    let statuses = rpc_client
+        .get_signatures_for_address_with_config(
+            &request.reference,
+            GetConfirmedSignaturesForAddress2Config {
+                before: None,
+                until: None,
+                limit: Some(SEARCH_LIMIT),
+                commitment: Some(CommitmentConfig::confirmed()),
+            },
+        )
+        .context("Failed to look for the payment")?;
+    Ok(statuses
+        .into_iter()
+        .filter(|status| status.err.is_none())
+        .find(|status| {
+            Signature::from_str(&status.signature)
+                .ok()
+                .and_then(|signature| {
+                    history::fetch_details(rpc_client, &signature, &request.recipient)
+                })
+                .is_some_and(|details| details.lamports_delta >= request.lamports as i64)
+        })
+        .map(|status| status.signature))
+}
+
+/// `text` with everything but unreserved URI characters escaped as UTF-8
+/// bytes
+fn percent_encode(text: &str) -> String {
+    let mut out = String::new();
+    for byte in text.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-_.~".contains(&byte) {
+            out.push(char::from(byte));
+        } else {
+            let _ = write!(out, "%{:02X}", byte);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::MockRpc;
+
+    #[test]
+    fn builds_the_uri() {
+        let recipient = Pubkey::new_unique();
+        let request = PaymentRequest::new(recipient, 1_500_000_000, Some("Café 42".to_string()));
+        assert_eq!(
+            request.uri(),
+            format!(
+                "solana:{}?amount=1.5&reference={}&label=Caf%C3%A9%2042",
+                recipient, request.reference
+            )
+        );
+        let other = PaymentRequest::new(recipient, 1, None);
+        assert_ne!(other.reference, request.reference);
+        assert!(other
+            .uri()
+            .ends_with(&format!("amount=0.000000001&reference={}", other.reference)));
+    }
+
+    #[test]
+    fn expires_unpaid_requests() {
+        let mut saved = SavedRequest {
+            request: PaymentRequest::new(Pubkey::new_unique(), 1, None),
+            signature: None,
+        };
+        let made = saved.request.created_at;
+        assert_eq!(saved.status(made + 3599, 1), RequestStatus::Pending);
+        assert_eq!(saved.status(made + 3600, 1), RequestStatus::Expired);
+        assert_eq!(saved.status(made + 360_000, 0), RequestStatus::Pending);
+        saved.signature = Some("paid".to_string());
+        assert_eq!(
+            saved.status(made + 3600, 1),
+            RequestStatus::Paid("paid".to_string())
+        );
+    }
+
+    #[test]
+    fn unpaid_until_a_transaction_has_the_reference() {
+        let rpc = MockRpc::default();
+        let request = PaymentRequest::new(Pubkey::new_unique(), 1, None);
+        assert_eq!(find_payment(&rpc, &request).unwrap(), None);
+        assert!(check_requests(&rpc, std::slice::from_ref(&request))
+            .unwrap()
+            .is_empty());
+        rpc.fail("get_signatures_for_address_with_config");
+        assert!(find_payment(&rpc, &request).is_err());
+    }
+}
@@ -0,0 +1,105 @@
+//! Plain output for screen readers and limited terminals: no colors, and
+//! ASCII in place of box drawing, the block cursor and other symbols
+
+use ratatui::{
+    buffer::Buffer,
+    style::{Color, Modifier},
+};
+use std::env;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PlainMode {
+    /// Draw everything in the terminal's own colors
+    pub no_color: bool,
+    /// Only print ASCII characters
+    pub ascii: bool,
+}
+
+impl PlainMode {
+    /// From the command line flags, with `NO_COLOR` (https://no-color.org)
+    /// also turning colors off
+    pub fn new(no_color: bool, ascii: bool) -> Self {
+        let no_color_env = env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+        Self {
+            no_color: no_color || no_color_env,
+            ascii,
+        }
+    }
+
+    /// Rewrite a drawn frame. Text that stood out only by its background,
+    /// like search matches, is shown reversed instead.
+    pub fn apply(&self, buffer: &mut Buffer) {
+        if !self.no_color && !self.ascii {
+            return;
+        }
+        let area = buffer.area;
+        for y in area.top()..area.bottom() {
+            for x in area.left()..area.right() {
+                let cell = &mut buffer[(x, y)];
+                if self.no_color {
+                    if cell.bg != Color::Reset {
+                        cell.modifier.insert(Modifier::REVERSED);
+                    }
+                    cell.fg = Color::Reset;
+                    cell.bg = Color::Reset;
+                }
+                if self.ascii && !cell.symbol().is_ascii() {
+                    let symbol = ascii_symbol(cell.symbol());
+                    cell.set_symbol(symbol);
+                }
+            }
+        }
+    }
+
+    /// The text-entry cursor
+    pub fn cursor(&self) -> &'static str {
+        if self.ascii {
+            "_"
+        } else {
+            "█"
+        }
+    }
+}
+
+/// Closest ASCII for the symbols the UI draws; anything else, such as
+/// non-Latin text in a memo, becomes `?`
+fn ascii_symbol(symbol: &str) -> &'static str {
+    match symbol {
+        "─" | "━" | "═" => "-",
+        "│" | "┃" | "║" => "|",
+        "┌" | "┐" | "└" | "┘" | "╭" | "╮" | "╰" | "╯" | "├" | "┤" | "┬" | "┴" | "┼" | "╔" | "╗"
+        | "╚" | "╝" => "+",
+        "█" => "#",
+        "…" => "~",
+        "·" => "|",
+        "↑" | "▲" => "^",
+        "↓" | "▼" => "v",
+        "←" => "<",
+        "→" => ">",
+        _ => "?",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::{layout::Rect, style::Style};
+
+    #[test]
+    fn strips_colors_and_unicode() {
+        let area = Rect::new(0, 0, 4, 1);
+        let mut buffer = Buffer::empty(area);
+        buffer.set_string(0, 0, "┌a…", Style::default().fg(Color::Red));
+        buffer.set_string(3, 0, "x", Style::default().bg(Color::Yellow));
+        PlainMode {
+            no_color: true,
+            ascii: true,
+        }
+        .apply(&mut buffer);
+
+        assert_eq!(buffer[(0, 0)].symbol(), "+");
+        assert_eq!(buffer[(2, 0)].symbol(), "~");
+        assert_eq!(buffer[(0, 0)].fg, Color::Reset);
+        assert!(buffer[(3, 0)].modifier.contains(Modifier::REVERSED));
+    }
+}
@@ -0,0 +1,84 @@
+//! Extra screens compiled into the wallet. A plugin gets its own entry in the
+//! side menu and draws the content pane while it's open; it sees the wallet's
+//! address, balance and RPC connection but never the signing key.
+//!
+//! Plugins are built in rather than loaded from shared libraries, since Rust
+//! has no stable ABI to load them through. To add one, implement [`Plugin`]
+//! in a module under `src/plugin/`, behind a cargo feature, and list it in
+//! [`builtin`].
+
+use crossterm::event::KeyEvent;
+use ratatui::{layout::Rect, Frame};
+use solace::{failure::Failure, keymap::Action, rpc::RpcApi, theme::Theme};
+use solana_sdk::pubkey::Pubkey;
+use std::sync::Arc;
+
+#[cfg(feature = "epoch-plugin")]
+mod epoch;
+
+/// What a plugin can see of the wallet. Not every field is read by the
+/// plugins enabled in a given build.
+#[allow(dead_code)]
+pub struct PluginContext<'a> {
+    pub rpc_client: &'a Arc<dyn RpcApi>,
+    pub rpc_url: &'a str,
+    pub address: Pubkey,
+    /// In SOL, as of the last refresh
+    pub balance: f64,
+    pub theme: &'a Theme,
+    /// Whether to stick to ASCII, e.g. for spinners
+    pub ascii: bool,
+    /// The keys bound to retry, for `Failure::lines`
+    pub retry_key: Option<String>,
+}
+
+pub trait Plugin {
+    /// Shown in the side menu and as the pane's title
+    fn name(&self) -> &str;
+
+    /// Called each time the screen is opened, to start loading what it shows
+    fn open(&mut self, _context: &PluginContext) {}
+
+    fn render(&self, f: &mut Frame, area: Rect, context: &PluginContext);
+
+    /// Called while the screen is open each time it's due to refresh, as
+    /// set under `[refresh]`; loads what `open` does by default
+    fn refresh(&mut self, context: &PluginContext) {
+        self.open(context);
+    }
+
+    /// Handle a key while the screen is open, with the action it's bound to
+    /// in the `plugin` key context. Returns false to leave the key to the
+    /// app, which handles menu navigation and Esc.
+    fn handle_key(
+        &mut self,
+        _key: &KeyEvent,
+        _action: Option<Action>,
+        _context: &PluginContext,
+    ) -> bool {
+        false
+    }
+
+    /// Called on every pass of the event loop, to pick up the results of
+    /// background tasks
+    fn poll(&mut self) {}
+
+    /// Whether a background task is running, so spinners keep moving
+    fn busy(&self) -> bool {
+        false
+    }
+
+    /// The error the pane shows, if any. One marked `Retry::Plugin` makes
+    /// the retry key call `open` again.
+    fn failure(&self) -> Option<&Failure> {
+        None
+    }
+}
+
+/// The plugins enabled at build time, in menu order
+pub fn builtin() -> Vec<Box<dyn Plugin>> {
+    vec![
+        #[cfg(feature = "epoch-plugin")]
+        Box::<epoch::EpochPlugin>::default(),
+    ]
+}
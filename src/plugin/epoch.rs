@@ -0,0 +1,134 @@
+//! Progress through the cluster's current epoch, as an example of a plugin
+//! that loads its data in the background
+
+use super::{Plugin, PluginContext};
+use anyhow::Context;
+use crossterm::event::KeyEvent;
+use ratatui::{
+    layout::Rect,
+    style::Style,
+    text::Line,
+    widgets::{Block, Borders, Paragraph, Wrap},
+    Frame,
+};
+use solace::{
+    failure::{Failure, Retry},
+    keymap::Action,
+    task::Task,
+};
+use solana_sdk::epoch_info::EpochInfo;
+
+/// Target slot time; actual slots run a little slower
+const SLOT_MS: u64 = 400;
+
+const BAR_WIDTH: usize = 30;
+
+#[derive(Default)]
+pub struct EpochPlugin {
+    info: Option<EpochInfo>,
+    task: Option<Task<EpochInfo>>,
+    error: Option<Failure>,
+}
+
+impl Plugin for EpochPlugin {
+    fn name(&self) -> &str {
+        "Epoch"
+    }
+
+    fn open(&mut self, context: &PluginContext) {
+        let rpc_client = context.rpc_client.clone();
+        self.error = None;
+        self.task = Some(Task::spawn(move || {
+            rpc_client
+                .get_epoch_info()
+                .context("Failed to fetch epoch info")
+        }));
+    }
+
+    fn render(&self, f: &mut Frame, area: Rect, context: &PluginContext) {
+        let theme = context.theme;
+        let mut lines = vec![Line::from("Cluster Epoch"), Line::from("")];
+        match self.info {
+            Some(ref info) => lines.extend(info_lines(info, context.ascii)),
+            None if self.error.is_none() => lines.push(Line::from("Loading...")),
+            None => {}
+        }
+        if let Some(ref task) = self.task {
+            lines.push(Line::styled(
+                format!("{} refreshing", task.progress(context.ascii)),
+                Style::default().fg(theme.muted),
+            ));
+        }
+        if let Some(ref error) = self.error {
+            lines.push(Line::from(""));
+            lines.extend(error.lines(theme, context.retry_key.clone()));
+        }
+
+        let pane = Paragraph::new(lines)
+            .style(Style::default().fg(theme.text))
+            .wrap(Wrap { trim: false })
+            .block(Block::default().borders(Borders::ALL).title(self.name()));
+        f.render_widget(pane, area);
+    }
+
+    fn handle_key(
+        &mut self,
+        _key: &KeyEvent,
+        action: Option<Action>,
+        context: &PluginContext,
+    ) -> bool {
+        match action {
+            Some(Action::Refresh) if self.task.is_none() => self.open(context),
+            _ => return false,
+        }
+        true
+    }
+
+    fn poll(&mut self) {
+        let Some(task) = self.task.take() else {
+            return;
+        };
+        match task.poll() {
+            None => self.task = Some(task),
+            Some(Ok(info)) => self.info = Some(info),
+            Some(Err(e)) => self.error = Some(Failure::new(&e).retry(Retry::Plugin)),
+        }
+    }
+
+    fn busy(&self) -> bool {
+        self.task.is_some()
+    }
+
+    fn failure(&self) -> Option<&Failure> {
+        self.error.as_ref()
+    }
+}
+
+fn info_lines(info: &EpochInfo, ascii: bool) -> Vec<Line<'static>> {
+    let progress = info.slot_index as f64 / info.slots_in_epoch.max(1) as f64;
+    let filled = ((progress * BAR_WIDTH as f64) as usize).min(BAR_WIDTH);
+    let (full, empty) = if ascii { ('#', '-') } else { ('█', '░') };
+    let bar: String = std::iter::repeat_n(full, filled)
+        .chain(std::iter::repeat_n(empty, BAR_WIDTH - filled))
+        .collect();
+    let remaining = info.slots_in_epoch.saturating_sub(info.slot_index) * SLOT_MS / 1000;
+
+    let mut lines = vec![
+        Line::from(format!("Epoch: {}", info.epoch)),
+        Line::from(format!("{} {:.1}%", bar, progress * 100.0)),
+        Line::from(format!(
+            "Slot {} of {} (absolute {})",
+            info.slot_index, info.slots_in_epoch, info.absolute_slot
+        )),
+        Line::from(format!(
+            "Ends in about {}h {}m",
+            remaining / 3600,
+            remaining % 3600 / 60
+        )),
+        Line::from(format!("Block height: {}", info.block_height)),
+    ];
+    if let Some(count) = info.transaction_count {
+        lines.push(Line::from(format!("Transactions: {}", count)));
+    }
+    lines
+}
@@ -0,0 +1,337 @@
+//! Price alerts: the USD prices of SOL and tokens, checked in the background
+//! against the thresholds in `[price_alerts]`, with an alert each time one
+//! is crossed
+
+use crate::{
+    config::{PriceAlertConfig, PriceThreshold},
+    history::mint_symbol,
+};
+use anyhow::{Context, Result};
+use serde_json::Value;
+use solana_sdk::{pubkey, pubkey::Pubkey};
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::mpsc::{self, Receiver, RecvTimeoutError, Sender},
+    thread,
+    time::Duration,
+};
+
+/// The wrapped SOL mint, which SOL's price is quoted under
+pub const SOL_MINT: Pubkey = pubkey!("So11111111111111111111111111111111111111112");
+
+/// Which way a price went past its threshold
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Above,
+    Below,
+}
+
+/// A price that crossed one of its thresholds
+#[derive(Debug, Clone, PartialEq)]
+pub struct Alert {
+    pub mint: Pubkey,
+    pub direction: Direction,
+    pub threshold: f64,
+    pub price: f64,
+}
+
+impl Alert {
+    /// One line for a notification
+    pub fn message(&self) -> String {
+        let symbol = if self.mint == SOL_MINT {
+            "SOL".to_string()
+        } else {
+            mint_symbol(&self.mint)
+        };
+        format!(
+            "{} is {} ${}: ${}",
+            symbol,
+            match self.direction {
+                Direction::Above => "above",
+                Direction::Below => "below",
+            },
+            self.threshold,
+            format_price(self.price)
+        )
+    }
+}
+
+/// The thresholds in `config` by mint, SOL's under [`SOL_MINT`]
+pub fn thresholds(config: &PriceAlertConfig) -> Result<HashMap<Pubkey, PriceThreshold>> {
+    let mut thresholds = HashMap::new();
+    let sol = PriceThreshold {
+        above: config.sol_above,
+        below: config.sol_below,
+    };
+    if sol != PriceThreshold::default() {
+        thresholds.insert(SOL_MINT, sol);
+    }
+    for (mint, &threshold) in &config.tokens {
+        let mint = Pubkey::from_str(mint.trim())
+            .with_context(|| format!("Invalid mint in [price_alerts.tokens]: {}", mint))?;
+        thresholds.insert(mint, threshold);
+    }
+    Ok(thresholds)
+}
+
+/// The thresholds the price went past between two checks. The first check
+/// of a price has nothing to compare to, so it never alerts.
+pub fn crossed(
+    mint: Pubkey,
+    threshold: &PriceThreshold,
+    before: Option<f64>,
+    after: f64,
+) -> Vec<Alert> {
+    let Some(before) = before else {
+        return Vec::new();
+    };
+    let mut alerts = Vec::new();
+    if let Some(above) = threshold
+        .above
+        .filter(|&above| before < above && after >= above)
+    {
+        alerts.push(Alert {
+            mint,
+            direction: Direction::Above,
+            threshold: above,
+            price: after,
+        });
+    }
+    if let Some(below) = threshold
+        .below
+        .filter(|&below| before > below && after <= below)
+    {
+        alerts.push(Alert {
+            mint,
+            direction: Direction::Below,
+            threshold: below,
+            price: after,
+        });
+    }
+    alerts
+}
+
+/// Handle on the background thread checking the prices. The thread exits
+/// when this is dropped.
+pub struct Checker {
+    _stop: Sender<()>,
+    alerts: Receiver<Alert>,
+    errors: Receiver<String>,
+}
+
+impl Checker {
+    /// Start checking the prices `config` has thresholds for, or `None` if
+    /// it has none
+    pub fn start(config: &PriceAlertConfig) -> Result<Option<Self>> {
+        let thresholds = thresholds(config)?;
+        if thresholds.is_empty() {
+            return Ok(None);
+        }
+        let (stop, stopped) = mpsc::channel();
+        let (alerts, received) = mpsc::channel();
+        let (report, errors) = mpsc::channel();
+        let worker = Worker {
+            url: config.url.clone(),
+            interval: Duration::from_secs(config.seconds.max(1)),
+            thresholds,
+            prices: HashMap::new(),
+            alerts,
+            report,
+        };
+        thread::spawn(move || worker.run(&stopped));
+        Ok(Some(Self {
+            _stop: stop,
+            alerts: received,
+            errors,
+        }))
+    }
+
+    /// Thresholds crossed since the last call
+    pub fn take_alerts(&self) -> Vec<Alert> {
+        self.alerts.try_iter().collect()
+    }
+
+    /// Why the prices couldn't be fetched, since the last call
+    pub fn take_errors(&self) -> Vec<String> {
+        self.errors.try_iter().collect()
+    }
+}
+
+struct Worker {
+    url: String,
+    interval: Duration,
+    thresholds: HashMap<Pubkey, PriceThreshold>,
+    /// The price at the last check, by mint
+    prices: HashMap<Pubkey, f64>,
+    alerts: Sender<Alert>,
+    report: Sender<String>,
+}
+
+impl Worker {
+    fn run(mut self, stopped: &Receiver<()>) {
+        let mints: Vec<Pubkey> = self.thresholds.keys().copied().collect();
+        let mut failing = false;
+        loop {
+            match fetch(&self.url, &mints) {
+                Ok(prices) => {
+                    failing = false;
+                    for (mint, price) in prices {
+                        let Some(threshold) = self.thresholds.get(&mint) else {
+                            continue;
+                        };
+                        let before = self.prices.insert(mint, price);
+                        for alert in crossed(mint, threshold, before, price) {
+                            let _ = self.alerts.send(alert);
+                        }
+                    }
+                }
+                Err(e) => {
+                    let error = format!("{:#}", e);
+                    tracing::warn!(error, "Price check failed");
+                    // Once until the prices come back, not at every check
+                    if !failing {
+                        let _ = self.report.send(error);
+                    }
+                    failing = true;
+                }
+            }
+            match stopped.recv_timeout(self.interval) {
+                Err(RecvTimeoutError::Timeout) => {}
+                Ok(()) | Err(RecvTimeoutError::Disconnected) => return,
+            }
+        }
+    }
+}
+
+/// The USD prices of `mints`, leaving out those the API has none for
+fn fetch(url: &str, mints: &[Pubkey]) -> Result<HashMap<Pubkey, f64>> {
+    let ids = mints
+        .iter()
+        .map(Pubkey::to_string)
+        .collect::<Vec<_>>()
+        .join(",");
+    let response: Value = reqwest::blocking::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()?
+        .get(format!("{}?ids={}", url, ids))
+        .send()
+        .and_then(|response| response.error_for_status())
+        .context("Failed to fetch prices")?
+        .json()
+        .context("Invalid response from the price API")?;
+    Ok(parse_prices(&response))
+}
+
+/// `{"<mint>": {"usdPrice": 123.4, ...}, ...}`
+fn parse_prices(response: &Value) -> HashMap<Pubkey, f64> {
+    response
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter_map(|(mint, quote)| {
+            Some((Pubkey::from_str(mint).ok()?, quote["usdPrice"].as_f64()?))
+        })
+        .collect()
+}
+
+/// Cents for prices of a dollar or more, and enough digits to see a
+/// change for those below
+fn format_price(price: f64) -> String {
+    if price >= 1.0 {
+        format!("{:.2}", price)
+    } else {
+        format!("{:.6}", price)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn alerts_when_a_threshold_is_crossed() {
+        let threshold = PriceThreshold {
+            above: Some(200.0),
+            below: Some(120.0),
+        };
+        // Nothing to compare the first price to
+        assert!(crossed(SOL_MINT, &threshold, None, 250.0).is_empty());
+        // Staying on one side
+        assert!(crossed(SOL_MINT, &threshold, Some(150.0), 199.0).is_empty());
+        assert!(crossed(SOL_MINT, &threshold, Some(210.0), 220.0).is_empty());
+
+        let alerts = crossed(SOL_MINT, &threshold, Some(199.0), 200.5);
+        assert_eq!(
+            alerts,
+            [Alert {
+                mint: SOL_MINT,
+                direction: Direction::Above,
+                threshold: 200.0,
+                price: 200.5,
+            }]
+        );
+        assert_eq!(alerts[0].message(), "SOL is above $200: $200.50");
+
+        let alerts = crossed(SOL_MINT, &threshold, Some(121.0), 119.0);
+        assert_eq!(alerts[0].direction, Direction::Below);
+        assert_eq!(alerts[0].message(), "SOL is below $120: $119.00");
+    }
+
+    #[test]
+    fn reads_thresholds_and_prices() {
+        let bonk = Pubkey::from_str("DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263").unwrap();
+        let mut config = PriceAlertConfig::default();
+        assert!(thresholds(&config).unwrap().is_empty());
+        assert!(Checker::start(&config).unwrap().is_none());
+
+        config.sol_below = Some(100.0);
+        config.tokens.insert(
+            bonk.to_string(),
+            PriceThreshold {
+                above: Some(0.00003),
+                below: None,
+            },
+        );
+        let thresholds = thresholds(&config).unwrap();
+        assert_eq!(thresholds[&SOL_MINT].below, Some(100.0));
+        assert_eq!(thresholds[&bonk].above, Some(0.00003));
+
+        config
+            .tokens
+            .insert("bonk".to_string(), PriceThreshold::default());
+        assert!(Checker::start(&config).is_err());
+
+        let prices = parse_prices(&json!({
+            SOL_MINT.to_string(): {"usdPrice": 187.25, "decimals": 9},
+            bonk.to_string(): {"usdPrice": 0.0000241},
+            "unknown": {"usdPrice": 1.0},
+        }));
+        assert_eq!(prices.len(), 2);
+        assert_eq!(prices[&SOL_MINT], 187.25);
+        let alert = Alert {
+            mint: bonk,
+            direction: Direction::Above,
+            threshold: 0.00003,
+            price: prices[&bonk],
+        };
+        assert_eq!(alert.message(), "BONK is above $0.00003: $0.000024");
+    }
+
+    #[test]
+    fn unreachable_price_apis_are_reported_once() {
+        let config = PriceAlertConfig {
+            sol_above: Some(200.0),
+            seconds: 1,
+            url: "http://127.0.0.1:9/price".to_string(),
+            ..PriceAlertConfig::default()
+        };
+        let checker = Checker::start(&config).unwrap().unwrap();
+        thread::sleep(Duration::from_millis(2500));
+        let errors = checker.take_errors();
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert!(errors[0].starts_with("Failed to fetch prices"));
+        assert!(checker.take_alerts().is_empty());
+    }
+}
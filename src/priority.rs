@@ -0,0 +1,152 @@
+//! Priority fees: the compute-unit price suggested for a transaction, from
+//! what was recently paid to write to the same accounts, and the compute
+//! unit limit it's paid on, from what a simulation of it used
+
+use crate::config::PriorityFeeConfig;
+use crate::rpc::RpcApi;
+use anyhow::{Context, Result};
+use solana_client::rpc_config::RpcSimulateTransactionConfig;
+use solana_sdk::{
+    compute_budget::ComputeBudgetInstruction,
+    hash::Hash,
+    instruction::Instruction,
+    pubkey::Pubkey,
+    transaction::{Transaction, VersionedTransaction},
+};
+
+/// The most compute units a transaction can ask for
+pub const MAX_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+
+/// The share of the simulated units added on top, in percent, for a
+/// transaction that does a little more work when it lands than when it
+/// was simulated
+pub const COMPUTE_UNIT_MARGIN: u64 = 20;
+
+/// The suggested price in micro-lamports per compute unit for a transaction
+/// writing to `accounts`: the configured percentile of the recent fees,
+/// capped at the configured maximum. Slots where nothing was paid count, so
+/// a quiet cluster suggests 0.
+pub fn suggest(
+    rpc_client: &dyn RpcApi,
+    config: &PriorityFeeConfig,
+    accounts: &[Pubkey],
+) -> Result<u64> {
+    if config.percentile == 0 {
+        return Ok(0);
+    }
+    let mut fees: Vec<u64> = rpc_client
+        .get_recent_prioritization_fees(accounts)
+        .context("Failed to fetch recent priority fees")?
+        .into_iter()
+        .map(|fee| fee.prioritization_fee)
+        .collect();
+    Ok(percentile(&mut fees, config.percentile).min(config.max))
+}
+
+/// The compute unit limit for a transaction of `instructions` paid for by
+/// `payer`: the units a simulation of it consumed, plus the margin. The
+/// priority fee is charged on the limit rather than what's used, so this
+/// keeps it down for a plain transfer, and a program needing more than the
+/// default of 200,000 units per instruction doesn't run out. `None` if the
+/// simulation fails or reports nothing, leaving the default; the failure
+/// itself comes back when the transaction is sent.
+pub fn compute_unit_limit(
+    rpc_client: &dyn RpcApi,
+    payer: &Pubkey,
+    instructions: &[Instruction],
+    recent_blockhash: Hash,
+) -> Option<u32> {
+    // Simulated with the most it could get, including the limit
+    // instruction it's going to be sent with
+    let mut simulated = vec![ComputeBudgetInstruction::set_compute_unit_limit(
+        MAX_COMPUTE_UNIT_LIMIT,
+    )];
+    simulated.extend_from_slice(instructions);
+    let mut transaction = Transaction::new_with_payer(&simulated, Some(payer));
+    transaction.message.recent_blockhash = recent_blockhash;
+    let config = RpcSimulateTransactionConfig {
+        // Not signed yet
+        sig_verify: false,
+        commitment: Some(rpc_client.commitment()),
+        ..RpcSimulateTransactionConfig::default()
+    };
+    let result = rpc_client
+        .simulate_transaction_with_config(&VersionedTransaction::from(transaction), config)
+        .ok()?
+        .value;
+    if result.err.is_some() {
+        return None;
+    }
+    let units = result.units_consumed.filter(|&units| units > 0)?;
+    let limit = units + (units * COMPUTE_UNIT_MARGIN).div_ceil(100);
+    Some(
+        u32::try_from(limit).map_or(MAX_COMPUTE_UNIT_LIMIT, |limit| {
+            limit.min(MAX_COMPUTE_UNIT_LIMIT)
+        }),
+    )
+}
+
+/// The nearest-rank `percentile` of `fees`, 0 if there are none
+fn percentile(fees: &mut [u64], percentile: u8) -> u64 {
+    if fees.is_empty() {
+        return 0;
+    }
+    fees.sort_unstable();
+    let rank = (fees.len() * usize::from(percentile.min(100))).div_ceil(100);
+    fees[rank.saturating_sub(1)]
+}
+
+/// `micro_lamports` per compute unit as text, like `1,500 µL/CU`
+pub fn format_price(micro_lamports: u64, ascii: bool) -> String {
+    let digits = micro_lamports.to_string();
+    let mut grouped = String::new();
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    format!("{} {}L/CU", grouped, if ascii { "u" } else { "µ" })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::{MockRpc, MOCK_UNITS_CONSUMED};
+
+    #[test]
+    fn suggests_a_percentile_of_recent_fees() {
+        let mut fees = [0, 0, 100, 5_000, 1_000, 0, 200, 0, 0, 300];
+        assert_eq!(percentile(&mut fees, 50), 0);
+        assert_eq!(percentile(&mut fees, 75), 300);
+        assert_eq!(percentile(&mut fees, 100), 5_000);
+        assert_eq!(percentile(&mut [], 75), 0);
+
+        let rpc = MockRpc::default();
+        rpc.set_prioritization_fees(&fees);
+        let config = PriorityFeeConfig {
+            percentile: 90,
+            max: 2_000,
+        };
+        assert_eq!(suggest(&rpc, &config, &[]).unwrap(), 1_000);
+        let config = PriorityFeeConfig {
+            percentile: 100,
+            ..config
+        };
+        assert_eq!(suggest(&rpc, &config, &[]).unwrap(), 2_000);
+
+        assert_eq!(format_price(1_234_567, false), "1,234,567 µL/CU");
+        assert_eq!(format_price(500, true), "500 uL/CU");
+    }
+
+    #[test]
+    fn limits_compute_units_to_the_simulation_with_a_margin() {
+        let rpc = MockRpc::default();
+        let payer = Pubkey::new_unique();
+        let limit = compute_unit_limit(&rpc, &payer, &[], Hash::default()).unwrap();
+        assert_eq!(u64::from(limit), MOCK_UNITS_CONSUMED * 6 / 5);
+
+        rpc.fail("simulate_transaction_with_config");
+        assert_eq!(compute_unit_limit(&rpc, &payer, &[], Hash::default()), None);
+    }
+}
@@ -0,0 +1,133 @@
+//! Well-known programs, used to label history entries
+
+#[allow(deprecated)]
+use solana_sdk::{compute_budget, stake, system_program};
+use solana_sdk::{pubkey, pubkey::Pubkey};
+
+/// Memo program v2, which only annotates a transaction
+pub const MEMO_PROGRAM_ID: Pubkey = pubkey!("MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr");
+
+/// What interacting with a program usually means for the user
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Category {
+    Swap,
+    Liquidity,
+    Nft,
+    Lending,
+    LiquidStaking,
+}
+
+pub struct KnownProgram {
+    pub id: Pubkey,
+    pub name: &'static str,
+    pub category: Category,
+}
+
+const fn known(id: Pubkey, name: &'static str, category: Category) -> KnownProgram {
+    KnownProgram { id, name, category }
+}
+
+pub const KNOWN_PROGRAMS: &[KnownProgram] = &[
+    known(
+        pubkey!("JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4"),
+        "Jupiter",
+        Category::Swap,
+    ),
+    known(
+        pubkey!("JUP4Fb2cqiRUcaTHdrPC8h2gNsA2ETXiPDD33WcGuJB"),
+        "Jupiter v4",
+        Category::Swap,
+    ),
+    known(
+        pubkey!("675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8"),
+        "Raydium AMM",
+        Category::Swap,
+    ),
+    known(
+        pubkey!("CAMMCzo5YL8w4VFF8KVHrK22GGUsp5VTaW7grrKgrWqK"),
+        "Raydium CLMM",
+        Category::Liquidity,
+    ),
+    known(
+        pubkey!("CPMMoo8L3F4NbTegBCKVNunggL7H1ZpdTHKxQB5qKP1C"),
+        "Raydium CPMM",
+        Category::Swap,
+    ),
+    known(
+        pubkey!("whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc"),
+        "Orca Whirlpools",
+        Category::Swap,
+    ),
+    known(
+        pubkey!("LBUZKhRxPF3XUpBCjp4YzTKgLccjZhTSDM9YuVaPwxo"),
+        "Meteora DLMM",
+        Category::Liquidity,
+    ),
+    known(
+        pubkey!("TSWAPaqyCSx2KABk68Shruf4rp7CxcNi8hAsbdwmHbN"),
+        "Tensor Swap",
+        Category::Nft,
+    ),
+    known(
+        pubkey!("TCMPhJdwDryooaGtiocG1u3xcYbRpiJzb283XfCZsDp"),
+        "Tensor Marketplace",
+        Category::Nft,
+    ),
+    known(
+        pubkey!("M2mx93ekt1fmXSVkTrUL9xVFHkmME8HTUi5Cyc5aF7K"),
+        "Magic Eden",
+        Category::Nft,
+    ),
+    known(
+        pubkey!("MFv2hWf31Z9kbCa1snEPYctwafyhdvnV7FZnsebVacA"),
+        "Marginfi",
+        Category::Lending,
+    ),
+    known(
+        pubkey!("KLend2g3cP87fffoy8q1mQqGKjrxjC8boSyAYavgmjD"),
+        "Kamino Lend",
+        Category::Lending,
+    ),
+    known(
+        pubkey!("So1endDq2YkqhipRh3WViPa8hdiSpxWy6z3Z6tMCpAo"),
+        "Solend",
+        Category::Lending,
+    ),
+    known(
+        pubkey!("MarBmsSgKXdrN1egZf5sqe1TMai9K1rChYNDJgjq7aD"),
+        "Marinade",
+        Category::LiquidStaking,
+    ),
+    known(
+        pubkey!("SPoo1Ku8WFXoNDMHPsrGSTSG1Y47rzgn41SLUNakuHy"),
+        "SPL Stake Pool",
+        Category::LiquidStaking,
+    ),
+];
+
+/// Native and SPL programs most transactions call
+const CORE_PROGRAMS: &[(Pubkey, &str)] = &[
+    (system_program::ID, "System Program"),
+    (stake::program::ID, "Stake Program"),
+    (compute_budget::ID, "Compute Budget"),
+    (spl_token::ID, "Token Program"),
+    (
+        pubkey!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb"),
+        "Token-2022",
+    ),
+    (spl_associated_token_account::ID, "Associated Token Account"),
+    (MEMO_PROGRAM_ID, "Memo"),
+];
+
+pub fn lookup(id: &Pubkey) -> Option<&'static KnownProgram> {
+    KNOWN_PROGRAMS.iter().find(|program| program.id == *id)
+}
+
+/// Display name for core and well-known programs
+pub fn name(id: &Pubkey) -> Option<&'static str> {
+    CORE_PROGRAMS
+        .iter()
+        .find(|(program, _)| program == id)
+        .map(|(_, name)| *name)
+        .or_else(|| lookup(id).map(|program| program.name))
+}
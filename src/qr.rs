@@ -0,0 +1,209 @@
+//! QR codes drawn to fit the terminal, and saved as image files for
+//! invoices and anywhere outside it
+
+use anyhow::{Context, Result};
+use flate2::{write::ZlibEncoder, Compression, Crc};
+use qrcode::{
+    render::{svg, unicode},
+    Color, EcLevel, QrCode,
+};
+use std::{fs, path::Path};
+
+/// Pixels per QR module in the PNG output
+const PNG_MODULE: usize = 8;
+
+/// Quiet zone around the code, in modules
+const QUIET_ZONE: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QrFormat {
+    Png,
+    Svg,
+}
+
+impl QrFormat {
+    /// SVG for `.svg` files, PNG for anything else
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension() {
+            Some(ext) if ext.eq_ignore_ascii_case("svg") => Self::Svg,
+            _ => Self::Png,
+        }
+    }
+}
+
+/// The QR code for `data` as the largest text that fits in `width` by
+/// `height` cells: two columns and a line per module, then half a line per
+/// module, each at medium error correction before low. Light modules are
+/// drawn, so it reads right as light text on a dark background. `None`
+/// when even the smallest doesn't fit.
+pub fn fit(data: &str, width: u16, height: u16, ascii: bool) -> Option<Vec<String>> {
+    let codes: Vec<QrCode> = [EcLevel::M, EcLevel::L]
+        .into_iter()
+        .filter_map(|level| QrCode::with_error_correction_level(data, level).ok())
+        .collect();
+    let full = |code: &QrCode| {
+        code.render::<char>()
+            .dark_color(' ')
+            .light_color(if ascii { '#' } else { '█' })
+            .module_dimensions(2, 1)
+            .build()
+    };
+    let half = |code: &QrCode| {
+        code.render::<unicode::Dense1x2>()
+            .dark_color(unicode::Dense1x2::Light)
+            .light_color(unicode::Dense1x2::Dark)
+            .build()
+    };
+    // Half blocks have no ASCII equivalent
+    let renderers: &[&dyn Fn(&QrCode) -> String] = if ascii { &[&full] } else { &[&full, &half] };
+    renderers
+        .iter()
+        .flat_map(|render| codes.iter().map(render))
+        .map(|text| text.lines().map(str::to_string).collect::<Vec<_>>())
+        .find(|lines| {
+            lines.len() <= usize::from(height)
+                && lines
+                    .iter()
+                    .all(|line| line.chars().count() <= usize::from(width))
+        })
+}
+
+/// Encode `data` as a QR code in `format`
+pub fn render(data: &str, format: QrFormat) -> Result<Vec<u8>> {
+    let code = QrCode::new(data).context("Failed to encode QR code")?;
+    match format {
+        QrFormat::Svg => Ok(code
+            .render::<svg::Color>()
+            .quiet_zone(true)
+            .min_dimensions(256, 256)
+            .build()
+            .into_bytes()),
+        QrFormat::Png => png(&code),
+    }
+}
+
+/// Save `data` as a QR code image, picking the format from the extension
+pub fn write_to_file(data: &str, path: &Path) -> Result<()> {
+    let contents = render(data, QrFormat::from_path(path))?;
+    fs::write(path, contents)
+        .with_context(|| format!("Failed to write QR code: {}", path.display()))
+}
+
+/// A black and white PNG of the code, one byte per pixel in grayscale
+fn png(code: &QrCode) -> Result<Vec<u8>> {
+    let modules = code.width() + 2 * QUIET_ZONE;
+    let side = modules * PNG_MODULE;
+    let colors = code.to_colors();
+    let dark = |x: usize, y: usize| {
+        let (x, y) = (x / PNG_MODULE, y / PNG_MODULE);
+        (QUIET_ZONE..QUIET_ZONE + code.width()).contains(&x)
+            && (QUIET_ZONE..QUIET_ZONE + code.width()).contains(&y)
+            && colors[(y - QUIET_ZONE) * code.width() + x - QUIET_ZONE] == Color::Dark
+    };
+
+    // Each row starts with filter type 0 (none)
+    let mut pixels = Vec::with_capacity(side * (side + 1));
+    for y in 0..side {
+        pixels.push(0);
+        pixels.extend((0..side).map(|x| if dark(x, y) { 0 } else { 0xff }));
+    }
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    std::io::Write::write_all(&mut encoder, &pixels)?;
+    let data = encoder.finish()?;
+
+    let side = u32::try_from(side).context("QR code too large")?;
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&side.to_be_bytes());
+    header.extend_from_slice(&side.to_be_bytes());
+    // 8-bit grayscale, deflate, adaptive filtering, not interlaced
+    header.extend_from_slice(&[8, 0, 0, 0, 0]);
+
+    let mut out = b"\x89PNG\r\n\x1a\n".to_vec();
+    chunk(&mut out, b"IHDR", &header)?;
+    chunk(&mut out, b"IDAT", &data)?;
+    chunk(&mut out, b"IEND", &[])?;
+    Ok(out)
+}
+
+/// Append a PNG chunk: length, type, data and the CRC of type and data
+fn chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) -> Result<()> {
+    let len = u32::try_from(data.len()).context("PNG chunk too large")?;
+    out.extend_from_slice(&len.to_be_bytes());
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    let mut crc = Crc::new();
+    crc.update(kind);
+    crc.update(data);
+    out.extend_from_slice(&crc.sum().to_be_bytes());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::ZlibDecoder;
+    use std::io::Read;
+
+    #[test]
+    fn formats_by_extension() {
+        assert_eq!(QrFormat::from_path(Path::new("a.SVG")), QrFormat::Svg);
+        assert_eq!(QrFormat::from_path(Path::new("a.png")), QrFormat::Png);
+        assert_eq!(QrFormat::from_path(Path::new("a")), QrFormat::Png);
+
+        let svg = String::from_utf8(render("address", QrFormat::Svg).unwrap()).unwrap();
+        assert!(svg.contains("<svg"));
+    }
+
+    #[test]
+    fn fits_the_largest_rendering_that_fits() {
+        let address = "9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM";
+        let side = |level| {
+            QrCode::with_error_correction_level(address, level)
+                .unwrap()
+                .width()
+                + 8
+        };
+        let (medium, low) = (side(EcLevel::M), side(EcLevel::L));
+
+        let lines = fit(address, 200, 100, false).unwrap();
+        assert_eq!(lines.len(), medium);
+        assert_eq!(lines[0].chars().count(), 2 * medium);
+
+        // Too short for full blocks: half blocks, at medium then low
+        let short = u16::try_from(medium.div_ceil(2)).unwrap();
+        let lines = fit(address, 200, short, false).unwrap();
+        assert_eq!(lines.len(), medium.div_ceil(2));
+        assert_eq!(lines[0].chars().count(), medium);
+        if low < medium {
+            let lines = fit(address, u16::try_from(low).unwrap(), 100, false).unwrap();
+            assert_eq!(lines[0].chars().count(), low);
+        }
+
+        assert!(fit(address, 200, short, true).is_none());
+        assert!(fit(address, 20, 10, false).is_none());
+    }
+
+    #[test]
+    fn png_has_the_code_in_its_pixels() {
+        let png = render("address", QrFormat::Png).unwrap();
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        assert_eq!(&png[12..16], b"IHDR");
+        let side = u32::from_be_bytes(png[16..20].try_into().unwrap()) as usize;
+        let code = QrCode::new("address").unwrap();
+        assert_eq!(side, (code.width() + 2 * QUIET_ZONE) * PNG_MODULE);
+        assert!(png.ends_with(&[0, 0, 0, 0, b'I', b'E', b'N', b'D', 0xae, 0x42, 0x60, 0x82]));
+
+        let len = u32::from_be_bytes(png[33..37].try_into().unwrap()) as usize;
+        assert_eq!(&png[37..41], b"IDAT");
+        let mut pixels = Vec::new();
+        ZlibDecoder::new(&png[41..41 + len])
+            .read_to_end(&mut pixels)
+            .unwrap();
+        assert_eq!(pixels.len(), side * (side + 1));
+        // The quiet zone is white and the finder pattern's corner black
+        let row = |y: usize| &pixels[y * (side + 1) + 1..(y + 1) * (side + 1)];
+        assert_eq!(row(0)[0], 0xff);
+        let corner = QUIET_ZONE * PNG_MODULE;
+        assert_eq!(row(corner)[corner], 0);
+    }
+}
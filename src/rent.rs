@@ -0,0 +1,115 @@
+//! Rent-exempt minimums at the cluster's current rates, and the checks that
+//! keep sends from leaving an account below one
+
+use crate::rpc::RpcApi;
+use anyhow::{Context, Result};
+#[allow(deprecated)]
+use solana_sdk::system_instruction;
+use solana_sdk::{
+    message::{Message, VersionedMessage},
+    pubkey::Pubkey,
+};
+
+/// Data lengths of the accounts most often created, as their programs lay
+/// them out
+pub const COMMON_SIZES: [(&str, usize); 5] = [
+    ("System account", 0),
+    ("Nonce account", 80),
+    ("Token mint", 82),
+    ("Token account", 165),
+    ("Stake account", 200),
+];
+
+/// Lamports an account with `data_len` bytes of data needs to be exempt
+pub fn minimum(rpc_client: &dyn RpcApi, data_len: usize) -> Result<u64> {
+    rpc_client
+        .get_minimum_balance_for_rent_exemption(data_len)
+        .context("Failed to fetch rent-exempt minimum")
+}
+
+/// The minimum for each of `COMMON_SIZES`
+pub fn common_minimums(rpc_client: &dyn RpcApi) -> Result<Vec<(&'static str, usize, u64)>> {
+    COMMON_SIZES
+        .iter()
+        .map(|&(name, data_len)| Ok((name, data_len, minimum(rpc_client, data_len)?)))
+        .collect()
+}
+
+/// The rent-exempt minimum if `recipient` doesn't exist yet and `lamports`
+/// is less than it, since such a transfer would leave the account unusable
+/// (or be rejected by the runtime)
+pub fn recipient_shortfall(
+    rpc_client: &dyn RpcApi,
+    recipient: &Pubkey,
+    lamports: u64,
+) -> Result<Option<u64>> {
+    let account = rpc_client
+        .get_account_with_commitment(recipient, rpc_client.commitment())
+        .context("Failed to fetch recipient account")?
+        .value;
+    if account.is_some() {
+        return Ok(None);
+    }
+    let minimum = minimum(rpc_client, 0)?;
+    Ok((lamports < minimum).then_some(minimum))
+}
+
+/// The rent-exempt minimum if sending `lamports` and the fee would leave
+/// `sender` with some SOL but less than the minimum, which the runtime
+/// rejects. The fee only counts if `sender` is the `fee_payer`. Emptying
+/// the account is fine, and so is not having enough, which fails on its own.
+pub fn sender_shortfall(
+    rpc_client: &dyn RpcApi,
+    sender: &Pubkey,
+    fee_payer: &Pubkey,
+    lamports: u64,
+) -> Result<Option<u64>> {
+    let balance = rpc_client
+        .get_balance(sender)
+        .context("Failed to fetch balance")?;
+    let fee = if sender == fee_payer {
+        let message = Message::new(
+            &[system_instruction::transfer(sender, sender, lamports)],
+            Some(sender),
+        );
+        rpc_client
+            .get_fee_for_message(&VersionedMessage::Legacy(message))
+            .context("Failed to fetch the fee")?
+    } else {
+        0
+    };
+    let Some(left) = balance.checked_sub(lamports.saturating_add(fee)) else {
+        return Ok(None);
+    };
+    if left == 0 {
+        return Ok(None);
+    }
+    let minimum = minimum(rpc_client, 0)?;
+    Ok((left < minimum).then_some(minimum))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::{MockRpc, MOCK_FEE, MOCK_RENT_MINIMUM};
+
+    #[test]
+    fn only_flags_sends_that_leave_dust() {
+        let rpc = MockRpc::default();
+        let sender = Pubkey::new_unique();
+        let balance = 10 * MOCK_RENT_MINIMUM;
+        rpc.set_balance(sender, balance);
+
+        let shortfall = |lamports| sender_shortfall(&rpc, &sender, &sender, lamports).unwrap();
+        assert_eq!(shortfall(balance - MOCK_FEE - 1), Some(MOCK_RENT_MINIMUM));
+        assert_eq!(shortfall(balance - MOCK_FEE), None);
+        assert_eq!(shortfall(balance), None);
+        assert_eq!(shortfall(balance - MOCK_FEE - MOCK_RENT_MINIMUM), None);
+
+        // Only the amount leaves when someone else pays the fee
+        let fee_payer = Pubkey::new_unique();
+        let shortfall = |lamports| sender_shortfall(&rpc, &sender, &fee_payer, lamports).unwrap();
+        assert_eq!(shortfall(balance - MOCK_FEE), Some(MOCK_RENT_MINIMUM));
+        assert_eq!(shortfall(balance - MOCK_RENT_MINIMUM), None);
+    }
+}
@@ -0,0 +1,655 @@
+//! Decoded transaction history written out as CSV or JSON for accounting, or
+//! in the CSV layouts crypto-tax importers accept, along with the staking
+//! rewards paid to the wallet's stake accounts, which no transaction shows
+
+use crate::history::{self, HistoryEntry, TxDetails, TxKind, KNOWN_MINTS, SOL_DECIMALS};
+use crate::programs::{self, Category};
+use crate::staking::Reward;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::{fs, path::Path};
+
+const CSV_HEADER: &[&str] = &[
+    "timestamp",
+    "signature",
+    "status",
+    "type",
+    "counterparty",
+    "amount",
+    "token",
+    "mint",
+    "fee",
+    "memo",
+    "note",
+];
+
+const KOINLY_HEADER: &[&str] = &[
+    "Date",
+    "Sent Amount",
+    "Sent Currency",
+    "Received Amount",
+    "Received Currency",
+    "Fee Amount",
+    "Fee Currency",
+    "Net Worth Amount",
+    "Net Worth Currency",
+    "Label",
+    "Description",
+    "TxHash",
+];
+
+const COINTRACKER_HEADER: &[&str] = &[
+    "Date",
+    "Received Quantity",
+    "Received Currency",
+    "Sent Quantity",
+    "Sent Currency",
+    "Fee Amount",
+    "Fee Currency",
+    "Tag",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum ReportFormat {
+    Csv,
+    Json,
+    /// Koinly universal import layout
+    Koinly,
+    /// CoinTracker CSV import layout
+    Cointracker,
+}
+
+impl ReportFormat {
+    pub const ALL: [Self; 4] = [Self::Csv, Self::Json, Self::Koinly, Self::Cointracker];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Csv => "CSV",
+            Self::Json => "JSON",
+            Self::Koinly => "Koinly",
+            Self::Cointracker => "CoinTracker",
+        }
+    }
+
+    /// JSON for `.json` files, CSV for anything else
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension() {
+            Some(ext) if ext.eq_ignore_ascii_case("json") => Self::Json,
+            _ => Self::Csv,
+        }
+    }
+}
+
+/// One transaction or staking reward as written to a JSON report
+#[derive(Serialize)]
+struct Record<'a> {
+    /// `YYYY-MM-DD HH:MM:SS` in UTC, if the RPC knew the block time
+    timestamp: Option<String>,
+    /// `None` for a staking reward
+    signature: Option<&'a str>,
+    slot: u64,
+    /// The epoch a staking reward was for
+    #[serde(skip_serializing_if = "Option::is_none")]
+    epoch: Option<u64>,
+    status: &'static str,
+    #[serde(rename = "type")]
+    kind: Option<&'static str>,
+    counterparty: Option<String>,
+    /// SOL paid in fees by the wallet, as a decimal string
+    fee: Option<String>,
+    memo: Option<&'a str>,
+    note: Option<&'a str>,
+    changes: Vec<Change>,
+}
+
+/// A net balance change, as a decimal string with its sign
+#[derive(Serialize)]
+struct Change {
+    amount: String,
+    token: String,
+    /// `None` for SOL
+    mint: Option<String>,
+}
+
+impl<'a> Record<'a> {
+    fn new(entry: &'a HistoryEntry) -> Self {
+        let details = entry.details.as_ref();
+        let mut changes = Vec::new();
+        if let Some(details) = details {
+            // Token-only transactions still move SOL for the fee, but a zero
+            // SOL row next to token rows would only be noise
+            if details.lamports_delta != 0 || details.token_deltas.is_empty() {
+                changes.push(Change {
+                    amount: decimal(i128::from(details.lamports_delta), SOL_DECIMALS),
+                    token: "SOL".to_string(),
+                    mint: None,
+                });
+            }
+            changes.extend(details.token_deltas.iter().map(|delta| Change {
+                amount: decimal(delta.amount, delta.decimals),
+                token: delta.symbol(),
+                mint: Some(delta.mint.to_string()),
+            }));
+        }
+
+        Self {
+            timestamp: entry.block_time.map(history::format_datetime),
+            signature: Some(&entry.signature),
+            slot: entry.slot,
+            epoch: None,
+            status: if entry.failed { "failed" } else { "ok" },
+            kind: details.map(|details| details.label()),
+            counterparty: details
+                .and_then(|details| details.counterparty)
+                .map(|key| key.to_string()),
+            fee: details.map(|details| decimal(i128::from(details.paid_fee()), SOL_DECIMALS)),
+            memo: entry.memo.as_deref(),
+            note: entry.note.as_deref(),
+            changes,
+        }
+    }
+
+    /// A reward, from the stake account it was paid to
+    fn reward(reward: &Reward) -> Self {
+        Self {
+            timestamp: reward.block_time.map(history::format_datetime),
+            signature: None,
+            slot: reward.slot,
+            epoch: Some(reward.epoch),
+            status: "ok",
+            kind: Some(REWARD_LABEL),
+            counterparty: Some(reward.stake_account.to_string()),
+            fee: None,
+            memo: None,
+            note: None,
+            changes: vec![Change {
+                amount: decimal(i128::from(reward.lamports), SOL_DECIMALS),
+                token: "SOL".to_string(),
+                mint: None,
+            }],
+        }
+    }
+}
+
+/// The type rewards are listed as
+const REWARD_LABEL: &str = "Staking reward";
+
+/// Entries and rewards (both newest first) as records, newest first
+fn records<'a>(entries: &[&'a HistoryEntry], rewards: &[Reward]) -> Vec<Record<'a>> {
+    let mut records = Vec::with_capacity(entries.len() + rewards.len());
+    let mut rewards = rewards.iter().peekable();
+    for entry in entries {
+        while let Some(reward) = rewards.next_if(|reward| reward.slot > entry.slot) {
+            records.push(Record::reward(reward));
+        }
+        records.push(Record::new(entry));
+    }
+    records.extend(rewards.map(Record::reward));
+    records
+}
+
+/// Render entries and staking rewards (newest first, as loaded) as a report
+pub fn render(
+    entries: &[&HistoryEntry],
+    rewards: &[Reward],
+    format: ReportFormat,
+) -> Result<String> {
+    let records = records(entries, rewards);
+    match format {
+        ReportFormat::Json => {
+            let mut json = serde_json::to_string_pretty(&records)?;
+            json.push('\n');
+            Ok(json)
+        }
+        ReportFormat::Csv => Ok(render_csv(&records)),
+        ReportFormat::Koinly => Ok(render_koinly(entries, rewards)),
+        ReportFormat::Cointracker => Ok(render_cointracker(entries, rewards)),
+    }
+}
+
+/// Entries and rewards as the JSON report's records, to nest in other JSON
+pub fn to_json(entries: &[&HistoryEntry], rewards: &[Reward]) -> Result<serde_json::Value> {
+    Ok(serde_json::to_value(records(entries, rewards))?)
+}
+
+pub fn write_to_file(
+    entries: &[&HistoryEntry],
+    rewards: &[Reward],
+    format: ReportFormat,
+    path: &Path,
+) -> Result<()> {
+    let contents = render(entries, rewards, format)?;
+    fs::write(path, contents).with_context(|| format!("Failed to write: {}", path.display()))
+}
+
+/// One row per balance change. The fee is only on a transaction's first row
+/// so summing the column doesn't count it twice.
+fn render_csv(records: &[Record]) -> String {
+    let mut out = CSV_HEADER.join(",");
+    out.push('\n');
+    for record in records {
+        let timestamp = record.timestamp.as_deref().unwrap_or_default();
+        let fee = record.fee.as_deref().unwrap_or_default();
+        let mut rows: Vec<[&str; 3]> = record
+            .changes
+            .iter()
+            .map(|change| {
+                [
+                    change.amount.as_str(),
+                    change.token.as_str(),
+                    change.mint.as_deref().unwrap_or_default(),
+                ]
+            })
+            .collect();
+        if rows.is_empty() {
+            rows.push(["", "", ""]);
+        }
+        for (i, [amount, token, mint]) in rows.into_iter().enumerate() {
+            let fields = [
+                timestamp,
+                record.signature.unwrap_or_default(),
+                record.status,
+                record.kind.unwrap_or_default(),
+                record.counterparty.as_deref().unwrap_or_default(),
+                amount,
+                token,
+                mint,
+                if i == 0 { fee } else { "" },
+                record.memo.unwrap_or_default(),
+                record.note.unwrap_or_default(),
+            ];
+            push_csv_row(&mut out, &fields);
+        }
+    }
+    out
+}
+
+fn push_csv_row(out: &mut String, fields: &[&str]) {
+    let fields: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+    out.push_str(&fields.join(","));
+    out.push('\n');
+}
+
+/// How a tax importer should treat a row
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TaxClass {
+    /// Plain deposit or withdrawal
+    Transfer,
+    /// One asset out, another in
+    Swap,
+    /// SOL moved into one of the wallet's own stake accounts
+    Stake,
+    /// SOL withdrawn back from a stake account
+    Unstake,
+    /// A payout the wallet received without sending anything or paying for
+    /// the transaction, from a staking or lending program
+    StakingReward,
+    /// Only the fee was spent, e.g. a failed transaction
+    Fee,
+}
+
+/// One row of a tax import: at most one asset sent and one received
+struct TaxRow<'a> {
+    /// Empty for a staking reward
+    signature: &'a str,
+    description: String,
+    timestamp: i64,
+    class: TaxClass,
+    /// Unsigned decimal amount and currency
+    sent: Option<(String, String)>,
+    received: Option<(String, String)>,
+    /// Fee in SOL, on the first row of a transaction only
+    fee: Option<String>,
+}
+
+/// Split a transaction into tax rows. Entries without a block time or
+/// decoded details can't be classified and are left out.
+fn tax_rows(entry: &HistoryEntry) -> Vec<TaxRow<'_>> {
+    let (Some(timestamp), Some(details)) = (entry.block_time, entry.details.as_ref()) else {
+        return Vec::new();
+    };
+    let fee =
+        (details.paid_fee() > 0).then(|| unsigned(i128::from(details.paid_fee()), SOL_DECIMALS));
+    let row = |class, sent, received, fee| TaxRow {
+        signature: &entry.signature,
+        description: description(entry),
+        timestamp,
+        class,
+        sent,
+        received,
+        fee,
+    };
+
+    // A failed transaction only costs its fee
+    let mut changes = Vec::new();
+    if !entry.failed {
+        // The fee is reported separately, so take it back out of the SOL change
+        let sol = i128::from(details.lamports_delta) + i128::from(details.paid_fee());
+        changes.push((sol, currency(None), SOL_DECIMALS));
+        changes.extend(
+            details
+                .token_deltas
+                .iter()
+                .map(|delta| (delta.amount, currency(Some(&delta.mint)), delta.decimals)),
+        );
+    }
+    let amount = |(value, currency, decimals): &(i128, String, u8)| {
+        (unsigned(*value, *decimals), currency.clone())
+    };
+    let mut sent = changes.iter().filter(|change| change.0 < 0).map(amount);
+    let mut received = changes.iter().filter(|change| change.0 > 0).map(amount);
+
+    let mut rows = Vec::new();
+    loop {
+        let fee = if rows.is_empty() { fee.clone() } else { None };
+        let (class, sent, received) = match (sent.next(), received.next()) {
+            (Some(sent), Some(received)) => (TaxClass::Swap, Some(sent), Some(received)),
+            (Some(sent), None) => (transfer_class(details, false), Some(sent), None),
+            (None, Some(received)) => (transfer_class(details, true), None, Some(received)),
+            (None, None) => break,
+        };
+        rows.push(row(class, sent, received, fee));
+    }
+    if rows.is_empty() {
+        if let Some(fee) = fee {
+            rows.push(row(TaxClass::Fee, Some((fee, currency(None))), None, None));
+        }
+    }
+    rows
+}
+
+/// A reward as a tax row, if the RPC knew when it was paid
+fn reward_row<'a>(reward: &Reward) -> Option<TaxRow<'a>> {
+    Some(TaxRow {
+        signature: "",
+        description: format!(
+            "{} for epoch {} ({})",
+            REWARD_LABEL, reward.epoch, reward.stake_account
+        ),
+        timestamp: reward.block_time?,
+        class: TaxClass::StakingReward,
+        sent: None,
+        received: Some((
+            unsigned(i128::from(reward.lamports), SOL_DECIMALS),
+            currency(None),
+        )),
+        fee: None,
+    })
+}
+
+/// The tax rows of entries and rewards, newest first
+fn all_tax_rows<'a>(entries: &[&'a HistoryEntry], rewards: &[Reward]) -> Vec<TaxRow<'a>> {
+    let mut rows: Vec<TaxRow> = entries.iter().copied().flat_map(tax_rows).collect();
+    rows.extend(rewards.iter().filter_map(reward_row));
+    // Stable, so a transaction's rows stay in order
+    rows.sort_by_key(|row| std::cmp::Reverse(row.timestamp));
+    rows
+}
+
+fn transfer_class(details: &TxDetails, incoming: bool) -> TaxClass {
+    let staking_program = match details.kind {
+        TxKind::Stake => true,
+        TxKind::Program(program) => matches!(
+            programs::lookup(&program).map(|p| p.category),
+            Some(Category::LiquidStaking | Category::Lending)
+        ),
+        _ => false,
+    };
+    match (details.kind, incoming) {
+        (TxKind::Stake, false) => TaxClass::Stake,
+        (_, true) if staking_program && !details.fee_payer => TaxClass::StakingReward,
+        (TxKind::Stake, true) => TaxClass::Unstake,
+        _ => TaxClass::Transfer,
+    }
+}
+
+/// Known symbol, or the mint address for anything else
+fn currency(mint: Option<&solana_sdk::pubkey::Pubkey>) -> String {
+    match mint {
+        None => "SOL".to_string(),
+        Some(mint) => KNOWN_MINTS
+            .iter()
+            .find(|(known, _)| known == mint)
+            .map_or_else(|| mint.to_string(), |(_, symbol)| symbol.to_string()),
+    }
+}
+
+/// What the importer shows next to the row: the history label, memo and
+/// the user's note
+fn description(entry: &HistoryEntry) -> String {
+    let label = entry
+        .details
+        .as_ref()
+        .map(|details| match details.program_name() {
+            Some(name) => format!("{} ({})", details.label(), name),
+            None => details.label().to_string(),
+        });
+    let parts: Vec<String> = label
+        .into_iter()
+        .chain(entry.memo.clone())
+        .chain(entry.note.clone())
+        .collect();
+    parts.join(" - ")
+}
+
+fn render_koinly(entries: &[&HistoryEntry], rewards: &[Reward]) -> String {
+    let mut out = KOINLY_HEADER.join(",");
+    out.push('\n');
+    for row in all_tax_rows(entries, rewards) {
+        let date = format!("{} UTC", history::format_datetime(row.timestamp));
+        let (sent_amount, sent_currency) = split(&row.sent);
+        let (received_amount, received_currency) = split(&row.received);
+        let label = match row.class {
+            TaxClass::Transfer | TaxClass::Swap => "",
+            TaxClass::Stake => "stake",
+            TaxClass::Unstake => "unstake",
+            TaxClass::StakingReward => "reward",
+            TaxClass::Fee => "cost",
+        };
+        push_csv_row(
+            &mut out,
+            &[
+                &date,
+                sent_amount,
+                sent_currency,
+                received_amount,
+                received_currency,
+                row.fee.as_deref().unwrap_or_default(),
+                if row.fee.is_some() { "SOL" } else { "" },
+                "",
+                "",
+                label,
+                &row.description,
+                row.signature,
+            ],
+        );
+    }
+    out
+}
+
+fn render_cointracker(entries: &[&HistoryEntry], rewards: &[Reward]) -> String {
+    let mut out = COINTRACKER_HEADER.join(",");
+    out.push('\n');
+    for row in all_tax_rows(entries, rewards) {
+        // CoinTracker wants MM/DD/YYYY HH:MM:SS
+        let datetime = history::format_datetime(row.timestamp);
+        let date = format!(
+            "{}/{}/{} {}",
+            &datetime[5..7],
+            &datetime[8..10],
+            &datetime[..4],
+            &datetime[11..]
+        );
+        let (sent_amount, sent_currency) = split(&row.sent);
+        let (received_amount, received_currency) = split(&row.received);
+        let tag = if row.class == TaxClass::StakingReward {
+            "staked"
+        } else {
+            ""
+        };
+        push_csv_row(
+            &mut out,
+            &[
+                &date,
+                received_amount,
+                received_currency,
+                sent_amount,
+                sent_currency,
+                row.fee.as_deref().unwrap_or_default(),
+                if row.fee.is_some() { "SOL" } else { "" },
+                tag,
+            ],
+        );
+    }
+    out
+}
+
+fn split(amount: &Option<(String, String)>) -> (&str, &str) {
+    amount
+        .as_ref()
+        .map_or(("", ""), |(amount, currency)| (amount, currency))
+}
+
+/// Quote a field if it contains a separator, quote or line break
+pub(crate) fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Signed decimal without the `+` the history view shows
+fn decimal(amount: i128, decimals: u8) -> String {
+    history::format_amount(amount, decimals)
+        .trim_start_matches('+')
+        .to_string()
+}
+
+fn unsigned(amount: i128, decimals: u8) -> String {
+    history::format_amount(amount, decimals)[1..].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::{TokenDelta, TxDetails, TxKind};
+    use solana_sdk::pubkey::Pubkey;
+
+    fn entry(kind: TxKind, lamports_delta: i64, fee_payer: bool, tokens: &[i128]) -> HistoryEntry {
+        HistoryEntry {
+            signature: "sig".to_string(),
+            slot: 1,
+            block_time: Some(1_709_251_199),
+            failed: false,
+            memo: Some("rent, march".to_string()),
+            note: None,
+            reference: None,
+            details: Some(TxDetails {
+                kind,
+                lamports_delta,
+                fee: 5_000,
+                fee_payer,
+                counterparty: None,
+                token_deltas: tokens
+                    .iter()
+                    .map(|&amount| TokenDelta {
+                        mint: Pubkey::new_unique(),
+                        amount,
+                        decimals: 6,
+                    })
+                    .collect(),
+            }),
+        }
+    }
+
+    #[test]
+    fn csv_has_a_row_per_change_and_the_fee_once() {
+        let entry = entry(TxKind::Token, -1_505_000, true, &[25_000_000]);
+        let csv = render(&[&entry], &[], ReportFormat::Csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[1].starts_with("2024-02-29 23:59:59,sig,ok,Token,,-0.001505,SOL,,0.000005,"));
+        assert!(lines[1].ends_with(",\"rent, march\","));
+        assert!(lines[2].contains(",25,"));
+        assert!(lines[2].contains(",,\"rent, march\","));
+    }
+
+    #[test]
+    fn tax_rows_pair_swaps_and_separate_the_fee() {
+        let jupiter = programs::KNOWN_PROGRAMS[0].id;
+        let swap = entry(
+            TxKind::Program(jupiter),
+            -1_000_005_000,
+            true,
+            &[150_000_000],
+        );
+        let rows = tax_rows(&swap);
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].class, TaxClass::Swap);
+        assert_eq!(rows[0].sent, Some(("1".to_string(), "SOL".to_string())));
+        assert_eq!(rows[0].received.as_ref().unwrap().0, "150");
+        assert_eq!(rows[0].fee.as_deref(), Some("0.000005"));
+
+        // Incoming stake payout the wallet didn't pay for
+        let reward = entry(TxKind::Stake, 2_000_000, false, &[]);
+        assert_eq!(tax_rows(&reward)[0].class, TaxClass::StakingReward);
+        let unstake = entry(TxKind::Stake, 2_000_000, true, &[]);
+        assert_eq!(tax_rows(&unstake)[0].class, TaxClass::Unstake);
+
+        let mut failed = entry(TxKind::Transfer, -5_000, true, &[]);
+        failed.failed = true;
+        let rows = tax_rows(&failed);
+        assert_eq!(rows[0].class, TaxClass::Fee);
+        assert!(rows[0].fee.is_none());
+    }
+
+    #[test]
+    fn cointracker_dates_are_month_first() {
+        let entry = entry(TxKind::Transfer, 1_000_000_000, false, &[]);
+        let csv = render(&[&entry], &[], ReportFormat::Cointracker).unwrap();
+        assert_eq!(csv.lines().nth(1), Some("02/29/2024 23:59:59,1,SOL,,,,,"));
+    }
+
+    #[test]
+    fn rewards_are_listed_with_the_transactions_by_time() {
+        let entry = entry(TxKind::Transfer, 1_000_000_000, false, &[]);
+        let stake_account = Pubkey::new_unique();
+        let reward = |epoch, slot, block_time| Reward {
+            epoch,
+            stake_account,
+            lamports: 2_500_000,
+            post_balance: 10_002_500_000,
+            commission: Some(5),
+            slot,
+            block_time: Some(block_time),
+        };
+        let rewards = [reward(600, 2, 1_709_337_600), reward(599, 0, 1_709_164_800)];
+
+        let csv = render(&[&entry], &rewards, ReportFormat::Csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(
+            lines[1],
+            format!(
+                "2024-03-02 00:00:00,,ok,Staking reward,{},0.0025,SOL,,,,",
+                stake_account
+            )
+        );
+        assert!(lines[2].contains(",sig,"));
+        assert!(lines[3].starts_with("2024-02-29 00:00:00,,"));
+
+        let json: serde_json::Value =
+            serde_json::from_str(&render(&[&entry], &rewards, ReportFormat::Json).unwrap())
+                .unwrap();
+        assert_eq!(json[0]["signature"], serde_json::Value::Null);
+        assert_eq!(json[0]["epoch"], 600);
+        assert!(json[1].get("epoch").is_none());
+
+        let koinly = render(&[&entry], &rewards, ReportFormat::Koinly).unwrap();
+        assert!(koinly.lines().nth(1).unwrap().contains(&format!(
+            ",reward,Staking reward for epoch 600 ({}),",
+            stake_account
+        )));
+    }
+}
@@ -0,0 +1,752 @@
+//! The RPC calls the wallet makes, as a trait so the wallet can run against
+//! an in-memory node in tests instead of a live cluster
+
+// `ClientError` is large, but it's what `RpcClient` returns
+#![allow(clippy::result_large_err)]
+
+use crate::{
+    cache::CachedRpc,
+    config::Config,
+    throttle::{self, Backoff, ThrottledRpc},
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use solana_account_decoder::{encode_ui_account, UiAccountEncoding};
+use solana_client::{
+    client_error::{ClientError, ClientErrorKind, Result as ClientResult},
+    rpc_client::{GetConfirmedSignaturesForAddress2Config, RpcClient},
+    rpc_config::{
+        RpcProgramAccountsConfig, RpcSendTransactionConfig, RpcSimulateTransactionConfig,
+        RpcTransactionConfig,
+    },
+    rpc_filter::RpcFilterType,
+    rpc_request::RpcError,
+    rpc_response::{
+        Response, RpcConfirmedTransactionStatusWithSignature, RpcInflationReward,
+        RpcPrioritizationFee, RpcResponseContext, RpcResult, RpcSimulateTransactionResult,
+    },
+};
+#[allow(deprecated)]
+use solana_sdk::system_program;
+use solana_sdk::{
+    account::Account,
+    clock::{Epoch, Slot, UnixTimestamp},
+    commitment_config::CommitmentConfig,
+    epoch_info::EpochInfo,
+    hash::Hash,
+    instruction::InstructionError,
+    message::VersionedMessage,
+    pubkey::Pubkey,
+    signature::Signature,
+    transaction::{TransactionError, VersionedTransaction},
+};
+use solana_transaction_status::{
+    EncodedConfirmedTransactionWithStatusMeta, EncodedTransaction,
+    EncodedTransactionWithStatusMeta, TransactionBinaryEncoding, TransactionConfirmationStatus,
+    TransactionStatus,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// The subset of `RpcClient` the wallet uses, with the same signatures
+pub trait RpcApi: Send + Sync {
+    fn commitment(&self) -> CommitmentConfig;
+    fn get_balance(&self, pubkey: &Pubkey) -> ClientResult<u64>;
+    fn get_account(&self, pubkey: &Pubkey) -> ClientResult<Account>;
+    fn get_account_with_commitment(
+        &self,
+        pubkey: &Pubkey,
+        commitment_config: CommitmentConfig,
+    ) -> RpcResult<Option<Account>>;
+    fn get_multiple_accounts(&self, pubkeys: &[Pubkey]) -> ClientResult<Vec<Option<Account>>>;
+    fn get_program_accounts_with_config(
+        &self,
+        pubkey: &Pubkey,
+        config: RpcProgramAccountsConfig,
+    ) -> ClientResult<Vec<(Pubkey, Account)>>;
+    fn get_minimum_balance_for_rent_exemption(&self, data_len: usize) -> ClientResult<u64>;
+    fn get_genesis_hash(&self) -> ClientResult<Hash>;
+    fn get_slot_with_commitment(&self, commitment_config: CommitmentConfig) -> ClientResult<Slot>;
+    fn get_latest_blockhash(&self) -> ClientResult<Hash>;
+    fn get_epoch_info(&self) -> ClientResult<EpochInfo>;
+    fn get_inflation_reward(
+        &self,
+        addresses: &[Pubkey],
+        epoch: Option<Epoch>,
+    ) -> ClientResult<Vec<Option<RpcInflationReward>>>;
+    fn get_block_time(&self, slot: Slot) -> ClientResult<UnixTimestamp>;
+    fn get_fee_for_message(&self, message: &VersionedMessage) -> ClientResult<u64>;
+    fn get_recent_prioritization_fees(
+        &self,
+        addresses: &[Pubkey],
+    ) -> ClientResult<Vec<RpcPrioritizationFee>>;
+    fn simulate_transaction_with_config(
+        &self,
+        transaction: &VersionedTransaction,
+        config: RpcSimulateTransactionConfig,
+    ) -> RpcResult<RpcSimulateTransactionResult>;
+    fn send_transaction_with_config(
+        &self,
+        transaction: &VersionedTransaction,
+        config: RpcSendTransactionConfig,
+    ) -> ClientResult<Signature>;
+    fn get_signature_statuses(
+        &self,
+        signatures: &[Signature],
+    ) -> RpcResult<Vec<Option<TransactionStatus>>>;
+    fn get_signature_statuses_with_history(
+        &self,
+        signatures: &[Signature],
+    ) -> RpcResult<Vec<Option<TransactionStatus>>>;
+    fn is_blockhash_valid(
+        &self,
+        blockhash: &Hash,
+        commitment: CommitmentConfig,
+    ) -> ClientResult<bool>;
+    fn get_signatures_for_address_with_config(
+        &self,
+        address: &Pubkey,
+        config: GetConfirmedSignaturesForAddress2Config,
+    ) -> ClientResult<Vec<RpcConfirmedTransactionStatusWithSignature>>;
+    fn get_transaction_with_config(
+        &self,
+        signature: &Signature,
+        config: RpcTransactionConfig,
+    ) -> ClientResult<EncodedConfirmedTransactionWithStatusMeta>;
+}
+
+impl RpcApi for RpcClient {
+    fn commitment(&self) -> CommitmentConfig {
+        self.commitment()
+    }
+
+    fn get_balance(&self, pubkey: &Pubkey) -> ClientResult<u64> {
+        self.get_balance(pubkey)
+    }
+
+    fn get_account(&self, pubkey: &Pubkey) -> ClientResult<Account> {
+        self.get_account(pubkey)
+    }
+
+    fn get_account_with_commitment(
+        &self,
+        pubkey: &Pubkey,
+        commitment_config: CommitmentConfig,
+    ) -> RpcResult<Option<Account>> {
+        self.get_account_with_commitment(pubkey, commitment_config)
+    }
+
+    fn get_multiple_accounts(&self, pubkeys: &[Pubkey]) -> ClientResult<Vec<Option<Account>>> {
+        self.get_multiple_accounts(pubkeys)
+    }
+
+    fn get_program_accounts_with_config(
+        &self,
+        pubkey: &Pubkey,
+        config: RpcProgramAccountsConfig,
+    ) -> ClientResult<Vec<(Pubkey, Account)>> {
+        self.get_program_accounts_with_config(pubkey, config)
+    }
+
+    fn get_minimum_balance_for_rent_exemption(&self, data_len: usize) -> ClientResult<u64> {
+        self.get_minimum_balance_for_rent_exemption(data_len)
+    }
+
+    fn get_genesis_hash(&self) -> ClientResult<Hash> {
+        self.get_genesis_hash()
+    }
+
+    fn get_slot_with_commitment(&self, commitment_config: CommitmentConfig) -> ClientResult<Slot> {
+        self.get_slot_with_commitment(commitment_config)
+    }
+
+    fn get_latest_blockhash(&self) -> ClientResult<Hash> {
+        self.get_latest_blockhash()
+    }
+
+    fn get_epoch_info(&self) -> ClientResult<EpochInfo> {
+        self.get_epoch_info()
+    }
+
+    fn get_inflation_reward(
+        &self,
+        addresses: &[Pubkey],
+        epoch: Option<Epoch>,
+    ) -> ClientResult<Vec<Option<RpcInflationReward>>> {
+        self.get_inflation_reward(addresses, epoch)
+    }
+
+    fn get_block_time(&self, slot: Slot) -> ClientResult<UnixTimestamp> {
+        self.get_block_time(slot)
+    }
+
+    fn get_fee_for_message(&self, message: &VersionedMessage) -> ClientResult<u64> {
+        match message {
+            VersionedMessage::Legacy(message) => self.get_fee_for_message(message),
+            VersionedMessage::V0(message) => self.get_fee_for_message(message),
+        }
+    }
+
+    fn get_recent_prioritization_fees(
+        &self,
+        addresses: &[Pubkey],
+    ) -> ClientResult<Vec<RpcPrioritizationFee>> {
+        self.get_recent_prioritization_fees(addresses)
+    }
+
+    fn simulate_transaction_with_config(
+        &self,
+        transaction: &VersionedTransaction,
+        config: RpcSimulateTransactionConfig,
+    ) -> RpcResult<RpcSimulateTransactionResult> {
+        self.simulate_transaction_with_config(transaction, config)
+    }
+
+    fn send_transaction_with_config(
+        &self,
+        transaction: &VersionedTransaction,
+        config: RpcSendTransactionConfig,
+    ) -> ClientResult<Signature> {
+        self.send_transaction_with_config(transaction, config)
+    }
+
+    fn get_signature_statuses(
+        &self,
+        signatures: &[Signature],
+    ) -> RpcResult<Vec<Option<TransactionStatus>>> {
+        self.get_signature_statuses(signatures)
+    }
+
+    fn get_signature_statuses_with_history(
+        &self,
+        signatures: &[Signature],
+    ) -> RpcResult<Vec<Option<TransactionStatus>>> {
+        self.get_signature_statuses_with_history(signatures)
+    }
+
+    fn is_blockhash_valid(
+        &self,
+        blockhash: &Hash,
+        commitment: CommitmentConfig,
+    ) -> ClientResult<bool> {
+        self.is_blockhash_valid(blockhash, commitment)
+    }
+
+    fn get_signatures_for_address_with_config(
+        &self,
+        address: &Pubkey,
+        config: GetConfirmedSignaturesForAddress2Config,
+    ) -> ClientResult<Vec<RpcConfirmedTransactionStatusWithSignature>> {
+        self.get_signatures_for_address_with_config(address, config)
+    }
+
+    fn get_transaction_with_config(
+        &self,
+        signature: &Signature,
+        config: RpcTransactionConfig,
+    ) -> ClientResult<EncodedConfirmedTransactionWithStatusMeta> {
+        self.get_transaction_with_config(signature, config)
+    }
+}
+
+/// The RPC client for `rpc_url`, rate limited and cached as configured, and
+/// the handle that tells whether it's backing off
+pub fn connect(config: &Config, rpc_url: &str) -> (Arc<dyn RpcApi>, Arc<Backoff>) {
+    let throttled = ThrottledRpc::new(
+        RpcClient::new_with_commitment(
+            rpc_url.to_string(),
+            CommitmentConfig {
+                commitment: config.cluster.commitment,
+            },
+        ),
+        throttle::requests_per_second(&config.rate_limit, rpc_url),
+        config.rate_limit.retries,
+    );
+    let backoff = throttled.backoff();
+    let rpc_client = Arc::new(CachedRpc::new(
+        throttled,
+        Duration::from_secs(config.cache.seconds),
+    ));
+    (rpc_client, backoff)
+}
+
+/// Rent-exempt minimum of an account without data, as on the real clusters
+pub const MOCK_RENT_MINIMUM: u64 = 890_880;
+
+/// Fee the mock charges per signature
+pub const MOCK_FEE: u64 = 5_000;
+
+/// Slot the mock confirms transactions at
+pub const MOCK_SLOT: u64 = 302_508_000;
+
+/// Block time of `MOCK_SLOT`; other slots are 400ms apart from it
+pub const MOCK_BLOCK_TIME: UnixTimestamp = 1_727_000_000;
+
+/// Slots in each of the mock's epochs
+pub const MOCK_SLOTS_PER_EPOCH: u64 = 432_000;
+
+/// Compute units the mock's simulations report, what a plain transfer with
+/// its compute budget instructions takes
+pub const MOCK_UNITS_CONSUMED: u64 = 450;
+
+/// An in-memory node for tests: accounts hold SOL unless given data, sent
+/// transactions are recorded (and confirmed, or dropped) and simulated
+/// without being applied, and any call can be made to fail
+#[derive(Default)]
+pub struct MockRpc {
+    state: Mutex<MockState>,
+}
+
+#[derive(Default)]
+struct MockState {
+    balances: HashMap<Pubkey, u64>,
+    /// Accounts with data, which take the place of a balance
+    accounts: HashMap<Pubkey, Account>,
+    /// Priority fees of recent slots, oldest first
+    prioritization_fees: Vec<u64>,
+    /// Lamports paid to an address as rewards for an epoch
+    inflation_rewards: HashMap<(Epoch, Pubkey), u64>,
+    /// Methods, by their `RpcApi` name, that return an error
+    failing: HashSet<&'static str>,
+    /// Methods answered as rate limited, and how many more times
+    throttled: HashMap<&'static str, usize>,
+    sent: Vec<VersionedTransaction>,
+    /// How many times each sent transaction's status was checked; each
+    /// check finds it one commitment level further
+    checked: HashMap<Signature, usize>,
+    /// Whether transactions are lost on the way, never landing before their
+    /// blockhash expires
+    dropping: bool,
+    /// Sent transactions that landed but failed
+    failed: HashSet<Signature>,
+    /// Landed transactions old enough to have left the status cache, so
+    /// only a search of the ledger finds them
+    aged: HashSet<Signature>,
+}
+
+impl MockRpc {
+    /// Create or replace the account at `address`
+    pub fn set_balance(&self, address: Pubkey, lamports: u64) {
+        self.state().balances.insert(address, lamports);
+    }
+
+    /// Create or replace the account at `address`, data and all
+    pub fn set_account(&self, address: Pubkey, account: Account) {
+        self.state().accounts.insert(address, account);
+    }
+
+    /// The priority fees recent slots were paid, whatever the accounts
+    pub fn set_prioritization_fees(&self, fees: &[u64]) {
+        self.state().prioritization_fees = fees.to_vec();
+    }
+
+    /// Pay `address` a reward of `lamports` for `epoch`, credited at the
+    /// first slot of the next one
+    pub fn set_inflation_reward(&self, epoch: Epoch, address: Pubkey, lamports: u64) {
+        self.state()
+            .inflation_rewards
+            .insert((epoch, address), lamports);
+    }
+
+    /// Make calls to `method` fail until `recover` is called
+    pub fn fail(&self, method: &'static str) {
+        self.state().failing.insert(method);
+    }
+
+    /// Answer the next `times` calls to `method` as a rate-limiting node
+    /// does
+    pub fn throttle(&self, method: &'static str, times: usize) {
+        self.state().throttled.insert(method, times);
+    }
+
+    pub fn recover(&self, method: &'static str) {
+        self.state().failing.remove(method);
+    }
+
+    /// Lose every transaction sent from now on, as a congested cluster does
+    pub fn drop_transactions(&self) {
+        self.state().dropping = true;
+    }
+
+    /// Record the sent transaction `signature` as failed on chain
+    pub fn fail_transaction(&self, signature: Signature) {
+        self.state().failed.insert(signature);
+    }
+
+    /// Let the transactions sent so far age out of the recent status cache,
+    /// as they do after about 150 slots
+    pub fn age_transactions(&self) {
+        let mut state = self.state();
+        state.aged = state.sent.iter().map(|sent| sent.signatures[0]).collect();
+    }
+
+    /// Transactions sent so far, oldest first
+    pub fn sent(&self) -> Vec<VersionedTransaction> {
+        self.state().sent.clone()
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, MockState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn check(&self, method: &'static str) -> ClientResult<()> {
+        if let Some(times) = self
+            .state()
+            .throttled
+            .get_mut(method)
+            .filter(|times| **times > 0)
+        {
+            *times -= 1;
+            return Err(ClientError::from(ClientErrorKind::RpcError(
+                RpcError::RpcRequestError("HTTP status 429 Too Many Requests".to_string()),
+            )));
+        }
+        if self.state().failing.contains(method) {
+            return Err(ClientError::from(ClientErrorKind::Custom(format!(
+                "{} failed",
+                method
+            ))));
+        }
+        Ok(())
+    }
+
+    /// Statuses of sent transactions; out of the recent status cache once
+    /// they've aged, unless the `history` is searched
+    fn statuses(
+        &self,
+        signatures: &[Signature],
+        history: bool,
+    ) -> RpcResult<Vec<Option<TransactionStatus>>> {
+        let mut state = self.state();
+        let mut statuses = Vec::new();
+        for signature in signatures {
+            let landed = state
+                .sent
+                .iter()
+                .any(|sent| sent.signatures[0] == *signature);
+            if !landed || (state.aged.contains(signature) && !history) {
+                statuses.push(None);
+                continue;
+            }
+            let err =
+                state
+                    .failed
+                    .contains(signature)
+                    .then_some(TransactionError::InstructionError(
+                        0,
+                        InstructionError::Custom(1),
+                    ));
+            let checked = state.checked.entry(*signature).or_default();
+            // Nodes count confirmations until the slot is rooted
+            let (confirmation_status, confirmations) = match *checked {
+                0 => (TransactionConfirmationStatus::Processed, Some(0)),
+                1 => (TransactionConfirmationStatus::Confirmed, Some(1)),
+                _ => (TransactionConfirmationStatus::Finalized, None),
+            };
+            *checked += 1;
+            statuses.push(Some(TransactionStatus {
+                slot: MOCK_SLOT,
+                confirmations,
+                status: err.clone().map_or(Ok(()), Err),
+                err,
+                confirmation_status: Some(confirmation_status),
+            }));
+        }
+        Ok(Response {
+            context: RpcResponseContext {
+                slot: MOCK_SLOT,
+                api_version: None,
+            },
+            value: statuses,
+        })
+    }
+
+    fn account(&self, pubkey: &Pubkey) -> Option<Account> {
+        let state = self.state();
+        if let Some(account) = state.accounts.get(pubkey) {
+            return Some(account.clone());
+        }
+        state.balances.get(pubkey).map(|&lamports| Account {
+            lamports,
+            data: Vec::new(),
+            owner: system_program::id(),
+            executable: false,
+            rent_epoch: 0,
+        })
+    }
+}
+
+impl RpcApi for MockRpc {
+    fn commitment(&self) -> CommitmentConfig {
+        CommitmentConfig::confirmed()
+    }
+
+    fn get_balance(&self, pubkey: &Pubkey) -> ClientResult<u64> {
+        self.check("get_balance")?;
+        Ok(self.account(pubkey).map_or(0, |account| account.lamports))
+    }
+
+    fn get_account(&self, pubkey: &Pubkey) -> ClientResult<Account> {
+        self.check("get_account")?;
+        self.account(pubkey).ok_or_else(|| {
+            ClientError::from(ClientErrorKind::Custom(format!(
+                "AccountNotFound: pubkey={}",
+                pubkey
+            )))
+        })
+    }
+
+    fn get_account_with_commitment(
+        &self,
+        pubkey: &Pubkey,
+        _commitment_config: CommitmentConfig,
+    ) -> RpcResult<Option<Account>> {
+        self.check("get_account_with_commitment")?;
+        Ok(Response {
+            context: RpcResponseContext {
+                slot: 0,
+                api_version: None,
+            },
+            value: self.account(pubkey),
+        })
+    }
+
+    fn get_multiple_accounts(&self, pubkeys: &[Pubkey]) -> ClientResult<Vec<Option<Account>>> {
+        self.check("get_multiple_accounts")?;
+        Ok(pubkeys.iter().map(|pubkey| self.account(pubkey)).collect())
+    }
+
+    fn get_program_accounts_with_config(
+        &self,
+        pubkey: &Pubkey,
+        config: RpcProgramAccountsConfig,
+    ) -> ClientResult<Vec<(Pubkey, Account)>> {
+        self.check("get_program_accounts_with_config")?;
+        let filters = config.filters.unwrap_or_default();
+        let mut accounts: Vec<(Pubkey, Account)> = self
+            .state()
+            .accounts
+            .iter()
+            .filter(|(_, account)| account.owner == *pubkey)
+            .filter(|(_, account)| {
+                filters.iter().all(|filter| match filter {
+                    RpcFilterType::DataSize(size) => account.data.len() as u64 == *size,
+                    RpcFilterType::Memcmp(compare) => compare.bytes_match(&account.data),
+                    RpcFilterType::TokenAccountState => true,
+                })
+            })
+            .map(|(address, account)| (*address, account.clone()))
+            .collect();
+        accounts.sort_by_key(|(address, _)| *address);
+        Ok(accounts)
+    }
+
+    fn get_minimum_balance_for_rent_exemption(&self, _data_len: usize) -> ClientResult<u64> {
+        self.check("get_minimum_balance_for_rent_exemption")?;
+        Ok(MOCK_RENT_MINIMUM)
+    }
+
+    fn get_genesis_hash(&self) -> ClientResult<Hash> {
+        self.check("get_genesis_hash")?;
+        Ok(Hash::default())
+    }
+
+    fn get_slot_with_commitment(&self, _commitment_config: CommitmentConfig) -> ClientResult<Slot> {
+        self.check("get_slot_with_commitment")?;
+        Ok(MOCK_SLOT)
+    }
+
+    fn get_latest_blockhash(&self) -> ClientResult<Hash> {
+        self.check("get_latest_blockhash")?;
+        Ok(Hash::new_unique())
+    }
+
+    fn get_epoch_info(&self) -> ClientResult<EpochInfo> {
+        self.check("get_epoch_info")?;
+        Ok(EpochInfo {
+            epoch: 700,
+            slot_index: 108_000,
+            slots_in_epoch: MOCK_SLOTS_PER_EPOCH,
+            absolute_slot: MOCK_SLOT,
+            block_height: 280_000_000,
+            transaction_count: None,
+        })
+    }
+
+    fn get_inflation_reward(
+        &self,
+        addresses: &[Pubkey],
+        epoch: Option<Epoch>,
+    ) -> ClientResult<Vec<Option<RpcInflationReward>>> {
+        self.check("get_inflation_reward")?;
+        // Defaults to the last completed epoch, as on a real node
+        let epoch = epoch.unwrap_or(MOCK_SLOT / MOCK_SLOTS_PER_EPOCH - 1);
+        let rewards = self.state().inflation_rewards.clone();
+        Ok(addresses
+            .iter()
+            .map(|address| {
+                let amount = *rewards.get(&(epoch, *address))?;
+                Some(RpcInflationReward {
+                    epoch,
+                    effective_slot: (epoch + 1) * MOCK_SLOTS_PER_EPOCH,
+                    amount,
+                    post_balance: self.account(address).map_or(0, |account| account.lamports),
+                    commission: Some(5),
+                })
+            })
+            .collect())
+    }
+
+    fn get_block_time(&self, slot: Slot) -> ClientResult<UnixTimestamp> {
+        self.check("get_block_time")?;
+        Ok(MOCK_BLOCK_TIME + (slot as i64 - MOCK_SLOT as i64) * 2 / 5)
+    }
+
+    fn get_fee_for_message(&self, message: &VersionedMessage) -> ClientResult<u64> {
+        self.check("get_fee_for_message")?;
+        Ok(MOCK_FEE * u64::from(message.header().num_required_signatures))
+    }
+
+    fn get_recent_prioritization_fees(
+        &self,
+        _addresses: &[Pubkey],
+    ) -> ClientResult<Vec<RpcPrioritizationFee>> {
+        self.check("get_recent_prioritization_fees")?;
+        Ok((MOCK_SLOT..)
+            .zip(&self.state().prioritization_fees)
+            .map(|(slot, &prioritization_fee)| RpcPrioritizationFee {
+                slot,
+                prioritization_fee,
+            })
+            .collect())
+    }
+
+    fn simulate_transaction_with_config(
+        &self,
+        _transaction: &VersionedTransaction,
+        config: RpcSimulateTransactionConfig,
+    ) -> RpcResult<RpcSimulateTransactionResult> {
+        self.check("simulate_transaction_with_config")?;
+        let accounts = config.accounts.map(|accounts| {
+            accounts
+                .addresses
+                .iter()
+                .map(|address| {
+                    let pubkey = address.parse().ok()?;
+                    let account = self.account(&pubkey)?;
+                    Some(encode_ui_account(
+                        &pubkey,
+                        &account,
+                        UiAccountEncoding::Base64,
+                        None,
+                        None,
+                    ))
+                })
+                .collect()
+        });
+        Ok(Response {
+            context: RpcResponseContext {
+                slot: 0,
+                api_version: None,
+            },
+            value: RpcSimulateTransactionResult {
+                err: None,
+                logs: Some(Vec::new()),
+                accounts,
+                units_consumed: Some(MOCK_UNITS_CONSUMED),
+                loaded_accounts_data_size: None,
+                return_data: None,
+                inner_instructions: None,
+                replacement_blockhash: None,
+            },
+        })
+    }
+
+    fn send_transaction_with_config(
+        &self,
+        transaction: &VersionedTransaction,
+        _config: RpcSendTransactionConfig,
+    ) -> ClientResult<Signature> {
+        self.check("send_transaction_with_config")?;
+        let mut state = self.state();
+        // Sent again while it confirms, but it only lands once
+        let signature = transaction.signatures[0];
+        if !state.dropping
+            && !state
+                .sent
+                .iter()
+                .any(|sent| sent.signatures[0] == signature)
+        {
+            state.sent.push(transaction.clone());
+        }
+        Ok(signature)
+    }
+
+    fn get_signature_statuses(
+        &self,
+        signatures: &[Signature],
+    ) -> RpcResult<Vec<Option<TransactionStatus>>> {
+        self.check("get_signature_statuses")?;
+        self.statuses(signatures, false)
+    }
+
+    fn get_signature_statuses_with_history(
+        &self,
+        signatures: &[Signature],
+    ) -> RpcResult<Vec<Option<TransactionStatus>>> {
+        self.check("get_signature_statuses_with_history")?;
+        self.statuses(signatures, true)
+    }
+
+    fn is_blockhash_valid(
+        &self,
+        _blockhash: &Hash,
+        _commitment: CommitmentConfig,
+    ) -> ClientResult<bool> {
+        self.check("is_blockhash_valid")?;
+        Ok(!self.state().dropping)
+    }
+
+    fn get_signatures_for_address_with_config(
+        &self,
+        _address: &Pubkey,
+        _config: GetConfirmedSignaturesForAddress2Config,
+    ) -> ClientResult<Vec<RpcConfirmedTransactionStatusWithSignature>> {
+        self.check("get_signatures_for_address_with_config")?;
+        Ok(Vec::new())
+    }
+
+    fn get_transaction_with_config(
+        &self,
+        signature: &Signature,
+        _config: RpcTransactionConfig,
+    ) -> ClientResult<EncodedConfirmedTransactionWithStatusMeta> {
+        self.check("get_transaction_with_config")?;
+        let state = self.state();
+        let Some(transaction) = state
+            .sent
+            .iter()
+            .find(|sent| sent.signatures[0] == *signature)
+        else {
+            return Err(ClientError::from(ClientErrorKind::Custom(format!(
+                "Transaction {} not found",
+                signature
+            ))));
+        };
+        let bytes = bincode::serialize(transaction).expect("transactions serialize");
+        Ok(EncodedConfirmedTransactionWithStatusMeta {
+            slot: MOCK_SLOT,
+            transaction: EncodedTransactionWithStatusMeta {
+                transaction: EncodedTransaction::Binary(
+                    STANDARD.encode(bytes),
+                    TransactionBinaryEncoding::Base64,
+                ),
+                meta: None,
+                version: None,
+            },
+            block_time: Some(MOCK_BLOCK_TIME),
+        })
+    }
+}
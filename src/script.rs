@@ -0,0 +1,185 @@
+//! The hook script: a Rhai script whose `on_receive`, `on_confirm` and
+//! `pre_send` functions are called with wallet events. Scripts are
+//! sandboxed: they can't reach files, the network or other scripts, they get
+//! a copy of the event rather than the wallet, and each call is bounded in
+//! operations, size and time.
+
+use crate::config;
+use anyhow::{Context, Result};
+use rhai::{module_resolvers::DummyModuleResolver, Dynamic, Engine, EvalAltResult, Scope, AST};
+use serde::Serialize;
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Operations a call may take, far more than any policy needs
+const MAX_OPERATIONS: u64 = 10_000_000;
+
+/// Messages a call may pass to `notify`
+const MAX_NOTICES: usize = 10;
+
+/// A compiled hook script
+#[derive(Clone)]
+pub struct Script {
+    path: PathBuf,
+    ast: Arc<AST>,
+}
+
+/// What a hook returned, and what it asked to be shown on the way
+pub struct Outcome {
+    pub value: Dynamic,
+    pub notices: Vec<String>,
+}
+
+impl Script {
+    /// Read and compile the script at `path`
+    pub fn load(path: &str) -> Result<Self> {
+        let path = config::expand_tilde(Path::new(path));
+        let source = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read hook script {}", path.display()))?;
+        let ast = sandbox(Duration::MAX, Arc::default())
+            .compile(source)
+            .with_context(|| format!("Failed to compile hook script {}", path.display()))?;
+        Ok(Self {
+            path,
+            ast: Arc::new(ast),
+        })
+    }
+
+    /// Call the script's `hook` function with `event`, or `None` if it has
+    /// none. A call taking longer than `timeout` is stopped.
+    pub fn call(
+        &self,
+        hook: &str,
+        event: &impl Serialize,
+        timeout: Duration,
+    ) -> Result<Option<Outcome>> {
+        if !self
+            .ast
+            .iter_functions()
+            .any(|function| function.name == hook && function.params.len() == 1)
+        {
+            return Ok(None);
+        }
+        let event = rhai::serde::to_dynamic(event).map_err(anyhow::Error::msg)?;
+        let notices = Arc::default();
+        let value = sandbox(timeout, Arc::clone(&notices))
+            .call_fn::<Dynamic>(&mut Scope::new(), &self.ast, hook, (event,))
+            .map_err(|e| match *e {
+                EvalAltResult::ErrorTerminated(..) => {
+                    anyhow::anyhow!("did not finish within {}s", timeout.as_secs())
+                }
+                e => anyhow::Error::msg(e.to_string()),
+            })
+            .with_context(|| format!("Hook script {} failed in {}", self.path.display(), hook))?;
+        let notices = std::mem::take(&mut *notices.lock().unwrap());
+        Ok(Some(Outcome { value, notices }))
+    }
+}
+
+/// An engine with nothing to reach outside the script but `notify`, which
+/// collects into `notices`, and that stops once `timeout` has passed
+fn sandbox(timeout: Duration, notices: Arc<Mutex<Vec<String>>>) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_module_resolver(DummyModuleResolver::new());
+    engine.disable_symbol("eval");
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.set_max_call_levels(32);
+    engine.set_max_expr_depths(64, 32);
+    engine.set_max_string_size(64 * 1024);
+    engine.set_max_array_size(10_000);
+    engine.set_max_map_size(10_000);
+    // stdout belongs to the terminal UI
+    engine.on_print(|text| tracing::info!(text, "Hook script printed"));
+    engine.on_debug(|text, _, position| tracing::debug!(text, %position, "Hook script debug"));
+    let deadline = Instant::now().checked_add(timeout);
+    engine.on_progress(move |_| {
+        deadline
+            .filter(|deadline| Instant::now() > *deadline)
+            .map(|_| Dynamic::UNIT)
+    });
+    engine.register_fn("notify", move |text: &str| {
+        let mut notices = notices.lock().unwrap();
+        if notices.len() < MAX_NOTICES {
+            notices.push(text.to_string());
+        }
+    });
+    engine
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn script(source: &str) -> Script {
+        Script {
+            path: PathBuf::from("test.rhai"),
+            ast: Arc::new(
+                sandbox(Duration::MAX, Arc::default())
+                    .compile(source)
+                    .unwrap(),
+            ),
+        }
+    }
+
+    #[test]
+    fn hooks_get_the_event_and_can_notify() {
+        let script = script(
+            r#"
+            fn pre_send(send) {
+                if send.lamports > 1000 { return "too much for " + send.recipient; }
+                true
+            }
+            fn on_receive(event) { notify("got " + event.lamports); }
+            "#,
+        );
+        let outcome = script
+            .call(
+                "pre_send",
+                &json!({"lamports": 5000, "recipient": "bob"}),
+                Duration::from_secs(1),
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(outcome.value.into_string().unwrap(), "too much for bob");
+
+        let outcome = script
+            .call(
+                "on_receive",
+                &json!({"lamports": 7}),
+                Duration::from_secs(1),
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(outcome.notices, ["got 7"]);
+
+        assert!(script
+            .call("on_confirm", &json!({}), Duration::from_secs(1))
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn scripts_stay_in_the_sandbox() {
+        for source in [
+            r#"fn pre_send(send) { import "other" as other; true }"#,
+            r#"fn pre_send(send) { eval("true") }"#,
+            r#"fn pre_send(send) { loop {} }"#,
+        ] {
+            let result = sandbox(Duration::MAX, Arc::default())
+                .compile(source)
+                .map_err(anyhow::Error::from)
+                .and_then(|ast| {
+                    let script = Script {
+                        path: PathBuf::from("test.rhai"),
+                        ast: Arc::new(ast),
+                    };
+                    script.call("pre_send", &json!({}), Duration::from_millis(100))
+                });
+            assert!(result.is_err(), "{}", source);
+        }
+    }
+}
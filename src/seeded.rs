@@ -0,0 +1,166 @@
+//! Accounts at addresses derived from the wallet's with a seed and an owner
+//! program (`create_account_with_seed`), which some programs and older
+//! workflows expect. Nothing on chain links them to the wallet, so the ones
+//! created or funded here are kept in a list in the data directory.
+
+use crate::config::data_dir;
+use crate::rpc::RpcApi;
+use crate::sender;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Signature, Signer},
+};
+#[allow(deprecated)]
+use solana_sdk::{system_instruction, system_program};
+use std::{fs, path::PathBuf};
+
+/// An address derived from `base`, `seed` and `owner`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SeededAccount {
+    #[serde(with = "base58")]
+    pub base: Pubkey,
+    pub seed: String,
+    #[serde(with = "base58")]
+    pub owner: Pubkey,
+}
+
+impl SeededAccount {
+    /// Fails if the seed is longer than the runtime allows (32 bytes)
+    pub fn new(base: Pubkey, seed: &str, owner: Option<Pubkey>) -> Result<Self> {
+        let account = Self {
+            base,
+            seed: seed.to_string(),
+            // Accounts holding SOL are owned by the System Program
+            owner: owner.unwrap_or(system_program::id()),
+        };
+        account.address()?;
+        Ok(account)
+    }
+
+    pub fn address(&self) -> Result<Pubkey> {
+        Pubkey::create_with_seed(&self.base, &self.seed, &self.owner)
+            .with_context(|| format!("Invalid seed {:?}", self.seed))
+    }
+}
+
+/// The seeded accounts the wallet knows about, saved as `seeded.json`
+#[derive(Debug, Default)]
+pub struct SeededAccounts {
+    path: Option<PathBuf>,
+    accounts: Vec<SeededAccount>,
+}
+
+impl SeededAccounts {
+    pub fn load() -> Result<Self> {
+        let path = data_dir()?.join("seeded.json");
+        let accounts = if path.exists() {
+            let contents = fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            serde_json::from_str(&contents)
+                .with_context(|| format!("Failed to parse {}", path.display()))?
+        } else {
+            Vec::new()
+        };
+        Ok(Self {
+            path: Some(path),
+            accounts,
+        })
+    }
+
+    /// The accounts derived from `base`, oldest first
+    pub fn of<'a>(&'a self, base: &'a Pubkey) -> impl Iterator<Item = &'a SeededAccount> {
+        self.accounts
+            .iter()
+            .filter(move |account| account.base == *base)
+    }
+
+    /// Remember `account`, if it isn't already
+    pub fn add(&mut self, account: SeededAccount) -> Result<()> {
+        if self.accounts.contains(&account) {
+            return Ok(());
+        }
+        self.accounts.push(account);
+        if let Some(ref path) = self.path {
+            fs::write(path, serde_json::to_string_pretty(&self.accounts)?)
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+        }
+        Ok(())
+    }
+}
+
+/// Create `account` with `space` bytes of data, paid for and signed by
+/// `signer`, which must be the account's base
+pub fn create(
+    rpc_client: &dyn RpcApi,
+    signer: &dyn Signer,
+    account: &SeededAccount,
+    lamports: u64,
+    space: u64,
+) -> Result<Signature> {
+    let payer = signer.pubkey();
+    let instruction = system_instruction::create_account_with_seed(
+        &payer,
+        &account.address()?,
+        &account.base,
+        &account.seed,
+        lamports,
+        space,
+        &account.owner,
+    );
+    sender::send_instructions(rpc_client, &[signer], &[instruction])
+        .context("Failed to create the account")
+}
+
+/// Pubkeys as base58 strings rather than byte arrays
+mod base58 {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+    use solana_sdk::pubkey::Pubkey;
+
+    pub fn serialize<S: Serializer>(key: &Pubkey, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(key)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Pubkey, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::MockRpc;
+    use solana_sdk::{signature::Keypair, system_instruction::SystemInstruction};
+
+    #[test]
+    fn creates_the_derived_account() {
+        let rpc = MockRpc::default();
+        let wallet = Keypair::new();
+        let account = SeededAccount::new(wallet.pubkey(), "savings", None).unwrap();
+        assert!(SeededAccount::new(wallet.pubkey(), &"x".repeat(33), None).is_err());
+
+        create(&rpc, &wallet, &account, 1_000_000, 0).unwrap();
+        let sent = rpc.sent();
+        let instruction = &sent[0].message.instructions()[0];
+        let decoded: SystemInstruction = bincode::deserialize(&instruction.data).unwrap();
+        assert!(matches!(
+            decoded,
+            SystemInstruction::CreateAccountWithSeed { ref seed, lamports: 1_000_000, .. }
+                if seed == "savings"
+        ));
+        assert_eq!(
+            sent[0].message.static_account_keys()[1],
+            Pubkey::create_with_seed(&wallet.pubkey(), "savings", &system_program::id()).unwrap()
+        );
+
+        let json = serde_json::to_value(&account).unwrap();
+        assert_eq!(json["base"], wallet.pubkey().to_string());
+        assert_eq!(
+            serde_json::from_value::<SeededAccount>(json).unwrap(),
+            account
+        );
+    }
+}
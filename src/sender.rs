@@ -0,0 +1,740 @@
+//! Getting a signed transaction landed. RPC nodes drop transactions when
+//! the cluster is busy, so it's sent again every couple of seconds until
+//! the cluster has seen it, and its status is followed until it's
+//! confirmed, fails, or its blockhash expires and it never can land.
+//!
+//! Every transaction goes through here, so this is also where the blocklist
+//! and the pre-send hooks get their say over what's sent, whichever screen
+//! or command built it.
+
+// `ClientError` is large, but it's what `RpcClient` returns
+#![allow(clippy::result_large_err)]
+
+use crate::blocklist::Blocklist;
+use crate::config::WebhookConfig;
+use crate::jito::Jito;
+use crate::keys::SecretKeypair;
+use crate::output::Refused;
+use crate::rpc::RpcApi;
+use crate::wallet::NoBlockhash;
+use crate::webhook;
+use anyhow::{bail, Context, Result};
+use solana_client::{
+    client_error::{ClientError, ClientErrorKind},
+    rpc_config::{RpcSendTransactionConfig, RpcSimulateTransactionConfig, RpcTransactionConfig},
+    rpc_request::{RpcError, RpcResponseErrorData},
+    rpc_response::RpcSimulateTransactionResult,
+};
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{Signature, Signer},
+    transaction::{Transaction, VersionedTransaction},
+};
+use solana_transaction_status::{
+    option_serializer::OptionSerializer, TransactionConfirmationStatus, UiTransactionEncoding,
+};
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, RwLock,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+static DRY_RUN: AtomicBool = AtomicBool::new(false);
+
+static GUARD: RwLock<Option<Arc<Guard>>> = RwLock::new(None);
+
+/// Make every pipeline created from now on a dry run, simulating what it
+/// would send
+pub fn set_dry_run(dry_run: bool) {
+    DRY_RUN.store(dry_run, Ordering::Relaxed);
+}
+
+/// Whether sends are only simulated
+pub fn dry_run() -> bool {
+    DRY_RUN.load(Ordering::Relaxed)
+}
+
+/// Check what every pipeline created from now on sends against `guard`
+pub fn set_guard(guard: Guard) {
+    *GUARD.write().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(guard));
+}
+
+/// What a transaction is checked against before it's sent
+#[derive(Debug, Clone, Default)]
+pub struct Guard {
+    pub blocklist: Blocklist,
+    pub hooks: WebhookConfig,
+    /// For the cluster the hooks are told about
+    pub rpc_url: String,
+}
+
+impl Guard {
+    /// Refuse `transaction` if it names a blocklisted account or a pre-send
+    /// hook turns it down. The hooks are waited on, so this isn't for the UI
+    /// thread.
+    pub fn check(&self, transaction: &VersionedTransaction) -> Result<()> {
+        for address in transaction.message.static_account_keys() {
+            if let Some(reason) = self.blocklist.reason(address) {
+                bail!(Refused(format!(
+                    "{} is on your blocklist: {}",
+                    address, reason
+                )));
+            }
+        }
+        webhook::check_pre_send(&self.hooks, &self.rpc_url, &transaction.message)
+    }
+
+    /// The same checks, except for `address`, which the user chose to send
+    /// to despite it being blocklisted
+    pub fn acknowledging(&self, address: &Pubkey) -> Self {
+        let mut guard = self.clone();
+        guard.blocklist.remove(address);
+        guard
+    }
+}
+
+/// Where a sent transaction has got to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Status {
+    /// Sent, but not seen in a block yet
+    Submitted,
+    Processed {
+        slot: u64,
+    },
+    Confirmed {
+        slot: u64,
+    },
+    Finalized {
+        slot: u64,
+    },
+}
+
+impl Status {
+    /// The commitment levels in the order a transaction reaches them
+    pub const LEVELS: [&'static str; 3] = ["Processed", "Confirmed", "Finalized"];
+
+    /// How many of `LEVELS` it has reached
+    pub fn level(self) -> usize {
+        match self {
+            Self::Submitted => 0,
+            Self::Processed { .. } => 1,
+            Self::Confirmed { .. } => 2,
+            Self::Finalized { .. } => 3,
+        }
+    }
+
+    pub fn slot(self) -> Option<u64> {
+        match self {
+            Self::Submitted => None,
+            Self::Processed { slot } | Self::Confirmed { slot } | Self::Finalized { slot } => {
+                Some(slot)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.slot() {
+            Some(slot) => write!(f, "{} at slot {}", Self::LEVELS[self.level() - 1], slot),
+            None => f.write_str("Sent, waiting for the cluster to see it"),
+        }
+    }
+}
+
+/// The latest status of a send running on another thread, for the screen
+/// that's waiting on it
+#[derive(Debug, Clone, Default)]
+pub struct Tracker(Arc<Mutex<Option<(Signature, Status)>>>);
+
+impl Tracker {
+    pub fn update(&self, signature: &Signature, status: Status) {
+        *self.0.lock().unwrap_or_else(|e| e.into_inner()) = Some((*signature, status));
+    }
+
+    /// The signature and status, once it's been sent
+    pub fn get(&self) -> Option<(Signature, Status)> {
+        *self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// How a send ended
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    /// Reached the commitment waited for, in this slot
+    Confirmed { signature: Signature, slot: u64 },
+    /// The blockhash expired without the transaction landing, so it never
+    /// will; sending it again with a new blockhash is safe
+    Expired { signature: Signature },
+    /// Rejected by the simulation before it was sent, or landed and failed
+    Failed {
+        signature: Signature,
+        error: String,
+        logs: Vec<String>,
+    },
+    /// Succeeded in the simulation of a dry run, and wasn't sent
+    Simulated {
+        signature: Signature,
+        /// Compute units the simulation used, if the node said
+        units: Option<u64>,
+        logs: Vec<String>,
+    },
+}
+
+/// A confirmed transaction
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Landed {
+    pub signature: Signature,
+    pub slot: u64,
+}
+
+impl Outcome {
+    /// The confirmed transaction, or the expiry, failure or dry run as an
+    /// error
+    pub fn into_result(self) -> Result<Landed> {
+        match self {
+            Self::Confirmed { signature, slot } => Ok(Landed { signature, slot }),
+            Self::Expired { signature } => Err(Expired(signature).into()),
+            Self::Failed {
+                signature,
+                error,
+                logs,
+            } => Err(TransactionFailed {
+                signature,
+                error,
+                logs,
+            }
+            .into()),
+            Self::Simulated {
+                signature,
+                units,
+                logs,
+            } => Err(DryRun {
+                signature,
+                units,
+                logs,
+            }
+            .into()),
+        }
+    }
+}
+
+/// The error of `Outcome::Expired`
+#[derive(Debug)]
+pub struct Expired(pub Signature);
+
+impl fmt::Display for Expired {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Transaction {} expired before it landed; nothing was sent",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for Expired {}
+
+/// The error of `Outcome::Failed`, with the program logs
+#[derive(Debug)]
+pub struct TransactionFailed {
+    pub signature: Signature,
+    pub error: String,
+    pub logs: Vec<String>,
+}
+
+/// The error of `Outcome::Simulated`: in a dry run, the simulation of a
+/// transaction that would have succeeded
+#[derive(Debug)]
+pub struct DryRun {
+    pub signature: Signature,
+    /// Compute units the simulation used, if the node said
+    pub units: Option<u64>,
+    pub logs: Vec<String>,
+}
+
+impl fmt::Display for DryRun {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Dry run: transaction {} would have been sent and succeeded in the simulation",
+            self.signature
+        )?;
+        if let Some(units) = self.units {
+            write!(f, ", using {} compute units", units)?;
+        }
+        write!(f, "; nothing was sent")
+    }
+}
+
+impl std::error::Error for DryRun {}
+
+impl fmt::Display for TransactionFailed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Transaction failed: {}", self.error)
+    }
+}
+
+impl std::error::Error for TransactionFailed {}
+
+/// How often to send and check on a transaction, and where to
+#[derive(Debug, Clone)]
+pub struct Pipeline {
+    /// Between sends of a transaction the cluster hasn't seen yet
+    pub rebroadcast_interval: Duration,
+    /// Between status checks
+    pub poll_interval: Duration,
+    /// When to stop waiting, which only durable nonce transactions (that
+    /// don't expire) should get to
+    pub timeout: Duration,
+    /// Wait for the transaction to be finalized, rather than for the
+    /// client's commitment
+    pub finalized: bool,
+    /// Send as Jito bundles instead of through the RPC node. The
+    /// transaction has to pay the tip itself, from `Jito::tip_instruction`.
+    pub jito: Option<Jito>,
+    /// Priority fee of the transactions built for it, in micro-lamports per
+    /// compute unit
+    pub compute_unit_price: Option<u64>,
+    /// Lookup tables the transactions built for it load addresses from,
+    /// when that makes them smaller
+    pub lookup_tables: Vec<Pubkey>,
+    /// Who signs for and pays the fees (and any tip) of the transactions
+    /// built for it, instead of the account they move SOL from
+    pub fee_payer: Option<Arc<SecretKeypair>>,
+    /// Key added to the transfers built for it, as Solana Pay does, so the
+    /// recipient can tell which payment is which
+    pub reference: Option<Pubkey>,
+    /// Only simulate transactions and report how that went, never sending
+    /// them. Set for every pipeline by `set_dry_run`.
+    pub dry_run: bool,
+    /// Checked before a transaction is simulated or sent. Set for every
+    /// pipeline by `set_guard`.
+    pub guard: Option<Arc<Guard>>,
+}
+
+impl Default for Pipeline {
+    fn default() -> Self {
+        Self {
+            rebroadcast_interval: Duration::from_secs(2),
+            poll_interval: Duration::from_millis(500),
+            timeout: Duration::from_secs(120),
+            finalized: false,
+            jito: None,
+            compute_unit_price: None,
+            lookup_tables: Vec::new(),
+            fee_payer: None,
+            reference: None,
+            dry_run: DRY_RUN.load(Ordering::Relaxed),
+            guard: GUARD.read().unwrap_or_else(|e| e.into_inner()).clone(),
+        }
+    }
+}
+
+impl Pipeline {
+    /// The default pipeline, waiting for finalization if `finalized`
+    pub fn finalized(finalized: bool) -> Self {
+        Self {
+            finalized,
+            ..Self::default()
+        }
+    }
+
+    /// Who pays for a transaction built for it that moves SOL from `sender`
+    pub fn fee_payer_of(&self, sender: &Pubkey) -> Pubkey {
+        self.fee_payer
+            .as_ref()
+            .map_or(*sender, |fee_payer| fee_payer.pubkey())
+    }
+
+    /// Send `transaction` and follow it to an outcome, reporting each status
+    /// it reaches with its signature. Errors are left for when there's no telling whether it
+    /// landed.
+    pub fn submit(
+        &self,
+        rpc_client: &dyn RpcApi,
+        transaction: &VersionedTransaction,
+        on_status: &mut dyn FnMut(&Signature, Status),
+    ) -> Result<Outcome> {
+        let commitment = if self.finalized {
+            CommitmentConfig::finalized()
+        } else {
+            rpc_client.commitment()
+        };
+        let signature = transaction.signatures[0];
+        let blockhash = *transaction.message.recent_blockhash();
+        let expires = !transaction.uses_durable_nonce();
+
+        if let Some(ref guard) = self.guard {
+            guard.check(transaction)?;
+        }
+
+        if self.dry_run {
+            let result = simulate(rpc_client, transaction)?;
+            let logs = result.logs.unwrap_or_default();
+            if let Some(error) = result.err {
+                tracing::info!(%signature, %error, "Dry run failed in simulation");
+                return Ok(Outcome::Failed {
+                    signature,
+                    error: error.to_string(),
+                    logs,
+                });
+            }
+            tracing::info!(%signature, "Dry run simulated, not sent");
+            return Ok(Outcome::Simulated {
+                signature,
+                units: result.units_consumed,
+                logs,
+            });
+        }
+
+        // The first send runs the simulation, which catches most failures
+        // with their logs before anything is paid for
+        if let Some(ref jito) = self.jito {
+            // The block engine doesn't simulate, so the node does
+            if let Some((error, logs)) = simulation_failure(rpc_client, transaction)? {
+                tracing::info!(%signature, %error, "Transaction failed in simulation");
+                return Ok(Outcome::Failed {
+                    signature,
+                    error,
+                    logs,
+                });
+            }
+            jito.send_bundle(std::slice::from_ref(transaction))?;
+        } else {
+            let first = RpcSendTransactionConfig {
+                preflight_commitment: Some(rpc_client.commitment().commitment),
+                ..RpcSendTransactionConfig::default()
+            };
+            if let Err(e) = rpc_client.send_transaction_with_config(transaction, first) {
+                if let Some((error, logs)) = preflight_failure(&e) {
+                    tracing::info!(%signature, %error, "Transaction failed in simulation");
+                    return Ok(Outcome::Failed {
+                        signature,
+                        error,
+                        logs,
+                    });
+                }
+                tracing::warn!(%signature, error = %e, "Failed to send transaction");
+                return Err(e).context("Failed to send transaction");
+            }
+        }
+        tracing::info!(%signature, %blockhash, jito = self.jito.is_some(), "Sent transaction");
+        on_status(&signature, Status::Submitted);
+        // Again as they're sent from here; the node shouldn't retry on its own
+        let again = RpcSendTransactionConfig {
+            skip_preflight: true,
+            max_retries: Some(0),
+            ..RpcSendTransactionConfig::default()
+        };
+
+        let started = Instant::now();
+        let mut last_sent = started;
+        let mut last_status = Status::Submitted;
+        loop {
+            thread::sleep(self.poll_interval);
+            // A failed check is tried again on the next round
+            let status = rpc_client
+                .get_signature_statuses(&[signature])
+                .ok()
+                .and_then(|statuses| statuses.value.into_iter().next().flatten());
+
+            if let Some(status) = status {
+                if let Some(err) = status.err {
+                    tracing::warn!(%signature, error = %err, "Transaction failed");
+                    return Ok(Outcome::Failed {
+                        signature,
+                        error: err.to_string(),
+                        logs: logs(rpc_client, &signature),
+                    });
+                }
+                let slot = status.slot;
+                let reached = match status.confirmation_status() {
+                    TransactionConfirmationStatus::Finalized => Status::Finalized { slot },
+                    TransactionConfirmationStatus::Confirmed => Status::Confirmed { slot },
+                    TransactionConfirmationStatus::Processed => Status::Processed { slot },
+                };
+                if reached != last_status {
+                    tracing::info!(%signature, status = ?reached, "Transaction status");
+                    on_status(&signature, reached);
+                    last_status = reached;
+                }
+                if status.satisfies_commitment(commitment) {
+                    return Ok(Outcome::Confirmed { signature, slot });
+                }
+            } else if last_sent.elapsed() >= self.rebroadcast_interval {
+                let valid = !expires
+                    || rpc_client
+                        .is_blockhash_valid(&blockhash, CommitmentConfig::processed())
+                        .unwrap_or(true);
+                if !valid {
+                    // It may have landed just before the blockhash went
+                    if still_unseen(rpc_client, &signature) {
+                        tracing::warn!(%signature, "Transaction expired without landing");
+                        return Ok(Outcome::Expired { signature });
+                    }
+                    continue;
+                }
+                // Not known to have landed, so sending it again can't
+                // double it: the cluster drops copies of a signature it has
+                if let Some(ref jito) = self.jito {
+                    let _ = jito.send_bundle(std::slice::from_ref(transaction));
+                } else {
+                    let _ = rpc_client.send_transaction_with_config(transaction, again);
+                }
+                tracing::debug!(%signature, "Sent transaction again");
+                last_sent = Instant::now();
+            }
+
+            if started.elapsed() >= self.timeout {
+                tracing::warn!(%signature, "Stopped waiting for transaction to confirm");
+                bail!(
+                    "Stopped waiting for transaction {} to confirm; it may still land, so \
+                     check the history before sending again",
+                    signature
+                );
+            }
+        }
+    }
+}
+
+/// Send `transaction` with the default pipeline and wait for it to be
+/// confirmed; expiry and failure are errors
+pub fn send(rpc_client: &dyn RpcApi, transaction: &VersionedTransaction) -> Result<Landed> {
+    Pipeline::default()
+        .submit(rpc_client, transaction, &mut |_, _| {})?
+        .into_result()
+}
+
+/// Sign `instructions` with `signers`, the first paying, and send them with
+/// the default pipeline, returning the signature once confirmed
+pub fn send_instructions(
+    rpc_client: &dyn RpcApi,
+    signers: &[&dyn Signer],
+    instructions: &[Instruction],
+) -> Result<Signature> {
+    let recent_blockhash = rpc_client.get_latest_blockhash().context(NoBlockhash)?;
+    let transaction = Transaction::new_signed_with_payer(
+        instructions,
+        Some(&signers[0].pubkey()),
+        signers,
+        recent_blockhash,
+    );
+    Ok(send(rpc_client, &transaction.into())?.signature)
+}
+
+/// The error and logs of a transaction the node's simulation turned down
+fn preflight_failure(error: &ClientError) -> Option<(String, Vec<String>)> {
+    let ClientErrorKind::RpcError(RpcError::RpcResponseError {
+        ref message,
+        data: RpcResponseErrorData::SendTransactionPreflightFailure(ref result),
+        ..
+    }) = *error.kind()
+    else {
+        return None;
+    };
+    let error = result
+        .err
+        .as_ref()
+        .map_or_else(|| message.clone(), ToString::to_string);
+    Some((error, result.logs.clone().unwrap_or_default()))
+}
+
+/// The error and logs if the node's simulation of `transaction` fails
+fn simulation_failure(
+    rpc_client: &dyn RpcApi,
+    transaction: &VersionedTransaction,
+) -> Result<Option<(String, Vec<String>)>> {
+    let result = simulate(rpc_client, transaction)?;
+    Ok(result
+        .err
+        .map(|error| (error.to_string(), result.logs.unwrap_or_default())))
+}
+
+/// The node's simulation of `transaction`, signatures checked
+fn simulate(
+    rpc_client: &dyn RpcApi,
+    transaction: &VersionedTransaction,
+) -> Result<RpcSimulateTransactionResult> {
+    let config = RpcSimulateTransactionConfig {
+        sig_verify: true,
+        commitment: Some(rpc_client.commitment()),
+        ..RpcSimulateTransactionConfig::default()
+    };
+    Ok(rpc_client
+        .simulate_transaction_with_config(transaction, config)
+        .context("Failed to simulate transaction")?
+        .value)
+}
+
+fn still_unseen(rpc_client: &dyn RpcApi, signature: &Signature) -> bool {
+    rpc_client
+        .get_signature_statuses(&[*signature])
+        .is_ok_and(|statuses| statuses.value.first().is_none_or(Option::is_none))
+}
+
+/// The program logs of a landed transaction, if the node has them
+fn logs(rpc_client: &dyn RpcApi, signature: &Signature) -> Vec<String> {
+    let config = RpcTransactionConfig {
+        encoding: Some(UiTransactionEncoding::Base64),
+        commitment: Some(CommitmentConfig::confirmed()),
+        max_supported_transaction_version: Some(0),
+    };
+    let Ok(transaction) = rpc_client.get_transaction_with_config(signature, config) else {
+        return Vec::new();
+    };
+    match transaction.transaction.meta.map(|meta| meta.log_messages) {
+        Some(OptionSerializer::Some(logs)) => logs,
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{BlocklistConfig, BlocklistEntry};
+    use crate::rpc::{MockRpc, MOCK_SLOT, MOCK_UNITS_CONSUMED};
+    #[allow(deprecated)]
+    use solana_sdk::system_instruction;
+    use solana_sdk::{
+        hash::Hash,
+        pubkey::Pubkey,
+        signature::{Keypair, Signer},
+        transaction::Transaction,
+    };
+
+    const FAST: Pipeline = Pipeline {
+        rebroadcast_interval: Duration::from_millis(2),
+        poll_interval: Duration::from_millis(1),
+        timeout: Duration::from_secs(5),
+        finalized: false,
+        jito: None,
+        compute_unit_price: None,
+        lookup_tables: Vec::new(),
+        fee_payer: None,
+        reference: None,
+        dry_run: false,
+        guard: None,
+    };
+
+    fn transfer() -> VersionedTransaction {
+        let wallet = Keypair::new();
+        let instruction = system_instruction::transfer(&wallet.pubkey(), &Pubkey::new_unique(), 1);
+        Transaction::new_signed_with_payer(
+            &[instruction],
+            Some(&wallet.pubkey()),
+            &[&wallet],
+            Hash::new_unique(),
+        )
+        .into()
+    }
+
+    #[test]
+    fn follows_the_transaction_to_an_outcome() {
+        let rpc = MockRpc::default();
+        let transaction = transfer();
+        let mut statuses = Vec::new();
+        let outcome = FAST
+            .submit(&rpc, &transaction, &mut |_, status| statuses.push(status))
+            .unwrap();
+        assert_eq!(
+            outcome,
+            Outcome::Confirmed {
+                signature: transaction.signatures[0],
+                slot: MOCK_SLOT
+            }
+        );
+        assert_eq!(
+            statuses,
+            [
+                Status::Submitted,
+                Status::Processed { slot: MOCK_SLOT },
+                Status::Confirmed { slot: MOCK_SLOT }
+            ]
+        );
+
+        let pipeline = Pipeline {
+            finalized: true,
+            ..FAST
+        };
+        let mut reached = Status::Submitted;
+        pipeline
+            .submit(&rpc, &transfer(), &mut |_, status| reached = status)
+            .unwrap();
+        assert_eq!(reached, Status::Finalized { slot: MOCK_SLOT });
+        assert_eq!(
+            reached.to_string(),
+            format!("Finalized at slot {}", MOCK_SLOT)
+        );
+
+        // Lost on the way and never landing
+        rpc.drop_transactions();
+        let transaction = transfer();
+        let outcome = FAST.submit(&rpc, &transaction, &mut |_, _| {}).unwrap();
+        assert_eq!(
+            outcome,
+            Outcome::Expired {
+                signature: transaction.signatures[0]
+            }
+        );
+        assert!(outcome.into_result().unwrap_err().is::<Expired>());
+        assert_eq!(rpc.sent().len(), 2);
+
+        rpc.fail("send_transaction_with_config");
+        assert!(FAST.submit(&rpc, &transfer(), &mut |_, _| {}).is_err());
+    }
+
+    #[test]
+    fn dry_runs_only_simulate() {
+        let rpc = MockRpc::default();
+        let pipeline = Pipeline {
+            dry_run: true,
+            ..FAST
+        };
+        let transaction = transfer();
+        let outcome = pipeline.submit(&rpc, &transaction, &mut |_, _| {}).unwrap();
+        assert!(matches!(outcome, Outcome::Simulated { .. }));
+        let error = outcome.into_result().unwrap_err();
+        let dry_run = error.downcast_ref::<DryRun>().expect("a dry run");
+        assert_eq!(dry_run.signature, transaction.signatures[0]);
+        assert_eq!(dry_run.units, Some(MOCK_UNITS_CONSUMED));
+        assert!(rpc.sent().is_empty());
+    }
+
+    #[test]
+    fn the_guard_refuses_blocklisted_accounts() {
+        let rpc = MockRpc::default();
+        let transaction = transfer();
+        let recipient = transaction.message.static_account_keys()[1];
+        let config = BlocklistConfig {
+            addresses: vec![BlocklistEntry {
+                address: recipient.to_string(),
+                reason: Some("drainer".to_string()),
+            }],
+            ..BlocklistConfig::default()
+        };
+        let pipeline = Pipeline {
+            guard: Some(Arc::new(Guard {
+                blocklist: Blocklist::load(&config).unwrap(),
+                hooks: WebhookConfig::default(),
+                rpc_url: "https://api.devnet.solana.com".to_string(),
+            })),
+            ..FAST
+        };
+        let error = pipeline
+            .submit(&rpc, &transaction, &mut |_, _| {})
+            .unwrap_err();
+        assert!(error.is::<Refused>());
+        assert_eq!(
+            error.to_string(),
+            format!("{} is on your blocklist: drainer", recipient)
+        );
+        assert!(rpc.sent().is_empty());
+    }
+}
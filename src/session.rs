@@ -0,0 +1,99 @@
+//! Where the TUI was left: the open screen, how far it was scrolled, the
+//! Transactions filters and selection, and the derived account switched to.
+//! Saved on quit and restored at the next start.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use solace::{config::data_dir, history::HistoryFilter};
+use std::{collections::BTreeMap, fs, path::PathBuf};
+
+/// What is restored at the next start
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionState {
+    #[serde(skip)]
+    path: Option<PathBuf>,
+    /// The open screen, by its name in the side menu (`sign_in` for
+    /// "Sign In")
+    pub screen: Option<String>,
+    /// How far the content pane was scrolled
+    pub scroll: u16,
+    /// By the address each wallet was opened with, so another keypair
+    /// doesn't pick up this one's accounts and filters
+    pub wallets: BTreeMap<String, WalletSession>,
+}
+
+/// The state of one wallet
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WalletSession {
+    /// The derived account switched to (`root` or a BIP44 path), if not
+    /// the one opened
+    pub account: Option<String>,
+    pub history_filters: Vec<HistoryFilter>,
+    pub history_search: String,
+    /// Position of the selection among the filtered transactions
+    pub history_selected: usize,
+    /// Position of the selection on the Accounts screen
+    pub accounts_selected: usize,
+}
+
+impl SessionState {
+    /// Load the state saved in the data directory, if any
+    pub fn load() -> Result<Self> {
+        Self::load_from(data_dir()?.join("session.json"))
+    }
+
+    fn load_from(path: PathBuf) -> Result<Self> {
+        let mut state: Self = if path.exists() {
+            let contents = fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read session state: {}", path.display()))?;
+            serde_json::from_str(&contents)
+                .with_context(|| format!("Failed to parse session state: {}", path.display()))?
+        } else {
+            Self::default()
+        };
+        state.path = Some(path);
+        Ok(state)
+    }
+
+    /// Write the state back to where it was loaded from; one that wasn't
+    /// loaded, as in tests, isn't saved
+    pub fn save(&self) -> Result<()> {
+        if let Some(ref path) = self.path {
+            fs::write(path, serde_json::to_string_pretty(self)?)
+                .with_context(|| format!("Failed to write session state: {}", path.display()))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::pubkey::Pubkey;
+
+    #[test]
+    fn restores_what_was_saved() {
+        let path = std::env::temp_dir().join(format!("solace-test-{}.json", Pubkey::new_unique()));
+        let mut state = SessionState::load_from(path.clone()).unwrap();
+        assert_eq!(state.screen, None);
+
+        state.screen = Some("transactions".to_string());
+        state.scroll = 3;
+        state.wallets.insert(
+            Pubkey::new_unique().to_string(),
+            WalletSession {
+                account: Some("m/44'/501'/1'/0'".to_string()),
+                history_filters: vec![HistoryFilter::Incoming, HistoryFilter::OtherProgram],
+                history_search: "rent".to_string(),
+                history_selected: 7,
+                accounts_selected: 2,
+            },
+        );
+        state.save().unwrap();
+        let loaded = SessionState::load_from(path.clone());
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.unwrap(), state);
+    }
+}
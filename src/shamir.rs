@@ -0,0 +1,316 @@
+//! M-of-N Shamir backup shares of the wallet's signing key.
+//!
+//! The 32-byte ed25519 secret is split byte-wise over GF(256), so any
+//! `threshold` shares rebuild it and fewer reveal nothing about it. Each
+//! share is a single line of text:
+//!
+//! ```text
+//! solace-share-v1:<threshold>:<index>:<base58(group || data || checksum)>
+//! ```
+//!
+//! `group` is the start of the wallet address, used to catch shares from
+//! different wallets being mixed, and `checksum` catches typos.
+
+use crate::keys::{self, SecretKeypair};
+use anyhow::{bail, Context, Result};
+use rand::{rngs::OsRng, RngCore};
+use solana_sdk::{hash::hashv, signature::Signer};
+use std::{
+    fmt,
+    io::Write,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+use zeroize::Zeroizing;
+
+const SHARE_PREFIX: &str = "solace-share-v1";
+const SECRET_LENGTH: usize = 32;
+const GROUP_LENGTH: usize = 4;
+const CHECKSUM_LENGTH: usize = 4;
+
+/// One share of a split key
+pub struct Share {
+    threshold: u8,
+    index: u8,
+    group: [u8; GROUP_LENGTH],
+    data: Zeroizing<[u8; SECRET_LENGTH]>,
+}
+
+impl Share {
+    pub fn index(&self) -> u8 {
+        self.index
+    }
+
+    fn checksum(&self) -> [u8; CHECKSUM_LENGTH] {
+        let hash = hashv(&[
+            SHARE_PREFIX.as_bytes(),
+            &[self.threshold, self.index],
+            &self.group,
+            &self.data[..],
+        ]);
+        let mut checksum = [0u8; CHECKSUM_LENGTH];
+        checksum.copy_from_slice(&hash.as_ref()[..CHECKSUM_LENGTH]);
+        checksum
+    }
+}
+
+impl fmt::Display for Share {
+    /// The share line. This is secret material, so only write it where the
+    /// user asked for it.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut payload = Zeroizing::new(Vec::with_capacity(
+            GROUP_LENGTH + SECRET_LENGTH + CHECKSUM_LENGTH,
+        ));
+        payload.extend_from_slice(&self.group);
+        payload.extend_from_slice(&self.data[..]);
+        payload.extend_from_slice(&self.checksum());
+        let encoded = Zeroizing::new(bs58::encode(&payload[..]).into_string());
+        write!(
+            f,
+            "{}:{}:{}:{}",
+            SHARE_PREFIX, self.threshold, self.index, *encoded
+        )
+    }
+}
+
+impl FromStr for Share {
+    type Err = anyhow::Error;
+
+    fn from_str(line: &str) -> Result<Self> {
+        let mut parts = line.trim().split(':');
+        if parts.next() != Some(SHARE_PREFIX) {
+            bail!("Not a {} backup share", SHARE_PREFIX);
+        }
+        let threshold: u8 = parts
+            .next()
+            .and_then(|part| part.parse().ok())
+            .context("Invalid share threshold")?;
+        let index: u8 = parts
+            .next()
+            .and_then(|part| part.parse().ok())
+            .filter(|index| *index > 0)
+            .context("Invalid share index")?;
+        let encoded = parts.next().context("Share is missing its data")?;
+        if parts.next().is_some() {
+            bail!("Share has trailing data");
+        }
+
+        let mut payload = Zeroizing::new([0u8; GROUP_LENGTH + SECRET_LENGTH + CHECKSUM_LENGTH]);
+        let len = bs58::decode(encoded)
+            .onto(&mut payload[..])
+            .map_err(|_| anyhow::anyhow!("Share data is not valid base58"))?;
+        if len != payload.len() {
+            bail!("Share data has the wrong length");
+        }
+
+        let mut share = Share {
+            threshold,
+            index,
+            group: [0u8; GROUP_LENGTH],
+            data: Zeroizing::new([0u8; SECRET_LENGTH]),
+        };
+        share.group.copy_from_slice(&payload[..GROUP_LENGTH]);
+        share
+            .data
+            .copy_from_slice(&payload[GROUP_LENGTH..GROUP_LENGTH + SECRET_LENGTH]);
+        if payload[GROUP_LENGTH + SECRET_LENGTH..] != share.checksum() {
+            bail!("Share {} failed its checksum (check for typos)", index);
+        }
+        Ok(share)
+    }
+}
+
+/// Split the key into `count` shares, any `threshold` of which restore it
+pub fn split(keypair: &SecretKeypair, threshold: u8, count: u8) -> Result<Vec<Share>> {
+    if threshold < 2 {
+        bail!("At least 2 shares must be required to restore");
+    }
+    if count < threshold {
+        bail!(
+            "Can't require {} shares when only {} are made",
+            threshold,
+            count
+        );
+    }
+
+    let mut group = [0u8; GROUP_LENGTH];
+    group.copy_from_slice(&keypair.pubkey().to_bytes()[..GROUP_LENGTH]);
+    let secret = keypair.secret_bytes();
+
+    let mut shares: Vec<Share> = (1..=count)
+        .map(|index| Share {
+            threshold,
+            index,
+            group,
+            data: Zeroizing::new([0u8; SECRET_LENGTH]),
+        })
+        .collect();
+
+    // One random polynomial per secret byte, with the byte as its constant term
+    let mut coefficients = Zeroizing::new(vec![0u8; threshold as usize]);
+    for (i, byte) in secret.iter().enumerate() {
+        coefficients[0] = *byte;
+        OsRng.fill_bytes(&mut coefficients[1..]);
+        for share in &mut shares {
+            share.data[i] = evaluate(&coefficients, share.index);
+        }
+    }
+    Ok(shares)
+}
+
+/// Rebuild the key from at least `threshold` shares of the same wallet
+pub fn combine(shares: &[Share]) -> Result<SecretKeypair> {
+    let first = shares.first().context("No shares given")?;
+    if shares
+        .iter()
+        .any(|share| share.group != first.group || share.threshold != first.threshold)
+    {
+        bail!("Shares come from different backups");
+    }
+    for (i, share) in shares.iter().enumerate() {
+        if shares[..i].iter().any(|other| other.index == share.index) {
+            bail!("Share {} was given more than once", share.index);
+        }
+    }
+    if shares.len() < first.threshold as usize {
+        bail!(
+            "{} shares are needed to restore this key, only {} given",
+            first.threshold,
+            shares.len()
+        );
+    }
+
+    // Lagrange interpolation at x = 0 using exactly `threshold` shares
+    let shares = &shares[..first.threshold as usize];
+    let mut secret = Zeroizing::new([0u8; SECRET_LENGTH]);
+    for share in shares {
+        let mut basis = 1u8;
+        for other in shares {
+            if other.index != share.index {
+                basis = mul(basis, div(other.index, other.index ^ share.index));
+            }
+        }
+        for (byte, y) in secret.iter_mut().zip(share.data.iter()) {
+            *byte ^= mul(*y, basis);
+        }
+    }
+
+    let keypair = SecretKeypair::from_secret(&secret)?;
+    if keypair.pubkey().to_bytes()[..GROUP_LENGTH] != first.group {
+        bail!("Shares don't restore the original key (are they from the same backup?)");
+    }
+    Ok(keypair)
+}
+
+/// Write each share to its own owner-only file in `dir`
+pub fn write_to_dir(keypair: &SecretKeypair, shares: &[Share], dir: &Path) -> Result<Vec<PathBuf>> {
+    let count = shares.len();
+    let mut paths = Vec::with_capacity(count);
+    for share in shares {
+        let path = dir.join(format!("share-{}-of-{}.txt", share.index, count));
+        let mut file = keys::create_secret_file(&path)?;
+        let contents = Zeroizing::new(format!(
+            "Solace backup share {} of {} for {}\nAny {} shares restore the key with `solace restore`.\n{}\n",
+            share.index,
+            count,
+            keypair.pubkey(),
+            share.threshold,
+            share
+        ));
+        file.write_all(contents.as_bytes())
+            .with_context(|| format!("Failed to write share: {}", path.display()))?;
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+/// Pull the share lines out of share files (or pasted text)
+pub fn parse_shares(text: &str) -> Result<Vec<Share>> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| line.starts_with(SHARE_PREFIX))
+        .map(str::parse)
+        .collect()
+}
+
+/// Evaluate a polynomial (lowest coefficient first) at `x`
+fn evaluate(coefficients: &[u8], x: u8) -> u8 {
+    coefficients
+        .iter()
+        .rev()
+        .fold(0, |acc, coefficient| mul(acc, x) ^ coefficient)
+}
+
+/// Multiplication in GF(256) with the AES polynomial
+fn mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0;
+    while b != 0 {
+        if b & 1 != 0 {
+            product ^= a;
+        }
+        let carry = a & 0x80 != 0;
+        a <<= 1;
+        if carry {
+            a ^= 0x1b;
+        }
+        b >>= 1;
+    }
+    product
+}
+
+fn div(a: u8, b: u8) -> u8 {
+    // b^254 is the inverse of b, as the multiplicative group has order 255
+    let mut inverse = 1;
+    for _ in 0..254 {
+        inverse = mul(inverse, b);
+    }
+    mul(a, inverse)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::signature::Keypair;
+
+    fn keypair() -> SecretKeypair {
+        SecretKeypair::from_bytes(&Keypair::new().to_bytes()).unwrap()
+    }
+
+    #[test]
+    fn any_threshold_subset_restores_the_key() {
+        let keypair = keypair();
+        let lines: Vec<String> = split(&keypair, 3, 5)
+            .unwrap()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+
+        for subset in [[0, 1, 2], [4, 2, 0], [1, 3, 4]] {
+            let shares: Vec<Share> = subset.iter().map(|&i| lines[i].parse().unwrap()).collect();
+            assert_eq!(combine(&shares).unwrap().pubkey(), keypair.pubkey());
+        }
+    }
+
+    #[test]
+    fn too_few_or_mixed_shares_are_rejected() {
+        let shares = split(&keypair(), 3, 5).unwrap();
+        assert!(combine(&shares[..2]).is_err());
+
+        let mut mixed = split(&keypair(), 3, 5).unwrap();
+        mixed.truncate(2);
+        mixed.push(shares.into_iter().nth(2).unwrap());
+        assert!(combine(&mixed).is_err());
+    }
+
+    #[test]
+    fn typo_fails_checksum() {
+        let line = split(&keypair(), 2, 2).unwrap()[0].to_string();
+        let last = line.chars().last().unwrap();
+        let typo = format!(
+            "{}{}",
+            &line[..line.len() - 1],
+            if last == '2' { '3' } else { '2' }
+        );
+        assert!(typo.parse::<Share>().is_err());
+    }
+}
@@ -0,0 +1,369 @@
+//! Sign-In With Solana: turning a dApp's sign-in request into the message
+//! the wallet signs, and the response that proves the wallet signed it
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Signature, Signer},
+};
+use std::{
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const HEADER: &str = " wants you to sign in with your Solana account:";
+
+/// The request, as the `SolanaSignInInput` of the wallet standard. Only the
+/// domain is required; a missing address means the wallet's own.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignInRequest {
+    pub domain: String,
+    pub address: Option<String>,
+    pub statement: Option<String>,
+    pub uri: Option<String>,
+    pub version: Option<String>,
+    pub chain_id: Option<String>,
+    pub nonce: Option<String>,
+    pub issued_at: Option<String>,
+    pub expiration_time: Option<String>,
+    pub not_before: Option<String>,
+    pub request_id: Option<String>,
+    #[serde(default)]
+    pub resources: Vec<String>,
+}
+
+impl SignInRequest {
+    /// Read a request given as JSON or as the text of the message itself
+    pub fn parse(text: &str) -> Result<Self> {
+        let text = text.trim();
+        if text.starts_with("solana-action:") {
+            bail!("solana-action: links aren't supported; paste the sign-in request instead");
+        }
+        let request = if text.starts_with('{') {
+            serde_json::from_str(text).context("Invalid sign-in request")?
+        } else {
+            Self::from_message(text)?
+        };
+        if request.domain.is_empty() || request.domain.contains(char::is_whitespace) {
+            bail!("The sign-in request has no valid domain");
+        }
+        if let Some(ref statement) = request.statement {
+            if statement.contains('\n') {
+                bail!("The sign-in statement must be a single line");
+            }
+        }
+        Ok(request)
+    }
+
+    fn from_message(text: &str) -> Result<Self> {
+        let text = text.replace("\r\n", "\n");
+        let mut sections = text.split("\n\n");
+        let header = sections.next().unwrap_or_default();
+        let (domain, address) = header
+            .split_once('\n')
+            .and_then(|(first, address)| Some((first.strip_suffix(HEADER)?, address)))
+            .context("Not a sign-in request: expected JSON or a Sign-In With Solana message")?;
+
+        let mut request = Self {
+            domain: domain.to_string(),
+            address: Some(address.to_string()),
+            ..Self::default()
+        };
+        let mut fields = sections.next();
+        if let Some(section) = fields.filter(|section| !is_field(section)) {
+            request.statement = Some(section.to_string());
+            fields = sections.next();
+        }
+        for line in fields.unwrap_or_default().lines() {
+            let (key, value) = line.split_once(':').context("Malformed sign-in message")?;
+            let value = value.strip_prefix(' ').unwrap_or(value).to_string();
+            match key {
+                "URI" => request.uri = Some(value),
+                "Version" => request.version = Some(value),
+                "Chain ID" => request.chain_id = Some(value),
+                "Nonce" => request.nonce = Some(value),
+                "Issued At" => request.issued_at = Some(value),
+                "Expiration Time" => request.expiration_time = Some(value),
+                "Not Before" => request.not_before = Some(value),
+                "Request ID" => request.request_id = Some(value),
+                "Resources" => {}
+                _ => match line.strip_prefix("- ") {
+                    Some(resource) => request.resources.push(resource.to_string()),
+                    None => bail!("Unknown field in sign-in message: {}", line),
+                },
+            }
+        }
+        // Only sign text that's exactly what these fields produce, so what's
+        // shown for review is everything that gets signed
+        if sections.next().is_some() || request.message(address) != text {
+            bail!("Malformed sign-in message");
+        }
+        Ok(request)
+    }
+
+    /// The message text, with `address` as the account
+    pub fn message(&self, address: &str) -> String {
+        let mut message = format!("{}{}\n{}", self.domain, HEADER, address);
+        if let Some(ref statement) = self.statement {
+            message.push_str("\n\n");
+            message.push_str(statement);
+        }
+
+        let mut fields = Vec::new();
+        let optional = [
+            ("URI", &self.uri),
+            ("Version", &self.version),
+            ("Chain ID", &self.chain_id),
+            ("Nonce", &self.nonce),
+            ("Issued At", &self.issued_at),
+            ("Expiration Time", &self.expiration_time),
+            ("Not Before", &self.not_before),
+            ("Request ID", &self.request_id),
+        ];
+        for (name, value) in optional {
+            if let Some(value) = value {
+                fields.push(format!("{}: {}", name, value));
+            }
+        }
+        if !self.resources.is_empty() {
+            fields.push("Resources:".to_string());
+            fields.extend(
+                self.resources
+                    .iter()
+                    .map(|resource| format!("- {}", resource)),
+            );
+        }
+        if !fields.is_empty() {
+            message.push_str("\n\n");
+            message.push_str(&fields.join("\n"));
+        }
+        message
+    }
+
+    /// Reasons the request can't be signed by `wallet` right now
+    pub fn check(&self, wallet: &Pubkey) -> Result<()> {
+        if let Some(ref address) = self.address {
+            let address =
+                Pubkey::from_str(address).context("Invalid address in sign-in request")?;
+            if address != *wallet {
+                bail!("The sign-in request is for {}, not this wallet", address);
+            }
+        }
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs() as i64);
+        if let Some(ref expiration) = self.expiration_time {
+            if parse_time(expiration).context("Invalid expiration time")? <= now {
+                bail!("The sign-in request expired at {}", expiration);
+            }
+        }
+        if let Some(ref not_before) = self.not_before {
+            if parse_time(not_before).context("Invalid not-before time")? > now {
+                bail!("The sign-in request isn't valid until {}", not_before);
+            }
+        }
+        Ok(())
+    }
+
+    /// Warnings to show next to the request: a URI on another host than the
+    /// domain asking is a sign of phishing
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        if let Some(ref uri) = self.uri {
+            let host = uri
+                .split_once("://")
+                .map_or(uri.as_str(), |(_, rest)| rest)
+                .split(['/', '?', '#'])
+                .next()
+                .unwrap_or_default();
+            if !host.eq_ignore_ascii_case(&self.domain) {
+                warnings.push(format!(
+                    "The URI ({}) is not on {}, the domain asking you to sign in",
+                    uri, self.domain
+                ));
+            }
+        }
+        warnings
+    }
+
+    /// Sign the request, returning the `SolanaSignInOutput` for the dApp with
+    /// the signed message and signature in base58
+    pub fn sign(&self, signer: &dyn Signer) -> Result<Value> {
+        let address = signer.pubkey();
+        self.check(&address)?;
+        let message = self.message(&address.to_string());
+        let signature: Signature = signer
+            .try_sign_message(message.as_bytes())
+            .context("Failed to sign in")?;
+        Ok(json!({
+            "account": { "address": address.to_string() },
+            "signedMessage": bs58::encode(message.as_bytes()).into_string(),
+            "signature": signature.to_string(),
+            "signatureType": "ed25519",
+        }))
+    }
+}
+
+fn is_field(section: &str) -> bool {
+    section.starts_with("URI: ")
+        || section.starts_with("Version: ")
+        || section.starts_with("Chain ID: ")
+        || section.starts_with("Nonce: ")
+        || section.starts_with("Issued At: ")
+        || section.starts_with("Expiration Time: ")
+        || section.starts_with("Not Before: ")
+        || section.starts_with("Request ID: ")
+        || section.starts_with("Resources:")
+}
+
+/// Seconds since the epoch of an RFC 3339 time like
+/// `2024-02-29T23:59:00.000Z` or `2024-03-01T01:59:00+02:00`
+fn parse_time(time: &str) -> Result<i64> {
+    let invalid = || anyhow::anyhow!("Not an RFC 3339 time: {}", time);
+    let number = |range: std::ops::Range<usize>| -> Result<i64> {
+        time.get(range)
+            .filter(|digits| digits.bytes().all(|b| b.is_ascii_digit()))
+            .and_then(|digits| digits.parse().ok())
+            .ok_or_else(invalid)
+    };
+    let bytes = time.as_bytes();
+    if bytes.len() < 20
+        || bytes[4] != b'-'
+        || bytes[7] != b'-'
+        || !matches!(bytes[10], b'T' | b't' | b' ')
+        || bytes[13] != b':'
+        || bytes[16] != b':'
+    {
+        return Err(invalid());
+    }
+    let (year, month, day) = (number(0..4)?, number(5..7)?, number(8..10)?);
+    let seconds = number(11..13)? * 3_600 + number(14..16)? * 60 + number(17..19)?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return Err(invalid());
+    }
+
+    // Skip fractional seconds, then read the offset
+    let mut rest = &time[19..];
+    if let Some(fraction) = rest.strip_prefix('.') {
+        rest = fraction.trim_start_matches(|c: char| c.is_ascii_digit());
+    }
+    let offset = match rest {
+        "Z" | "z" => 0,
+        _ if rest.len() == 6 && rest.as_bytes()[3] == b':' => {
+            let start = time.len() - 5;
+            let offset = number(start..start + 2)? * 3_600 + number(start + 3..start + 5)? * 60;
+            match rest.as_bytes()[0] {
+                b'+' => offset,
+                b'-' => -offset,
+                _ => return Err(invalid()),
+            }
+        }
+        _ => return Err(invalid()),
+    };
+
+    // Days-from-civil, from Howard Hinnant's date algorithms
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+    Ok(days * 86_400 + seconds - offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::signature::Keypair;
+
+    #[test]
+    fn builds_and_parses_the_message() {
+        let keypair = Keypair::new();
+        let address = keypair.pubkey().to_string();
+        let request = SignInRequest::parse(
+            r#"{"domain": "example.com", "statement": "Sign in to Example",
+                "uri": "https://example.com/login", "nonce": "32891756",
+                "issuedAt": "2024-01-01T00:00:00Z", "resources": ["https://example.com/tos"]}"#,
+        )
+        .unwrap();
+        let message = request.message(&address);
+        assert_eq!(
+            message,
+            format!(
+                "example.com wants you to sign in with your Solana account:\n{}\n\n\
+                 Sign in to Example\n\n\
+                 URI: https://example.com/login\nNonce: 32891756\n\
+                 Issued At: 2024-01-01T00:00:00Z\nResources:\n- https://example.com/tos",
+                address
+            )
+        );
+        assert!(request.warnings().is_empty());
+
+        // The message text reads back as the same request, for this wallet
+        let parsed = SignInRequest::parse(&message).unwrap();
+        assert_eq!(
+            parsed,
+            SignInRequest {
+                address: Some(address.clone()),
+                ..request.clone()
+            }
+        );
+
+        let output = request.sign(&keypair).unwrap();
+        let signed = bs58::decode(output["signedMessage"].as_str().unwrap())
+            .into_vec()
+            .unwrap();
+        let signature = Signature::from_str(output["signature"].as_str().unwrap()).unwrap();
+        assert_eq!(signed, message.as_bytes());
+        assert!(signature.verify(keypair.pubkey().as_ref(), &signed));
+    }
+
+    #[test]
+    fn refuses_requests_it_should_not_sign() {
+        let keypair = Keypair::new();
+        let other = SignInRequest {
+            domain: "example.com".to_string(),
+            address: Some(Pubkey::new_unique().to_string()),
+            ..SignInRequest::default()
+        };
+        assert!(other.sign(&keypair).is_err());
+
+        let expired = SignInRequest {
+            domain: "example.com".to_string(),
+            expiration_time: Some("2020-01-01T00:00:00.000+01:00".to_string()),
+            ..SignInRequest::default()
+        };
+        assert!(expired.sign(&keypair).is_err());
+
+        let phishing = SignInRequest {
+            domain: "example.com".to_string(),
+            uri: Some("https://examp1e.com".to_string()),
+            ..SignInRequest::default()
+        };
+        assert_eq!(phishing.warnings().len(), 1);
+
+        // Text not in the exact format
+        assert!(SignInRequest::parse(
+            "example.com wants you to sign in with your Solana account:\nabc\n\nHello\n\nFoo: bar"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn parses_rfc3339_times() {
+        assert_eq!(parse_time("1970-01-01T00:00:00Z").unwrap(), 0);
+        assert_eq!(
+            parse_time("2024-02-29T23:59:00.123Z").unwrap(),
+            1_709_251_140
+        );
+        assert_eq!(
+            parse_time("2024-03-01T01:59:00+02:00").unwrap(),
+            1_709_251_140
+        );
+        assert!(parse_time("2024-02-29").is_err());
+    }
+}
@@ -0,0 +1,242 @@
+//! Staking rewards: the inflation rewards paid to the wallet's stake
+//! accounts (those it's the staker or withdrawer of) over recent epochs,
+//! for the rewards table and the history export
+
+use crate::rpc::RpcApi;
+use anyhow::{Context, Result};
+use solana_account_decoder::{UiAccountEncoding, UiDataSliceConfig};
+use solana_client::{
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+    rpc_filter::{Memcmp, RpcFilterType},
+};
+#[allow(deprecated)]
+use solana_sdk::stake::{self, state::StakeStateV2};
+use solana_sdk::{
+    clock::{Epoch, Slot, UnixTimestamp},
+    pubkey::Pubkey,
+};
+use std::collections::HashMap;
+
+/// Where the staker and withdrawer authorities are in a stake account:
+/// after the state's tag and the rent-exempt reserve
+const STAKER_OFFSET: usize = 12;
+const WITHDRAWER_OFFSET: usize = 44;
+
+/// A reward credited to one stake account for one epoch
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reward {
+    pub epoch: Epoch,
+    pub stake_account: Pubkey,
+    pub lamports: u64,
+    /// The account's balance once it was credited
+    pub post_balance: u64,
+    /// The vote account's commission at the time, in percent
+    pub commission: Option<u8>,
+    /// Where it was credited, at the start of the next epoch
+    pub slot: Slot,
+    /// `None` if the RPC no longer has the block
+    pub block_time: Option<UnixTimestamp>,
+}
+
+/// The rewards of one epoch across the stake accounts
+#[derive(Debug, Clone, PartialEq)]
+pub struct EpochRewards {
+    pub epoch: Epoch,
+    pub lamports: u64,
+    /// How many stake accounts were paid
+    pub accounts: usize,
+    /// Everything paid up to and including this epoch, over the epochs
+    /// fetched
+    pub cumulative: u64,
+}
+
+/// Stake accounts `authority` is the staker or withdrawer of, sorted
+pub fn stake_accounts(rpc_client: &dyn RpcApi, authority: &Pubkey) -> Result<Vec<Pubkey>> {
+    let mut accounts = Vec::new();
+    for offset in [STAKER_OFFSET, WITHDRAWER_OFFSET] {
+        let found = rpc_client
+            .get_program_accounts_with_config(
+                &stake::program::ID,
+                RpcProgramAccountsConfig {
+                    filters: Some(vec![
+                        RpcFilterType::DataSize(StakeStateV2::size_of() as u64),
+                        RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+                            offset,
+                            authority.to_bytes().to_vec(),
+                        )),
+                    ]),
+                    account_config: RpcAccountInfoConfig {
+                        encoding: Some(UiAccountEncoding::Base64),
+                        // Only the addresses are needed
+                        data_slice: Some(UiDataSliceConfig {
+                            offset: 0,
+                            length: 0,
+                        }),
+                        ..RpcAccountInfoConfig::default()
+                    },
+                    ..RpcProgramAccountsConfig::default()
+                },
+            )
+            .context("Failed to look up stake accounts")?;
+        accounts.extend(found.into_iter().map(|(address, _)| address));
+    }
+    accounts.sort();
+    accounts.dedup();
+    Ok(accounts)
+}
+
+/// The rewards paid to `accounts` for the last `epochs` completed epochs,
+/// newest first
+pub fn rewards(rpc_client: &dyn RpcApi, accounts: &[Pubkey], epochs: u64) -> Result<Vec<Reward>> {
+    if accounts.is_empty() || epochs == 0 {
+        return Ok(Vec::new());
+    }
+    let current = rpc_client
+        .get_epoch_info()
+        .context("Failed to fetch the epoch")?
+        .epoch;
+    let mut block_times = HashMap::new();
+    let mut rewards = Vec::new();
+    // The current epoch's rewards are only paid once it ends
+    for epoch in (current.saturating_sub(epochs)..current).rev() {
+        let paid = rpc_client
+            .get_inflation_reward(accounts, Some(epoch))
+            .with_context(|| format!("Failed to fetch the rewards for epoch {}", epoch))?;
+        for (&stake_account, reward) in accounts.iter().zip(paid) {
+            let Some(reward) = reward else { continue };
+            // Every account is paid in the same block at the start of the
+            // epoch, so this is usually one lookup per epoch
+            let block_time = *block_times
+                .entry(reward.effective_slot)
+                .or_insert_with(|| rpc_client.get_block_time(reward.effective_slot).ok());
+            rewards.push(Reward {
+                epoch: reward.epoch,
+                stake_account,
+                lamports: reward.amount,
+                post_balance: reward.post_balance,
+                commission: reward.commission,
+                slot: reward.effective_slot,
+                block_time,
+            });
+        }
+    }
+    Ok(rewards)
+}
+
+/// The rewards paid to the stake accounts of `authority` for the last
+/// `epochs` completed epochs, newest first, and the accounts looked at
+pub fn wallet_rewards(
+    rpc_client: &dyn RpcApi,
+    authority: &Pubkey,
+    epochs: u64,
+) -> Result<(Vec<Pubkey>, Vec<Reward>)> {
+    if epochs == 0 {
+        return Ok((Vec::new(), Vec::new()));
+    }
+    let accounts = stake_accounts(rpc_client, authority)?;
+    let rewards = rewards(rpc_client, &accounts, epochs)?;
+    Ok((accounts, rewards))
+}
+
+/// Rewards (newest first) summed per epoch, newest first, with the running
+/// total from the oldest
+pub fn per_epoch(rewards: &[Reward]) -> Vec<EpochRewards> {
+    let mut epochs: Vec<EpochRewards> = Vec::new();
+    for reward in rewards.iter().rev() {
+        match epochs.last_mut() {
+            Some(last) if last.epoch == reward.epoch => {
+                last.lamports += reward.lamports;
+                last.accounts += 1;
+                last.cumulative += reward.lamports;
+            }
+            last => {
+                let cumulative = last.map_or(0, |last| last.cumulative) + reward.lamports;
+                epochs.push(EpochRewards {
+                    epoch: reward.epoch,
+                    lamports: reward.lamports,
+                    accounts: 1,
+                    cumulative,
+                });
+            }
+        }
+    }
+    epochs.reverse();
+    epochs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::{MockRpc, MOCK_BLOCK_TIME, MOCK_SLOT, MOCK_SLOTS_PER_EPOCH};
+    use solana_sdk::account::Account;
+    #[allow(deprecated)]
+    use solana_sdk::stake::state::{Authorized, Meta};
+
+    fn stake_account(staker: Pubkey, withdrawer: Pubkey) -> Account {
+        let state = StakeStateV2::Initialized(Meta {
+            authorized: Authorized { staker, withdrawer },
+            ..Meta::default()
+        });
+        let mut data = bincode::serialize(&state).unwrap();
+        data.resize(StakeStateV2::size_of(), 0);
+        Account {
+            lamports: 10_000_000_000,
+            data,
+            owner: stake::program::ID,
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    #[test]
+    fn sums_rewards_per_epoch() {
+        let rpc = MockRpc::default();
+        let wallet = Pubkey::new_unique();
+        let staked = Pubkey::new_unique();
+        let withdrawable = Pubkey::new_unique();
+        rpc.set_account(staked, stake_account(wallet, wallet));
+        rpc.set_account(withdrawable, stake_account(Pubkey::new_unique(), wallet));
+        rpc.set_account(
+            Pubkey::new_unique(),
+            stake_account(Pubkey::new_unique(), Pubkey::new_unique()),
+        );
+
+        let current = MOCK_SLOT / MOCK_SLOTS_PER_EPOCH;
+        rpc.set_inflation_reward(current - 1, staked, 3_000_000);
+        rpc.set_inflation_reward(current - 1, withdrawable, 1_000_000);
+        rpc.set_inflation_reward(current - 3, staked, 2_000_000);
+        // Too long ago
+        rpc.set_inflation_reward(current - 4, staked, 9_000_000);
+
+        let (accounts, rewards) = wallet_rewards(&rpc, &wallet, 3).unwrap();
+        let mut expected = vec![staked, withdrawable];
+        expected.sort();
+        assert_eq!(accounts, expected);
+        assert_eq!(rewards.len(), 3);
+        assert_eq!(rewards[0].epoch, current - 1);
+        assert_eq!(rewards[2].epoch, current - 3);
+        assert_eq!(rewards[0].slot, current * MOCK_SLOTS_PER_EPOCH);
+        assert_eq!(
+            rewards[0].block_time,
+            Some(MOCK_BLOCK_TIME - (MOCK_SLOT - current * MOCK_SLOTS_PER_EPOCH) as i64 * 2 / 5)
+        );
+
+        assert_eq!(
+            per_epoch(&rewards),
+            [
+                EpochRewards {
+                    epoch: current - 1,
+                    lamports: 4_000_000,
+                    accounts: 2,
+                    cumulative: 6_000_000,
+                },
+                EpochRewards {
+                    epoch: current - 3,
+                    lamports: 2_000_000,
+                    accounts: 1,
+                    cumulative: 2_000_000,
+                },
+            ]
+        );
+    }
+}
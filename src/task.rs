@@ -0,0 +1,80 @@
+//! RPC calls run on a background thread so the UI keeps drawing, and can
+//! stop waiting for them, while they're in flight
+
+use anyhow::{anyhow, Result};
+use std::{
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// How long each spinner frame is shown, and so how often to redraw while
+/// a task runs
+pub const SPINNER_FRAME: Duration = Duration::from_millis(100);
+
+const SPINNER: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+const ASCII_SPINNER: [&str; 4] = ["|", "/", "-", "\\"];
+
+/// A running operation. Dropping it stops waiting for the result, though
+/// the thread runs the request to the end.
+pub struct Task<T> {
+    started: Instant,
+    result: Receiver<Result<T>>,
+}
+
+impl<T: Send + 'static> Task<T> {
+    pub fn spawn(work: impl FnOnce() -> Result<T> + Send + 'static) -> Self {
+        let (sender, result) = mpsc::channel();
+        thread::spawn(move || {
+            // The receiver is gone if the task was cancelled
+            let _ = sender.send(work());
+        });
+        Self {
+            started: Instant::now(),
+            result,
+        }
+    }
+
+    /// The result, once the work is done
+    pub fn poll(&self) -> Option<Result<T>> {
+        match self.result.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err(stopped())),
+        }
+    }
+
+    /// Block until the work is done
+    pub fn wait(self) -> Result<T> {
+        self.result.recv().unwrap_or_else(|_| Err(stopped()))
+    }
+
+    /// Spinner and elapsed time, like `⠹ 3s`
+    pub fn progress(&self, ascii: bool) -> String {
+        let elapsed = self.started.elapsed();
+        let frames: &[&str] = if ascii { &ASCII_SPINNER } else { &SPINNER };
+        let frame = (elapsed.as_millis() / SPINNER_FRAME.as_millis()) as usize % frames.len();
+        format!("{} {}s", frames[frame], elapsed.as_secs())
+    }
+}
+
+fn stopped() -> anyhow::Error {
+    anyhow!("The background request stopped unexpectedly")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delivers_the_result_once_done() {
+        let (go, wait) = mpsc::channel::<()>();
+        let task = Task::spawn(move || {
+            wait.recv()?;
+            Ok(42)
+        });
+        assert!(task.poll().is_none());
+        go.send(()).unwrap();
+        assert_eq!(task.wait().unwrap(), 42);
+    }
+}
@@ -0,0 +1,205 @@
+//! Colors used across the UI: a built-in preset, with single colors
+//! overridden from the config
+
+use crate::config::ThemeConfig;
+use crate::explorer::Cluster;
+use anyhow::{anyhow, bail, Result};
+use ratatui::style::{Color, Style};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ThemePreset {
+    /// For dark terminal backgrounds
+    #[default]
+    Dark,
+    /// For light terminal backgrounds
+    Light,
+    /// Bright colors only, and no tinted screens
+    HighContrast,
+}
+
+impl ThemePreset {
+    pub const ALL: [Self; 3] = [Self::Dark, Self::Light, Self::HighContrast];
+
+    /// Name used in the config
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Dark => "dark",
+            Self::Light => "light",
+            Self::HighContrast => "high-contrast",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Theme {
+    /// Ordinary text
+    pub text: Color,
+    /// Titles, labels and active filters
+    pub heading: Color,
+    /// Selected items, text being typed and key names
+    pub accent: Color,
+    /// Hints and inactive items
+    pub muted: Color,
+    /// Incoming amounts and completed actions
+    pub success: Color,
+    /// Errors, warnings, failed transactions and outgoing amounts
+    pub error: Color,
+    /// Text of the wallet overview and the address on Receive
+    pub wallet: Color,
+    /// Text of the transaction history and details
+    pub history: Color,
+    /// Text of the settings screen
+    pub settings: Color,
+    /// Search matches are drawn in this color…
+    pub match_text: Color,
+    /// …on this one
+    pub match_background: Color,
+    /// The title bar and menu on mainnet, where sends are real money
+    pub mainnet: Color,
+    /// The title bar and menu on testnet
+    pub testnet: Color,
+    /// The title bar and menu on devnet
+    pub devnet: Color,
+    /// The title bar and menu on any other cluster, such as a local
+    /// validator
+    pub custom: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::preset(ThemePreset::Dark)
+    }
+}
+
+impl Theme {
+    pub fn preset(preset: ThemePreset) -> Self {
+        match preset {
+            ThemePreset::Dark => Self {
+                text: Color::White,
+                heading: Color::Cyan,
+                accent: Color::Yellow,
+                muted: Color::DarkGray,
+                success: Color::Green,
+                error: Color::Red,
+                wallet: Color::Green,
+                history: Color::Blue,
+                settings: Color::Magenta,
+                match_text: Color::Black,
+                match_background: Color::Yellow,
+                mainnet: Color::Red,
+                testnet: Color::Yellow,
+                devnet: Color::Green,
+                custom: Color::Cyan,
+            },
+            ThemePreset::Light => Self {
+                text: Color::Black,
+                heading: Color::Blue,
+                accent: Color::Magenta,
+                muted: Color::DarkGray,
+                success: Color::Green,
+                error: Color::Red,
+                wallet: Color::Green,
+                history: Color::Blue,
+                settings: Color::Magenta,
+                match_text: Color::White,
+                match_background: Color::Magenta,
+                mainnet: Color::Red,
+                testnet: Color::Magenta,
+                devnet: Color::Green,
+                custom: Color::Blue,
+            },
+            ThemePreset::HighContrast => Self {
+                text: Color::White,
+                heading: Color::LightCyan,
+                accent: Color::LightYellow,
+                muted: Color::Gray,
+                success: Color::LightGreen,
+                error: Color::LightRed,
+                wallet: Color::White,
+                history: Color::White,
+                settings: Color::White,
+                match_text: Color::Black,
+                match_background: Color::LightYellow,
+                mainnet: Color::LightRed,
+                testnet: Color::LightYellow,
+                devnet: Color::LightGreen,
+                custom: Color::LightCyan,
+            },
+        }
+    }
+
+    /// The configured preset with `[theme.colors]` applied. Colors are
+    /// names like `red` or `light-blue`, `#rrggbb`, or a palette index.
+    pub fn from_config(config: &ThemeConfig) -> Result<Self> {
+        let mut theme = Self::preset(config.preset);
+        for (name, value) in &config.colors {
+            let color = value
+                .parse()
+                .map_err(|_| anyhow!("Invalid color `{}` for {} in [theme.colors]", value, name))?;
+            *theme.color_mut(name)? = color;
+        }
+        Ok(theme)
+    }
+
+    fn color_mut(&mut self, name: &str) -> Result<&mut Color> {
+        Ok(match name {
+            "text" => &mut self.text,
+            "heading" => &mut self.heading,
+            "accent" => &mut self.accent,
+            "muted" => &mut self.muted,
+            "success" => &mut self.success,
+            "error" => &mut self.error,
+            "wallet" => &mut self.wallet,
+            "history" => &mut self.history,
+            "settings" => &mut self.settings,
+            "match_text" => &mut self.match_text,
+            "match_background" => &mut self.match_background,
+            "mainnet" => &mut self.mainnet,
+            "testnet" => &mut self.testnet,
+            "devnet" => &mut self.devnet,
+            "custom" => &mut self.custom,
+            _ => bail!("Unknown color `{}` in [theme.colors]", name),
+        })
+    }
+
+    /// The color that tells the cluster apart
+    pub fn cluster(&self, cluster: &Cluster) -> Color {
+        match cluster {
+            Cluster::Mainnet => self.mainnet,
+            Cluster::Testnet => self.testnet,
+            Cluster::Devnet => self.devnet,
+            Cluster::Custom(_) => self.custom,
+        }
+    }
+
+    /// How search matches are highlighted
+    pub fn search_match(&self) -> Style {
+        Style::default()
+            .fg(self.match_text)
+            .bg(self.match_background)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_overrides_the_preset() {
+        let mut config = ThemeConfig {
+            preset: ThemePreset::Light,
+            ..Default::default()
+        };
+        config
+            .colors
+            .insert("accent".to_string(), "#ff8800".to_string());
+        let theme = Theme::from_config(&config).unwrap();
+        assert_eq!(theme.accent, Color::Rgb(0xff, 0x88, 0x00));
+        assert_eq!(theme.text, Color::Black);
+
+        config.colors.insert("bogus".to_string(), "red".to_string());
+        assert!(Theme::from_config(&config).is_err());
+    }
+}
@@ -0,0 +1,453 @@
+//! Keeping to the request rate an RPC endpoint allows, and riding out the
+//! times it says no. Public endpoints limit the bursts of reads a wallet
+//! makes; requests are spaced out to stay under the configured rate, and
+//! ones answered with HTTP 429 or that time out are retried after a wait
+//! that doubles each time, while the UI says the node is throttling rather
+//! than showing the error.
+
+// `ClientError` is large, but it's what `RpcClient` returns
+#![allow(clippy::result_large_err)]
+
+use crate::config::RateLimitConfig;
+use crate::rpc::RpcApi;
+use solana_client::{
+    client_error::{ClientError, ClientErrorKind, Result as ClientResult},
+    rpc_client::GetConfirmedSignaturesForAddress2Config,
+    rpc_config::{
+        RpcProgramAccountsConfig, RpcSendTransactionConfig, RpcSimulateTransactionConfig,
+        RpcTransactionConfig,
+    },
+    rpc_request::RpcError,
+    rpc_response::{
+        RpcConfirmedTransactionStatusWithSignature, RpcInflationReward, RpcPrioritizationFee,
+        RpcResult, RpcSimulateTransactionResult,
+    },
+};
+use solana_sdk::{
+    account::Account,
+    clock::{Epoch, Slot, UnixTimestamp},
+    commitment_config::CommitmentConfig,
+    epoch_info::EpochInfo,
+    hash::Hash,
+    message::VersionedMessage,
+    pubkey::Pubkey,
+    signature::Signature,
+    transaction::VersionedTransaction,
+};
+use solana_transaction_status::{EncodedConfirmedTransactionWithStatusMeta, TransactionStatus};
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Solana's own endpoints, which allow about 10 requests a second from one
+/// address
+const PUBLIC_ENDPOINTS: &[&str] = &[
+    "https://api.mainnet-beta.solana.com",
+    "https://api.testnet.solana.com",
+    "https://api.devnet.solana.com",
+];
+
+/// The rate kept to on the public endpoints unless configured otherwise
+pub const PUBLIC_REQUESTS_PER_SECOND: f64 = 10.0;
+
+/// The wait before the first retry; each one after waits twice as long
+pub const FIRST_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// The longest wait between retries
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(8);
+
+/// The requests per second allowed to `url`: its entry under
+/// `[rate_limit.endpoints]`, the configured rate, or the public endpoints'
+/// rate for those. `None` for no limit.
+pub fn requests_per_second(config: &RateLimitConfig, url: &str) -> Option<f64> {
+    let url = url.trim_end_matches('/');
+    let rate = config
+        .endpoints
+        .iter()
+        .find(|(endpoint, _)| endpoint.trim_end_matches('/') == url)
+        .map(|(_, &rate)| rate)
+        .or(config.requests_per_second)
+        .unwrap_or(if PUBLIC_ENDPOINTS.contains(&url) {
+            PUBLIC_REQUESTS_PER_SECOND
+        } else {
+            0.0
+        });
+    (rate > 0.0).then_some(rate)
+}
+
+/// Whether a request failed because the node is throttling or slow, rather
+/// than because of the request itself
+pub fn is_throttled(error: &ClientError) -> bool {
+    match error.kind() {
+        ClientErrorKind::Reqwest(e) => {
+            e.is_timeout() || e.status().is_some_and(|status| status.as_u16() == 429)
+        }
+        ClientErrorKind::Io(e) => e.kind() == std::io::ErrorKind::TimedOut,
+        // Some providers answer in JSON-RPC rather than with the status
+        ClientErrorKind::RpcError(
+            RpcError::RpcRequestError(message) | RpcError::RpcResponseError { message, .. },
+        ) => {
+            let message = message.to_lowercase();
+            ["429", "too many requests", "rate limit"]
+                .iter()
+                .any(|pattern| message.contains(pattern))
+        }
+        _ => false,
+    }
+}
+
+/// How many requests are waiting to be retried, for the UI
+#[derive(Debug, Default)]
+pub struct Backoff {
+    waiting: AtomicUsize,
+}
+
+impl Backoff {
+    /// Whether the node throttled a request that's still being retried
+    pub fn retrying(&self) -> bool {
+        self.waiting.load(Ordering::Relaxed) > 0
+    }
+}
+
+/// Counts a request as retrying until it's dropped
+struct Retrying<'a>(&'a Backoff);
+
+impl<'a> Retrying<'a> {
+    fn new(backoff: &'a Backoff) -> Self {
+        backoff.waiting.fetch_add(1, Ordering::Relaxed);
+        Self(backoff)
+    }
+}
+
+impl Drop for Retrying<'_> {
+    fn drop(&mut self) {
+        self.0.waiting.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Requests allowed right away, refilled at the rate up to a second's worth
+struct Bucket {
+    rate: f64,
+    /// Below zero when requests are queued for later
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn new(rate: f64) -> Self {
+        Self {
+            rate,
+            tokens: rate.max(1.0),
+            updated: Instant::now(),
+        }
+    }
+
+    /// Take a request's turn, returning how long to wait for it
+    fn take(&mut self) -> Duration {
+        let now = Instant::now();
+        let refilled = now.duration_since(self.updated).as_secs_f64() * self.rate;
+        self.tokens = (self.tokens + refilled).min(self.rate.max(1.0)) - 1.0;
+        self.updated = now;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.rate)
+        }
+    }
+}
+
+/// An RPC client kept to a request rate, retrying throttled requests
+pub struct ThrottledRpc<R> {
+    inner: R,
+    /// `None` for no limit
+    bucket: Option<Mutex<Bucket>>,
+    retries: u32,
+    backoff: Arc<Backoff>,
+}
+
+impl<R: RpcApi> ThrottledRpc<R> {
+    /// `requests_per_second` of `None` sends requests as they come
+    pub fn new(inner: R, requests_per_second: Option<f64>, retries: u32) -> Self {
+        Self {
+            inner,
+            bucket: requests_per_second.map(|rate| Mutex::new(Bucket::new(rate))),
+            retries,
+            backoff: Arc::default(),
+        }
+    }
+
+    pub fn inner(&self) -> &R {
+        &self.inner
+    }
+
+    /// Whether requests are being retried, shared with the UI
+    pub fn backoff(&self) -> Arc<Backoff> {
+        self.backoff.clone()
+    }
+
+    /// Make a request in its turn, retrying it while it's throttled
+    fn call<T>(
+        &self,
+        method: &'static str,
+        request: impl Fn(&R) -> ClientResult<T>,
+    ) -> ClientResult<T> {
+        let mut retrying = None;
+        let mut delay = FIRST_RETRY_DELAY;
+        let mut retries = self.retries;
+        loop {
+            if let Some(ref bucket) = self.bucket {
+                let wait = bucket.lock().unwrap_or_else(|e| e.into_inner()).take();
+                thread::sleep(wait);
+            }
+            let started = Instant::now();
+            let result = request(&self.inner);
+            let ms = started.elapsed().as_millis() as u64;
+            match result {
+                Err(e) if retries > 0 && is_throttled(&e) => {
+                    tracing::warn!(method, ms, error = %e, "RPC node throttled, retrying");
+                    retrying.get_or_insert_with(|| Retrying::new(&self.backoff));
+                    retries -= 1;
+                    thread::sleep(delay);
+                    delay = (delay * 2).min(MAX_RETRY_DELAY);
+                }
+                // Often expected, like a missing account
+                Err(e) => {
+                    tracing::debug!(method, ms, error = %e, "RPC request failed");
+                    return Err(e);
+                }
+                Ok(value) => {
+                    tracing::debug!(method, ms, "RPC request");
+                    return Ok(value);
+                }
+            }
+        }
+    }
+}
+
+impl<R: RpcApi> RpcApi for ThrottledRpc<R> {
+    fn commitment(&self) -> CommitmentConfig {
+        self.inner.commitment()
+    }
+
+    fn get_balance(&self, pubkey: &Pubkey) -> ClientResult<u64> {
+        self.call("get_balance", |rpc| rpc.get_balance(pubkey))
+    }
+
+    fn get_account(&self, pubkey: &Pubkey) -> ClientResult<Account> {
+        self.call("get_account", |rpc| rpc.get_account(pubkey))
+    }
+
+    fn get_account_with_commitment(
+        &self,
+        pubkey: &Pubkey,
+        commitment_config: CommitmentConfig,
+    ) -> RpcResult<Option<Account>> {
+        self.call("get_account_with_commitment", |rpc| {
+            rpc.get_account_with_commitment(pubkey, commitment_config)
+        })
+    }
+
+    fn get_multiple_accounts(&self, pubkeys: &[Pubkey]) -> ClientResult<Vec<Option<Account>>> {
+        self.call("get_multiple_accounts", |rpc| {
+            rpc.get_multiple_accounts(pubkeys)
+        })
+    }
+
+    fn get_program_accounts_with_config(
+        &self,
+        pubkey: &Pubkey,
+        config: RpcProgramAccountsConfig,
+    ) -> ClientResult<Vec<(Pubkey, Account)>> {
+        self.call("get_program_accounts_with_config", |rpc| {
+            rpc.get_program_accounts_with_config(pubkey, config.clone())
+        })
+    }
+
+    fn get_minimum_balance_for_rent_exemption(&self, data_len: usize) -> ClientResult<u64> {
+        self.call("get_minimum_balance_for_rent_exemption", |rpc| {
+            rpc.get_minimum_balance_for_rent_exemption(data_len)
+        })
+    }
+
+    fn get_genesis_hash(&self) -> ClientResult<Hash> {
+        self.call("get_genesis_hash", |rpc| rpc.get_genesis_hash())
+    }
+
+    fn get_slot_with_commitment(&self, commitment_config: CommitmentConfig) -> ClientResult<Slot> {
+        self.call("get_slot_with_commitment", |rpc| {
+            rpc.get_slot_with_commitment(commitment_config)
+        })
+    }
+
+    fn get_latest_blockhash(&self) -> ClientResult<Hash> {
+        self.call("get_latest_blockhash", |rpc| rpc.get_latest_blockhash())
+    }
+
+    fn get_epoch_info(&self) -> ClientResult<EpochInfo> {
+        self.call("get_epoch_info", |rpc| rpc.get_epoch_info())
+    }
+
+    fn get_inflation_reward(
+        &self,
+        addresses: &[Pubkey],
+        epoch: Option<Epoch>,
+    ) -> ClientResult<Vec<Option<RpcInflationReward>>> {
+        self.call("get_inflation_reward", |rpc| {
+            rpc.get_inflation_reward(addresses, epoch)
+        })
+    }
+
+    fn get_block_time(&self, slot: Slot) -> ClientResult<UnixTimestamp> {
+        self.call("get_block_time", |rpc| rpc.get_block_time(slot))
+    }
+
+    fn get_fee_for_message(&self, message: &VersionedMessage) -> ClientResult<u64> {
+        self.call("get_fee_for_message", |rpc| {
+            rpc.get_fee_for_message(message)
+        })
+    }
+
+    fn get_recent_prioritization_fees(
+        &self,
+        addresses: &[Pubkey],
+    ) -> ClientResult<Vec<RpcPrioritizationFee>> {
+        self.call("get_recent_prioritization_fees", |rpc| {
+            rpc.get_recent_prioritization_fees(addresses)
+        })
+    }
+
+    fn simulate_transaction_with_config(
+        &self,
+        transaction: &VersionedTransaction,
+        config: RpcSimulateTransactionConfig,
+    ) -> RpcResult<RpcSimulateTransactionResult> {
+        self.call("simulate_transaction_with_config", |rpc| {
+            rpc.simulate_transaction_with_config(transaction, config.clone())
+        })
+    }
+
+    fn send_transaction_with_config(
+        &self,
+        transaction: &VersionedTransaction,
+        config: RpcSendTransactionConfig,
+    ) -> ClientResult<Signature> {
+        // Sending a signed transaction again can't make it land twice
+        self.call("send_transaction_with_config", |rpc| {
+            rpc.send_transaction_with_config(transaction, config)
+        })
+    }
+
+    fn get_signature_statuses(
+        &self,
+        signatures: &[Signature],
+    ) -> RpcResult<Vec<Option<TransactionStatus>>> {
+        self.call("get_signature_statuses", |rpc| {
+            rpc.get_signature_statuses(signatures)
+        })
+    }
+
+    fn get_signature_statuses_with_history(
+        &self,
+        signatures: &[Signature],
+    ) -> RpcResult<Vec<Option<TransactionStatus>>> {
+        self.call("get_signature_statuses_with_history", |rpc| {
+            rpc.get_signature_statuses_with_history(signatures)
+        })
+    }
+
+    fn is_blockhash_valid(
+        &self,
+        blockhash: &Hash,
+        commitment: CommitmentConfig,
+    ) -> ClientResult<bool> {
+        self.call("is_blockhash_valid", |rpc| {
+            rpc.is_blockhash_valid(blockhash, commitment)
+        })
+    }
+
+    fn get_signatures_for_address_with_config(
+        &self,
+        address: &Pubkey,
+        config: GetConfirmedSignaturesForAddress2Config,
+    ) -> ClientResult<Vec<RpcConfirmedTransactionStatusWithSignature>> {
+        // Not `Clone`
+        self.call("get_signatures_for_address_with_config", |rpc| {
+            rpc.get_signatures_for_address_with_config(
+                address,
+                GetConfirmedSignaturesForAddress2Config {
+                    before: config.before,
+                    until: config.until,
+                    limit: config.limit,
+                    commitment: config.commitment,
+                },
+            )
+        })
+    }
+
+    fn get_transaction_with_config(
+        &self,
+        signature: &Signature,
+        config: RpcTransactionConfig,
+    ) -> ClientResult<EncodedConfirmedTransactionWithStatusMeta> {
+        self.call("get_transaction_with_config", |rpc| {
+            rpc.get_transaction_with_config(signature, config)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::MockRpc;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn limits_the_public_endpoints_unless_configured() {
+        let mut config = RateLimitConfig::default();
+        assert_eq!(
+            requests_per_second(&config, "https://api.devnet.solana.com/"),
+            Some(PUBLIC_REQUESTS_PER_SECOND)
+        );
+        assert_eq!(requests_per_second(&config, "http://localhost:8899"), None);
+
+        config.requests_per_second = Some(25.0);
+        config.endpoints = BTreeMap::from([("http://localhost:8899".to_string(), 0.0)]);
+        assert_eq!(
+            requests_per_second(&config, "https://api.devnet.solana.com"),
+            Some(25.0)
+        );
+        assert_eq!(requests_per_second(&config, "http://localhost:8899"), None);
+    }
+
+    #[test]
+    fn spaces_out_requests_and_retries_throttled_ones() {
+        let rpc = ThrottledRpc::new(MockRpc::default(), Some(50.0), 2);
+        let started = Instant::now();
+        for _ in 0..60 {
+            rpc.get_balance(&Pubkey::new_unique()).unwrap();
+        }
+        // A second's worth right away, the rest at the rate
+        assert!(started.elapsed() >= Duration::from_millis(150));
+
+        let rpc = ThrottledRpc::new(MockRpc::default(), None, 2);
+        rpc.inner().throttle("get_balance", 1);
+        let started = Instant::now();
+        rpc.get_balance(&Pubkey::new_unique()).unwrap();
+        assert!(started.elapsed() >= FIRST_RETRY_DELAY);
+        assert!(!rpc.backoff().retrying());
+
+        // Given up on once the retries run out, with the node's answer
+        rpc.inner().throttle("get_balance", 3);
+        let error = rpc.get_balance(&Pubkey::new_unique()).unwrap_err();
+        assert!(is_throttled(&error));
+        rpc.inner().fail("get_balance");
+        assert!(!is_throttled(
+            &rpc.get_balance(&Pubkey::new_unique()).unwrap_err()
+        ));
+    }
+}
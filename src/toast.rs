@@ -0,0 +1,112 @@
+//! Short-lived notifications shown over the bottom-right corner
+
+use ratatui::{
+    layout::Rect,
+    style::Style,
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+    Frame,
+};
+use solace::theme::Theme;
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+/// How long a toast stays up
+const TOAST_DURATION: Duration = Duration::from_secs(4);
+
+/// Older toasts are dropped once this many are showing
+const MAX_TOASTS: usize = 3;
+
+/// Widest a toast gets, borders included
+const MAX_WIDTH: u16 = 60;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ToastKind {
+    Info,
+    Success,
+    Error,
+}
+
+struct Toast {
+    message: String,
+    kind: ToastKind,
+    shown_at: Instant,
+}
+
+#[derive(Default)]
+pub struct Toasts {
+    /// Oldest first
+    toasts: VecDeque<Toast>,
+}
+
+impl Toasts {
+    pub fn push(&mut self, kind: ToastKind, message: impl Into<String>) {
+        if self.toasts.len() == MAX_TOASTS {
+            self.toasts.pop_front();
+        }
+        self.toasts.push_back(Toast {
+            message: message.into(),
+            kind,
+            shown_at: Instant::now(),
+        });
+    }
+
+    pub fn info(&mut self, message: impl Into<String>) {
+        self.push(ToastKind::Info, message);
+    }
+
+    pub fn success(&mut self, message: impl Into<String>) {
+        self.push(ToastKind::Success, message);
+    }
+
+    pub fn error(&mut self, message: impl Into<String>) {
+        let message = message.into();
+        tracing::warn!(error = %message, "Error toast");
+        self.push(ToastKind::Error, message);
+    }
+
+    /// Drop toasts that have been up long enough
+    pub fn expire(&mut self) {
+        self.toasts
+            .retain(|toast| toast.shown_at.elapsed() < TOAST_DURATION);
+    }
+
+    /// Draw the toasts stacked upwards from the bottom-right corner of
+    /// `area`, newest at the bottom
+    pub fn render(&self, f: &mut Frame, area: Rect, theme: &Theme) {
+        let mut bottom = area.bottom();
+        for toast in self.toasts.iter().rev() {
+            let (title, color) = match toast.kind {
+                ToastKind::Info => ("Info", theme.heading),
+                ToastKind::Success => ("Done", theme.success),
+                ToastKind::Error => ("Error", theme.error),
+            };
+            let max_width = MAX_WIDTH.min(area.width);
+            let text_width = max_width.saturating_sub(2).max(1);
+            let length = toast.message.chars().count() as u16;
+            let width = (length + 2).clamp(title.len() as u16 + 2, max_width);
+            let height = length.div_ceil(text_width).max(1) + 2;
+            if bottom < area.top() + height {
+                break;
+            }
+            bottom -= height;
+
+            let rect = Rect::new(area.right() - width, bottom, width, height);
+            let style = Style::default().fg(color);
+            f.render_widget(Clear, rect);
+            f.render_widget(
+                Paragraph::new(toast.message.clone())
+                    .style(style)
+                    .wrap(Wrap { trim: true })
+                    .block(
+                        Block::default()
+                            .borders(Borders::ALL)
+                            .border_style(style)
+                            .title(title),
+                    ),
+                rect,
+            );
+        }
+    }
+}
@@ -0,0 +1,676 @@
+//! SPL token mints made from the wallet, for trying tokens out on devnet
+//! without the `spl-token` CLI, optionally named with Metaplex metadata so
+//! explorers and other wallets show more than an address. Mints the wallet
+//! is the authority of can then have tokens minted, and its own burned.
+//! Delegates approved to spend from the wallet's token accounts can be
+//! found and revoked, and the accounts holding a mint the wallet is the
+//! freeze authority of frozen and thawed.
+
+use crate::rpc::RpcApi;
+use crate::sender;
+use anyhow::{bail, Context, Result};
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::{
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+    rpc_filter::{Memcmp, RpcFilterType},
+};
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    program_pack::{IsInitialized, Pack},
+    pubkey,
+    pubkey::Pubkey,
+    signature::{Keypair, Signature, Signer},
+};
+#[allow(deprecated)]
+use solana_sdk::{system_instruction, system_program, sysvar};
+use spl_associated_token_account::{
+    get_associated_token_address, instruction::create_associated_token_account_idempotent,
+};
+use spl_token::{
+    instruction::AuthorityType,
+    state::{Account as TokenAccount, Mint},
+};
+
+/// Metaplex's Token Metadata program
+pub const METADATA_PROGRAM_ID: Pubkey = pubkey!("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+
+/// The longest name, symbol and URI the metadata program takes, in bytes
+pub const MAX_NAME_LEN: usize = 32;
+pub const MAX_SYMBOL_LEN: usize = 10;
+pub const MAX_URI_LEN: usize = 200;
+
+/// `CreateMetadataAccountV3` in the metadata program's instruction enum
+const CREATE_METADATA_ACCOUNT_V3: u8 = 33;
+
+/// What a token is called. The URI points at a JSON file with the rest,
+/// such as its image.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Metadata {
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+}
+
+impl Metadata {
+    /// Fails on fields longer than the metadata program allows
+    pub fn new(name: &str, symbol: &str, uri: &str) -> Result<Self> {
+        for (field, value, max) in [
+            ("name", name, MAX_NAME_LEN),
+            ("symbol", symbol, MAX_SYMBOL_LEN),
+            ("URI", uri, MAX_URI_LEN),
+        ] {
+            if value.len() > max {
+                bail!("The token {} can be at most {} bytes long", field, max);
+            }
+        }
+        Ok(Self {
+            name: name.to_string(),
+            symbol: symbol.to_string(),
+            uri: uri.to_string(),
+        })
+    }
+}
+
+/// The settings of a mint to create
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewMint {
+    pub decimals: u8,
+    /// Who can mint more of the token
+    pub mint_authority: Pubkey,
+    /// Who can freeze token accounts holding it, if anyone
+    pub freeze_authority: Option<Pubkey>,
+    pub metadata: Option<Metadata>,
+}
+
+/// The address of a mint's metadata account
+pub fn metadata_address(mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"metadata", METADATA_PROGRAM_ID.as_ref(), mint.as_ref()],
+        &METADATA_PROGRAM_ID,
+    )
+    .0
+}
+
+/// Create a mint at a new address with `signer` paying for it, returning
+/// the address. Metadata has to be signed for by the mint authority, so a
+/// mint with metadata and another authority starts out with the wallet as
+/// its authority and is handed over in the same transaction; the wallet
+/// stays able to update the metadata.
+pub fn create_mint(
+    rpc_client: &dyn RpcApi,
+    signer: &dyn Signer,
+    new: &NewMint,
+) -> Result<(Pubkey, Signature)> {
+    let payer = signer.pubkey();
+    let mint = Keypair::new();
+    let address = mint.pubkey();
+    let lamports = rpc_client
+        .get_minimum_balance_for_rent_exemption(Mint::LEN)
+        .context("Failed to fetch the rent-exempt minimum")?;
+    let initial_authority = if new.metadata.is_some() {
+        payer
+    } else {
+        new.mint_authority
+    };
+    let mut instructions = vec![
+        system_instruction::create_account(
+            &payer,
+            &address,
+            lamports,
+            Mint::LEN as u64,
+            &spl_token::ID,
+        ),
+        spl_token::instruction::initialize_mint2(
+            &spl_token::ID,
+            &address,
+            &initial_authority,
+            new.freeze_authority.as_ref(),
+            new.decimals,
+        )?,
+    ];
+    if let Some(ref metadata) = new.metadata {
+        instructions.push(create_metadata_instruction(&address, &payer, metadata));
+        if new.mint_authority != payer {
+            instructions.push(spl_token::instruction::set_authority(
+                &spl_token::ID,
+                &address,
+                Some(&new.mint_authority),
+                AuthorityType::MintTokens,
+                &payer,
+                &[],
+            )?);
+        }
+    }
+
+    let signature = sender::send_instructions(rpc_client, &[signer, &mint], &instructions)
+        .context("Failed to create the mint")?;
+    Ok((address, signature))
+}
+
+/// The mint at `address`, which must belong to the Token Program
+pub fn fetch_mint(rpc_client: &dyn RpcApi, address: &Pubkey) -> Result<Mint> {
+    let account = rpc_client
+        .get_account(address)
+        .with_context(|| format!("Failed to fetch mint {}", address))?;
+    if account.owner != spl_token::ID {
+        bail!("{} is not a Token Program mint", address);
+    }
+    Mint::unpack(&account.data).with_context(|| format!("{} is not a token mint", address))
+}
+
+/// The mints `authority` can mint more of, by address
+pub fn minted_by(rpc_client: &dyn RpcApi, authority: &Pubkey) -> Result<Vec<(Pubkey, Mint)>> {
+    // The mint authority leads a mint's data, as a `COption` tag of 1
+    // followed by the key
+    let mut mint_authority = vec![1, 0, 0, 0];
+    mint_authority.extend_from_slice(authority.as_ref());
+    find(rpc_client, 0, mint_authority).context("Failed to fetch the wallet's mints")
+}
+
+/// The token accounts holding `mint`, by address
+pub fn holders(rpc_client: &dyn RpcApi, mint: &Pubkey) -> Result<Vec<(Pubkey, TokenAccount)>> {
+    find(rpc_client, 0, mint.to_bytes().to_vec()).context("Failed to fetch the mint's holders")
+}
+
+/// Freeze the token `accounts` holding `mint`, so nothing can move in or
+/// out of them, or thaw them again, in one transaction. Only the freeze
+/// authority can.
+pub fn set_frozen(
+    rpc_client: &dyn RpcApi,
+    signer: &dyn Signer,
+    address: &Pubkey,
+    mint: &Mint,
+    accounts: &[Pubkey],
+    frozen: bool,
+) -> Result<Signature> {
+    let wallet = signer.pubkey();
+    if Option::from(mint.freeze_authority) != Some(wallet) {
+        bail!("The wallet is not the freeze authority of {}", address);
+    }
+    let instruction = if frozen {
+        spl_token::instruction::freeze_account
+    } else {
+        spl_token::instruction::thaw_account
+    };
+    let instructions = accounts
+        .iter()
+        .map(|account| instruction(&spl_token::ID, account, address, &wallet, &[]))
+        .collect::<Result<Vec<_>, _>>()?;
+    sender::send_instructions(rpc_client, &[signer], &instructions).with_context(|| {
+        if frozen {
+            "Failed to freeze the accounts"
+        } else {
+            "Failed to thaw the accounts"
+        }
+    })
+}
+
+/// The token accounts `owner` holds, by address
+pub fn accounts_of(rpc_client: &dyn RpcApi, owner: &Pubkey) -> Result<Vec<(Pubkey, TokenAccount)>> {
+    // After the mint
+    find(rpc_client, 32, owner.to_bytes().to_vec())
+        .context("Failed to fetch the wallet's token accounts")
+}
+
+/// The accounts the wallet has let others spend from, with the delegate
+/// and what's left of the allowance. dApps often ask for an approval and
+/// leave it in place after they're done.
+pub fn delegations(rpc_client: &dyn RpcApi, owner: &Pubkey) -> Result<Vec<(Pubkey, TokenAccount)>> {
+    let mut accounts = accounts_of(rpc_client, owner)?;
+    accounts.retain(|(_, account)| account.delegate.is_some());
+    Ok(accounts)
+}
+
+/// Let `delegate` spend up to `amount` (in the smallest units) of `mint`
+/// from the wallet's associated token account, replacing any approval
+/// already on it
+pub fn approve(
+    rpc_client: &dyn RpcApi,
+    signer: &dyn Signer,
+    address: &Pubkey,
+    mint: &Mint,
+    delegate: &Pubkey,
+    amount: u64,
+) -> Result<Signature> {
+    let wallet = signer.pubkey();
+    let instruction = spl_token::instruction::approve_checked(
+        &spl_token::ID,
+        &get_associated_token_address(&wallet, address),
+        address,
+        delegate,
+        &wallet,
+        &[],
+        amount,
+        mint.decimals,
+    )?;
+    sender::send_instructions(rpc_client, &[signer], &[instruction])
+        .context("Failed to approve the delegate")
+}
+
+/// Take away the delegates of the wallet's token `accounts`, in one
+/// transaction
+pub fn revoke(
+    rpc_client: &dyn RpcApi,
+    signer: &dyn Signer,
+    accounts: &[Pubkey],
+) -> Result<Signature> {
+    let wallet = signer.pubkey();
+    let instructions = accounts
+        .iter()
+        .map(|account| spl_token::instruction::revoke(&spl_token::ID, account, &wallet, &[]))
+        .collect::<Result<Vec<_>, _>>()?;
+    sender::send_instructions(rpc_client, &[signer], &instructions)
+        .context("Failed to revoke the delegates")
+}
+
+/// The Token Program's accounts of `T`'s size with `bytes` at `offset`
+fn find<T: Pack + IsInitialized>(
+    rpc_client: &dyn RpcApi,
+    offset: usize,
+    bytes: Vec<u8>,
+) -> Result<Vec<(Pubkey, T)>> {
+    let accounts = rpc_client.get_program_accounts_with_config(
+        &spl_token::ID,
+        RpcProgramAccountsConfig {
+            filters: Some(vec![
+                RpcFilterType::DataSize(T::LEN as u64),
+                RpcFilterType::Memcmp(Memcmp::new_raw_bytes(offset, bytes)),
+            ]),
+            account_config: RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                ..RpcAccountInfoConfig::default()
+            },
+            ..RpcProgramAccountsConfig::default()
+        },
+    )?;
+    Ok(accounts
+        .into_iter()
+        .filter_map(|(address, account)| Some((address, T::unpack(&account.data).ok()?)))
+        .collect())
+}
+
+/// Mint `amount` (in the smallest units) of `mint` to `owner`'s associated
+/// token account, creating it first if there isn't one. Only the mint
+/// authority can.
+pub fn mint_to(
+    rpc_client: &dyn RpcApi,
+    signer: &dyn Signer,
+    address: &Pubkey,
+    mint: &Mint,
+    owner: &Pubkey,
+    amount: u64,
+) -> Result<Signature> {
+    let wallet = signer.pubkey();
+    if Option::from(mint.mint_authority) != Some(wallet) {
+        bail!("The wallet is not the mint authority of {}", address);
+    }
+    let destination = get_associated_token_address(owner, address);
+    let instructions = [
+        create_associated_token_account_idempotent(&wallet, owner, address, &spl_token::ID),
+        spl_token::instruction::mint_to_checked(
+            &spl_token::ID,
+            address,
+            &destination,
+            &wallet,
+            &[],
+            amount,
+            mint.decimals,
+        )?,
+    ];
+    sender::send_instructions(rpc_client, &[signer], &instructions)
+        .context("Failed to mint the tokens")
+}
+
+/// Burn `amount` (in the smallest units) of `mint` from the wallet's
+/// associated token account
+pub fn burn(
+    rpc_client: &dyn RpcApi,
+    signer: &dyn Signer,
+    address: &Pubkey,
+    mint: &Mint,
+    amount: u64,
+) -> Result<Signature> {
+    let wallet = signer.pubkey();
+    let source = get_associated_token_address(&wallet, address);
+    let balance = rpc_client
+        .get_account(&source)
+        .ok()
+        .and_then(|account| TokenAccount::unpack(&account.data).ok())
+        .map_or(0, |account| account.amount);
+    if amount > balance {
+        bail!(
+            "The wallet holds only {} of {}",
+            format_amount(balance, mint.decimals),
+            address
+        );
+    }
+    let instruction = spl_token::instruction::burn_checked(
+        &spl_token::ID,
+        &source,
+        address,
+        &wallet,
+        &[],
+        amount,
+        mint.decimals,
+    )?;
+    sender::send_instructions(rpc_client, &[signer], &[instruction])
+        .context("Failed to burn the tokens")
+}
+
+/// An amount of whole tokens, like `1.5`, in the smallest units of a token
+/// with `decimals` places
+pub fn parse_amount(text: &str, decimals: u8) -> Result<u64> {
+    let text = text.trim();
+    let (whole, fraction) = text.split_once('.').unwrap_or((text, ""));
+    let valid = |digits: &str| digits.chars().all(|c| c.is_ascii_digit());
+    if whole.is_empty() && fraction.is_empty() || !valid(whole) || !valid(fraction) {
+        bail!("Invalid amount {:?}", text);
+    }
+    if fraction.len() > usize::from(decimals) {
+        bail!("The token has only {} decimal places", decimals);
+    }
+    let digits = format!(
+        "{}{:0<width$}",
+        whole,
+        fraction,
+        width = usize::from(decimals)
+    );
+    let amount: u64 = digits
+        .parse()
+        .with_context(|| format!("Amount {} is too large", text))?;
+    if amount == 0 {
+        bail!("The amount must be more than 0");
+    }
+    Ok(amount)
+}
+
+/// `amount` in the smallest units of a token with `decimals` places as
+/// whole tokens, without trailing zeros
+pub fn format_amount(amount: u64, decimals: u8) -> String {
+    let decimals = usize::from(decimals);
+    let digits = format!("{:0>width$}", amount, width = decimals + 1);
+    let (whole, fraction) = digits.split_at(digits.len() - decimals);
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() {
+        whole.to_string()
+    } else {
+        format!("{}.{}", whole, fraction)
+    }
+}
+
+/// `CreateMetadataAccountV3` for `mint`, with `authority` as its mint
+/// authority, payer and update authority. The data is Borsh encoded: the
+/// three strings, then no royalties, creators, collection or uses, the
+/// metadata left mutable, and no collection details.
+fn create_metadata_instruction(
+    mint: &Pubkey,
+    authority: &Pubkey,
+    metadata: &Metadata,
+) -> Instruction {
+    let mut data = vec![CREATE_METADATA_ACCOUNT_V3];
+    for field in [&metadata.name, &metadata.symbol, &metadata.uri] {
+        data.extend_from_slice(&(field.len() as u32).to_le_bytes());
+        data.extend_from_slice(field.as_bytes());
+    }
+    // Seller fee basis points, then the options
+    data.extend_from_slice(&0u16.to_le_bytes());
+    data.extend_from_slice(&[0, 0, 0, 1, 0]);
+    Instruction {
+        program_id: METADATA_PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(metadata_address(mint), false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new(*authority, true),
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+        ],
+        data,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::MockRpc;
+    use solana_sdk::account::Account;
+    use spl_token::instruction::TokenInstruction;
+
+    #[test]
+    fn creates_a_mint_with_metadata() {
+        let rpc = MockRpc::default();
+        let wallet = Keypair::new();
+        assert!(Metadata::new("Test", "TOOLONGSYMBOL", "").is_err());
+
+        let new = NewMint {
+            decimals: 6,
+            mint_authority: wallet.pubkey(),
+            freeze_authority: None,
+            metadata: None,
+        };
+        let (mint, _) = create_mint(&rpc, &wallet, &new).unwrap();
+        let sent = rpc.sent();
+        assert_eq!(sent[0].signatures.len(), 2);
+        assert_eq!(sent[0].message.static_account_keys()[1], mint);
+        let instructions = sent[0].message.instructions();
+        assert_eq!(instructions.len(), 2);
+        assert_eq!(
+            TokenInstruction::unpack(&instructions[1].data).unwrap(),
+            TokenInstruction::InitializeMint2 {
+                decimals: 6,
+                mint_authority: wallet.pubkey(),
+                freeze_authority: None.into(),
+            }
+        );
+
+        // Handed to another authority after the metadata is made
+        let authority = Pubkey::new_unique();
+        let new = NewMint {
+            mint_authority: authority,
+            metadata: Some(Metadata::new("Test Token", "TEST", "https://example.com").unwrap()),
+            ..new
+        };
+        create_mint(&rpc, &wallet, &new).unwrap();
+        let sent = rpc.sent();
+        let instructions = sent[1].message.instructions();
+        assert_eq!(instructions.len(), 4);
+        let metadata = &instructions[2].data;
+        assert_eq!(metadata[0], CREATE_METADATA_ACCOUNT_V3);
+        assert_eq!(&metadata[1..5], &10u32.to_le_bytes());
+        assert_eq!(&metadata[5..15], b"Test Token");
+        assert!(matches!(
+            TokenInstruction::unpack(&instructions[3].data).unwrap(),
+            TokenInstruction::SetAuthority { new_authority, .. }
+                if new_authority == Some(authority).into()
+        ));
+    }
+
+    /// A Token Program account holding `state`
+    fn token_account<T: Pack>(state: T) -> Account {
+        let mut data = vec![0; T::LEN];
+        state.pack_into_slice(&mut data);
+        Account {
+            lamports: 1,
+            data,
+            owner: spl_token::ID,
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    #[test]
+    fn mints_and_burns_for_the_mints_it_controls() {
+        let rpc = MockRpc::default();
+        let wallet = Keypair::new();
+        let ours = Pubkey::new_unique();
+        let mint = Mint {
+            mint_authority: Some(wallet.pubkey()).into(),
+            decimals: 6,
+            is_initialized: true,
+            ..Mint::default()
+        };
+        rpc.set_account(ours, token_account(mint));
+        let theirs = Pubkey::new_unique();
+        let other = Mint {
+            mint_authority: Some(Pubkey::new_unique()).into(),
+            ..mint
+        };
+        rpc.set_account(theirs, token_account(other));
+
+        let minted = minted_by(&rpc, &wallet.pubkey()).unwrap();
+        assert_eq!(minted, [(ours, mint)]);
+        assert_eq!(fetch_mint(&rpc, &ours).unwrap(), mint);
+        assert!(mint_to(&rpc, &wallet, &theirs, &other, &wallet.pubkey(), 1).is_err());
+
+        let recipient = Pubkey::new_unique();
+        mint_to(&rpc, &wallet, &ours, &mint, &recipient, 1_500_000).unwrap();
+        let sent = rpc.sent();
+        let instructions = sent[0].message.instructions();
+        assert_eq!(
+            TokenInstruction::unpack(&instructions[1].data).unwrap(),
+            TokenInstruction::MintToChecked {
+                amount: 1_500_000,
+                decimals: 6
+            }
+        );
+        assert!(sent[0]
+            .message
+            .static_account_keys()
+            .contains(&get_associated_token_address(&recipient, &ours)));
+
+        // Only what the wallet holds can be burned
+        assert!(burn(&rpc, &wallet, &ours, &mint, 1).is_err());
+        let held = TokenAccount {
+            mint: ours,
+            owner: wallet.pubkey(),
+            amount: 2_000_000,
+            state: spl_token::state::AccountState::Initialized,
+            ..TokenAccount::default()
+        };
+        rpc.set_account(
+            get_associated_token_address(&wallet.pubkey(), &ours),
+            token_account(held),
+        );
+        burn(&rpc, &wallet, &ours, &mint, 2_000_000).unwrap();
+        assert_eq!(rpc.sent().len(), 2);
+    }
+
+    #[test]
+    fn finds_and_revokes_delegations() {
+        let rpc = MockRpc::default();
+        let wallet = Keypair::new();
+        let mint = Pubkey::new_unique();
+        let delegated = Pubkey::new_unique();
+        let delegate = Pubkey::new_unique();
+        let account = TokenAccount {
+            mint,
+            owner: wallet.pubkey(),
+            amount: 100,
+            state: spl_token::state::AccountState::Initialized,
+            ..TokenAccount::default()
+        };
+        rpc.set_account(
+            delegated,
+            token_account(TokenAccount {
+                delegate: Some(delegate).into(),
+                delegated_amount: 40,
+                ..account
+            }),
+        );
+        rpc.set_account(Pubkey::new_unique(), token_account(account));
+        rpc.set_account(
+            Pubkey::new_unique(),
+            token_account(TokenAccount {
+                owner: Pubkey::new_unique(),
+                delegate: Some(delegate).into(),
+                ..account
+            }),
+        );
+
+        assert_eq!(accounts_of(&rpc, &wallet.pubkey()).unwrap().len(), 2);
+        let found = delegations(&rpc, &wallet.pubkey()).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].0, delegated);
+        assert_eq!(found[0].1.delegated_amount, 40);
+
+        revoke(&rpc, &wallet, &[delegated]).unwrap();
+        let sent = rpc.sent();
+        assert_eq!(
+            TokenInstruction::unpack(&sent[0].message.instructions()[0].data).unwrap(),
+            TokenInstruction::Revoke
+        );
+
+        let mint_state = Mint {
+            decimals: 2,
+            is_initialized: true,
+            ..Mint::default()
+        };
+        approve(&rpc, &wallet, &mint, &mint_state, &delegate, 500).unwrap();
+        assert_eq!(
+            TokenInstruction::unpack(&rpc.sent()[1].message.instructions()[0].data).unwrap(),
+            TokenInstruction::ApproveChecked {
+                amount: 500,
+                decimals: 2
+            }
+        );
+    }
+
+    #[test]
+    fn freezes_holders_as_freeze_authority() {
+        let rpc = MockRpc::default();
+        let wallet = Keypair::new();
+        let address = Pubkey::new_unique();
+        let mint = Mint {
+            freeze_authority: Some(wallet.pubkey()).into(),
+            is_initialized: true,
+            ..Mint::default()
+        };
+        rpc.set_account(address, token_account(mint));
+        let holder = Pubkey::new_unique();
+        let account = TokenAccount {
+            mint: address,
+            owner: Pubkey::new_unique(),
+            state: spl_token::state::AccountState::Initialized,
+            ..TokenAccount::default()
+        };
+        rpc.set_account(holder, token_account(account));
+        rpc.set_account(
+            Pubkey::new_unique(),
+            token_account(TokenAccount {
+                mint: Pubkey::new_unique(),
+                ..account
+            }),
+        );
+
+        assert_eq!(holders(&rpc, &address).unwrap(), [(holder, account)]);
+        set_frozen(&rpc, &wallet, &address, &mint, &[holder], true).unwrap();
+        set_frozen(&rpc, &wallet, &address, &mint, &[holder], false).unwrap();
+        let sent = rpc.sent();
+        let instruction =
+            |i: usize| TokenInstruction::unpack(&sent[i].message.instructions()[0].data).unwrap();
+        assert_eq!(instruction(0), TokenInstruction::FreezeAccount);
+        assert_eq!(instruction(1), TokenInstruction::ThawAccount);
+
+        let unfreezable = Mint {
+            freeze_authority: None.into(),
+            ..mint
+        };
+        assert!(set_frozen(&rpc, &wallet, &address, &unfreezable, &[holder], true).is_err());
+    }
+
+    #[test]
+    fn converts_amounts_of_whole_tokens() {
+        assert_eq!(parse_amount("1.5", 6).unwrap(), 1_500_000);
+        assert_eq!(parse_amount("42", 0).unwrap(), 42);
+        assert_eq!(parse_amount(".25", 2).unwrap(), 25);
+        for invalid in ["", ".", "1.2.3", "-1", "0", "1.0000001"] {
+            assert!(parse_amount(invalid, 6).is_err(), "{:?}", invalid);
+        }
+        assert!(parse_amount("18446744073709551616", 0).is_err());
+
+        assert_eq!(format_amount(1_500_000, 6), "1.5");
+        assert_eq!(format_amount(25, 2), "0.25");
+        assert_eq!(format_amount(42, 0), "42");
+    }
+}
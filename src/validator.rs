@@ -0,0 +1,135 @@
+//! A `solana-test-validator` run for the session, for trying programs and
+//! transfers against a local cluster without a faucet or real money
+
+use crate::{config, wallet::sol_to_lamports};
+use anyhow::{bail, Context, Result};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey};
+use std::{
+    fs::File,
+    path::{Path, PathBuf},
+    process::{Child, Command, ExitStatus, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Where the validator's RPC listens, its default
+pub const RPC_URL: &str = "http://127.0.0.1:8899";
+
+/// How long the validator gets to start answering, which on a first run
+/// includes creating the ledger
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How often it's asked whether it's up, or whether an airdrop landed
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// The validator, stopped when this is dropped. One that was already
+/// running when solace started is used as it is and left running.
+pub struct LocalValidator {
+    child: Option<Child>,
+    ledger: PathBuf,
+}
+
+impl LocalValidator {
+    /// Start `solana-test-validator` with its ledger in the data directory,
+    /// kept between sessions, and wait until its RPC answers
+    pub fn start() -> Result<Self> {
+        let ledger = config::data_dir()?.join("test-ledger");
+        let rpc_client = RpcClient::new(RPC_URL.to_string());
+        if rpc_client.get_health().is_ok() {
+            tracing::info!("Using the local validator already running");
+            return Ok(Self {
+                child: None,
+                ledger,
+            });
+        }
+
+        // Its own log is in the ledger; this is for what it prints on the way
+        std::fs::create_dir_all(&ledger)
+            .with_context(|| format!("Failed to create {}", ledger.display()))?;
+        let output = File::create(ledger.join("solace-output.log"))
+            .context("Failed to create the validator's output log")?;
+        let child = Command::new("solana-test-validator")
+            .arg("--ledger")
+            .arg(&ledger)
+            .arg("--quiet")
+            .stdin(Stdio::null())
+            .stdout(output.try_clone()?)
+            .stderr(output)
+            .spawn()
+            .context("Failed to start solana-test-validator; is the Solana CLI installed?")?;
+        tracing::info!(pid = child.id(), ledger = %ledger.display(), "Started local validator");
+        let mut validator = Self {
+            child: Some(child),
+            ledger,
+        };
+        validator.wait_until_ready(&rpc_client)?;
+        Ok(validator)
+    }
+
+    fn wait_until_ready(&mut self, rpc_client: &RpcClient) -> Result<()> {
+        let started = Instant::now();
+        loop {
+            if let Some(status) = self.exited() {
+                bail!(
+                    "solana-test-validator stopped ({}); see {}",
+                    status,
+                    self.ledger.join("solace-output.log").display()
+                );
+            }
+            if rpc_client.get_health().is_ok() {
+                tracing::info!(elapsed = ?started.elapsed(), "Local validator ready");
+                return Ok(());
+            }
+            if started.elapsed() > STARTUP_TIMEOUT {
+                bail!(
+                    "solana-test-validator didn't answer within {} seconds",
+                    STARTUP_TIMEOUT.as_secs()
+                );
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    /// The ledger directory, to reset the cluster by removing it
+    pub fn ledger(&self) -> &Path {
+        &self.ledger
+    }
+
+    /// How the validator ended, if it did since it was started
+    pub fn exited(&mut self) -> Option<ExitStatus> {
+        self.child.as_mut()?.try_wait().ok().flatten()
+    }
+
+    /// Airdrop `sol` to `address` from the validator's faucet and wait for
+    /// it to be confirmed
+    pub fn airdrop(&self, address: &Pubkey, sol: f64) -> Result<()> {
+        let rpc_client =
+            RpcClient::new_with_commitment(RPC_URL.to_string(), CommitmentConfig::confirmed());
+        let signature = rpc_client
+            .request_airdrop(address, sol_to_lamports(sol))
+            .context("Failed to request the airdrop")?;
+        let started = Instant::now();
+        while !rpc_client
+            .confirm_transaction(&signature)
+            .context("Failed to confirm the airdrop")?
+        {
+            if started.elapsed() > STARTUP_TIMEOUT {
+                bail!("The airdrop {} wasn't confirmed in time", signature);
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+        tracing::info!(%address, sol, %signature, "Airdropped");
+        Ok(())
+    }
+}
+
+impl Drop for LocalValidator {
+    fn drop(&mut self) {
+        if let Some(ref mut child) = self.child {
+            let _ = child.kill();
+            let _ = child.wait();
+            tracing::info!("Stopped local validator");
+        }
+    }
+}
@@ -0,0 +1,596 @@
+//! The wallet's work apart from the TUI: resolving the cluster, loading
+//! seed phrase accounts, the checks and steps of a send, and fetching the
+//! history into the local index. Shared by the TUI, the headless commands
+//! and the daemon.
+
+use crate::{
+    blocklist::Blocklist,
+    config::Config,
+    history::{History, SOL_DECIMALS},
+    historydb::HistoryDb,
+    jito::Jito,
+    keys::{Derivation, KeypairSource, SecretKeypair, SeedPhrase},
+    limits::{LimitCheck, SpendingLedger},
+    lookup_table,
+    output::Refused,
+    pay, priority, rent,
+    rpc::RpcApi,
+    sender::{Landed, Pipeline, Status},
+};
+use anyhow::{bail, Context, Result};
+#[allow(deprecated)]
+use solana_sdk::system_instruction;
+use solana_sdk::{
+    compute_budget::ComputeBudgetInstruction,
+    instruction::AccountMeta,
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    signature::{Signature, Signer},
+    transaction::VersionedTransaction,
+};
+use std::{
+    fmt,
+    io::{self, IsTerminal},
+    str::FromStr,
+    sync::Arc,
+};
+
+pub const MAINNET_GENESIS_HASH: &str = "5eykt4UsFv8P8NJdTREpY1vzqKqZKvdpKuc147dw2N9d";
+pub const DEVNET_GENESIS_HASH: &str = "EtWTRABZaYq6iMfeYKouRu166VU2xqa1wcaWoxPkrZBG";
+pub const TESTNET_GENESIS_HASH: &str = "4uhcVJyU9pJkvQyS88uRDiswHXSCkY3zQawwpjk2NsNY";
+
+/// Context for failing to fetch a blockhash: the transfer was never sent,
+/// so it's safe to try again
+#[derive(Debug)]
+pub struct NoBlockhash;
+
+impl fmt::Display for NoBlockhash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Failed to get recent blockhash")
+    }
+}
+
+/// Sign a transfer of `lamports` to `recipient`, send it through
+/// `pipeline` and wait for it to be confirmed, reporting its progress
+pub fn transfer(
+    rpc_client: &dyn RpcApi,
+    pipeline: &Pipeline,
+    keypair: &SecretKeypair,
+    payer: &Pubkey,
+    recipient: &Pubkey,
+    lamports: u64,
+    on_status: &mut dyn FnMut(&Signature, Status),
+) -> Result<Landed> {
+    let transaction = sign_transfer_for(rpc_client, pipeline, keypair, payer, recipient, lamports)?;
+    pipeline
+        .submit(rpc_client, &transaction, on_status)?
+        .into_result()
+}
+
+/// A transfer signed with a recent blockhash, ready to send
+pub fn sign_transfer(
+    rpc_client: &dyn RpcApi,
+    keypair: &SecretKeypair,
+    payer: &Pubkey,
+    recipient: &Pubkey,
+    lamports: u64,
+) -> Result<VersionedTransaction> {
+    sign_transfer_for(
+        rpc_client,
+        &Pipeline::default(),
+        keypair,
+        payer,
+        recipient,
+        lamports,
+    )
+}
+
+/// A transfer signed for `pipeline`, with its priority fee, paying the tip
+/// if it goes through Jito, and as a v0 transaction if one of its lookup
+/// tables makes it smaller. Its fee payer signs too, if it has one.
+fn sign_transfer_for(
+    rpc_client: &dyn RpcApi,
+    pipeline: &Pipeline,
+    keypair: &SecretKeypair,
+    from: &Pubkey,
+    recipient: &Pubkey,
+    lamports: u64,
+) -> Result<VersionedTransaction> {
+    let payer = &pipeline.fee_payer_of(from);
+
+    // Create transfer instruction
+    let mut instructions = Vec::new();
+    if let Some(price) = pipeline.compute_unit_price.filter(|&price| price > 0) {
+        instructions.push(ComputeBudgetInstruction::set_compute_unit_price(price));
+    }
+    let mut transfer = system_instruction::transfer(from, recipient, lamports);
+    if let Some(reference) = pipeline.reference {
+        // Read-only and unsigned, like a Solana Pay reference
+        transfer
+            .accounts
+            .push(AccountMeta::new_readonly(reference, false));
+    }
+    instructions.push(transfer);
+    if let Some(ref jito) = pipeline.jito {
+        instructions.push(jito.tip_instruction(payer));
+    }
+
+    // Get recent blockhash
+    let recent_blockhash = rpc_client.get_latest_blockhash().context(NoBlockhash)?;
+
+    // Ask for only the compute units it needs
+    if let Some(limit) =
+        priority::compute_unit_limit(rpc_client, payer, &instructions, recent_blockhash)
+    {
+        instructions.insert(0, ComputeBudgetInstruction::set_compute_unit_limit(limit));
+    }
+
+    // Build transaction
+    let tables = pipeline
+        .lookup_tables
+        .iter()
+        .map(|table| lookup_table::fetch(rpc_client, table).map(|table| table.lookup_account()))
+        .collect::<Result<Vec<_>>>()?;
+    let message = lookup_table::compile(payer, &instructions, recent_blockhash, &tables)?;
+    let mut signers: Vec<&dyn Signer> = vec![keypair];
+    if let Some(ref fee_payer) = pipeline.fee_payer {
+        if fee_payer.pubkey() != keypair.pubkey() {
+            signers.push(&**fee_payer);
+        }
+    }
+    VersionedTransaction::try_new(message, &signers).context("Failed to sign the transfer")
+}
+
+/// How transfers are sent: waiting for finalization if `finalized` or
+/// `[confirm] finalized`, through Jito with a tip if `[jito]` is enabled or
+/// `tip` (in SOL) is given, with the fees paid by `fee_payer` if there is
+/// one, and with a new reference key if `[payments] reference` is on. Jito
+/// only reaches mainnet, which `mainnet` is asked about then.
+pub fn transfer_pipeline(
+    config: &Config,
+    finalized: bool,
+    tip: Option<f64>,
+    fee_payer: Option<Arc<SecretKeypair>>,
+    mainnet: impl FnOnce() -> bool,
+) -> Result<Pipeline> {
+    let mut pipeline = Pipeline::finalized(finalized || config.confirm.finalized);
+    pipeline.fee_payer = fee_payer;
+    pipeline.reference = config.payments.reference.then(pay::new_reference);
+    pipeline.lookup_tables = config
+        .lookup_tables
+        .addresses
+        .iter()
+        .map(|address| {
+            Pubkey::from_str(address)
+                .with_context(|| format!("Invalid lookup table address {:?}", address))
+        })
+        .collect::<Result<_>>()?;
+    if config.jito.enabled || tip.is_some() {
+        if !mainnet() {
+            bail!(Refused(
+                "Jito's block engine only sends to mainnet; turn off [jito] to send on this \
+                 cluster"
+                    .to_string()
+            ));
+        }
+        pipeline.jito = Some(Jito::from_config(&config.jito, tip)?);
+    }
+    Ok(pipeline)
+}
+
+/// Whether `rpc_url` is on mainnet, which it's taken to be when the node
+/// can't say
+pub fn is_mainnet(rpc_client: &dyn RpcApi, rpc_url: &str) -> bool {
+    rpc_url.contains("mainnet")
+        || rpc_client
+            .get_genesis_hash()
+            .map_or(true, |hash| hash.to_string() == MAINNET_GENESIS_HASH)
+}
+
+/// Sends of at least this many lamports on mainnet need the amount retyped
+pub fn typed_threshold(config: &Config) -> u64 {
+    sol_to_lamports(config.confirm.typed_threshold)
+}
+
+/// `sol` in lamports, through its decimal text so that 1.005 SOL is exactly
+/// 1,005,000,000 lamports rather than what the float multiplies to. Digits
+/// past a lamport are dropped, and amounts too large for a u64 saturate.
+pub fn sol_to_lamports(sol: f64) -> u64 {
+    let text = sol.max(0.0).to_string();
+    let (whole, fraction) = text.split_once('.').unwrap_or((&text, ""));
+    let fraction = &fraction[..fraction.len().min(usize::from(SOL_DECIMALS))];
+    format!(
+        "{}{:0<width$}",
+        whole,
+        fraction,
+        width = usize::from(SOL_DECIMALS)
+    )
+    .parse()
+    .unwrap_or(u64::MAX)
+}
+
+/// The recipient and lamports of a send requested by address and SOL
+pub fn parse_send(to: &str, amount: f64) -> Result<(Pubkey, u64)> {
+    let recipient = Pubkey::from_str(to).context("Invalid recipient address")?;
+    let lamports = sol_to_lamports(amount);
+    if lamports == 0 {
+        bail!("The amount must be more than 0 SOL");
+    }
+    Ok((recipient, lamports))
+}
+
+/// The Send screen's checks for sends made without it: blocklisted
+/// recipients and sends over a hard spending limit are refused, and so are
+/// amounts that would leave a new account, or the sender, below the
+/// rent-exempt minimum. Returns why the send exceeds a soft limit, if it
+/// does.
+#[allow(clippy::too_many_arguments)]
+pub fn check_send(
+    rpc_client: &dyn RpcApi,
+    config: &Config,
+    blocklist: &Blocklist,
+    spending: &SpendingLedger,
+    sender: &Pubkey,
+    fee_payer: &Pubkey,
+    recipient: &Pubkey,
+    lamports: u64,
+) -> Result<Option<String>> {
+    if let Some(reason) = blocklist.reason(recipient) {
+        bail!(Refused(format!(
+            "{} is on your blocklist: {}",
+            recipient, reason
+        )));
+    }
+    let exceeded = match spending.check(&config.limits, lamports) {
+        LimitCheck::Within => None,
+        LimitCheck::Exceeded(reason) => Some(reason),
+        LimitCheck::Blocked(reason) => bail!(Refused(format!("Send blocked: amount {}", reason))),
+    };
+    if let Some(minimum) = rent::recipient_shortfall(rpc_client, recipient, lamports)? {
+        bail!(Refused(format!(
+            "{} does not exist yet, and amounts below the rent-exempt minimum ({} SOL) may be \
+             lost or unusable",
+            recipient,
+            minimum as f64 / LAMPORTS_PER_SOL as f64
+        )));
+    }
+    if let Some(minimum) = rent::sender_shortfall(rpc_client, sender, fee_payer, lamports)? {
+        bail!(Refused(sender_rent_message(minimum)));
+    }
+    Ok(exceeded)
+}
+
+/// Why a send that would leave the sender below the rent-exempt minimum
+/// can't go through
+pub fn sender_rent_message(minimum: u64) -> String {
+    format!(
+        "This would leave less than the rent-exempt minimum ({} SOL) in the wallet, which the \
+         network rejects; leave at least that much, or send everything but the fee",
+        minimum as f64 / LAMPORTS_PER_SOL as f64
+    )
+}
+
+/// The priority fee suggested for a transfer from `sender` to `recipient`,
+/// or none if there's no suggestion, with a warning on stderr
+pub fn suggest_priority_fee(
+    rpc_client: &dyn RpcApi,
+    config: &Config,
+    sender: &Pubkey,
+    recipient: &Pubkey,
+) -> u64 {
+    priority::suggest(rpc_client, &config.priority_fee, &[*sender, *recipient]).unwrap_or_else(
+        |e| {
+            eprintln!("Warning: {:#}; sending without a priority fee", e);
+            0
+        },
+    )
+}
+
+/// A confirmed transfer made without the TUI
+pub struct Sent {
+    pub signature: Signature,
+    /// Where it was confirmed
+    pub slot: u64,
+    pub from: Pubkey,
+    pub to: Pubkey,
+    pub lamports: u64,
+    /// Who paid the fee, if not the wallet
+    pub fee_payer: Option<Pubkey>,
+    /// Lamports, if the node could price the message
+    pub fee: Option<u64>,
+    /// Lamports paid to Jito, if it went through the block engine
+    pub tip: Option<u64>,
+    /// The reference key added to the transfer, if it has one
+    pub reference: Option<Pubkey>,
+}
+
+impl Sent {
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "signature": self.signature.to_string(),
+            "slot": self.slot,
+            "from": self.from.to_string(),
+            "to": self.to.to_string(),
+            "lamports": self.lamports,
+            "fee_payer": self.fee_payer.map(|fee_payer| fee_payer.to_string()),
+            "fee": self.fee,
+            "tip": self.tip,
+            "reference": self.reference.map(|reference| reference.to_string()),
+        })
+    }
+}
+
+/// Sign and send a transfer from the wallet itself, recording it against
+/// the spending limits once confirmed, and its reference key in the local
+/// history
+#[allow(clippy::too_many_arguments)]
+pub fn send_and_record(
+    rpc_client: &dyn RpcApi,
+    pipeline: &Pipeline,
+    keypair: &SecretKeypair,
+    spending: &mut SpendingLedger,
+    recipient: &Pubkey,
+    lamports: u64,
+    on_status: &mut dyn FnMut(&Signature, Status),
+) -> Result<Sent> {
+    let from = keypair.pubkey();
+    let transaction = sign_transfer_for(rpc_client, pipeline, keypair, &from, recipient, lamports)?;
+    // Only reported, so a node that can't price the message doesn't stop
+    // the send
+    let fee = rpc_client.get_fee_for_message(&transaction.message).ok();
+    let landed = pipeline
+        .submit(rpc_client, &transaction, on_status)?
+        .into_result()?;
+    if let Err(e) = spending.record(lamports) {
+        eprintln!("Warning: {:#}", e);
+    }
+    if let Some(ref reference) = pipeline.reference {
+        if let Err(e) = record_reference(rpc_client, &from, &landed.signature, reference) {
+            eprintln!("Warning: {:#}", e);
+        }
+    }
+    Ok(Sent {
+        signature: landed.signature,
+        slot: landed.slot,
+        from,
+        to: *recipient,
+        lamports,
+        fee_payer: Some(pipeline.fee_payer_of(&from)).filter(|&fee_payer| fee_payer != from),
+        fee,
+        tip: pipeline.jito.as_ref().map(|jito| jito.tip),
+        reference: pipeline.reference,
+    })
+}
+
+/// Keep the reference key of a transfer the wallet sent with its signature,
+/// for the cluster the RPC node is on
+fn record_reference(
+    rpc_client: &dyn RpcApi,
+    wallet: &Pubkey,
+    signature: &Signature,
+    reference: &Pubkey,
+) -> Result<()> {
+    let cluster = rpc_client
+        .get_genesis_hash()
+        .context("Failed to record the reference key")?
+        .to_string();
+    HistoryDb::open()?.save_reference(wallet, &cluster, &signature.to_string(), reference)
+}
+
+/// The balance result of `solace balance` and the daemon
+pub fn balance_json(address: &Pubkey, lamports: u64) -> serde_json::Value {
+    serde_json::json!({
+        "address": address.to_string(),
+        "lamports": lamports,
+        "sol": lamports as f64 / LAMPORTS_PER_SOL as f64,
+    })
+}
+
+/// Ask on the terminal whether to go ahead with a send, failing if the
+/// answer is no or there's no terminal to ask on
+pub fn confirm_send(recipient: &Pubkey, amount: f64, typed: bool) -> Result<()> {
+    if !io::stdin().is_terminal() {
+        bail!(Refused(
+            "Not sending without confirmation; pass --yes to skip it".to_string()
+        ));
+    }
+    let mut answer = String::new();
+    if typed {
+        eprint!(
+            "Large mainnet send of {} SOL to {}. Type the amount to confirm: ",
+            amount, recipient
+        );
+        io::stdin().read_line(&mut answer)?;
+        if answer.trim().parse::<f64>().ok() != Some(amount) {
+            bail!(Refused(
+                "Confirmation does not match; nothing was sent".to_string()
+            ));
+        }
+    } else {
+        eprint!("Send {} SOL to {}? [y/N] ", amount, recipient);
+        io::stdin().read_line(&mut answer)?;
+        if !answer.trim().eq_ignore_ascii_case("y") {
+            bail!(Refused("Cancelled; nothing was sent".to_string()));
+        }
+    }
+    Ok(())
+}
+
+pub fn resolve_rpc_url(cluster: &str) -> String {
+    // Check if cluster is a known preset or a custom URL
+    match cluster.to_lowercase().as_str() {
+        "mainnet" | "mainnet-beta" => "https://api.mainnet-beta.solana.com".to_string(),
+        "testnet" => "https://api.testnet.solana.com".to_string(),
+        "devnet" => "https://api.devnet.solana.com".to_string(),
+        "localhost" | "localnet" => "http://localhost:8899".to_string(),
+        // If not a preset, assume it's a custom RPC URL
+        custom => {
+            // Add https:// if no protocol is specified
+            if custom.starts_with("http://") || custom.starts_with("https://") {
+                custom.to_string()
+            } else {
+                format!("https://{}", custom)
+            }
+        }
+    }
+}
+
+/// Load the wallet's keypair. Seed phrases use `derivation_path` or
+/// `account_index`, or else list `count` accounts to choose from.
+pub fn load_keypair(
+    source: &KeypairSource,
+    derivation_path: Option<&str>,
+    account_index: Option<u32>,
+    rpc_client: &dyn RpcApi,
+    count: u32,
+) -> Result<SecretKeypair> {
+    if *source == KeypairSource::Prompt {
+        let seed = Arc::new(SeedPhrase::prompt()?);
+        let derivation = match (derivation_path, account_index) {
+            (Some(path), _) => Derivation::parse(path)?,
+            (None, Some(index)) => Derivation::account(index),
+            (None, None) => select_derived_account(&seed, rpc_client, count)?,
+        };
+        seed.derive(&derivation)
+    } else {
+        source.load().with_context(|| match source {
+            KeypairSource::File(ref path) => format!(
+                "Failed to load keypair from {}. 
+Please ensure the file exists and contains a valid Solana keypair.
+You can create one with: solana-keygen new -o {}",
+                path.display(),
+                path.display()
+            ),
+            ref source => format!("Failed to load keypair from {}", source),
+        })
+    }
+}
+
+/// List the first `count` BIP44 accounts of a seed phrase (plus the root key
+/// used by `solana-keygen`) with their balances and ask which one to use
+pub fn select_derived_account(
+    seed: &Arc<SeedPhrase>,
+    rpc_client: &dyn RpcApi,
+    count: u32,
+) -> Result<Derivation> {
+    let mut derivations = vec![Derivation::Root];
+    derivations.extend((0..count).map(Derivation::account));
+
+    let addresses = derivations
+        .iter()
+        .map(|derivation| seed.derive(derivation).map(|keypair| keypair.pubkey()))
+        .collect::<Result<Vec<_>>>()?;
+    let balances = rpc_client
+        .get_multiple_accounts(&addresses)
+        .context("Failed to fetch account balances")?;
+
+    println!("Accounts derived from your seed phrase:");
+    for (i, ((derivation, address), account)) in derivations
+        .iter()
+        .zip(&addresses)
+        .zip(&balances)
+        .enumerate()
+    {
+        let label = if i == 0 {
+            "r".to_string()
+        } else {
+            (i - 1).to_string()
+        };
+        let lamports = account.as_ref().map_or(0, |account| account.lamports);
+        println!(
+            "  {:>3}  {:<20} {:<44} {:.9} SOL",
+            label,
+            derivation,
+            address,
+            lamports as f64 / LAMPORTS_PER_SOL as f64
+        );
+    }
+
+    loop {
+        print!("Select account [0]: ");
+        io::Write::flush(&mut io::stdout())?;
+        let mut choice = String::new();
+        io::stdin().read_line(&mut choice)?;
+        match choice.trim() {
+            "" => return Ok(Derivation::account(0)),
+            "r" | "R" => return Ok(Derivation::Root),
+            index => match index.parse::<u32>() {
+                Ok(index) if index < count => return Ok(Derivation::account(index)),
+                _ => println!(
+                    "Enter 'r' or an index between 0 and {}",
+                    count.saturating_sub(1)
+                ),
+            },
+        }
+    }
+}
+
+/// The wallet's history from the local index, synced with the RPC and
+/// fetched back until it holds at least `wanted` transactions, reporting how
+/// many it holds after each page
+pub fn fetch_history(
+    rpc_client: &dyn RpcApi,
+    address: &Pubkey,
+    wanted: usize,
+    on_progress: &mut dyn FnMut(usize),
+) -> Result<History> {
+    // The index is keyed by cluster, never by the RPC URL and its API key
+    let cluster = rpc_client
+        .get_genesis_hash()
+        .context("Failed to fetch the genesis hash")?
+        .to_string();
+    let mut db = HistoryDb::open()?;
+    let (entries, complete) = db.load(address, &cluster)?;
+    let mut history = History::from_cache(entries, complete);
+
+    if history.pending_sync {
+        let (new, replaced) = history.sync_new(rpc_client, address)?;
+        if replaced {
+            db.clear(address, &cluster)?;
+        }
+        db.insert(address, &cluster, new)?;
+    }
+    while !history.exhausted && history.entries.len() < wanted {
+        let new = history.load_page(rpc_client, address)?;
+        db.insert(address, &cluster, new)?;
+        on_progress(history.entries.len());
+    }
+    if history.exhausted {
+        db.set_complete(address, &cluster, true)?;
+    }
+    history.apply_notes(&db.load_notes(address, &cluster)?);
+    history.apply_references(&db.load_references(address, &cluster)?);
+    Ok(history)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_sol_to_lamports_without_rounding_errors() {
+        assert_eq!(sol_to_lamports(1.005), 1_005_000_000);
+        assert_eq!(sol_to_lamports(0.000000007), 7);
+        assert_eq!(sol_to_lamports(0.0000000001), 0);
+        assert_eq!(sol_to_lamports(25.0), 25 * LAMPORTS_PER_SOL);
+        assert_eq!(sol_to_lamports(-1.0), 0);
+        assert_eq!(sol_to_lamports(1e30), u64::MAX);
+    }
+
+    #[test]
+    fn resolves_clusters_and_parses_sends() {
+        assert_eq!(resolve_rpc_url("Devnet"), "https://api.devnet.solana.com");
+        assert_eq!(
+            resolve_rpc_url("rpc.example.com/?key=1"),
+            "https://rpc.example.com/?key=1"
+        );
+
+        let recipient = Pubkey::new_unique();
+        assert_eq!(
+            parse_send(&recipient.to_string(), 0.25).unwrap(),
+            (recipient, LAMPORTS_PER_SOL / 4)
+        );
+        assert!(parse_send(&recipient.to_string(), 0.0).is_err());
+        assert!(parse_send("not an address", 1.0).is_err());
+    }
+}
@@ -0,0 +1,242 @@
+//! Addresses watched without their keys, such as exchange deposit addresses
+//! and counterparties: what they hold and their latest transactions,
+//! fetched the way the wallet's own are, and the alerts when they move
+
+use crate::{
+    config::WatchConfig,
+    dashboard::{self, WalletBalance},
+    history::{self, HistoryEntry, SOL_DECIMALS},
+    rpc::RpcApi,
+    token::format_amount,
+    wallet::sol_to_lamports,
+};
+use anyhow::{Context, Result};
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+
+/// Transactions shown for each watched address
+pub const RECENT: usize = 5;
+
+/// What a watched address holds and did last
+#[derive(Debug, Clone)]
+pub struct Watched {
+    pub balance: WalletBalance,
+    /// Newest first
+    pub recent: Vec<HistoryEntry>,
+}
+
+/// The addresses listed in the config
+pub fn addresses(config: &WatchConfig) -> Result<Vec<Pubkey>> {
+    config
+        .addresses
+        .iter()
+        .map(|address| {
+            Pubkey::from_str(address.trim())
+                .with_context(|| format!("Invalid watch address: {}", address))
+        })
+        .collect()
+}
+
+/// Fetch the balances of all `addresses`, then the latest transactions of
+/// each
+pub fn fetch(rpc_client: &dyn RpcApi, addresses: &[Pubkey]) -> Result<Vec<Watched>> {
+    dashboard::fetch(rpc_client, addresses)?
+        .wallets
+        .into_iter()
+        .map(|balance| {
+            let recent = history::fetch_latest(rpc_client, &balance.address, RECENT)?;
+            Ok(Watched { balance, recent })
+        })
+        .collect()
+}
+
+/// Something a watched address did that its alerts ask about
+#[derive(Debug, Clone)]
+pub enum Alert {
+    /// The balance moved by at least the threshold since the last refresh
+    Balance {
+        address: Pubkey,
+        before: u64,
+        after: u64,
+    },
+    /// A transaction involving the address landed
+    Transaction {
+        address: Pubkey,
+        entry: Box<HistoryEntry>,
+    },
+}
+
+impl Alert {
+    pub fn address(&self) -> Pubkey {
+        match *self {
+            Self::Balance { address, .. } | Self::Transaction { address, .. } => address,
+        }
+    }
+
+    /// One line for a notification, the address shown as `label`
+    pub fn message(&self, label: &str) -> String {
+        match *self {
+            Self::Balance { before, after, .. } => {
+                let change = after as i128 - before as i128;
+                format!(
+                    "{}: balance {}{} SOL, now {} SOL",
+                    label,
+                    if change < 0 { "-" } else { "+" },
+                    format_amount(change.unsigned_abs() as u64, SOL_DECIMALS),
+                    format_amount(after, SOL_DECIMALS)
+                )
+            }
+            Self::Transaction { ref entry, .. } => {
+                let mut message = format!("{}: ", label);
+                match entry.details {
+                    Some(ref details) => {
+                        message.push_str(details.label());
+                        message.push(' ');
+                        message.push_str(&details.sol_change());
+                        for delta in &details.token_deltas {
+                            message.push_str(&format!(", {}", delta));
+                        }
+                    }
+                    None => message.push_str("new transaction"),
+                }
+                if entry.failed {
+                    message.push_str(" (failed)");
+                }
+                message
+            }
+        }
+    }
+}
+
+/// What the alerts in `config` ask about between two refreshes, oldest
+/// transaction first. Addresses missing from `before` were only just added,
+/// so nothing is reported for them.
+pub fn alerts(config: &WatchConfig, before: &[Watched], after: &[Watched]) -> Vec<Alert> {
+    let mut alerts = Vec::new();
+    for watched in after {
+        let address = watched.balance.address;
+        let Some(thresholds) = config.alerts.get(&address.to_string()) else {
+            continue;
+        };
+        let Some(previous) = before.iter().find(|w| w.balance.address == address) else {
+            continue;
+        };
+        if let Some(minimum) = thresholds.transaction {
+            let minimum = sol_to_lamports(minimum);
+            let new = watched.recent.iter().take_while(|entry| {
+                !previous
+                    .recent
+                    .iter()
+                    .any(|seen| seen.signature == entry.signature)
+            });
+            for entry in new.collect::<Vec<_>>().into_iter().rev() {
+                let moved = entry
+                    .details
+                    .as_ref()
+                    .map_or(0, |details| details.lamports_delta.unsigned_abs());
+                if moved >= minimum {
+                    alerts.push(Alert::Transaction {
+                        address,
+                        entry: Box::new(entry.clone()),
+                    });
+                }
+            }
+        }
+        if let Some(minimum) = thresholds.balance_change {
+            let (before, after) = (previous.balance.lamports, watched.balance.lamports);
+            if before != after && before.abs_diff(after) >= sol_to_lamports(minimum) {
+                alerts.push(Alert::Balance {
+                    address,
+                    before,
+                    after,
+                });
+            }
+        }
+    }
+    alerts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::AlertConfig, rpc::MockRpc};
+
+    #[test]
+    fn fetches_what_watched_addresses_hold() {
+        let rpc = MockRpc::default();
+        let deposit = Pubkey::new_unique();
+        rpc.set_balance(deposit, 7_000_000_000);
+        let config = WatchConfig {
+            addresses: vec![deposit.to_string(), Pubkey::new_unique().to_string()],
+            ..WatchConfig::default()
+        };
+        let watched = fetch(&rpc, &addresses(&config).unwrap()).unwrap();
+        assert_eq!(watched.len(), 2);
+        assert_eq!(watched[0].balance.lamports, 7_000_000_000);
+        assert_eq!(watched[1].balance.lamports, 0);
+        assert!(watched[0].recent.is_empty());
+
+        let config = WatchConfig {
+            addresses: vec!["exchange".to_string()],
+            ..WatchConfig::default()
+        };
+        assert!(addresses(&config).is_err());
+    }
+
+    #[test]
+    fn alerts_past_the_thresholds() {
+        let address = Pubkey::new_unique();
+        let watched = |lamports: u64, signatures: &[(&str, i64)]| Watched {
+            balance: WalletBalance {
+                address,
+                lamports,
+                tokens: Vec::new(),
+            },
+            recent: signatures
+                .iter()
+                .map(|&(signature, lamports_delta)| HistoryEntry {
+                    signature: signature.to_string(),
+                    slot: 0,
+                    block_time: None,
+                    failed: false,
+                    memo: None,
+                    note: None,
+                    reference: None,
+                    details: Some(history::TxDetails {
+                        kind: history::TxKind::Transfer,
+                        lamports_delta,
+                        fee: 0,
+                        fee_payer: false,
+                        counterparty: None,
+                        token_deltas: Vec::new(),
+                    }),
+                })
+                .collect(),
+        };
+        let mut config = WatchConfig::default();
+        config.alerts.insert(
+            address.to_string(),
+            AlertConfig {
+                transaction: Some(1.0),
+                balance_change: Some(5.0),
+            },
+        );
+        let before = [watched(10_000_000_000, &[("old", 1)])];
+        let after = [watched(
+            3_000_000_000,
+            &[("big", -6_000_000_000), ("dust", -1_000), ("old", 1)],
+        )];
+
+        // Nothing to compare a newly watched address to
+        assert!(alerts(&config, &[], &after).is_empty());
+        let alerts = alerts(&config, &before, &after);
+        assert_eq!(alerts.len(), 2);
+        assert!(
+            matches!(alerts[0], Alert::Transaction { ref entry, .. } if entry.signature == "big")
+        );
+        assert_eq!(
+            alerts[1].message("Exchange"),
+            "Exchange: balance -7 SOL, now 3 SOL"
+        );
+    }
+}
@@ -0,0 +1,760 @@
+//! Wallet event hooks: a webhook POST, a local command and/or the hook
+//! script, run when a transfer is received, a sent transaction confirms or a
+//! watched address alerts, and the pre-send command and script function that
+//! can refuse a transaction before it's sent
+
+use crate::{
+    config::WebhookConfig,
+    explorer::Cluster,
+    history::{self, HistoryEntry},
+    inspector,
+    output::Refused,
+    script::Script,
+    watch::Alert,
+};
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use solana_client::rpc_client::{GetConfirmedSignaturesForAddress2Config, RpcClient};
+use solana_sdk::{
+    commitment_config::CommitmentConfig, message::VersionedMessage, pubkey::Pubkey,
+    signature::Signature,
+};
+use std::{
+    io::{Read, Write},
+    process::{Command, Stdio},
+    str::FromStr,
+    sync::mpsc::{self, Receiver, RecvTimeoutError, Sender},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// How long the pre-send command gets to answer before the send is refused
+const PRE_SEND_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long the script's event hooks get; they hold up the events after them
+const SCRIPT_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    Received,
+    Sent,
+    /// A transaction involving a watched address landed
+    Activity,
+    /// A watched address's balance moved past its threshold
+    BalanceChanged,
+}
+
+impl EventKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Received => "received",
+            Self::Sent => "sent",
+            Self::Activity => "activity",
+            Self::BalanceChanged => "balance_changed",
+        }
+    }
+
+    /// The script function called with events of this kind
+    fn hook(self) -> Option<&'static str> {
+        match self {
+            Self::Received => Some("on_receive"),
+            Self::Sent => Some("on_confirm"),
+            Self::Activity | Self::BalanceChanged => None,
+        }
+    }
+}
+
+/// The JSON payload POSTed to the webhook and piped to the command
+#[derive(Debug, Clone, Serialize)]
+pub struct Event {
+    pub event: EventKind,
+    pub wallet: String,
+    /// `mainnet`, `devnet`, `testnet` or `custom`; the RPC URL itself is
+    /// left out as it often carries an API key
+    pub cluster: &'static str,
+    /// `None` for balance changes
+    pub signature: Option<String>,
+    pub slot: Option<u64>,
+    pub block_time: Option<i64>,
+    /// Change in the wallet's SOL balance, negative when sent
+    pub lamports: i64,
+    pub counterparty: Option<String>,
+    pub tokens: Vec<TokenAmount>,
+    pub memo: Option<String>,
+}
+
+/// The JSON piped to the pre-send command
+#[derive(Debug, Clone, Serialize)]
+struct ProposedSend {
+    event: &'static str,
+    /// Where the SOL goes from, or the fee payer of transactions moving none
+    wallet: String,
+    cluster: &'static str,
+    /// Where the SOL goes, if any moves
+    recipient: Option<String>,
+    /// SOL going from `wallet`
+    lamports: u64,
+    /// Every account the transaction names
+    accounts: Vec<String>,
+}
+
+impl ProposedSend {
+    fn new(rpc_url: &str, message: &VersionedMessage) -> Self {
+        let keys = message.static_account_keys();
+        let transfers = inspector::sol_transfers(message);
+        let wallet = transfers
+            .first()
+            .map(|transfer| transfer.from)
+            .or_else(|| keys.first().copied())
+            .unwrap_or_default();
+        Self {
+            event: "pre_send",
+            wallet: wallet.to_string(),
+            cluster: cluster_name(rpc_url),
+            recipient: transfers.first().map(|transfer| transfer.to.to_string()),
+            lamports: transfers
+                .iter()
+                .filter(|transfer| transfer.from == wallet)
+                .map(|transfer| transfer.lamports)
+                .sum(),
+            accounts: keys.iter().map(Pubkey::to_string).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TokenAmount {
+    pub mint: String,
+    pub symbol: String,
+    /// Decimal string, so large amounts don't lose precision
+    pub amount: String,
+}
+
+impl Event {
+    /// A transfer into the wallet, if that's what this entry is: some
+    /// balance went up in a successful transaction someone else paid for
+    fn received(entry: &HistoryEntry, wallet: &Pubkey, cluster: &'static str) -> Option<Self> {
+        let details = entry.details.as_ref()?;
+        let incoming =
+            details.lamports_delta > 0 || details.token_deltas.iter().any(|d| d.amount > 0);
+        if entry.failed || details.fee_payer || !incoming {
+            return None;
+        }
+        Some(Self::from_entry(
+            EventKind::Received,
+            entry,
+            wallet,
+            cluster,
+        ))
+    }
+
+    /// The transaction `entry` of `address`, as seen from that address
+    fn from_entry(
+        event: EventKind,
+        entry: &HistoryEntry,
+        address: &Pubkey,
+        cluster: &'static str,
+    ) -> Self {
+        let details = entry.details.as_ref();
+        Self {
+            event,
+            wallet: address.to_string(),
+            cluster,
+            signature: Some(entry.signature.clone()),
+            slot: Some(entry.slot),
+            block_time: entry.block_time,
+            lamports: details.map_or(0, |details| details.lamports_delta),
+            counterparty: details
+                .and_then(|details| details.counterparty)
+                .map(|key| key.to_string()),
+            tokens: details
+                .into_iter()
+                .flat_map(|details| &details.token_deltas)
+                .map(|delta| TokenAmount {
+                    mint: delta.mint.to_string(),
+                    symbol: delta.symbol(),
+                    amount: history::format_amount(delta.amount, delta.decimals)
+                        .trim_start_matches('+')
+                        .to_string(),
+                })
+                .collect(),
+            memo: entry.memo.clone(),
+        }
+    }
+}
+
+enum Message {
+    /// Watch another address for incoming transfers
+    Watch(Pubkey),
+    Deliver(Box<Event>),
+}
+
+/// What the hook script asked for in answer to an event
+#[derive(Debug, Clone, PartialEq)]
+pub enum Notice {
+    /// Text passed to `notify`
+    Message(String),
+    /// A note for a received transfer, returned by `on_receive`
+    Label {
+        wallet: String,
+        signature: String,
+        label: String,
+    },
+}
+
+/// Handle on the background thread that watches the wallet and delivers
+/// events. The thread exits when this is dropped.
+pub struct Hooks {
+    messages: Sender<Message>,
+    errors: Receiver<String>,
+    notices: Receiver<Notice>,
+    cluster: &'static str,
+}
+
+impl Hooks {
+    /// Start watching `address`, or `None` if no hook is configured
+    pub fn start(config: &WebhookConfig, rpc_url: &str, address: Pubkey) -> Option<Self> {
+        if config.url.is_none() && config.command.is_none() && config.script.is_none() {
+            return None;
+        }
+        let cluster = cluster_name(rpc_url);
+        let (messages, inbox) = mpsc::channel();
+        let (report, errors) = mpsc::channel();
+        let (notify, notices) = mpsc::channel();
+        let script = match config.script.as_deref().map(Script::load).transpose() {
+            Ok(script) => script,
+            Err(e) => {
+                let _ = report.send(format!("{:#}", e));
+                None
+            }
+        };
+        let worker = Worker {
+            config: config.clone(),
+            rpc_client: RpcClient::new_with_commitment(
+                rpc_url.to_string(),
+                CommitmentConfig::confirmed(),
+            ),
+            cluster,
+            address,
+            started: false,
+            cursor: None,
+            script,
+            report,
+            notify,
+        };
+        thread::spawn(move || worker.run(inbox));
+        Some(Self {
+            messages,
+            errors,
+            notices,
+            cluster,
+        })
+    }
+
+    /// Follow the wallet after switching accounts
+    pub fn watch(&self, address: Pubkey) {
+        let _ = self.messages.send(Message::Watch(address));
+    }
+
+    /// Report a confirmed outgoing SOL transfer
+    pub fn sent(&self, wallet: &Pubkey, signature: &Signature, recipient: &Pubkey, lamports: u64) {
+        let event = Event {
+            event: EventKind::Sent,
+            wallet: wallet.to_string(),
+            cluster: self.cluster,
+            signature: Some(signature.to_string()),
+            slot: None,
+            block_time: None,
+            lamports: -(lamports as i64),
+            counterparty: Some(recipient.to_string()),
+            tokens: Vec::new(),
+            memo: None,
+        };
+        let _ = self.messages.send(Message::Deliver(Box::new(event)));
+    }
+
+    /// Report an alert on a watched address
+    pub fn alert(&self, alert: &Alert) {
+        let event = match *alert {
+            Alert::Transaction { address, ref entry } => {
+                Event::from_entry(EventKind::Activity, entry, &address, self.cluster)
+            }
+            Alert::Balance {
+                address,
+                before,
+                after,
+            } => Event {
+                event: EventKind::BalanceChanged,
+                wallet: address.to_string(),
+                cluster: self.cluster,
+                signature: None,
+                slot: None,
+                block_time: None,
+                lamports: after as i64 - before as i64,
+                counterparty: None,
+                tokens: Vec::new(),
+                memo: None,
+            },
+        };
+        let _ = self.messages.send(Message::Deliver(Box::new(event)));
+    }
+
+    /// Delivery failures since the last call
+    pub fn take_errors(&self) -> Vec<String> {
+        self.errors.try_iter().collect()
+    }
+
+    /// What the hook script asked for since the last call
+    pub fn take_notices(&self) -> Vec<Notice> {
+        self.notices.try_iter().collect()
+    }
+}
+
+struct Worker {
+    config: WebhookConfig,
+    rpc_client: RpcClient,
+    cluster: &'static str,
+    address: Pubkey,
+    /// Whether the history has been looked at since watching `address`
+    started: bool,
+    /// Newest signature seen, `None` if the address had no history
+    cursor: Option<Signature>,
+    script: Option<Script>,
+    report: Sender<String>,
+    notify: Sender<Notice>,
+}
+
+impl Worker {
+    fn run(mut self, inbox: Receiver<Message>) {
+        let interval = Duration::from_secs(self.config.poll_seconds.max(1));
+        self.poll();
+        loop {
+            match inbox.recv_timeout(interval) {
+                Ok(Message::Watch(address)) => {
+                    self.address = address;
+                    self.started = false;
+                    self.cursor = None;
+                    self.poll();
+                }
+                Ok(Message::Deliver(event)) => self.deliver(&event),
+                Err(RecvTimeoutError::Timeout) => self.poll(),
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+        }
+    }
+
+    fn poll(&mut self) {
+        if let Err(e) = self.try_poll() {
+            let _ = self.report.send(format!("{:#}", e));
+        }
+    }
+
+    /// Deliver an event for each transfer received since the last poll.
+    /// The first poll only records where to start from.
+    fn try_poll(&mut self) -> Result<()> {
+        if !self.started {
+            self.cursor = self.newest_signature()?;
+            self.started = true;
+            return Ok(());
+        }
+        let entries = history::fetch_page(&self.rpc_client, &self.address, None, self.cursor)?;
+        if let Some(newest) = entries.first() {
+            self.cursor =
+                Some(Signature::from_str(&newest.signature).context("Invalid signature")?);
+        }
+        // Oldest first, to deliver in the order they landed
+        for entry in entries.iter().rev() {
+            if let Some(event) = Event::received(entry, &self.address, self.cluster) {
+                self.deliver(&event);
+            }
+        }
+        Ok(())
+    }
+
+    fn newest_signature(&self) -> Result<Option<Signature>> {
+        self.rpc_client
+            .get_signatures_for_address_with_config(
+                &self.address,
+                GetConfirmedSignaturesForAddress2Config {
+                    limit: Some(1),
+                    commitment: Some(CommitmentConfig::confirmed()),
+                    ..Default::default()
+                },
+            )
+            .context("Failed to fetch transaction history")?
+            .first()
+            .map(|status| Signature::from_str(&status.signature).context("Invalid signature"))
+            .transpose()
+    }
+
+    fn deliver(&self, event: &Event) {
+        let payload = serde_json::to_string(event).expect("events serialize");
+        if let Some(ref url) = self.config.url {
+            if let Err(e) = post(url, &payload) {
+                let _ = self.report.send(format!("{:#}", e));
+            }
+        }
+        if let Some(ref command) = self.config.command {
+            if let Err(e) = run_command(command, event.event, payload, self.report.clone()) {
+                let _ = self.report.send(format!("{:#}", e));
+            }
+        }
+        if let Err(e) = self.run_script(event) {
+            let _ = self.report.send(format!("{:#}", e));
+        }
+    }
+
+    /// Call the script's function for the event, if it has one. A string
+    /// returned by `on_receive` labels the transfer.
+    fn run_script(&self, event: &Event) -> Result<()> {
+        let (Some(script), Some(hook)) = (&self.script, event.event.hook()) else {
+            return Ok(());
+        };
+        let Some(outcome) = script.call(hook, event, SCRIPT_TIMEOUT)? else {
+            return Ok(());
+        };
+        for text in outcome.notices {
+            let _ = self.notify.send(Notice::Message(text));
+        }
+        if event.event == EventKind::Received {
+            if let (Ok(label), Some(signature)) =
+                (outcome.value.into_string(), event.signature.clone())
+            {
+                if !label.is_empty() {
+                    let _ = self.notify.send(Notice::Label {
+                        wallet: event.wallet.clone(),
+                        signature,
+                        label,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The `cluster` of events; the RPC URL itself often carries an API key
+fn cluster_name(rpc_url: &str) -> &'static str {
+    match Cluster::from_rpc_url(rpc_url) {
+        Cluster::Mainnet => "mainnet",
+        Cluster::Devnet => "devnet",
+        Cluster::Testnet => "testnet",
+        Cluster::Custom(_) => "custom",
+    }
+}
+
+/// Ask the `pre_send` command and the script's `pre_send` function, where
+/// configured, whether to go ahead with sending `message`. This waits on
+/// them, so it isn't for the UI thread.
+pub fn check_pre_send(
+    config: &WebhookConfig,
+    rpc_url: &str,
+    message: &VersionedMessage,
+) -> Result<()> {
+    let send = ProposedSend::new(rpc_url, message);
+    if let Some(ref command) = config.pre_send {
+        run_pre_send(command, &send)?;
+    }
+    if let Some(ref path) = config.script {
+        run_pre_send_script(&Script::load(path)?, &send)?;
+    }
+    Ok(())
+}
+
+/// The command gets the send as JSON on stdin; a non-zero exit refuses it,
+/// with the first line it printed as the reason. So does taking longer than
+/// `PRE_SEND_TIMEOUT`.
+fn run_pre_send(command: &str, send: &ProposedSend) -> Result<()> {
+    let payload = serde_json::to_string(send).expect("sends serialize");
+
+    let mut child = shell(command)
+        .env("SOLACE_EVENT", "pre_send")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .with_context(|| format!("Failed to run pre-send command: {}", command))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = stdin.write_all(payload.as_bytes());
+    }
+    // Read on the side, so a command printing a lot can't fill the pipe
+    // and stall
+    let mut stdout = child.stdout.take().expect("stdout is piped");
+    let output = thread::spawn(move || {
+        let mut output = String::new();
+        let _ = stdout.read_to_string(&mut output);
+        output
+    });
+
+    let started = Instant::now();
+    let status = loop {
+        if let Some(status) = child
+            .try_wait()
+            .with_context(|| format!("Pre-send command {} failed", command))?
+        {
+            break status;
+        }
+        if started.elapsed() > PRE_SEND_TIMEOUT {
+            let _ = child.kill();
+            let _ = child.wait();
+            bail!(Refused(format!(
+                "Pre-send command {} did not answer within {}s",
+                command,
+                PRE_SEND_TIMEOUT.as_secs()
+            )));
+        }
+        thread::sleep(Duration::from_millis(20));
+    };
+    if status.success() {
+        return Ok(());
+    }
+    let output = output.join().unwrap_or_default();
+    let reason = match output.lines().map(str::trim).find(|line| !line.is_empty()) {
+        Some(line) => line.to_string(),
+        None => format!("it exited with {}", status),
+    };
+    bail!(Refused(format!(
+        "Refused by the pre-send command: {}",
+        reason
+    )))
+}
+
+/// The function gets the send as a map and approves it by returning `true`
+/// or nothing. `false` refuses it, and so does a string, as the reason.
+fn run_pre_send_script(script: &Script, send: &ProposedSend) -> Result<()> {
+    let Some(outcome) = script.call("pre_send", send, PRE_SEND_TIMEOUT)? else {
+        return Ok(());
+    };
+    // Nothing to show them on while the send is reviewed
+    for text in outcome.notices {
+        tracing::info!(text, "Hook script notice");
+    }
+    let value = outcome.value;
+    if value.is_unit() || value.as_bool() == Ok(true) {
+        return Ok(());
+    }
+    if value.as_bool() == Ok(false) {
+        bail!(Refused("Refused by the hook script".to_string()));
+    }
+    match value.into_string() {
+        Ok(reason) => bail!(Refused(format!("Refused by the hook script: {}", reason))),
+        Err(kind) => bail!(
+            "The hook script's pre_send returned {}, rather than true, false or a reason",
+            kind
+        ),
+    }
+}
+
+/// `command` run through the shell
+fn shell(command: &str) -> Command {
+    #[cfg(windows)]
+    let mut shell = {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    };
+    #[cfg(not(windows))]
+    let mut shell = {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    shell.arg(command);
+    shell
+}
+
+fn post(url: &str, payload: &str) -> Result<()> {
+    reqwest::blocking::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()?
+        .post(url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(payload.to_string())
+        .send()
+        .and_then(|response| response.error_for_status())
+        .with_context(|| format!("Webhook failed: {}", url))?;
+    Ok(())
+}
+
+/// Run the command through the shell with the payload on stdin and the
+/// event kind in `SOLACE_EVENT`. It's waited on in its own thread so a slow
+/// command doesn't hold up other events.
+fn run_command(
+    command: &str,
+    kind: EventKind,
+    payload: String,
+    report: Sender<String>,
+) -> Result<()> {
+    let mut child = shell(command)
+        .env("SOLACE_EVENT", kind.as_str())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .with_context(|| format!("Failed to run hook command: {}", command))?;
+    let command = command.to_string();
+    thread::spawn(move || {
+        if let Some(mut stdin) = child.stdin.take() {
+            let _ = stdin.write_all(payload.as_bytes());
+        }
+        match child.wait() {
+            Ok(status) if status.success() => {}
+            Ok(status) => {
+                let _ = report.send(format!("Hook command {} exited with {}", command, status));
+            }
+            Err(e) => {
+                let _ = report.send(format!("Hook command {} failed: {}", command, e));
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::{TokenDelta, TxDetails, TxKind};
+    use solana_sdk::message::Message;
+
+    /// 5 lamports from one new account to another
+    fn transfer() -> VersionedMessage {
+        #[allow(deprecated)]
+        let instruction = solana_sdk::system_instruction::transfer(
+            &Pubkey::new_unique(),
+            &Pubkey::new_unique(),
+            5,
+        );
+        VersionedMessage::Legacy(Message::new(&[instruction], None))
+    }
+
+    fn entry(lamports_delta: i64, fee_payer: bool, token: Option<i128>) -> HistoryEntry {
+        HistoryEntry {
+            signature: "sig".to_string(),
+            slot: 7,
+            block_time: None,
+            failed: false,
+            memo: None,
+            note: None,
+            reference: None,
+            details: Some(TxDetails {
+                kind: TxKind::Transfer,
+                lamports_delta,
+                fee: 5_000,
+                fee_payer,
+                counterparty: None,
+                token_deltas: token
+                    .into_iter()
+                    .map(|amount| TokenDelta {
+                        mint: Pubkey::new_unique(),
+                        amount,
+                        decimals: 6,
+                    })
+                    .collect(),
+            }),
+        }
+    }
+
+    #[test]
+    fn only_transfers_paid_by_others_count_as_received() {
+        let wallet = Pubkey::new_unique();
+        let received = Event::received(&entry(0, false, Some(2_500_000)), &wallet, "devnet");
+        let received = received.expect("incoming token transfer");
+        assert_eq!(received.tokens[0].amount, "2.5");
+
+        assert!(Event::received(&entry(1_000, false, None), &wallet, "devnet").is_some());
+        // Swaps and other transactions the wallet signed itself
+        assert!(Event::received(&entry(-5_000, true, Some(10)), &wallet, "devnet").is_none());
+        assert!(Event::received(&entry(-1_000, false, None), &wallet, "devnet").is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn pre_send_command_can_refuse() {
+        let check = |command: &str| {
+            let config = WebhookConfig {
+                pre_send: Some(command.to_string()),
+                ..WebhookConfig::default()
+            };
+            check_pre_send(&config, "https://api.devnet.solana.com", &transfer())
+        };
+        check(r#"grep -q '"lamports":5' && test "$SOLACE_EVENT" = pre_send"#).unwrap();
+
+        let refused = check("echo; echo '  over my limit'; exit 1").unwrap_err();
+        assert!(refused.is::<Refused>());
+        assert_eq!(
+            refused.to_string(),
+            "Refused by the pre-send command: over my limit"
+        );
+        assert!(check("exit 3")
+            .unwrap_err()
+            .to_string()
+            .ends_with("it exited with exit status: 3"));
+    }
+
+    #[test]
+    fn pre_send_script_can_refuse() {
+        let check = |source: &str| {
+            let path =
+                std::env::temp_dir().join(format!("solace-test-{}.rhai", Pubkey::new_unique()));
+            std::fs::write(&path, source).unwrap();
+            let config = WebhookConfig {
+                script: Some(path.display().to_string()),
+                ..WebhookConfig::default()
+            };
+            let result = check_pre_send(&config, "https://api.devnet.solana.com", &transfer());
+            std::fs::remove_file(path).unwrap();
+            result
+        };
+        check(r#"fn pre_send(send) { send.lamports == 5 && send.cluster == "devnet" }"#).unwrap();
+        check("fn on_receive(event) { false }").unwrap();
+
+        let refused = check(r#"fn pre_send(send) { "not on Sundays" }"#).unwrap_err();
+        assert!(refused.is::<Refused>());
+        assert_eq!(
+            refused.to_string(),
+            "Refused by the hook script: not on Sundays"
+        );
+        assert!(check("fn pre_send(send) { false }")
+            .unwrap_err()
+            .is::<Refused>());
+        // A broken script stops sends rather than letting them through
+        assert!(check("fn pre_send(send) { send.nothing.more }").is_err());
+        assert!(check("fn pre_send(send) {").is_err());
+    }
+
+    #[test]
+    fn received_transfers_can_be_labeled() {
+        let path = std::env::temp_dir().join(format!("solace-test-{}.rhai", Pubkey::new_unique()));
+        let source = r#"fn on_receive(event) { notify("+" + event.lamports); "salary" }"#;
+        std::fs::write(&path, source).unwrap();
+        let script = Script::load(&path.display().to_string()).unwrap();
+        std::fs::remove_file(path).unwrap();
+        let (report, _errors) = mpsc::channel();
+        let (notify, notices) = mpsc::channel();
+        let worker = Worker {
+            config: WebhookConfig::default(),
+            rpc_client: RpcClient::new("http://127.0.0.1:9".to_string()),
+            cluster: "devnet",
+            address: Pubkey::new_unique(),
+            started: true,
+            cursor: None,
+            script: Some(script),
+            report,
+            notify,
+        };
+        let event = Event::received(&entry(1_000, false, None), &worker.address, "devnet").unwrap();
+        worker.deliver(&event);
+        assert_eq!(
+            notices.try_iter().collect::<Vec<_>>(),
+            [
+                Notice::Message("+1000".to_string()),
+                Notice::Label {
+                    wallet: worker.address.to_string(),
+                    signature: "sig".to_string(),
+                    label: "salary".to_string(),
+                },
+            ]
+        );
+    }
+}